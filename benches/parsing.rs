@@ -0,0 +1,86 @@
+use cddl::{lexer::Lexer, lexer_from_str, parser::Parser, token::Token};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+const SCHEMAS: &[(&str, &str)] = &[
+  ("reputon", include_str!("../tests/data/cddl/reputon.cddl")),
+  ("coswid", include_str!("../tests/data/cddl/coswid.cddl")),
+  ("shelley", include_str!("../tests/data/cddl/shelley.cddl")),
+];
+
+// A schema of many rules referencing each other, standing in for large
+// specifications
+fn large_schema(rules: usize) -> String {
+  (0..rules)
+    .map(|i| {
+      format!(
+        "rule{} = {{\n  id: uint,\n  ? name: tstr .size (1..64), ; display name\n  next: rule{} / null,\n  tags: [* tstr],\n}}\n",
+        i,
+        (i + 1) % rules
+      )
+    })
+    .collect()
+}
+
+fn lexing(c: &mut Criterion) {
+  let mut group = c.benchmark_group("lexing");
+
+  for (name, input) in SCHEMAS.iter() {
+    group.bench_function(*name, |b| {
+      // The lexer yields EOF tokens indefinitely once the input is exhausted
+      b.iter(|| {
+        Lexer::new(black_box(input))
+          .iter()
+          .take_while(|item| match item {
+            Ok((_, Token::EOF)) | Err(_) => false,
+            _ => true,
+          })
+          .count()
+      })
+    });
+  }
+
+  let large = large_schema(1000);
+  group.bench_function("generated (1000 rules)", |b| {
+    b.iter(|| {
+      Lexer::new(black_box(&large))
+        .iter()
+        .take_while(|item| match item {
+          Ok((_, Token::EOF)) | Err(_) => false,
+          _ => true,
+        })
+        .count()
+    })
+  });
+
+  group.finish();
+}
+
+fn parsing(c: &mut Criterion) {
+  let mut group = c.benchmark_group("parsing");
+
+  for (name, input) in SCHEMAS.iter() {
+    group.bench_function(*name, |b| {
+      b.iter(|| {
+        Parser::new(lexer_from_str(black_box(input)).iter(), input)
+          .unwrap()
+          .parse_cddl()
+          .unwrap()
+      })
+    });
+  }
+
+  let large = large_schema(1000);
+  group.bench_function("generated (1000 rules)", |b| {
+    b.iter(|| {
+      Parser::new(lexer_from_str(black_box(&large)).iter(), &large)
+        .unwrap()
+        .parse_cddl()
+        .unwrap()
+    })
+  });
+
+  group.finish();
+}
+
+criterion_group!(benches, lexing, parsing);
+criterion_main!(benches);
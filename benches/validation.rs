@@ -0,0 +1,149 @@
+use cddl::{lexer_from_str, parser::cddl_from_str, validation::Validator};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde_json::{json, Value};
+
+const REPUTON: &str = include_str!("../tests/data/cddl/reputon.cddl");
+const REPUTON_JSON: &str = include_str!("../tests/data/json/reputon.json");
+
+const RECORDS: &str = r#"
+  records = [* record]
+  record = { id: uint, name: tstr, tags: [* tstr] }
+"#;
+
+const CONFIG: &str = r#"
+  config = {
+    name: tstr,
+    port: uint .le 65535,
+    ? tls: { cert: tstr, key: tstr },
+    log-level: "debug" / "info" / "warn",
+  }
+"#;
+
+// The fields of SenML records (RFC 8428)
+const SENML: &str = r#"
+  pack = [1* record]
+  record = {
+    ? bn: tstr, ? bt: number, ? bu: tstr,
+    ? n: tstr, ? u: tstr, ? t: number,
+    ? (v: number // vs: tstr // vb: bool),
+  }
+"#;
+
+// A recursive rule, validated against data nested as deep as the validator
+// comfortably supports
+const TREE: &str = r#"
+  tree = { value: int, children: [* tree] }
+"#;
+
+fn tree(depth: usize) -> Value {
+  (0..depth).fold(
+    json!({ "value": 0, "children": [] }),
+    |child, value| json!({ "value": value, "children": [child] }),
+  )
+}
+
+// Data that is only matched by the last of several choices at every level,
+// so validating it means failing to match every other choice along the way
+const DOCUMENT: &str = r#"
+  document = node
+  node = null / bool / number / text / [* node] / { * text => node }
+"#;
+
+fn document(depth: usize) -> Value {
+  (0..depth).fold(
+    json!({ "leaf": "value" }),
+    |node, _| json!({ "a": node.clone(), "b": [node, 1, "text"] }),
+  )
+}
+
+fn json_validation(c: &mut Criterion) {
+  let mut group = c.benchmark_group("json validation");
+
+  let cddl = cddl_from_str(&mut lexer_from_str(CONFIG), CONFIG, false).unwrap();
+  let valid = json!({
+    "name": "server",
+    "port": 8080,
+    "tls": { "cert": "server.pem", "key": "server.key" },
+    "log-level": "info",
+  });
+  assert!(Validator::<Value>::validate(&cddl, &valid).is_ok());
+
+  group.bench_function("small config", |b| {
+    b.iter(|| Validator::<Value>::validate(&cddl, black_box(&valid)))
+  });
+
+  let cddl = cddl_from_str(&mut lexer_from_str(REPUTON), REPUTON, false).unwrap();
+  let valid: Value = serde_json::from_str(REPUTON_JSON).unwrap();
+  let mut invalid = valid.clone();
+  invalid["reputons"][1]["rating"] = json!("high");
+  assert!(Validator::<Value>::validate(&cddl, &valid).is_ok());
+  assert!(Validator::<Value>::validate(&cddl, &invalid).is_err());
+
+  group.bench_function("reputon", |b| {
+    b.iter(|| Validator::<Value>::validate(&cddl, black_box(&valid)))
+  });
+  group.bench_function("invalid reputon", |b| {
+    b.iter(|| Validator::<Value>::validate(&cddl, black_box(&invalid)))
+  });
+
+  let cddl = cddl_from_str(&mut lexer_from_str(DOCUMENT), DOCUMENT, false).unwrap();
+  let valid = document(6);
+  assert!(Validator::<Value>::validate(&cddl, &valid).is_ok());
+
+  group.bench_function("nested choices", |b| {
+    b.iter(|| Validator::<Value>::validate(&cddl, black_box(&valid)))
+  });
+
+  let cddl = cddl_from_str(&mut lexer_from_str(TREE), TREE, false).unwrap();
+  let valid = tree(16);
+  assert!(Validator::<Value>::validate(&cddl, &valid).is_ok());
+
+  group.bench_function("deep recursion", |b| {
+    b.iter(|| Validator::<Value>::validate(&cddl, black_box(&valid)))
+  });
+
+  let cddl = cddl_from_str(&mut lexer_from_str(SENML), SENML, false).unwrap();
+  let valid = Value::Array(
+    (0..10_000)
+      .map(|i| {
+        json!({
+          "bn": "urn:dev:ow:10e2073a01080063:",
+          "bt": 1.320067464e+09,
+          "bu": "Cel",
+          "n": "temp",
+          "u": "Cel",
+          "t": i,
+          "v": 23.5,
+        })
+      })
+      .collect(),
+  );
+  assert!(Validator::<Value>::validate(&cddl, &valid).is_ok());
+
+  group.sample_size(10);
+  group.bench_function("senml (10k records)", |b| {
+    b.iter(|| Validator::<Value>::validate(&cddl, black_box(&valid)))
+  });
+
+  group.finish();
+}
+
+fn cbor_validation(c: &mut Criterion) {
+  let mut group = c.benchmark_group("cbor validation");
+
+  let cddl = cddl_from_str(&mut lexer_from_str(RECORDS), RECORDS, false).unwrap();
+  let records = (0..100)
+    .map(|id| json!({ "id": id, "name": format!("record {}", id), "tags": ["a", "b"] }))
+    .collect::<Vec<_>>();
+  let valid: serde_cbor::Value = serde_cbor::value::to_value(&records).unwrap();
+  assert!(Validator::<serde_cbor::Value>::validate(&cddl, &valid).is_ok());
+
+  group.bench_function("records", |b| {
+    b.iter(|| Validator::<serde_cbor::Value>::validate(&cddl, black_box(&valid)))
+  });
+
+  group.finish();
+}
+
+criterion_group!(benches, json_validation, cbor_validation);
+criterion_main!(benches);
@@ -0,0 +1,18 @@
+#![no_main]
+use cddl::{lexer::Lexer, parser::Parser};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+  if let Ok(input) = std::str::from_utf8(data) {
+    let mut lexer = Lexer::new(input);
+
+    if let Ok(mut parser) = Parser::new(lexer.iter(), input) {
+      // Anything that parses must also survive the semantic checks and
+      // formatting back to CDDL
+      if let Ok(cddl) = parser.parse_cddl() {
+        let _ = cddl.compile();
+        let _ = cddl.to_string();
+      }
+    }
+  }
+});
@@ -0,0 +1,21 @@
+#![no_main]
+use cddl::{cbor_core, lexer_from_str, parser::cddl_from_str};
+use libfuzzer_sys::fuzz_target;
+
+// The input is CDDL text and an encoded CBOR data item separated by a NUL
+// byte, which is validated by both the `serde_cbor` based validator and
+// `cbor_core`
+fuzz_target!(|data: &[u8]| {
+  let mut parts = data.splitn(2, |&b| b == 0);
+
+  if let (Some(cddl), Some(cbor)) = (parts.next(), parts.next()) {
+    if let Ok(input) = std::str::from_utf8(cddl) {
+      if let Ok(cddl) = cddl_from_str(&mut lexer_from_str(input), input, false) {
+        let _ = cddl.validate_cbor_slice(cbor);
+        if let Ok(value) = cbor_core::decode(cbor) {
+          let _ = cbor_core::validate(&cddl, &value);
+        }
+      }
+    }
+  }
+});
@@ -0,0 +1,15 @@
+#![no_main]
+use cddl::validate_json_from_str;
+use libfuzzer_sys::fuzz_target;
+
+// The input is CDDL text and a JSON document separated by a NUL byte, so that
+// both the definition and the data are mutated
+fuzz_target!(|data: &[u8]| {
+  let mut parts = data.splitn(2, |&b| b == 0);
+
+  if let (Some(cddl), Some(json)) = (parts.next(), parts.next()) {
+    if let (Ok(cddl), Ok(json)) = (std::str::from_utf8(cddl), std::str::from_utf8(json)) {
+      let _ = validate_json_from_str(cddl, json);
+    }
+  }
+});
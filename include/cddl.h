@@ -0,0 +1,47 @@
+/*
+ * C bindings for validating JSON and CBOR against CDDL definitions. Built
+ * into the cdylib when the `capi` feature of the cddl crate is enabled.
+ *
+ * Each validation function returns CDDL_OK if the data conforms to the
+ * definition, CDDL_INVALID if it doesn't and CDDL_BAD_INPUT if the definition
+ * or the data can't be parsed or an argument is null or isn't valid UTF-8. In
+ * the latter two cases, an error describing the failure is stored in `error`,
+ * unless it's NULL, and must be released with cddl_error_free.
+ */
+
+#ifndef CDDL_H
+#define CDDL_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define CDDL_OK 0
+#define CDDL_INVALID 1
+#define CDDL_BAD_INPUT 2
+
+typedef struct cddl_error cddl_error;
+
+int cddl_validate_json(const char *cddl, const char *json, cddl_error **error);
+
+int cddl_validate_json_for_rule(const char *cddl, const char *json, const char *rule,
+                                cddl_error **error);
+
+int cddl_validate_cbor(const char *cddl, const uint8_t *cbor, size_t len, cddl_error **error);
+
+int cddl_validate_cbor_for_rule(const char *cddl, const uint8_t *cbor, size_t len,
+                                const char *rule, cddl_error **error);
+
+/* Valid until the error is freed */
+const char *cddl_error_message(const cddl_error *error);
+
+void cddl_error_free(cddl_error *error);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CDDL_H */
@@ -1,16 +1,20 @@
-use super::token::{RangeValue, SocketPlug, Value};
+use super::token::{ByteStringLiteral, RangeValue, SocketPlug, TextLiteral, Value};
+use itertools::Either;
 use std::fmt;
 
 #[cfg(feature = "std")]
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::HashMap};
 
-#[cfg(target_arch = "wasm32")]
-use serde::Serialize;
+#[cfg(feature = "std")]
+use super::token::{control_str_from_token, lookup_control_from_str};
+#[cfg(feature = "std")]
+use serde::{de, Deserialize, Deserializer, Serialize};
 
 #[cfg(not(feature = "std"))]
 use alloc::{
   borrow::Cow,
   boxed::Box,
+  collections::BTreeMap as HashMap,
   string::{String, ToString},
   vec::Vec,
 };
@@ -23,11 +27,129 @@ pub type Span = (usize, usize, usize);
 /// ```abnf
 /// cddl = S 1*(rule S)
 /// ```
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
-#[derive(Default, Debug, PartialEq)]
+#[cfg_attr(feature = "std", derive(Serialize))]
+#[derive(Default)]
 pub struct CDDL<'a> {
   /// Zero or more production rules
+  ///
+  /// Rules should be added via `push_rule` so that they can be looked up by
+  /// name without scanning every rule
   pub rules: Vec<Rule<'a>>,
+  #[cfg_attr(feature = "std", serde(skip))]
+  pub(crate) rule_index: RuleIndex<'a>,
+  #[cfg(any(feature = "json-validation", feature = "cbor-validation"))]
+  #[cfg(not(target_arch = "wasm32"))]
+  #[serde(skip)]
+  pub(crate) validation_options: crate::validation::ValidationOptions,
+}
+
+// Positions of rules within `CDDL::rules` keyed by rule name. Rules extending
+// an existing rule with additional choices share the name of the rule they
+// extend, so a name can map to more than one position.
+#[derive(Default)]
+pub(crate) struct RuleIndex<'a> {
+  positions: HashMap<&'a str, Vec<usize>>,
+  len: usize,
+}
+
+impl<'a> RuleIndex<'a> {
+  fn insert(&mut self, name: &'a str, idx: usize) {
+    self.positions.entry(name).or_default().push(idx);
+    self.len += 1;
+  }
+}
+
+impl<'a> CDDL<'a> {
+  /// Appends a rule, indexing it by name
+  pub fn push_rule(&mut self, rule: Rule<'a>) {
+    // Rebuild the index if `rules` has been modified directly since it was
+    // last updated
+    if self.rule_index.len != self.rules.len() {
+      self.rule_index = RuleIndex::default();
+
+      for (idx, r) in self.rules.iter().enumerate() {
+        self.rule_index.insert(r.name_ident(), idx);
+      }
+    }
+
+    self.rule_index.insert(rule.name_ident(), self.rules.len());
+    self.rules.push(rule);
+  }
+
+  /// Returns the first rule defined with the given name, if any
+  pub fn rule(&self, name: &str) -> Option<&Rule<'a>> {
+    self.rules_named(name).next()
+  }
+
+  /// Returns all rules defined with the given name in the order they're
+  /// defined, including those extending the first with additional choices via
+  /// `/=` or `//=`
+  pub fn rules_named<'b: 'c, 'c>(
+    &'b self,
+    name: &'c str,
+  ) -> impl Iterator<Item = &'b Rule<'a>> + 'c {
+    // Fall back to scanning every rule if `rules` has been modified directly
+    if self.rule_index.len != self.rules.len() {
+      return Either::Right(self.rules.iter().filter(move |r| r.name_ident() == name));
+    }
+
+    let positions = match self.rule_index.positions.get(name) {
+      Some(positions) => &positions[..],
+      None => &[],
+    };
+
+    Either::Left(
+      positions
+        .iter()
+        .filter_map(move |&idx| self.rules.get(idx))
+        .filter(move |r| r.name_ident() == name),
+    )
+  }
+}
+
+impl<'a> CDDL<'a> {
+  // Returns the rules that the given identifier refers to. Sockets and other
+  // rules are looked up by their names without the socket prefix, yet `$a` and
+  // `a` are distinct names.
+  pub(crate) fn rules_for<'b: 'c, 'c>(
+    &'b self,
+    ident: &'c Identifier<'a>,
+  ) -> impl Iterator<Item = &'b Rule<'a>> + 'c {
+    self
+      .rules_named(ident.ident)
+      .filter(move |r| r.name_socket().is_some() == ident.socket.is_some())
+  }
+}
+
+// Rules are indexed as they're deserialized, as they are when parsed
+#[cfg(feature = "std")]
+impl<'de: 'a, 'a> Deserialize<'de> for CDDL<'a> {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    #[derive(Deserialize)]
+    struct Rules<'a> {
+      #[serde(borrow)]
+      rules: Vec<Rule<'a>>,
+    }
+
+    let mut cddl = CDDL::default();
+    for rule in Rules::deserialize(deserializer)?.rules {
+      cddl.push_rule(rule);
+    }
+
+    Ok(cddl)
+  }
+}
+
+impl<'a> PartialEq for CDDL<'a> {
+  fn eq(&self, other: &Self) -> bool {
+    self.rules == other.rules
+  }
+}
+
+impl<'a> fmt::Debug for CDDL<'a> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("CDDL").field("rules", &self.rules).finish()
+  }
 }
 
 impl<'a> fmt::Display for CDDL<'a> {
@@ -50,7 +172,8 @@ impl<'a> fmt::Display for CDDL<'a> {
 /// EALPHA = ALPHA / "@" / "_" / "$"
 /// DIGIT = %x30-39
 /// ```
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(deserialize = "'de: 'a")))]
 #[derive(Debug, PartialEq, Clone)]
 pub struct Identifier<'a> {
   /// Identifier
@@ -109,8 +232,9 @@ impl<'a> From<&'static str> for Identifier<'a> {
 /// rule = typename [genericparm] S assignt S type
 ///     / groupname [genericparm] S assigng S grpent
 /// ```
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
-#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(deserialize = "'de: 'a")))]
+#[derive(Debug, Clone, PartialEq)]
 #[allow(missing_docs)]
 pub enum Rule<'a> {
   /// Type expression
@@ -150,6 +274,20 @@ impl<'a> Rule<'a> {
     }
   }
 
+  pub(crate) fn name_socket(&self) -> Option<SocketPlug> {
+    match self {
+      Rule::Type { rule, .. } => rule.name.socket,
+      Rule::Group { rule, .. } => rule.name.socket,
+    }
+  }
+
+  pub(crate) fn name_ident(&self) -> &'a str {
+    match self {
+      Rule::Type { rule, .. } => rule.name.ident,
+      Rule::Group { rule, .. } => rule.name.ident,
+    }
+  }
+
   /// Returns whether or not a rule extends an existing type or group rule with
   /// additional choices
   pub fn is_choice_alternate(&self) -> bool {
@@ -165,8 +303,9 @@ impl<'a> Rule<'a> {
 /// ```abnf
 /// typename [genericparm] S assignt S type
 /// ```
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
-#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(deserialize = "'de: 'a")))]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TypeRule<'a> {
   /// Type name identifier
   pub name: Identifier<'a>,
@@ -203,8 +342,9 @@ impl<'a> fmt::Display for TypeRule<'a> {
 /// ```abnf
 /// groupname [genericparm] S assigng S grpent
 /// ```
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
-#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(deserialize = "'de: 'a")))]
+#[derive(Debug, Clone, PartialEq)]
 pub struct GroupRule<'a> {
   /// Group name identifier
   pub name: Identifier<'a>,
@@ -241,8 +381,9 @@ impl<'a> fmt::Display for GroupRule<'a> {
 /// ```abnf
 /// genericparm =  "<" S id S *("," S id S ) ">"
 /// ```
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
-#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(deserialize = "'de: 'a")))]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct GenericParm<'a> {
   /// List of generic parameters
   pub params: Vec<Identifier<'a>>,
@@ -272,7 +413,8 @@ impl<'a> fmt::Display for GenericParm<'a> {
 /// ```abnf
 /// genericarg = "<" S type1 S *("," S type1 S )  ">"
 /// ```
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(deserialize = "'de: 'a")))]
 #[derive(Debug, Clone, PartialEq)]
 pub struct GenericArg<'a> {
   /// Generic arguments
@@ -298,9 +440,9 @@ impl<'a> fmt::Display for GenericArg<'a> {
   }
 }
 
-impl<'a> GenericArg<'a> {
+impl<'a> Default for GenericArg<'a> {
   /// Default `GenericArg`
-  pub fn default() -> Self {
+  fn default() -> Self {
     GenericArg {
       args: Vec::new(),
       span: (0, 0, 0),
@@ -313,7 +455,8 @@ impl<'a> GenericArg<'a> {
 /// ```abnf
 /// type = type1 *(S "/" S  type1)
 /// ```
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(deserialize = "'de: 'a")))]
 #[derive(Debug, Clone, PartialEq)]
 pub struct Type<'a> {
   /// Type choices
@@ -332,7 +475,7 @@ impl<'a> fmt::Display for Type<'a> {
         continue;
       }
 
-      types.push_str(&format!(" / {}", t1.to_string()));
+      types.push_str(&format!(" / {}", t1));
     }
 
     write!(f, "{}", types)
@@ -367,7 +510,8 @@ impl<'a> Type<'a> {
 /// ```abnf
 /// type1 = type2 [S (rangeop / ctlop) S type2]
 /// ```
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(deserialize = "'de: 'a")))]
 #[derive(Debug, Clone, PartialEq)]
 pub struct Type1<'a> {
   /// Type
@@ -384,17 +528,14 @@ impl<'a> fmt::Display for Type1<'a> {
 
     t1.push_str(&self.type2.to_string());
 
-    if let Type2::Typename { .. } = self.type2 {
-      if self.operator.is_some() {
-        t1.push_str(" ");
-      }
-    }
-
     if let Some((rco, t2)) = &self.operator {
-      t1.push_str(&rco.to_string());
-
-      if let Type2::Typename { .. } = self.type2 {
-        t1.push_str(" ");
+      // Ranges between type names are spaced so that they aren't lexed as a
+      // single identifier
+      match (rco, &self.type2, t2) {
+        (RangeCtlOp::RangeOp { .. }, Type2::Typename { .. }, _)
+        | (RangeCtlOp::RangeOp { .. }, _, Type2::Typename { .. })
+        | (RangeCtlOp::CtlOp { .. }, ..) => t1.push_str(&format!(" {} ", rco)),
+        _ => t1.push_str(&rco.to_string()),
       }
 
       t1.push_str(&t2.to_string());
@@ -410,7 +551,7 @@ impl<'a> fmt::Display for Type1<'a> {
 /// rangeop = "..." / ".."
 /// ctlop = "." id
 /// ```
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
+#[cfg_attr(feature = "std", derive(Serialize))]
 #[derive(Debug, PartialEq, Clone)]
 #[allow(missing_docs)]
 pub enum RangeCtlOp {
@@ -420,6 +561,36 @@ pub enum RangeCtlOp {
   CtlOp { ctrl: &'static str, span: Span },
 }
 
+// Control operators are represented by the static strings the lexer maps them
+// to, so they're looked up rather than borrowed from the data
+#[cfg(feature = "std")]
+impl<'de> Deserialize<'de> for RangeCtlOp {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(rename = "RangeCtlOp")]
+    enum Op<'a> {
+      RangeOp {
+        is_inclusive: bool,
+        span: Span,
+      },
+      CtlOp {
+        #[serde(borrow)]
+        ctrl: Cow<'a, str>,
+        span: Span,
+      },
+    }
+
+    match Op::deserialize(deserializer)? {
+      Op::RangeOp { is_inclusive, span } => Ok(RangeCtlOp::RangeOp { is_inclusive, span }),
+      Op::CtlOp { ctrl, span } => lookup_control_from_str(&ctrl)
+        .as_ref()
+        .and_then(control_str_from_token)
+        .map(|ctrl| RangeCtlOp::CtlOp { ctrl, span })
+        .ok_or_else(|| de::Error::custom(format!("unknown control operator {}", ctrl))),
+    }
+  }
+}
+
 impl fmt::Display for RangeCtlOp {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match self {
@@ -450,18 +621,19 @@ impl fmt::Display for RangeCtlOp {
 ///     / "#" DIGIT ["." uint]                ; major/ai
 ///     / "#"                                 ; any
 /// ```
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(deserialize = "'de: 'a")))]
 #[derive(Debug, Clone, PartialEq)]
 #[allow(missing_docs)]
 pub enum Type2<'a> {
   /// Integer value
-  IntValue { value: isize, span: Span },
+  IntValue { value: i128, span: Span },
   /// Unsigned integer value
-  UintValue { value: usize, span: Span },
+  UintValue { value: u128, span: Span },
   /// Float value
   FloatValue { value: f64, span: Span },
-  /// Text string value (enclosed by '"')
-  TextValue { value: &'a str, span: Span },
+  /// Text string value (enclosed by '"'), with any escape sequences decoded
+  TextValue { value: Cow<'a, str>, span: Span },
   /// UTF-8 encoded byte string (enclosed by '')
   UTF8ByteString { value: Cow<'a, [u8]>, span: Span },
   /// Base 16 encoded prefixed byte string
@@ -516,19 +688,29 @@ impl<'a> fmt::Display for Type2<'a> {
     match self {
       Type2::IntValue { value, .. } => write!(f, "{}", value),
       Type2::UintValue { value, .. } => write!(f, "{}", value),
-      Type2::FloatValue { value, .. } => write!(f, "{}", value),
-      Type2::TextValue { value, .. } => write!(f, "\"{}\"", value),
+      Type2::FloatValue { value, .. } => {
+        if value.fract() == 0.0 && value.is_finite() {
+          return write!(f, "{:.1}", value);
+        }
+
+        write!(f, "{}", value)
+      }
+      Type2::TextValue { value, .. } => write!(f, "{}", TextLiteral(value)),
       Type2::UTF8ByteString { value, .. } => write!(
         f,
-        "'{}'",
+        "{}",
+        ByteStringLiteral(std::str::from_utf8(value).map_err(|_| fmt::Error)?)
+      ),
+      Type2::B16ByteString { value, .. } => write!(
+        f,
+        "h'{}'",
+        std::str::from_utf8(value).map_err(|_| fmt::Error)?
+      ),
+      Type2::B64ByteString { value, .. } => write!(
+        f,
+        "b64'{}'",
         std::str::from_utf8(value).map_err(|_| fmt::Error)?
       ),
-      Type2::B16ByteString { value, .. } => {
-        write!(f, "{}", std::str::from_utf8(value).map_err(|_| fmt::Error)?)
-      }
-      Type2::B64ByteString { value, .. } => {
-        write!(f, "{}", std::str::from_utf8(value).map_err(|_| fmt::Error)?)
-      }
       Type2::Typename {
         ident, generic_arg, ..
       } => {
@@ -545,10 +727,10 @@ impl<'a> fmt::Display for Type2<'a> {
         ident, generic_arg, ..
       } => {
         if let Some(args) = generic_arg {
-          return write!(f, "{}{}", ident, args);
+          return write!(f, "~{}{}", ident, args);
         }
 
-        write!(f, "{}", ident)
+        write!(f, "~{}", ident)
       }
       Type2::ChoiceFromInlineGroup { group, .. } => write!(f, "&({})", group),
       Type2::ChoiceFromGroup {
@@ -569,10 +751,10 @@ impl<'a> fmt::Display for Type2<'a> {
       }
       Type2::TaggedDataMajorType { mt, constraint, .. } => {
         if let Some(c) = constraint {
-          return write!(f, "{}.{}", mt, c);
+          return write!(f, "#{}.{}", mt, c);
         }
 
-        write!(f, "{}", mt)
+        write!(f, "#{}", mt)
       }
       Type2::Any(_) => write!(f, "#"),
     }
@@ -605,7 +787,8 @@ impl<'a> From<RangeValue<'a>> for Type2<'a> {
 /// ```abnf
 /// group = grpchoice * (S "//" S grpchoice)
 /// ```
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(deserialize = "'de: 'a")))]
 #[derive(Debug, Clone, PartialEq)]
 #[allow(missing_docs)]
 pub struct Group<'a> {
@@ -624,7 +807,7 @@ impl<'a> fmt::Display for Group<'a> {
         continue;
       }
 
-      group_choices.push_str(&format!(" / {}", gc));
+      group_choices.push_str(&format!(" // {}", gc));
     }
 
     write!(f, "{}", group_choices)
@@ -638,7 +821,8 @@ impl<'a> fmt::Display for Group<'a> {
 /// ```
 ///
 /// If tuple is true, then entry is marked by a trailing comma
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(deserialize = "'de: 'a")))]
 #[derive(Debug, Clone, PartialEq)]
 pub struct GroupChoice<'a> {
   /// Group entries where the second item in the tuple indicates where or not a
@@ -650,8 +834,8 @@ pub struct GroupChoice<'a> {
 
 impl<'a> fmt::Display for GroupChoice<'a> {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    if self.group_entries.len() == 1 {
-      return write!(f, "{}", self.group_entries[0].0);
+    if let [(ge, has_comma)] = &self.group_entries[..] {
+      return write!(f, "{}{}", ge, if *has_comma { "," } else { "" });
     }
 
     let mut group_entries = String::new();
@@ -675,7 +859,8 @@ impl<'a> fmt::Display for GroupChoice<'a> {
 ///       / [occur S] groupname [genericarg]  ; preempted by above
 ///       / [occur S] "(" S group S ")"
 /// ```
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(deserialize = "'de: 'a")))]
 #[derive(Debug, Clone, PartialEq)]
 #[allow(missing_docs)]
 pub enum GroupEntry<'a> {
@@ -719,7 +904,8 @@ impl<'a> fmt::Display for GroupEntry<'a> {
 /// ```abnf
 /// [occur S] [memberkey S] type
 /// ```
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(deserialize = "'de: 'a")))]
 #[derive(Debug, Clone, PartialEq)]
 pub struct ValueMemberKeyEntry<'a> {
   /// Optional occurrence indicator
@@ -749,7 +935,8 @@ impl<'a> fmt::Display for ValueMemberKeyEntry<'a> {
 }
 
 /// Group entry from a named type or group
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(deserialize = "'de: 'a")))]
 #[derive(Debug, Clone, PartialEq)]
 pub struct TypeGroupnameEntry<'a> {
   /// Optional occurrence indicator
@@ -764,14 +951,14 @@ impl<'a> fmt::Display for TypeGroupnameEntry<'a> {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     if let Some(o) = &self.occur {
       if let Some(ga) = &self.generic_arg {
-        return write!(f, "{} {} {}", o, self.name, ga);
+        return write!(f, "{} {}{}", o, self.name, ga);
       }
 
       return write!(f, "{} {}", o, self.name);
     }
 
     if let Some(ga) = &self.generic_arg {
-      return write!(f, "{} {}", self.name, ga);
+      return write!(f, "{}{}", self.name, ga);
     }
 
     write!(f, "{}", self.name)
@@ -784,7 +971,8 @@ impl<'a> fmt::Display for TypeGroupnameEntry<'a> {
 ///           / bareword S ":"
 ///           / value S ":"
 /// ```
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(deserialize = "'de: 'a")))]
 #[derive(Debug, Clone, PartialEq)]
 #[allow(missing_docs)]
 pub enum MemberKey<'a> {
@@ -807,7 +995,8 @@ pub enum MemberKey<'a> {
   NonMemberKey(NonMemberKey<'a>),
 }
 
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(deserialize = "'de: 'a")))]
 #[derive(Debug, Clone, PartialEq)]
 #[allow(missing_docs)]
 pub enum NonMemberKey<'a> {
@@ -839,7 +1028,7 @@ impl<'a> fmt::Display for MemberKey<'a> {
 ///       / "+"
 ///       / "?"
 /// ```
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, PartialEq)]
 #[allow(missing_docs)]
 pub enum Occur {
@@ -967,4 +1156,96 @@ mod tests {
       "\tkey1: \"value1\",\n\tkey2: \"value2\",\n".to_string()
     )
   }
+
+  #[test]
+  #[cfg(feature = "std")]
+  fn verify_single_entry_trailing_comma_output() {
+    use crate::{lexer::Lexer, parser::cddl_from_str};
+
+    let input = "a = { b: int, }\nc = [int]\nd = (e: tstr,)";
+    let cddl = cddl_from_str(&mut Lexer::new(input), input, true).unwrap();
+
+    assert_eq!(
+      cddl.to_string(),
+      "a = {b: int,}\n\nc = [int]\n\nd = (e: tstr,)\n\n"
+    );
+  }
+
+  #[test]
+  #[cfg(feature = "std")]
+  fn verify_rule_lookup() {
+    use crate::{lexer::Lexer, parser::cddl_from_str};
+
+    let input = "a = b\nb = { c: a }\nd = [* b]";
+    let mut lexer = Lexer::new(input);
+    let mut cddl = cddl_from_str(&mut lexer, input, false).unwrap();
+
+    assert_eq!(cddl.rule("b").map(Rule::name), Some("b".to_string()));
+    assert_eq!(cddl.rules_named("d").count(), 1);
+    assert!(cddl.rule("e").is_none());
+
+    // Lookups remain correct after the rules are modified directly
+    cddl.rules.remove(0);
+    assert!(cddl.rule("a").is_none());
+    assert_eq!(cddl.rule("d").map(Rule::name), Some("d".to_string()));
+
+    cddl.push_rule(Rule::Type {
+      rule: TypeRule {
+        name: Identifier::from("e"),
+        generic_param: None,
+        is_type_choice_alternate: false,
+        value: Type {
+          type_choices: vec![Type1 {
+            type2: Type2::Typename {
+              ident: Identifier::from("tstr"),
+              generic_arg: None,
+              span: (0, 0, 0),
+            },
+            operator: None,
+            span: (0, 0, 0),
+          }],
+          span: (0, 0, 0),
+        },
+      },
+      span: (0, 0, 0),
+    });
+    assert_eq!(cddl.rule("e").map(Rule::name), Some("e".to_string()));
+  }
+
+  #[test]
+  #[cfg(feature = "cbor-validation")]
+  fn verify_serde_roundtrip() {
+    use crate::{lexer::Lexer, parser::cddl_from_str};
+
+    let input = r#"person<T> = { name: tstr, ? age: T .le 150, * tstr => any }
+adult = person<uint>
+color = "red" / "green"
+color /= "blue"
+digest = bstr .size 32 / h'0102'
+entries = (a: 1..10, b: [+ bool])
+quote = "say \"hi\"\n"
+"#;
+    let mut lexer = Lexer::new(input);
+    let cddl = cddl_from_str(&mut lexer, input, true).unwrap();
+
+    let cbor = serde_cbor::to_vec(&cddl).unwrap();
+    let from_cbor: CDDL = serde_cbor::from_slice(&cbor).unwrap();
+    assert_eq!(from_cbor.rules, cddl.rules);
+    assert_eq!(from_cbor.to_string(), cddl.to_string());
+    assert_eq!(
+      from_cbor.rule("adult").map(Rule::name),
+      Some("adult".to_string())
+    );
+    assert_eq!(from_cbor.rules_named("color").count(), 2);
+
+    let json = serde_json::to_string(&cddl).unwrap();
+    let from_json: CDDL = serde_json::from_str(&json).unwrap();
+    assert_eq!(from_json.rules, cddl.rules);
+    assert!(from_json.rule("entries").is_some());
+
+    // Control operators must be ones the lexer knows of
+    assert!(json.contains("\"ctrl\":\".size\""));
+    let json = json.replace("\"ctrl\":\".size\"", "\"ctrl\":\".nope\"");
+    assert!(serde_json::from_str::<CDDL>(&json).is_err());
+  }
 }
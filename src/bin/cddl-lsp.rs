@@ -0,0 +1,9 @@
+use cddl::lsp;
+use std::{error::Error, io};
+
+fn main() -> Result<(), Box<dyn Error>> {
+  let input = io::stdin();
+  let output = io::stdout();
+  lsp::run(input.lock(), output.lock())?;
+  Ok(())
+}
@@ -1,13 +1,28 @@
 #[macro_use]
 extern crate clap;
 
-use cddl::{cddl_from_str, lexer_from_str, validate_json_from_str};
+use cddl::{
+  cddl_from_str, compat,
+  docgen::{docs_from_str, DocFormat},
+  formatter::{format_str, FormatterOptions},
+  graph::{rule_graph, GraphFormat},
+  lexer_from_str,
+  lint::{lint_with_config, LintConfig, Severity},
+  loader::{load_schema, FileLoader},
+  schema::CddlSchema,
+  Validator,
+};
 use clap::{App, AppSettings, SubCommand};
 use codespan_reporting::term::termcolor::{
   Color, ColorChoice, ColorSpec, StandardStream, WriteColor,
 };
 use std::{error::Error, fs, io::Write};
 
+#[cfg(feature = "cbor-validation")]
+use cddl::conformance::run_file;
+#[cfg(feature = "http")]
+use cddl::fetch::{fetch_schema, HttpFetcher};
+
 fn main() -> Result<(), Box<dyn Error>> {
   let app = App::new("cddl")
                     .version(crate_version!())
@@ -17,13 +32,40 @@ fn main() -> Result<(), Box<dyn Error>> {
                     .subcommand(SubCommand::with_name("compile-cddl")
                                 .about("compiles CDDL against RFC 8610")
                                 .arg_from_usage("-c --cddl=<FILE> 'CDDL input file'"))
+                    .subcommand(SubCommand::with_name("compat")
+                                .about("reports changes that make a new version of a CDDL definition reject data accepted by the old one")
+                                .arg_from_usage("<OLD> 'CDDL file of the old version'")
+                                .arg_from_usage("<NEW> 'CDDL file of the new version'"))
                     .subcommand(SubCommand::with_name("compile-json")
                                 .about("compiles JSON")
                                 .arg_from_usage("-j --json=<FILE> 'JSON input file'"))
+                    .subcommand(SubCommand::with_name("doc")
+                                .about("generates Markdown documentation for CDDL, printing the result to stdout")
+                                .arg_from_usage("-c --cddl=<FILE> 'CDDL input file'")
+                                .arg_from_usage("--html 'Generates an HTML page instead of Markdown'"))
+                    .subcommand(SubCommand::with_name("fmt")
+                                .about("formats CDDL, printing the result to stdout")
+                                .arg_from_usage("-c --cddl=<FILE> 'CDDL input file'")
+                                .arg_from_usage("--check 'Exits with a non-zero status if the file is not formatted'")
+                                .arg_from_usage("-w --write 'Writes the formatted output back to the input file'"))
+                    .subcommand(SubCommand::with_name("graph")
+                                .about("prints the references between rules as a Graphviz DOT graph")
+                                .arg_from_usage("-c --cddl=<FILE> 'CDDL input file'")
+                                .arg_from_usage("--mermaid 'Prints a Mermaid flowchart instead of DOT'"))
+                    .subcommand(SubCommand::with_name("lint")
+                                .about("checks CDDL for stylistic and suspicious constructs")
+                                .arg_from_usage("-c --cddl=<FILE> 'CDDL input file'")
+                                .arg_from_usage("-A --allow=[ID]... 'Lint to disable'")
+                                .arg_from_usage("-D --deny=[ID]... 'Lint to report as an error, causing a non-zero exit status'"))
+                    .subcommand(SubCommand::with_name("test")
+                                .about("runs the conformance suites described by .cddltest manifests")
+                                .arg_from_usage("<MANIFEST>... '.cddltest manifest pairing a CDDL file with valid and invalid instances'"))
                     .subcommand(SubCommand::with_name("validate")
                                 .about("validate JSON against CDDL definition")
-                                .arg_from_usage("-c --cddl=<FILE> 'CDDL input file'")
-                                .arg_from_usage("-j --json=<FILE> 'JSON input file"));
+                                .arg_from_usage("-c --cddl=<FILE> 'CDDL input file, or URL of one if built with the http feature'")
+                                .arg_from_usage("-j --json=<FILE> 'JSON input file")
+                                .arg_from_usage("-r --rule=[RULE] 'Rule to validate against instead of the root type'")
+                                .arg_from_usage("-I --include-path=[DIR]... 'Directory to search for schemas referenced by include and import directives'"));
 
   let matches = app.get_matches();
 
@@ -46,6 +88,26 @@ fn main() -> Result<(), Box<dyn Error>> {
     }
   }
 
+  if let Some(matches) = matches.subcommand_matches("compat") {
+    if let (Some(old), Some(new)) = (matches.value_of("OLD"), matches.value_of("NEW")) {
+      let old_content = fs::read_to_string(old)?;
+      let new_content = fs::read_to_string(new)?;
+      let old_cddl = cddl_from_str(&mut lexer_from_str(&old_content), &old_content, true)?;
+      let new_cddl = cddl_from_str(&mut lexer_from_str(&new_content), &new_content, true)?;
+
+      let changes = compat::check(&old_cddl, &new_cddl);
+      for c in changes.iter() {
+        println!("{}: {}", new, c);
+      }
+
+      if !changes.is_empty() {
+        std::process::exit(1);
+      }
+
+      return Ok(());
+    }
+  }
+
   if let Some(matches) = matches.subcommand_matches("compile-json") {
     if let Some(c) = matches.value_of("json") {
       let file = std::fs::File::open(c)?;
@@ -56,10 +118,129 @@ fn main() -> Result<(), Box<dyn Error>> {
     }
   }
 
+  if let Some(matches) = matches.subcommand_matches("doc") {
+    if let Some(c) = matches.value_of("cddl") {
+      let file_content = fs::read_to_string(c)?;
+      let format = if matches.is_present("html") {
+        DocFormat::Html
+      } else {
+        DocFormat::Markdown
+      };
+
+      print!("{}", docs_from_str(&file_content, format)?);
+
+      return Ok(());
+    }
+  }
+
+  if let Some(matches) = matches.subcommand_matches("fmt") {
+    if let Some(c) = matches.value_of("cddl") {
+      let file_content = fs::read_to_string(c)?;
+      let formatted = format_str(&file_content, &FormatterOptions::default())?;
+
+      if matches.is_present("check") {
+        if formatted != file_content {
+          let mut stderr = StandardStream::stderr(ColorChoice::Auto);
+          stderr.set_color(ColorSpec::new().set_fg(Some(Color::Red)))?;
+          writeln!(&mut stderr, "{} is not formatted", c)?;
+
+          std::process::exit(1);
+        }
+
+        return Ok(());
+      }
+
+      if matches.is_present("write") {
+        fs::write(c, formatted)?;
+      } else {
+        print!("{}", formatted);
+      }
+
+      return Ok(());
+    }
+  }
+
+  if let Some(matches) = matches.subcommand_matches("graph") {
+    if let Some(c) = matches.value_of("cddl") {
+      let file_content = fs::read_to_string(c)?;
+      let cddl = cddl_from_str(&mut lexer_from_str(&file_content), &file_content, true)?;
+      let format = if matches.is_present("mermaid") {
+        GraphFormat::Mermaid
+      } else {
+        GraphFormat::Dot
+      };
+
+      print!("{}", rule_graph(&cddl, format));
+
+      return Ok(());
+    }
+  }
+
+  if let Some(matches) = matches.subcommand_matches("lint") {
+    if let Some(c) = matches.value_of("cddl") {
+      let file_content = fs::read_to_string(c)?;
+      let cddl = cddl_from_str(&mut lexer_from_str(&file_content), &file_content, true)?;
+
+      let config = matches
+        .values_of("allow")
+        .into_iter()
+        .flatten()
+        .fold(LintConfig::default(), LintConfig::allow);
+      let config = matches
+        .values_of("deny")
+        .into_iter()
+        .flatten()
+        .fold(config, |config, id| config.severity(id, Severity::Error));
+
+      let lints = lint_with_config(&cddl, &config);
+      for l in lints.iter() {
+        println!("{}:{}: {}[{}]: {}", c, l.span.2, l.severity, l.kind.id(), l);
+      }
+
+      if lints.iter().any(|l| l.severity == Severity::Error) {
+        std::process::exit(1);
+      }
+
+      return Ok(());
+    }
+  }
+
+  if let Some(matches) = matches.subcommand_matches("test") {
+    let mut success = true;
+    for manifest in matches.values_of("MANIFEST").into_iter().flatten() {
+      success &= run_conformance_suite(manifest)?;
+    }
+
+    if !success {
+      std::process::exit(1);
+    }
+
+    return Ok(());
+  }
+
   if let Some(matches) = matches.subcommand_matches("validate") {
     if let Some(cddl) = matches.value_of("cddl") {
       if let Some(json) = matches.value_of("json") {
-        match validate_json_from_str(&fs::read_to_string(cddl)?, &fs::read_to_string(json)?) {
+        // Schemas referenced via include and import directives are resolved
+        // relative to the given file or URL
+        let schema = if cddl.starts_with("http://") || cddl.starts_with("https://") {
+          fetch_remote_schema(cddl)?
+        } else {
+          let loader = matches
+            .values_of("include-path")
+            .into_iter()
+            .flatten()
+            .fold(FileLoader::new(), FileLoader::search_path);
+          load_schema(&loader, cddl)?
+        };
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(json)?)?;
+
+        let result = match matches.value_of("rule") {
+          Some(rule) => schema.cddl().validate_with_root(rule, &json),
+          None => schema.cddl().validate(&json),
+        };
+
+        match result {
           Ok(()) => {
             let mut stdout = StandardStream::stdout(ColorChoice::Auto);
             stdout.set_color(ColorSpec::new().set_fg(Some(Color::Green)))?;
@@ -79,3 +260,39 @@ fn main() -> Result<(), Box<dyn Error>> {
 
   Ok(())
 }
+
+#[cfg(feature = "cbor-validation")]
+fn run_conformance_suite(manifest: &str) -> Result<bool, Box<dyn Error>> {
+  let report = run_file(manifest)?;
+  let color = if report.is_success() {
+    Color::Green
+  } else {
+    Color::Red
+  };
+
+  let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+  stdout.set_color(ColorSpec::new().set_fg(Some(color)))?;
+  writeln!(&mut stdout, "{}", report)?;
+  stdout.reset()?;
+
+  Ok(report.is_success())
+}
+
+#[cfg(not(feature = "cbor-validation"))]
+fn run_conformance_suite(manifest: &str) -> Result<bool, Box<dyn Error>> {
+  Err(format!("running {} requires the cbor-validation feature", manifest).into())
+}
+
+#[cfg(feature = "http")]
+fn fetch_remote_schema(url: &str) -> Result<CddlSchema, Box<dyn Error>> {
+  let runtime = tokio_crate::runtime::Builder::new_current_thread()
+    .enable_all()
+    .build()?;
+
+  Ok(runtime.block_on(fetch_schema(&HttpFetcher::new(), url))?)
+}
+
+#[cfg(not(feature = "http"))]
+fn fetch_remote_schema(url: &str) -> Result<CddlSchema, Box<dyn Error>> {
+  Err(format!("fetching {} requires the http feature", url).into())
+}
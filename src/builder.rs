@@ -0,0 +1,749 @@
+use super::{
+  ast::*,
+  token::{control_str_from_token, lookup_control_from_str, SocketPlug},
+};
+
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+
+#[cfg(not(feature = "std"))]
+use alloc::{borrow::Cow, boxed::Box, vec, vec::Vec};
+
+const NO_SPAN: Span = (0, 0, 0);
+
+/// Builds a type from one or more type choices
+///
+/// # Example
+///
+/// ```
+/// use cddl::builder::TypeBuilder;
+///
+/// let t = TypeBuilder::named("tstr")
+///   .control(".size", TypeBuilder::uint(8))
+///   .or(TypeBuilder::named("nil"))
+///   .build();
+///
+/// assert_eq!(t.to_string(), "tstr .size 8 / nil");
+/// ```
+#[derive(Debug, Clone)]
+pub struct TypeBuilder<'a> {
+  type_choices: Vec<Type1<'a>>,
+}
+
+impl<'a> TypeBuilder<'a> {
+  /// Type consisting of a single choice
+  pub fn new(t2: Type2<'a>) -> Self {
+    TypeBuilder {
+      type_choices: vec![Type1 {
+        type2: t2,
+        operator: None,
+        span: NO_SPAN,
+      }],
+    }
+  }
+
+  /// Reference to a type or group defined by a rule or the standard prelude,
+  /// e.g. `tstr`. Names starting with `$` or `$$` refer to type or group
+  /// sockets respectively.
+  pub fn named(name: &'a str) -> Self {
+    TypeBuilder::new(Type2::Typename {
+      ident: identifier(name),
+      generic_arg: None,
+      span: NO_SPAN,
+    })
+  }
+
+  /// Reference to a generic rule instantiated with the given arguments, e.g.
+  /// `pair<int>`
+  pub fn generic<I>(name: &'a str, args: I) -> Self
+  where
+    I: IntoIterator<Item = TypeBuilder<'a>>,
+  {
+    TypeBuilder::new(Type2::Typename {
+      ident: identifier(name),
+      generic_arg: Some(GenericArg {
+        args: args.into_iter().map(TypeBuilder::into_type1).collect(),
+        span: NO_SPAN,
+      }),
+      span: NO_SPAN,
+    })
+  }
+
+  /// Unsigned integer value
+  pub fn uint(value: u128) -> Self {
+    TypeBuilder::new(Type2::UintValue {
+      value,
+      span: NO_SPAN,
+    })
+  }
+
+  /// Integer value, which is an unsigned integer value if it isn't negative
+  pub fn int(value: i128) -> Self {
+    if value >= 0 {
+      return TypeBuilder::uint(value as u128);
+    }
+
+    TypeBuilder::new(Type2::IntValue {
+      value,
+      span: NO_SPAN,
+    })
+  }
+
+  /// Floating-point value
+  pub fn float(value: f64) -> Self {
+    TypeBuilder::new(Type2::FloatValue {
+      value,
+      span: NO_SPAN,
+    })
+  }
+
+  /// Text string value
+  pub fn text(value: &'a str) -> Self {
+    TypeBuilder::new(Type2::TextValue {
+      value: value.into(),
+      span: NO_SPAN,
+    })
+  }
+
+  /// Byte string value, written as a UTF-8 byte string if it's valid UTF-8
+  /// without single quotes, and in base 16 otherwise
+  pub fn bytes(value: &'a [u8]) -> Self {
+    match core::str::from_utf8(value) {
+      Ok(s) if !s.contains('\'') && !s.contains('\\') => TypeBuilder::new(Type2::UTF8ByteString {
+        value: Cow::Borrowed(value),
+        span: NO_SPAN,
+      }),
+      _ => {
+        let hex = value
+          .iter()
+          .flat_map(|b| {
+            let digits = b"0123456789abcdef";
+            vec![digits[(b >> 4) as usize], digits[(b & 0xf) as usize]]
+          })
+          .collect::<Vec<_>>();
+
+        TypeBuilder::new(Type2::B16ByteString {
+          value: Cow::Owned(hex),
+          span: NO_SPAN,
+        })
+      }
+    }
+  }
+
+  /// Map whose entries are described by the given group
+  pub fn map(group: GroupBuilder<'a>) -> Self {
+    TypeBuilder::new(Type2::Map {
+      group: group.build(),
+      span: NO_SPAN,
+    })
+  }
+
+  /// Array whose elements are described by the given group
+  pub fn array(group: GroupBuilder<'a>) -> Self {
+    TypeBuilder::new(Type2::Array {
+      group: group.build(),
+      span: NO_SPAN,
+    })
+  }
+
+  /// Choice between the types of the entries of the given group, e.g.
+  /// `&(red: 0, green: 1)`
+  pub fn choice_from_group(group: GroupBuilder<'a>) -> Self {
+    TypeBuilder::new(Type2::ChoiceFromInlineGroup {
+      group: group.build(),
+      span: NO_SPAN,
+    })
+  }
+
+  /// Choice between the types of the entries of the group with the given
+  /// name, e.g. `&colors`
+  pub fn choice_from_group_named(name: &'a str) -> Self {
+    TypeBuilder::new(Type2::ChoiceFromGroup {
+      ident: identifier(name),
+      generic_arg: None,
+      span: NO_SPAN,
+    })
+  }
+
+  /// Data item tagged with the given tag, or with any tag if there isn't one
+  pub fn tagged(tag: Option<usize>, t: TypeBuilder<'a>) -> Self {
+    TypeBuilder::new(Type2::TaggedData {
+      tag,
+      t: t.build(),
+      span: NO_SPAN,
+    })
+  }
+
+  /// Any data item, `#`
+  pub fn any() -> Self {
+    TypeBuilder::new(Type2::Any(NO_SPAN))
+  }
+
+  /// Adds the choices of another type
+  pub fn or(mut self, other: TypeBuilder<'a>) -> Self {
+    self.type_choices.extend(other.type_choices);
+    self
+  }
+
+  /// Constrains the last choice to the range between it and the given upper
+  /// bound, which is included if `is_inclusive` is true, e.g. `0..10`
+  pub fn range(self, upper: TypeBuilder<'a>, is_inclusive: bool) -> Self {
+    self.with_operator(
+      RangeCtlOp::RangeOp {
+        is_inclusive,
+        span: NO_SPAN,
+      },
+      upper,
+    )
+  }
+
+  /// Constrains the last choice with the given control operator, e.g.
+  /// `.size`, and controller
+  ///
+  /// # Panics
+  ///
+  /// Panics if `ctrl` isn't a control operator supported by the parser
+  pub fn control(self, ctrl: &str, controller: TypeBuilder<'a>) -> Self {
+    let ctrl = lookup_control_from_str(ctrl)
+      .as_ref()
+      .and_then(control_str_from_token)
+      .unwrap_or_else(|| panic!("unknown control operator {}", ctrl));
+
+    self.with_operator(
+      RangeCtlOp::CtlOp {
+        ctrl,
+        span: NO_SPAN,
+      },
+      controller,
+    )
+  }
+
+  fn with_operator(mut self, op: RangeCtlOp, t: TypeBuilder<'a>) -> Self {
+    let t2 = t.into_type2();
+    if let Some(t1) = self.type_choices.pop() {
+      // Operators bind to a single type, so e.g. a range of a range needs
+      // parenthesizing
+      let t1 = if t1.operator.is_some() {
+        TypeBuilder {
+          type_choices: vec![t1],
+        }
+        .into_type2()
+      } else {
+        t1.type2
+      };
+
+      self.type_choices.push(Type1 {
+        type2: t1,
+        operator: Some((op, t2)),
+        span: NO_SPAN,
+      });
+    }
+
+    self
+  }
+
+  // Single type choice, parenthesized if there's more than one
+  fn into_type1(self) -> Type1<'a> {
+    if self.type_choices.len() == 1 {
+      return self.type_choices.into_iter().next().unwrap();
+    }
+
+    Type1 {
+      type2: self.into_type2(),
+      operator: None,
+      span: NO_SPAN,
+    }
+  }
+
+  // Single type without an operator, parenthesized if need be
+  fn into_type2(self) -> Type2<'a> {
+    match self.type_choices.as_slice() {
+      [Type1 { operator: None, .. }] => self.type_choices.into_iter().next().unwrap().type2,
+      _ => Type2::ParenthesizedType {
+        pt: self.build(),
+        span: NO_SPAN,
+      },
+    }
+  }
+
+  /// Returns the type
+  pub fn build(self) -> Type<'a> {
+    Type {
+      type_choices: self.type_choices,
+      span: NO_SPAN,
+    }
+  }
+}
+
+impl<'a> From<Type2<'a>> for TypeBuilder<'a> {
+  fn from(t2: Type2<'a>) -> Self {
+    TypeBuilder::new(t2)
+  }
+}
+
+/// Builds a group from its entries, e.g. for the members of a map or the
+/// elements of an array. Occurrence indicators apply to the last entry added.
+///
+/// # Example
+///
+/// ```
+/// use cddl::builder::{GroupBuilder, TypeBuilder};
+///
+/// let g = GroupBuilder::new()
+///   .member("name", TypeBuilder::named("tstr"))
+///   .member("age", TypeBuilder::named("uint"))
+///   .optional()
+///   .build();
+///
+/// assert_eq!(g.to_string(), "\tname: tstr,\n\t? age: uint\n");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct GroupBuilder<'a> {
+  group_choices: Vec<Vec<GroupEntry<'a>>>,
+}
+
+impl<'a> GroupBuilder<'a> {
+  /// Group without any entries
+  pub fn new() -> Self {
+    GroupBuilder::default()
+  }
+
+  /// Adds an entry whose member key is the given name, e.g. `name: tstr`. The
+  /// name must be a valid CDDL identifier.
+  pub fn member(self, name: &'a str, t: TypeBuilder<'a>) -> Self {
+    self.entry(
+      Some(MemberKey::Bareword {
+        ident: identifier(name),
+        span: NO_SPAN,
+      }),
+      t,
+    )
+  }
+
+  /// Adds an entry whose member key is a type, e.g. `* tstr => any`
+  pub fn keyed(self, key: TypeBuilder<'a>, t: TypeBuilder<'a>) -> Self {
+    self.entry(
+      Some(MemberKey::Type1 {
+        t1: Box::from(key.into_type1()),
+        is_cut: false,
+        span: NO_SPAN,
+      }),
+      t,
+    )
+  }
+
+  /// Adds an entry without a member key, e.g. an element of an array
+  pub fn value(self, t: TypeBuilder<'a>) -> Self {
+    self.entry(None, t)
+  }
+
+  /// Adds a reference to the group defined by the rule with the given name
+  pub fn group_named(self, name: &'a str) -> Self {
+    self.push(GroupEntry::TypeGroupname {
+      ge: TypeGroupnameEntry {
+        occur: None,
+        name: identifier(name),
+        generic_arg: None,
+      },
+      span: NO_SPAN,
+    })
+  }
+
+  /// Adds a parenthesized group, e.g. so that an occurrence indicator applies
+  /// to several entries at once
+  pub fn inline(self, group: GroupBuilder<'a>) -> Self {
+    self.push(GroupEntry::InlineGroup {
+      occur: None,
+      group: group.build(),
+      span: NO_SPAN,
+    })
+  }
+
+  /// Starts another group choice, so that subsequent entries are an
+  /// alternative to those added so far, e.g. `int, int // tstr`
+  pub fn or(mut self) -> Self {
+    self.group_choices.push(Vec::new());
+    self
+  }
+
+  /// Makes the last entry optional, `?`
+  pub fn optional(self) -> Self {
+    self.occur(Occur::Optional(NO_SPAN))
+  }
+
+  /// Allows the last entry to occur any number of times, `*`
+  pub fn zero_or_more(self) -> Self {
+    self.occur(Occur::ZeroOrMore(NO_SPAN))
+  }
+
+  /// Requires the last entry to occur at least once, `+`
+  pub fn one_or_more(self) -> Self {
+    self.occur(Occur::OneOrMore(NO_SPAN))
+  }
+
+  /// Sets the minimum and maximum number of times the last entry occurs, e.g.
+  /// `1*3`
+  pub fn occurs(self, lower: Option<usize>, upper: Option<usize>) -> Self {
+    self.occur(Occur::Exact {
+      lower,
+      upper,
+      span: NO_SPAN,
+    })
+  }
+
+  fn occur(mut self, occur: Occur) -> Self {
+    if let Some(ge) = self.group_choices.last_mut().and_then(|gc| gc.last_mut()) {
+      match ge {
+        GroupEntry::ValueMemberKey { ge, .. } => ge.occur = Some(occur),
+        GroupEntry::TypeGroupname { ge, .. } => ge.occur = Some(occur),
+        GroupEntry::InlineGroup { occur: o, .. } => *o = Some(occur),
+      }
+    }
+
+    self
+  }
+
+  fn entry(self, member_key: Option<MemberKey<'a>>, t: TypeBuilder<'a>) -> Self {
+    let entry_type = t.build();
+
+    // A type name without a member key is parsed as a reference to a type or
+    // group, so it's represented as one
+    if member_key.is_none() {
+      if let Some((name, generic_arg, _)) = entry_type.groupname_entry() {
+        return self.push(GroupEntry::TypeGroupname {
+          ge: TypeGroupnameEntry {
+            occur: None,
+            name,
+            generic_arg,
+          },
+          span: NO_SPAN,
+        });
+      }
+    }
+
+    self.push(GroupEntry::ValueMemberKey {
+      ge: Box::from(ValueMemberKeyEntry {
+        occur: None,
+        member_key,
+        entry_type,
+      }),
+      span: NO_SPAN,
+    })
+  }
+
+  fn push(mut self, ge: GroupEntry<'a>) -> Self {
+    match self.group_choices.last_mut() {
+      Some(gc) => gc.push(ge),
+      None => self.group_choices.push(vec![ge]),
+    }
+
+    self
+  }
+
+  /// Returns the group
+  pub fn build(self) -> Group<'a> {
+    let mut group_choices = self.group_choices;
+    if group_choices.is_empty() {
+      group_choices.push(Vec::new());
+    }
+
+    Group {
+      group_choices: group_choices
+        .into_iter()
+        .map(|entries| {
+          let len = entries.len();
+
+          GroupChoice {
+            // Entries are separated by commas
+            group_entries: entries
+              .into_iter()
+              .enumerate()
+              .map(|(idx, ge)| (ge, idx + 1 < len))
+              .collect(),
+            span: NO_SPAN,
+          }
+        })
+        .collect(),
+      span: NO_SPAN,
+    }
+  }
+}
+
+/// Builds a type or group rule
+///
+/// # Example
+///
+/// ```
+/// use cddl::{
+///   ast::CDDL,
+///   builder::{GroupBuilder, RuleBuilder, TypeBuilder},
+///   Validator,
+/// };
+///
+/// let mut cddl = CDDL::default();
+/// cddl.push_rule(
+///   RuleBuilder::type_rule(
+///     "person",
+///     TypeBuilder::map(
+///       GroupBuilder::new()
+///         .member("name", TypeBuilder::named("tstr"))
+///         .member("age", TypeBuilder::named("uint")),
+///     ),
+///   )
+///   .build(),
+/// );
+///
+/// assert_eq!(cddl.to_string(), "person = {\tname: tstr,\n\tage: uint\n}\n\n");
+/// assert!(cddl
+///   .validate(&serde_json::json!({ "name": "Alice", "age": 42 }))
+///   .is_ok());
+/// ```
+#[derive(Debug, Clone)]
+pub struct RuleBuilder<'a> {
+  name: &'a str,
+  generic_params: Vec<&'a str>,
+  is_choice_alternate: bool,
+  definition: Definition<'a>,
+}
+
+#[derive(Debug, Clone)]
+enum Definition<'a> {
+  Type(Type<'a>),
+  Group(GroupEntry<'a>),
+}
+
+impl<'a> RuleBuilder<'a> {
+  /// Rule defining a type, e.g. `name = tstr`
+  pub fn type_rule(name: &'a str, t: TypeBuilder<'a>) -> Self {
+    RuleBuilder {
+      name,
+      generic_params: Vec::new(),
+      is_choice_alternate: false,
+      definition: Definition::Type(t.build()),
+    }
+  }
+
+  /// Rule defining a group, e.g. `header = (alg: int, kid: bstr)`
+  pub fn group_rule(name: &'a str, group: GroupBuilder<'a>) -> Self {
+    RuleBuilder {
+      name,
+      generic_params: Vec::new(),
+      is_choice_alternate: false,
+      definition: Definition::Group(GroupEntry::InlineGroup {
+        occur: None,
+        group: group.build(),
+        span: NO_SPAN,
+      }),
+    }
+  }
+
+  /// Makes the rule generic over parameters with the given names, e.g.
+  /// `pair<T> = [T, T]`
+  pub fn generic_params<I>(mut self, params: I) -> Self
+  where
+    I: IntoIterator<Item = &'a str>,
+  {
+    self.generic_params = params.into_iter().collect();
+    self
+  }
+
+  /// Makes the rule extend an existing rule of the same name with additional
+  /// choices, via `/=` or `//=`
+  pub fn choice_alternate(mut self) -> Self {
+    self.is_choice_alternate = true;
+    self
+  }
+
+  /// Returns the rule
+  pub fn build(self) -> Rule<'a> {
+    let name = identifier(self.name);
+    let generic_param = if self.generic_params.is_empty() {
+      None
+    } else {
+      Some(GenericParm {
+        params: self.generic_params.into_iter().map(identifier).collect(),
+        span: NO_SPAN,
+      })
+    };
+
+    match self.definition {
+      Definition::Type(value) => Rule::Type {
+        rule: TypeRule {
+          name,
+          generic_param,
+          is_type_choice_alternate: self.is_choice_alternate,
+          value,
+        },
+        span: NO_SPAN,
+      },
+      Definition::Group(entry) => Rule::Group {
+        rule: Box::from(GroupRule {
+          name,
+          generic_param,
+          is_group_choice_alternate: self.is_choice_alternate,
+          entry,
+        }),
+        span: NO_SPAN,
+      },
+    }
+  }
+}
+
+// Identifier for a name, which refers to a group socket if it starts with $$
+// and to a type socket if it starts with $, as the lexer would produce
+fn identifier(name: &str) -> Identifier<'_> {
+  let ident = name.trim_start_matches('$');
+  let socket = match name.len() - ident.len() {
+    0 => None,
+    1 => Some(SocketPlug::TYPE),
+    _ => Some(SocketPlug::GROUP),
+  };
+
+  Identifier {
+    ident,
+    socket,
+    span: NO_SPAN,
+  }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+  use super::*;
+  use crate::{lexer::Lexer, parser::cddl_from_str};
+
+  #[cfg(feature = "json-validation")]
+  #[cfg(not(target_arch = "wasm32"))]
+  use crate::validation::Validator;
+
+  // Checks that the built rules are displayed as CDDL that parses back into
+  // the same rules
+  fn assert_reparses(rules: Vec<Rule>) {
+    let mut cddl = CDDL::default();
+    for rule in rules {
+      cddl.push_rule(rule);
+    }
+
+    let input = cddl.to_string();
+    let parsed = cddl_from_str(&mut Lexer::new(&input), &input, true).unwrap();
+    assert_eq!(parsed.to_string(), input);
+  }
+
+  #[test]
+  fn verify_type_builder() {
+    let t = TypeBuilder::uint(0)
+      .range(TypeBuilder::int(10), true)
+      .or(TypeBuilder::text("a"))
+      .or(TypeBuilder::int(-1))
+      .or(TypeBuilder::bytes(b"\x01\xff"))
+      .or(TypeBuilder::tagged(Some(1), TypeBuilder::named("tdate")))
+      .build();
+
+    assert_eq!(t.to_string(), "0..10 / \"a\" / -1 / h'01ff' / #6.1(tdate)");
+
+    let t = TypeBuilder::named("bstr")
+      .control(".cbor", TypeBuilder::named("a").or(TypeBuilder::named("b")))
+      .build();
+
+    assert_eq!(t.to_string(), "bstr .cbor (a / b)");
+  }
+
+  #[test]
+  #[should_panic(expected = "unknown control operator .nope")]
+  fn verify_unknown_control() {
+    TypeBuilder::named("int").control(".nope", TypeBuilder::uint(1));
+  }
+
+  #[test]
+  fn verify_rule_builder() {
+    let header = GroupBuilder::new()
+      .member("alg", TypeBuilder::named("T"))
+      .member(
+        "crit",
+        TypeBuilder::array(
+          GroupBuilder::new()
+            .value(TypeBuilder::named("tstr"))
+            .zero_or_more(),
+        ),
+      )
+      .optional();
+
+    assert_reparses(vec![
+      RuleBuilder::type_rule(
+        "message",
+        TypeBuilder::map(
+          GroupBuilder::new()
+            .group_named("header")
+            .keyed(TypeBuilder::named("tstr"), TypeBuilder::any())
+            .zero_or_more(),
+        ),
+      )
+      .build(),
+      RuleBuilder::group_rule("header", header)
+        .generic_params(vec!["T"])
+        .build(),
+      RuleBuilder::type_rule(
+        "color",
+        TypeBuilder::choice_from_group(
+          GroupBuilder::new()
+            .member("red", TypeBuilder::uint(0))
+            .member("green", TypeBuilder::uint(1)),
+        ),
+      )
+      .build(),
+      RuleBuilder::type_rule(
+        "pair",
+        TypeBuilder::array(
+          GroupBuilder::new()
+            .value(TypeBuilder::named("int"))
+            .value(TypeBuilder::named("int"))
+            .or()
+            .value(TypeBuilder::named("tstr")),
+        ),
+      )
+      .build(),
+      RuleBuilder::type_rule("$extension", TypeBuilder::named("int")).build(),
+      RuleBuilder::type_rule("$extension", TypeBuilder::named("tstr"))
+        .choice_alternate()
+        .build(),
+    ]);
+  }
+
+  #[test]
+  #[cfg(feature = "json-validation")]
+  #[cfg(not(target_arch = "wasm32"))]
+  fn verify_built_rules_validate() {
+    let mut cddl = CDDL::default();
+    cddl.push_rule(
+      RuleBuilder::type_rule(
+        "reputon",
+        TypeBuilder::map(
+          GroupBuilder::new()
+            .member("rater", TypeBuilder::named("tstr"))
+            .member(
+              "rating",
+              TypeBuilder::named("float").control(".le", TypeBuilder::float(1.0)),
+            )
+            .member(
+              "tags",
+              TypeBuilder::array(
+                GroupBuilder::new()
+                  .value(TypeBuilder::named("tag"))
+                  .zero_or_more(),
+              ),
+            ),
+        ),
+      )
+      .build(),
+    );
+    cddl.push_rule(
+      RuleBuilder::type_rule("tag", TypeBuilder::text("a").or(TypeBuilder::text("b"))).build(),
+    );
+
+    let reputon = |rating: f64, tag: &str| serde_json::json!({ "rater": "Ninja", "rating": rating, "tags": [tag] });
+
+    assert!(cddl.validate(&reputon(0.5, "a")).is_ok());
+    assert!(cddl.validate(&reputon(1.5, "a")).is_err());
+    assert!(cddl.validate(&reputon(0.5, "c")).is_err());
+  }
+}
@@ -0,0 +1,324 @@
+use crate::validation::{
+  cbor::{validate_cbor_from_slice, validate_cbor_from_slice_for_rule},
+  json::{validate_json_from_str, validate_json_from_str_for_rule},
+  Error as ValidationError,
+};
+use std::{
+  ffi::{CStr, CString},
+  os::raw::{c_char, c_int},
+  panic::{self, AssertUnwindSafe},
+  ptr, slice,
+};
+
+/// Returned when the data conforms to the CDDL definition
+pub const CDDL_OK: c_int = 0;
+/// Returned when the data doesn't conform to the CDDL definition
+pub const CDDL_INVALID: c_int = 1;
+/// Returned when the CDDL definition or the data can't be parsed, or an
+/// argument is null or isn't valid UTF-8
+pub const CDDL_BAD_INPUT: c_int = 2;
+
+/// Error describing why validation failed, opaque to C callers
+#[derive(Debug)]
+pub struct Error {
+  message: CString,
+}
+
+impl Error {
+  fn new(message: String) -> Self {
+    // Interior nul bytes would otherwise truncate the message in C, so
+    // they're dropped
+    let message = message.replace('\0', "");
+
+    Error {
+      message: CString::new(message).unwrap_or_default(),
+    }
+  }
+}
+
+/// Validates the nul-terminated JSON text `json` against the nul-terminated
+/// CDDL definition `cddl`
+///
+/// # Safety
+///
+/// `cddl` and `json` must be null or point to nul-terminated strings, and
+/// `error` must be null or point to memory a pointer can be written to.
+#[no_mangle]
+pub unsafe extern "C" fn cddl_validate_json(
+  cddl: *const c_char,
+  json: *const c_char,
+  error: *mut *mut Error,
+) -> c_int {
+  run(error, || {
+    validate_json_from_str(to_str(cddl, "cddl")?, to_str(json, "json")?).map_err(from_validation)
+  })
+}
+
+/// Validates the nul-terminated JSON text `json` against the rule named
+/// `rule` in the nul-terminated CDDL definition `cddl`
+///
+/// # Safety
+///
+/// `cddl`, `json` and `rule` must be null or point to nul-terminated strings,
+/// and `error` must be null or point to memory a pointer can be written to.
+#[no_mangle]
+pub unsafe extern "C" fn cddl_validate_json_for_rule(
+  cddl: *const c_char,
+  json: *const c_char,
+  rule: *const c_char,
+  error: *mut *mut Error,
+) -> c_int {
+  run(error, || {
+    validate_json_from_str_for_rule(
+      to_str(cddl, "cddl")?,
+      to_str(json, "json")?,
+      to_str(rule, "rule")?,
+    )
+    .map_err(from_validation)
+  })
+}
+
+/// Validates the `len` bytes of encoded CBOR at `cbor` against the
+/// nul-terminated CDDL definition `cddl`
+///
+/// # Safety
+///
+/// `cddl` must be null or point to a nul-terminated string, `cbor` must be
+/// null or point to `len` readable bytes, and `error` must be null or point to
+/// memory a pointer can be written to.
+#[no_mangle]
+pub unsafe extern "C" fn cddl_validate_cbor(
+  cddl: *const c_char,
+  cbor: *const u8,
+  len: usize,
+  error: *mut *mut Error,
+) -> c_int {
+  run(error, || {
+    validate_cbor_from_slice(to_str(cddl, "cddl")?, to_slice(cbor, len)?).map_err(from_validation)
+  })
+}
+
+/// Validates the `len` bytes of encoded CBOR at `cbor` against the rule named
+/// `rule` in the nul-terminated CDDL definition `cddl`
+///
+/// # Safety
+///
+/// `cddl` and `rule` must be null or point to nul-terminated strings, `cbor`
+/// must be null or point to `len` readable bytes, and `error` must be null or
+/// point to memory a pointer can be written to.
+#[no_mangle]
+pub unsafe extern "C" fn cddl_validate_cbor_for_rule(
+  cddl: *const c_char,
+  cbor: *const u8,
+  len: usize,
+  rule: *const c_char,
+  error: *mut *mut Error,
+) -> c_int {
+  run(error, || {
+    validate_cbor_from_slice_for_rule(
+      to_str(cddl, "cddl")?,
+      to_slice(cbor, len)?,
+      to_str(rule, "rule")?,
+    )
+    .map_err(from_validation)
+  })
+}
+
+/// Returns the nul-terminated message of an error, which remains valid until
+/// the error is freed, or null if `error` is null
+///
+/// # Safety
+///
+/// `error` must be null or have been returned by one of the validation
+/// functions and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn cddl_error_message(error: *const Error) -> *const c_char {
+  match error.as_ref() {
+    Some(error) => error.message.as_ptr(),
+    None => ptr::null(),
+  }
+}
+
+/// Frees an error returned by one of the validation functions. Does nothing
+/// if `error` is null.
+///
+/// # Safety
+///
+/// `error` must be null or have been returned by one of the validation
+/// functions and not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn cddl_error_free(error: *mut Error) {
+  if !error.is_null() {
+    drop(Box::from_raw(error));
+  }
+}
+
+// Runs a validation, storing any error in `error` and translating the outcome
+// to a status code. Panics mustn't unwind into C, so they're reported as
+// errors too.
+unsafe fn run<F>(error: *mut *mut Error, validate: F) -> c_int
+where
+  F: FnOnce() -> std::result::Result<(), (c_int, Error)>,
+{
+  if !error.is_null() {
+    *error = ptr::null_mut();
+  }
+
+  let (status, e) = match panic::catch_unwind(AssertUnwindSafe(validate)) {
+    Ok(Ok(())) => return CDDL_OK,
+    Ok(Err(failure)) => failure,
+    Err(_) => (
+      CDDL_BAD_INPUT,
+      Error::new("validation panicked".to_string()),
+    ),
+  };
+
+  if !error.is_null() {
+    *error = Box::into_raw(Box::new(e));
+  }
+
+  status
+}
+
+fn from_validation(error: ValidationError) -> (c_int, Error) {
+  let status = match error {
+    ValidationError::Compilation(_) | ValidationError::Target(_) => CDDL_BAD_INPUT,
+    _ => CDDL_INVALID,
+  };
+
+  (status, Error::new(error.to_string()))
+}
+
+unsafe fn to_str<'a>(s: *const c_char, name: &str) -> std::result::Result<&'a str, (c_int, Error)> {
+  if s.is_null() {
+    return Err(bad_input(format!("{} is null", name)));
+  }
+
+  CStr::from_ptr(s)
+    .to_str()
+    .map_err(|e| bad_input(format!("{} isn't valid UTF-8: {}", name, e)))
+}
+
+unsafe fn to_slice<'a>(
+  bytes: *const u8,
+  len: usize,
+) -> std::result::Result<&'a [u8], (c_int, Error)> {
+  if bytes.is_null() {
+    if len == 0 {
+      return Ok(&[]);
+    }
+
+    return Err(bad_input("cbor is null".to_string()));
+  }
+
+  Ok(slice::from_raw_parts(bytes, len))
+}
+
+fn bad_input(message: String) -> (c_int, Error) {
+  (CDDL_BAD_INPUT, Error::new(message))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn message(error: *const Error) -> String {
+    unsafe { CStr::from_ptr(cddl_error_message(error)) }
+      .to_string_lossy()
+      .into_owned()
+  }
+
+  #[test]
+  fn validate_json() {
+    let cddl = CString::new("thing = { id: uint, ? name: tstr }").unwrap();
+    let mut error = ptr::null_mut();
+
+    let json = CString::new(r#"{ "id": 1 }"#).unwrap();
+    let status = unsafe { cddl_validate_json(cddl.as_ptr(), json.as_ptr(), &mut error) };
+    assert_eq!(status, CDDL_OK);
+    assert!(error.is_null());
+
+    let json = CString::new(r#"{ "id": "one" }"#).unwrap();
+    let status = unsafe { cddl_validate_json(cddl.as_ptr(), json.as_ptr(), &mut error) };
+    assert_eq!(status, CDDL_INVALID);
+    assert!(message(error).contains("id"));
+    unsafe { cddl_error_free(error) };
+
+    let json = CString::new(r#"{ "id": "#).unwrap();
+    let status = unsafe { cddl_validate_json(cddl.as_ptr(), json.as_ptr(), &mut error) };
+    assert_eq!(status, CDDL_BAD_INPUT);
+    unsafe { cddl_error_free(error) };
+
+    let rule = CString::new("name").unwrap();
+    let cddl = CString::new("thing = { name: name }\nname = tstr").unwrap();
+    let json = CString::new(r#""Alice""#).unwrap();
+    let status = unsafe {
+      cddl_validate_json_for_rule(cddl.as_ptr(), json.as_ptr(), rule.as_ptr(), ptr::null_mut())
+    };
+    assert_eq!(status, CDDL_OK);
+  }
+
+  #[test]
+  fn validate_cbor() {
+    let cddl = CString::new("reading = [sensor: uint, value: int]").unwrap();
+    let mut error = ptr::null_mut();
+
+    let cbor = [0x82, 0x01, 0x38, 0x63];
+    let status =
+      unsafe { cddl_validate_cbor(cddl.as_ptr(), cbor.as_ptr(), cbor.len(), &mut error) };
+    assert_eq!(status, CDDL_OK);
+    assert!(error.is_null());
+
+    let cbor = [0x82, 0x01, 0x61, 0x61];
+    let status =
+      unsafe { cddl_validate_cbor(cddl.as_ptr(), cbor.as_ptr(), cbor.len(), &mut error) };
+    assert_eq!(status, CDDL_INVALID);
+    assert!(!message(error).is_empty());
+    unsafe { cddl_error_free(error) };
+
+    let cbor = [0x82, 0x01];
+    let status =
+      unsafe { cddl_validate_cbor(cddl.as_ptr(), cbor.as_ptr(), cbor.len(), &mut error) };
+    assert_eq!(status, CDDL_BAD_INPUT);
+    unsafe { cddl_error_free(error) };
+
+    let rule = CString::new("sensor").unwrap();
+    let cddl = CString::new("reading = [sensor, value: int]\nsensor = uint").unwrap();
+    let cbor = [0x01];
+    let status = unsafe {
+      cddl_validate_cbor_for_rule(
+        cddl.as_ptr(),
+        cbor.as_ptr(),
+        cbor.len(),
+        rule.as_ptr(),
+        ptr::null_mut(),
+      )
+    };
+    assert_eq!(status, CDDL_OK);
+  }
+
+  #[test]
+  fn verify_bad_arguments() {
+    let mut error = ptr::null_mut();
+
+    let status = unsafe { cddl_validate_json(ptr::null(), ptr::null(), &mut error) };
+    assert_eq!(status, CDDL_BAD_INPUT);
+    assert_eq!(message(error), "cddl is null");
+    unsafe { cddl_error_free(error) };
+
+    let cddl = CString::new("thing = uint").unwrap();
+    let status = unsafe { cddl_validate_cbor(cddl.as_ptr(), ptr::null(), 1, &mut error) };
+    assert_eq!(status, CDDL_BAD_INPUT);
+    assert_eq!(message(error), "cbor is null");
+    unsafe { cddl_error_free(error) };
+
+    let cddl = CString::new("thing = = uint").unwrap();
+    let json = CString::new("1").unwrap();
+    let status = unsafe { cddl_validate_json(cddl.as_ptr(), json.as_ptr(), &mut error) };
+    assert_eq!(status, CDDL_BAD_INPUT);
+    unsafe { cddl_error_free(error) };
+
+    assert!(unsafe { cddl_error_message(ptr::null()) }.is_null());
+    unsafe { cddl_error_free(ptr::null_mut()) };
+  }
+}
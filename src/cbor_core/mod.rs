@@ -0,0 +1,2072 @@
+mod value;
+
+pub use value::{
+  decode, decode_deterministic, duplicate_keys, first_unsorted_key, remove_duplicate_keys,
+  DecodeError, DuplicateKey, EncodingViolation, EncodingViolationKind, FloatWidth, UnsortedKey,
+  Value,
+};
+
+#[cfg(feature = "tokio")]
+pub(crate) use value::decode_prefix;
+#[cfg(feature = "cbor-validation")]
+pub(crate) use value::decode_seq;
+
+use crate::{
+  ast::*,
+  error,
+  prelude::{is_extension_type, is_numeric_data_type, is_prelude_type, with_prelude},
+  token,
+};
+use std::{convert::TryFrom, fmt};
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+  string::{String, ToString},
+  vec::Vec,
+};
+
+/// Maximum number of nested rule references followed while validating data.
+/// This is kept lower than the limit of the standard validators to suit the
+/// small stacks of embedded targets.
+pub const MAX_DEPTH: usize = 64;
+
+/// Error validating CBOR
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+  /// CDDL that couldn't be parsed
+  CDDL(String),
+  /// Data that isn't well-formed CBOR
+  Decode(DecodeError),
+  /// Data item that doesn't match the type it's expected to be
+  Mismatch {
+    /// Location of the data item as a JSON Pointer (RFC 6901), e.g.
+    /// `/reputons/0/rating`
+    path: String,
+    /// Type the data item is expected to match
+    expected: String,
+  },
+  /// Map or array that lacks a required entry
+  MissingEntry {
+    /// Location of the map or array as a JSON Pointer
+    path: String,
+    /// Group entry that wasn't matched, e.g. `name: tstr`
+    entry: String,
+  },
+  /// Map entry or array element that isn't described by the definition
+  UnexpectedEntry {
+    /// Location of the entry as a JSON Pointer
+    path: String,
+  },
+  /// Reference to a rule that isn't defined
+  UndefinedRule(String),
+  /// CDDL construct that this validator doesn't check the way the standard
+  /// validators do, e.g. the `.regexp` control operator. It's reported in
+  /// place of any other outcome once the data has been checked against it.
+  Unsupported(String),
+  /// Maximum depth of nested rule references exceeded
+  Recursion {
+    /// Name of the rule that was being resolved when the limit was reached
+    rule: String,
+    /// Maximum depth in effect
+    max_depth: usize,
+  },
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Error::CDDL(e) => write!(f, "CDDL error: {}", e),
+      Error::Decode(e) => write!(f, "{}", e),
+      Error::Mismatch { path, expected } => {
+        write!(f, "expected {} at \"{}\"", expected, path)
+      }
+      Error::MissingEntry { path, entry } => {
+        write!(f, "missing entry {} at \"{}\"", entry, path)
+      }
+      Error::UnexpectedEntry { path } => write!(f, "unexpected entry at \"{}\"", path),
+      Error::UndefinedRule(rule) => write!(f, "no rule with name \"{}\" defined", rule),
+      Error::Unsupported(construct) => write!(f, "unsupported CDDL construct: {}", construct),
+      Error::Recursion { rule, max_depth } => write!(
+        f,
+        "maximum validation depth of {} exceeded while resolving rule \"{}\"",
+        max_depth, rule
+      ),
+    }
+  }
+}
+
+impl error::Error for Error {
+  fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+    match self {
+      Error::Decode(e) => Some(e),
+      _ => None,
+    }
+  }
+}
+
+impl From<DecodeError> for Error {
+  fn from(e: DecodeError) -> Self {
+    Error::Decode(e)
+  }
+}
+
+/// Validates a CBOR data item against the first type rule of the given CDDL
+///
+/// The verdict is the same as that of the standard CBOR validator, unless the
+/// data meets a construct this validator doesn't check, such as the `.regexp`
+/// control operator, in which case `Error::Unsupported` is returned instead.
+///
+/// # Example
+///
+/// ```
+/// use cddl::{cbor_core, lexer_from_str, parser::Parser};
+///
+/// let input = r#"point = [x: int, y: int]"#;
+/// let cddl = Parser::new(lexer_from_str(input).iter(), input)
+///   .unwrap()
+///   .parse_cddl()
+///   .unwrap();
+///
+/// let point = cbor_core::decode(&[0x82, 0x01, 0x20]).unwrap();
+/// assert!(cbor_core::validate(&cddl, &point).is_ok());
+/// ```
+pub fn validate(cddl: &CDDL, value: &Value) -> Result<(), Error> {
+  match cddl.rules.iter().find_map(|r| match r {
+    Rule::Type { rule, .. } => Some(rule.name.ident),
+    _ => None,
+  }) {
+    Some(root) => validate_for_rule(cddl, root, value),
+    None => Ok(()),
+  }
+}
+
+/// Validates a CBOR data item against the rule with the given name
+pub fn validate_for_rule(cddl: &CDDL, rule_name: &str, value: &Value) -> Result<(), Error> {
+  let root = match cddl.rule(rule_name) {
+    Some(Rule::Type { rule, .. }) => &rule.name,
+    Some(Rule::Group { rule, .. }) => &rule.name,
+    None => return Err(Error::UndefinedRule(rule_name.to_string())),
+  };
+
+  with_prelude(|prelude| {
+    let mut validator = Validator {
+      cddl,
+      prelude,
+      path: Vec::new(),
+      depth: 0,
+      exceeded: None,
+      unsupported: None,
+    };
+
+    let result = validator.validate_typename(root, None, value, None);
+
+    match (validator.exceeded, validator.unsupported) {
+      (Some((rule, max_depth)), _) => Err(Error::Recursion { rule, max_depth }),
+      (None, Some(construct)) => Err(Error::Unsupported(construct)),
+      (None, None) => result,
+    }
+  })
+}
+
+/// Validates encoded CBOR against the first type rule of the given CDDL input
+///
+/// # Example
+///
+/// ```
+/// use cddl::cbor_core::validate_from_slice;
+///
+/// let cddl_input = r#"reputon = { rater: tstr, rating: 0..10 }"#;
+///
+/// // {"rater": "Ninja", "rating": 7}
+/// let cbor = [
+///   0xa2, 0x65, b'r', b'a', b't', b'e', b'r', 0x65, b'N', b'i', b'n', b'j', b'a', 0x66, b'r',
+///   b'a', b't', b'i', b'n', b'g', 0x07,
+/// ];
+/// assert!(validate_from_slice(cddl_input, &cbor).is_ok());
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+pub fn validate_from_slice(cddl_input: &str, cbor: &[u8]) -> Result<(), Error> {
+  let mut lexer = crate::lexer::Lexer::new(cddl_input);
+
+  #[cfg(feature = "std")]
+  let cddl = crate::parser::cddl_from_str(&mut lexer, cddl_input, false);
+  #[cfg(not(feature = "std"))]
+  let cddl = crate::parser::cddl_from_str(&mut lexer, cddl_input);
+
+  validate(&cddl.map_err(Error::CDDL)?, &decode(cbor)?)
+}
+
+// Generic arguments bound to the generic parameters of the rule being
+// validated against. Arguments are resolved in the scope of the rule that
+// referenced the generic rule.
+struct Scope<'s, 'c> {
+  params: &'c [Identifier<'c>],
+  args: &'c [Type1<'c>],
+  parent: Env<'s, 'c>,
+}
+
+type Env<'s, 'c> = Option<&'s Scope<'s, 'c>>;
+
+// Unbounded upper limit of an occurrence indicator
+const UNBOUNDED: usize = !0;
+
+struct Validator<'c> {
+  cddl: &'c CDDL<'c>,
+  prelude: &'c CDDL<'c>,
+  // Location within the data being validated as JSON Pointer reference tokens
+  path: Vec<String>,
+  depth: usize,
+  // Name of the rule and maximum depth in effect when the maximum depth was
+  // first exceeded, reported once validation completes rather than being
+  // masked by the errors of alternative choices
+  exceeded: Option<(String, usize)>,
+  // First construct met that isn't checked the way the standard validators
+  // check it, reported once validation completes as the outcome could differ
+  // from theirs
+  unsupported: Option<String>,
+}
+
+// Whether the value is an integer equal to the given uint literal, which may
+// exceed the range of the integers decoded
+fn is_uint(value: &Value, u: u128) -> bool {
+  value
+    .as_integer()
+    .map(|i| i >= 0 && i as u128 == u)
+    .unwrap_or(false)
+}
+
+// Numeric value of a literal or range bound
+#[derive(Clone, Copy)]
+enum Number {
+  Int(i128),
+  Float(f64),
+}
+
+impl Number {
+  fn of(value: &Value) -> Option<Number> {
+    match value {
+      Value::Float(f, _) => Some(Number::Float(*f)),
+      _ => value.as_integer().map(Number::Int),
+    }
+  }
+
+  fn as_f64(self) -> f64 {
+    match self {
+      Number::Int(i) => i as f64,
+      Number::Float(f) => f,
+    }
+  }
+
+  fn partial_cmp(self, other: Number) -> Option<core::cmp::Ordering> {
+    match (self, other) {
+      (Number::Int(a), Number::Int(b)) => Some(a.cmp(&b)),
+      (a, b) => a.as_f64().partial_cmp(&b.as_f64()),
+    }
+  }
+}
+
+// Type or type name that an enumerated group entry stands for
+enum Choice<'c> {
+  Type(&'c Type<'c>),
+  Name(&'c Identifier<'c>, Option<&'c GenericArg<'c>>),
+}
+
+impl<'c> Validator<'c> {
+  fn current_path(&self) -> String {
+    pointer(&self.path)
+  }
+
+  fn at<R, F: FnOnce(&mut Self) -> R>(&mut self, token: String, f: F) -> R {
+    self.path.push(token);
+    let result = f(self);
+    self.path.pop();
+
+    result
+  }
+
+  fn mismatch(&self, expected: &dyn fmt::Display) -> Error {
+    Error::Mismatch {
+      path: self.current_path(),
+      expected: expected.to_string(),
+    }
+  }
+
+  fn unsupported(&mut self, construct: String) -> Error {
+    if self.unsupported.is_none() {
+      self.unsupported = Some(construct.clone());
+    }
+
+    Error::Unsupported(construct)
+  }
+
+  fn rules(&self, name: &str) -> Vec<&'c Rule<'c>> {
+    let rules = self.cddl.rules_named(name).collect::<Vec<_>>();
+    if rules.is_empty() && is_prelude_type(name) {
+      return self.prelude.rules_named(name).collect();
+    }
+
+    rules
+  }
+
+  // Returns the error of the choice that got furthest into the data when none
+  // of the choices matched, or a single mismatch if none of them matched any
+  // of it
+  fn choice_error(&self, errors: Vec<Error>, expected: &dyn fmt::Display) -> Error {
+    let current = self.current_path();
+    let depth = |e: &Error| match e {
+      Error::Mismatch { path, .. }
+      | Error::MissingEntry { path, .. }
+      | Error::UnexpectedEntry { path } => Some(path.len()),
+      _ => None,
+    };
+
+    if errors.iter().all(|e| depth(e) == Some(current.len())) {
+      return self.mismatch(expected);
+    }
+
+    let mut errors = errors.into_iter();
+    let first = errors.next();
+    errors
+      .fold(first, |deepest, e| match deepest {
+        Some(d) if depth(&e).unwrap_or(0) <= depth(&d).unwrap_or(0) => Some(d),
+        _ => Some(e),
+      })
+      .unwrap_or_else(|| self.mismatch(expected))
+  }
+
+  fn validate_type(
+    &mut self,
+    t: &'c Type<'c>,
+    value: &Value,
+    env: Env<'_, 'c>,
+  ) -> Result<(), Error> {
+    let mut errors = Vec::new();
+
+    for t1 in t.type_choices.iter() {
+      match self.validate_type1(t1, value, env) {
+        Ok(()) => return Ok(()),
+        Err(e) => errors.push(e),
+      }
+    }
+
+    if errors.len() == 1 {
+      return Err(errors.remove(0));
+    }
+
+    Err(self.choice_error(errors, t))
+  }
+
+  fn validate_type1(
+    &mut self,
+    t1: &'c Type1<'c>,
+    value: &Value,
+    env: Env<'_, 'c>,
+  ) -> Result<(), Error> {
+    let (op, controller) = match &t1.operator {
+      Some((op, controller)) => (op, controller),
+      None => return self.validate_type2(&t1.type2, value, env),
+    };
+
+    match op {
+      RangeCtlOp::RangeOp { is_inclusive, .. } => {
+        if self.is_in_range(&t1.type2, controller, *is_inclusive, value, env) {
+          Ok(())
+        } else {
+          Err(self.mismatch(t1))
+        }
+      }
+      RangeCtlOp::CtlOp { ctrl, .. } => self.validate_control(t1, ctrl, controller, value, env),
+    }
+  }
+
+  // Checks a number against a range. As with the standard validators, integer
+  // bounds only admit integers, float bounds admit any number and bounds that
+  // aren't both integers or both floats admit nothing. Bounds given by name
+  // are resolved to the values of the rules they name.
+  fn is_in_range(
+    &self,
+    lower: &'c Type2<'c>,
+    upper: &'c Type2<'c>,
+    is_inclusive: bool,
+    value: &Value,
+    env: Env<'_, 'c>,
+  ) -> bool {
+    let n = match Number::of(value) {
+      Some(n) => n,
+      None => return false,
+    };
+
+    let is_within = |n: Number, lower: Number, upper: Number| {
+      let above = match n.partial_cmp(lower) {
+        Some(o) => o != core::cmp::Ordering::Less,
+        None => false,
+      };
+      let below = match n.partial_cmp(upper) {
+        Some(core::cmp::Ordering::Less) => true,
+        Some(core::cmp::Ordering::Equal) => is_inclusive,
+        _ => false,
+      };
+
+      above && below
+    };
+
+    match (lower, upper, numeric_literal(lower), numeric_literal(upper)) {
+      (Type2::UintValue { .. }, Type2::IntValue { .. }, ..) => false,
+      (_, _, Some(l @ Number::Int(_)), Some(u @ Number::Int(_))) => match n {
+        Number::Int(_) => is_within(n, l, u),
+        Number::Float(_) => false,
+      },
+      (_, _, Some(l @ Number::Float(_)), Some(u @ Number::Float(_))) => is_within(n, l, u),
+      (_, _, None, _) => match self.range_bounds(lower, env) {
+        Some(bounds) => bounds
+          .into_iter()
+          .any(|bound| self.is_in_range(bound, upper, is_inclusive, value, env)),
+        None => false,
+      },
+      (_, _, _, None) => match self.range_bounds(upper, env) {
+        Some(bounds) => bounds
+          .into_iter()
+          .any(|bound| self.is_in_range(lower, bound, is_inclusive, value, env)),
+        None => false,
+      },
+      _ => false,
+    }
+  }
+
+  // Validates data against a control operator the way the standard validators
+  // do. Operators they check that this validator can't, and operators applied
+  // to targets or controllers they can't be checked against, are unsupported.
+  fn validate_control(
+    &mut self,
+    t1: &'c Type1<'c>,
+    ctrl: &str,
+    controller: &'c Type2<'c>,
+    value: &Value,
+    env: Env<'_, 'c>,
+  ) -> Result<(), Error> {
+    let target = &t1.type2;
+
+    match ctrl {
+      ".default" => self.validate_type2(target, value, env),
+      ".capture" => {
+        // The controller has to be a single text string naming the capture
+        match self.text_values(controller, env).as_deref() {
+          Some([_]) => self.validate_type2(target, value, env),
+          _ => Err(self.unsupported(t1.to_string())),
+        }
+      }
+      ".and" => {
+        self.validate_type2(target, value, env)?;
+        self.validate_type2(controller, value, env)
+      }
+      // The target is meant to be a subset of the controller, so a value that
+      // only matches the target points to a mistake in the definition rather
+      // than in the data
+      ".within" => self.validate_type2(target, value, env),
+      ".size" => {
+        let size = match value {
+          Value::Text(t) if self.is_target_of(target, env, is_text_type, 0) => t.len(),
+          // An unsigned integer must fit in the given number of bytes
+          Value::Unsigned(n) if self.is_target_of(target, env, is_numeric_data_type, 0) => {
+            return match controller {
+              Type2::UintValue { value: size, .. }
+                if *size >= 16 || u128::from(*n) >> (8 * *size as u32) == 0 =>
+              {
+                Ok(())
+              }
+              _ => Err(self.mismatch(t1)),
+            };
+          }
+          Value::Bytes(b) if self.is_target_of(target, env, is_byte_string_type, 0) => b.len(),
+          _ => return Err(self.mismatch(t1)),
+        };
+
+        self
+          .validate_type2(controller, &Value::Unsigned(size as u64), env)
+          .map_err(|_| self.mismatch(t1))
+      }
+      ".bits" => {
+        self.validate_type2(target, value, env)?;
+
+        // Bits are numbered from the least significant bit of an integer, or
+        // of the first byte of a byte string. Other values have no bits set.
+        let set_bits = match value {
+          Value::Unsigned(n) => (0..64)
+            .filter(|bit| n & (1 << bit) != 0)
+            .collect::<Vec<u64>>(),
+          Value::Bytes(b) => (0..b.len() as u64 * 8)
+            .filter(|bit| b[(bit / 8) as usize] & (1 << (bit % 8)) != 0)
+            .collect(),
+          _ => Vec::new(),
+        };
+
+        for bit in set_bits {
+          if self
+            .validate_type2(controller, &Value::Unsigned(bit), env)
+            .is_err()
+          {
+            return Err(self.mismatch(t1));
+          }
+        }
+
+        Ok(())
+      }
+      ".lt" | ".le" | ".gt" | ".ge" | ".eq" | ".ne" => {
+        let is_equality = ctrl == ".eq" || ctrl == ".ne";
+
+        // Numbers are compared numerically, and text strings can only be
+        // compared for equality
+        let satisfied = if self.is_target_of(target, env, is_numeric_data_type, 0) {
+          self.validate_type2(target, value, env)?;
+
+          let controllers = match self.numeric_values(controller, env) {
+            Some(controllers) => controllers,
+            None => return Err(self.unsupported(t1.to_string())),
+          };
+          let n = Number::of(value);
+
+          let mut orderings = controllers
+            .into_iter()
+            .map(|c| n.and_then(|n| n.partial_cmp(c)));
+
+          match ctrl {
+            ".lt" => orderings.any(|o| o == Some(core::cmp::Ordering::Less)),
+            ".le" => orderings.any(|o| o.map_or(false, |o| o != core::cmp::Ordering::Greater)),
+            ".gt" => orderings.any(|o| o == Some(core::cmp::Ordering::Greater)),
+            ".ge" => orderings.any(|o| o.map_or(false, |o| o != core::cmp::Ordering::Less)),
+            ".eq" => orderings.any(|o| o == Some(core::cmp::Ordering::Equal)),
+            _ => !orderings.any(|o| o == Some(core::cmp::Ordering::Equal)),
+          }
+        } else if is_equality && self.is_target_of(target, env, is_text_type, 0) {
+          if ctrl == ".ne" {
+            self.validate_type2(target, value, env)?;
+          }
+
+          let controllers = match self.text_values(controller, env) {
+            Some(controllers) => controllers,
+            None => return Err(self.unsupported(t1.to_string())),
+          };
+          let is_equal = match value {
+            Value::Text(t) => controllers.iter().any(|c| c == t),
+            _ => false,
+          };
+
+          is_equal == (ctrl == ".eq")
+        } else {
+          return Err(self.unsupported(t1.to_string()));
+        };
+
+        if satisfied {
+          Ok(())
+        } else {
+          Err(self.mismatch(t1))
+        }
+      }
+      ".cbor" | ".cborseq" => {
+        self.validate_type2(target, value, env)?;
+
+        let bytes = match value {
+          Value::Bytes(b) => b,
+          _ => return Err(self.mismatch(t1)),
+        };
+
+        let embedded = if ctrl == ".cbor" {
+          decode(bytes)
+        } else {
+          value::decode_seq(bytes).map(Value::Array)
+        };
+
+        match embedded {
+          Ok(embedded) => self.validate_type2(controller, &embedded, env),
+          Err(_) => Err(self.mismatch(t1)),
+        }
+      }
+      _ => Err(self.unsupported(format!("{} control operator", ctrl))),
+    }
+  }
+
+  fn validate_type2(
+    &mut self,
+    t2: &'c Type2<'c>,
+    value: &Value,
+    env: Env<'_, 'c>,
+  ) -> Result<(), Error> {
+    let matches = match t2 {
+      Type2::IntValue { value: i, .. } => value.as_integer() == Some(*i),
+      Type2::UintValue { value: u, .. } => is_uint(value, *u),
+      #[allow(clippy::float_cmp)]
+      Type2::FloatValue { value: f, .. } => match value {
+        Value::Float(v, _) => v == f,
+        _ => false,
+      },
+      Type2::TextValue { value: t, .. } => match value {
+        Value::Text(v) => v == t,
+        _ => false,
+      },
+      Type2::UTF8ByteString { value: b, .. } => match value {
+        Value::Bytes(v) => v[..] == b[..],
+        _ => false,
+      },
+      Type2::B16ByteString { value: b, .. } => match value {
+        Value::Bytes(v) => decode_b16(b).as_ref() == Some(v),
+        _ => false,
+      },
+      Type2::B64ByteString { value: b, .. } => match value {
+        Value::Bytes(v) => decode_b64(b).as_ref() == Some(v),
+        _ => false,
+      },
+      Type2::Typename {
+        ident, generic_arg, ..
+      } => return self.validate_typename(ident, generic_arg.as_ref(), value, env),
+      Type2::Unwrap { .. } => return Err(self.unsupported(format!("unwrap operator in {}", t2))),
+      Type2::ParenthesizedType { pt, .. } => return self.validate_type(pt, value, env),
+      Type2::Map { group, .. } => match value {
+        Value::Map(entries) => return self.validate_map(group, entries, env),
+        _ => false,
+      },
+      Type2::Array { group, .. } => match value {
+        Value::Array(values) => return self.validate_array(group, values, env),
+        _ => false,
+      },
+      Type2::ChoiceFromInlineGroup { group, .. } => {
+        let mut choices = Vec::new();
+        self.group_choices(group, &mut choices);
+
+        return self.validate_choices(&choices, t2, value, env);
+      }
+      Type2::ChoiceFromGroup {
+        ident, generic_arg, ..
+      } => {
+        for rule in self.rules(ident.ident) {
+          match rule {
+            Rule::Group { rule, .. } => {
+              let mut choices = Vec::new();
+              self.entry_choices(&rule.entry, &mut choices);
+
+              let scope = scope(&rule.generic_param, generic_arg.as_ref(), env);
+              if self
+                .validate_choices(&choices, t2, value, scope.as_ref())
+                .is_ok()
+              {
+                return Ok(());
+              }
+            }
+            Rule::Type { .. } => {
+              return Err(Error::CDDL(format!(
+                "{} must refer to a group rule to enumerate it",
+                t2
+              )))
+            }
+          }
+        }
+
+        false
+      }
+      Type2::TaggedData { tag, t, .. } => match value {
+        Value::Tag(actual, tagged) if tag.map(|tag| tag as u64).unwrap_or(*actual) == *actual => {
+          return self.validate_type(t, tagged, env)
+        }
+        _ => false,
+      },
+      Type2::TaggedDataMajorType { mt, constraint, .. } => {
+        is_major_type(*mt, constraint.map(|c| c as u64), value)
+      }
+      Type2::Any(_) => true,
+    };
+
+    if matches {
+      Ok(())
+    } else {
+      Err(self.mismatch(t2))
+    }
+  }
+
+  fn validate_choices(
+    &mut self,
+    choices: &[Choice<'c>],
+    t2: &'c Type2<'c>,
+    value: &Value,
+    env: Env<'_, 'c>,
+  ) -> Result<(), Error> {
+    for choice in choices.iter() {
+      let result = match choice {
+        Choice::Type(t) => self.validate_type(t, value, env),
+        Choice::Name(ident, generic_arg) => self.validate_typename(ident, *generic_arg, value, env),
+      };
+
+      if result.is_ok() {
+        return Ok(());
+      }
+    }
+
+    Err(self.mismatch(t2))
+  }
+
+  // Collects the types of the entries of a group enumerated via the `&`
+  // operator
+  fn group_choices(&self, group: &'c Group<'c>, choices: &mut Vec<Choice<'c>>) {
+    for gc in group.group_choices.iter() {
+      for (entry, _) in gc.group_entries.iter() {
+        self.entry_choices(entry, choices);
+      }
+    }
+  }
+
+  fn entry_choices(&self, entry: &'c GroupEntry<'c>, choices: &mut Vec<Choice<'c>>) {
+    match entry {
+      GroupEntry::ValueMemberKey { ge, .. } => choices.push(Choice::Type(&ge.entry_type)),
+      GroupEntry::TypeGroupname { ge, .. } => {
+        let group_rules = self
+          .rules(ge.name.ident)
+          .into_iter()
+          .filter_map(|rule| match rule {
+            Rule::Group { rule, .. } => Some(&rule.entry),
+            _ => None,
+          })
+          .collect::<Vec<_>>();
+
+        if group_rules.is_empty() {
+          choices.push(Choice::Name(&ge.name, ge.generic_arg.as_ref()));
+        }
+
+        for entry in group_rules {
+          self.entry_choices(entry, choices);
+        }
+      }
+      GroupEntry::InlineGroup { group, .. } => self.group_choices(group, choices),
+    }
+  }
+
+  fn validate_typename(
+    &mut self,
+    ident: &'c Identifier<'c>,
+    generic_arg: Option<&'c GenericArg<'c>>,
+    value: &Value,
+    env: Env<'_, 'c>,
+  ) -> Result<(), Error> {
+    // Generic parameters of the rule being validated against shadow rules of
+    // the same name
+    if let Some(scope) = env {
+      if let Some(idx) = scope.params.iter().position(|p| p.ident == ident.ident) {
+        return match scope.args.get(idx) {
+          Some(arg) => self.validate_type1(arg, value, scope.parent),
+          None => Err(Error::CDDL(format!(
+            "missing generic argument for parameter {}",
+            ident
+          ))),
+        };
+      }
+    }
+
+    if let Some(result) = self.validate_prelude_semantics(ident, value) {
+      return result;
+    }
+
+    let rules = self.rules(ident.ident);
+    if rules.is_empty() {
+      // A socket without any definitions is an empty choice
+      if ident.socket.is_some() {
+        return Err(self.mismatch(ident));
+      }
+
+      return Err(Error::UndefinedRule(ident.ident.to_string()));
+    }
+
+    if self.depth >= MAX_DEPTH {
+      if self.exceeded.is_none() {
+        self.exceeded = Some((ident.ident.to_string(), MAX_DEPTH));
+      }
+
+      return Err(Error::Recursion {
+        rule: ident.ident.to_string(),
+        max_depth: MAX_DEPTH,
+      });
+    }
+
+    self.depth += 1;
+    let result = self.validate_rules(&rules, generic_arg, value, env);
+    self.depth -= 1;
+
+    // Report the name of the rule rather than its definition when the value
+    // itself didn't match
+    match result {
+      Err(Error::Mismatch { path, .. }) if path == self.current_path() => Err(Error::Mismatch {
+        path,
+        expected: match generic_arg {
+          Some(ga) => format!("{}{}", ident, ga),
+          None => ident.to_string(),
+        },
+      }),
+      result => result,
+    }
+  }
+
+  // Checks data against the semantics the standard validators give some types
+  // of the standard prelude beyond their definitions, e.g. that the number of
+  // seconds tagged as `time` is finite. Returns None if the definition of the
+  // type is all there is to check.
+  fn validate_prelude_semantics(
+    &mut self,
+    ident: &'c Identifier<'c>,
+    value: &Value,
+  ) -> Option<Result<(), Error>> {
+    match (ident.ident, value) {
+      // Bignums are interchangeable with integers of the same value
+      ("biguint", Value::Unsigned(_)) | ("bignint", Value::Negative(_)) => Some(Ok(())),
+      ("bigint", Value::Unsigned(_)) | ("bigint", Value::Negative(_)) => Some(Ok(())),
+      ("biguint", Value::Negative(_)) | ("bignint", Value::Unsigned(_)) => {
+        Some(Err(self.mismatch(ident)))
+      }
+      ("time", Value::Tag(1, seconds)) => match **seconds {
+        Value::Float(f, _) if !f.is_finite() => Some(Err(self.mismatch(ident))),
+        _ => None,
+      },
+      // The formats of text strings tagged as these types are checked by the
+      // standard validators, those other than dates only with the "formats"
+      // feature
+      ("tdate", Value::Tag(0, text)) => self.unsupported_format(ident, text),
+      #[cfg(feature = "formats")]
+      ("uri", Value::Tag(32, text))
+      | ("b64url", Value::Tag(33, text))
+      | ("b64legacy", Value::Tag(34, text))
+      | ("regexp", Value::Tag(35, text))
+      | ("mime-message", Value::Tag(36, text)) => self.unsupported_format(ident, text),
+      (name, _) if is_extension_type(name) && self.cddl.rules_named(name).next().is_none() => {
+        Some(Err(self.unsupported(format!("extension type {}", ident))))
+      }
+      _ => None,
+    }
+  }
+
+  fn unsupported_format(&mut self, ident: &Identifier, text: &Value) -> Option<Result<(), Error>> {
+    match text {
+      Value::Text(_) => Some(Err(self.unsupported(format!("format of {}", ident)))),
+      _ => None,
+    }
+  }
+
+  fn validate_rules(
+    &mut self,
+    rules: &[&'c Rule<'c>],
+    generic_arg: Option<&'c GenericArg<'c>>,
+    value: &Value,
+    env: Env<'_, 'c>,
+  ) -> Result<(), Error> {
+    let mut errors = Vec::new();
+
+    // Rules extended via /= are satisfied by any of their definitions
+    for rule in rules.iter() {
+      let result = match rule {
+        Rule::Type { rule, .. } => {
+          let scope = scope(&rule.generic_param, generic_arg, env);
+
+          self.validate_type(&rule.value, value, scope.as_ref())
+        }
+        Rule::Group { rule, .. } => Err(Error::CDDL(format!(
+          "group rule {} can't be used as a type",
+          rule.name
+        ))),
+      };
+
+      match result {
+        Ok(()) => return Ok(()),
+        Err(e) => errors.push(e),
+      }
+    }
+
+    if errors.len() == 1 {
+      return Err(errors.remove(0));
+    }
+
+    Err(self.choice_error(errors, &rules[0].name()))
+  }
+
+  fn validate_array(
+    &mut self,
+    group: &'c Group<'c>,
+    values: &[Value],
+    env: Env<'_, 'c>,
+  ) -> Result<(), Error> {
+    let mut failure = None;
+    let ends = self.group_ends(group, values, 0, env, &mut failure);
+
+    if ends.contains(&values.len()) {
+      return Ok(());
+    }
+
+    // Report the failure found furthest into the array, unless the array has
+    // elements beyond anything the group could match
+    let furthest = ends.iter().cloned().max();
+    match failure {
+      Some((idx, e)) if furthest.map(|end| idx >= end).unwrap_or(true) => Err(e),
+      _ => Err(Error::UnexpectedEntry {
+        path: format!("{}/{}", self.current_path(), furthest.unwrap_or(0)),
+      }),
+    }
+  }
+
+  // Returns every position in the array at which matching the group starting
+  // at the given position can end, recording the furthest failure to match an
+  // element along the way
+  fn group_ends(
+    &mut self,
+    group: &'c Group<'c>,
+    values: &[Value],
+    start: usize,
+    env: Env<'_, 'c>,
+    failure: &mut Option<(usize, Error)>,
+  ) -> Vec<usize> {
+    let mut ends = Vec::new();
+
+    for gc in group.group_choices.iter() {
+      let mut positions = vec![start];
+
+      for (entry, _) in gc.group_entries.iter() {
+        let mut next = Vec::new();
+
+        for pos in positions {
+          for end in self.entry_ends(entry, values, pos, env, failure) {
+            if !next.contains(&end) {
+              next.push(end);
+            }
+          }
+        }
+
+        positions = next;
+      }
+
+      for end in positions {
+        if !ends.contains(&end) {
+          ends.push(end);
+        }
+      }
+    }
+
+    ends
+  }
+
+  fn entry_ends(
+    &mut self,
+    entry: &'c GroupEntry<'c>,
+    values: &[Value],
+    start: usize,
+    env: Env<'_, 'c>,
+    failure: &mut Option<(usize, Error)>,
+  ) -> Vec<usize> {
+    match entry {
+      GroupEntry::ValueMemberKey { ge, .. } => {
+        if let Some(MemberKey::NonMemberKey(NonMemberKey::Group(group))) = &ge.member_key {
+          return self.repeat(ge.occur.as_ref(), start, &mut |v, pos| {
+            v.group_ends(group, values, pos, env, failure)
+          });
+        }
+
+        if ge.member_key.is_none() {
+          if let Some((group, generic_param, generic_arg)) = self.inline_group(&ge.entry_type) {
+            let scope = scope(generic_param, generic_arg, env);
+
+            return self.repeat(ge.occur.as_ref(), start, &mut |v, pos| {
+              v.group_ends(group, values, pos, scope.as_ref(), failure)
+            });
+          }
+        }
+
+        self.repeat(ge.occur.as_ref(), start, &mut |v, pos| {
+          v.element_ends(entry, &ge.entry_type, values, pos, env, failure)
+        })
+      }
+      GroupEntry::TypeGroupname { ge, .. } => match self.group_rule(&ge.name, env) {
+        Some((group_entry, generic_param)) => {
+          let scope = scope(generic_param, ge.generic_arg.as_ref(), env);
+
+          self.repeat(ge.occur.as_ref(), start, &mut |v, pos| {
+            v.entry_ends(group_entry, values, pos, scope.as_ref(), failure)
+          })
+        }
+        None => self.repeat(
+          ge.occur.as_ref(),
+          start,
+          &mut |v, pos| match values.get(pos) {
+            Some(value) => {
+              match v.at(pos.to_string(), |v| {
+                v.validate_typename(&ge.name, ge.generic_arg.as_ref(), value, env)
+              }) {
+                Ok(()) => vec![pos + 1],
+                Err(e) => {
+                  record_failure(failure, pos, e);
+                  Vec::new()
+                }
+              }
+            }
+            None => {
+              let missing = v.missing(entry);
+              record_failure(failure, pos, missing);
+              Vec::new()
+            }
+          },
+        ),
+      },
+      GroupEntry::InlineGroup { occur, group, .. } => {
+        self.repeat(occur.as_ref(), start, &mut |v, pos| {
+          v.group_ends(group, values, pos, env, failure)
+        })
+      }
+    }
+  }
+
+  fn element_ends(
+    &mut self,
+    entry: &'c GroupEntry<'c>,
+    t: &'c Type<'c>,
+    values: &[Value],
+    pos: usize,
+    env: Env<'_, 'c>,
+    failure: &mut Option<(usize, Error)>,
+  ) -> Vec<usize> {
+    match values.get(pos) {
+      Some(value) => match self.at(pos.to_string(), |v| v.validate_type(t, value, env)) {
+        Ok(()) => vec![pos + 1],
+        Err(e) => {
+          record_failure(failure, pos, e);
+          Vec::new()
+        }
+      },
+      None => {
+        let missing = self.missing(entry);
+        record_failure(failure, pos, missing);
+        Vec::new()
+      }
+    }
+  }
+
+  fn missing(&self, entry: &GroupEntry) -> Error {
+    Error::MissingEntry {
+      path: self.current_path(),
+      entry: entry.to_string(),
+    }
+  }
+
+  // Returns every position at which the given number of repetitions of a
+  // match starting at the given position can end
+  fn repeat(
+    &mut self,
+    occur: Option<&Occur>,
+    start: usize,
+    f: &mut dyn FnMut(&mut Self, usize) -> Vec<usize>,
+  ) -> Vec<usize> {
+    let (min, max) = occurrence_bounds(occur);
+
+    let mut ends = if min == 0 { vec![start] } else { Vec::new() };
+    let mut frontier = vec![start];
+    let mut count = 0;
+
+    while count < max && !frontier.is_empty() {
+      let mut next = Vec::new();
+      for pos in frontier {
+        for end in f(self, pos) {
+          if !next.contains(&end) {
+            next.push(end);
+          }
+        }
+      }
+      count += 1;
+
+      if count >= min {
+        // Positions reached before can't lead anywhere new, which also stops
+        // repetitions of matches that don't consume any elements
+        next.retain(|end| !ends.contains(end));
+        ends.extend(next.iter().cloned());
+      }
+
+      frontier = next;
+    }
+
+    ends
+  }
+
+  fn validate_map(
+    &mut self,
+    group: &'c Group<'c>,
+    entries: &[(Value, Value)],
+    env: Env<'_, 'c>,
+  ) -> Result<(), Error> {
+    let mut matched = vec![false; entries.len()];
+    self.match_map_group(group, entries, &mut matched, env)?;
+
+    match matched.iter().position(|m| !m) {
+      Some(idx) => Err(Error::UnexpectedEntry {
+        path: format!(
+          "{}/{}",
+          self.current_path(),
+          key_token(&entries[idx].0)
+            .replace('~', "~0")
+            .replace('/', "~1")
+        ),
+      }),
+      None => Ok(()),
+    }
+  }
+
+  // Matches the entries of a map against a group, marking the entries that
+  // were matched
+  fn match_map_group(
+    &mut self,
+    group: &'c Group<'c>,
+    entries: &[(Value, Value)],
+    matched: &mut Vec<bool>,
+    env: Env<'_, 'c>,
+  ) -> Result<(), Error> {
+    let mut best: Option<(usize, Error)> = None;
+
+    for gc in group.group_choices.iter() {
+      let mut choice_matched = matched.clone();
+
+      let result = gc
+        .group_entries
+        .iter()
+        .try_for_each(|(entry, _)| self.match_map_entry(entry, entries, &mut choice_matched, env));
+
+      match result {
+        Ok(()) => {
+          *matched = choice_matched;
+          return Ok(());
+        }
+        Err(e) => {
+          // Report the failure of the choice that matched the most entries
+          let progress = choice_matched.iter().filter(|m| **m).count();
+          match best {
+            Some((p, _)) if progress <= p => (),
+            _ => best = Some((progress, e)),
+          }
+        }
+      }
+    }
+
+    match best {
+      Some((_, e)) => Err(e),
+      None => Ok(()),
+    }
+  }
+
+  fn match_map_entry(
+    &mut self,
+    entry: &'c GroupEntry<'c>,
+    entries: &[(Value, Value)],
+    matched: &mut Vec<bool>,
+    env: Env<'_, 'c>,
+  ) -> Result<(), Error> {
+    match entry {
+      GroupEntry::ValueMemberKey { ge, .. } => match &ge.member_key {
+        Some(MemberKey::NonMemberKey(NonMemberKey::Group(group))) => {
+          self.repeat_map_group(entry, ge.occur.as_ref(), matched, &mut |v, m| {
+            v.match_map_group(group, entries, m, env)
+          })
+        }
+        Some(MemberKey::NonMemberKey(NonMemberKey::Type(_))) | None => {
+          match self.inline_group(&ge.entry_type) {
+            Some((group, generic_param, generic_arg)) => {
+              let scope = scope(generic_param, generic_arg, env);
+
+              self.repeat_map_group(entry, ge.occur.as_ref(), matched, &mut |v, m| {
+                v.match_map_group(group, entries, m, scope.as_ref())
+              })
+            }
+            None => match ge.entry_type.type_choices.as_slice() {
+              [Type1 {
+                type2: t2 @ Type2::Unwrap { .. },
+                ..
+              }] => Err(self.unsupported(format!("unwrap operator in {}", t2))),
+              _ => Err(Error::CDDL(format!(
+                "map entry {} must have a member key",
+                entry
+              ))),
+            },
+          }
+        }
+        Some(key) => self.match_map_member(
+          entry,
+          key,
+          &ge.entry_type,
+          ge.occur.as_ref(),
+          entries,
+          matched,
+          env,
+        ),
+      },
+      GroupEntry::TypeGroupname { ge, .. } => match self.group_rule(&ge.name, env) {
+        Some((group_entry, generic_param)) => {
+          let scope = scope(generic_param, ge.generic_arg.as_ref(), env);
+
+          self.repeat_map_group(entry, ge.occur.as_ref(), matched, &mut |v, m| {
+            v.match_map_entry(group_entry, entries, m, scope.as_ref())
+          })
+        }
+        None => Err(Error::CDDL(format!(
+          "map entry {} must have a member key",
+          entry
+        ))),
+      },
+      GroupEntry::InlineGroup { occur, group, .. } => {
+        self.repeat_map_group(entry, occur.as_ref(), matched, &mut |v, m| {
+          v.match_map_group(group, entries, m, env)
+        })
+      }
+    }
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  fn match_map_member(
+    &mut self,
+    entry: &'c GroupEntry<'c>,
+    key: &'c MemberKey<'c>,
+    entry_type: &'c Type<'c>,
+    occur: Option<&Occur>,
+    entries: &[(Value, Value)],
+    matched: &mut [bool],
+    env: Env<'_, 'c>,
+  ) -> Result<(), Error> {
+    let (min, max) = occurrence_bounds(occur);
+    // Values of entries whose keys are matched by a bareword, value or cut
+    // member key must match the entry's type
+    let is_cut = match key {
+      MemberKey::Type1 { is_cut, .. } => *is_cut,
+      _ => true,
+    };
+
+    let mut count = 0;
+    let mut value_error = None;
+
+    for (idx, (k, v)) in entries.iter().enumerate() {
+      if matched[idx] || count == max || !self.key_matches(key, k, env) {
+        continue;
+      }
+
+      match self.at(key_token(k), |s| s.validate_type(entry_type, v, env)) {
+        Ok(()) => {
+          matched[idx] = true;
+          count += 1;
+        }
+        Err(e) if is_cut => return Err(e),
+        Err(e) => {
+          if value_error.is_none() {
+            value_error = Some(e);
+          }
+        }
+      }
+    }
+
+    if count < min {
+      return Err(value_error.unwrap_or_else(|| self.missing(entry)));
+    }
+
+    Ok(())
+  }
+
+  // Matches a group within a map as many times as its occurrence indicator
+  // allows. Each repetition matches all of the group's entries or none of
+  // them.
+  #[allow(clippy::type_complexity)]
+  fn repeat_map_group(
+    &mut self,
+    entry: &'c GroupEntry<'c>,
+    occur: Option<&Occur>,
+    matched: &mut Vec<bool>,
+    f: &mut dyn FnMut(&mut Self, &mut Vec<bool>) -> Result<(), Error>,
+  ) -> Result<(), Error> {
+    let (min, max) = occurrence_bounds(occur);
+    let mut count = 0;
+    let mut error = None;
+
+    while count < max {
+      let mut repetition = matched.clone();
+
+      match f(self, &mut repetition) {
+        Ok(()) => {
+          let progressed = repetition != *matched;
+          *matched = repetition;
+          count += 1;
+
+          // Further repetitions would match nothing more
+          if !progressed {
+            count = count.max(min);
+            break;
+          }
+        }
+        Err(e) => {
+          error = Some(e);
+          break;
+        }
+      }
+    }
+
+    if count < min {
+      return Err(error.unwrap_or_else(|| self.missing(entry)));
+    }
+
+    Ok(())
+  }
+
+  fn key_matches(&mut self, key: &'c MemberKey<'c>, k: &Value, env: Env<'_, 'c>) -> bool {
+    match key {
+      MemberKey::Bareword { ident, .. } => match k {
+        Value::Text(t) => t == ident.ident,
+        _ => false,
+      },
+      MemberKey::Value { value, .. } => match (value, k) {
+        (token::Value::TEXT(t), Value::Text(k)) => t == k,
+        (token::Value::INT(i), k) => k.as_integer() == Some(*i),
+        (token::Value::UINT(u), k) => is_uint(k, *u),
+        #[allow(clippy::float_cmp)]
+        (token::Value::FLOAT(f), Value::Float(k, _)) => f == k,
+        (token::Value::BYTE(b), Value::Bytes(k)) => match b {
+          token::ByteValue::UTF8(b) => b[..] == k[..],
+          token::ByteValue::B16(b) => decode_b16(b).as_ref() == Some(k),
+          token::ByteValue::B64(b) => decode_b64(b).as_ref() == Some(k),
+        },
+        _ => false,
+      },
+      MemberKey::Type1 { t1, .. } => self.validate_type1(t1, k, env).is_ok(),
+      MemberKey::NonMemberKey(_) => false,
+    }
+  }
+
+  // Returns the entry of the group rule with the given name, unless the name
+  // refers to a generic parameter or a type
+  fn group_rule(
+    &self,
+    ident: &Identifier,
+    env: Env<'_, 'c>,
+  ) -> Option<(&'c GroupEntry<'c>, &'c Option<GenericParm<'c>>)> {
+    if let Some(scope) = env {
+      if scope.params.iter().any(|p| p.ident == ident.ident) {
+        return None;
+      }
+    }
+
+    match self.cddl.rule(ident.ident) {
+      Some(Rule::Group { rule, .. }) => Some((&rule.entry, &rule.generic_param)),
+      _ => None,
+    }
+  }
+
+  // Returns the group a keyless entry stands for, if the entry is a reference
+  // to a group rule
+  #[allow(clippy::type_complexity)]
+  fn inline_group(
+    &self,
+    t: &'c Type<'c>,
+  ) -> Option<(
+    &'c Group<'c>,
+    &'c Option<GenericParm<'c>>,
+    Option<&'c GenericArg<'c>>,
+  )> {
+    let (ident, generic_arg) = match t.type_choices.as_slice() {
+      [Type1 {
+        type2: Type2::Typename {
+          ident, generic_arg, ..
+        },
+        operator: None,
+        ..
+      }] => (ident, generic_arg),
+      _ => return None,
+    };
+
+    match self.cddl.rule(ident.ident)? {
+      Rule::Group { rule, .. } => match &rule.entry {
+        GroupEntry::InlineGroup {
+          occur: None, group, ..
+        } => Some((group, &rule.generic_param, generic_arg.as_ref())),
+        _ => None,
+      },
+      _ => None,
+    }
+  }
+
+  // Returns the numeric values a range bound given by name stands for, or
+  // None if it doesn't stand for constant numbers
+  fn range_bounds(&self, bound: &'c Type2<'c>, env: Env<'_, 'c>) -> Option<Vec<&'c Type2<'c>>> {
+    if let Type2::Typename { ident, .. } = bound {
+      if let Some((arg, parent)) = bound_arg(ident, env) {
+        return match arg {
+          Some(Type1 {
+            type2,
+            operator: None,
+            ..
+          }) => self.range_bounds(type2, parent),
+          _ => None,
+        };
+      }
+    }
+
+    self.cddl.range_bound_values(bound)
+  }
+
+  // Returns whether the target of a control operator is one of the given types
+  // of the standard prelude, or a rule with a choice that is, as the standard
+  // validators require of the targets of control operators they check
+  fn is_target_of(
+    &self,
+    target: &'c Type2<'c>,
+    env: Env<'_, 'c>,
+    is_type: fn(&str) -> bool,
+    depth: usize,
+  ) -> bool {
+    // Rules defined in terms of each other never resolve to a type
+    if depth > MAX_DEPTH {
+      return false;
+    }
+
+    let ident = match target {
+      Type2::Typename { ident, .. } => ident,
+      _ => return false,
+    };
+
+    if is_type(ident.ident) {
+      return true;
+    }
+
+    if let Some((arg, parent)) = bound_arg(ident, env) {
+      return arg.map_or(false, |arg| {
+        self.is_target_of(&arg.type2, parent, is_type, depth + 1)
+      });
+    }
+
+    self.cddl.rules_for(ident).any(|rule| match rule {
+      Rule::Type { rule, .. } => rule
+        .value
+        .type_choices
+        .iter()
+        .any(|tc| self.is_target_of(&tc.type2, None, is_type, depth + 1)),
+      _ => false,
+    })
+  }
+
+  // Returns the numeric values of the controller of a comparison, or None if
+  // it isn't a numeric literal or a rule defined as a choice of them
+  fn numeric_values(&self, controller: &'c Type2<'c>, env: Env<'_, 'c>) -> Option<Vec<Number>> {
+    let mut literals = Vec::new();
+    if !self.controller_literals(controller, env, 0, &mut literals) {
+      return None;
+    }
+
+    literals.into_iter().map(numeric_literal).collect()
+  }
+
+  // Returns the text values of a controller, or None if it isn't a text
+  // literal or a rule defined as a choice of them
+  fn text_values(&self, controller: &'c Type2<'c>, env: Env<'_, 'c>) -> Option<Vec<&'c str>> {
+    let mut literals = Vec::new();
+    if !self.controller_literals(controller, env, 0, &mut literals) {
+      return None;
+    }
+
+    literals
+      .into_iter()
+      .map(|literal| match literal {
+        Type2::TextValue { value, .. } => Some(value.as_ref()),
+        _ => None,
+      })
+      .collect()
+  }
+
+  // Collects the literals a controller stands for, following the rules it
+  // names, e.g. 5 and 7 for `b` in `uint .lt b` with `b = 5 / 7`. Returns
+  // false if it stands for anything but literals.
+  fn controller_literals(
+    &self,
+    controller: &'c Type2<'c>,
+    env: Env<'_, 'c>,
+    depth: usize,
+    literals: &mut Vec<&'c Type2<'c>>,
+  ) -> bool {
+    if depth > MAX_DEPTH {
+      return false;
+    }
+
+    let ident = match controller {
+      Type2::IntValue { .. }
+      | Type2::UintValue { .. }
+      | Type2::FloatValue { .. }
+      | Type2::TextValue { .. } => {
+        literals.push(controller);
+        return true;
+      }
+      Type2::Typename {
+        ident,
+        generic_arg: None,
+        ..
+      } => ident,
+      _ => return false,
+    };
+
+    if let Some((arg, parent)) = bound_arg(ident, env) {
+      return match arg {
+        Some(Type1 {
+          type2,
+          operator: None,
+          ..
+        }) => self.controller_literals(type2, parent, depth + 1, literals),
+        _ => false,
+      };
+    }
+
+    self.cddl.rules_for(ident).all(|rule| match rule {
+      Rule::Type { rule, .. } if rule.generic_param.is_none() => {
+        rule.value.type_choices.iter().all(|tc| {
+          tc.operator.is_none() && self.controller_literals(&tc.type2, None, depth + 1, literals)
+        })
+      }
+      _ => false,
+    })
+  }
+}
+
+// Returns the generic argument bound to the generic parameter with the given
+// name, along with the scope the argument is resolved in
+#[allow(clippy::type_complexity)]
+fn bound_arg<'s, 'c>(
+  ident: &Identifier,
+  env: Env<'s, 'c>,
+) -> Option<(Option<&'c Type1<'c>>, Env<'s, 'c>)> {
+  let scope = env?;
+  let idx = scope.params.iter().position(|p| p.ident == ident.ident)?;
+
+  Some((scope.args.get(idx), scope.parent))
+}
+
+// Returns the value of a numeric literal
+fn numeric_literal(t2: &Type2) -> Option<Number> {
+  match t2 {
+    Type2::IntValue { value, .. } => Some(Number::Int(*value)),
+    // No decoded integer reaches uint literals beyond the range of i128, so
+    // these can saturate
+    Type2::UintValue { value, .. } => Some(Number::Int(
+      i128::try_from(*value).unwrap_or((!0u128 >> 1) as i128),
+    )),
+    Type2::FloatValue { value, .. } => Some(Number::Float(*value)),
+    _ => None,
+  }
+}
+
+fn is_text_type(ident: &str) -> bool {
+  ident == "tstr" || ident == "text"
+}
+
+fn is_byte_string_type(ident: &str) -> bool {
+  ident == "bstr" || ident == "bytes"
+}
+
+// Binds the generic arguments of a rule reference to the generic parameters
+// of the rule
+fn scope<'s, 'c>(
+  generic_param: &'c Option<GenericParm<'c>>,
+  generic_arg: Option<&'c GenericArg<'c>>,
+  env: Env<'s, 'c>,
+) -> Option<Scope<'s, 'c>> {
+  generic_param.as_ref().map(|gp| Scope {
+    params: &gp.params,
+    args: generic_arg.map_or(&[], |ga| &ga.args),
+    parent: env,
+  })
+}
+
+fn record_failure(failure: &mut Option<(usize, Error)>, pos: usize, e: Error) {
+  match failure {
+    Some((idx, _)) if pos < *idx => (),
+    _ => *failure = Some((pos, e)),
+  }
+}
+
+fn occurrence_bounds(occur: Option<&Occur>) -> (usize, usize) {
+  match occur {
+    None => (1, 1),
+    Some(Occur::Optional(_)) => (0, 1),
+    Some(Occur::ZeroOrMore(_)) => (0, UNBOUNDED),
+    Some(Occur::OneOrMore(_)) => (1, UNBOUNDED),
+    Some(Occur::Exact { lower, upper, .. }) => (lower.unwrap_or(0), upper.unwrap_or(UNBOUNDED)),
+  }
+}
+
+// Returns the JSON Pointer reference token for the value of a map entry
+// Joins reference tokens into a JSON Pointer (RFC 6901)
+fn pointer(tokens: &[String]) -> String {
+  tokens.iter().fold(String::new(), |mut path, token| {
+    path.push('/');
+    path.push_str(&token.replace('~', "~0").replace('/', "~1"));
+    path
+  })
+}
+
+fn key_token(key: &Value) -> String {
+  match key {
+    Value::Text(t) => t.clone(),
+    k => k.to_string(),
+  }
+}
+
+fn is_major_type(mt: u8, constraint: Option<u64>, value: &Value) -> bool {
+  let argument = match (mt, value) {
+    (0, Value::Unsigned(n)) | (1, Value::Negative(n)) => *n,
+    (2, Value::Bytes(b)) => b.len() as u64,
+    (3, Value::Text(t)) => t.len() as u64,
+    (4, Value::Array(a)) => a.len() as u64,
+    (5, Value::Map(m)) => m.len() as u64,
+    (6, Value::Tag(tag, _)) => *tag,
+    (7, Value::Simple(s)) => u64::from(*s),
+    // As with the standard validators, which don't retain the encoded width of
+    // a float, a float satisfies a width constraint if it can be represented
+    // in that width without loss of precision
+    (7, Value::Float(f, _)) => {
+      return match constraint {
+        None | Some(27) => true,
+        Some(26) => is_f32_representable(*f),
+        Some(25) => is_f16_representable(*f),
+        _ => false,
+      }
+    }
+    _ => return false,
+  };
+
+  constraint.unwrap_or(argument) == argument
+}
+
+// Checks whether or not a float can be represented in single precision without
+// loss of precision
+pub(crate) fn is_f32_representable(f: f64) -> bool {
+  f.is_nan() || f as f32 as f64 == f
+}
+
+// Checks whether or not a float can be represented in half precision without
+// loss of precision
+pub(crate) fn is_f16_representable(f: f64) -> bool {
+  if f.is_nan() || f.is_infinite() || f == 0.0 {
+    return true;
+  }
+
+  if !is_f32_representable(f) {
+    return false;
+  }
+
+  let bits = (f as f32).to_bits();
+  let exponent = ((bits >> 23) & 0xff) as i32 - 127;
+  let significand = (bits & 0x7f_ffff) | 0x80_0000;
+
+  match exponent {
+    // Normal half-precision floats retain the top 10 bits of the fraction
+    -14..=15 => significand.trailing_zeros() >= 13,
+    // Subnormal half-precision floats are multiples of 2^-24
+    -24..=-15 => significand.trailing_zeros() as i32 >= -exponent - 1,
+    _ => false,
+  }
+}
+
+pub(crate) fn decode_b16(encoded: &[u8]) -> Option<Vec<u8>> {
+  let mut decoded = vec![0; encoded.len() / 2];
+
+  base16::decode_slice(encoded, &mut decoded).ok().map(|len| {
+    decoded.truncate(len);
+    decoded
+  })
+}
+
+pub(crate) fn decode_b64(encoded: &[u8]) -> Option<Vec<u8>> {
+  let mut decoded = vec![0; encoded.len() / 4 * 3 + 3];
+
+  base64::decode_config_slice(encoded, base64::URL_SAFE, &mut decoded)
+    .ok()
+    .map(|len| {
+      decoded.truncate(len);
+      decoded
+    })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{lexer_from_str, parser::cddl_from_str};
+
+  fn validate_hex(cddl_input: &str, rule_name: &str, cbor_hex: &str) -> Result<(), Error> {
+    #[cfg(feature = "std")]
+    let cddl = cddl_from_str(&mut lexer_from_str(cddl_input), cddl_input, false).unwrap();
+    #[cfg(not(feature = "std"))]
+    let cddl = cddl_from_str(&mut lexer_from_str(cddl_input), cddl_input).unwrap();
+    let mut cbor = vec![0; cbor_hex.len() / 2];
+    base16::decode_slice(cbor_hex, &mut cbor).unwrap();
+
+    validate_for_rule(&cddl, rule_name, &decode(&cbor)?)
+  }
+
+  #[test]
+  fn validate_maps() {
+    let cddl = r#"
+      reputon = {
+        rater: tstr,
+        ? confidence: float16,
+        * tstr => any,
+      }
+    "#;
+
+    // {"rater": "Ninja", "rating": 7}
+    assert_eq!(
+      validate_hex(
+        cddl,
+        "reputon",
+        "a2657261746572654e696e6a6166726174696e6707"
+      ),
+      Ok(())
+    );
+    // {"rater": 1}
+    assert_eq!(
+      validate_hex(
+        cddl,
+        "reputon",
+        "a1657261746572 01".replace(' ', "").as_str()
+      ),
+      Err(Error::Mismatch {
+        path: "/rater".to_string(),
+        expected: "tstr".to_string(),
+      })
+    );
+    // {}
+    assert!(match validate_hex(cddl, "reputon", "a0") {
+      Err(Error::MissingEntry { path, .. }) => path.is_empty(),
+      _ => false,
+    });
+    // {"rater": "a", 1: 2}
+    assert_eq!(
+      validate_hex(cddl, "reputon", "a265726174657261610102"),
+      Err(Error::UnexpectedEntry {
+        path: "/1".to_string()
+      })
+    );
+  }
+
+  #[test]
+  fn validate_arrays() {
+    let cddl = r#"
+      values = [+ int, ? tstr]
+      pair = [first: uint, second: (uint / null)]
+    "#;
+
+    assert_eq!(validate_hex(cddl, "values", "8301026161"), Ok(()));
+    assert_eq!(validate_hex(cddl, "values", "820102"), Ok(()));
+    assert!(match validate_hex(cddl, "values", "80") {
+      Err(Error::MissingEntry { path, .. }) => path.is_empty(),
+      _ => false,
+    });
+    assert_eq!(
+      validate_hex(cddl, "values", "8301616102"),
+      Err(Error::UnexpectedEntry {
+        path: "/2".to_string()
+      })
+    );
+    assert_eq!(validate_hex(cddl, "pair", "8201f6"), Ok(()));
+    assert_eq!(
+      validate_hex(cddl, "pair", "8201f5"),
+      Err(Error::Mismatch {
+        path: "/1".to_string(),
+        expected: "uint / null".to_string(),
+      })
+    );
+  }
+
+  #[test]
+  fn validate_generics() {
+    let cddl = r#"
+      reputation = message<"reputation", uint>
+      message<t, v> = { type: t, value: v }
+    "#;
+
+    // {"type": "reputation", "value": 5}
+    assert_eq!(
+      validate_hex(
+        cddl,
+        "reputation",
+        "a264747970656a72657075746174696f6e6576616c756505"
+      ),
+      Ok(())
+    );
+    // {"type": "reputation", "value": -1}
+    assert_eq!(
+      validate_hex(
+        cddl,
+        "reputation",
+        "a264747970656a72657075746174696f6e6576616c756520"
+      ),
+      Err(Error::Mismatch {
+        path: "/value".to_string(),
+        expected: "uint".to_string(),
+      })
+    );
+  }
+
+  #[test]
+  fn validate_tags_and_major_types() {
+    let cddl = r#"
+      uri-ref = #6.32(tstr)
+      any-tag = #6(uint)
+      half = #7.25
+    "#;
+
+    assert_eq!(validate_hex(cddl, "uri-ref", "d8206161"), Ok(()));
+    assert!(validate_hex(cddl, "uri-ref", "d8216161").is_err());
+    assert_eq!(validate_hex(cddl, "any-tag", "d9010001"), Ok(()));
+    assert_eq!(validate_hex(cddl, "half", "f93c00"), Ok(()));
+    // Floats satisfy the widths they can be represented in without loss
+    assert_eq!(validate_hex(cddl, "half", "fa3f800000"), Ok(()));
+    assert!(validate_hex(cddl, "half", "fa3f800001").is_err());
+    // Prelude types are defined in terms of tags and major types
+    assert_eq!(validate_hex("t = encoded-cbor", "t", "d81841f6"), Ok(()));
+    assert_eq!(validate_hex("t = biguint", "t", "c24101"), Ok(()));
+  }
+
+  #[test]
+  fn validate_any_and_undefined() {
+    // [null, undefined, h'01', 1(2), true]
+    assert_eq!(validate_hex("t = [* any]", "t", "85f6f74101c102f5"), Ok(()));
+    assert_eq!(validate_hex("t = [* #]", "t", "85f6f74101c102f5"), Ok(()));
+    assert_eq!(
+      validate_hex("t = { * any => any }", "t", "a201f64101f7"),
+      Ok(())
+    );
+    assert_eq!(validate_hex("t = undefined", "t", "f7"), Ok(()));
+    assert!(validate_hex("t = undefined", "t", "f6").is_err());
+    assert!(validate_hex("t = null", "t", "f7").is_err());
+  }
+
+  #[test]
+  fn validate_controls() {
+    let cddl = r#"
+      short = tstr .size 2
+      small = uint .size 1
+      port = uint .lt 65536
+      flags = uint .bits (0 / 2)
+      digest = bstr .size (1..4)
+      embedded = bstr .cbor [uint, uint]
+      pattern = tstr .regexp "a+"
+      percent = 0..100
+    "#;
+
+    assert_eq!(validate_hex(cddl, "short", "626162"), Ok(()));
+    assert!(validate_hex(cddl, "short", "6161").is_err());
+    assert_eq!(validate_hex(cddl, "short", "7f61616162ff"), Ok(()));
+    assert!(validate_hex(cddl, "short", "7f616161626163ff").is_err());
+    assert_eq!(validate_hex(cddl, "small", "18ff"), Ok(()));
+    assert!(validate_hex(cddl, "small", "190100").is_err());
+    assert_eq!(validate_hex(cddl, "port", "19ffff"), Ok(()));
+    assert!(validate_hex(cddl, "port", "1a00010000").is_err());
+    assert_eq!(validate_hex(cddl, "flags", "05"), Ok(()));
+    assert!(validate_hex(cddl, "flags", "02").is_err());
+    assert_eq!(validate_hex(cddl, "digest", "43010203"), Ok(()));
+    assert!(validate_hex(cddl, "digest", "40").is_err());
+    assert_eq!(validate_hex(cddl, "digest", "5f410142020340ff"), Ok(()));
+    assert!(validate_hex(cddl, "digest", "5f42010242020341ffff").is_err());
+    assert_eq!(validate_hex(cddl, "embedded", "43820102"), Ok(()));
+    assert!(validate_hex(cddl, "embedded", "4201ff").is_err());
+    assert_eq!(validate_hex(cddl, "percent", "1864"), Ok(()));
+    assert!(validate_hex(cddl, "percent", "1865").is_err());
+    assert_eq!(
+      validate_hex(cddl, "pattern", "6161"),
+      Err(Error::Unsupported(".regexp control operator".to_string()))
+    );
+  }
+
+  #[test]
+  fn report_unsupported_constructs() {
+    // Controls applied to targets or controllers the standard validators don't
+    // check them against
+    assert_eq!(
+      validate_hex("t = bstr .eq h'01'", "t", "4101"),
+      Err(Error::Unsupported("bstr .eq h'01'".to_string()))
+    );
+    assert_eq!(
+      validate_hex("t = uint .lt (5)", "t", "01"),
+      Err(Error::Unsupported("uint .lt (5)".to_string()))
+    );
+    assert_eq!(
+      validate_hex("t = [~pair, tstr] pair = [int, int]", "t", "8301026161"),
+      Err(Error::Unsupported("unwrap operator in ~pair".to_string()))
+    );
+    assert_eq!(
+      validate_hex("t = tdate", "t", "c06178"),
+      Err(Error::Unsupported("format of tdate".to_string()))
+    );
+    // Unsupported constructs are reported even if another choice matches
+    assert_eq!(
+      validate_hex("t = [* (tstr .regexp \"a\"), int]", "t", "8101"),
+      Err(Error::Unsupported(".regexp control operator".to_string()))
+    );
+  }
+
+  #[test]
+  fn validate_integer_literals() {
+    let cddl = r#"
+      max = 18446744073709551615
+      min = -18446744073709551616
+      huge = 340282366920938463463374607431768211455
+      wide = 0..340282366920938463463374607431768211455
+    "#;
+
+    assert_eq!(validate_hex(cddl, "max", "1bffffffffffffffff"), Ok(()));
+    assert!(validate_hex(cddl, "max", "1bfffffffffffffffe").is_err());
+    assert!(validate_hex(cddl, "max", "3bfffffffffffffffe").is_err());
+    assert_eq!(validate_hex(cddl, "min", "3bffffffffffffffff"), Ok(()));
+    assert!(validate_hex(cddl, "min", "1bffffffffffffffff").is_err());
+    assert!(validate_hex(cddl, "huge", "1bffffffffffffffff").is_err());
+    assert_eq!(validate_hex(cddl, "wide", "1bffffffffffffffff"), Ok(()));
+    assert!(validate_hex(cddl, "wide", "20").is_err());
+  }
+
+  #[test]
+  fn validate_choices_and_recursion() {
+    let cddl = r#"
+      tree = uint / [* tree]
+      colour = &(red: 0, green: 1, blue: 2)
+      missing = undefined-rule
+    "#;
+
+    assert_eq!(validate_hex(cddl, "tree", "8301808102"), Ok(()));
+    assert_eq!(
+      validate_hex(cddl, "tree", "830180816161"),
+      Err(Error::Mismatch {
+        path: "/2/0".to_string(),
+        expected: "tree".to_string(),
+      })
+    );
+    assert_eq!(validate_hex(cddl, "colour", "02"), Ok(()));
+    assert!(validate_hex(cddl, "colour", "03").is_err());
+    let nested = format!("{}00", "81".repeat(MAX_DEPTH));
+    assert!(match validate_hex(cddl, "tree", &nested) {
+      Err(Error::Recursion { max_depth, .. }) => max_depth == MAX_DEPTH,
+      _ => false,
+    });
+    assert_eq!(
+      validate_hex(cddl, "missing", "00"),
+      Err(Error::UndefinedRule("undefined-rule".to_string()))
+    );
+    assert!(
+      match validate_hex("root = { a: g }  g = (b: uint)", "root", "a1616101") {
+        Err(Error::CDDL(_)) => true,
+        _ => false,
+      }
+    );
+  }
+
+  // Both validators are expected to agree on whether data items are valid,
+  // unless the data meets a construct this validator reports as unsupported
+  #[test]
+  #[cfg(feature = "cbor-validation")]
+  fn agree_with_standard_validator() {
+    let agree = |cddl: &str, cbor: &[u8], data: &str| match validate_from_slice(cddl, cbor) {
+      Err(Error::Unsupported(_)) => (),
+      result => assert_eq!(
+        result.is_ok(),
+        crate::validate_cbor_from_slice(cddl, cbor).is_ok(),
+        "{} with {}",
+        cddl,
+        data
+      ),
+    };
+
+    let cddl = r#"
+      root = [* item]
+      item = uint / tdate / #6.32(tstr) / biguint / undefined / record / pair<int>
+      record = { ? name: tstr, * int => bool }
+      pair<t> = [t, bstr .size 2]
+    "#;
+
+    for hex in &[
+      "80",
+      "8101",
+      "81c074323031332d30332d32315432303a30343a30305a",
+      "8174323031332d30332d32315432303a30343a30305a",
+      "81c174323031332d30332d32315432303a30343a30305a",
+      "81d8206161",
+      "81d8216161",
+      "81c2420100",
+      "81c3420100",
+      "81f7",
+      "81f6",
+      "81a0",
+      "81a2646e616d65616101f5",
+      "81a1646e616d6501",
+      "81822042abcd",
+      "8182f94200420102",
+      "818220430102",
+      "8201f7",
+    ] {
+      let mut cbor = vec![0; hex.len() / 2];
+      base16::decode_slice(hex, &mut cbor).unwrap();
+
+      agree(cddl, &cbor, hex);
+    }
+
+    for (cddl, items) in &[
+      ("a = int .lt 10", &["5", "10", "5.5", "-1"][..]),
+      ("a = float .gt 1", &["1.5", "1.0", "2"]),
+      ("a = int .eq 1", &["1", "2", "1.0"]),
+      ("a = int .ne 1", &["1", "2", "1.0"]),
+      ("a = tstr .ne \"a\"", &["\"a\"", "\"b\"", "1"]),
+      ("a = c .ne \"a\"\nc = tstr / int", &["1", "\"a\"", "\"b\""]),
+      ("a = bstr .eq h'01'", &["h'01'", "h'02'"]),
+      ("a = uint .lt b\nb = 5 / 7", &["4", "6", "8"]),
+      ("a = uint .lt (5)", &["4"]),
+      ("a = c .lt 5\nc = uint / tstr", &["4", "6", "\"a\""]),
+      ("a = c<5>\nc<T> = uint .lt T", &["4", "6"]),
+      ("a = uint .within (0..10)", &["5", "20", "-1"]),
+      ("a = uint .size b\nb = 1", &["1"]),
+      ("a = \"ab\" .size 2", &["\"ab\""]),
+      (
+        "a = c .size 1\nc = int / tstr",
+        &["1", "\"a\"", "-1", "256"],
+      ),
+      ("a = int .bits (0 / 1)", &["-1", "3", "4"]),
+      ("a = tstr .bits (0 / 1)", &["\"a\""]),
+      ("a = tstr .regexp \"a+\"", &["\"aa\"", "\"b\""]),
+      ("a = 0..10", &["5", "5.5", "11"]),
+      ("a = 0..10.0", &["5", "5.5"]),
+      ("a = 0.0..10.0", &["5", "5.5"]),
+      ("a = b..10\nb = 0 / 5", &["3", "11"]),
+      ("a = c<1, 5>\nc<L, U> = L..U", &["1", "5", "6"]),
+      ("a = [~b, tstr]\nb = [int]", &["[1, \"a\"]", "[[1], \"a\"]"]),
+      (
+        "a = {~b, c: tstr}\nb = {d: int}",
+        &["{\"d\": 1, \"c\": \"a\"}"],
+      ),
+      ("a = float16", &["1.5", "1.1"]),
+      ("a = float32", &["1.1", "1.5"]),
+      ("a = bigint", &["1", "-1", "2(h'01')"]),
+      ("a = time", &["1(1)", "1(1.5)"]),
+      ("a = tdate", &["0(\"x\")"]),
+      ("a = duration", &["1002({1: 1})"]),
+      ("a = &b<1>\nb<T> = (x: T, y: 2)", &["1", "3"]),
+      ("a = [1, h'0102', b64'AQI']", &["[1, h'0102', h'0102']"]),
+    ] {
+      for data in items.iter() {
+        let cbor = serde_cbor::to_vec(&crate::edn::parse(data).unwrap()).unwrap();
+
+        agree(cddl, &cbor, data);
+      }
+    }
+  }
+
+  #[test]
+  fn validate_encoded() {
+    assert_eq!(validate_from_slice("a = [uint]", &[0x81, 0x01]), Ok(()));
+    assert_eq!(
+      validate_from_slice(
+        "a = { b: [* uint] }",
+        &[0xbf, 0x61, b'b', 0x9f, 0x01, 0x02, 0xff, 0xff]
+      ),
+      Ok(())
+    );
+    assert!(match validate_from_slice("a = [uint", &[0x81, 0x01]) {
+      Err(Error::CDDL(_)) => true,
+      _ => false,
+    });
+    assert!(match validate_from_slice("a = [uint]", &[0x82, 0x01]) {
+      Err(Error::Decode(_)) => true,
+      _ => false,
+    });
+  }
+}
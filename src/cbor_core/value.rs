@@ -0,0 +1,1015 @@
+use super::{key_token, pointer};
+use crate::error;
+use std::fmt;
+
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+  boxed::Box,
+  collections::BTreeMap,
+  string::{String, ToString},
+  vec,
+  vec::Vec,
+};
+
+// Nesting depth of arrays, maps and tags beyond which decoding fails rather
+// than risking exhausting the stack
+const MAX_NESTING: usize = 512;
+
+/// CBOR data item (RFC 8949)
+///
+/// Unlike `serde_cbor::Value`, the distinction between unsigned and negative
+/// integers, the encoded width of floats and simple values such as
+/// `undefined` are retained, since CDDL can tell them apart.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+  /// Unsigned integer (major type 0)
+  Unsigned(u64),
+  /// Negative integer (major type 1), holding `-1 - n` as `n`
+  Negative(u64),
+  /// Byte string (major type 2)
+  Bytes(Vec<u8>),
+  /// Text string (major type 3)
+  Text(String),
+  /// Array (major type 4)
+  Array(Vec<Value>),
+  /// Map (major type 5), with entries in the order they were encoded
+  Map(Vec<(Value, Value)>),
+  /// Tagged data item (major type 6)
+  Tag(u64, Box<Value>),
+  /// Simple value (major type 7), e.g. 20 for `false` and 23 for `undefined`
+  Simple(u8),
+  /// Floating-point number (major type 7) along with the width it was encoded
+  /// in
+  Float(f64, FloatWidth),
+}
+
+/// Encoded width of a floating-point number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatWidth {
+  /// IEEE 754 half-precision (16 bits)
+  Half,
+  /// IEEE 754 single-precision (32 bits)
+  Single,
+  /// IEEE 754 double-precision (64 bits)
+  Double,
+}
+
+impl Value {
+  /// Returns the value of the item if it's an integer
+  pub fn as_integer(&self) -> Option<i128> {
+    match self {
+      Value::Unsigned(n) => Some(i128::from(*n)),
+      Value::Negative(n) => Some(-1 - i128::from(*n)),
+      _ => None,
+    }
+  }
+
+  /// Returns the major type of the item
+  pub fn major_type(&self) -> u8 {
+    match self {
+      Value::Unsigned(_) => 0,
+      Value::Negative(_) => 1,
+      Value::Bytes(_) => 2,
+      Value::Text(_) => 3,
+      Value::Array(_) => 4,
+      Value::Map(_) => 5,
+      Value::Tag(..) => 6,
+      Value::Simple(_) | Value::Float(..) => 7,
+    }
+  }
+}
+
+impl fmt::Display for Value {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Value::Unsigned(n) => write!(f, "{}", n),
+      Value::Negative(n) => write!(f, "{}", -1 - i128::from(*n)),
+      Value::Bytes(b) => {
+        write!(f, "h'")?;
+        for byte in b.iter() {
+          write!(f, "{:02x}", byte)?;
+        }
+        write!(f, "'")
+      }
+      Value::Text(t) => write!(f, "{:?}", t),
+      Value::Array(a) => {
+        write!(f, "[")?;
+        for (idx, v) in a.iter().enumerate() {
+          if idx > 0 {
+            write!(f, ", ")?;
+          }
+          write!(f, "{}", v)?;
+        }
+        write!(f, "]")
+      }
+      Value::Map(m) => {
+        write!(f, "{{")?;
+        for (idx, (k, v)) in m.iter().enumerate() {
+          if idx > 0 {
+            write!(f, ", ")?;
+          }
+          write!(f, "{}: {}", k, v)?;
+        }
+        write!(f, "}}")
+      }
+      Value::Tag(tag, v) => write!(f, "{}({})", tag, v),
+      Value::Simple(20) => write!(f, "false"),
+      Value::Simple(21) => write!(f, "true"),
+      Value::Simple(22) => write!(f, "null"),
+      Value::Simple(23) => write!(f, "undefined"),
+      Value::Simple(s) => write!(f, "simple({})", s),
+      Value::Float(n, _) => {
+        if n.is_nan() {
+          write!(f, "NaN")
+        } else if n.is_infinite() {
+          write!(f, "{}Infinity", if *n < 0.0 { "-" } else { "" })
+        } else if (*n as i64) as f64 == *n {
+          write!(f, "{:.1}", n)
+        } else {
+          write!(f, "{}", n)
+        }
+      }
+    }
+  }
+}
+
+/// Error decoding CBOR
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeError {
+  /// Byte offset of the error in the input
+  pub position: usize,
+  /// Description of the error
+  pub message: String,
+}
+
+impl fmt::Display for DecodeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "CBOR decoding error at offset {}: {}",
+      self.position, self.message
+    )
+  }
+}
+
+impl error::Error for DecodeError {}
+
+/// Departure of encoded CBOR from the deterministic encoding requirements of
+/// RFC 8949 section 4.2.1
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodingViolation {
+  /// Byte offset of the offending data item or map key in the input
+  pub position: usize,
+  /// Kind of departure
+  pub kind: EncodingViolationKind,
+}
+
+impl fmt::Display for EncodingViolation {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "non-deterministic CBOR encoding at offset {}: {}",
+      self.position, self.kind
+    )
+  }
+}
+
+impl error::Error for EncodingViolation {}
+
+/// Kinds of departures from deterministic encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodingViolationKind {
+  /// Integer, length or tag number encoded in more bytes than needed
+  NonShortestArgument,
+  /// Floating-point number encoded in more bytes than needed to preserve its
+  /// value
+  NonShortestFloat,
+  /// Indefinite-length string, array or map
+  IndefiniteLength,
+  /// Map key whose encoding doesn't sort after that of the previous key in
+  /// bytewise lexicographic order, including a duplicate key
+  UnsortedMapKey,
+}
+
+impl fmt::Display for EncodingViolationKind {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      EncodingViolationKind::NonShortestArgument => {
+        write!(f, "argument isn't encoded in the shortest form")
+      }
+      EncodingViolationKind::NonShortestFloat => write!(
+        f,
+        "floating-point number isn't encoded in the shortest form preserving its value"
+      ),
+      EncodingViolationKind::IndefiniteLength => write!(f, "indefinite-length item"),
+      EncodingViolationKind::UnsortedMapKey => write!(
+        f,
+        "map key doesn't sort after the previous key in bytewise lexicographic order"
+      ),
+    }
+  }
+}
+
+/// Decodes a single CBOR data item, failing if it's malformed or followed by
+/// any further bytes
+///
+/// Both definite and indefinite-length items are accepted.
+///
+/// # Example
+///
+/// ```
+/// use cddl::cbor_core::{decode, Value};
+///
+/// assert_eq!(
+///   decode(&[0x82, 0x01, 0x20]),
+///   Ok(Value::Array(vec![Value::Unsigned(1), Value::Negative(0)]))
+/// );
+/// ```
+pub fn decode(input: &[u8]) -> Result<Value, DecodeError> {
+  let mut decoder = Decoder::new(input);
+
+  let value = decoder.item(0)?;
+  if decoder.position < input.len() {
+    return Err(decoder.error("unexpected data after the data item"));
+  }
+
+  Ok(value)
+}
+
+// Decodes a CBOR sequence (RFC 8742), which may be empty
+pub(crate) fn decode_seq(input: &[u8]) -> Result<Vec<Value>, DecodeError> {
+  let mut decoder = Decoder::new(input);
+
+  let mut values = Vec::new();
+  while decoder.position < input.len() {
+    values.push(decoder.item(0)?);
+  }
+
+  Ok(values)
+}
+
+// Decodes the data item at the start of the input, returning it along with the
+// number of bytes it takes up, or None if the input ends partway through it
+#[cfg(feature = "tokio")]
+pub(crate) fn decode_prefix(input: &[u8]) -> Result<Option<(Value, usize)>, DecodeError> {
+  let mut decoder = Decoder::new(input);
+
+  match decoder.item(0) {
+    Ok(value) => Ok(Some((value, decoder.position))),
+    Err(_) if decoder.is_truncated => Ok(None),
+    Err(e) => Err(e),
+  }
+}
+
+// Header of an encoded data item
+enum Argument {
+  Value(u64),
+  Indefinite,
+}
+
+/// Decodes a single CBOR data item like `decode`, also returning every
+/// departure of its encoding from the deterministic encoding requirements of
+/// RFC 8949 section 4.2.1: shortest-form arguments and floats, definite
+/// lengths and map keys sorted by their encodings.
+///
+/// # Example
+///
+/// ```
+/// use cddl::cbor_core::{decode_deterministic, EncodingViolationKind};
+///
+/// // {"b": 1, "a": 1} with the second 1 encoded in two bytes
+/// let (_, violations) =
+///   decode_deterministic(&[0xa2, 0x61, b'b', 0x01, 0x61, b'a', 0x18, 0x01]).unwrap();
+///
+/// assert_eq!(violations.len(), 2);
+/// assert_eq!(violations[0].kind, EncodingViolationKind::UnsortedMapKey);
+/// assert_eq!(violations[0].position, 4);
+/// assert_eq!(violations[1].kind, EncodingViolationKind::NonShortestArgument);
+/// assert_eq!(violations[1].position, 6);
+/// ```
+pub fn decode_deterministic(input: &[u8]) -> Result<(Value, Vec<EncodingViolation>), DecodeError> {
+  let mut decoder = Decoder::new(input);
+  decoder.violations = Some(Vec::new());
+
+  let value = decoder.item(0)?;
+  if decoder.position < input.len() {
+    return Err(decoder.error("unexpected data after the data item"));
+  }
+
+  Ok((value, decoder.violations.unwrap_or_default()))
+}
+
+/// Map key whose encoding doesn't sort after that of the key before it in the
+/// same map, as found by `first_unsorted_key`
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnsortedKey {
+  /// Key out of order
+  pub key: Value,
+  /// Location of the entry with the key as a JSON Pointer (RFC 6901), whose
+  /// reference tokens are array indexes, text keys or other keys in
+  /// diagnostic notation
+  pub path: String,
+  /// Byte offset of the key in the input
+  pub position: usize,
+}
+
+/// Returns the first map key of the encoded data item whose encoding doesn't
+/// sort after that of the key before it in bytewise lexicographic order, as
+/// deterministic encoding (RFC 8949 section 4.2.1) requires, if any
+///
+/// # Example
+///
+/// ```
+/// use cddl::cbor_core::{first_unsorted_key, Value};
+///
+/// // [{"a": 1, "b": 2}, {"b": 1, "a": 2}]
+/// let unsorted = first_unsorted_key(&[
+///   0x82, 0xa2, 0x61, b'a', 0x01, 0x61, b'b', 0x02, 0xa2, 0x61, b'b', 0x01, 0x61, b'a', 0x02,
+/// ])
+/// .unwrap()
+/// .unwrap();
+///
+/// assert_eq!(unsorted.key, Value::Text("a".into()));
+/// assert_eq!(unsorted.path, "/1/a");
+/// assert_eq!(unsorted.position, 12);
+/// ```
+pub fn first_unsorted_key(input: &[u8]) -> Result<Option<UnsortedKey>, DecodeError> {
+  let mut decoder = Decoder::new(input);
+  decoder.path = Some(Vec::new());
+
+  decoder.item(0)?;
+  if decoder.position < input.len() {
+    return Err(decoder.error("unexpected data after the data item"));
+  }
+
+  Ok(decoder.unsorted)
+}
+
+/// Map key occurring more than once in the same map. Keys are duplicates if
+/// they're the same value, however they're encoded, e.g. `1` and `0x18 0x01`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateKey {
+  /// Duplicated key
+  pub key: Value,
+  /// Byte offsets of each occurrence of the key in the input, in order
+  pub positions: Vec<usize>,
+}
+
+/// Returns the keys occurring more than once in any map of the encoded data
+/// item, ordered by their first occurrence
+///
+/// # Example
+///
+/// ```
+/// use cddl::cbor_core::{duplicate_keys, Value};
+///
+/// // {"a": 1, "b": 2, "a": 3}
+/// let duplicates =
+///   duplicate_keys(&[0xa3, 0x61, b'a', 0x01, 0x61, b'b', 0x02, 0x61, b'a', 0x03]).unwrap();
+///
+/// assert_eq!(duplicates.len(), 1);
+/// assert_eq!(duplicates[0].key, Value::Text("a".into()));
+/// assert_eq!(duplicates[0].positions, [1, 7]);
+/// ```
+pub fn duplicate_keys(input: &[u8]) -> Result<Vec<DuplicateKey>, DecodeError> {
+  let mut duplicates = decode_maps(input)?
+    .into_iter()
+    .flat_map(|map| map.keys)
+    .map(|(key, entries)| DuplicateKey {
+      key,
+      positions: entries.iter().map(|(start, _)| *start).collect(),
+    })
+    .collect::<Vec<_>>();
+  duplicates.sort_by_key(|d| d.positions[0]);
+
+  Ok(duplicates)
+}
+
+/// Removes all but the first entry with each key from the maps of the encoded
+/// data item, leaving the encoding otherwise untouched. Decoders that keep the
+/// last of the entries with the same key can then be used to keep the first.
+///
+/// # Example
+///
+/// ```
+/// use cddl::cbor_core::remove_duplicate_keys;
+///
+/// // {"a": 1, "b": 2, "a": 3}
+/// let cbor = remove_duplicate_keys(&[0xa3, 0x61, b'a', 0x01, 0x61, b'b', 0x02, 0x61, b'a', 0x03]);
+///
+/// assert_eq!(cbor.unwrap(), [0xa2, 0x61, b'a', 0x01, 0x61, b'b', 0x02]);
+/// ```
+pub fn remove_duplicate_keys(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+  // Ranges of the input to replace, and their replacements
+  let mut edits = Vec::new();
+
+  for map in decode_maps(input)? {
+    let mut removed = 0;
+    for (_, entries) in map.keys {
+      removed += entries.len() - 1;
+      edits.extend(
+        entries[1..]
+          .iter()
+          .map(|&(start, end)| (start, end, Vec::new())),
+      );
+    }
+
+    // The number of entries of definite-length maps is rewritten in the same
+    // number of bytes
+    if let Some(len) = map.len {
+      let width = match map.info {
+        24 => 1,
+        25 => 2,
+        26 => 4,
+        27 => 8,
+        _ => 0,
+      };
+      let len = (len - removed) as u64;
+
+      let header = if width == 0 {
+        vec![0xa0 | len as u8]
+      } else {
+        let mut header = vec![0xa0 | map.info];
+        header.extend_from_slice(&len.to_be_bytes()[8 - width..]);
+        header
+      };
+      edits.push((map.header, map.header + 1 + width, header));
+    }
+  }
+
+  // Edits within entries that are removed are skipped
+  edits.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+
+  let mut output = Vec::with_capacity(input.len());
+  let mut position = 0;
+  for (start, end, replacement) in edits {
+    if start < position {
+      continue;
+    }
+
+    output.extend_from_slice(&input[position..start]);
+    output.extend_from_slice(&replacement);
+    position = end;
+  }
+  output.extend_from_slice(&input[position..]);
+
+  Ok(output)
+}
+
+// Decodes a single data item, returning the maps within it that have
+// duplicate keys
+fn decode_maps(input: &[u8]) -> Result<Vec<MapLayout>, DecodeError> {
+  let mut decoder = Decoder::new(input);
+  decoder.maps = Some(Vec::new());
+
+  decoder.item(0)?;
+  if decoder.position < input.len() {
+    return Err(decoder.error("unexpected data after the data item"));
+  }
+
+  Ok(decoder.maps.unwrap_or_default())
+}
+
+// Location of a map with duplicate keys in the input
+struct MapLayout {
+  // Offset and additional information of the initial byte
+  header: usize,
+  info: u8,
+  // Number of entries, unless the map is of indefinite length
+  len: Option<usize>,
+  // Duplicated keys along with the range of each entry with the key, in order
+  keys: Vec<(Value, Vec<(usize, usize)>)>,
+}
+
+struct Decoder<'a> {
+  input: &'a [u8],
+  position: usize,
+  // Departures from deterministic encoding, if they're being checked for
+  violations: Option<Vec<EncodingViolation>>,
+  // Maps with duplicate keys, if they're being looked for
+  maps: Option<Vec<MapLayout>>,
+  // Reference tokens locating the item being decoded, if the first unsorted
+  // map key is being looked for
+  path: Option<Vec<String>>,
+  unsorted: Option<UnsortedKey>,
+  // Whether decoding failed because the input ends partway through an item
+  is_truncated: bool,
+}
+
+impl<'a> Decoder<'a> {
+  fn new(input: &'a [u8]) -> Self {
+    Decoder {
+      input,
+      position: 0,
+      violations: None,
+      maps: None,
+      path: None,
+      unsorted: None,
+      is_truncated: false,
+    }
+  }
+
+  fn violation(&mut self, position: usize, kind: EncodingViolationKind) {
+    if let Some(violations) = &mut self.violations {
+      violations.push(EncodingViolation { position, kind });
+    }
+  }
+
+  // Reads a map key, checking that its encoding sorts after that of the
+  // previous key, whose range in the input is updated
+  fn key(
+    &mut self,
+    depth: usize,
+    previous: &mut Option<(usize, usize)>,
+  ) -> Result<Value, DecodeError> {
+    let start = self.position;
+    let key = self.item(depth)?;
+
+    if self.violations.is_some() || self.path.is_some() {
+      let encoding = &self.input[start..self.position];
+      if let Some((s, e)) = *previous {
+        if encoding <= &self.input[s..e] {
+          self.violation(start, EncodingViolationKind::UnsortedMapKey);
+
+          if let (Some(path), None) = (&self.path, &self.unsorted) {
+            let mut path = path.clone();
+            path.push(key_token(&key));
+
+            self.unsorted = Some(UnsortedKey {
+              key: key.clone(),
+              path: pointer(&path),
+              position: start,
+            });
+          }
+        }
+      }
+
+      *previous = Some((start, self.position));
+    }
+
+    Ok(key)
+  }
+
+  // Reads an element of an array or the value of a map entry, located by the
+  // given reference token if the path of items is being tracked
+  fn item_at<F: FnOnce() -> String>(
+    &mut self,
+    depth: usize,
+    token: F,
+  ) -> Result<Value, DecodeError> {
+    if let Some(path) = &mut self.path {
+      path.push(token());
+    }
+    let item = self.item(depth);
+    if let Some(path) = &mut self.path {
+      path.pop();
+    }
+
+    item
+  }
+
+  fn map(
+    &mut self,
+    depth: usize,
+    header: usize,
+    info: u8,
+    argument: Argument,
+  ) -> Result<Value, DecodeError> {
+    let len = match argument {
+      Argument::Value(n) => Some(self.len(n)?),
+      Argument::Indefinite => None,
+    };
+
+    let mut entries = Vec::new();
+    // Range of each entry in the input, if duplicate keys are being looked for
+    let mut ranges = Vec::new();
+    let mut previous = None;
+    loop {
+      match len {
+        Some(len) if entries.len() == len => break,
+        None if self.is_break()? => break,
+        _ => (),
+      }
+
+      let start = self.position;
+      let key = self.key(depth + 1, &mut previous)?;
+      let value = self.item_at(depth + 1, || key_token(&key))?;
+      entries.push((key, value));
+      ranges.push((start, self.position));
+    }
+
+    if self.maps.is_some() {
+      // Keys are compared in diagnostic notation, which tells apart every
+      // value
+      let mut occurrences = BTreeMap::new();
+      for (idx, (key, _)) in entries.iter().enumerate() {
+        occurrences
+          .entry(key.to_string())
+          .or_insert_with(Vec::new)
+          .push(idx);
+      }
+
+      let mut keys = occurrences
+        .into_iter()
+        .filter(|(_, idxs)| idxs.len() > 1)
+        .map(|(_, idxs)| {
+          (
+            entries[idxs[0]].0.clone(),
+            idxs.iter().map(|idx| ranges[*idx]).collect::<Vec<_>>(),
+          )
+        })
+        .collect::<Vec<_>>();
+
+      if !keys.is_empty() {
+        keys.sort_by_key(|(_, ranges)| ranges[0].0);
+
+        if let Some(maps) = &mut self.maps {
+          maps.push(MapLayout {
+            header,
+            info,
+            len,
+            keys,
+          });
+        }
+      }
+    }
+
+    Ok(Value::Map(entries))
+  }
+
+  fn error(&self, message: &str) -> DecodeError {
+    DecodeError {
+      position: self.position,
+      message: message.to_string(),
+    }
+  }
+
+  fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+    match self.position.checked_add(len) {
+      Some(end) if end <= self.input.len() => {
+        let bytes = &self.input[self.position..end];
+        self.position = end;
+        Ok(bytes)
+      }
+      _ => {
+        self.is_truncated = true;
+        Err(self.error("unexpected end of input"))
+      }
+    }
+  }
+
+  fn uint(&mut self, len: usize) -> Result<u64, DecodeError> {
+    Ok(
+      self
+        .take(len)?
+        .iter()
+        .fold(0, |n, byte| (n << 8) | u64::from(*byte)),
+    )
+  }
+
+  // Reads the initial byte of a data item, returning the major type,
+  // additional information and argument
+  fn header(&mut self) -> Result<(u8, u8, Argument), DecodeError> {
+    let start = self.position;
+    let initial = self.take(1)?[0];
+    let (major_type, info) = (initial >> 5, initial & 0x1f);
+
+    let argument = match info {
+      0..=23 => Argument::Value(u64::from(info)),
+      24 => Argument::Value(self.uint(1)?),
+      25 => Argument::Value(self.uint(2)?),
+      26 => Argument::Value(self.uint(4)?),
+      27 => Argument::Value(self.uint(8)?),
+      31 => {
+        self.violation(start, EncodingViolationKind::IndefiniteLength);
+        Argument::Indefinite
+      }
+      _ => return Err(self.error("reserved additional information")),
+    };
+
+    // The arguments of floats are their bits, which are checked separately
+    if let Argument::Value(n) = argument {
+      let is_shortest = match info {
+        24 => n >= 24,
+        25 => n > 0xff,
+        26 => n > 0xffff,
+        27 => n > 0xffff_ffff,
+        _ => true,
+      };
+      if !is_shortest && (major_type != 7 || info == 24) {
+        self.violation(start, EncodingViolationKind::NonShortestArgument);
+      }
+    }
+
+    Ok((major_type, info, argument))
+  }
+
+  fn len(&mut self, n: u64) -> Result<usize, DecodeError> {
+    // Every element takes up at least a byte, so longer lengths are malformed
+    match n as usize {
+      len if (len as u64) == n && len <= self.input.len() - self.position => Ok(len),
+      _ => {
+        self.is_truncated = true;
+        Err(self.error("length exceeds the remaining input"))
+      }
+    }
+  }
+
+  // Returns whether the next byte is the "break" stop code, consuming it if so
+  fn is_break(&mut self) -> Result<bool, DecodeError> {
+    match self.input.get(self.position) {
+      Some(0xff) => {
+        self.position += 1;
+        Ok(true)
+      }
+      Some(_) => Ok(false),
+      None => {
+        self.is_truncated = true;
+        Err(self.error("unexpected end of input"))
+      }
+    }
+  }
+
+  // Reads a byte or text string, concatenating the chunks of an
+  // indefinite-length string
+  fn string(&mut self, major_type: u8, argument: Argument) -> Result<Vec<u8>, DecodeError> {
+    let len = match argument {
+      Argument::Value(n) => self.len(n)?,
+      Argument::Indefinite => {
+        let mut bytes = Vec::new();
+
+        while !self.is_break()? {
+          match self.header()? {
+            (mt, _, Argument::Value(n)) if mt == major_type => {
+              let len = self.len(n)?;
+              bytes.extend_from_slice(self.take(len)?);
+            }
+            _ => return Err(self.error("invalid chunk of indefinite-length string")),
+          }
+        }
+
+        return Ok(bytes);
+      }
+    };
+
+    Ok(self.take(len)?.to_vec())
+  }
+
+  fn item(&mut self, depth: usize) -> Result<Value, DecodeError> {
+    if depth > MAX_NESTING {
+      return Err(self.error("data items nested too deeply"));
+    }
+
+    let start = self.position;
+    let (major_type, info, argument) = self.header()?;
+
+    match (major_type, argument) {
+      (0, Argument::Value(n)) => Ok(Value::Unsigned(n)),
+      (1, Argument::Value(n)) => Ok(Value::Negative(n)),
+      (2, argument) => Ok(Value::Bytes(self.string(2, argument)?)),
+      (3, argument) => {
+        let bytes = self.string(3, argument)?;
+
+        String::from_utf8(bytes)
+          .map(Value::Text)
+          .map_err(|_| DecodeError {
+            position: start,
+            message: "text string isn't valid UTF-8".to_string(),
+          })
+      }
+      (4, Argument::Value(n)) => {
+        let len = self.len(n)?;
+
+        (0..len)
+          .map(|idx| self.item_at(depth + 1, || idx.to_string()))
+          .collect::<Result<_, _>>()
+          .map(Value::Array)
+      }
+      (4, Argument::Indefinite) => {
+        let mut values = Vec::new();
+        while !self.is_break()? {
+          let idx = values.len();
+          values.push(self.item_at(depth + 1, || idx.to_string())?);
+        }
+
+        Ok(Value::Array(values))
+      }
+      (5, argument) => self.map(depth, start, info, argument),
+      (6, Argument::Value(tag)) => Ok(Value::Tag(tag, Box::new(self.item(depth + 1)?))),
+      (7, Argument::Value(n)) => match info {
+        0..=23 => Ok(Value::Simple(info)),
+        24 if n >= 32 => Ok(Value::Simple(n as u8)),
+        25 => Ok(Value::Float(f16_to_f64(n as u16), FloatWidth::Half)),
+        26 => {
+          let single = f32::from_bits(n as u32);
+          if fits_half(single) {
+            self.violation(start, EncodingViolationKind::NonShortestFloat);
+          }
+
+          Ok(Value::Float(f64::from(single), FloatWidth::Single))
+        }
+        27 => {
+          let double = f64::from_bits(n);
+          if double.is_nan() || f64::from(double as f32) == double {
+            self.violation(start, EncodingViolationKind::NonShortestFloat);
+          }
+
+          Ok(Value::Float(double, FloatWidth::Double))
+        }
+        _ => Err(DecodeError {
+          position: start,
+          message: "invalid simple value".to_string(),
+        }),
+      },
+      _ => Err(DecodeError {
+        position: start,
+        message: "unexpected indefinite-length item or break".to_string(),
+      }),
+    }
+  }
+}
+
+// Whether a single-precision float can be encoded as a half-precision float
+// without losing precision, which NaNs and infinities always can
+fn fits_half(single: f32) -> bool {
+  let bits = single.to_bits();
+  let exponent = ((bits >> 23) & 0xff) as i32 - 127;
+  let mantissa = bits & 0x7f_ffff;
+
+  match exponent {
+    128 => true,
+    -127 => mantissa == 0,
+    // Normal half-precision floats have 10 bits of mantissa
+    -14..=15 => mantissa & 0x1fff == 0,
+    // Subnormal half-precision floats are multiples of 2^-24
+    -24..=-15 => (0x80_0000 | mantissa) & ((1 << (-exponent - 1)) - 1) == 0,
+    _ => false,
+  }
+}
+
+// Converts an IEEE 754 half-precision float to a double
+fn f16_to_f64(half: u16) -> f64 {
+  let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+  let exponent = i32::from((half >> 10) & 0x1f);
+  let mantissa = f64::from(half & 0x3ff);
+
+  sign
+    * match exponent {
+      0 => mantissa * pow2(-24),
+      // Infinities and NaNs keep their sign and payload when widened
+      31 => {
+        return f64::from(f32::from_bits(
+          (u32::from(half & 0x8000) << 16) | 0x7f80_0000 | (u32::from(half & 0x3ff) << 13),
+        ))
+      }
+      _ => (1024.0 + mantissa) * pow2(exponent - 25),
+    }
+}
+
+// Computes 2^exp without relying on the floating-point functions of std
+fn pow2(exp: i32) -> f64 {
+  f64::from_bits(((1023 + exp) as u64) << 52)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn decode_items() {
+    assert_eq!(
+      decode(&[0x1b, 0, 0, 0, 1, 0, 0, 0, 0]),
+      Ok(Value::Unsigned(1 << 32))
+    );
+    assert_eq!(decode(&[0x38, 0x63]), Ok(Value::Negative(99)));
+    assert_eq!(decode(&[0x43, 1, 2, 3]), Ok(Value::Bytes(vec![1, 2, 3])));
+    assert_eq!(
+      decode(&[0x7f, 0x62, b'a', b'b', 0x61, b'c', 0xff]),
+      Ok(Value::Text("abc".into()))
+    );
+    assert_eq!(
+      decode(&[0xbf, 0x61, b'a', 0x9f, 0xf4, 0xf7, 0xff, 0xff]),
+      Ok(Value::Map(vec![(
+        Value::Text("a".into()),
+        Value::Array(vec![Value::Simple(20), Value::Simple(23)])
+      )]))
+    );
+    assert_eq!(
+      decode(&[0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0]),
+      Ok(Value::Tag(1, Box::new(Value::Unsigned(1_363_896_240))))
+    );
+  }
+
+  #[test]
+  fn decode_floats() {
+    assert_eq!(
+      decode(&[0xf9, 0x3e, 0x00]),
+      Ok(Value::Float(1.5, FloatWidth::Half))
+    );
+    assert_eq!(
+      decode(&[0xf9, 0x00, 0x01]),
+      Ok(Value::Float(5.960_464_477_539_063e-8, FloatWidth::Half))
+    );
+    assert!(match decode(&[0xf9, 0xfc, 0x00]) {
+      Ok(Value::Float(f, FloatWidth::Half)) => f.is_infinite() && f.is_sign_negative(),
+      _ => false,
+    });
+    assert_eq!(
+      decode(&[0xfa, 0x47, 0xc3, 0x50, 0x00]),
+      Ok(Value::Float(100_000.0, FloatWidth::Single))
+    );
+    assert_eq!(
+      decode(&[0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a]),
+      Ok(Value::Float(1.1, FloatWidth::Double))
+    );
+  }
+
+  #[test]
+  fn decode_deterministic_violations() {
+    let kinds = |input: &[u8]| {
+      decode_deterministic(input)
+        .unwrap()
+        .1
+        .into_iter()
+        .map(|v| (v.position, v.kind))
+        .collect::<Vec<_>>()
+    };
+
+    assert!(kinds(&[0xa2, 0x01, 0x80, 0x61, b'a', 0xf9, 0x3e, 0x00]).is_empty());
+    assert_eq!(
+      kinds(&[0x9f, 0x19, 0x00, 0x10, 0xd8, 0x01, 0x00, 0xff]),
+      vec![
+        (0, EncodingViolationKind::IndefiniteLength),
+        (1, EncodingViolationKind::NonShortestArgument),
+        (4, EncodingViolationKind::NonShortestArgument),
+      ]
+    );
+    assert_eq!(
+      kinds(&[0xa2, 0x01, 0x00, 0x01, 0x00]),
+      vec![(3, EncodingViolationKind::UnsortedMapKey)]
+    );
+    assert_eq!(
+      kinds(&[0x82, 0xfa, 0x3f, 0xc0, 0x00, 0x00, 0xfa, 0x3f, 0x8c, 0xcc, 0xcd]),
+      vec![(1, EncodingViolationKind::NonShortestFloat)]
+    );
+    assert_eq!(
+      kinds(&[0xfb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0]),
+      vec![(0, EncodingViolationKind::NonShortestFloat)]
+    );
+    assert!(kinds(&[0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a]).is_empty());
+    assert!(!kinds(&[0xfa, 0x33, 0x80, 0x00, 0x00]).is_empty());
+    assert!(kinds(&[0xfa, 0x33, 0x00, 0x00, 0x00]).is_empty());
+  }
+
+  #[test]
+  fn find_and_remove_duplicate_keys() {
+    // {1: {_ "a": 0, "a": 1, "b": 2, "a": 3}, 2: 0, 0x18 0x01: {0: 0, 0: 1}}
+    let input = [
+      0xa3, 0x01, 0xbf, 0x61, b'a', 0x00, 0x61, b'a', 0x01, 0x61, b'b', 0x02, 0x61, b'a', 0x03,
+      0xff, 0x02, 0x00, 0x18, 0x01, 0xa2, 0x00, 0x00, 0x00, 0x01,
+    ];
+
+    let duplicates = duplicate_keys(&input)
+      .unwrap()
+      .into_iter()
+      .map(|d| (d.key, d.positions))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      duplicates,
+      vec![
+        (Value::Unsigned(1), vec![1, 18]),
+        (Value::Text("a".to_string()), vec![3, 6, 12]),
+        (Value::Unsigned(0), vec![21, 23]),
+      ]
+    );
+
+    let deduped = remove_duplicate_keys(&input).unwrap();
+    assert_eq!(
+      deduped,
+      [0xa2, 0x01, 0xbf, 0x61, b'a', 0x00, 0x61, b'b', 0x02, 0xff, 0x02, 0x00]
+    );
+    assert!(duplicate_keys(&deduped).unwrap().is_empty());
+
+    // Map lengths are rewritten in the width they were encoded in
+    let mut input = vec![0xb8, 0x19];
+    for key in 0..25 {
+      input.extend_from_slice(&[key.min(22), 0x00]);
+    }
+    let deduped = remove_duplicate_keys(&input).unwrap();
+    assert_eq!(deduped[..2], [0xb8, 0x17]);
+    assert_eq!(deduped[2..], input[2..48]);
+  }
+
+  #[test]
+  fn decode_malformed() {
+    assert_eq!(decode(&[0x82, 0x01]).unwrap_err().position, 1);
+    assert_eq!(decode(&[0x01, 0x02]).unwrap_err().position, 1);
+    assert!(decode(&[0x62, 0xff, 0xfe]).is_err());
+    assert!(decode(&[0x9b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]).is_err());
+    assert!(decode(&[0x1f]).is_err());
+    assert!(decode(&[0x5f, 0x61, b'a', 0xff]).is_err());
+  }
+}
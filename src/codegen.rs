@@ -0,0 +1,759 @@
+use super::{ast::*, token};
+use std::fmt::Write;
+
+#[cfg(feature = "std")]
+use std::collections::BTreeSet;
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+  collections::BTreeSet,
+  format,
+  string::{String, ToString},
+  vec::Vec,
+};
+
+const DERIVES: &str = "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]";
+
+/// Generates Rust type definitions with serde derives for the rules of a CDDL
+/// definition
+///
+/// Maps become structs whose fields are named after the member keys, type
+/// choices become enums, arrays of a single repeated entry become `Vec`s and
+/// other arrays become tuple structs. Optional entries become `Option`s and
+/// repeated entries become `Vec`s. Group rules become structs that are
+/// flattened into the maps that reference them. Since the generated types rely
+/// on serde's data model, they're an approximation of the CDDL. In particular,
+/// values and controls aren't enforced, so data should still be validated
+/// against the CDDL itself. Types that can't be represented are generated as
+/// `serde_json::Value`.
+///
+/// # Example
+///
+/// ```
+/// use cddl::{codegen::generate_rust, lexer_from_str, parser::cddl_from_str};
+///
+/// let input = r#"person = { name: tstr, ? age: uint }"#;
+/// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+///
+/// let output = generate_rust(&cddl);
+/// assert!(output.contains("pub struct Person {"));
+/// assert!(output.contains("pub age: Option<u64>,"));
+/// ```
+pub fn generate_rust(cddl: &CDDL) -> String {
+  let mut generator = Generator {
+    cddl,
+    names: BTreeSet::new(),
+    items: Vec::new(),
+  };
+
+  // Reserve the names of the rules up front so that the names of the types
+  // generated for nested maps and arrays don't collide with them
+  for rule in cddl.rules.iter() {
+    generator.names.insert(pascal_case(rule_name(rule)));
+  }
+
+  for rule in cddl.rules.iter().filter(|r| !r.is_choice_alternate()) {
+    match rule {
+      Rule::Type { rule, .. } => generator.type_rule(rule),
+      Rule::Group { rule, .. } => generator.group_rule(rule),
+    }
+  }
+
+  let mut output = String::from(
+    "// Generated from CDDL. Data should still be validated against the CDDL since\n// not every constraint can be expressed by these types.\n\nuse serde::{Deserialize, Serialize};\n",
+  );
+
+  for item in generator.items.iter() {
+    output.push('\n');
+    output.push_str(item);
+  }
+
+  output
+}
+
+struct Generator<'a, 'b> {
+  cddl: &'b CDDL<'a>,
+  // Names of the generated types
+  names: BTreeSet<String>,
+  items: Vec<String>,
+}
+
+// Name and generic parameters of the type an item is being generated for
+struct Scope<'c> {
+  name: &'c str,
+  generic_params: &'c [String],
+}
+
+impl<'a, 'b> Generator<'a, 'b> {
+  fn type_rule(&mut self, tr: &TypeRule<'a>) {
+    let generic_params = generic_params(&tr.generic_param);
+    let name = pascal_case(tr.name.ident);
+    let scope = Scope {
+      name: &name,
+      generic_params: &generic_params,
+    };
+
+    // Rules extended via /= contribute additional type choices
+    let mut type_choices = Vec::new();
+    for rule in self.cddl.rules_named(tr.name.ident) {
+      if let Rule::Type { rule, .. } = rule {
+        type_choices.extend(rule.value.type_choices.iter());
+      }
+    }
+
+    let ident = format!("{}{}", name, generics(&generic_params));
+
+    if type_choices.len() == 1 {
+      match &type_choices[0].type2 {
+        Type2::Map { group, .. } if !is_table(group) => {
+          return self.struct_item(&ident, group, &scope)
+        }
+        Type2::Array { group, .. } if !is_repeated(group) => {
+          return self.tuple_item(&ident, group, &scope)
+        }
+        _ => (),
+      }
+    }
+
+    if type_choices.len() > 1 {
+      return self.enum_item(&ident, &type_choices, &scope);
+    }
+
+    let rust_type = self.type1(type_choices[0], &scope, &name);
+    self
+      .items
+      .push(format!("pub type {} = {};\n", ident, rust_type));
+  }
+
+  fn group_rule(&mut self, gr: &GroupRule<'a>) {
+    let generic_params = generic_params(&gr.generic_param);
+    let name = pascal_case(gr.name.ident);
+    let scope = Scope {
+      name: &name,
+      generic_params: &generic_params,
+    };
+
+    let ident = format!("{}{}", name, generics(&generic_params));
+
+    match &gr.entry {
+      GroupEntry::InlineGroup { group, .. } => self.struct_item(&ident, group, &scope),
+      entry => {
+        let mut fields = String::new();
+        self.field(&mut fields, entry, &scope);
+        self.push_struct(&ident, &fields);
+      }
+    }
+  }
+
+  // Generates a struct for the entries of a map
+  fn struct_item(&mut self, ident: &str, group: &Group<'a>, scope: &Scope) {
+    if group.group_choices.len() > 1 {
+      let mut variants = String::new();
+      for (idx, gc) in group.group_choices.iter().enumerate() {
+        let variant = self.unique_name(&format!("{}{}", scope.name, idx + 1));
+        let mut fields = String::new();
+        for (ge, _) in gc.group_entries.iter() {
+          self.field(&mut fields, ge, scope);
+        }
+        self.push_struct(&variant, &fields);
+
+        let _ = writeln!(variants, "  {}({}),", variant, variant);
+      }
+
+      return self.push_enum(ident, true, &variants);
+    }
+
+    let mut fields = String::new();
+    for gc in group.group_choices.iter() {
+      for (ge, _) in gc.group_entries.iter() {
+        self.field(&mut fields, ge, scope);
+      }
+    }
+
+    self.push_struct(ident, &fields);
+  }
+
+  // Generates a tuple struct for the entries of an array
+  fn tuple_item(&mut self, ident: &str, group: &Group<'a>, scope: &Scope) {
+    let mut elements = Vec::new();
+
+    if let Some(gc) = group.group_choices.first() {
+      for (idx, (ge, _)) in gc.group_entries.iter().enumerate() {
+        let context = format!("{}{}", scope.name, idx + 1);
+        let element = match ge {
+          GroupEntry::ValueMemberKey { ge, .. } => {
+            let rust_type = self.r#type(&ge.entry_type, scope, &context);
+            with_occurrence(rust_type, ge.occur.as_ref())
+          }
+          GroupEntry::TypeGroupname { ge, .. } => {
+            let rust_type = self.typename(&ge.name, ge.generic_arg.as_ref(), scope);
+            with_occurrence(rust_type, ge.occur.as_ref())
+          }
+          GroupEntry::InlineGroup { .. } => "serde_json::Value".to_string(),
+        };
+
+        elements.push(format!("pub {}", element));
+      }
+    }
+
+    self.items.push(format!(
+      "{}\npub struct {}({});\n",
+      DERIVES,
+      ident,
+      elements.join(", ")
+    ));
+  }
+
+  // Generates an enum for the choices of a type. Choices made up of text
+  // values only become unit variants. Otherwise, the enum is untagged with a
+  // variant for each choice.
+  fn enum_item(&mut self, ident: &str, type_choices: &[&Type1<'a>], scope: &Scope) {
+    let mut variants = String::new();
+
+    let is_text_values = type_choices.iter().all(|tc| match tc.type2 {
+      Type2::TextValue { .. } => tc.operator.is_none(),
+      _ => false,
+    });
+
+    if is_text_values {
+      for tc in type_choices.iter() {
+        if let Type2::TextValue { value, .. } = &tc.type2 {
+          let variant = pascal_case(value);
+          if variant != *value {
+            let _ = writeln!(variants, "  #[serde(rename = {:?})]", value);
+          }
+          let _ = writeln!(variants, "  {},", variant);
+        }
+      }
+
+      return self.push_enum(ident, false, &variants);
+    }
+
+    let mut variant_names = BTreeSet::new();
+    for (idx, tc) in type_choices.iter().enumerate() {
+      let mut variant = match &tc.type2 {
+        Type2::Typename { ident, .. } => pascal_case(ident.ident),
+        Type2::Map { .. } => "Map".to_string(),
+        Type2::Array { .. } => "Array".to_string(),
+        t2 => pascal_case(&self.type2(t2, scope, "")),
+      };
+      if variant.is_empty() || !variant_names.insert(variant.clone()) {
+        variant = format!("Variant{}", idx + 1);
+        variant_names.insert(variant.clone());
+      }
+
+      let context = format!("{}{}", scope.name, variant);
+      let rust_type = self.type1(tc, scope, &context);
+      if rust_type == "()" {
+        let _ = writeln!(variants, "  {},", variant);
+      } else {
+        let _ = writeln!(variants, "  {}({}),", variant, boxed(rust_type, scope));
+      }
+    }
+
+    self.push_enum(ident, true, &variants);
+  }
+
+  // Appends the field(s) for a map entry
+  fn field(&mut self, fields: &mut String, ge: &GroupEntry<'a>, scope: &Scope) {
+    match ge {
+      GroupEntry::ValueMemberKey { ge, .. } => {
+        let key = match &ge.member_key {
+          Some(MemberKey::Bareword { ident, .. }) => ident.ident,
+          Some(MemberKey::Value {
+            value: token::Value::TEXT(text),
+            ..
+          }) => text,
+          Some(MemberKey::Type1 { t1, .. }) => {
+            let context = format!("{}Value", scope.name);
+            let key_type = self.type1(t1, scope, "");
+            let value_type = self.r#type(&ge.entry_type, scope, &context);
+            let _ = writeln!(fields, "  #[serde(flatten)]");
+            let _ = writeln!(
+              fields,
+              "  pub extra: std::collections::BTreeMap<{}, {}>,",
+              key_type, value_type
+            );
+            return;
+          }
+          Some(mk) => {
+            let _ = writeln!(
+              fields,
+              "  // Skipped entry with unsupported member key {}",
+              mk
+            );
+            return;
+          }
+          None => {
+            let _ = writeln!(fields, "  // Skipped entry without a member key: {}", ge);
+            return;
+          }
+        };
+
+        let field = snake_case(key);
+        let context = format!("{}{}", scope.name, pascal_case(key));
+        let rust_type = boxed(self.r#type(&ge.entry_type, scope, &context), scope);
+
+        if field.trim_start_matches("r#") != key {
+          let _ = writeln!(fields, "  #[serde(rename = \"{}\")]", key);
+        }
+        push_field(fields, &field, rust_type, ge.occur.as_ref());
+      }
+      GroupEntry::TypeGroupname { ge, .. } => {
+        let rust_type = self.typename(&ge.name, ge.generic_arg.as_ref(), scope);
+        let _ = writeln!(fields, "  #[serde(flatten)]");
+        push_field(
+          fields,
+          &snake_case(ge.name.ident),
+          rust_type,
+          ge.occur.as_ref(),
+        );
+      }
+      GroupEntry::InlineGroup { group, occur, .. } => {
+        let name = self.unique_name(&format!("{}Group", scope.name));
+        let nested = Scope {
+          name: &name,
+          generic_params: scope.generic_params,
+        };
+        self.struct_item(&name, group, &nested);
+
+        let _ = writeln!(fields, "  #[serde(flatten)]");
+        push_field(fields, &snake_case(&name), name.clone(), occur.as_ref());
+      }
+    }
+  }
+
+  fn r#type(&mut self, t: &Type<'a>, scope: &Scope, context: &str) -> String {
+    let (nulls, choices): (Vec<&Type1<'a>>, Vec<&Type1<'a>>) =
+      t.type_choices.iter().partition(|tc| match &tc.type2 {
+        Type2::Typename { ident, .. } => ident.ident == "null" || ident.ident == "nil",
+        _ => false,
+      });
+
+    // Choices between null and another type become optional
+    if !nulls.is_empty() && choices.len() == 1 {
+      return format!("Option<{}>", self.type1(choices[0], scope, context));
+    }
+
+    if t.type_choices.len() == 1 {
+      return self.type1(&t.type_choices[0], scope, context);
+    }
+
+    let name = self.unique_name(context);
+    let type_choices = t.type_choices.iter().collect::<Vec<_>>();
+    self.enum_item(&name, &type_choices, scope);
+
+    name
+  }
+
+  fn type1(&mut self, t1: &Type1<'a>, scope: &Scope, context: &str) -> String {
+    // Ranges and controls constrain the values of a type but not its
+    // representation
+    self.type2(&t1.type2, scope, context)
+  }
+
+  fn type2(&mut self, t2: &Type2<'a>, scope: &Scope, context: &str) -> String {
+    match t2 {
+      Type2::IntValue { .. } => "i64".to_string(),
+      Type2::UintValue { .. } => "u64".to_string(),
+      Type2::FloatValue { .. } => "f64".to_string(),
+      Type2::TextValue { .. } => "String".to_string(),
+      Type2::UTF8ByteString { .. } | Type2::B16ByteString { .. } | Type2::B64ByteString { .. } => {
+        "Vec<u8>".to_string()
+      }
+      Type2::Typename {
+        ident, generic_arg, ..
+      } => self.typename(ident, generic_arg.as_ref(), scope),
+      Type2::ParenthesizedType { pt, .. } => self.r#type(pt, scope, context),
+      Type2::TaggedData { t, .. } => self.r#type(t, scope, context),
+      Type2::Map { group, .. } if is_table(group) => self.table(group, scope, context),
+      Type2::Map { group, .. } => {
+        let name = self.unique_name(context);
+        self.struct_item(&name, group, scope);
+        name
+      }
+      Type2::Array { group, .. } if is_repeated(group) => self.repeated(group, scope, context),
+      Type2::Array { group, .. } => {
+        let name = self.unique_name(context);
+        self.tuple_item(&name, group, scope);
+        name
+      }
+      _ => "serde_json::Value".to_string(),
+    }
+  }
+
+  fn typename(
+    &mut self,
+    ident: &Identifier<'a>,
+    generic_arg: Option<&GenericArg<'a>>,
+    scope: &Scope,
+  ) -> String {
+    let name = pascal_case(ident.ident);
+    if scope.generic_params.contains(&name) {
+      return name;
+    }
+
+    if self.cddl.rule(ident.ident).is_some() {
+      return match generic_arg {
+        Some(ga) => {
+          let args = ga
+            .args
+            .iter()
+            .enumerate()
+            .map(|(idx, arg)| {
+              let context = format!("{}Arg{}", name, idx + 1);
+              self.type1(arg, scope, &context)
+            })
+            .collect::<Vec<_>>();
+
+          format!("{}<{}>", name, args.join(", "))
+        }
+        None => name,
+      };
+    }
+
+    prelude_type(ident.ident).to_string()
+  }
+
+  // Returns a map type for maps made up of a single entry whose key is a type,
+  // e.g. { * tstr => uint }
+  fn table(&mut self, group: &Group<'a>, scope: &Scope, context: &str) -> String {
+    if let Some(GroupEntry::ValueMemberKey { ge, .. }) = group
+      .group_choices
+      .first()
+      .and_then(|gc| gc.group_entries.first())
+      .map(|(ge, _)| ge)
+    {
+      if let Some(MemberKey::Type1 { t1, .. }) = &ge.member_key {
+        let key_type = self.type1(t1, scope, "");
+        let value_type = self.r#type(&ge.entry_type, scope, &format!("{}Value", context));
+
+        return format!("std::collections::BTreeMap<{}, {}>", key_type, value_type);
+      }
+    }
+
+    "serde_json::Value".to_string()
+  }
+
+  // Returns a vector type for arrays made up of a single repeated entry, e.g.
+  // [* tstr]
+  fn repeated(&mut self, group: &Group<'a>, scope: &Scope, context: &str) -> String {
+    let element = match group
+      .group_choices
+      .first()
+      .and_then(|gc| gc.group_entries.first())
+      .map(|(ge, _)| ge)
+    {
+      Some(GroupEntry::ValueMemberKey { ge, .. }) => self.r#type(&ge.entry_type, scope, context),
+      Some(GroupEntry::TypeGroupname { ge, .. }) => {
+        self.typename(&ge.name, ge.generic_arg.as_ref(), scope)
+      }
+      _ => "serde_json::Value".to_string(),
+    };
+
+    format!("Vec<{}>", element)
+  }
+
+  fn push_struct(&mut self, ident: &str, fields: &str) {
+    self.items.push(format!(
+      "{}\npub struct {} {{\n{}}}\n",
+      DERIVES, ident, fields
+    ));
+  }
+
+  fn push_enum(&mut self, ident: &str, is_untagged: bool, variants: &str) {
+    let untagged = if is_untagged {
+      "#[serde(untagged)]\n"
+    } else {
+      ""
+    };
+
+    self.items.push(format!(
+      "{}\n{}pub enum {} {{\n{}}}\n",
+      DERIVES, untagged, ident, variants
+    ));
+  }
+
+  // Returns the given name, or the name suffixed with a number if it's already
+  // been taken
+  fn unique_name(&mut self, name: &str) -> String {
+    let mut unique = name.to_string();
+    let mut suffix = 2;
+    while self.names.contains(&unique) {
+      unique = format!("{}{}", name, suffix);
+      suffix += 1;
+    }
+
+    self.names.insert(unique.clone());
+    unique
+  }
+}
+
+fn rule_name<'a>(rule: &Rule<'a>) -> &'a str {
+  match rule {
+    Rule::Type { rule, .. } => rule.name.ident,
+    Rule::Group { rule, .. } => rule.name.ident,
+  }
+}
+
+fn generic_params(gp: &Option<GenericParm>) -> Vec<String> {
+  gp.iter()
+    .flat_map(|gp| gp.params.iter().map(|p| pascal_case(p.ident)))
+    .collect()
+}
+
+fn generics(generic_params: &[String]) -> String {
+  if generic_params.is_empty() {
+    return String::new();
+  }
+
+  format!("<{}>", generic_params.join(", "))
+}
+
+// Whether or not a map consists of a single entry whose key is a type
+fn is_table(group: &Group) -> bool {
+  if let [gc] = group.group_choices.as_slice() {
+    if let [(GroupEntry::ValueMemberKey { ge, .. }, _)] = gc.group_entries.as_slice() {
+      if let Some(MemberKey::Type1 { .. }) = ge.member_key {
+        return true;
+      }
+    }
+  }
+
+  false
+}
+
+// Whether or not an array consists of a single entry that can occur more than
+// once
+fn is_repeated(group: &Group) -> bool {
+  let occur = match group.group_choices.as_slice() {
+    [gc] => match gc.group_entries.as_slice() {
+      [(GroupEntry::ValueMemberKey { ge, .. }, _)] => ge.occur.as_ref(),
+      [(GroupEntry::TypeGroupname { ge, .. }, _)] => ge.occur.as_ref(),
+      _ => None,
+    },
+    _ => None,
+  };
+
+  match occur {
+    Some(Occur::Optional(_)) | None => false,
+    Some(_) => true,
+  }
+}
+
+fn with_occurrence(rust_type: String, occur: Option<&Occur>) -> String {
+  match occur {
+    Some(Occur::Optional(_)) => format!("Option<{}>", rust_type),
+    Some(_) => format!("Vec<{}>", rust_type),
+    None => rust_type,
+  }
+}
+
+fn push_field(fields: &mut String, field: &str, rust_type: String, occur: Option<&Occur>) {
+  if let Some(Occur::Optional(_)) = occur {
+    let _ = writeln!(
+      fields,
+      "  #[serde(default, skip_serializing_if = \"Option::is_none\")]"
+    );
+  }
+
+  let _ = writeln!(
+    fields,
+    "  pub {}: {},",
+    field,
+    with_occurrence(rust_type, occur)
+  );
+}
+
+// Boxes references to the type being generated, which would otherwise have an
+// infinite size
+fn boxed(rust_type: String, scope: &Scope) -> String {
+  if rust_type == scope.name {
+    return format!("Box<{}>", rust_type);
+  }
+
+  if rust_type == format!("Option<{}>", scope.name) {
+    return format!("Option<Box<{}>>", scope.name);
+  }
+
+  rust_type
+}
+
+fn prelude_type(ident: &str) -> &'static str {
+  match ident {
+    "bool" | "true" | "false" => "bool",
+    "uint" | "unsigned" => "u64",
+    "nint" | "int" | "integer" | "time" => "i64",
+    "float16" | "float32" | "float16-32" => "f32",
+    "float64" | "float32-64" | "float" | "number" => "f64",
+    "tstr" | "text" | "tdate" | "uri" | "b64url" | "b64legacy" | "regexp" | "mime-message" => {
+      "String"
+    }
+    "bstr" | "bytes" | "encoded-cbor" => "Vec<u8>",
+    "null" | "nil" | "undefined" => "()",
+    _ => "serde_json::Value",
+  }
+}
+
+fn words(ident: &str) -> Vec<String> {
+  let mut words = Vec::new();
+  let mut word = String::new();
+  let mut prev_lowercase = false;
+
+  for c in ident.chars() {
+    if !c.is_ascii_alphanumeric() {
+      if !word.is_empty() {
+        words.push(word.clone());
+        word.clear();
+      }
+      prev_lowercase = false;
+      continue;
+    }
+
+    if c.is_ascii_uppercase() && prev_lowercase && !word.is_empty() {
+      words.push(word.clone());
+      word.clear();
+    }
+
+    prev_lowercase = c.is_ascii_lowercase() || c.is_ascii_digit();
+    word.push(c);
+  }
+
+  if !word.is_empty() {
+    words.push(word);
+  }
+
+  words
+}
+
+fn pascal_case(ident: &str) -> String {
+  let mut pascal = words(ident)
+    .iter()
+    .map(|w| {
+      let mut chars = w.chars();
+      match chars.next() {
+        Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+        None => String::new(),
+      }
+    })
+    .collect::<String>();
+
+  if pascal.starts_with(|c: char| c.is_ascii_digit()) {
+    pascal.insert(0, '_');
+  }
+
+  pascal
+}
+
+fn snake_case(ident: &str) -> String {
+  let mut snake = words(ident)
+    .iter()
+    .map(|w| w.to_ascii_lowercase())
+    .collect::<Vec<_>>()
+    .join("_");
+
+  if snake.is_empty() || snake.starts_with(|c: char| c.is_ascii_digit()) {
+    snake.insert(0, '_');
+  }
+
+  match snake.as_str() {
+    "as" | "break" | "const" | "continue" | "crate" | "else" | "enum" | "extern" | "false"
+    | "fn" | "for" | "if" | "impl" | "in" | "let" | "loop" | "match" | "mod" | "move" | "mut"
+    | "pub" | "ref" | "return" | "static" | "struct" | "trait" | "true" | "type" | "unsafe"
+    | "use" | "where" | "while" | "async" | "await" | "dyn" => format!("r#{}", snake),
+    _ => snake,
+  }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+  use super::*;
+  use crate::{lexer::Lexer, parser::cddl_from_str};
+  use indoc::indoc;
+  use pretty_assertions::assert_eq;
+
+  #[test]
+  fn verify_generate_rust() {
+    let input = indoc!(
+      r#"
+        reputon = {
+          rater: text,
+          ratingValue: float16 .le 1.0,
+          ? type: kind,
+          tags: [* tstr],
+          * tstr => any,
+        }
+        kind = "good" / "bad-actor"
+        point = [x: int, y: int]
+        node = { value: uint / tstr, ? next: node }
+        pair<t> = ( key: tstr, value: t )
+        entry = { pair<uint> }
+      "#
+    );
+
+    let mut lexer = Lexer::new(input);
+    let cddl = cddl_from_str(&mut lexer, input, false).unwrap();
+
+    assert_eq!(
+      generate_rust(&cddl),
+      indoc!(
+        r#"
+          // Generated from CDDL. Data should still be validated against the CDDL since
+          // not every constraint can be expressed by these types.
+
+          use serde::{Deserialize, Serialize};
+
+          #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+          pub struct Reputon {
+            pub rater: String,
+            #[serde(rename = "ratingValue")]
+            pub rating_value: f32,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub r#type: Option<Kind>,
+            pub tags: Vec<String>,
+            #[serde(flatten)]
+            pub extra: std::collections::BTreeMap<String, serde_json::Value>,
+          }
+
+          #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+          pub enum Kind {
+            #[serde(rename = "good")]
+            Good,
+            #[serde(rename = "bad-actor")]
+            BadActor,
+          }
+
+          #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+          pub struct Point(pub i64, pub i64);
+
+          #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+          #[serde(untagged)]
+          pub enum NodeValue {
+            Uint(u64),
+            Tstr(String),
+          }
+
+          #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+          pub struct Node {
+            pub value: NodeValue,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub next: Option<Box<Node>>,
+          }
+
+          #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+          pub struct Pair<T> {
+            pub key: String,
+            pub value: T,
+          }
+
+          #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+          pub struct Entry {
+            #[serde(flatten)]
+            pub pair: Pair<u64>,
+          }
+        "#
+      )
+    );
+  }
+}
@@ -0,0 +1,889 @@
+use super::ast::*;
+use std::fmt;
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+  string::{String, ToString},
+  vec::Vec,
+};
+
+/// Change between two versions of a CDDL definition that causes data valid
+/// against the old version to be rejected by the new one
+#[derive(Debug, Clone, PartialEq)]
+pub struct BreakingChange {
+  /// Name of the rule the change was found in
+  pub rule: String,
+  /// Location of the change within the rule, as a JSON Pointer made up of the
+  /// map keys and array indices leading to it. Empty if the change applies to
+  /// the rule's type as a whole.
+  pub path: String,
+  /// Kind of change
+  pub kind: BreakingChangeKind,
+}
+
+impl fmt::Display for BreakingChange {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}{}: {}", self.rule, self.path, self.kind)
+  }
+}
+
+/// Kinds of breaking changes
+#[derive(Debug, Clone, PartialEq)]
+pub enum BreakingChangeKind {
+  /// Rule was removed
+  RemovedRule,
+  /// First rule of the definition, against which data is validated by
+  /// default, is now the given rule
+  ChangedRoot(String),
+  /// Type or group choice is no longer accepted by any alternative
+  RemovedChoice(String),
+  /// Range was replaced by one that doesn't contain all of its values
+  NarrowedRange {
+    /// Old range
+    old: String,
+    /// New range
+    new: String,
+  },
+  /// Entry that's either new or was previously optional must now be present
+  NewRequiredEntry(String),
+  /// Entry is no longer allowed
+  RemovedEntry(String),
+  /// Entry may occur fewer times than before. Bounds are written like CDDL
+  /// occurrence indicators, e.g. `0*1` for `?`.
+  NarrowedOccurrence {
+    /// Key or type of the entry
+    entry: String,
+    /// Old bounds
+    old: String,
+    /// New bounds
+    new: String,
+  },
+  /// Type was replaced by one that doesn't accept all of its values
+  ChangedType {
+    /// Old type
+    old: String,
+    /// New type
+    new: String,
+  },
+}
+
+impl fmt::Display for BreakingChangeKind {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      BreakingChangeKind::RemovedRule => write!(f, "rule was removed"),
+      BreakingChangeKind::ChangedRoot(root) => write!(f, "root rule was changed to '{}'", root),
+      BreakingChangeKind::RemovedChoice(choice) => {
+        write!(f, "choice {} is no longer accepted", choice)
+      }
+      BreakingChangeKind::NarrowedRange { old, new } => {
+        write!(f, "range {} was narrowed to {}", old, new)
+      }
+      BreakingChangeKind::NewRequiredEntry(entry) => write!(f, "entry {} is required", entry),
+      BreakingChangeKind::RemovedEntry(entry) => write!(f, "entry {} was removed", entry),
+      BreakingChangeKind::NarrowedOccurrence { entry, old, new } => write!(
+        f,
+        "occurrence of entry {} was narrowed from {} to {}",
+        entry, old, new
+      ),
+      BreakingChangeKind::ChangedType { old, new } => {
+        write!(f, "type {} was changed to {}", old, new)
+      }
+    }
+  }
+}
+
+/// Compares two versions of a CDDL definition, returning the changes that
+/// prevent the new version from accepting everything the old one accepts. An
+/// empty result means the new version is backwards compatible, which makes
+/// this suitable for gating the evolution of a protocol in CI.
+///
+/// Rules are compared by name, and references to other rules are compared by
+/// name too since the rules they refer to are compared on their own. The check
+/// is conservative: types it can't relate, such as those with differing
+/// controls or generic parameters, are reported as changed.
+///
+/// # Example
+///
+/// ```
+/// use cddl::{compat::*, lexer_from_str, parser::cddl_from_str};
+///
+/// let old = "person = { name: tstr, ? age: 0..150 }";
+/// let new = "person = { name: tstr, age: 0..120, ? email: tstr }";
+///
+/// let old = cddl_from_str(&mut lexer_from_str(old), old, true).unwrap();
+/// let new = cddl_from_str(&mut lexer_from_str(new), new, true).unwrap();
+///
+/// let changes = check(&old, &new);
+/// assert_eq!(changes.len(), 2);
+/// assert_eq!(changes[0].to_string(), "person: entry age is required");
+/// assert_eq!(changes[1].to_string(), "person/age: range 0..150 was narrowed to 0..120");
+/// ```
+pub fn check(old: &CDDL, new: &CDDL) -> Vec<BreakingChange> {
+  let mut changes = Vec::new();
+  let mut names: Vec<&str> = Vec::new();
+
+  for rule in old.rules.iter() {
+    let name = rule.name_ident();
+    if names.contains(&name) {
+      continue;
+    }
+    names.push(name);
+
+    let mut checker = Checker::default();
+    compare_rules(&mut checker, old, new, name);
+
+    changes.extend(
+      checker
+        .changes
+        .into_iter()
+        .map(|(path, kind)| BreakingChange {
+          rule: name.to_string(),
+          path,
+          kind,
+        }),
+    );
+  }
+
+  if let (Some(old_root), Some(new_root)) = (old.rules.first(), new.rules.first()) {
+    if old_root.name_ident() != new_root.name_ident() {
+      changes.push(BreakingChange {
+        rule: old_root.name(),
+        path: String::new(),
+        kind: BreakingChangeKind::ChangedRoot(new_root.name()),
+      });
+    }
+  }
+
+  changes
+}
+
+// Definition of a rule with the choices added by its alternates merged in
+pub(crate) enum Definition<'a> {
+  Type(Type<'a>),
+  Group(Group<'a>),
+}
+
+pub(crate) fn definition<'a>(cddl: &CDDL<'a>, name: &str) -> Option<(Definition<'a>, String)> {
+  let mut rules = cddl.rules_named(name);
+  let first = rules.next()?;
+
+  let mut definition = match first {
+    Rule::Type { rule, .. } => Definition::Type(rule.value.clone()),
+    Rule::Group { rule, .. } => Definition::Group(group_of_entry(&rule.entry)),
+  };
+
+  let generic_params = match first {
+    Rule::Type { rule, .. } => rule.generic_param.as_ref().map(|gp| gp.to_string()),
+    Rule::Group { rule, .. } => rule.generic_param.as_ref().map(|gp| gp.to_string()),
+  };
+
+  for rule in rules {
+    match (&mut definition, rule) {
+      (Definition::Type(t), Rule::Type { rule, .. }) => t
+        .type_choices
+        .extend(rule.value.type_choices.iter().cloned()),
+      (Definition::Group(g), Rule::Group { rule, .. }) => g
+        .group_choices
+        .extend(group_of_entry(&rule.entry).group_choices),
+      _ => {}
+    }
+  }
+
+  Some((definition, generic_params.unwrap_or_default()))
+}
+
+// Group rules assign either a parenthesized group or a single entry
+fn group_of_entry<'a>(entry: &GroupEntry<'a>) -> Group<'a> {
+  if let GroupEntry::InlineGroup {
+    occur: None, group, ..
+  } = entry
+  {
+    return group.clone();
+  }
+
+  Group {
+    group_choices: vec![GroupChoice {
+      group_entries: vec![(entry.clone(), false)],
+      span: (0, 0, 0),
+    }],
+    span: (0, 0, 0),
+  }
+}
+
+fn compare_rules(checker: &mut Checker, old: &CDDL, new: &CDDL, name: &str) {
+  let (old_def, old_params) = match definition(old, name) {
+    Some(d) => d,
+    None => return,
+  };
+  let (new_def, new_params) = match definition(new, name) {
+    Some(d) => d,
+    None => return checker.report(BreakingChangeKind::RemovedRule),
+  };
+
+  // Generic rules can't be related without knowing their arguments
+  if !old_params.is_empty() || !new_params.is_empty() {
+    let old_text = definition_text(&old_def, &old_params);
+    let new_text = definition_text(&new_def, &new_params);
+
+    if old_text != new_text {
+      checker.report(BreakingChangeKind::ChangedType {
+        old: old_text,
+        new: new_text,
+      });
+    }
+
+    return;
+  }
+
+  match (&old_def, &new_def) {
+    (Definition::Type(o), Definition::Type(n)) => checker.compare_type(o, n),
+    (Definition::Group(o), Definition::Group(n)) => checker.compare_group(o, n, false),
+    _ => checker.report(BreakingChangeKind::ChangedType {
+      old: definition_text(&old_def, &old_params),
+      new: definition_text(&new_def, &new_params),
+    }),
+  }
+}
+
+pub(crate) fn definition_text(definition: &Definition, generic_params: &str) -> String {
+  match definition {
+    Definition::Type(t) => format!("{}{}", generic_params, t),
+    Definition::Group(g) => format!("{}({})", generic_params, g),
+  }
+}
+
+#[derive(Default)]
+struct Checker {
+  path: String,
+  changes: Vec<(String, BreakingChangeKind)>,
+}
+
+impl Checker {
+  fn report(&mut self, kind: BreakingChangeKind) {
+    self.changes.push((self.path.clone(), kind));
+  }
+
+  // Runs a comparison one level deeper in the JSON Pointer, escaping the
+  // segment as required by RFC 6901
+  fn at<F: FnOnce(&mut Self)>(&mut self, segment: &str, f: F) {
+    let len = self.path.len();
+    self.path.push('/');
+    self
+      .path
+      .push_str(&segment.replace('~', "~0").replace('/', "~1"));
+
+    f(self);
+
+    self.path.truncate(len);
+  }
+
+  fn compare_type(&mut self, old: &Type, new: &Type) {
+    if let ([o], [n]) = (&old.type_choices[..], &new.type_choices[..]) {
+      return self.compare_type1(o, n);
+    }
+
+    for o in old.type_choices.iter() {
+      if new.type_choices.iter().any(|n| accepts(o, n)) {
+        continue;
+      }
+
+      // Report what changed within the choice if it still has a counterpart
+      // of the same shape, otherwise it's been dropped altogether
+      match new.type_choices.iter().find(|n| same_shape(o, n)) {
+        Some(n) => self.compare_type1(o, n),
+        None => self.report(BreakingChangeKind::RemovedChoice(o.to_string())),
+      }
+    }
+  }
+
+  fn compare_type1(&mut self, old: &Type1, new: &Type1) {
+    if accepts_scalar(old, new) {
+      return;
+    }
+
+    if let (Some(o), Some(n)) = (parenthesized(old), parenthesized(new)) {
+      return self.compare_type(o, n);
+    }
+    if let Some(o) = parenthesized(old) {
+      return self.compare_type(o, &type_of(new));
+    }
+    if let Some(n) = parenthesized(new) {
+      return self.compare_type(&type_of(old), n);
+    }
+
+    match (&old.operator, &new.operator, &old.type2, &new.type2) {
+      (Some((RangeCtlOp::RangeOp { .. }, _)), Some((RangeCtlOp::RangeOp { .. }, _)), ..) => self
+        .report(BreakingChangeKind::NarrowedRange {
+          old: old.to_string(),
+          new: new.to_string(),
+        }),
+      (None, None, Type2::Map { group: o, .. }, Type2::Map { group: n, .. }) => {
+        self.compare_group(o, n, false)
+      }
+      (None, None, Type2::Array { group: o, .. }, Type2::Array { group: n, .. }) => {
+        self.compare_group(o, n, true)
+      }
+      (
+        None,
+        None,
+        Type2::TaggedData { tag: ot, t: o, .. },
+        Type2::TaggedData { tag: nt, t: n, .. },
+      ) if ot == nt => self.compare_type(o, n),
+      _ => self.report(BreakingChangeKind::ChangedType {
+        old: old.to_string(),
+        new: new.to_string(),
+      }),
+    }
+  }
+
+  // Compares the groups of maps or arrays, or of group rules which are
+  // compared like maps since their entries are typically keyed
+  fn compare_group(&mut self, old: &Group, new: &Group, is_array: bool) {
+    if let ([o], [n]) = (&old.group_choices[..], &new.group_choices[..]) {
+      return self.compare_group_choice(o, n, is_array);
+    }
+
+    for o in old.group_choices.iter() {
+      let accepted = new.group_choices.iter().any(|n| {
+        let mut probe = Checker::default();
+        probe.compare_group_choice(o, n, is_array);
+        probe.changes.is_empty()
+      });
+      if accepted {
+        continue;
+      }
+
+      match &new.group_choices[..] {
+        [n] => self.compare_group_choice(o, n, is_array),
+        _ => self.report(BreakingChangeKind::RemovedChoice(format!("({})", o))),
+      }
+    }
+  }
+
+  fn compare_group_choice(&mut self, old: &GroupChoice, new: &GroupChoice, is_array: bool) {
+    let old_entries = old.group_entries.iter().map(|(ge, _)| Entry::from(ge));
+    let new_entries = new.group_entries.iter().map(|(ge, _)| Entry::from(ge));
+
+    if is_array {
+      self.compare_array_entries(
+        &old_entries.collect::<Vec<_>>(),
+        &new_entries.collect::<Vec<_>>(),
+      )
+    } else {
+      self.compare_map_entries(
+        &old_entries.collect::<Vec<_>>(),
+        &new_entries.collect::<Vec<_>>(),
+      )
+    }
+  }
+
+  fn compare_map_entries(&mut self, old: &[Entry], new: &[Entry]) {
+    // Keys that aren't literals may still match those that were removed
+    let has_computed_keys = new.iter().any(|e| e.key.is_none() && e.is_keyed);
+
+    for o in old.iter() {
+      let n = new.iter().find(|n| match (&o.key, &n.key) {
+        (Some(ok), Some(nk)) => ok == nk,
+        (None, None) => o.text == n.text,
+        _ => false,
+      });
+
+      match n {
+        Some(n) => self.compare_entry(o, n),
+        None if o.key.is_some() && has_computed_keys => {}
+        None => self.report(BreakingChangeKind::RemovedEntry(o.name())),
+      }
+    }
+
+    for n in new.iter() {
+      let is_new = !old.iter().any(|o| match (&o.key, &n.key) {
+        (Some(ok), Some(nk)) => ok == nk,
+        (None, None) => o.text == n.text,
+        _ => false,
+      });
+
+      if is_new && n.bounds.0 > 0 {
+        self.report(BreakingChangeKind::NewRequiredEntry(n.name()));
+      }
+    }
+  }
+
+  fn compare_array_entries(&mut self, old: &[Entry], new: &[Entry]) {
+    for (idx, (o, n)) in old.iter().zip(new.iter()).enumerate() {
+      if o.key != n.key || (o.entry_type.is_none() && o.text != n.text) {
+        self.at(&idx.to_string(), |c| {
+          c.report(BreakingChangeKind::ChangedType {
+            old: o.text.clone(),
+            new: n.text.clone(),
+          })
+        });
+        continue;
+      }
+
+      self.compare_entry_at(o, n, &idx.to_string());
+    }
+
+    for o in old.iter().skip(new.len()) {
+      self.report(BreakingChangeKind::RemovedEntry(o.name()));
+    }
+
+    for n in new.iter().skip(old.len()) {
+      if n.bounds.0 > 0 {
+        self.report(BreakingChangeKind::NewRequiredEntry(n.name()));
+      }
+    }
+  }
+
+  fn compare_entry(&mut self, old: &Entry, new: &Entry) {
+    let segment = old.key.clone().unwrap_or_else(|| old.text.clone());
+
+    self.compare_entry_at(old, new, &segment)
+  }
+
+  fn compare_entry_at(&mut self, old: &Entry, new: &Entry, segment: &str) {
+    let ((old_lower, old_upper), (new_lower, new_upper)) = (old.bounds, new.bounds);
+
+    if old_lower == 0 && new_lower > 0 {
+      self.report(BreakingChangeKind::NewRequiredEntry(old.name()));
+    } else if new_lower > old_lower || is_below(new_upper, old_upper) {
+      self.report(BreakingChangeKind::NarrowedOccurrence {
+        entry: old.name(),
+        old: bounds_text(old.bounds),
+        new: bounds_text(new.bounds),
+      });
+    }
+
+    if let (Some(o), Some(n)) = (&old.entry_type, &new.entry_type) {
+      self.at(segment, |c| c.compare_type(o, n));
+    }
+  }
+}
+
+// Group entry reduced to what's needed to compare it
+pub(crate) struct Entry<'a> {
+  // Literal key of a map entry
+  pub(crate) key: Option<String>,
+  // Whether the entry has a member key, literal or not
+  pub(crate) is_keyed: bool,
+  pub(crate) bounds: (usize, Option<usize>),
+  // Type of the entry, or the name of the type or group it refers to
+  pub(crate) entry_type: Option<Type<'a>>,
+  // Entry without its occurrence indicator
+  pub(crate) text: String,
+}
+
+impl<'a> Entry<'a> {
+  pub(crate) fn name(&self) -> String {
+    self.key.clone().unwrap_or_else(|| self.text.clone())
+  }
+}
+
+impl<'a, 'b> From<&'b GroupEntry<'a>> for Entry<'a> {
+  fn from(ge: &'b GroupEntry<'a>) -> Self {
+    match ge {
+      GroupEntry::ValueMemberKey { ge, .. } => {
+        let text = match &ge.member_key {
+          Some(mk) => format!("{} {}", mk, ge.entry_type),
+          None => ge.entry_type.to_string(),
+        };
+
+        Entry {
+          key: ge.member_key.as_ref().and_then(literal_key),
+          is_keyed: ge.member_key.is_some(),
+          bounds: bounds(ge.occur.as_ref()),
+          entry_type: Some(ge.entry_type.clone()),
+          text,
+        }
+      }
+      GroupEntry::TypeGroupname { ge, .. } => Entry {
+        key: None,
+        is_keyed: false,
+        bounds: bounds(ge.occur.as_ref()),
+        entry_type: Some(Type {
+          type_choices: vec![Type1 {
+            type2: Type2::Typename {
+              ident: ge.name.clone(),
+              generic_arg: ge.generic_arg.clone(),
+              span: ge.name.span,
+            },
+            operator: None,
+            span: ge.name.span,
+          }],
+          span: ge.name.span,
+        }),
+        text: match &ge.generic_arg {
+          Some(ga) => format!("{}{}", ge.name, ga),
+          None => ge.name.to_string(),
+        },
+      },
+      GroupEntry::InlineGroup { occur, group, .. } => Entry {
+        key: None,
+        is_keyed: false,
+        bounds: bounds(occur.as_ref()),
+        entry_type: None,
+        text: format!("({})", group),
+      },
+    }
+  }
+}
+
+// Returns the key of a map entry if it's a literal, so that `a: int`,
+// `"a": int` and `"a" => int` are all keyed by `a`
+pub(crate) fn literal_key(mk: &MemberKey) -> Option<String> {
+  match mk {
+    MemberKey::Bareword { ident, .. } => Some(ident.ident.to_string()),
+    MemberKey::Value { value, .. } => match value {
+      crate::token::Value::TEXT(t) => Some(t.to_string()),
+      crate::token::Value::INT(i) => Some(i.to_string()),
+      crate::token::Value::UINT(u) => Some(u.to_string()),
+      _ => Some(value.to_string()),
+    },
+    MemberKey::Type1 { t1, .. } if t1.operator.is_none() => match &t1.type2 {
+      Type2::TextValue { value, .. } => Some(value.to_string()),
+      Type2::IntValue { value, .. } => Some(value.to_string()),
+      Type2::UintValue { value, .. } => Some(value.to_string()),
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+fn bounds(occur: Option<&Occur>) -> (usize, Option<usize>) {
+  match occur {
+    None => (1, Some(1)),
+    Some(Occur::Optional(_)) => (0, Some(1)),
+    Some(Occur::ZeroOrMore(_)) => (0, None),
+    Some(Occur::OneOrMore(_)) => (1, None),
+    Some(Occur::Exact { lower, upper, .. }) => (lower.unwrap_or(0), *upper),
+  }
+}
+
+pub(crate) fn bounds_text(bounds: (usize, Option<usize>)) -> String {
+  match bounds.1 {
+    Some(upper) => format!("{}*{}", bounds.0, upper),
+    None => format!("{}*", bounds.0),
+  }
+}
+
+// Whether an upper bound is lower than another, where None is unbounded
+fn is_below(upper: Option<usize>, other: Option<usize>) -> bool {
+  match (upper, other) {
+    (Some(u), Some(o)) => u < o,
+    (Some(_), None) => true,
+    (None, _) => false,
+  }
+}
+
+fn parenthesized<'a, 'b>(t1: &'b Type1<'a>) -> Option<&'b Type<'a>> {
+  if let (None, Type2::ParenthesizedType { pt, .. }) = (&t1.operator, &t1.type2) {
+    return Some(pt);
+  }
+
+  None
+}
+
+pub(crate) fn type_of<'a>(t1: &Type1<'a>) -> Type<'a> {
+  Type {
+    type_choices: vec![t1.clone()],
+    span: t1.span,
+  }
+}
+
+// Whether every value of the old type choice is accepted by the new one
+fn accepts(old: &Type1, new: &Type1) -> bool {
+  let mut probe = Checker::default();
+  probe.compare_type1(old, new);
+
+  probe.changes.is_empty()
+}
+
+// Whether every value of the old type is accepted by the new one
+pub(crate) fn accepts_type(old: &Type, new: &Type) -> bool {
+  let mut probe = Checker::default();
+  probe.compare_type(old, new);
+
+  probe.changes.is_empty()
+}
+
+// Whether everything matched by the old group is matched by the new one
+pub(crate) fn accepts_group(old: &Group, new: &Group, is_array: bool) -> bool {
+  let mut probe = Checker::default();
+  probe.compare_group(old, new, is_array);
+
+  probe.changes.is_empty()
+}
+
+// Whether two type choices describe the same kind of structure, in which case
+// the changes between them are reported rather than the old one being removed
+fn same_shape(old: &Type1, new: &Type1) -> bool {
+  match (&old.operator, &new.operator, &old.type2, &new.type2) {
+    (Some((RangeCtlOp::RangeOp { .. }, _)), Some((RangeCtlOp::RangeOp { .. }, _)), ..) => {
+      range_of(old).is_some() && range_of(new).is_some()
+    }
+    (None, None, Type2::Map { .. }, Type2::Map { .. })
+    | (None, None, Type2::Array { .. }, Type2::Array { .. }) => true,
+    (None, None, Type2::TaggedData { tag: o, .. }, Type2::TaggedData { tag: n, .. }) => o == n,
+    _ => false,
+  }
+}
+
+fn accepts_scalar(old: &Type1, new: &Type1) -> bool {
+  if old.to_string() == new.to_string() {
+    return true;
+  }
+
+  match (&old.operator, &new.operator) {
+    (_, None) => {
+      if is_any(&new.type2) {
+        return true;
+      }
+
+      match &old.operator {
+        // Dropping a control only widens the type
+        Some((RangeCtlOp::CtlOp { .. }, _)) | None => accepts_type2(&old.type2, &new.type2),
+        Some((RangeCtlOp::RangeOp { .. }, _)) => match (range_of(old), &new.type2) {
+          (Some(range), Type2::Typename { ident, .. }) => is_subtype(range.kind(), ident.ident),
+          _ => false,
+        },
+      }
+    }
+    (None, Some((RangeCtlOp::RangeOp { .. }, _))) => match (number_of(&old.type2), range_of(new)) {
+      (Some(n), Some(range)) => range.contains(n),
+      _ => false,
+    },
+    (Some((RangeCtlOp::RangeOp { .. }, _)), Some((RangeCtlOp::RangeOp { .. }, _))) => {
+      match (range_of(old), range_of(new)) {
+        (Some(o), Some(n)) => n.contains_range(&o),
+        _ => false,
+      }
+    }
+    _ => false,
+  }
+}
+
+fn accepts_type2(old: &Type2, new: &Type2) -> bool {
+  if let Type2::Typename { ident: n, .. } = new {
+    if let Type2::Typename { ident: o, .. } = old {
+      return is_subtype(o.ident, n.ident);
+    }
+
+    let kind = match old {
+      Type2::UintValue { .. } => "uint",
+      Type2::IntValue { value, .. } if *value >= 0 => "uint",
+      Type2::IntValue { .. } => "nint",
+      Type2::FloatValue { .. } => "float",
+      Type2::TextValue { .. } => "tstr",
+      Type2::UTF8ByteString { .. } | Type2::B16ByteString { .. } | Type2::B64ByteString { .. } => {
+        "bstr"
+      }
+      _ => return false,
+    };
+
+    return is_subtype(kind, n.ident);
+  }
+
+  old.to_string() == new.to_string()
+}
+
+fn is_any(t2: &Type2) -> bool {
+  if let Type2::Any(_) = t2 {
+    return true;
+  }
+  if let Type2::Typename { ident, .. } = t2 {
+    return ident.ident == "any";
+  }
+
+  false
+}
+
+// Whether every value of a prelude type, or a type of literal, is a value of
+// another prelude type
+fn is_subtype(sub: &str, sup: &str) -> bool {
+  let (sub, sup) = (synonym(sub), synonym(sup));
+
+  sub == sup || supertypes(sub).iter().any(|s| is_subtype(s, sup))
+}
+
+fn synonym(name: &str) -> &str {
+  match name {
+    "text" => "tstr",
+    "bytes" => "bstr",
+    "null" => "nil",
+    _ => name,
+  }
+}
+
+fn supertypes(name: &str) -> &'static [&'static str] {
+  match name {
+    "uint" => &["int", "unsigned"],
+    "nint" => &["int"],
+    "int" => &["integer", "number"],
+    "biguint" => &["bigint", "unsigned"],
+    "bignint" => &["bigint"],
+    "bigint" => &["integer"],
+    "float16" => &["float16-32"],
+    "float32" => &["float16-32", "float32-64"],
+    "float64" => &["float32-64"],
+    "float16-32" | "float32-64" => &["float"],
+    "float" => &["number"],
+    "true" | "false" => &["bool"],
+    _ => &[],
+  }
+}
+
+// Numeric range whose bounds are literals
+struct Range {
+  lower: f64,
+  upper: f64,
+  is_inclusive: bool,
+  is_float: bool,
+}
+
+impl Range {
+  // Prelude type of the range's values
+  fn kind(&self) -> &'static str {
+    if self.is_float {
+      "float"
+    } else if self.lower >= 0.0 {
+      "uint"
+    } else {
+      "int"
+    }
+  }
+
+  fn contains(&self, (value, is_float): (f64, bool)) -> bool {
+    is_float == self.is_float
+      && value >= self.lower
+      && (value < self.upper || (self.is_inclusive && value == self.upper))
+  }
+
+  fn contains_range(&self, other: &Range) -> bool {
+    other.is_float == self.is_float
+      && other.lower >= self.lower
+      && (other.upper < self.upper
+        || (other.upper == self.upper && (self.is_inclusive || !other.is_inclusive)))
+  }
+}
+
+fn range_of(t1: &Type1) -> Option<Range> {
+  if let Some((RangeCtlOp::RangeOp { is_inclusive, .. }, upper)) = &t1.operator {
+    let (lower, lower_is_float) = number_of(&t1.type2)?;
+    let (upper, upper_is_float) = number_of(upper)?;
+
+    return Some(Range {
+      lower,
+      upper,
+      is_inclusive: *is_inclusive,
+      is_float: lower_is_float || upper_is_float,
+    });
+  }
+
+  None
+}
+
+// Returns a numeric literal and whether it's a float
+fn number_of(t2: &Type2) -> Option<(f64, bool)> {
+  match t2 {
+    Type2::UintValue { value, .. } => Some((*value as f64, false)),
+    Type2::IntValue { value, .. } => Some((*value as f64, false)),
+    Type2::FloatValue { value, .. } => Some((*value, true)),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+  use super::*;
+  use crate::{lexer::Lexer, parser::cddl_from_str};
+
+  fn changes(old: &str, new: &str) -> Vec<String> {
+    let old_cddl = cddl_from_str(&mut Lexer::new(old), old, true).unwrap();
+    let new_cddl = cddl_from_str(&mut Lexer::new(new), new, true).unwrap();
+
+    check(&old_cddl, &new_cddl)
+      .iter()
+      .map(|c| c.to_string())
+      .collect()
+  }
+
+  #[test]
+  fn verify_compatible() {
+    let old = r#"
+      message = {
+        id: uint,
+        kind: "ping" / "pong",
+        ? payload: [* bytes],
+        sent: 0..100,
+        ratio: float32,
+        meta: meta,
+      }
+
+      meta = (source: tstr .size 10)
+    "#;
+    let new = r#"
+      message = {
+        id: int,
+        kind: "ping" / "pong" / "reset",
+        ? payload: [* bstr],
+        sent: 0..1000 / -1,
+        ratio: float,
+        ? trace: tstr,
+        meta: meta,
+      }
+
+      meta = (source: text, ? region: tstr)
+    "#;
+
+    assert!(changes(old, new).is_empty(), "{:?}", changes(old, new));
+  }
+
+  #[test]
+  fn verify_breaking_changes() {
+    let old = r#"
+      message = {
+        id: int,
+        kind: "ping" / "pong",
+        ? payload: [uint, * tstr],
+        sent: 0..100,
+        tags: [* tstr],
+        legacy: bool,
+      }
+
+      header = [version: uint]
+    "#;
+    let new = r#"
+      message = {
+        id: uint,
+        kind: "ping",
+        payload: [uint],
+        sent: 0...100,
+        tags: [? tstr],
+        trace: tstr,
+      }
+    "#;
+
+    assert_eq!(
+      changes(old, new),
+      vec![
+        "message/id: type int was changed to uint",
+        "message/kind: choice \"pong\" is no longer accepted",
+        "message: entry payload is required",
+        "message/payload: entry tstr was removed",
+        "message/sent: range 0..100 was narrowed to 0...100",
+        "message/tags: occurrence of entry tstr was narrowed from 0* to 0*1",
+        "message: entry legacy was removed",
+        "message: entry trace is required",
+        "header: rule was removed",
+      ]
+    );
+  }
+
+  #[test]
+  fn verify_changed_root() {
+    assert_eq!(
+      changes("a = b\nb = int", "b = int\na = b"),
+      vec!["a: root rule was changed to 'b'"]
+    );
+  }
+}
@@ -0,0 +1,649 @@
+use super::{
+  ast::*,
+  prelude::{is_extension_type, is_prelude_type},
+  visitor::*,
+};
+use std::{fmt, ptr, result};
+
+#[cfg(feature = "std")]
+use std::collections::BTreeSet;
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+  collections::BTreeSet,
+  string::{String, ToString},
+  vec::Vec,
+};
+
+/// Semantic issue found when compiling a CDDL definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+  /// Kind of issue
+  pub kind: DiagnosticKind,
+  /// Span of the rule, reference or type the issue applies to
+  pub span: Span,
+}
+
+/// Kinds of semantic issues found when compiling a CDDL definition
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiagnosticKind {
+  /// Reference to a name that's neither defined by a rule, a generic parameter
+  /// nor the standard prelude
+  UndefinedReference(String),
+  /// Rule defined more than once without extending it via `/=` or `//=`
+  DuplicateRule(String),
+  /// Rule that isn't referenced by any other rule. The first type rule is
+  /// exempt since it defines the root type.
+  UnusedRule(String),
+  /// Reference to a rule with a different number of generic arguments than
+  /// the rule has generic parameters
+  GenericArity {
+    /// Name of the referenced rule
+    name: String,
+    /// Number of generic parameters defined by the rule
+    expected: usize,
+    /// Number of generic arguments given by the reference
+    found: usize,
+  },
+  /// Bound of a range that isn't a numeric value, nor the name of a rule
+  /// defined as one or a choice of numeric values, e.g. `max-port` in
+  /// `port = 1..max-port` with `max-port = tstr`
+  NonConstantRangeBound(String),
+  /// `.within` control whose target can take values outside of the controller
+  WithinViolation {
+    /// Target type
+    target: String,
+    /// Controller type
+    controller: String,
+  },
+}
+
+impl Diagnostic {
+  /// Returns whether or not the issue makes the definition invalid, as
+  /// opposed to merely being suspicious
+  pub fn is_error(&self) -> bool {
+    if let DiagnosticKind::UnusedRule(_) = self.kind {
+      return false;
+    }
+
+    true
+  }
+}
+
+impl fmt::Display for Diagnostic {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match &self.kind {
+      DiagnosticKind::UndefinedReference(name) => write!(f, "undefined rule '{}'", name),
+      DiagnosticKind::DuplicateRule(name) => write!(
+        f,
+        "rule '{}' already defined. Use /= or //= to add choices to an existing rule",
+        name
+      ),
+      DiagnosticKind::UnusedRule(name) => write!(f, "rule '{}' is never used", name),
+      DiagnosticKind::GenericArity {
+        name,
+        expected,
+        found,
+      } => write!(
+        f,
+        "rule '{}' expects {} generic argument(s) but {} were given",
+        name, expected, found
+      ),
+      DiagnosticKind::NonConstantRangeBound(bound) => write!(
+        f,
+        "range bound '{}' isn't a numeric value or a rule defined as one",
+        bound
+      ),
+      DiagnosticKind::WithinViolation { target, controller } => write!(
+        f,
+        "'{}' isn't a subset of '{}' as required by .within",
+        target, controller
+      ),
+    }
+  }
+}
+
+impl<'a> CDDL<'a> {
+  /// Checks the whole definition for semantic issues, returning every issue
+  /// found rather than stopping at the first. Issues that aren't errors, such
+  /// as unused rules, are returned on success.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{compiler::DiagnosticKind, lexer_from_str, parser::cddl_from_str};
+  ///
+  /// let input = r#"person = { name: tstr, address: addr }"#;
+  /// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+  ///
+  /// let diagnostics = cddl.compile().unwrap_err();
+  /// assert_eq!(
+  ///   diagnostics[0].kind,
+  ///   DiagnosticKind::UndefinedReference("addr".into())
+  /// );
+  /// ```
+  pub fn compile(&self) -> result::Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+    let mut diagnostics = Vec::new();
+
+    for rule in self.rules.iter() {
+      let name = rule_name(rule);
+
+      if !rule.is_choice_alternate()
+        && self
+          .rules_named(name)
+          .take_while(|r| !ptr::eq(*r, rule))
+          .any(|r| !r.is_choice_alternate())
+      {
+        diagnostics.push(Diagnostic {
+          kind: DiagnosticKind::DuplicateRule(name.to_string()),
+          span: rule.span(),
+        });
+      }
+    }
+
+    let mut references = References {
+      cddl: self,
+      rule: "",
+      generic_params: Vec::new(),
+      used: BTreeSet::new(),
+      diagnostics,
+    };
+    walk_cddl(&mut references, self);
+
+    let root = self.rules.iter().find_map(|r| match r {
+      Rule::Type { rule, .. } => Some(rule.name.ident),
+      _ => None,
+    });
+
+    let mut diagnostics = references.diagnostics;
+    for rule in self.rules.iter() {
+      let name = rule_name(rule);
+
+      // Only the first definition of a rule is reported
+      if Some(name) != root
+        && !references.used.contains(name)
+        && self.rule(name).into_iter().any(|r| ptr::eq(r, rule))
+      {
+        diagnostics.push(Diagnostic {
+          kind: DiagnosticKind::UnusedRule(name.to_string()),
+          span: rule.span(),
+        });
+      }
+    }
+
+    if diagnostics.iter().any(Diagnostic::is_error) {
+      return Err(diagnostics);
+    }
+
+    Ok(diagnostics)
+  }
+}
+
+impl<'a> CDDL<'a> {
+  // Returns the numeric values a bound of a range stands for, following the
+  // rules it names until each choice is a numeric literal, e.g. [65535] for
+  // max-port in 1..max-port with max-port = 65535. Returns None if the bound
+  // isn't constant.
+  pub(crate) fn range_bound_values<'b>(
+    &'b self,
+    bound: &'b Type2<'a>,
+  ) -> Option<Vec<&'b Type2<'a>>> {
+    let mut values = Vec::new();
+    if self.collect_range_bound_values(bound, 0, &mut values) {
+      return Some(values);
+    }
+
+    None
+  }
+
+  fn collect_range_bound_values<'b>(
+    &'b self,
+    bound: &'b Type2<'a>,
+    depth: usize,
+    values: &mut Vec<&'b Type2<'a>>,
+  ) -> bool {
+    // Rules defined in terms of each other never resolve to a value
+    if depth > 16 {
+      return false;
+    }
+
+    let collect_choices = |t: &'b Type<'a>, values: &mut Vec<&'b Type2<'a>>| {
+      t.type_choices.iter().all(|tc| {
+        tc.operator.is_none() && self.collect_range_bound_values(&tc.type2, depth + 1, values)
+      })
+    };
+
+    match bound {
+      Type2::IntValue { .. } | Type2::UintValue { .. } | Type2::FloatValue { .. } => {
+        values.push(bound);
+        true
+      }
+      Type2::ParenthesizedType { pt, .. } => collect_choices(pt, values),
+      Type2::Typename {
+        ident,
+        generic_arg: None,
+        ..
+      } => {
+        let mut is_defined = false;
+        for rule in self.rules_named(ident.ident) {
+          match rule {
+            Rule::Type { rule, .. } if rule.generic_param.is_none() => {
+              if !collect_choices(&rule.value, values) {
+                return false;
+              }
+            }
+            _ => return false,
+          }
+
+          is_defined = true;
+        }
+
+        is_defined
+      }
+      _ => false,
+    }
+  }
+}
+
+fn rule_name<'a>(rule: &Rule<'a>) -> &'a str {
+  match rule {
+    Rule::Type { rule, .. } => rule.name.ident,
+    Rule::Group { rule, .. } => rule.name.ident,
+  }
+}
+
+// Collects references to rules, checking that each refers to a defined rule
+// with the matching number of generic arguments
+struct References<'a, 'b> {
+  cddl: &'b CDDL<'a>,
+  // Name of the rule being walked
+  rule: &'a str,
+  // Generic parameters of the rule being walked
+  generic_params: Vec<&'a str>,
+  // Names of the rules referenced by other rules
+  used: BTreeSet<&'a str>,
+  diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a, 'b> References<'a, 'b> {
+  fn check_reference(&mut self, ident: &Identifier<'a>, generic_arg: Option<&GenericArg<'a>>) {
+    if self.generic_params.contains(&ident.ident) {
+      return;
+    }
+
+    // Sockets may be left without any plugs
+    if ident.socket.is_some() {
+      if ident.ident != self.rule {
+        self.used.insert(ident.ident);
+      }
+
+      return;
+    }
+
+    let found = generic_arg.map_or(0, |ga| ga.args.len());
+
+    let expected = match self.cddl.rule(ident.ident) {
+      Some(rule) => {
+        if ident.ident != self.rule {
+          self.used.insert(ident.ident);
+        }
+
+        let generic_param = match rule {
+          Rule::Type { rule, .. } => rule.generic_param.as_ref(),
+          Rule::Group { rule, .. } => rule.generic_param.as_ref(),
+        };
+
+        generic_param.map_or(0, |gp| gp.params.len())
+      }
+      None if is_prelude_type(ident.ident) || is_extension_type(ident.ident) => 0,
+      None => {
+        self.diagnostics.push(Diagnostic {
+          kind: DiagnosticKind::UndefinedReference(ident.ident.to_string()),
+          span: ident.span,
+        });
+
+        return;
+      }
+    };
+
+    if expected != found {
+      self.diagnostics.push(Diagnostic {
+        kind: DiagnosticKind::GenericArity {
+          name: ident.ident.to_string(),
+          expected,
+          found,
+        },
+        span: ident.span,
+      });
+    }
+  }
+
+  // Returns whether a type can be resolved without knowing the generic
+  // arguments of the rule being walked, and refers to a defined rule if it's a
+  // name. References to undefined rules are reported on their own.
+  fn is_resolvable(&self, t2: &Type2<'a>) -> bool {
+    match t2 {
+      Type2::Typename { ident, .. } => {
+        !self.generic_params.contains(&ident.ident)
+          && (self.cddl.rule(ident.ident).is_some()
+            || is_prelude_type(ident.ident)
+            || is_extension_type(ident.ident))
+      }
+      _ => true,
+    }
+  }
+
+  // Returns the kinds of data a type can take, each paired with the type's
+  // text if it's a literal value rather than a data type. Returns None if they
+  // can't be determined statically.
+  fn kinds(&self, t2: &Type2<'a>, depth: usize) -> Option<Vec<(u16, Option<String>)>> {
+    if depth > 16 {
+      return None;
+    }
+
+    let kind = match t2 {
+      Type2::UintValue { .. } => UINT,
+      Type2::IntValue { value, .. } if *value < 0 => NINT,
+      Type2::IntValue { .. } => UINT,
+      Type2::FloatValue { .. } => FLOAT,
+      Type2::TextValue { .. } => TEXT,
+      Type2::UTF8ByteString { .. } | Type2::B16ByteString { .. } | Type2::B64ByteString { .. } => {
+        BYTES
+      }
+      Type2::Map { .. } => return Some(vec![(MAP, None)]),
+      Type2::Array { .. } => return Some(vec![(ARRAY, None)]),
+      Type2::ParenthesizedType { pt, .. } => return self.type_kinds(pt, depth + 1),
+      Type2::Typename {
+        ident,
+        generic_arg: None,
+        ..
+      } if !self.generic_params.contains(&ident.ident) => {
+        if self.cddl.rule(ident.ident).is_none() {
+          return prelude_kinds(ident.ident).map(|k| vec![(k, None)]);
+        }
+
+        let mut kinds = Vec::new();
+        for rule in self.cddl.rules_named(ident.ident) {
+          match rule {
+            Rule::Type { rule, .. } if rule.generic_param.is_none() => {
+              kinds.append(&mut self.type_kinds(&rule.value, depth + 1)?)
+            }
+            _ => return None,
+          }
+        }
+
+        return Some(kinds);
+      }
+      _ => return None,
+    };
+
+    Some(vec![(kind, Some(t2.to_string()))])
+  }
+
+  fn type_kinds(&self, t: &Type<'a>, depth: usize) -> Option<Vec<(u16, Option<String>)>> {
+    let mut kinds = Vec::new();
+    for tc in t.type_choices.iter() {
+      // Types further constrained by range or control operators are left alone
+      if tc.operator.is_some() {
+        return None;
+      }
+
+      kinds.append(&mut self.kinds(&tc.type2, depth)?);
+    }
+
+    Some(kinds)
+  }
+
+  // Checks that every value the target can take is a value the controller can
+  // take, giving the benefit of the doubt to types that can't be determined
+  // statically
+  fn is_within(&self, target: &Type2<'a>, controller: &Type2<'a>) -> bool {
+    let (target, controller) = match (self.kinds(target, 0), self.kinds(controller, 0)) {
+      (Some(t), Some(c)) => (t, c),
+      _ => return true,
+    };
+
+    target.iter().all(|(tk, tv)| {
+      controller.iter().any(|(ck, cv)| match cv {
+        Some(cv) => tv.as_ref() == Some(cv),
+        None => tk & ck == *tk,
+      })
+    })
+  }
+}
+
+impl<'a, 'b> Visitor<'a> for References<'a, 'b> {
+  fn visit_type_rule(&mut self, tr: &TypeRule<'a>) {
+    self.rule = tr.name.ident;
+    self.generic_params = generic_params(&tr.generic_param);
+
+    walk_type_rule(self, tr);
+  }
+
+  fn visit_group_rule(&mut self, gr: &GroupRule<'a>) {
+    self.rule = gr.name.ident;
+    self.generic_params = generic_params(&gr.generic_param);
+
+    walk_group_rule(self, gr);
+  }
+
+  fn visit_type1(&mut self, t1: &Type1<'a>) {
+    if let Some((RangeCtlOp::RangeOp { .. }, upper)) = &t1.operator {
+      for bound in [&t1.type2, upper].iter() {
+        if !self.is_resolvable(bound) || self.cddl.range_bound_values(bound).is_some() {
+          continue;
+        }
+
+        self.diagnostics.push(Diagnostic {
+          kind: DiagnosticKind::NonConstantRangeBound(bound.to_string()),
+          span: t1.span,
+        });
+      }
+    }
+
+    if let Some((
+      RangeCtlOp::CtlOp {
+        ctrl: ".within", ..
+      },
+      controller,
+    )) = &t1.operator
+    {
+      if !self.is_within(&t1.type2, controller) {
+        self.diagnostics.push(Diagnostic {
+          kind: DiagnosticKind::WithinViolation {
+            target: t1.type2.to_string(),
+            controller: controller.to_string(),
+          },
+          span: t1.span,
+        });
+      }
+    }
+
+    walk_type1(self, t1);
+  }
+
+  fn visit_type2(&mut self, t2: &Type2<'a>) {
+    match t2 {
+      Type2::Typename {
+        ident, generic_arg, ..
+      }
+      | Type2::Unwrap {
+        ident, generic_arg, ..
+      }
+      | Type2::ChoiceFromGroup {
+        ident, generic_arg, ..
+      } => self.check_reference(ident, generic_arg.as_ref()),
+      _ => (),
+    }
+
+    walk_type2(self, t2);
+  }
+
+  fn visit_type_groupname_entry(&mut self, tge: &TypeGroupnameEntry<'a>) {
+    self.check_reference(&tge.name, tge.generic_arg.as_ref());
+
+    walk_type_groupname_entry(self, tge);
+  }
+}
+
+fn generic_params<'a>(gp: &Option<GenericParm<'a>>) -> Vec<&'a str> {
+  gp.iter()
+    .flat_map(|gp| gp.params.iter().map(|p| p.ident))
+    .collect()
+}
+
+const UINT: u16 = 1;
+const NINT: u16 = 1 << 1;
+const FLOAT: u16 = 1 << 2;
+const TEXT: u16 = 1 << 3;
+const BYTES: u16 = 1 << 4;
+const BOOL: u16 = 1 << 5;
+const NULL: u16 = 1 << 6;
+const UNDEFINED: u16 = 1 << 7;
+const MAP: u16 = 1 << 8;
+const ARRAY: u16 = 1 << 9;
+
+// Returns the kinds of data a prelude type can take, if it can be determined
+// from its name alone
+fn prelude_kinds(ident: &str) -> Option<u16> {
+  match ident {
+    "any" => Some(UINT | NINT | FLOAT | TEXT | BYTES | BOOL | NULL | UNDEFINED | MAP | ARRAY),
+    "uint" | "unsigned" => Some(UINT),
+    "nint" => Some(NINT),
+    "int" | "integer" => Some(UINT | NINT),
+    "float16" | "float32" | "float64" | "float16-32" | "float32-64" | "float" => Some(FLOAT),
+    "number" => Some(UINT | NINT | FLOAT),
+    "tstr" | "text" => Some(TEXT),
+    "bstr" | "bytes" => Some(BYTES),
+    "bool" | "true" | "false" => Some(BOOL),
+    "null" | "nil" => Some(NULL),
+    "undefined" => Some(UNDEFINED),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+  use super::*;
+  use crate::{lexer::Lexer, parser::cddl_from_str};
+
+  fn compile(input: &str) -> result::Result<Vec<DiagnosticKind>, Vec<DiagnosticKind>> {
+    let mut lexer = Lexer::new(input);
+    let cddl = cddl_from_str(&mut lexer, input, false).unwrap();
+
+    cddl
+      .compile()
+      .map(|d| d.into_iter().map(|d| d.kind).collect())
+      .map_err(|d| d.into_iter().map(|d| d.kind).collect())
+  }
+
+  #[test]
+  fn verify_valid_definition() {
+    let input = r#"
+      root = { name: tstr, tags: [* tag<tstr>], ? color: &colors, kind: kind, * $$extension }
+      tag<t> = ( key: t, value: t )
+      $$extension //= ( note: tstr )
+      colors = ( red: 1, green: 2 )
+      kind = "a"
+      kind /= "b"
+      small = uint .within int
+    "#;
+
+    assert_eq!(
+      compile(input),
+      Ok(vec![DiagnosticKind::UnusedRule("small".into())])
+    );
+  }
+
+  #[test]
+  fn verify_diagnostics() {
+    let input = r#"
+      root = [pair<int>, pair<int, int>, missing, person<tstr>]
+      pair<k, v> = [k, v]
+      person = { name: tstr }
+      word = tstr .within int
+      letter = "a" .within letters
+      letters = "a" / "b"
+      digit = 5 .within "5"
+    "#;
+
+    assert_eq!(
+      compile(input),
+      Err(vec![
+        DiagnosticKind::GenericArity {
+          name: "pair".into(),
+          expected: 2,
+          found: 1,
+        },
+        DiagnosticKind::UndefinedReference("missing".into()),
+        DiagnosticKind::GenericArity {
+          name: "person".into(),
+          expected: 0,
+          found: 1,
+        },
+        DiagnosticKind::WithinViolation {
+          target: "tstr".into(),
+          controller: "int".into(),
+        },
+        DiagnosticKind::WithinViolation {
+          target: "5".into(),
+          controller: "\"5\"".into(),
+        },
+        DiagnosticKind::UnusedRule("word".into()),
+        DiagnosticKind::UnusedRule("letter".into()),
+        DiagnosticKind::UnusedRule("digit".into()),
+      ])
+    );
+  }
+
+  #[test]
+  fn verify_range_bounds() {
+    let input = r#"
+      root = [port, level, size]
+      port = 1..max-port
+      max-port = 65535
+      level = low .. high
+      low = 0 / 1
+      high = 10
+      size = 0..max-size
+      max-size = tstr
+    "#;
+
+    assert_eq!(
+      compile(input),
+      Err(vec![DiagnosticKind::NonConstantRangeBound(
+        "max-size".into()
+      )])
+    );
+  }
+
+  #[test]
+  fn verify_duplicate_rules() {
+    let mut cddl = CDDL::default();
+    let input = "a = int\nb = a";
+    let mut lexer = Lexer::new(input);
+    for rule in cddl_from_str(&mut lexer, input, false)
+      .unwrap()
+      .rules
+      .into_iter()
+    {
+      cddl.push_rule(rule);
+    }
+
+    let input = "a = tstr";
+    let mut lexer = Lexer::new(input);
+    for rule in cddl_from_str(&mut lexer, input, false)
+      .unwrap()
+      .rules
+      .into_iter()
+    {
+      cddl.push_rule(rule);
+    }
+
+    assert_eq!(
+      cddl.compile().unwrap_err()[0].kind,
+      DiagnosticKind::DuplicateRule("a".into())
+    );
+  }
+}
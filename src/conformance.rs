@@ -0,0 +1,449 @@
+use super::{
+  ast::CDDL,
+  edn,
+  loader::{self, load_schema, FileLoader},
+  validation::{self, cbor::decode_cbor, Validator},
+};
+use std::{
+  fmt, fs,
+  path::{Path, PathBuf},
+};
+
+/// Whether an instance is expected to conform to the schema
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Expectation {
+  /// Instance listed by a `valid` directive
+  Valid,
+  /// Instance listed by an `invalid` directive
+  Invalid,
+}
+
+/// Instance listed by a manifest along with the outcome expected of
+/// validating it
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestCase {
+  /// Path of the instance, resolved against the directory of the manifest
+  pub instance: PathBuf,
+  /// Rule to validate against, or `None` for the root type
+  pub rule: Option<String>,
+  /// Expected outcome
+  pub expectation: Expectation,
+  /// Line of the manifest listing the instance
+  pub line: usize,
+}
+
+/// Parsed `.cddltest` manifest
+///
+/// A manifest is a line-based text file pairing a CDDL file with JSON and
+/// CBOR instances that are expected to conform to it and ones that aren't:
+///
+/// ```text
+/// # Conformance suite for the person schema
+/// cddl person.cddl
+///
+/// valid alice.json
+/// valid bob.cbor
+/// invalid missing-name.json
+///
+/// # Cases after a rule directive are validated against that rule rather than
+/// # the root type
+/// rule address
+/// valid address.diag
+/// invalid address-without-city.json
+/// ```
+///
+/// Each line holds a directive followed by its argument. Blank lines and lines
+/// starting with `#` are ignored. Exactly one `cddl` directive must precede
+/// the cases. Paths are relative to the directory of the manifest, and the
+/// CDDL file may reference other schemas via `;# include` and `;# import`
+/// directives. Instances are decoded according to their extension: `.json`
+/// for JSON, `.cbor` for encoded CBOR and `.diag` or `.edn` for CBOR
+/// diagnostic notation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifest {
+  /// Path of the manifest
+  pub path: PathBuf,
+  /// Path of the CDDL file, resolved against the directory of the manifest
+  pub cddl: PathBuf,
+  /// Instances in the order they're listed
+  pub cases: Vec<TestCase>,
+}
+
+/// Error preventing a manifest from being run
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+  /// Manifest that couldn't be read
+  Io {
+    /// Path of the manifest
+    path: PathBuf,
+    /// Error returned reading it
+    message: String,
+  },
+  /// Manifest that isn't well-formed
+  Syntax {
+    /// Path of the manifest
+    path: PathBuf,
+    /// Line of the error
+    line: usize,
+    /// Description of the error
+    message: String,
+  },
+  /// CDDL file that couldn't be loaded or isn't conformant
+  Schema(loader::Error),
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Error::Io { path, message } => {
+        write!(f, "error reading {}: {}", path.display(), message)
+      }
+      Error::Syntax {
+        path,
+        line,
+        message,
+      } => write!(f, "{}:{}: {}", path.display(), line, message),
+      Error::Schema(e) => write!(f, "{}", e),
+    }
+  }
+}
+
+impl std::error::Error for Error {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      Error::Schema(e) => Some(e),
+      _ => None,
+    }
+  }
+}
+
+impl Manifest {
+  /// Reads and parses the manifest at the given path
+  pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Manifest, Error> {
+    let path = path.as_ref();
+    let input = fs::read_to_string(path).map_err(|e| Error::Io {
+      path: path.to_path_buf(),
+      message: e.to_string(),
+    })?;
+
+    Manifest::parse(&input, path)
+  }
+
+  /// Parses a manifest, resolving the paths it lists against the directory of
+  /// the given path
+  pub fn parse<P: AsRef<Path>>(input: &str, path: P) -> Result<Manifest, Error> {
+    let path = path.as_ref();
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let error = |line: usize, message: String| Error::Syntax {
+      path: path.to_path_buf(),
+      line,
+      message,
+    };
+
+    let mut cddl = None;
+    let mut rule = None;
+    let mut cases = Vec::new();
+
+    for (idx, text) in input.lines().enumerate() {
+      let line = idx + 1;
+      let text = text.trim();
+      if text.is_empty() || text.starts_with('#') {
+        continue;
+      }
+
+      let mut parts = text.splitn(2, char::is_whitespace);
+      let directive = parts.next().unwrap_or_default();
+      let argument = parts.next().map(str::trim).unwrap_or_default();
+      if argument.is_empty() {
+        return Err(error(
+          line,
+          format!("missing argument to \"{}\" directive", directive),
+        ));
+      }
+
+      let expectation = match directive {
+        "cddl" if cddl.is_some() => {
+          return Err(error(line, "more than one \"cddl\" directive".to_string()))
+        }
+        "cddl" if !cases.is_empty() => {
+          return Err(error(
+            line,
+            "\"cddl\" directive must precede the cases".to_string(),
+          ))
+        }
+        "cddl" => {
+          cddl = Some(dir.join(argument));
+          continue;
+        }
+        "rule" => {
+          rule = Some(argument.to_string());
+          continue;
+        }
+        "valid" => Expectation::Valid,
+        "invalid" => Expectation::Invalid,
+        _ => return Err(error(line, format!("unknown directive \"{}\"", directive))),
+      };
+
+      if cddl.is_none() {
+        return Err(error(
+          line,
+          "\"cddl\" directive must precede the cases".to_string(),
+        ));
+      }
+
+      cases.push(TestCase {
+        instance: dir.join(argument),
+        rule: rule.clone(),
+        expectation,
+        line,
+      });
+    }
+
+    Ok(Manifest {
+      path: path.to_path_buf(),
+      cddl: cddl.ok_or_else(|| error(1, "missing \"cddl\" directive".to_string()))?,
+      cases,
+    })
+  }
+
+  /// Loads the CDDL file and validates every instance against it
+  ///
+  /// An instance fails if the outcome of validating it differs from the
+  /// expected one. Instances that can't be read or decoded, and cases naming
+  /// a rule that isn't defined, fail regardless of the expected outcome, so
+  /// that mistakes in a suite aren't mistaken for instances the schema
+  /// rejects.
+  pub fn run(&self) -> Result<Report, Error> {
+    let schema =
+      load_schema(&FileLoader::new(), &self.cddl.to_string_lossy()).map_err(Error::Schema)?;
+    let cddl = schema.cddl();
+
+    let outcomes = self
+      .cases
+      .iter()
+      .map(|case| {
+        let result = match case.instance.extension().and_then(|e| e.to_str()) {
+          Some("json") => read_to_string(&case.instance).and_then(|json| {
+            let value =
+              serde_json::from_str::<serde_json::Value>(&json).map_err(|e| e.to_string())?;
+            Ok(validate(cddl, case.rule.as_deref(), &value))
+          }),
+          Some("cbor") => fs::read(&case.instance)
+            .map_err(|e| e.to_string())
+            .and_then(|cbor| {
+              let value = decode_cbor(&cbor).map_err(|e| e.to_string())?;
+              Ok(validate(cddl, case.rule.as_deref(), &value))
+            }),
+          Some("diag") | Some("edn") => read_to_string(&case.instance).and_then(|diag| {
+            let value = edn::parse(&diag).map_err(|e| e.to_string())?;
+            Ok(validate(cddl, case.rule.as_deref(), &value))
+          }),
+          _ => Err("instance must have a .json, .cbor, .diag or .edn extension".to_string()),
+        };
+
+        let failure = match (result, case.expectation) {
+          (Err(e), _) => Some(e),
+          (Ok(Ok(())), Expectation::Valid) => None,
+          (Ok(Ok(())), Expectation::Invalid) => Some("expected to be invalid".to_string()),
+          (Ok(Err(e)), Expectation::Valid) => Some(format!("expected to be valid: {}", e)),
+          (Ok(Err(e)), Expectation::Invalid) => match e {
+            validation::Error::Compilation(_) | validation::Error::UnknownRule(_) => {
+              Some(e.to_string())
+            }
+            _ => None,
+          },
+        };
+
+        Outcome {
+          case: case.clone(),
+          failure,
+        }
+      })
+      .collect();
+
+    Ok(Report {
+      manifest: self.path.clone(),
+      outcomes,
+    })
+  }
+}
+
+fn read_to_string(path: &Path) -> Result<String, String> {
+  fs::read_to_string(path).map_err(|e| e.to_string())
+}
+
+fn validate<'a, T>(cddl: &CDDL<'a>, rule: Option<&str>, value: &T) -> validation::Result
+where
+  CDDL<'a>: Validator<T>,
+{
+  match rule {
+    Some(rule) => cddl.validate_with_root(rule, value),
+    None => cddl.validate(value),
+  }
+}
+
+/// Outcome of validating a single instance
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+  /// Instance that was validated
+  pub case: TestCase,
+  /// Why the instance failed, or `None` if validation had the expected
+  /// outcome
+  pub failure: Option<String>,
+}
+
+/// Outcomes of running a manifest
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+  /// Path of the manifest
+  pub manifest: PathBuf,
+  /// Outcome of each instance in the order they're listed
+  pub outcomes: Vec<Outcome>,
+}
+
+impl Report {
+  /// Returns whether every instance had the expected outcome
+  pub fn is_success(&self) -> bool {
+    self.outcomes.iter().all(|o| o.failure.is_none())
+  }
+
+  /// Returns the outcomes of the instances that failed
+  pub fn failures(&self) -> impl Iterator<Item = &Outcome> {
+    self.outcomes.iter().filter(|o| o.failure.is_some())
+  }
+}
+
+impl fmt::Display for Report {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for o in self.failures() {
+      writeln!(
+        f,
+        "{}:{}: {}: {}",
+        self.manifest.display(),
+        o.case.line,
+        o.case.instance.display(),
+        o.failure.as_deref().unwrap_or_default()
+      )?;
+    }
+
+    let failed = self.failures().count();
+    write!(
+      f,
+      "{}: {} passed; {} failed",
+      self.manifest.display(),
+      self.outcomes.len() - failed,
+      failed
+    )
+  }
+}
+
+/// Runs the manifest at the given path
+pub fn run_file<P: AsRef<Path>>(path: P) -> Result<Report, Error> {
+  Manifest::from_file(path)?.run()
+}
+
+/// Runs the manifest at the given path, panicking with the failures if any
+/// instance doesn't have the expected outcome. Intended for running suites
+/// via `cargo test`.
+///
+/// # Example
+///
+/// ```no_run
+/// #[test]
+/// fn person_conformance() {
+///   cddl::conformance::assert_file("tests/person.cddltest");
+/// }
+/// ```
+pub fn assert_file<P: AsRef<Path>>(path: P) {
+  match run_file(path) {
+    Ok(report) if report.is_success() => (),
+    Ok(report) => panic!("{}", report),
+    Err(e) => panic!("{}", e),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn verify_manifest_parse() {
+    let input = r#"
+      # Comment
+      cddl person.cddl
+
+      valid alice.json
+      rule address
+      invalid nested/address.diag
+    "#;
+
+    let manifest = Manifest::parse(input, "suite/person.cddltest").unwrap();
+    assert_eq!(manifest.cddl, Path::new("suite/person.cddl"));
+    assert_eq!(
+      manifest.cases,
+      [
+        TestCase {
+          instance: PathBuf::from("suite/alice.json"),
+          rule: None,
+          expectation: Expectation::Valid,
+          line: 5,
+        },
+        TestCase {
+          instance: PathBuf::from("suite/nested/address.diag"),
+          rule: Some("address".to_string()),
+          expectation: Expectation::Invalid,
+          line: 7,
+        },
+      ]
+    );
+
+    let line = |input: &str| match Manifest::parse(input, "person.cddltest") {
+      Err(Error::Syntax { line, .. }) => line,
+      r => panic!("expected syntax error, got {:?}", r),
+    };
+    assert_eq!(line("valid alice.json"), 1);
+    assert_eq!(line("cddl a.cddl\ncddl b.cddl"), 2);
+    assert_eq!(line("cddl a.cddl\nvalid\n"), 2);
+    assert_eq!(line("cddl a.cddl\npassing alice.json"), 2);
+    assert_eq!(line("# Empty"), 1);
+  }
+
+  #[test]
+  fn verify_manifest_run() {
+    let report = run_file("tests/data/conformance/person.cddltest").unwrap();
+    assert!(report.is_success(), "{}", report);
+    assert_eq!(report.outcomes.len(), 6);
+
+    // The same instances with the opposite expectations
+    let input = r#"
+      cddl person.cddl
+      invalid alice.json
+      valid missing-name.json
+      valid malformed.json
+    "#;
+    let manifest = Manifest::parse(input, "tests/data/conformance/inverted.cddltest").unwrap();
+    let report = manifest.run().unwrap();
+
+    let failures = report
+      .failures()
+      .map(|o| (o.case.line, o.failure.clone().unwrap()))
+      .collect::<Vec<_>>();
+    assert_eq!(failures.len(), 3);
+    assert_eq!(failures[0], (3, "expected to be invalid".to_string()));
+    assert!(failures[1].1.starts_with("expected to be valid"));
+    assert!(!failures[2].1.starts_with("expected"));
+
+    let manifest = Manifest::parse(
+      "cddl person.cddl\nrule missing\ninvalid alice.json",
+      "tests/data/conformance/missing.cddltest",
+    )
+    .unwrap();
+    assert!(!manifest.run().unwrap().is_success());
+
+    let manifest = Manifest::parse("cddl missing.cddl", "tests/missing.cddltest").unwrap();
+    assert!(match manifest.run() {
+      Err(Error::Schema(_)) => true,
+      _ => false,
+    });
+  }
+}
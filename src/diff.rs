@@ -0,0 +1,510 @@
+use super::{
+  ast::*,
+  compat::{
+    accepts_group, accepts_type, bounds_text, definition, definition_text, type_of, Definition,
+    Entry,
+  },
+};
+use std::fmt;
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+  string::{String, ToString},
+  vec::Vec,
+};
+
+/// Structural difference between two CDDL definitions, found within a rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaDelta {
+  /// Name of the rule the difference was found in
+  pub rule: String,
+  /// Location of the difference within the rule, as a JSON Pointer made up of
+  /// the map keys and array indices leading to it. Empty if the difference
+  /// applies to the rule as a whole.
+  pub path: String,
+  /// Kind of difference
+  pub kind: DeltaKind,
+}
+
+impl fmt::Display for SchemaDelta {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}{}: {}", self.rule, self.path, self.kind)
+  }
+}
+
+/// Kinds of structural differences
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeltaKind {
+  /// Rule was added with the given definition
+  AddedRule(String),
+  /// Rule with the given definition was removed
+  RemovedRule(String),
+  /// Map entry or array element was added
+  AddedEntry(String),
+  /// Map entry or array element was removed
+  RemovedEntry(String),
+  /// Number of times an entry may occur changed. Bounds are written like CDDL
+  /// occurrence indicators, e.g. `0*1` for `?`.
+  ChangedOccurrence {
+    /// Key or type of the entry
+    entry: String,
+    /// Old bounds
+    old: String,
+    /// New bounds
+    new: String,
+  },
+  /// Type was replaced by one that accepts all of its values and more
+  WidenedType {
+    /// Old type
+    old: String,
+    /// New type
+    new: String,
+  },
+  /// Type was replaced by one that accepts only some of its values
+  NarrowedType {
+    /// Old type
+    old: String,
+    /// New type
+    new: String,
+  },
+  /// Type was replaced by one that neither accepts all of its values nor only
+  /// some of them
+  ChangedType {
+    /// Old type
+    old: String,
+    /// New type
+    new: String,
+  },
+}
+
+impl fmt::Display for DeltaKind {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      DeltaKind::AddedRule(definition) => write!(f, "rule was added as {}", definition),
+      DeltaKind::RemovedRule(definition) => write!(f, "rule {} was removed", definition),
+      DeltaKind::AddedEntry(entry) => write!(f, "entry {} was added", entry),
+      DeltaKind::RemovedEntry(entry) => write!(f, "entry {} was removed", entry),
+      DeltaKind::ChangedOccurrence { entry, old, new } => write!(
+        f,
+        "occurrence of entry {} was changed from {} to {}",
+        entry, old, new
+      ),
+      DeltaKind::WidenedType { old, new } => write!(f, "type {} was widened to {}", old, new),
+      DeltaKind::NarrowedType { old, new } => write!(f, "type {} was narrowed to {}", old, new),
+      DeltaKind::ChangedType { old, new } => write!(f, "type {} was changed to {}", old, new),
+    }
+  }
+}
+
+impl DeltaKind {
+  // Marker prefixing the difference when rendered
+  fn marker(&self) -> char {
+    match self {
+      DeltaKind::AddedRule(_) | DeltaKind::AddedEntry(_) | DeltaKind::WidenedType { .. } => '+',
+      DeltaKind::RemovedRule(_) | DeltaKind::RemovedEntry(_) | DeltaKind::NarrowedType { .. } => {
+        '-'
+      }
+      DeltaKind::ChangedOccurrence { .. } | DeltaKind::ChangedType { .. } => '~',
+    }
+  }
+}
+
+/// Compares two CDDL definitions rule by rule, returning the rules that were
+/// added or removed and, for rules whose definitions differ, the entries that
+/// were added or removed, the occurrences that changed and the types that
+/// were widened, narrowed or otherwise changed.
+///
+/// Rules are compared by name, and references to other rules are compared by
+/// name too since the rules they refer to are compared on their own. Types
+/// are related as by `compat::check`, so a type replaced by an equivalent one,
+/// such as `text` by `tstr`, isn't reported.
+///
+/// # Example
+///
+/// ```
+/// use cddl::{diff::*, lexer_from_str, parser::cddl_from_str};
+///
+/// let a = "person = { name: tstr, ? age: 0..150 }";
+/// let b = "person = { name: tstr, age: uint, ? email: tstr }\nemail = tstr";
+///
+/// let a = cddl_from_str(&mut lexer_from_str(a), a, true).unwrap();
+/// let b = cddl_from_str(&mut lexer_from_str(b), b, true).unwrap();
+///
+/// let deltas = diff(&a, &b);
+/// assert_eq!(
+///   render(&deltas),
+///   "~ person: occurrence of entry age was changed from 0*1 to 1*1
+/// + person/age: type 0..150 was widened to uint
+/// + person: entry email was added
+/// + email: rule was added as tstr
+/// "
+/// );
+/// ```
+pub fn diff(a: &CDDL, b: &CDDL) -> Vec<SchemaDelta> {
+  let mut deltas = Vec::new();
+  let mut names: Vec<&str> = Vec::new();
+
+  for rule in a.rules.iter().chain(b.rules.iter()) {
+    let name = rule.name_ident();
+    if names.contains(&name) {
+      continue;
+    }
+    names.push(name);
+
+    let mut differ = Differ::default();
+    match (definition(a, name), definition(b, name)) {
+      (Some((old, old_params)), Some((new, new_params))) => {
+        differ.compare_definitions(&old, &old_params, &new, &new_params)
+      }
+      (Some((old, params)), None) => {
+        differ.report(DeltaKind::RemovedRule(definition_text(&old, &params)))
+      }
+      (None, Some((new, params))) => {
+        differ.report(DeltaKind::AddedRule(definition_text(&new, &params)))
+      }
+      (None, None) => {}
+    }
+
+    deltas.extend(differ.deltas.into_iter().map(|(path, kind)| SchemaDelta {
+      rule: name.to_string(),
+      path,
+      kind,
+    }));
+  }
+
+  deltas
+}
+
+/// Renders differences one per line, each prefixed by `+` for added rules and
+/// entries and widened types, `-` for removed rules and entries and narrowed
+/// types, or `~` for other changes
+pub fn render(deltas: &[SchemaDelta]) -> String {
+  let mut output = String::new();
+
+  for delta in deltas.iter() {
+    output.push(delta.kind.marker());
+    output.push(' ');
+    output.push_str(&delta.to_string());
+    output.push('\n');
+  }
+
+  output
+}
+
+#[derive(Default)]
+struct Differ {
+  path: String,
+  deltas: Vec<(String, DeltaKind)>,
+}
+
+impl Differ {
+  fn report(&mut self, kind: DeltaKind) {
+    self.deltas.push((self.path.clone(), kind));
+  }
+
+  // Runs a comparison one level deeper in the JSON Pointer, escaping the
+  // segment as required by RFC 6901
+  fn at<F: FnOnce(&mut Self)>(&mut self, segment: &str, f: F) {
+    let len = self.path.len();
+    self.path.push('/');
+    self
+      .path
+      .push_str(&segment.replace('~', "~0").replace('/', "~1"));
+
+    f(self);
+
+    self.path.truncate(len);
+  }
+
+  // Reports a type as widened, narrowed or changed given whether the new
+  // type accepts everything the old one does and the reverse
+  fn report_types(&mut self, widens: bool, narrows: bool, old: String, new: String) {
+    match (widens, narrows) {
+      (true, true) => {}
+      (true, false) => self.report(DeltaKind::WidenedType { old, new }),
+      (false, true) => self.report(DeltaKind::NarrowedType { old, new }),
+      (false, false) => self.report(DeltaKind::ChangedType { old, new }),
+    }
+  }
+
+  fn compare_definitions(
+    &mut self,
+    old: &Definition,
+    old_params: &str,
+    new: &Definition,
+    new_params: &str,
+  ) {
+    let (old_text, new_text) = (
+      definition_text(old, old_params),
+      definition_text(new, new_params),
+    );
+    if old_text == new_text {
+      return;
+    }
+
+    // Generic rules can't be related without knowing their arguments
+    if !old_params.is_empty() || !new_params.is_empty() {
+      return self.report(DeltaKind::ChangedType {
+        old: old_text,
+        new: new_text,
+      });
+    }
+
+    match (old, new) {
+      (Definition::Type(o), Definition::Type(n)) => self.compare_type(o, n),
+      (Definition::Group(o), Definition::Group(n)) => {
+        self.compare_group(o, n, false, old_text, new_text)
+      }
+      _ => self.report(DeltaKind::ChangedType {
+        old: old_text,
+        new: new_text,
+      }),
+    }
+  }
+
+  fn compare_type(&mut self, old: &Type, new: &Type) {
+    let (old_text, new_text) = (old.to_string(), new.to_string());
+    if old_text == new_text {
+      return;
+    }
+
+    if let ([o], [n]) = (&old.type_choices[..], &new.type_choices[..]) {
+      return self.compare_type1(o, n, old_text, new_text);
+    }
+
+    self.report_types(
+      accepts_type(old, new),
+      accepts_type(new, old),
+      old_text,
+      new_text,
+    )
+  }
+
+  fn compare_type1(&mut self, old: &Type1, new: &Type1, old_text: String, new_text: String) {
+    match (&old.operator, &new.operator, &old.type2, &new.type2) {
+      (
+        None,
+        None,
+        Type2::ParenthesizedType { pt: o, .. },
+        Type2::ParenthesizedType { pt: n, .. },
+      ) => self.compare_type(o, n),
+      (None, None, Type2::Map { group: o, .. }, Type2::Map { group: n, .. }) => {
+        self.compare_group(o, n, false, old_text, new_text)
+      }
+      (None, None, Type2::Array { group: o, .. }, Type2::Array { group: n, .. }) => {
+        self.compare_group(o, n, true, old_text, new_text)
+      }
+      (
+        None,
+        None,
+        Type2::TaggedData { tag: ot, t: o, .. },
+        Type2::TaggedData { tag: nt, t: n, .. },
+      ) if ot == nt => self.compare_type(o, n),
+      _ => {
+        let (old, new) = (type_of(old), type_of(new));
+
+        self.report_types(
+          accepts_type(&old, &new),
+          accepts_type(&new, &old),
+          old_text,
+          new_text,
+        )
+      }
+    }
+  }
+
+  // Compares the groups of maps or arrays, or of group rules which are
+  // compared like maps since their entries are typically keyed. Groups with
+  // more than one choice are compared as a whole.
+  fn compare_group(
+    &mut self,
+    old: &Group,
+    new: &Group,
+    is_array: bool,
+    old_text: String,
+    new_text: String,
+  ) {
+    if let ([o], [n]) = (&old.group_choices[..], &new.group_choices[..]) {
+      let old_entries = o
+        .group_entries
+        .iter()
+        .map(|(ge, _)| Entry::from(ge))
+        .collect::<Vec<_>>();
+      let new_entries = n
+        .group_entries
+        .iter()
+        .map(|(ge, _)| Entry::from(ge))
+        .collect::<Vec<_>>();
+
+      return if is_array {
+        self.compare_array_entries(&old_entries, &new_entries)
+      } else {
+        self.compare_map_entries(&old_entries, &new_entries)
+      };
+    }
+
+    self.report_types(
+      accepts_group(old, new, is_array),
+      accepts_group(new, old, is_array),
+      old_text,
+      new_text,
+    )
+  }
+
+  fn compare_map_entries(&mut self, old: &[Entry], new: &[Entry]) {
+    let is_same = |o: &Entry, n: &Entry| match (&o.key, &n.key) {
+      (Some(ok), Some(nk)) => ok == nk,
+      (None, None) => o.text == n.text,
+      _ => false,
+    };
+
+    for o in old.iter() {
+      match new.iter().find(|n| is_same(o, n)) {
+        Some(n) => {
+          let segment = o.key.clone().unwrap_or_else(|| o.text.clone());
+          self.compare_entry(o, n, &segment);
+        }
+        None => self.report(DeltaKind::RemovedEntry(o.name())),
+      }
+    }
+
+    for n in new.iter() {
+      if !old.iter().any(|o| is_same(o, n)) {
+        self.report(DeltaKind::AddedEntry(n.name()));
+      }
+    }
+  }
+
+  fn compare_array_entries(&mut self, old: &[Entry], new: &[Entry]) {
+    for (idx, (o, n)) in old.iter().zip(new.iter()).enumerate() {
+      if o.key != n.key || (o.entry_type.is_none() && o.text != n.text) {
+        self.at(&idx.to_string(), |d| {
+          d.report(DeltaKind::ChangedType {
+            old: o.text.clone(),
+            new: n.text.clone(),
+          })
+        });
+        continue;
+      }
+
+      self.compare_entry(o, n, &idx.to_string());
+    }
+
+    for o in old.iter().skip(new.len()) {
+      self.report(DeltaKind::RemovedEntry(o.name()));
+    }
+
+    for n in new.iter().skip(old.len()) {
+      self.report(DeltaKind::AddedEntry(n.name()));
+    }
+  }
+
+  fn compare_entry(&mut self, old: &Entry, new: &Entry, segment: &str) {
+    if old.bounds != new.bounds {
+      self.report(DeltaKind::ChangedOccurrence {
+        entry: old.name(),
+        old: bounds_text(old.bounds),
+        new: bounds_text(new.bounds),
+      });
+    }
+
+    if let (Some(o), Some(n)) = (&old.entry_type, &new.entry_type) {
+      self.at(segment, |d| d.compare_type(o, n));
+    }
+  }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+  use super::*;
+  use crate::{lexer::Lexer, parser::cddl_from_str};
+
+  fn deltas(a: &str, b: &str) -> Vec<String> {
+    let a_cddl = cddl_from_str(&mut Lexer::new(a), a, true).unwrap();
+    let b_cddl = cddl_from_str(&mut Lexer::new(b), b, true).unwrap();
+
+    diff(&a_cddl, &b_cddl)
+      .iter()
+      .map(|d| d.to_string())
+      .collect()
+  }
+
+  #[test]
+  fn verify_identical() {
+    let cddl = "message = { id: uint, ? tags: [* tstr] }\nid = uint";
+
+    assert!(deltas(cddl, cddl).is_empty());
+    assert!(deltas("a = text", "a = tstr").is_empty());
+  }
+
+  #[test]
+  fn verify_rule_deltas() {
+    let a = r#"
+      message = {
+        id: int,
+        kind: "ping" / "pong",
+        ? payload: [uint, * tstr],
+        sent: 0..100,
+        legacy: bool,
+      }
+
+      header = [version: uint]
+      meta = (source: tstr)
+    "#;
+    let b = r#"
+      message = {
+        id: uint,
+        kind: "ping" / "pong" / "reset",
+        payload: [uint],
+        sent: 0..100 / tstr,
+        trace: [+ tstr],
+      }
+
+      meta = [source: tstr]
+      footer = { checksum: bstr }
+    "#;
+
+    assert_eq!(
+      deltas(a, b),
+      vec![
+        "message/id: type int was narrowed to uint",
+        "message/kind: type \"ping\" / \"pong\" was widened to \"ping\" / \"pong\" / \"reset\"",
+        "message: occurrence of entry payload was changed from 0*1 to 1*1",
+        "message/payload: entry tstr was removed",
+        "message/sent: type 0..100 was widened to 0..100 / tstr",
+        "message: entry legacy was removed",
+        "message: entry trace was added",
+        "header: rule [version: uint] was removed",
+        "meta: type (source: tstr) was changed to [source: tstr]",
+        "footer: rule was added as {checksum: bstr}",
+      ]
+    );
+  }
+
+  #[test]
+  fn verify_occurrence_deltas() {
+    assert_eq!(
+      deltas("a = [* tstr]", "a = [+ tstr]"),
+      vec!["a: occurrence of entry tstr was changed from 0* to 1*"]
+    );
+    assert_eq!(
+      deltas("a = (b: uint, c: tstr)", "a = (b: uint, 1*2 c: tstr)"),
+      vec!["a: occurrence of entry c was changed from 1*1 to 1*2"]
+    );
+  }
+
+  #[test]
+  fn verify_render() {
+    let a = "a = { b: uint }\nc = [* int]";
+    let b = "a = { b: int, ? d: tstr }\nc = [* uint]";
+
+    let a = cddl_from_str(&mut Lexer::new(a), a, true).unwrap();
+    let b = cddl_from_str(&mut Lexer::new(b), b, true).unwrap();
+
+    assert_eq!(
+      render(&diff(&a, &b)),
+      "+ a/b: type uint was widened to int\n+ a: entry d was added\n- c/0: type int was narrowed to uint\n"
+    );
+  }
+}
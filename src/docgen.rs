@@ -0,0 +1,832 @@
+use super::{
+  ast::*,
+  lexer::Lexer,
+  parser::cddl_from_str,
+  token::Token,
+  visitor::{self, Visitor},
+};
+use std::{collections::BTreeMap, fmt::Write};
+
+/// Format of generated documentation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocFormat {
+  /// Markdown, with tables for the members of maps, arrays and groups
+  Markdown,
+  /// Standalone HTML page
+  Html,
+}
+
+/// Documentation of a rule, along with any rules extending it with additional
+/// choices
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleDoc {
+  /// Name of the rule
+  pub name: String,
+  /// Text of the comment lines immediately preceding the rule and of any
+  /// comment on the line it starts on
+  pub description: String,
+  /// Source text of the rule and of the rules extending it
+  pub definition: String,
+  /// Members of the map, array or group defined by the rule, one list per
+  /// group choice
+  pub members: Vec<Vec<MemberDoc>>,
+  /// Summaries of the range and control operators constraining a type rule
+  pub constraints: Vec<String>,
+  /// Names of the rules this rule refers to
+  pub references: Vec<String>,
+  /// Names of the rules referring to this rule
+  pub referenced_by: Vec<String>,
+}
+
+/// Documentation of an entry of a map, array or group
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberDoc {
+  /// Member key, if any
+  pub key: Option<String>,
+  /// Type of the entry, without any range or control operators
+  pub entry_type: String,
+  /// How many times the entry may occur, e.g. "required" or "optional"
+  pub occurrence: String,
+  /// Summaries of the range and control operators constraining the entry
+  pub constraints: Vec<String>,
+  /// Text of the comment lines immediately preceding the entry and of any
+  /// comment trailing it
+  pub description: String,
+}
+
+/// Generates documentation for CDDL text, returning the documentation or an
+/// error message if the input isn't conformant
+///
+/// Each rule is documented in the order it's defined, with its description
+/// taken from its comments, its definition, a table of its members if it
+/// defines a map, array or group, and links to the rules it refers to and the
+/// rules that refer to it.
+///
+/// # Arguments
+///
+/// * `input` - A string slice with the CDDL text input
+/// * `format` - Format of the generated documentation
+///
+/// # Example
+///
+/// ```
+/// use cddl::docgen::{docs_from_str, DocFormat};
+///
+/// let input = r#"; A person
+/// person = {
+///   name: tstr,      ; Full name
+///   ? age: uint .le 150,
+/// }"#;
+///
+/// let docs = docs_from_str(input, DocFormat::Markdown).unwrap();
+/// assert!(docs.contains("| name | tstr | required |  | Full name |"));
+/// assert!(docs.contains("| age | uint | optional | at most 150 |  |"));
+/// ```
+pub fn docs_from_str(input: &str, format: DocFormat) -> Result<String, String> {
+  let docs = rule_docs(input)?;
+
+  Ok(match format {
+    DocFormat::Markdown => render_markdown(&docs),
+    DocFormat::Html => render_html(&docs),
+  })
+}
+
+/// Parses CDDL text and documents each of its rules, returning an error
+/// message if the input isn't conformant
+pub fn rule_docs(input: &str) -> Result<Vec<RuleDoc>, String> {
+  let mut lexer = Lexer::new(input);
+  let cddl = cddl_from_str(&mut lexer, input, false)?;
+  let comments = Comments::new(input)?;
+
+  let mut docs = Vec::new();
+  for rule in cddl.rules.iter().filter(|r| !r.is_choice_alternate()) {
+    docs.push(rule_doc(input, &cddl, rule, &comments));
+  }
+
+  for idx in 0..docs.len() {
+    let name = docs[idx].name.clone();
+    let referenced_by = docs
+      .iter()
+      .filter(|d| d.references.contains(&name))
+      .map(|d| d.name.clone())
+      .collect();
+    docs[idx].referenced_by = referenced_by;
+  }
+
+  Ok(docs)
+}
+
+fn rule_doc(input: &str, cddl: &CDDL, rule: &Rule, comments: &Comments) -> RuleDoc {
+  let name = rule.name_ident();
+  let rules = cddl.rules_named(name).collect::<Vec<_>>();
+  let (_, _, line) = rule.span();
+
+  let mut members = Vec::new();
+  let mut constraints = Vec::new();
+  let mut references = References {
+    names: Vec::new(),
+    generic_params: Vec::new(),
+  };
+
+  // Choices added by rules extending this one are documented alongside its
+  // own
+  let mut type_choices = Vec::new();
+  for r in rules.iter() {
+    match r {
+      Rule::Type { rule, .. } => {
+        type_choices.extend(rule.value.type_choices.iter());
+        if let Some(gp) = &rule.generic_param {
+          references.generic_params = gp.params.iter().map(|p| p.ident).collect();
+        }
+      }
+      Rule::Group { rule, .. } => {
+        match &rule.entry {
+          GroupEntry::InlineGroup {
+            group, occur: None, ..
+          } => {
+            for gc in group.group_choices.iter() {
+              members.push(member_docs(gc, line, comments));
+            }
+          }
+          entry => members.push(vec![member_doc(entry, None, line, comments)]),
+        }
+        if let Some(gp) = &rule.generic_param {
+          references.generic_params = gp.params.iter().map(|p| p.ident).collect();
+        }
+      }
+    }
+    visitor::walk_rule(&mut references, r);
+  }
+
+  if type_choices.len() == 1 {
+    let t1 = type_choices[0];
+    match &t1.type2 {
+      Type2::Map { group, .. } | Type2::Array { group, .. } => {
+        for gc in group.group_choices.iter() {
+          members.push(member_docs(gc, line, comments));
+        }
+      }
+      _ => {
+        if let Some((op, controller)) = &t1.operator {
+          constraints.push(constraint_summary(&t1.type2, op, controller));
+        }
+      }
+    }
+  }
+
+  RuleDoc {
+    name: name.to_string(),
+    description: comments.description(line, true),
+    definition: rules
+      .iter()
+      .map(|r| {
+        let (start, end, _) = r.span();
+        input.get(start..end).map(str::trim_end).unwrap_or_default()
+      })
+      .collect::<Vec<_>>()
+      .join("\n"),
+    members,
+    constraints,
+    references: references
+      .names
+      .into_iter()
+      .filter(|n| *n != name && cddl.rule(n).is_some())
+      .map(String::from)
+      .collect(),
+    referenced_by: Vec::new(),
+  }
+}
+
+fn member_docs(gc: &GroupChoice, rule_line: usize, comments: &Comments) -> Vec<MemberDoc> {
+  let entries = &gc.group_entries;
+
+  entries
+    .iter()
+    .enumerate()
+    .map(|(idx, (entry, _))| {
+      let next_line = entries.get(idx + 1).map(|(e, _)| entry_span(e).2);
+      member_doc(entry, next_line, rule_line, comments)
+    })
+    .collect()
+}
+
+fn member_doc(
+  entry: &GroupEntry,
+  next_line: Option<usize>,
+  rule_line: usize,
+  comments: &Comments,
+) -> MemberDoc {
+  let (_, _, line) = entry_span(entry);
+
+  // A comment trailing a line is attributed to the last entry on it, unless
+  // it's the line the rule starts on, in which case it describes the rule
+  let description = if line == rule_line {
+    String::new()
+  } else {
+    comments.description(line, next_line != Some(line))
+  };
+
+  match entry {
+    GroupEntry::ValueMemberKey { ge, .. } => {
+      let key = ge.member_key.as_ref().map(|mk| match mk {
+        MemberKey::Bareword { ident, .. } => ident.to_string(),
+        MemberKey::Value { value, .. } => value.to_string(),
+        MemberKey::Type1 { t1, .. } => t1.to_string(),
+        MemberKey::NonMemberKey(NonMemberKey::Group(g)) => format!("({})", g),
+        MemberKey::NonMemberKey(NonMemberKey::Type(t)) => t.to_string(),
+      });
+
+      let mut entry_type = Vec::new();
+      let mut constraints = Vec::new();
+      let is_choice = ge.entry_type.type_choices.len() > 1;
+      for t1 in ge.entry_type.type_choices.iter() {
+        match &t1.operator {
+          Some((op @ RangeCtlOp::RangeOp { .. }, controller)) => {
+            entry_type.push(t1.to_string());
+            constraints.push(constraint_summary(&t1.type2, op, controller));
+          }
+          Some((op, controller)) => {
+            entry_type.push(t1.type2.to_string());
+            let summary = constraint_summary(&t1.type2, op, controller);
+            if is_choice {
+              constraints.push(format!("{}: {}", t1.type2, summary));
+            } else {
+              constraints.push(summary);
+            }
+          }
+          None => entry_type.push(t1.to_string()),
+        }
+      }
+
+      MemberDoc {
+        key,
+        entry_type: entry_type.join(" / "),
+        occurrence: occurrence(&ge.occur),
+        constraints,
+        description,
+      }
+    }
+    GroupEntry::TypeGroupname { ge, .. } => MemberDoc {
+      key: None,
+      entry_type: match &ge.generic_arg {
+        Some(ga) => format!("{}{}", ge.name, ga),
+        None => ge.name.to_string(),
+      },
+      occurrence: occurrence(&ge.occur),
+      constraints: Vec::new(),
+      description,
+    },
+    GroupEntry::InlineGroup { occur, group, .. } => MemberDoc {
+      key: None,
+      entry_type: format!("({})", group),
+      occurrence: occurrence(occur),
+      constraints: Vec::new(),
+      description,
+    },
+  }
+}
+
+fn entry_span(entry: &GroupEntry) -> Span {
+  match entry {
+    GroupEntry::ValueMemberKey { span, .. }
+    | GroupEntry::TypeGroupname { span, .. }
+    | GroupEntry::InlineGroup { span, .. } => *span,
+  }
+}
+
+fn occurrence(occur: &Option<Occur>) -> String {
+  match occur {
+    None => "required".to_string(),
+    Some(Occur::Optional(_)) => "optional".to_string(),
+    Some(Occur::ZeroOrMore(_)) => "zero or more".to_string(),
+    Some(Occur::OneOrMore(_)) => "one or more".to_string(),
+    Some(Occur::Exact { lower, upper, .. }) => match (lower, upper) {
+      (Some(lower), Some(upper)) if lower == upper => format!("exactly {}", lower),
+      (Some(lower), Some(upper)) => format!("{} to {}", lower, upper),
+      (Some(lower), None) => format!("at least {}", lower),
+      (None, Some(upper)) => format!("at most {}", upper),
+      (None, None) => "zero or more".to_string(),
+    },
+  }
+}
+
+fn constraint_summary(target: &Type2, op: &RangeCtlOp, controller: &Type2) -> String {
+  match op {
+    RangeCtlOp::RangeOp {
+      is_inclusive: true, ..
+    } => format!("from {} to {}", target, controller),
+    RangeCtlOp::RangeOp {
+      is_inclusive: false,
+      ..
+    } => format!("from {} up to but excluding {}", target, controller),
+    RangeCtlOp::CtlOp { ctrl, .. } => {
+      let summary = match *ctrl {
+        ".size" => "size",
+        ".bits" => "only bits",
+        ".regexp" | ".pcre" => "matches",
+        ".lt" => "less than",
+        ".le" => "at most",
+        ".gt" => "greater than",
+        ".ge" => "at least",
+        ".eq" => "equal to",
+        ".ne" => "not equal to",
+        ".default" => "defaults to",
+        ".capture" => "captured as",
+        ".cbor" => "CBOR encoded",
+        ".cborseq" => "CBOR sequence of",
+        ".json" => "JSON text of",
+        ".within" | ".and" => "also matches",
+        ctrl => ctrl,
+      };
+
+      format!("{} {}", summary, controller)
+    }
+  }
+}
+
+// Names referred to by a rule, in the order they first appear, excluding its
+// generic parameters
+struct References<'a> {
+  names: Vec<&'a str>,
+  generic_params: Vec<&'a str>,
+}
+
+impl<'a> Visitor<'a> for References<'a> {
+  fn visit_typename(&mut self, ident: &Identifier<'a>) {
+    if !self.names.contains(&ident.ident) && !self.generic_params.contains(&ident.ident) {
+      self.names.push(ident.ident);
+    }
+  }
+}
+
+struct Comment<'a> {
+  text: &'a str,
+  // Whether or not the comment is the only thing on its line
+  is_own_line: bool,
+}
+
+// Comments aren't retained by the AST, so they're collected from a separate
+// pass over the token stream and attached to rules and group entries by line
+struct Comments<'a> {
+  lines: BTreeMap<usize, Comment<'a>>,
+}
+
+impl<'a> Comments<'a> {
+  fn new(input: &'a str) -> Result<Self, String> {
+    let mut lines = BTreeMap::new();
+
+    let mut lexer = Lexer::new(input);
+    loop {
+      match lexer.next_token().map_err(|e| e.to_string())? {
+        (_, Token::EOF) => break,
+        (position, Token::COMMENT(text)) => {
+          // The position of a comment is reported after the newline ending
+          // it, so its line is worked out from its offset instead
+          let before = &input[..position.range.0];
+          let line = before.matches('\n').count() + 1;
+          let is_own_line = before
+            .rsplit('\n')
+            .next()
+            .map(|before| before.trim().is_empty())
+            .unwrap_or(true);
+
+          lines.insert(
+            line,
+            Comment {
+              text: text.trim(),
+              is_own_line,
+            },
+          );
+        }
+        _ => (),
+      }
+    }
+
+    Ok(Comments { lines })
+  }
+
+  // Joins the comment lines immediately preceding the given line, along with
+  // the comment trailing it if requested
+  fn description(&self, line: usize, include_trailing: bool) -> String {
+    let mut text = Vec::new();
+
+    let mut above = line;
+    while let Some(c) = above
+      .checked_sub(1)
+      .and_then(|l| self.lines.get(&l))
+      .filter(|c| c.is_own_line)
+    {
+      text.push(c.text);
+      above -= 1;
+    }
+    text.reverse();
+
+    if include_trailing {
+      if let Some(c) = self.lines.get(&line).filter(|c| !c.is_own_line) {
+        text.push(c.text);
+      }
+    }
+
+    text
+      .into_iter()
+      .filter(|t| !t.is_empty())
+      .collect::<Vec<_>>()
+      .join("\n")
+  }
+}
+
+/// Renders rule documentation as Markdown
+pub fn render_markdown(docs: &[RuleDoc]) -> String {
+  let names = docs.iter().map(|d| d.name.as_str()).collect::<Vec<_>>();
+  let link = |name: &str| format!("[{}](#{})", escape_markdown(name), name);
+  let text = |text: &str| link_references(text, &names, &link, &escape_markdown);
+
+  let mut output = String::new();
+
+  for name in names.iter() {
+    let _ = writeln!(output, "- {}", link(name));
+  }
+
+  for doc in docs.iter() {
+    let _ = write!(
+      output,
+      "\n<a id=\"{}\"></a>\n## {}\n\n",
+      escape_html(&doc.name),
+      escape_markdown(&doc.name)
+    );
+
+    if !doc.description.is_empty() {
+      let _ = write!(output, "{}\n\n", escape_markdown(&doc.description));
+    }
+
+    let _ = write!(output, "```cddl\n{}\n```\n", doc.definition);
+
+    for (idx, members) in doc.members.iter().enumerate() {
+      if doc.members.len() > 1 {
+        let _ = write!(output, "\n**Choice {}**\n", idx + 1);
+      }
+
+      output.push_str("\n| Key | Type | Occurrence | Constraints | Description |\n");
+      output.push_str("| --- | --- | --- | --- | --- |\n");
+      for m in members.iter() {
+        let _ = writeln!(
+          output,
+          "| {} | {} | {} | {} | {} |",
+          m.key
+            .as_ref()
+            .map(|k| escape_markdown(k))
+            .unwrap_or_default(),
+          text(&m.entry_type),
+          m.occurrence,
+          text(&m.constraints.join("; ")),
+          escape_markdown(&m.description).replace('\n', " "),
+        );
+      }
+    }
+
+    if !doc.constraints.is_empty() {
+      let _ = write!(
+        output,
+        "\n**Constraints:** {}\n",
+        text(&doc.constraints.join("; "))
+      );
+    }
+
+    if !doc.references.is_empty() {
+      let _ = write!(
+        output,
+        "\n**References:** {}\n",
+        doc
+          .references
+          .iter()
+          .map(|n| link(n))
+          .collect::<Vec<_>>()
+          .join(", ")
+      );
+    }
+
+    if !doc.referenced_by.is_empty() {
+      let _ = write!(
+        output,
+        "\n**Referenced by:** {}\n",
+        doc
+          .referenced_by
+          .iter()
+          .map(|n| link(n))
+          .collect::<Vec<_>>()
+          .join(", ")
+      );
+    }
+  }
+
+  output
+}
+
+/// Renders rule documentation as a standalone HTML page
+pub fn render_html(docs: &[RuleDoc]) -> String {
+  let names = docs.iter().map(|d| d.name.as_str()).collect::<Vec<_>>();
+  let link = |name: &str| {
+    let name = escape_html(name);
+    format!("<a href=\"#{}\">{}</a>", name, name)
+  };
+  let text = |text: &str| link_references(text, &names, &link, &escape_html);
+
+  let mut output = String::from(
+    "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>CDDL documentation</title>\n</head>\n<body>\n<nav>\n<ul>\n",
+  );
+
+  for name in names.iter() {
+    let _ = writeln!(output, "<li>{}</li>", link(name));
+  }
+  output.push_str("</ul>\n</nav>\n");
+
+  for doc in docs.iter() {
+    let name = escape_html(&doc.name);
+    let _ = write!(output, "<section id=\"{}\">\n<h2>{}</h2>\n", name, name);
+
+    if !doc.description.is_empty() {
+      let _ = writeln!(output, "<p>{}</p>", escape_html(&doc.description));
+    }
+
+    let _ = writeln!(
+      output,
+      "<pre><code>{}</code></pre>",
+      escape_html(&doc.definition)
+    );
+
+    for (idx, members) in doc.members.iter().enumerate() {
+      if doc.members.len() > 1 {
+        let _ = writeln!(output, "<h3>Choice {}</h3>", idx + 1);
+      }
+
+      output.push_str("<table>\n<thead>\n<tr><th>Key</th><th>Type</th><th>Occurrence</th><th>Constraints</th><th>Description</th></tr>\n</thead>\n<tbody>\n");
+      for m in members.iter() {
+        let _ = writeln!(
+          output,
+          "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+          m.key.as_ref().map(|k| escape_html(k)).unwrap_or_default(),
+          text(&m.entry_type),
+          m.occurrence,
+          text(&m.constraints.join("; ")),
+          escape_html(&m.description),
+        );
+      }
+      output.push_str("</tbody>\n</table>\n");
+    }
+
+    if !doc.constraints.is_empty() {
+      let _ = writeln!(
+        output,
+        "<p><strong>Constraints:</strong> {}</p>",
+        text(&doc.constraints.join("; "))
+      );
+    }
+
+    if !doc.references.is_empty() {
+      let _ = writeln!(
+        output,
+        "<p><strong>References:</strong> {}</p>",
+        doc
+          .references
+          .iter()
+          .map(|n| link(n))
+          .collect::<Vec<_>>()
+          .join(", ")
+      );
+    }
+
+    if !doc.referenced_by.is_empty() {
+      let _ = writeln!(
+        output,
+        "<p><strong>Referenced by:</strong> {}</p>",
+        doc
+          .referenced_by
+          .iter()
+          .map(|n| link(n))
+          .collect::<Vec<_>>()
+          .join(", ")
+      );
+    }
+
+    output.push_str("</section>\n");
+  }
+
+  output.push_str("</body>\n</html>\n");
+
+  output
+}
+
+/// Links the identifiers in CDDL text that name documented rules, escaping the
+/// rest. Text and byte string literals are skipped over so that identifiers
+/// within them aren't linked.
+fn link_references(
+  text: &str,
+  names: &[&str],
+  link: &dyn Fn(&str) -> String,
+  escape: &dyn Fn(&str) -> String,
+) -> String {
+  let mut output = String::new();
+  let mut plain = String::new();
+  let mut chars = text.char_indices().peekable();
+
+  while let Some((start, c)) = chars.next() {
+    if c == '"' || c == '\'' {
+      plain.push(c);
+      let mut is_escaped = false;
+      for (_, next) in chars.by_ref() {
+        plain.push(next);
+        if next == c && !is_escaped {
+          break;
+        }
+        is_escaped = next == '\\' && !is_escaped;
+      }
+      continue;
+    }
+
+    let is_ident_start = c.is_ascii_alphabetic() || c == '@' || c == '_' || c == '$';
+    if !is_ident_start && !c.is_ascii_digit() {
+      plain.push(c);
+      continue;
+    }
+
+    let mut end = start + c.len_utf8();
+    while let Some(&(idx, next)) = chars.peek() {
+      if next.is_ascii_alphanumeric() || "@_$-.".contains(next) {
+        end = idx + next.len_utf8();
+        chars.next();
+      } else {
+        break;
+      }
+    }
+
+    let word = &text[start..end];
+    // Identifiers can't end with "-" or ".", e.g. the start of a range
+    let ident = word.trim_end_matches(&['-', '.'][..]);
+    if is_ident_start && names.contains(&ident) {
+      output.push_str(&escape(&plain));
+      plain.clear();
+      output.push_str(&link(ident));
+      plain.push_str(&word[ident.len()..]);
+    } else {
+      plain.push_str(word);
+    }
+  }
+
+  output.push_str(&escape(&plain));
+
+  output
+}
+
+fn escape_markdown(text: &str) -> String {
+  let mut output = String::new();
+  for c in text.chars() {
+    if "\\`*_[]<>|#".contains(c) {
+      output.push('\\');
+    }
+    output.push(c);
+  }
+
+  output
+}
+
+fn escape_html(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const INPUT: &str = r#"; A person
+; with an address
+person = { ; known to the system
+  name: tstr,              ; Full name
+  ? age: uint .le 150,
+  ; Where they live
+  address: address,
+  * tstr => any
+}
+
+address = [street: tstr, number: 1..9999]
+
+; Port number
+port = uint .le 65535
+
+message<T> = (id: uint, body: T // error: tstr)
+"#;
+
+  #[test]
+  fn verify_rule_docs() {
+    let docs = rule_docs(INPUT).unwrap();
+    assert_eq!(
+      docs.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(),
+      ["person", "address", "port", "message"]
+    );
+
+    let person = &docs[0];
+    assert_eq!(
+      person.description,
+      "A person\nwith an address\nknown to the system"
+    );
+    assert_eq!(person.members.len(), 1);
+    assert_eq!(
+      person.members[0],
+      [
+        MemberDoc {
+          key: Some("name".to_string()),
+          entry_type: "tstr".to_string(),
+          occurrence: "required".to_string(),
+          constraints: Vec::new(),
+          description: "Full name".to_string(),
+        },
+        MemberDoc {
+          key: Some("age".to_string()),
+          entry_type: "uint".to_string(),
+          occurrence: "optional".to_string(),
+          constraints: vec!["at most 150".to_string()],
+          description: String::new(),
+        },
+        MemberDoc {
+          key: Some("address".to_string()),
+          entry_type: "address".to_string(),
+          occurrence: "required".to_string(),
+          constraints: Vec::new(),
+          description: "Where they live".to_string(),
+        },
+        MemberDoc {
+          key: Some("tstr".to_string()),
+          entry_type: "any".to_string(),
+          occurrence: "zero or more".to_string(),
+          constraints: Vec::new(),
+          description: String::new(),
+        },
+      ]
+    );
+    assert_eq!(person.references, ["address"]);
+    assert!(person.referenced_by.is_empty());
+
+    let address = &docs[1];
+    assert_eq!(address.members[0][1].entry_type, "1..9999");
+    assert_eq!(address.members[0][1].constraints, ["from 1 to 9999"]);
+    assert_eq!(address.referenced_by, ["person"]);
+
+    let port = &docs[2];
+    assert_eq!(port.description, "Port number");
+    assert!(port.members.is_empty());
+    assert_eq!(port.constraints, ["at most 65535"]);
+
+    // Generic parameters aren't references to other rules
+    let message = &docs[3];
+    assert_eq!(message.members.len(), 2);
+    assert_eq!(message.members[0][1].entry_type, "T");
+    assert!(message.references.is_empty());
+  }
+
+  #[test]
+  fn verify_markdown_output() {
+    let output = docs_from_str(INPUT, DocFormat::Markdown).unwrap();
+
+    assert!(output.starts_with("- [person](#person)\n"));
+    assert!(output.contains("<a id=\"person\"></a>\n## person\n\nA person\nwith an address\nknown to the system\n\n```cddl\nperson = {"));
+    assert!(output.contains("| address | [address](#address) | required |  | Where they live |"));
+    assert!(output.contains("| tstr | any | zero or more |  |  |"));
+    assert!(output.contains("\n**Referenced by:** [person](#person)\n"));
+    assert!(output.contains("\n**Constraints:** at most 65535\n"));
+    assert!(output.contains("\n**Choice 2**\n"));
+  }
+
+  #[test]
+  fn verify_html_output() {
+    let output = docs_from_str(INPUT, DocFormat::Html).unwrap();
+
+    assert!(output.starts_with("<!DOCTYPE html>"));
+    assert!(output.contains("<section id=\"address\">\n<h2>address</h2>\n"));
+    assert!(output.contains("<tr><td>address</td><td><a href=\"#address\">address</a></td><td>required</td><td></td><td>Where they live</td></tr>"));
+    assert!(output.contains("<pre><code>message&lt;T&gt; = "));
+  }
+
+  #[test]
+  fn verify_link_references() {
+    let names = ["person", "id"];
+    let link = |name: &str| format!("<{}>", name);
+    let escape = |text: &str| text.to_string();
+
+    assert_eq!(
+      link_references(
+        "[* person] / { \"person\": id, b'id': persons }",
+        &names,
+        &link,
+        &escape
+      ),
+      "[* <person>] / { \"person\": <id>, b'id': persons }"
+    );
+    assert_eq!(
+      link_references("1.5 / id.x / id", &names, &link, &escape),
+      "1.5 / id.x / <id>"
+    );
+  }
+}
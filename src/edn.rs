@@ -0,0 +1,671 @@
+use crate::validation::cbor::{simple_value, SIMPLE_VALUE_TAG};
+use serde_cbor::Value;
+use std::{collections::BTreeMap, error, f64, fmt};
+
+/// Error parsing CBOR diagnostic notation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+  /// Byte offset of the error in the input
+  pub position: usize,
+  /// Description of the error
+  pub message: String,
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "diagnostic notation error at offset {}: {}",
+      self.position, self.message
+    )
+  }
+}
+
+impl error::Error for Error {}
+
+/// Parses a single data item in CBOR diagnostic notation (RFC 8949 section 8
+/// and RFC 8610 appendix G)
+///
+/// Supported are integers (including `0x`, `0o` and `0b` prefixes), floats
+/// including `Infinity` and `NaN`, text strings, byte strings in `h''`,
+/// `b64''` and `''` form, embedded CBOR in `<< >>`, arrays, maps, tags,
+/// `true`, `false`, `null`, `undefined` and other simple values written as
+/// `simple(n)`. Adjacent strings of the same kind are concatenated,
+/// indefinite-length markers and encoding indicators such as `_1` are accepted
+/// and ignored, and `/ comments /` are skipped. `undefined` and the simple
+/// values `serde_cbor::Value` has no variant for are represented as described
+/// for `cbor_validator::SIMPLE_VALUE_TAG`.
+///
+/// # Example
+///
+/// ```
+/// use cddl::edn;
+/// use serde_cbor::Value;
+///
+/// let value = edn::parse(r#"[1, "two", h'03', 1("2013-03-21T20:04:00Z")]"#).unwrap();
+/// assert_eq!(
+///   value,
+///   Value::Array(vec![
+///     Value::Integer(1),
+///     Value::Text("two".into()),
+///     Value::Bytes(vec![3]),
+///     Value::Tag(1, Box::new(Value::Text("2013-03-21T20:04:00Z".into()))),
+///   ])
+/// );
+/// ```
+pub fn parse(input: &str) -> Result<Value, Error> {
+  let mut parser = Parser { input, position: 0 };
+
+  let value = parser.item()?;
+  parser.skip_whitespace()?;
+
+  if parser.position < input.len() {
+    return Err(parser.error("unexpected input after data item"));
+  }
+
+  Ok(value)
+}
+
+/// Parses a CBOR sequence (RFC 8742) in diagnostic notation, i.e. zero or more
+/// comma-separated data items
+pub fn parse_seq(input: &str) -> Result<Vec<Value>, Error> {
+  let mut parser = Parser { input, position: 0 };
+  let mut items = Vec::new();
+
+  parser.skip_whitespace()?;
+  while parser.position < input.len() {
+    items.push(parser.item()?);
+
+    if !parser.eat(',')? {
+      break;
+    }
+
+    parser.skip_whitespace()?;
+  }
+
+  parser.skip_whitespace()?;
+  if parser.position < input.len() {
+    return Err(parser.error("expected \",\" between data items"));
+  }
+
+  Ok(items)
+}
+
+struct Parser<'a> {
+  input: &'a str,
+  position: usize,
+}
+
+impl<'a> Parser<'a> {
+  fn error<S: Into<String>>(&self, message: S) -> Error {
+    Error {
+      position: self.position,
+      message: message.into(),
+    }
+  }
+
+  fn rest(&self) -> &'a str {
+    &self.input[self.position..]
+  }
+
+  fn peek(&self) -> Option<char> {
+    self.rest().chars().next()
+  }
+
+  fn bump(&mut self) -> Option<char> {
+    let c = self.peek()?;
+    self.position += c.len_utf8();
+    Some(c)
+  }
+
+  // Skips whitespace and comments, which are enclosed in slashes
+  fn skip_whitespace(&mut self) -> Result<(), Error> {
+    loop {
+      match self.peek() {
+        Some(c) if c.is_whitespace() => {
+          self.bump();
+        }
+        Some('/') => {
+          let start = self.position;
+          self.bump();
+
+          match self.rest().find('/') {
+            Some(end) => self.position += end + 1,
+            None => {
+              self.position = start;
+              return Err(self.error("unterminated comment"));
+            }
+          }
+        }
+        _ => return Ok(()),
+      }
+    }
+  }
+
+  // Skips whitespace and consumes the given character if it's next
+  fn eat(&mut self, c: char) -> Result<bool, Error> {
+    self.skip_whitespace()?;
+
+    if self.peek() == Some(c) {
+      self.bump();
+      return Ok(true);
+    }
+
+    Ok(false)
+  }
+
+  fn expect(&mut self, c: char) -> Result<(), Error> {
+    if self.eat(c)? {
+      return Ok(());
+    }
+
+    Err(self.error(format!("expected \"{}\"", c)))
+  }
+
+  // Consumes the indefinite-length marker following an opening bracket
+  fn indefinite_marker(&mut self) -> Result<(), Error> {
+    self.skip_whitespace()?;
+
+    if self.rest().starts_with('_') {
+      self.bump();
+    }
+
+    Ok(())
+  }
+
+  // Consumes an encoding indicator such as _1 following a number or a string
+  fn encoding_indicator(&mut self) {
+    if self.peek() == Some('_') {
+      self.position += 1;
+      let rest = self.rest();
+      self.position += rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    }
+  }
+
+  fn item(&mut self) -> Result<Value, Error> {
+    self.skip_whitespace()?;
+
+    let value = match self.peek() {
+      Some('[') => self.array()?,
+      Some('{') => self.map()?,
+      Some('"') => self.strings(Value::Text(String::new()))?,
+      Some('\'') => self.strings(Value::Bytes(Vec::new()))?,
+      Some('<') if self.rest().starts_with("<<") => self.strings(Value::Bytes(Vec::new()))?,
+      Some('(') if self.rest()[1..].trim_start().starts_with('_') => self.indefinite_string()?,
+      Some(c) if c == '-' || c == '+' || c.is_ascii_digit() => self.number()?,
+      Some(c) if c.is_ascii_alphabetic() => self.word()?,
+      Some(_) => return Err(self.error("expected a data item")),
+      None => return Err(self.error("unexpected end of input")),
+    };
+
+    Ok(value)
+  }
+
+  fn array(&mut self) -> Result<Value, Error> {
+    self.expect('[')?;
+    self.indefinite_marker()?;
+
+    let mut values = Vec::new();
+    if self.eat(']')? {
+      return Ok(Value::Array(values));
+    }
+
+    loop {
+      values.push(self.item()?);
+
+      if self.eat(']')? {
+        return Ok(Value::Array(values));
+      }
+
+      self.expect(',')?;
+    }
+  }
+
+  fn map(&mut self) -> Result<Value, Error> {
+    self.expect('{')?;
+    self.indefinite_marker()?;
+
+    let mut entries = BTreeMap::new();
+    if self.eat('}')? {
+      return Ok(Value::Map(entries));
+    }
+
+    loop {
+      self.skip_whitespace()?;
+      let position = self.position;
+
+      let key = self.item()?;
+      self.expect(':')?;
+      let value = self.item()?;
+
+      if entries.insert(key, value).is_some() {
+        self.position = position;
+        return Err(self.error("duplicate map key"));
+      }
+
+      if self.eat('}')? {
+        return Ok(Value::Map(entries));
+      }
+
+      self.expect(',')?;
+    }
+  }
+
+  // Parses a string followed by any number of adjacent strings of the same
+  // kind, which are concatenated
+  fn strings(&mut self, mut value: Value) -> Result<Value, Error> {
+    loop {
+      self.skip_whitespace()?;
+
+      let is_text = match self.peek() {
+        Some('"') => true,
+        Some('\'') | Some('<') => false,
+        Some(c) if c.is_ascii_alphanumeric() && self.is_byte_string_prefix() => false,
+        _ => return Ok(value),
+      };
+
+      match (&mut value, is_text) {
+        (Value::Text(text), true) => text.push_str(&self.text()?),
+        (Value::Bytes(bytes), false) => bytes.extend(self.bytes()?),
+        _ => return Err(self.error("text and byte strings can't be concatenated")),
+      }
+    }
+  }
+
+  // Parses (_ "a", "b"), i.e. an indefinite-length string made of chunks
+  fn indefinite_string(&mut self) -> Result<Value, Error> {
+    self.expect('(')?;
+    self.indefinite_marker()?;
+    self.skip_whitespace()?;
+
+    let mut value = match self.peek() {
+      Some('"') => Value::Text(String::new()),
+      _ => Value::Bytes(Vec::new()),
+    };
+
+    if self.eat(')')? {
+      return Ok(value);
+    }
+
+    loop {
+      let chunk = self.item()?;
+
+      match (&mut value, chunk) {
+        (Value::Text(text), Value::Text(chunk)) => text.push_str(&chunk),
+        (Value::Bytes(bytes), Value::Bytes(chunk)) => bytes.extend(chunk),
+        _ => {
+          return Err(self.error("chunks of indefinite-length strings must be of the same kind"))
+        }
+      }
+
+      if self.eat(')')? {
+        return Ok(value);
+      }
+
+      self.expect(',')?;
+    }
+  }
+
+  fn is_byte_string_prefix(&self) -> bool {
+    let rest = self.rest();
+
+    ["h'", "b64'", "b32'", "h32'"]
+      .iter()
+      .any(|prefix| rest.starts_with(prefix))
+  }
+
+  fn text(&mut self) -> Result<String, Error> {
+    let start = self.position;
+    self.bump();
+
+    // Text strings use JSON escapes, so they're decoded with serde_json
+    let mut escaped = false;
+    loop {
+      match self.bump() {
+        Some('\\') if !escaped => escaped = true,
+        Some('"') if !escaped => break,
+        Some(_) => escaped = false,
+        None => {
+          self.position = start;
+          return Err(self.error("unterminated text string"));
+        }
+      }
+    }
+
+    let text = serde_json::from_str(&self.input[start..self.position]).map_err(|e| Error {
+      position: start,
+      message: format!("invalid text string: {}", e),
+    })?;
+    self.encoding_indicator();
+
+    Ok(text)
+  }
+
+  fn bytes(&mut self) -> Result<Vec<u8>, Error> {
+    let start = self.position;
+
+    if self.rest().starts_with("<<") {
+      self.position += 2;
+
+      let mut bytes = Vec::new();
+      self.skip_whitespace()?;
+      if self.rest().starts_with(">>") {
+        self.position += 2;
+        return Ok(bytes);
+      }
+
+      loop {
+        let item = self.item()?;
+        bytes.extend(serde_cbor::to_vec(&item).map_err(|e| self.error(e.to_string()))?);
+
+        self.skip_whitespace()?;
+        if self.rest().starts_with(">>") {
+          self.position += 2;
+          return Ok(bytes);
+        }
+
+        self.expect(',')?;
+      }
+    }
+
+    let prefix_len = self.rest().find('\'').unwrap_or(0);
+    let prefix = &self.rest()[..prefix_len];
+    self.position += prefix_len + 1;
+
+    let end = match self.rest().find('\'') {
+      Some(end) => end,
+      None => {
+        self.position = start;
+        return Err(self.error("unterminated byte string"));
+      }
+    };
+    let content = &self.rest()[..end];
+    self.position += end + 1;
+
+    // Whitespace is permitted within encoded byte strings
+    let encoded = content
+      .chars()
+      .filter(|c| !c.is_whitespace())
+      .collect::<String>();
+
+    let bytes = match prefix {
+      "" => Some(content.as_bytes().to_vec()),
+      "h" => decode_base16(&encoded),
+      "b64" => decode_base64(&encoded),
+      _ => {
+        self.position = start;
+        return Err(self.error(format!("unsupported byte string encoding {}''", prefix)));
+      }
+    };
+
+    bytes.ok_or_else(|| Error {
+      position: start,
+      message: format!("invalid {}'' byte string", prefix),
+    })
+  }
+
+  fn number(&mut self) -> Result<Value, Error> {
+    let start = self.position;
+
+    let is_negative = match self.peek() {
+      Some('-') => {
+        self.bump();
+        true
+      }
+      Some('+') => {
+        self.bump();
+        false
+      }
+      _ => false,
+    };
+
+    if self.rest().starts_with("Infinity") {
+      self.position += "Infinity".len();
+      self.encoding_indicator();
+
+      let infinity = f64::INFINITY;
+      return Ok(Value::Float(if is_negative { -infinity } else { infinity }));
+    }
+
+    let radix = match self.rest().get(..2) {
+      Some("0x") => 16,
+      Some("0o") => 8,
+      Some("0b") => 2,
+      _ => 10,
+    };
+
+    if radix != 10 {
+      self.position += 2;
+    }
+
+    let len = self
+      .rest()
+      .find(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '+' || c == '-'))
+      .unwrap_or_else(|| self.rest().len());
+    let literal = &self.rest()[..len];
+    let is_float = radix == 10 && literal.contains(&['.', 'e', 'E'][..]);
+    let digits = if is_float {
+      literal
+    } else {
+      literal.trim_end_matches(&['+', '-'][..])
+    };
+    self.position += digits.len();
+
+    let invalid = |position| Error {
+      position,
+      message: "invalid number".to_string(),
+    };
+
+    let value = if is_float {
+      let float = digits.parse::<f64>().map_err(|_| invalid(start))?;
+      Value::Float(if is_negative { -float } else { float })
+    } else {
+      let integer = u128::from_str_radix(digits, radix).map_err(|_| invalid(start))? as i128;
+      let integer = if is_negative { -integer } else { integer };
+
+      // Integers are limited to the arguments of major types 0 and 1
+      if !(-0x1_0000_0000_0000_0000..=0xffff_ffff_ffff_ffff).contains(&integer) {
+        return Err(invalid(start));
+      }
+
+      Value::Integer(integer)
+    };
+
+    self.encoding_indicator();
+
+    // Tags are written as their number followed by the enclosed data item
+    if let Value::Integer(tag) = value {
+      if self.peek() == Some('(') {
+        if tag < 0 || tag as u64 == SIMPLE_VALUE_TAG {
+          return Err(invalid(start));
+        }
+
+        self.bump();
+        let value = self.item()?;
+        self.expect(')')?;
+
+        return Ok(Value::Tag(tag as u64, Box::new(value)));
+      }
+    }
+
+    Ok(value)
+  }
+
+  fn word(&mut self) -> Result<Value, Error> {
+    if self.is_byte_string_prefix() {
+      return self.strings(Value::Bytes(Vec::new()));
+    }
+
+    let start = self.position;
+    let len = self
+      .rest()
+      .find(|c: char| !c.is_ascii_alphanumeric())
+      .unwrap_or_else(|| self.rest().len());
+    let word = &self.rest()[..len];
+    self.position += len;
+
+    let value = match word {
+      "true" => Value::Bool(true),
+      "false" => Value::Bool(false),
+      "null" => Value::Null,
+      "undefined" => simple_value(23),
+      "simple" if self.eat('(')? => {
+        let argument = self.item()?;
+        self.expect(')')?;
+
+        match argument {
+          // Simple values 24 to 31 are reserved (RFC 8949, section 3.3)
+          Value::Integer(n @ 0..=23) | Value::Integer(n @ 32..=255) => simple_value(n as u8),
+          _ => {
+            self.position = start;
+            return Err(self.error("invalid simple value"));
+          }
+        }
+      }
+      "NaN" => Value::Float(f64::NAN),
+      "Infinity" => Value::Float(f64::INFINITY),
+      _ => {
+        self.position = start;
+        return Err(self.error(format!("unsupported data item \"{}\"", word)));
+      }
+    };
+
+    self.encoding_indicator();
+
+    Ok(value)
+  }
+}
+
+fn decode_base16(encoded: &str) -> Option<Vec<u8>> {
+  let mut bytes = vec![0; encoded.len() / 2];
+  let len = base16::decode_slice(encoded, &mut bytes).ok()?;
+  bytes.truncate(len);
+
+  Some(bytes)
+}
+
+// Decodes base64 in either the URL-safe or the classic alphabet, with or
+// without padding
+fn decode_base64(encoded: &str) -> Option<Vec<u8>> {
+  let encoded = encoded
+    .trim_end_matches('=')
+    .replace('+', "-")
+    .replace('/', "_");
+
+  let mut bytes = vec![0; encoded.len() * 3 / 4 + 3];
+  let len = base64::decode_config_slice(&encoded, base64::URL_SAFE_NO_PAD, &mut bytes).ok()?;
+  bytes.truncate(len);
+
+  Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{cbor_core, validation::cbor::decode_cbor};
+  use pretty_assertions::assert_eq;
+
+  #[test]
+  fn verify_parse() {
+    let value = parse(
+      r#"
+        / a comment / {
+          "text": "a" "b",
+          1: h'01 02' h'03',
+          -1: [_ 1.5, -0x10, 0b11, Infinity, true, null],
+          'bytes': b64'AQID',
+          "cbor": <<1, "a">>,
+          "chunks": (_ h'01', h'02'),
+          "tag": 0("2013-03-21T20:04:00Z"),
+          "indicator": 1_1
+        }
+      "#,
+    )
+    .unwrap();
+
+    let mut expected = BTreeMap::new();
+    expected.insert(Value::Text("text".into()), Value::Text("ab".into()));
+    expected.insert(Value::Integer(1), Value::Bytes(vec![1, 2, 3]));
+    expected.insert(
+      Value::Integer(-1),
+      Value::Array(vec![
+        Value::Float(1.5),
+        Value::Integer(-16),
+        Value::Integer(3),
+        Value::Float(f64::INFINITY),
+        Value::Bool(true),
+        Value::Null,
+      ]),
+    );
+    expected.insert(Value::Bytes(b"bytes".to_vec()), Value::Bytes(vec![1, 2, 3]));
+    expected.insert(
+      Value::Text("cbor".into()),
+      Value::Bytes(vec![0x01, 0x61, 0x61]),
+    );
+    expected.insert(Value::Text("chunks".into()), Value::Bytes(vec![1, 2]));
+    expected.insert(
+      Value::Text("tag".into()),
+      Value::Tag(0, Box::new(Value::Text("2013-03-21T20:04:00Z".into()))),
+    );
+    expected.insert(Value::Text("indicator".into()), Value::Integer(1));
+
+    assert_eq!(value, Value::Map(expected));
+  }
+
+  #[test]
+  fn verify_parse_simple_values() {
+    assert_eq!(parse("simple(20)").unwrap(), Value::Bool(false));
+    assert_eq!(parse("simple(22)").unwrap(), parse("null").unwrap());
+    assert_eq!(parse("undefined").unwrap(), simple_value(23));
+    assert_eq!(parse("simple(23)").unwrap(), simple_value(23));
+    assert_ne!(parse("undefined").unwrap(), Value::Null);
+
+    // Diagnostic notation written for decoded data items parses back into the
+    // same values the validators decode them as
+    for cbor in &[
+      &[0xf7][..],
+      &[0xf0],
+      &[0xf8, 0xff],
+      &[0x83, 0xf4, 0xf6, 0xf7],
+      &[0xa1, 0xf7, 0xd8, 0x20, 0xf8, 0x20],
+    ] {
+      let diag = cbor_core::decode(cbor).unwrap().to_string();
+
+      assert_eq!(
+        parse(&diag).unwrap(),
+        decode_cbor(cbor).unwrap(),
+        "{}",
+        diag
+      );
+    }
+  }
+
+  #[test]
+  fn verify_parse_seq() {
+    assert_eq!(
+      parse_seq(r#"1, "a", [2]"#).unwrap(),
+      vec![
+        Value::Integer(1),
+        Value::Text("a".into()),
+        Value::Array(vec![Value::Integer(2)]),
+      ]
+    );
+    assert_eq!(parse_seq(" ").unwrap(), Vec::<Value>::new());
+  }
+
+  #[test]
+  fn verify_parse_errors() {
+    assert_eq!(parse("[1, 2").unwrap_err().position, 5);
+    assert_eq!(parse(r#"{"a": 1, "a": 2}"#).unwrap_err().position, 9);
+    assert_eq!(parse("[simple(24)]").unwrap_err().position, 1);
+    assert_eq!(parse("simple(256)").unwrap_err().position, 0);
+    assert_eq!(parse("simple").unwrap_err().position, 0);
+    assert!(parse("18446744073709551615(23)").is_err());
+    assert_eq!(parse("h'0'").unwrap_err().position, 0);
+    assert_eq!(parse(r#""a" h'01'"#).unwrap_err().position, 4);
+    assert!(parse("1 2").is_err());
+    assert!(parse("18446744073709551616").is_err());
+  }
+}
@@ -0,0 +1,52 @@
+#[cfg(not(feature = "std"))]
+use alloc::string;
+#[cfg(not(feature = "std"))]
+use core::num;
+use std::fmt;
+
+/// Trait implemented by the errors of this crate. This is
+/// `std::error::Error` when the `std` feature is enabled.
+#[cfg(feature = "std")]
+pub use std::error::Error;
+
+/// Trait implemented by the errors of this crate, standing in for
+/// `std::error::Error` when the `std` feature is disabled
+#[cfg(not(feature = "std"))]
+pub trait Error: fmt::Debug + fmt::Display {
+  /// Returns the lower-level error that caused this error, if any
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    None
+  }
+}
+
+#[cfg(not(feature = "std"))]
+impl Error for string::FromUtf8Error {}
+
+#[cfg(not(feature = "std"))]
+impl Error for num::ParseIntError {}
+
+/// Limit placed on untrusted input via `parser::ParserOptions` or
+/// `validation::ValidationOptions`, reported by `parser::Error::LimitExceeded`
+/// or `validation::Error::Limit` respectively once exceeded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+  /// Nesting depth of types and groups in a CDDL definition
+  NestingDepth,
+  /// Number of rules in a CDDL definition
+  Rules,
+  /// Number of alternatives of a single type or group
+  Choices,
+  /// Number of elements of an array being validated
+  ArrayLength,
+}
+
+impl fmt::Display for Limit {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Limit::NestingDepth => write!(f, "nesting depth"),
+      Limit::Rules => write!(f, "number of rules"),
+      Limit::Choices => write!(f, "number of choices"),
+      Limit::ArrayLength => write!(f, "array length"),
+    }
+  }
+}
@@ -0,0 +1,442 @@
+use super::{
+  loader::{directives, load_schema, CddlLoader, Error, Source},
+  schema::CddlSchema,
+};
+use std::{
+  borrow::Borrow,
+  collections::HashMap,
+  future::Future,
+  hash::{BuildHasher, Hash},
+  pin::Pin,
+  sync::Mutex,
+};
+
+/// Future resolving to the text of a schema fetched by a
+/// [`SchemaFetcher`](trait.SchemaFetcher.html), or to a message describing why
+/// it couldn't be fetched
+pub type FetchFuture<'a> = Pin<Box<dyn Future<Output = Result<String, String>> + Send + 'a>>;
+
+/// Retrieves the text of CDDL schemas by URL, e.g. over HTTP
+pub trait SchemaFetcher {
+  /// Fetches the schema at the given URL
+  fn fetch<'a>(&'a self, url: &'a str) -> FetchFuture<'a>;
+}
+
+/// Fetches schemas held in memory, keyed by URL, e.g. for tests or for
+/// mirroring remote schemas
+impl<K, V, S> SchemaFetcher for HashMap<K, V, S>
+where
+  K: Borrow<str> + Hash + Eq,
+  V: AsRef<str>,
+  S: BuildHasher,
+{
+  fn fetch<'a>(&'a self, url: &'a str) -> FetchFuture<'a> {
+    let result = self
+      .get(url)
+      .map(|text| text.as_ref().to_string())
+      .ok_or_else(|| format!("no schema at {}", url));
+
+    Box::pin(async move { result })
+  }
+}
+
+/// Storage consulted for a schema before it's fetched, and updated once it has
+/// been, so that schemas aren't fetched again on every load
+pub trait SchemaCache {
+  /// Returns the text of the schema at the given URL, if cached
+  fn get(&self, url: &str) -> Option<String>;
+
+  /// Stores the text of the schema fetched from the given URL
+  fn insert(&self, url: &str, text: &str);
+}
+
+/// Cache keeping fetched schemas in memory for as long as it lives
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+  schemas: Mutex<HashMap<String, String>>,
+}
+
+impl MemoryCache {
+  /// Creates an empty cache
+  pub fn new() -> Self {
+    MemoryCache::default()
+  }
+}
+
+impl SchemaCache for MemoryCache {
+  fn get(&self, url: &str) -> Option<String> {
+    self.schemas.lock().ok()?.get(url).cloned()
+  }
+
+  fn insert(&self, url: &str, text: &str) {
+    // A poisoned lock only means the schema isn't cached
+    if let Ok(mut schemas) = self.schemas.lock() {
+      schemas.insert(url.to_string(), text.to_string());
+    }
+  }
+}
+
+/// Fetcher that looks schemas up in a cache before fetching them with another
+/// fetcher, caching those it fetches
+#[derive(Debug)]
+pub struct CachedFetcher<F, C> {
+  fetcher: F,
+  cache: C,
+}
+
+impl<F, C> CachedFetcher<F, C> {
+  /// Wraps a fetcher with a cache
+  pub fn new(fetcher: F, cache: C) -> Self {
+    CachedFetcher { fetcher, cache }
+  }
+
+  /// Returns the cache
+  pub fn cache(&self) -> &C {
+    &self.cache
+  }
+}
+
+impl<F, C> SchemaFetcher for CachedFetcher<F, C>
+where
+  F: SchemaFetcher + Sync,
+  C: SchemaCache + Sync,
+{
+  fn fetch<'a>(&'a self, url: &'a str) -> FetchFuture<'a> {
+    Box::pin(async move {
+      if let Some(text) = self.cache.get(url) {
+        return Ok(text);
+      }
+
+      let text = self.fetcher.fetch(url).await?;
+      self.cache.insert(url, &text);
+
+      Ok(text)
+    })
+  }
+}
+
+/// Fetches schemas over HTTP(S) with [reqwest](https://docs.rs/reqwest).
+/// Requires the `http` feature.
+///
+/// Responses with an error status are reported as errors.
+#[cfg(feature = "http")]
+#[derive(Debug, Clone, Default)]
+pub struct HttpFetcher {
+  client: reqwest::Client,
+}
+
+#[cfg(feature = "http")]
+impl HttpFetcher {
+  /// Creates a fetcher with a default client
+  pub fn new() -> Self {
+    HttpFetcher::default()
+  }
+
+  /// Creates a fetcher with the given client, e.g. one configured with
+  /// timeouts, proxies or default headers
+  pub fn with_client(client: reqwest::Client) -> Self {
+    HttpFetcher { client }
+  }
+}
+
+#[cfg(feature = "http")]
+impl SchemaFetcher for HttpFetcher {
+  fn fetch<'a>(&'a self, url: &'a str) -> FetchFuture<'a> {
+    Box::pin(async move {
+      let response = self
+        .client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?;
+
+      response.text().await.map_err(|e| e.to_string())
+    })
+  }
+}
+
+/// Fetches the schema at the given URL along with every schema it references
+/// via `;# include` and `;# import` directives, merging their rules as
+/// [`load_schema`](../loader/fn.load_schema.html) does
+///
+/// Names given to directives are resolved against the URL of the schema
+/// containing them unless they're URLs themselves, with the `.cddl` extension
+/// added if they don't have one. Each schema is fetched once, and schemas are
+/// identified by their URL in errors.
+///
+/// # Example
+///
+/// ```
+/// use cddl::{fetch::fetch_schema, validation::Validator};
+/// use std::collections::HashMap;
+/// # use std::{future::Future, task::{Context, Poll, RawWaker, RawWakerVTable, Waker}};
+/// # fn noop(_: *const ()) {}
+/// # fn clone(_: *const ()) -> RawWaker {
+/// #   RawWaker::new(std::ptr::null(), &VTABLE)
+/// # }
+/// # static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+///
+/// let mut remote = HashMap::new();
+/// remote.insert(
+///   "https://example.com/specs/message.cddl",
+///   ";# include ../common/id\nmessage = { id: id, body: tstr }",
+/// );
+/// remote.insert("https://example.com/common/id.cddl", "id = uint");
+///
+/// let mut future = Box::pin(fetch_schema(&remote, "https://example.com/specs/message.cddl"));
+///
+/// # let waker = unsafe { Waker::from_raw(clone(std::ptr::null())) };
+/// # let mut cx = Context::from_waker(&waker);
+/// // Usually awaited within an async runtime
+/// let schema = match future.as_mut().poll(&mut cx) {
+///   Poll::Ready(schema) => schema.unwrap(),
+///   Poll::Pending => unreachable!("schemas in memory are always ready"),
+/// };
+///
+/// let json = serde_json::json!({ "id": 1, "body": "hello" });
+/// assert!(schema.cddl().validate(&json).is_ok());
+/// ```
+pub async fn fetch_schema<F: SchemaFetcher + ?Sized>(
+  fetcher: &F,
+  url: &str,
+) -> Result<CddlSchema, Error> {
+  let mut fetched = HashMap::new();
+  let mut pending = vec![(url.to_string(), None)];
+
+  while let Some((name, importer)) = pending.pop() {
+    let url = resolve_url(&name, importer.as_deref());
+    if fetched.contains_key(&url) {
+      continue;
+    }
+
+    let text = fetcher.fetch(&url).await.map_err(|message| Error::Load {
+      name,
+      importer,
+      message,
+    })?;
+
+    for (_, name) in directives(&text) {
+      pending.push((name, Some(url.clone())));
+    }
+
+    fetched.insert(url, text);
+  }
+
+  load_schema(&Fetched(fetched), url)
+}
+
+// Schemas fetched ahead of merging them, keyed by URL
+struct Fetched(HashMap<String, String>);
+
+impl CddlLoader for Fetched {
+  fn load(&self, name: &str, importer: Option<&str>) -> Result<Source, String> {
+    let url = resolve_url(name, importer);
+
+    match self.0.get(&url) {
+      Some(text) => Ok(Source {
+        id: url,
+        text: text.clone(),
+      }),
+      None => Err(format!("{} wasn't fetched", url)),
+    }
+  }
+}
+
+// Resolves the name given to a directive against the URL of the schema
+// containing it, adding the .cddl extension if the name doesn't have one
+fn resolve_url(name: &str, base: Option<&str>) -> String {
+  let mut name = name.to_string();
+
+  let file_name = name.rsplit('/').next().unwrap_or_default();
+  if !file_name.contains('.') && !name.contains(&['?', '#'][..]) {
+    name.push_str(".cddl");
+  }
+
+  let base = match base {
+    Some(base) if !name.contains("://") => base,
+    _ => return name,
+  };
+
+  // Scheme and authority of the base, e.g. https://example.com
+  let base = base.split(&['?', '#'][..]).next().unwrap_or_default();
+  let origin_end = base
+    .find("://")
+    .map(|idx| idx + 3)
+    .and_then(|start| base[start..].find('/').map(|idx| start + idx))
+    .unwrap_or(base.len());
+  let (origin, base_path) = base.split_at(origin_end);
+
+  let mut segments = if name.starts_with('/') {
+    Vec::new()
+  } else {
+    let mut segments = base_path.split('/').collect::<Vec<_>>();
+    segments.pop();
+    segments
+  };
+
+  for segment in name.split('/') {
+    match segment {
+      "" | "." => (),
+      ".." => {
+        segments.pop();
+      }
+      segment => segments.push(segment),
+    }
+  }
+
+  segments.retain(|s| !s.is_empty());
+
+  format!("{}/{}", origin, segments.join("/"))
+}
+
+#[cfg(test)]
+#[cfg(feature = "json-validation")]
+pub(crate) mod tests {
+  use super::*;
+  use crate::validation::Validator;
+  use serde_json::json;
+  use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+  };
+
+  // Polls a future that never waits, as is the case for in-memory fetchers and
+  // registries
+  pub(crate) fn ready<T>(future: impl Future<Output = T>) -> T {
+    fn noop(_: *const ()) {}
+    fn clone(_: *const ()) -> RawWaker {
+      RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+    let mut future = Box::pin(future);
+    let waker = unsafe { Waker::from_raw(clone(std::ptr::null())) };
+    match future.as_mut().poll(&mut Context::from_waker(&waker)) {
+      Poll::Ready(value) => value,
+      Poll::Pending => panic!("future isn't ready"),
+    }
+  }
+
+  // Counts the schemas fetched by the wrapped fetcher
+  struct Counting<'a> {
+    schemas: HashMap<&'a str, &'a str>,
+    count: AtomicUsize,
+  }
+
+  impl<'a> SchemaFetcher for Counting<'a> {
+    fn fetch<'b>(&'b self, url: &'b str) -> FetchFuture<'b> {
+      self.count.fetch_add(1, Ordering::SeqCst);
+      self.schemas.fetch(url)
+    }
+  }
+
+  #[test]
+  fn verify_resolve_url() {
+    let base = Some("https://example.com/specs/v1/message.cddl?rev=2");
+
+    assert_eq!(
+      resolve_url("common", base),
+      "https://example.com/specs/v1/common.cddl"
+    );
+    assert_eq!(
+      resolve_url("./types/id.cddl", base),
+      "https://example.com/specs/v1/types/id.cddl"
+    );
+    assert_eq!(
+      resolve_url("../../shared/id", base),
+      "https://example.com/shared/id.cddl"
+    );
+    assert_eq!(resolve_url("/root", base), "https://example.com/root.cddl");
+    assert_eq!(
+      resolve_url("https://other.org/cose", base),
+      "https://other.org/cose.cddl"
+    );
+    assert_eq!(
+      resolve_url("https://example.com/spec.cddl", None),
+      "https://example.com/spec.cddl"
+    );
+    assert_eq!(
+      resolve_url("https://example.com/schema?id=3", None),
+      "https://example.com/schema?id=3"
+    );
+  }
+
+  #[test]
+  fn verify_fetch_schema() -> Result<(), Error> {
+    let mut schemas = HashMap::new();
+    schemas.insert(
+      "https://example.com/specs/message.cddl",
+      ";# include common\n;# import https://other.org/cose\nmessage = { id: id, sig: signature }",
+    );
+    schemas.insert(
+      "https://example.com/specs/common.cddl",
+      ";# include message\nid = uint",
+    );
+    schemas.insert(
+      "https://other.org/cose.cddl",
+      "signature = bstr\nunused = tstr",
+    );
+
+    let schema = ready(fetch_schema(
+      &schemas,
+      "https://example.com/specs/message.cddl",
+    ))?;
+    let names = schema
+      .cddl()
+      .rules
+      .iter()
+      .map(|r| r.name())
+      .collect::<Vec<_>>();
+    assert_eq!(names, ["message", "id", "signature"]);
+
+    let json = json!({ "id": 1, "sig": "not bytes" });
+    assert!(schema.cddl().validate(&json).is_err());
+
+    schemas.remove("https://example.com/specs/common.cddl");
+    match ready(fetch_schema(
+      &schemas,
+      "https://example.com/specs/message.cddl",
+    )) {
+      Err(Error::Load { name, importer, .. }) => {
+        assert_eq!(name, "common");
+        assert_eq!(
+          importer.as_deref(),
+          Some("https://example.com/specs/message.cddl")
+        );
+      }
+      result => panic!(
+        "unexpected result: {:?}",
+        result.map(|s| s.source().to_string())
+      ),
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn verify_cached_fetcher() -> Result<(), Error> {
+    let mut schemas = HashMap::new();
+    schemas.insert("https://example.com/a.cddl", ";# include b\na = [* b]");
+    schemas.insert("https://example.com/b.cddl", "b = uint");
+
+    let fetcher = CachedFetcher::new(
+      Counting {
+        schemas,
+        count: AtomicUsize::new(0),
+      },
+      MemoryCache::new(),
+    );
+
+    ready(fetch_schema(&fetcher, "https://example.com/a.cddl"))?;
+    ready(fetch_schema(&fetcher, "https://example.com/a.cddl"))?;
+
+    assert_eq!(fetcher.fetcher.count.load(Ordering::SeqCst), 2);
+    assert_eq!(
+      fetcher.cache().get("https://example.com/b.cddl"),
+      Some("b = uint".to_string())
+    );
+
+    Ok(())
+  }
+}
@@ -0,0 +1,94 @@
+use super::ast::CDDL;
+use sha2::{Digest, Sha256};
+
+#[cfg(not(feature = "std"))]
+use alloc::string::ToString;
+
+impl<'a> CDDL<'a> {
+  /// Returns an identifier of the definition computed as the SHA-256 digest
+  /// of its normalized form, so that it only changes when the data the
+  /// definition accepts may have changed rather than when it's reformatted,
+  /// commented or reorganized. Protocols can embed the fingerprint in
+  /// messages, e.g. as the content of a CBOR tag, to identify the schema they
+  /// conform to, and tooling can compare the fingerprints of deployed
+  /// definitions to detect drift.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{lexer_from_str, parser::cddl_from_str};
+  ///
+  /// let a = "person = { age: uint, name: tstr }";
+  /// let b = "person = {\n  name: name, ; full name\n  age: uint,\n}\nname = tstr";
+  ///
+  /// let a = cddl_from_str(&mut lexer_from_str(a), a, true).unwrap();
+  /// let b = cddl_from_str(&mut lexer_from_str(b), b, true).unwrap();
+  ///
+  /// assert_eq!(a.fingerprint(), b.fingerprint());
+  /// ```
+  pub fn fingerprint(&self) -> [u8; 32] {
+    sha256(self.normalize().to_string().as_bytes())
+  }
+}
+
+fn sha256(input: &[u8]) -> [u8; 32] {
+  let mut digest = [0; 32];
+  digest.copy_from_slice(&Sha256::digest(input));
+  digest
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+  use super::*;
+  use crate::{lexer::Lexer, parser::cddl_from_str};
+
+  fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+  }
+
+  fn fingerprint(input: &str) -> [u8; 32] {
+    cddl_from_str(&mut Lexer::new(input), input, true)
+      .unwrap()
+      .fingerprint()
+  }
+
+  #[test]
+  fn verify_sha256() {
+    assert_eq!(
+      hex(&sha256(b"")),
+      "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(
+      hex(&sha256(b"abc")),
+      "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(
+      hex(&sha256(
+        b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+      )),
+      "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+    );
+    assert_eq!(
+      hex(&sha256(&[b'a'; 1_000_000])),
+      "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+    );
+  }
+
+  #[test]
+  fn verify_fingerprint() {
+    let a = fingerprint("a = { x: int, y: b }\nb = tstr / bstr");
+    let b = fingerprint("a = {\n  y: tstr / bstr, ; reordered\n  x: int,\n}");
+    let c = fingerprint("a = { x: int, y: tstr }");
+
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+
+    // Fingerprints are exchanged between peers, so they mustn't change between
+    // releases
+    assert_eq!(
+      hex(&a),
+      "f7afbde6187a30e101892be1e3476116728b65d6f120a0029c0f771949a41e4c"
+    );
+  }
+}
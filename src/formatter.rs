@@ -0,0 +1,870 @@
+use super::{ast::*, lexer::Lexer, parser::cddl_from_str, token::Token};
+
+/// Options used to configure the formatter
+#[derive(Debug, Clone)]
+pub struct FormatterOptions {
+  /// Number of spaces per level of indentation
+  pub indent_width: usize,
+  /// Maximum line width before a group is broken across multiple lines
+  pub max_width: usize,
+  /// Whether or not to align the types of entries with member keys within a
+  /// multi-line group
+  pub align_member_keys: bool,
+}
+
+impl Default for FormatterOptions {
+  fn default() -> Self {
+    FormatterOptions {
+      indent_width: 2,
+      max_width: 80,
+      align_member_keys: true,
+    }
+  }
+}
+
+/// Formats CDDL text, returning the formatted text or an error message if the
+/// input isn't conformant
+///
+/// Whitespace is normalized and groups are laid out either on a single line or
+/// with one entry per line. A group is kept on a single line if it was written
+/// that way, it fits within `max_width` and it doesn't contain any comments.
+/// Comments are preserved, either on their own line or trailing the rule or
+/// group entry they follow.
+///
+/// # Arguments
+///
+/// * `input` - A string slice with the CDDL text input
+/// * `options` - Formatting options
+///
+/// # Example
+///
+/// ```
+/// use cddl::formatter::{format_str, FormatterOptions};
+///
+/// let input = r#"person={ ; a person
+///   name:tstr,
+///   ? age :uint
+/// }"#;
+///
+/// assert_eq!(
+///   format_str(input, &FormatterOptions::default()).unwrap(),
+///   r#"person = { ; a person
+///   name:  tstr,
+///   ? age: uint,
+/// }
+/// "#
+/// );
+/// ```
+pub fn format_str(input: &str, options: &FormatterOptions) -> Result<String, String> {
+  let mut lexer = Lexer::new(input);
+  let cddl = cddl_from_str(&mut lexer, input, false)?;
+
+  let mut formatter = Formatter::new(input, options)?;
+
+  Ok(formatter.format_cddl(&cddl))
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Comment<'a> {
+  start: usize,
+  end: usize,
+  text: &'a str,
+  is_emitted: bool,
+}
+
+/// A formatted group entry, split into the occurrence indicator and member key
+/// (if any) and the remainder of the entry so that entries can be aligned
+struct Entry<'a> {
+  leading_comments: Vec<Comment<'a>>,
+  key: Option<String>,
+  value: String,
+  trailing_comments: Vec<Comment<'a>>,
+}
+
+struct Formatter<'a, 'o> {
+  input: &'a str,
+  options: &'o FormatterOptions,
+  comments: Vec<Comment<'a>>,
+  /// Ranges of all tokens other than comments and commas
+  token_ranges: Vec<(usize, usize)>,
+}
+
+impl<'a, 'o> Formatter<'a, 'o> {
+  fn new(input: &'a str, options: &'o FormatterOptions) -> Result<Self, String> {
+    let mut comments = Vec::new();
+    let mut token_ranges = Vec::new();
+
+    // Comments aren't retained by the AST, so they're collected from a
+    // separate pass over the token stream and reattached using the ranges of
+    // the surrounding tokens. AST spans alone aren't precise enough, since
+    // they can extend over trailing comments and commas.
+    let mut lexer = Lexer::new(input);
+    loop {
+      match lexer.next_token().map_err(|e| e.to_string())? {
+        (_, Token::EOF) => break,
+        (position, Token::COMMENT(text)) => comments.push(Comment {
+          start: position.range.0,
+          end: position.range.0 + text.len() + 1,
+          text: text.trim_end(),
+          is_emitted: false,
+        }),
+        (_, Token::COMMA) => (),
+        (position, _) => token_ranges.push(position.range),
+      }
+    }
+
+    Ok(Formatter {
+      input,
+      options,
+      comments,
+      token_ranges,
+    })
+  }
+
+  fn format_cddl(&mut self, cddl: &CDDL) -> String {
+    let mut output = String::new();
+    let mut last_end = None;
+
+    for rule in cddl.rules.iter() {
+      let (start, end, _) = rule.span();
+      let (start, end) = (self.token_start_from(start), self.token_end_before(end));
+
+      for c in self.take_comments_before(start) {
+        self.push_blank_line(&mut output, last_end, c.start);
+        push_comment_line(&mut output, "", &c);
+        last_end = Some(c.end);
+      }
+
+      self.push_blank_line(&mut output, last_end, start);
+      output.push_str(&self.format_rule(rule));
+
+      let trailing_comments = self.take_trailing_comments(end);
+      push_trailing_comments(&mut output, &trailing_comments);
+      output.push('\n');
+
+      last_end = Some(trailing_comments.last().map(|c| c.end).unwrap_or(end));
+
+      // Any comments nested within the rule that couldn't be attached to a
+      // group entry
+      for c in self.take_comments_before(end) {
+        push_comment_line(&mut output, "", &c);
+      }
+    }
+
+    for c in self.take_comments_before(self.input.len() + 1) {
+      self.push_blank_line(&mut output, last_end, c.start);
+      push_comment_line(&mut output, "", &c);
+      last_end = Some(c.end);
+    }
+
+    output
+  }
+
+  fn format_rule(&mut self, rule: &Rule) -> String {
+    match rule {
+      Rule::Type { rule, .. } => {
+        let mut output = rule.name.to_string();
+        if let Some(gp) = &rule.generic_param {
+          output.push_str(&gp.to_string());
+        }
+
+        if rule.is_type_choice_alternate {
+          output.push_str(" /= ");
+        } else {
+          output.push_str(" = ");
+        }
+
+        let t = self.format_type(&rule.value, 0, output.len());
+        output.push_str(&t);
+
+        output
+      }
+      Rule::Group { rule, .. } => {
+        let mut output = rule.name.to_string();
+        if let Some(gp) = &rule.generic_param {
+          output.push_str(&gp.to_string());
+        }
+
+        if rule.is_group_choice_alternate {
+          output.push_str(" //= ");
+        } else {
+          output.push_str(" = ");
+        }
+
+        let entry = self.format_group_entry(&rule.entry, 0, output.len());
+        output.push_str(&entry.joined());
+
+        output
+      }
+    }
+  }
+
+  /// Formats type choices on a single line or, if they don't fit, with each
+  /// subsequent choice on its own indented line
+  fn format_type(&mut self, t: &Type, indent: usize, column: usize) -> String {
+    let comments = self.comments.clone();
+
+    let mut output = String::new();
+    for (idx, t1) in t.type_choices.iter().enumerate() {
+      if idx != 0 {
+        output.push_str(" / ");
+      }
+
+      let t1 = self.format_type1(t1, indent, end_column(column, &output));
+      output.push_str(&t1);
+    }
+
+    if t.type_choices.len() < 2
+      || (!output.contains('\n') && end_column(column, &output) <= self.options.max_width)
+    {
+      return output;
+    }
+
+    // Comments consumed by the first attempt are restored so that they're
+    // emitted by the second
+    self.comments = comments;
+
+    let choice_indent = " ".repeat((indent + 1) * self.options.indent_width);
+    let mut output = String::new();
+    for (idx, t1) in t.type_choices.iter().enumerate() {
+      if idx != 0 {
+        output.push('\n');
+        output.push_str(&choice_indent);
+        output.push_str("/ ");
+      }
+
+      let t1 = self.format_type1(t1, indent + 1, end_column(column, &output));
+      output.push_str(&t1);
+    }
+
+    output
+  }
+
+  fn format_type1(&mut self, t1: &Type1, indent: usize, column: usize) -> String {
+    let mut output = self.format_type2(&t1.type2, indent, column);
+
+    if let Some((op, t2)) = &t1.operator {
+      match op {
+        // Range operators are only spaced when an endpoint is a type name, since
+        // a range such as "min..max" would otherwise be lexed as a single
+        // identifier
+        RangeCtlOp::RangeOp { .. } => {
+          if is_typename(&t1.type2) || is_typename(t2) {
+            output.push_str(&format!(" {} ", op));
+          } else {
+            output.push_str(&op.to_string());
+          }
+        }
+        RangeCtlOp::CtlOp { .. } => output.push_str(&format!(" {} ", op)),
+      }
+
+      let t2 = self.format_type2(t2, indent, end_column(column, &output));
+      output.push_str(&t2);
+    }
+
+    output
+  }
+
+  fn format_type2(&mut self, t2: &Type2, indent: usize, column: usize) -> String {
+    match t2 {
+      Type2::Typename {
+        ident, generic_arg, ..
+      } => self.format_name("", ident, generic_arg, indent, column),
+      Type2::Unwrap {
+        ident, generic_arg, ..
+      } => self.format_name("~", ident, generic_arg, indent, column),
+      Type2::ChoiceFromGroup {
+        ident, generic_arg, ..
+      } => self.format_name("&", ident, generic_arg, indent, column),
+      Type2::ParenthesizedType { pt, .. } => {
+        format!("({})", self.format_type(pt, indent, column + 1))
+      }
+      Type2::TaggedData { tag, t, .. } => {
+        let prefix = match tag {
+          Some(tag) => format!("#6.{}(", tag),
+          None => String::from("#6("),
+        };
+        let t = self.format_type(t, indent, column + prefix.len());
+
+        format!("{}{})", prefix, t)
+      }
+      Type2::Map { group, span } => self.format_group("{", "}", group, *span, indent, column),
+      Type2::Array { group, span } => self.format_group("[", "]", group, *span, indent, column),
+      Type2::ChoiceFromInlineGroup { group, span } => format!(
+        "&{}",
+        self.format_group("(", ")", group, *span, indent, column + 1)
+      ),
+      _ => t2.to_string(),
+    }
+  }
+
+  fn format_name(
+    &mut self,
+    prefix: &str,
+    ident: &Identifier,
+    generic_arg: &Option<GenericArg>,
+    indent: usize,
+    column: usize,
+  ) -> String {
+    let mut output = format!("{}{}", prefix, ident);
+
+    if let Some(ga) = generic_arg {
+      output.push('<');
+      for (idx, arg) in ga.args.iter().enumerate() {
+        if idx != 0 {
+          output.push_str(", ");
+        }
+
+        let arg = self.format_type1(arg, indent, end_column(column, &output));
+        output.push_str(&arg);
+      }
+      output.push('>');
+    }
+
+    output
+  }
+
+  /// Formats a group enclosed by the given delimiters. The group is kept on a
+  /// single line if there's no line break following the opening delimiter in
+  /// the source, it doesn't contain any comments and it fits within the
+  /// maximum width. Otherwise, each entry is written on its own line.
+  fn format_group(
+    &mut self,
+    open: &str,
+    close: &str,
+    group: &Group,
+    span: Span,
+    indent: usize,
+    column: usize,
+  ) -> String {
+    let has_comments = self.has_comments_within(span.0, span.1);
+
+    if !has_comments
+      && group
+        .group_choices
+        .iter()
+        .all(|gc| gc.group_entries.is_empty())
+    {
+      return format!("{}{}", open, close);
+    }
+
+    let open_end = self
+      .input
+      .get(span.0..)
+      .and_then(|s| s.find(open))
+      .map(|idx| span.0 + idx + open.len());
+
+    let is_multiline_in_source = open_end
+      .and_then(|idx| self.input.get(idx..))
+      .map(|s| {
+        s.chars()
+          .take_while(|c| c.is_whitespace())
+          .any(|c| c == '\n')
+      })
+      .unwrap_or(false);
+
+    if !has_comments && !is_multiline_in_source {
+      let padding = if open == "{" { " " } else { "" };
+      let entries = self.format_group_inline(group, indent, column + open.len() + padding.len());
+      let output = format!("{}{}{}{}{}", open, padding, entries, padding, close);
+
+      if !output.contains('\n') && column + output.chars().count() <= self.options.max_width {
+        return output;
+      }
+    }
+
+    self.format_group_multiline(open, close, group, (open_end, span.1), indent)
+  }
+
+  fn format_group_inline(&mut self, group: &Group, indent: usize, column: usize) -> String {
+    let mut output = String::new();
+
+    for (idx, gc) in group.group_choices.iter().enumerate() {
+      if idx != 0 {
+        output.push_str(" // ");
+      }
+
+      for (idx, (ge, _)) in gc.group_entries.iter().enumerate() {
+        if idx != 0 {
+          output.push_str(", ");
+        }
+
+        let entry = self.format_group_entry(ge, indent, end_column(column, &output));
+        output.push_str(&entry.joined());
+      }
+    }
+
+    output
+  }
+
+  fn format_group_multiline(
+    &mut self,
+    open: &str,
+    close: &str,
+    group: &Group,
+    (open_end, end): (Option<usize>, usize),
+    indent: usize,
+  ) -> String {
+    let entry_indent = " ".repeat((indent + 1) * self.options.indent_width);
+    let mut output = open.to_string();
+
+    if let Some(idx) = open_end {
+      let trailing_comments = self.take_trailing_comments(idx);
+      push_trailing_comments(&mut output, &trailing_comments);
+    }
+    output.push('\n');
+
+    for (choice_idx, gc) in group.group_choices.iter().enumerate() {
+      if choice_idx != 0 {
+        output.push_str(&entry_indent);
+        output.push_str("//\n");
+      }
+
+      // Entries are formatted in order before being written out so that
+      // comments are consumed in the order they appear in the source
+      let mut entries = Vec::with_capacity(gc.group_entries.len());
+      for (ge, _) in gc.group_entries.iter() {
+        let (start, end, _) = group_entry_span(ge);
+
+        let leading_comments = self.take_comments_before(self.token_start_from(start));
+        let mut entry = self.format_group_entry(ge, indent + 1, entry_indent.len());
+        entry.leading_comments = leading_comments;
+        entry.trailing_comments = self.take_trailing_comments(self.token_end_before(end));
+
+        entries.push(entry);
+      }
+
+      let key_width = if self.options.align_member_keys {
+        entries
+          .iter()
+          .filter_map(|e| e.key.as_ref())
+          .filter(|k| !k.contains('\n'))
+          .map(|k| k.chars().count())
+          .max()
+      } else {
+        None
+      };
+
+      for (idx, entry) in entries.iter().enumerate() {
+        for c in entry.leading_comments.iter() {
+          push_comment_line(&mut output, &entry_indent, c);
+        }
+
+        output.push_str(&entry_indent);
+        match (&entry.key, key_width) {
+          (Some(key), Some(width)) if !key.contains('\n') => {
+            output.push_str(&format!("{:width$} {}", key, entry.value, width = width))
+          }
+          _ => output.push_str(&entry.joined()),
+        }
+
+        // The parser doesn't accept a comma preceding a group choice
+        // delimiter
+        if idx + 1 != entries.len() || choice_idx + 1 == group.group_choices.len() {
+          output.push(',');
+        }
+
+        push_trailing_comments(&mut output, &entry.trailing_comments);
+        output.push('\n');
+      }
+    }
+
+    for c in self.take_comments_before(end) {
+      push_comment_line(&mut output, &entry_indent, &c);
+    }
+
+    output.push_str(&" ".repeat(indent * self.options.indent_width));
+    output.push_str(close);
+
+    output
+  }
+
+  fn format_group_entry(&mut self, ge: &GroupEntry, indent: usize, column: usize) -> Entry<'a> {
+    let (key, value) = match ge {
+      GroupEntry::ValueMemberKey { ge, .. } => {
+        let occur = occur_prefix(&ge.occur);
+
+        match &ge.member_key {
+          Some(mk) => {
+            let key = format!(
+              "{}{}",
+              occur,
+              self.format_member_key(mk, indent, column + occur.len())
+            );
+            let value = self.format_type(&ge.entry_type, indent, end_column(column, &key) + 1);
+
+            (Some(key), value)
+          }
+          None => (
+            None,
+            format!(
+              "{}{}",
+              occur,
+              self.format_type(&ge.entry_type, indent, column + occur.len())
+            ),
+          ),
+        }
+      }
+      GroupEntry::TypeGroupname { ge, .. } => {
+        let occur = occur_prefix(&ge.occur);
+        let name = self.format_name("", &ge.name, &ge.generic_arg, indent, column + occur.len());
+
+        (None, format!("{}{}", occur, name))
+      }
+      GroupEntry::InlineGroup { occur, group, span } => {
+        let occur = occur_prefix(occur);
+        let group = self.format_group("(", ")", group, *span, indent, column + occur.len());
+
+        (None, format!("{}{}", occur, group))
+      }
+    };
+
+    Entry {
+      leading_comments: Vec::new(),
+      key,
+      value,
+      trailing_comments: Vec::new(),
+    }
+  }
+
+  fn format_member_key(&mut self, mk: &MemberKey, indent: usize, column: usize) -> String {
+    match mk {
+      MemberKey::Type1 { t1, is_cut, .. } => {
+        let t1 = self.format_type1(t1, indent, column);
+
+        if *is_cut {
+          format!("{} ^ =>", t1)
+        } else {
+          format!("{} =>", t1)
+        }
+      }
+      MemberKey::Bareword { ident, .. } => format!("{}:", ident),
+      MemberKey::Value { value, .. } => format!("{}:", value),
+      MemberKey::NonMemberKey(NonMemberKey::Group(g)) => {
+        self.format_group_inline(g, indent, column)
+      }
+      MemberKey::NonMemberKey(NonMemberKey::Type(t)) => self.format_type(t, indent, column),
+    }
+  }
+
+  /// Inserts a single blank line if there's at least one blank line in the
+  /// source between the end of the last item and the start of the next
+  fn push_blank_line(&self, output: &mut String, last_end: Option<usize>, start: usize) {
+    if let Some(end) = last_end {
+      if let Some(between) = self.input.get(end..start) {
+        if between.matches('\n').count() > 1 {
+          output.push('\n');
+        }
+      }
+    }
+  }
+
+  /// Returns the start of the first token at or following the given offset
+  fn token_start_from(&self, offset: usize) -> usize {
+    let idx = match self.token_ranges.binary_search_by_key(&offset, |r| r.0) {
+      Ok(idx) | Err(idx) => idx,
+    };
+
+    self.token_ranges.get(idx).map(|r| r.0).unwrap_or(offset)
+  }
+
+  /// Returns the end of the last token starting before the given offset
+  fn token_end_before(&self, offset: usize) -> usize {
+    let idx = match self.token_ranges.binary_search_by_key(&offset, |r| r.0) {
+      Ok(idx) | Err(idx) => idx,
+    };
+
+    match idx
+      .checked_sub(1)
+      .and_then(|idx| self.token_ranges.get(idx))
+    {
+      Some(r) => r.1,
+      None => offset,
+    }
+  }
+
+  fn has_comments_within(&self, start: usize, end: usize) -> bool {
+    self
+      .comments
+      .iter()
+      .any(|c| !c.is_emitted && c.start > start && c.start < end)
+  }
+
+  fn take_comments_before(&mut self, offset: usize) -> Vec<Comment<'a>> {
+    let mut comments = Vec::new();
+
+    for c in self.comments.iter_mut() {
+      if !c.is_emitted && c.start < offset {
+        c.is_emitted = true;
+        comments.push(*c);
+      }
+    }
+
+    comments
+  }
+
+  /// Returns the next comment if it starts on the same line as the given
+  /// offset, with nothing other than whitespace or a comma in between. Any
+  /// comments on the lines immediately following it that start in the same
+  /// column are treated as its continuation.
+  fn take_trailing_comments(&mut self, offset: usize) -> Vec<Comment<'a>> {
+    let mut comments: Vec<Comment<'a>> = Vec::new();
+    let input = self.input;
+
+    for c in self
+      .comments
+      .iter_mut()
+      .filter(|c| !c.is_emitted && c.start >= offset)
+    {
+      let is_attached = match comments.last() {
+        Some(last) => input
+          .get(last.end..c.start)
+          .map(|between| {
+            between.trim().is_empty()
+              && between.matches('\n').count() == 1
+              && source_column(input, last.start) == source_column(input, c.start)
+          })
+          .unwrap_or(false),
+        None => input
+          .get(offset..c.start)
+          .map(|between| {
+            between
+              .chars()
+              .all(|ch| ch == ',' || (ch.is_whitespace() && ch != '\n'))
+          })
+          .unwrap_or(false),
+      };
+
+      if !is_attached {
+        break;
+      }
+
+      c.is_emitted = true;
+      comments.push(*c);
+    }
+
+    comments
+  }
+}
+
+impl<'a> Entry<'a> {
+  fn joined(&self) -> String {
+    match &self.key {
+      Some(key) => format!("{} {}", key, self.value),
+      None => self.value.clone(),
+    }
+  }
+}
+
+/// Appends trailing comments to the last line of the output, aligning any
+/// continuation lines with the first comment
+fn push_trailing_comments(output: &mut String, comments: &[Comment]) {
+  let column = end_column(0, output) + 1;
+
+  for (idx, c) in comments.iter().enumerate() {
+    if idx == 0 {
+      output.push_str(" ;");
+    } else {
+      output.push('\n');
+      output.push_str(&" ".repeat(column));
+      output.push(';');
+    }
+
+    output.push_str(c.text);
+  }
+}
+
+fn push_comment_line(output: &mut String, indent: &str, c: &Comment) {
+  output.push_str(indent);
+  output.push(';');
+  output.push_str(c.text);
+  output.push('\n');
+}
+
+fn occur_prefix(occur: &Option<Occur>) -> String {
+  match occur {
+    Some(o) => format!("{} ", o),
+    None => String::new(),
+  }
+}
+
+fn is_typename(t2: &Type2) -> bool {
+  if let Type2::Typename { .. } = t2 {
+    return true;
+  }
+
+  false
+}
+
+fn group_entry_span(ge: &GroupEntry) -> Span {
+  match ge {
+    GroupEntry::ValueMemberKey { span, .. }
+    | GroupEntry::TypeGroupname { span, .. }
+    | GroupEntry::InlineGroup { span, .. } => *span,
+  }
+}
+
+fn source_column(input: &str, offset: usize) -> usize {
+  let line_start = input[..offset].rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+
+  input[line_start..offset].chars().count()
+}
+
+/// Returns the column at which the given output ends, if it starts at the given
+/// column
+fn end_column(column: usize, output: &str) -> usize {
+  match output.rfind('\n') {
+    Some(idx) => output[idx + 1..].chars().count(),
+    None => column + output.chars().count(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn format(input: &str) -> String {
+    format_str(input, &FormatterOptions::default()).unwrap()
+  }
+
+  #[test]
+  fn verify_normalized_whitespace() {
+    assert_eq!(
+      format("myrule=int/tstr\nother  =  [ * uint ]"),
+      "myrule = int / tstr\nother = [* uint]\n"
+    );
+    assert_eq!(
+      format("range = 0..10\nsize = bstr .size 16\nnames = min .. max"),
+      "range = 0..10\nsize = bstr .size 16\nnames = min .. max\n"
+    );
+    assert_eq!(
+      format("grp = ( a: int,b : tstr )\nm = {a: int}\ne = {}\nc = &( x: 1 , y: 2 )"),
+      "grp = (a: int, b: tstr)\nm = { a: int }\ne = {}\nc = &(x: 1, y: 2)\n"
+    );
+  }
+
+  #[test]
+  fn verify_aligned_member_keys() {
+    let input = r#"reputon = {
+rater: text,
+    ? confidence: float16,
+  * tstr => any
+  inner: { x: int, y: int } // alt: bool
+}"#;
+
+    assert_eq!(
+      format(input),
+      r#"reputon = {
+  rater:        text,
+  ? confidence: float16,
+  * tstr =>     any,
+  inner:        { x: int, y: int }
+  //
+  alt: bool,
+}
+"#
+    );
+
+    let options = FormatterOptions {
+      indent_width: 4,
+      align_member_keys: false,
+      ..Default::default()
+    };
+    assert_eq!(
+      format_str("m = {\na: int, bb: tstr}", &options).unwrap(),
+      "m = {\n    a: int,\n    bb: tstr,\n}\n"
+    );
+  }
+
+  #[test]
+  fn verify_long_groups_are_broken() {
+    let input =
+      "message = [first-name: tstr, last-name: tstr, address: tstr, phone: uint, email: tstr]";
+
+    assert_eq!(
+      format(input),
+      r#"message = [
+  first-name: tstr,
+  last-name:  tstr,
+  address:    tstr,
+  phone:      uint,
+  email:      tstr,
+]
+"#
+    );
+  }
+
+  #[test]
+  fn verify_long_type_choices_are_broken() {
+    let input = r#"color = "red" / "orange" / "yellow" / "green" / "blue" / "indigo" / "violet" / "ultraviolet""#;
+
+    assert_eq!(
+      format(input),
+      r#"color = "red"
+  / "orange"
+  / "yellow"
+  / "green"
+  / "blue"
+  / "indigo"
+  / "violet"
+  / "ultraviolet"
+"#
+    );
+  }
+
+  #[test]
+  fn verify_preserved_comments() {
+    let input = r#"; leading comment
+foo = {   ; opening comment
+  a: int, ; trailing a
+
+  ; before b
+  b: tstr
+  ; dangling
+} ; after foo
+
+
+bar = int / tstr ; end
+baz = [ int ]   ; a comment spanning
+                ; multiple lines
+; last"#;
+
+    assert_eq!(
+      format(input),
+      r#"; leading comment
+foo = { ; opening comment
+  a: int, ; trailing a
+  ; before b
+  b: tstr,
+  ; dangling
+} ; after foo
+
+bar = int / tstr ; end
+baz = [int] ; a comment spanning
+            ; multiple lines
+; last
+"#
+    );
+  }
+
+  #[test]
+  fn verify_idempotence() {
+    let input = r#"; a schema
+person = {
+  name: tstr, ; the name
+  ? age: uint .le 120,
+  * $$person-ext
+  address: [* line: tstr] // contact: &( email: 0, phone: 1 )
+}
+
+$$person-ext //= ( nickname: tstr )
+pair<a, b> = [a, b]
+names = pair<tstr, ~text-map>
+text-map = { * tstr => tstr }
+tagged = #6.32(tstr) / #7.25 / h'0102' / 1.0
+"#;
+
+    let formatted = format(input);
+    assert_eq!(format(&formatted), formatted);
+  }
+}
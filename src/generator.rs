@@ -0,0 +1,1671 @@
+use super::{
+  ast::*,
+  token::{self, Token},
+};
+use regex_syntax::hir::{self, Hir, HirKind, RepetitionKind, RepetitionRange};
+use serde_cbor::Value;
+use std::{
+  collections::{BTreeMap, HashMap},
+  convert::TryFrom,
+  error, fmt,
+  ops::Bound,
+  time::{SystemTime, UNIX_EPOCH},
+};
+
+// Unbounded numeric types are mostly sampled from this range so that
+// generated instances stay readable. Bounds of the type are still sampled
+// occasionally.
+const SAMPLE_RANGE: i128 = 1000;
+
+// Number of additional occurrences generated beyond the lower bound of an
+// unbounded occurrence indicator
+const MAX_EXTRA_OCCURRENCES: usize = 3;
+
+// Number of attempts made at generating distinct keys and values satisfying
+// .ne controls
+const MAX_ATTEMPTS: usize = 8;
+
+// Bounds of the integers representable by CBOR major types 0 and 1, and of
+// the integers representable by JSON numbers in serde_json
+const UINT_MAX: i128 = 0xffff_ffff_ffff_ffff;
+const NINT_MIN: i128 = -0x1_0000_0000_0000_0000;
+const JSON_INT_MIN: i128 = -0x8000_0000_0000_0000;
+
+// Largest finite half-precision float
+const FLOAT16_MAX: f64 = 65504.0;
+
+/// Kind of instances to generate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+  /// Random instances, e.g. for fuzzing
+  Random,
+  /// The smallest instance of a rule, choosing the choices with the smallest
+  /// encoded size and the least number of occurrences
+  Minimal,
+}
+
+/// Options used to configure the generator
+#[derive(Debug, Clone)]
+pub struct GeneratorOptions {
+  /// Kind of instances to generate
+  pub mode: Mode,
+  /// Seed for the random number generator. If `None`, a seed is derived from
+  /// the current time.
+  pub seed: Option<u64>,
+  /// Number of nested rule references after which the smallest instance of a
+  /// rule is generated rather than a random one, which ensures that recursive
+  /// rules terminate
+  pub max_depth: usize,
+}
+
+impl Default for GeneratorOptions {
+  fn default() -> Self {
+    GeneratorOptions {
+      mode: Mode::Random,
+      seed: None,
+      max_depth: 8,
+    }
+  }
+}
+
+/// Generation error
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+  /// The rule to generate an instance of isn't defined
+  MissingRule(String),
+  /// No instance of the rule could be generated, e.g. because it only allows
+  /// types that can't be represented by the target data model
+  Unsatisfiable(String),
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Error::MissingRule(rule) => write!(f, "rule \"{}\" is not defined", rule),
+      Error::Unsatisfiable(rule) => {
+        write!(f, "unable to generate an instance of rule \"{}\"", rule)
+      }
+    }
+  }
+}
+
+impl error::Error for Error {}
+
+/// Generates instances of the rules of a CDDL definition
+///
+/// Instances respect the choices, occurrence indicators, ranges and controls
+/// of the definition. `.size`, `.regexp`, `.pcre`, `.lt`, `.le`, `.gt`, `.ge`,
+/// `.eq`, `.ne`, `.cbor`, `.cborseq` and `.json` controls are honored. For `.and`,
+/// `.within` and `.default`, an instance of the target type is generated, and
+/// `.bits` yields values without any bits set.
+///
+/// # Example
+///
+/// ```
+/// use cddl::{
+///   generator::{Generator, GeneratorOptions, Mode},
+///   lexer_from_str,
+///   parser::cddl_from_str,
+/// };
+///
+/// let input = r#"person = { name: tstr .size (1..16), ? age: 0..120 }"#;
+/// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+///
+/// let options = GeneratorOptions {
+///   seed: Some(42),
+///   ..Default::default()
+/// };
+/// let json = Generator::new(&cddl, &options).json().unwrap();
+/// assert!(json["name"].is_string());
+///
+/// let options = GeneratorOptions {
+///   mode: Mode::Minimal,
+///   ..Default::default()
+/// };
+/// let json = Generator::new(&cddl, &options).json().unwrap();
+/// assert_eq!(json, serde_json::json!({ "name": "a" }));
+/// ```
+pub struct Generator<'a, 'b> {
+  cddl: &'b CDDL<'a>,
+  rng: Rng,
+  mode: Mode,
+  max_depth: usize,
+  is_json: bool,
+  depth: usize,
+  // Number of enclosing rule references for which the smallest instance is
+  // being generated, even when generating random instances
+  forced_minimal: usize,
+  bindings: Vec<Binding<'a, 'b>>,
+  // Smallest instances of rules keyed by whether or not they're JSON
+  // compatible and the rule name
+  smallest: HashMap<(bool, &'a str), Option<Value>>,
+  // Rules whose smallest instance is being generated, used to cut off cycles
+  in_progress: Vec<&'a str>,
+  cycles: usize,
+}
+
+// Generic argument bound to a generic parameter. Arguments are generated with
+// the bindings that were in scope where they were given.
+struct Binding<'a, 'b> {
+  param: &'a str,
+  arg: &'b Type1<'a>,
+  scope: usize,
+}
+
+impl<'a, 'b> Generator<'a, 'b> {
+  /// Creates a generator for the given CDDL definition
+  pub fn new(cddl: &'b CDDL<'a>, options: &GeneratorOptions) -> Self {
+    let seed = options.seed.unwrap_or_else(|| {
+      SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default()
+    });
+
+    Generator {
+      cddl,
+      rng: Rng(seed),
+      mode: options.mode,
+      max_depth: options.max_depth,
+      is_json: false,
+      depth: 0,
+      forced_minimal: 0,
+      bindings: Vec::new(),
+      smallest: HashMap::new(),
+      in_progress: Vec::new(),
+      cycles: 0,
+    }
+  }
+
+  /// Generates a JSON instance of the root type, i.e. the first type rule
+  pub fn json(&mut self) -> Result<serde_json::Value, Error> {
+    let root = self.root()?;
+    self.json_for_rule(root)
+  }
+
+  /// Generates a JSON instance of the rule with the given name
+  pub fn json_for_rule(&mut self, rule: &str) -> Result<serde_json::Value, Error> {
+    self.is_json = true;
+    let value = self.generate(rule)?;
+
+    to_json(value).ok_or_else(|| Error::Unsatisfiable(rule.to_string()))
+  }
+
+  /// Generates a CBOR instance of the root type, i.e. the first type rule
+  pub fn cbor(&mut self) -> Result<Value, Error> {
+    let root = self.root()?;
+    self.cbor_for_rule(root)
+  }
+
+  /// Generates a CBOR instance of the rule with the given name
+  pub fn cbor_for_rule(&mut self, rule: &str) -> Result<Value, Error> {
+    self.is_json = false;
+    self.generate(rule)
+  }
+
+  fn root(&self) -> Result<&'a str, Error> {
+    self
+      .cddl
+      .rules
+      .iter()
+      .find_map(|r| match r {
+        Rule::Type { rule, .. } => Some(rule.name.ident),
+        _ => None,
+      })
+      .ok_or_else(|| Error::MissingRule("root".to_string()))
+  }
+
+  fn generate(&mut self, rule: &str) -> Result<Value, Error> {
+    let ident = match self.cddl.rule(rule) {
+      Some(Rule::Type { rule, .. }) => &rule.name,
+      Some(Rule::Group { rule, .. }) => &rule.name,
+      None => return Err(Error::MissingRule(rule.to_string())),
+    };
+
+    self.depth = 0;
+    self.forced_minimal = 0;
+    self.bindings.clear();
+    self.in_progress.clear();
+
+    self
+      .rule(ident.ident, None)
+      .ok_or_else(|| Error::Unsatisfiable(rule.to_string()))
+  }
+
+  fn is_minimal(&self) -> bool {
+    self.mode == Mode::Minimal || self.forced_minimal > 0
+  }
+
+  // Generates an instance of a rule, which is either a random instance or the
+  // smallest instance once the maximum depth is reached
+  fn rule(&mut self, name: &'a str, generic_arg: Option<&'b GenericArg<'a>>) -> Option<Value> {
+    let is_generic = self.cddl.rules_named(name).any(|r| match r {
+      Rule::Type { rule, .. } => rule.generic_param.is_some(),
+      Rule::Group { rule, .. } => rule.generic_param.is_some(),
+    });
+
+    if !self.is_minimal() && self.depth >= self.max_depth {
+      // Cycles are only tracked while generating smallest instances
+      let in_progress = self.in_progress.split_off(0);
+      self.forced_minimal += 1;
+      let value = self.rule(name, generic_arg);
+      self.forced_minimal -= 1;
+      self.in_progress = in_progress;
+
+      return value;
+    }
+
+    if !self.is_minimal() || is_generic {
+      self.depth += 1;
+      let value = self.rule_choices(name, generic_arg);
+      self.depth -= 1;
+
+      return value;
+    }
+
+    if let Some(value) = self.smallest.get(&(self.is_json, name)) {
+      return value.clone();
+    }
+
+    if self.in_progress.contains(&name) {
+      self.cycles += 1;
+      return None;
+    }
+
+    let cycles = self.cycles;
+    self.in_progress.push(name);
+    let value = self.rule_choices(name, generic_arg);
+    self.in_progress.pop();
+
+    // Instances generated while cutting off a cycle aren't necessarily the
+    // smallest instance of the rule
+    if self.cycles == cycles {
+      self.smallest.insert((self.is_json, name), value.clone());
+    }
+
+    value
+  }
+
+  fn rule_choices(
+    &mut self,
+    name: &'a str,
+    generic_arg: Option<&'b GenericArg<'a>>,
+  ) -> Option<Value> {
+    let cddl = self.cddl;
+
+    // Rules extended via /= contribute additional type choices
+    let mut choices = Vec::new();
+    for rule in cddl.rules_named(name) {
+      match rule {
+        Rule::Type { rule, .. } => {
+          for tc in rule.value.type_choices.iter() {
+            choices.push((rule.generic_param.as_ref(), tc));
+          }
+        }
+        // Groups used as types are treated as arrays
+        Rule::Group { rule, .. } => {
+          return self.with_generic_args(rule.generic_param.as_ref(), generic_arg, |g| {
+            let mut values = Vec::new();
+            g.array_entry(&rule.entry, &mut values)?;
+            Some(Value::Array(values))
+          });
+        }
+      }
+    }
+
+    self.choose(
+      choices.len(),
+      |g, idx| {
+        let (gp, tc) = choices[idx];
+        g.with_generic_args(gp, generic_arg, |g| g.type1(tc))
+      },
+      encoded_len,
+    )
+  }
+
+  fn with_generic_args<T, F>(
+    &mut self,
+    gp: Option<&'b GenericParm<'a>>,
+    generic_arg: Option<&'b GenericArg<'a>>,
+    f: F,
+  ) -> Option<T>
+  where
+    F: FnOnce(&mut Self) -> Option<T>,
+  {
+    let gp = match gp {
+      Some(gp) => gp,
+      None => return f(self),
+    };
+
+    let scope = self.bindings.len();
+    let args = generic_arg.iter().flat_map(|ga| ga.args.iter());
+    for (param, arg) in gp.params.iter().zip(args) {
+      self.bindings.push(Binding {
+        param: param.ident,
+        arg,
+        scope,
+      });
+    }
+
+    let value = f(self);
+    self.bindings.truncate(scope);
+
+    value
+  }
+
+  // Chooses one of a number of alternatives. Random instances use the first
+  // alternative that can be generated in a random order. Otherwise, the
+  // smallest of the alternatives is used.
+  fn choose<T, F, S>(&mut self, len: usize, mut f: F, size: S) -> Option<T>
+  where
+    F: FnMut(&mut Self, usize) -> Option<T>,
+    S: Fn(&T) -> usize,
+  {
+    if self.is_minimal() {
+      let mut smallest: Option<(usize, T)> = None;
+      for idx in 0..len {
+        if let Some(value) = f(self, idx) {
+          let len = size(&value);
+          let is_smaller = match &smallest {
+            Some((smallest, _)) => len < *smallest,
+            None => true,
+          };
+
+          if is_smaller {
+            smallest = Some((len, value));
+          }
+        }
+      }
+
+      return smallest.map(|(_, value)| value);
+    }
+
+    let mut order = (0..len).collect::<Vec<_>>();
+    for idx in (1..len).rev() {
+      order.swap(idx, self.rng.below(idx as u64 + 1) as usize);
+    }
+
+    order.into_iter().find_map(|idx| f(self, idx))
+  }
+
+  fn r#type(&mut self, t: &'b Type<'a>) -> Option<Value> {
+    self.choose(
+      t.type_choices.len(),
+      |g, idx| g.type1(&t.type_choices[idx]),
+      encoded_len,
+    )
+  }
+
+  fn type1(&mut self, t1: &'b Type1<'a>) -> Option<Value> {
+    match &t1.operator {
+      Some((RangeCtlOp::RangeOp { is_inclusive, .. }, upper)) => {
+        self.range(&t1.type2, upper, *is_inclusive)
+      }
+      Some((RangeCtlOp::CtlOp { ctrl, .. }, controller)) => {
+        self.control(&t1.type2, ctrl, controller)
+      }
+      None => self.type2(&t1.type2),
+    }
+  }
+
+  fn type2(&mut self, t2: &'b Type2<'a>) -> Option<Value> {
+    match t2 {
+      Type2::IntValue { value, .. } => Some(Value::Integer(*value)),
+      Type2::UintValue { value, .. } => i128::try_from(*value).ok().map(Value::Integer),
+      Type2::FloatValue { value, .. } => Some(Value::Float(*value)),
+      Type2::TextValue { value, .. } => Some(Value::Text(value.to_string())),
+      Type2::UTF8ByteString { value, .. }
+      | Type2::B16ByteString { value, .. }
+      | Type2::B64ByteString { value, .. } => self.bytes_value(value.to_vec()),
+      Type2::Typename {
+        ident, generic_arg, ..
+      } => self.typename(ident.ident, generic_arg.as_ref()),
+      Type2::ParenthesizedType { pt, .. } => self.r#type(pt),
+      Type2::Map { group, .. } => {
+        let entries = self.choose(
+          group.group_choices.len(),
+          |g, idx| {
+            let mut entries = Vec::new();
+            for (ge, _) in group.group_choices[idx].group_entries.iter() {
+              g.map_entry(ge, &mut entries)?;
+            }
+            Some(entries)
+          },
+          |entries| {
+            entries
+              .iter()
+              .map(|(k, v)| encoded_len(k) + encoded_len(v))
+              .sum()
+          },
+        )?;
+
+        Some(Value::Map(entries.into_iter().collect()))
+      }
+      Type2::Array { group, .. } => {
+        let values = self.choose(
+          group.group_choices.len(),
+          |g, idx| {
+            let mut values = Vec::new();
+            for (ge, _) in group.group_choices[idx].group_entries.iter() {
+              g.array_entry(ge, &mut values)?;
+            }
+            Some(values)
+          },
+          |values| values.iter().map(encoded_len).sum(),
+        )?;
+
+        Some(Value::Array(values))
+      }
+      Type2::ChoiceFromInlineGroup { group, .. } => self.enumeration(group),
+      Type2::ChoiceFromGroup { ident, .. } => {
+        let cddl = self.cddl;
+        let entries = cddl
+          .rules_named(ident.ident)
+          .filter_map(|r| match r {
+            Rule::Group { rule, .. } => Some(&rule.entry),
+            _ => None,
+          })
+          .collect::<Vec<_>>();
+
+        self.choose(
+          entries.len(),
+          |g, idx| match entries[idx] {
+            GroupEntry::InlineGroup { group, .. } => g.enumeration(group),
+            GroupEntry::ValueMemberKey { ge, .. } => g.r#type(&ge.entry_type),
+            GroupEntry::TypeGroupname { .. } => None,
+          },
+          encoded_len,
+        )
+      }
+      Type2::TaggedData { tag, t, .. } => {
+        let value = self.r#type(t)?;
+        self.tagged(tag.unwrap_or_default() as u64, value)
+      }
+      Type2::TaggedDataMajorType { mt, constraint, .. } => self.major_type(*mt, *constraint),
+      Type2::Any(_) => self.any(),
+      Type2::Unwrap { .. } => None,
+    }
+  }
+
+  fn typename(&mut self, name: &'a str, generic_arg: Option<&'b GenericArg<'a>>) -> Option<Value> {
+    if let Some(idx) = self.bindings.iter().rposition(|b| b.param == name) {
+      let arg = self.bindings[idx].arg;
+      let scope = self.bindings[idx].scope;
+
+      let bindings = self.bindings.split_off(scope);
+      let value = self.type1(arg);
+      self.bindings.extend(bindings);
+
+      return value;
+    }
+
+    if self.cddl.rule(name).is_some() {
+      return self.rule(name, generic_arg);
+    }
+
+    self.prelude(name)
+  }
+
+  fn prelude(&mut self, name: &str) -> Option<Value> {
+    let (min, max) = self.integer_bounds();
+
+    match name {
+      "any" => self.any(),
+      "uint" | "unsigned" => self.integer(0, max),
+      "nint" => self.integer(min, -1),
+      "int" | "integer" => self.integer(min, max),
+      "float16" => self.float(Bound::Included(-FLOAT16_MAX), Bound::Included(FLOAT16_MAX)),
+      "float32" | "float64" | "float16-32" | "float32-64" | "float" => {
+        self.float(Bound::Unbounded, Bound::Unbounded)
+      }
+      "number" => self.choose(
+        2,
+        |g, idx| {
+          if idx == 0 {
+            g.integer(min, max)
+          } else {
+            g.float(Bound::Unbounded, Bound::Unbounded)
+          }
+        },
+        encoded_len,
+      ),
+      "tstr" | "text" => self.text(0, None).map(Value::Text),
+      "bstr" | "bytes" => {
+        let bytes = self.bytes(0, None);
+        self.bytes_value(bytes)
+      }
+      "bool" => {
+        let b = !self.is_minimal() && self.rng.below(2) == 1;
+        Some(Value::Bool(b))
+      }
+      "true" => Some(Value::Bool(true)),
+      "false" => Some(Value::Bool(false)),
+      "nil" | "null" => Some(Value::Null),
+      "tdate" => {
+        let date = self.date();
+        self.tagged(0, Value::Text(date))
+      }
+      "time" => {
+        let time = self.integer(0, 4_102_444_800)?;
+        self.tagged(1, time)
+      }
+      "biguint" | "bignint" | "bigint" => {
+        let bytes = self.bytes(1, Some(16));
+        let tag = if name == "bignint" { 3 } else { 2 };
+        let value = self.bytes_value(bytes)?;
+        self.tagged(tag, value)
+      }
+      "uri" => {
+        let path = self.text(0, Some(8))?;
+        self.tagged(32, Value::Text(format!("https://example.com/{}", path)))
+      }
+      "b64url" | "b64legacy" => {
+        let bytes = self.bytes(0, None);
+        let (tag, config) = if name == "b64url" {
+          (33, base64::URL_SAFE_NO_PAD)
+        } else {
+          (34, base64::STANDARD)
+        };
+
+        let mut encoded = vec![0; bytes.len() * 4 / 3 + 4];
+        let len = base64::encode_config_slice(&bytes, config, &mut encoded);
+        encoded.truncate(len);
+
+        let text = String::from_utf8(encoded).ok()?;
+        self.tagged(tag, Value::Text(text))
+      }
+      "regexp" => self.tagged(35, Value::Text("[a-z]+".to_string())),
+      "mime-message" => self.tagged(
+        36,
+        Value::Text("Content-Type: text/plain\r\n\r\n".to_string()),
+      ),
+      "encoded-cbor" => {
+        let value = self.any()?;
+        let bytes = serde_cbor::to_vec(&value).ok()?;
+        let bytes = self.bytes_value(bytes)?;
+        self.tagged(24, bytes)
+      }
+      "cbor-any" => {
+        let value = self.any()?;
+        self.tagged(55799, value)
+      }
+      _ => None,
+    }
+  }
+
+  fn major_type(&mut self, mt: u8, constraint: Option<usize>) -> Option<Value> {
+    let (min, max) = self.integer_bounds();
+
+    match (mt, constraint) {
+      (0, Some(c)) | (1, Some(c)) if c < 24 => {
+        let value = c as i128;
+        Some(Value::Integer(if mt == 0 { value } else { -1 - value }))
+      }
+      (0, _) => self.integer(0, max),
+      (1, _) => self.integer(min, -1),
+      (2, _) => self.prelude("bstr"),
+      (3, _) => self.prelude("tstr"),
+      (4, _) => Some(Value::Array(Vec::new())),
+      (5, _) => Some(Value::Map(BTreeMap::new())),
+      (6, Some(tag)) => {
+        let value = self.any()?;
+        self.tagged(tag as u64, value)
+      }
+      (6, None) => {
+        let value = self.any()?;
+        self.tagged(0, value)
+      }
+      (7, Some(20)) => Some(Value::Bool(false)),
+      (7, Some(21)) => Some(Value::Bool(true)),
+      (7, Some(22)) => Some(Value::Null),
+      (7, Some(25)) => self.float(Bound::Included(-FLOAT16_MAX), Bound::Included(FLOAT16_MAX)),
+      (7, Some(26)) | (7, Some(27)) => self.float(Bound::Unbounded, Bound::Unbounded),
+      (7, None) => self.prelude("bool"),
+      _ => None,
+    }
+  }
+
+  fn any(&mut self) -> Option<Value> {
+    if self.is_minimal() {
+      return Some(Value::Null);
+    }
+
+    match self.rng.below(4) {
+      0 => Some(Value::Null),
+      1 => self.prelude("bool"),
+      2 => self.integer(-SAMPLE_RANGE, SAMPLE_RANGE),
+      _ => self.text(0, None).map(Value::Text),
+    }
+  }
+
+  fn range(
+    &mut self,
+    lower: &'b Type2<'a>,
+    upper: &'b Type2<'a>,
+    is_inclusive: bool,
+  ) -> Option<Value> {
+    match (self.literal(lower)?, self.literal(upper)?) {
+      (Value::Integer(lower), Value::Integer(upper)) => {
+        let upper = if is_inclusive { upper } else { upper - 1 };
+        self.integer(lower, upper)
+      }
+      (lower, upper) => {
+        let upper = as_f64(&upper)?;
+        let upper = if is_inclusive {
+          Bound::Included(upper)
+        } else {
+          Bound::Excluded(upper)
+        };
+
+        self.float(Bound::Included(as_f64(&lower)?), upper)
+      }
+    }
+  }
+
+  fn control(
+    &mut self,
+    target: &'b Type2<'a>,
+    ctrl: &str,
+    controller: &'b Type2<'a>,
+  ) -> Option<Value> {
+    match token::lookup_control_from_str(ctrl) {
+      Some(Token::SIZE) => {
+        let (min, max) = self.size(controller)?;
+
+        match self.base_type(target) {
+          "tstr" | "text" => self.text(min, Some(max)).map(Value::Text),
+          "bstr" | "bytes" => {
+            let bytes = self.bytes(min, Some(max));
+            self.bytes_value(bytes)
+          }
+          "uint" | "unsigned" => {
+            let max = if max >= 16 {
+              UINT_MAX
+            } else {
+              (1i128 << (max * 8)) - 1
+            };
+            let (_, bound) = self.integer_bounds();
+            self.integer(0, max.min(bound))
+          }
+          _ => self.type2(target),
+        }
+      }
+      Some(Token::CREGEXP) | Some(Token::PCRE) => match controller {
+        Type2::TextValue { value, .. } => self.regex(value).map(Value::Text),
+        _ => None,
+      },
+      Some(ctrl @ Token::LT)
+      | Some(ctrl @ Token::LE)
+      | Some(ctrl @ Token::GT)
+      | Some(ctrl @ Token::GE) => {
+        let bound = self.literal(controller)?;
+
+        match (self.base_type(target), bound) {
+          (base @ "uint", Value::Integer(bound))
+          | (base @ "int", Value::Integer(bound))
+          | (base @ "nint", Value::Integer(bound)) => {
+            let (mut min, mut max) = self.integer_bounds();
+            match base {
+              "uint" => min = 0,
+              "nint" => max = -1,
+              _ => (),
+            }
+
+            match ctrl {
+              Token::LT => max = max.min(bound - 1),
+              Token::LE => max = max.min(bound),
+              Token::GT => min = min.max(bound + 1),
+              _ => min = min.max(bound),
+            }
+
+            self.integer(min, max)
+          }
+          (_, bound) => {
+            let bound = as_f64(&bound)?;
+            match ctrl {
+              Token::LT => self.float(Bound::Unbounded, Bound::Excluded(bound)),
+              Token::LE => self.float(Bound::Unbounded, Bound::Included(bound)),
+              Token::GT => self.float(Bound::Excluded(bound), Bound::Unbounded),
+              _ => self.float(Bound::Included(bound), Bound::Unbounded),
+            }
+          }
+        }
+      }
+      Some(Token::EQ) => self.literal(controller),
+      Some(Token::NE) => {
+        let excluded = self.literal(controller);
+        for _ in 0..MAX_ATTEMPTS {
+          let value = self.type2(target)?;
+          if Some(&value) != excluded.as_ref() {
+            return Some(value);
+          }
+        }
+
+        None
+      }
+      Some(Token::BITS) => match self.base_type(target) {
+        "bstr" | "bytes" => self.bytes_value(Vec::new()),
+        _ => Some(Value::Integer(0)),
+      },
+      Some(Token::CBOR) => {
+        let is_json = self.is_json;
+        self.is_json = false;
+        let value = self.type2(controller);
+        self.is_json = is_json;
+
+        let bytes = serde_cbor::to_vec(&value?).ok()?;
+        self.bytes_value(bytes)
+      }
+      Some(Token::CBORSEQ) => {
+        let is_json = self.is_json;
+        self.is_json = false;
+        let value = self.type2(controller);
+        self.is_json = is_json;
+
+        let mut bytes = Vec::new();
+        match value? {
+          Value::Array(values) => {
+            for value in values.iter() {
+              bytes.extend(serde_cbor::to_vec(value).ok()?);
+            }
+          }
+          value => bytes.extend(serde_cbor::to_vec(&value).ok()?),
+        }
+
+        self.bytes_value(bytes)
+      }
+      Some(Token::JSON) => {
+        let is_json = self.is_json;
+        self.is_json = true;
+        let value = self.type2(controller);
+        self.is_json = is_json;
+
+        serde_json::to_string(&to_json(value?)?)
+          .ok()
+          .map(Value::Text)
+      }
+      _ => self.type2(target),
+    }
+  }
+
+  // Returns the bounds of a .size controller
+  fn size(&mut self, controller: &'b Type2<'a>) -> Option<(usize, usize)> {
+    if let Type2::ParenthesizedType { pt, .. } = controller {
+      if let [Type1 {
+        type2: lower,
+        operator: Some((RangeCtlOp::RangeOp { is_inclusive, .. }, upper)),
+        ..
+      }] = pt.type_choices.as_slice()
+      {
+        let lower = as_usize(&self.literal(lower)?)?;
+        let upper = as_usize(&self.literal(upper)?)?;
+
+        if *is_inclusive {
+          return Some((lower, upper));
+        }
+
+        return Some((lower, upper.checked_sub(1)?));
+      }
+    }
+
+    let size = as_usize(&self.literal(controller)?)?;
+    Some((size, size))
+  }
+
+  // Resolves a type to a literal value, following rules that consist of a
+  // single value
+  fn literal(&self, t2: &'b Type2<'a>) -> Option<Value> {
+    let mut t2 = t2;
+
+    for _ in 0..self.max_depth.max(1) {
+      match t2 {
+        Type2::IntValue { value, .. } => return Some(Value::Integer(*value)),
+        Type2::UintValue { value, .. } => return i128::try_from(*value).ok().map(Value::Integer),
+        Type2::FloatValue { value, .. } => return Some(Value::Float(*value)),
+        Type2::TextValue { value, .. } => return Some(Value::Text(value.to_string())),
+        Type2::Typename { ident, .. } => match self.cddl.rule(ident.ident) {
+          Some(Rule::Type { rule, .. }) => match rule.value.type_choices.as_slice() {
+            [tc] if tc.operator.is_none() => t2 = &tc.type2,
+            _ => return None,
+          },
+          _ => return None,
+        },
+        _ => return None,
+      }
+    }
+
+    None
+  }
+
+  // Returns the name of the prelude type a type resolves to, following rules
+  // that consist of a single type
+  fn base_type(&self, t2: &'b Type2<'a>) -> &'a str {
+    let mut t2 = t2;
+
+    for _ in 0..self.max_depth.max(1) {
+      match t2 {
+        Type2::Typename { ident, .. } => match self.cddl.rule(ident.ident) {
+          Some(Rule::Type { rule, .. }) => match rule.value.type_choices.as_slice() {
+            [tc] => t2 = &tc.type2,
+            _ => return "",
+          },
+          Some(_) => return "",
+          None => return ident.ident,
+        },
+        Type2::UintValue { .. } => return "uint",
+        Type2::IntValue { .. } => return "int",
+        _ => return "",
+      }
+    }
+
+    ""
+  }
+
+  fn map_entry(&mut self, ge: &'b GroupEntry<'a>, entries: &mut Vec<(Value, Value)>) -> Option<()> {
+    match ge {
+      GroupEntry::ValueMemberKey { ge, .. } => {
+        let key = match &ge.member_key {
+          Some(MemberKey::Bareword { ident, .. }) => Some(Value::Text(ident.ident.to_string())),
+          Some(MemberKey::Value { value, .. }) => Some(self.value(value)?),
+          Some(MemberKey::Type1 { .. }) => None,
+          Some(MemberKey::NonMemberKey { .. }) => return None,
+          // Group references without a member key
+          None => {
+            if let [Type1 {
+              type2: Type2::Typename {
+                ident, generic_arg, ..
+              },
+              operator: None,
+              ..
+            }] = ge.entry_type.type_choices.as_slice()
+            {
+              return self.repeat(ge.occur.as_ref(), entries, |g, entries| {
+                g.group_rule(ident.ident, generic_arg.as_ref(), |g, entry| {
+                  g.map_entry(entry, entries)
+                })
+              });
+            }
+
+            return None;
+          }
+        };
+
+        self.repeat(ge.occur.as_ref(), entries, |g, entries| {
+          let key = match &key {
+            Some(key) => key.clone(),
+            None => g.table_key(&ge.member_key, entries)?,
+          };
+
+          if g.is_json {
+            if let Value::Text(_) = key {
+            } else {
+              return None;
+            }
+          }
+
+          let value = g.r#type(&ge.entry_type)?;
+          entries.push((key, value));
+
+          Some(())
+        })
+      }
+      GroupEntry::TypeGroupname { ge, .. } => {
+        self.repeat(ge.occur.as_ref(), entries, |g, entries| {
+          g.group_rule(ge.name.ident, ge.generic_arg.as_ref(), |g, entry| {
+            g.map_entry(entry, entries)
+          })
+        })
+      }
+      GroupEntry::InlineGroup { occur, group, .. } => {
+        self.repeat(occur.as_ref(), entries, |g, entries| {
+          let choice = g.choose(
+            group.group_choices.len(),
+            |g, idx| {
+              let mut choice = entries.clone();
+              for (ge, _) in group.group_choices[idx].group_entries.iter() {
+                g.map_entry(ge, &mut choice)?;
+              }
+              Some(choice)
+            },
+            |entries| {
+              entries
+                .iter()
+                .map(|(k, v)| encoded_len(k) + encoded_len(v))
+                .sum()
+            },
+          )?;
+
+          *entries = choice;
+
+          Some(())
+        })
+      }
+    }
+  }
+
+  // Generates a key of a table that isn't already present in the map
+  fn table_key(
+    &mut self,
+    member_key: &'b Option<MemberKey<'a>>,
+    entries: &[(Value, Value)],
+  ) -> Option<Value> {
+    if let Some(MemberKey::Type1 { t1, .. }) = member_key {
+      for _ in 0..MAX_ATTEMPTS {
+        let key = self.type1(t1)?;
+        if entries.iter().all(|(k, _)| *k != key) {
+          return Some(key);
+        }
+
+        // Smallest instances are always the same
+        if self.is_minimal() {
+          break;
+        }
+      }
+    }
+
+    None
+  }
+
+  fn array_entry(&mut self, ge: &'b GroupEntry<'a>, values: &mut Vec<Value>) -> Option<()> {
+    match ge {
+      GroupEntry::ValueMemberKey { ge, .. } => {
+        // Group references are parsed as entries without member keys
+        if let (
+          None,
+          [Type1 {
+            type2: Type2::Typename {
+              ident, generic_arg, ..
+            },
+            operator: None,
+            ..
+          }],
+        ) = (&ge.member_key, ge.entry_type.type_choices.as_slice())
+        {
+          if let Some(Rule::Group { .. }) = self.cddl.rule(ident.ident) {
+            return self.repeat(ge.occur.as_ref(), values, |g, values| {
+              g.group_rule(ident.ident, generic_arg.as_ref(), |g, entry| {
+                g.array_entry(entry, values)
+              })
+            });
+          }
+        }
+
+        self.repeat(ge.occur.as_ref(), values, |g, values| {
+          values.push(g.r#type(&ge.entry_type)?);
+          Some(())
+        })
+      }
+      GroupEntry::TypeGroupname { ge, .. } => {
+        self.repeat(ge.occur.as_ref(), values, |g, values| {
+          if let Some(Rule::Group { .. }) = g.cddl.rule(ge.name.ident) {
+            return g.group_rule(ge.name.ident, ge.generic_arg.as_ref(), |g, entry| {
+              g.array_entry(entry, values)
+            });
+          }
+
+          values.push(g.typename(ge.name.ident, ge.generic_arg.as_ref())?);
+          Some(())
+        })
+      }
+      GroupEntry::InlineGroup { occur, group, .. } => {
+        self.repeat(occur.as_ref(), values, |g, values| {
+          let choice = g.choose(
+            group.group_choices.len(),
+            |g, idx| {
+              let mut choice = Vec::new();
+              for (ge, _) in group.group_choices[idx].group_entries.iter() {
+                g.array_entry(ge, &mut choice)?;
+              }
+              Some(choice)
+            },
+            |values| values.iter().map(encoded_len).sum(),
+          )?;
+
+          values.extend(choice);
+
+          Some(())
+        })
+      }
+    }
+  }
+
+  // Generates the occurrences of a group entry. Occurrences beyond the lower
+  // bound of the occurrence indicator are dropped rather than failing the
+  // whole instance, e.g. when an optional entry refers to an undefined socket.
+  fn repeat<T, F>(&mut self, occur: Option<&Occur>, items: &mut Vec<T>, mut f: F) -> Option<()>
+  where
+    F: FnMut(&mut Self, &mut Vec<T>) -> Option<()>,
+  {
+    let (min, max) = match occur {
+      Some(Occur::Optional(_)) => (0, Some(1)),
+      Some(Occur::ZeroOrMore(_)) => (0, None),
+      Some(Occur::OneOrMore(_)) => (1, None),
+      Some(Occur::Exact { lower, upper, .. }) => (lower.unwrap_or(0), *upper),
+      None => (1, Some(1)),
+    };
+
+    for idx in 0..self.count(min, max) {
+      let len = items.len();
+      if f(self, items).is_none() {
+        items.truncate(len);
+
+        if idx < min {
+          return None;
+        }
+
+        break;
+      }
+    }
+
+    Some(())
+  }
+
+  // Generates the entries of a group rule, choosing between the alternates of
+  // rules extended via //=
+  fn group_rule<F>(
+    &mut self,
+    name: &'a str,
+    generic_arg: Option<&'b GenericArg<'a>>,
+    mut f: F,
+  ) -> Option<()>
+  where
+    F: FnMut(&mut Self, &'b GroupEntry<'a>) -> Option<()>,
+  {
+    let cddl = self.cddl;
+    let rules = cddl
+      .rules_named(name)
+      .filter_map(|r| match r {
+        Rule::Group { rule, .. } => Some(rule),
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+
+    if rules.is_empty() {
+      return None;
+    }
+
+    if !self.is_minimal() && self.depth >= self.max_depth {
+      let in_progress = self.in_progress.split_off(0);
+      self.forced_minimal += 1;
+      let result = self.group_rule(name, generic_arg, f);
+      self.forced_minimal -= 1;
+      self.in_progress = in_progress;
+
+      return result;
+    }
+
+    // Group rules are always expanded, so they can only cut off cycles
+    if self.is_minimal() && self.in_progress.contains(&name) {
+      self.cycles += 1;
+      return None;
+    }
+
+    let idx = if self.is_minimal() {
+      0
+    } else {
+      self.rng.below(rules.len() as u64) as usize
+    };
+    let rule = rules[idx];
+
+    self.depth += 1;
+    self.in_progress.push(name);
+    let result = self.with_generic_args(rule.generic_param.as_ref(), generic_arg, |g| {
+      f(g, &rule.entry)
+    });
+    self.in_progress.pop();
+    self.depth -= 1;
+
+    result
+  }
+
+  // Returns the choice of values of the entries of a group, e.g. &(a: 1, b: 2)
+  fn enumeration(&mut self, group: &'b Group<'a>) -> Option<Value> {
+    let entries = group
+      .group_choices
+      .iter()
+      .flat_map(|gc| gc.group_entries.iter().map(|(ge, _)| ge))
+      .collect::<Vec<_>>();
+
+    self.choose(
+      entries.len(),
+      |g, idx| match entries[idx] {
+        GroupEntry::ValueMemberKey { ge, .. } => g.r#type(&ge.entry_type),
+        GroupEntry::TypeGroupname { ge, .. } => g.typename(ge.name.ident, ge.generic_arg.as_ref()),
+        GroupEntry::InlineGroup { group, .. } => g.enumeration(group),
+      },
+      encoded_len,
+    )
+  }
+
+  fn value(&mut self, value: &token::Value) -> Option<Value> {
+    match value {
+      token::Value::INT(i) => Some(Value::Integer(*i)),
+      token::Value::UINT(u) => i128::try_from(*u).ok().map(Value::Integer),
+      token::Value::FLOAT(f) => Some(Value::Float(*f)),
+      token::Value::TEXT(t) => Some(Value::Text(t.to_string())),
+      token::Value::BYTE(b) => {
+        let bytes = match b {
+          token::ByteValue::UTF8(b) | token::ByteValue::B16(b) | token::ByteValue::B64(b) => {
+            b.to_vec()
+          }
+        };
+        self.bytes_value(bytes)
+      }
+    }
+  }
+
+  fn count(&mut self, min: usize, max: Option<usize>) -> usize {
+    if self.is_minimal() {
+      return min;
+    }
+
+    let max = match max {
+      Some(max) => max.min(min + MAX_EXTRA_OCCURRENCES),
+      None => min + MAX_EXTRA_OCCURRENCES,
+    };
+
+    min + self.rng.below((max - min) as u64 + 1) as usize
+  }
+
+  fn integer_bounds(&self) -> (i128, i128) {
+    // JSON numbers are limited to 64-bit integers
+    if self.is_json {
+      (JSON_INT_MIN, UINT_MAX)
+    } else {
+      (NINT_MIN, UINT_MAX)
+    }
+  }
+
+  fn integer(&mut self, min: i128, max: i128) -> Option<Value> {
+    if min > max {
+      return None;
+    }
+
+    // The value closest to zero has the smallest encoding
+    if self.is_minimal() {
+      return Some(Value::Integer(0.max(min).min(max)));
+    }
+
+    let (lower, upper) = (min.max(-SAMPLE_RANGE), max.min(SAMPLE_RANGE));
+    let value = match self.rng.below(8) {
+      0 => min,
+      1 => max,
+      _ if lower <= upper => lower + self.rng.below((upper - lower) as u64 + 1) as i128,
+      _ => min + (self.rng.next_u128() % ((max - min) as u128).saturating_add(1)) as i128,
+    };
+
+    Some(Value::Integer(value))
+  }
+
+  // Floats are generated in quarter increments within the sample range, which
+  // are exactly representable by half-precision floats
+  fn float(&mut self, min: Bound<f64>, max: Bound<f64>) -> Option<Value> {
+    let is_valid = |f: f64| {
+      let above = match min {
+        Bound::Included(min) => f >= min,
+        Bound::Excluded(min) => f > min,
+        Bound::Unbounded => true,
+      };
+      let below = match max {
+        Bound::Included(max) => f <= max,
+        Bound::Excluded(max) => f < max,
+        Bound::Unbounded => true,
+      };
+
+      above && below
+    };
+
+    // Unbounded ends are replaced by the edges of the sample range
+    let sample_range = SAMPLE_RANGE as f64;
+    let (lower, upper) = match (bound_value(min), bound_value(max)) {
+      (Some(min), Some(max)) => (min, max),
+      (Some(min), None) => (min, min.max(0.0) + sample_range),
+      (None, Some(max)) => (max.min(0.0) - sample_range, max),
+      (None, None) => (-sample_range, sample_range),
+    };
+
+    if self.is_minimal() {
+      let closest = 0f64.max(lower).min(upper);
+      return [closest, lower, (lower + upper) / 2.0, upper]
+        .iter()
+        .find(|f| is_valid(**f))
+        .map(|f| Value::Float(*f));
+    }
+
+    let start = (lower.max(-sample_range) * 4.0).ceil() as i128;
+    let end = (upper.min(sample_range) * 4.0).floor() as i128;
+
+    if start <= end {
+      for _ in 0..MAX_ATTEMPTS {
+        let value = (start + self.rng.below((end - start) as u64 + 1) as i128) as f64 / 4.0;
+        if is_valid(value) {
+          return Some(Value::Float(value));
+        }
+      }
+    }
+
+    let value = lower + (upper - lower) * self.rng.unit();
+    [value, (lower + upper) / 2.0]
+      .iter()
+      .find(|f| is_valid(**f))
+      .map(|f| Value::Float(*f))
+  }
+
+  fn text(&mut self, min: usize, max: Option<usize>) -> Option<String> {
+    const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+
+    let len = self.count(min, max);
+    let text = (0..len)
+      .map(|_| {
+        if self.is_minimal() {
+          'a'
+        } else {
+          ALPHABET[self.rng.below(ALPHABET.len() as u64) as usize] as char
+        }
+      })
+      .collect();
+
+    Some(text)
+  }
+
+  fn bytes(&mut self, min: usize, max: Option<usize>) -> Vec<u8> {
+    let len = self.count(min, max);
+    if self.is_minimal() {
+      return vec![0; len];
+    }
+
+    (0..len).map(|_| self.rng.below(256) as u8).collect()
+  }
+
+  // JSON doesn't support byte strings
+  fn bytes_value(&self, bytes: Vec<u8>) -> Option<Value> {
+    if self.is_json {
+      return None;
+    }
+
+    Some(Value::Bytes(bytes))
+  }
+
+  // Tags are dropped from JSON instances, e.g. tdate is a plain text string
+  fn tagged(&self, tag: u64, value: Value) -> Option<Value> {
+    if self.is_json {
+      return Some(value);
+    }
+
+    Some(Value::Tag(tag, Box::new(value)))
+  }
+
+  fn date(&mut self) -> String {
+    if self.is_minimal() {
+      return "1970-01-01T00:00:00Z".to_string();
+    }
+
+    format!(
+      "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+      1970 + self.rng.below(130),
+      1 + self.rng.below(12),
+      1 + self.rng.below(28),
+      self.rng.below(24),
+      self.rng.below(60),
+      self.rng.below(60)
+    )
+  }
+
+  fn regex(&mut self, pattern: &str) -> Option<String> {
+    let hir = regex_syntax::Parser::new().parse(pattern).ok()?;
+
+    let mut text = String::new();
+    self.hir(&hir, &mut text);
+
+    Some(text)
+  }
+
+  fn hir(&mut self, hir: &Hir, text: &mut String) {
+    match hir.kind() {
+      HirKind::Literal(hir::Literal::Unicode(c)) => text.push(*c),
+      HirKind::Literal(hir::Literal::Byte(b)) => text.push(*b as char),
+      HirKind::Class(hir::Class::Unicode(class)) => {
+        let ranges = class
+          .ranges()
+          .iter()
+          .map(|r| (r.start() as u32, r.end() as u32))
+          .collect::<Vec<_>>();
+
+        if let Some(c) = self.class(&ranges) {
+          text.push(c);
+        }
+      }
+      HirKind::Class(hir::Class::Bytes(class)) => {
+        let ranges = class
+          .ranges()
+          .iter()
+          .map(|r| (r.start() as u32, r.end() as u32))
+          .collect::<Vec<_>>();
+
+        if let Some(c) = self.class(&ranges) {
+          text.push(c);
+        }
+      }
+      HirKind::Repetition(repetition) => {
+        let (min, max) = match &repetition.kind {
+          RepetitionKind::ZeroOrOne => (0, Some(1)),
+          RepetitionKind::ZeroOrMore => (0, None),
+          RepetitionKind::OneOrMore => (1, None),
+          RepetitionKind::Range(RepetitionRange::Exactly(n)) => (*n as usize, Some(*n as usize)),
+          RepetitionKind::Range(RepetitionRange::AtLeast(n)) => (*n as usize, None),
+          RepetitionKind::Range(RepetitionRange::Bounded(m, n)) => (*m as usize, Some(*n as usize)),
+        };
+
+        for _ in 0..self.count(min, max) {
+          self.hir(&repetition.hir, text);
+        }
+      }
+      HirKind::Group(group) => self.hir(&group.hir, text),
+      HirKind::Concat(hirs) => {
+        for hir in hirs.iter() {
+          self.hir(hir, text);
+        }
+      }
+      HirKind::Alternation(hirs) => {
+        let idx = if self.is_minimal() {
+          0
+        } else {
+          self.rng.below(hirs.len() as u64) as usize
+        };
+
+        self.hir(&hirs[idx], text);
+      }
+      HirKind::Empty | HirKind::Anchor(_) | HirKind::WordBoundary(_) => (),
+    }
+  }
+
+  // Picks a character from a character class, preferring printable ASCII
+  fn class(&mut self, ranges: &[(u32, u32)]) -> Option<char> {
+    let printable = ranges
+      .iter()
+      .map(|(start, end)| (*start.max(&0x21), *end.min(&0x7e)))
+      .filter(|(start, end)| start <= end)
+      .collect::<Vec<_>>();
+
+    let ranges = if printable.is_empty() {
+      ranges
+    } else {
+      &printable[..]
+    };
+
+    let (start, end) = if self.is_minimal() {
+      *ranges.first()?
+    } else {
+      ranges[self.rng.below(ranges.len() as u64) as usize]
+    };
+
+    if self.is_minimal() {
+      return std::char::from_u32(start);
+    }
+
+    std::char::from_u32(start + self.rng.below((end - start) as u64 + 1) as u32)
+      .or_else(|| std::char::from_u32(start))
+  }
+}
+
+// SplitMix64 pseudorandom number generator
+struct Rng(u64);
+
+impl Rng {
+  fn next_u64(&mut self) -> u64 {
+    self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+    let mut z = self.0;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+  }
+
+  fn next_u128(&mut self) -> u128 {
+    (self.next_u64() as u128) << 64 | self.next_u64() as u128
+  }
+
+  // Returns a number in [0, n)
+  fn below(&mut self, n: u64) -> u64 {
+    if n == 0 {
+      return 0;
+    }
+
+    self.next_u64() % n
+  }
+
+  // Returns a number in [0, 1)
+  fn unit(&mut self) -> f64 {
+    (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+  }
+}
+
+// Approximate size of the CBOR encoding of a value
+fn encoded_len(value: &Value) -> usize {
+  match value {
+    Value::Integer(i) if *i >= -24 && *i < 24 => 1,
+    Value::Integer(_) => 9,
+    Value::Float(_) => 9,
+    Value::Bytes(b) => 1 + b.len(),
+    Value::Text(t) => 1 + t.len(),
+    Value::Array(values) => 1 + values.iter().map(encoded_len).sum::<usize>(),
+    Value::Map(entries) => {
+      1 + entries
+        .iter()
+        .map(|(k, v)| encoded_len(k) + encoded_len(v))
+        .sum::<usize>()
+    }
+    Value::Tag(_, value) => 2 + encoded_len(value),
+    _ => 1,
+  }
+}
+
+fn bound_value(bound: Bound<f64>) -> Option<f64> {
+  match bound {
+    Bound::Included(value) | Bound::Excluded(value) => Some(value),
+    Bound::Unbounded => None,
+  }
+}
+
+fn as_f64(value: &Value) -> Option<f64> {
+  match value {
+    Value::Integer(i) => Some(*i as f64),
+    Value::Float(f) => Some(*f),
+    _ => None,
+  }
+}
+
+fn as_usize(value: &Value) -> Option<usize> {
+  match value {
+    Value::Integer(i) if *i >= 0 => Some(*i as usize),
+    _ => None,
+  }
+}
+
+fn to_json(value: Value) -> Option<serde_json::Value> {
+  match value {
+    Value::Null => Some(serde_json::Value::Null),
+    Value::Bool(b) => Some(serde_json::Value::Bool(b)),
+    Value::Integer(i) if i >= 0 => Some((i as u64).into()),
+    Value::Integer(i) => Some((i as i64).into()),
+    Value::Float(f) => serde_json::Number::from_f64(f).map(serde_json::Value::Number),
+    Value::Text(t) => Some(serde_json::Value::String(t)),
+    Value::Array(values) => values
+      .into_iter()
+      .map(to_json)
+      .collect::<Option<Vec<_>>>()
+      .map(serde_json::Value::Array),
+    Value::Map(entries) => entries
+      .into_iter()
+      .map(|(k, v)| match k {
+        Value::Text(k) => Some((k, to_json(v)?)),
+        _ => None,
+      })
+      .collect::<Option<serde_json::Map<_, _>>>()
+      .map(serde_json::Value::Object),
+    Value::Tag(_, value) => to_json(*value),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{lexer::Lexer, parser::cddl_from_str, validation::Validator};
+  use indoc::indoc;
+  use pretty_assertions::assert_eq;
+  use serde_json::json;
+
+  const INPUT: &str = indoc!(
+    r#"
+      reputation-object = {
+        application: text,
+        reputons: [* reputon],
+      }
+      reputon = {
+        rater: text .regexp "[a-z]{3,8}",
+        assertion: "spam" / "ham",
+        rating: 0..100,
+        sample-size: uint .le 100,
+        tags: [+ tstr],
+      }
+    "#
+  );
+
+  #[test]
+  fn verify_random_instances() {
+    let cddl = cddl_from_str(&mut Lexer::new(INPUT), INPUT, false).unwrap();
+
+    for seed in 0..32 {
+      let options = GeneratorOptions {
+        seed: Some(seed),
+        ..Default::default()
+      };
+
+      let json = Generator::new(&cddl, &options).json().unwrap();
+      assert!(cddl.validate(&json).is_ok(), "{}", json);
+    }
+  }
+
+  #[test]
+  fn verify_seeded_instances_are_reproducible() {
+    let cddl = cddl_from_str(&mut Lexer::new(INPUT), INPUT, false).unwrap();
+    let options = GeneratorOptions {
+      seed: Some(7),
+      ..Default::default()
+    };
+
+    assert_eq!(
+      Generator::new(&cddl, &options).json().unwrap(),
+      Generator::new(&cddl, &options).json().unwrap()
+    );
+  }
+
+  #[test]
+  fn verify_minimal_instances() {
+    let input = indoc!(
+      r#"
+        reputation-object = {
+          application: text,
+          reputons: [* reputon],
+        }
+        reputon = {
+          rater: text .regexp "[a-z]{3,8}",
+          assertion: "spam" / "ham",
+          ? rating: 0.0..1.0,
+          tags: [+ tstr .size (2..4)],
+          * tstr => any,
+        }
+      "#
+    );
+    let cddl = cddl_from_str(&mut Lexer::new(input), input, false).unwrap();
+    let options = GeneratorOptions {
+      mode: Mode::Minimal,
+      ..Default::default()
+    };
+
+    let mut generator = Generator::new(&cddl, &options);
+    assert_eq!(
+      generator.json().unwrap(),
+      json!({ "application": "", "reputons": [] })
+    );
+    assert_eq!(
+      generator.json_for_rule("reputon").unwrap(),
+      json!({ "rater": "aaa", "assertion": "ham", "tags": ["aa"] })
+    );
+  }
+
+  #[test]
+  fn verify_minimal_cbor_instances() {
+    let input = indoc!(
+      r#"
+        record = [
+          id: bstr .size 4,
+          created: tdate,
+          payload: bytes .cbor [* uint],
+          config: tstr .json { retries: uint },
+        ]
+      "#
+    );
+    let cddl = cddl_from_str(&mut Lexer::new(input), input, false).unwrap();
+    let options = GeneratorOptions {
+      mode: Mode::Minimal,
+      ..Default::default()
+    };
+
+    assert_eq!(
+      Generator::new(&cddl, &options).cbor().unwrap(),
+      Value::Array(vec![
+        Value::Bytes(vec![0, 0, 0, 0]),
+        Value::Tag(0, Box::new(Value::Text("1970-01-01T00:00:00Z".into()))),
+        Value::Bytes(vec![0x80]),
+        Value::Text(r#"{"retries":0}"#.into()),
+      ])
+    );
+  }
+
+  #[test]
+  fn verify_recursive_rules_terminate() {
+    let input = indoc!(
+      r#"
+        tree = { value: int, children: [* tree] }
+        list = [int, list / null]
+      "#
+    );
+    let cddl = cddl_from_str(&mut Lexer::new(input), input, false).unwrap();
+
+    for seed in 0..8 {
+      let options = GeneratorOptions {
+        seed: Some(seed),
+        max_depth: 4,
+        ..Default::default()
+      };
+
+      let mut generator = Generator::new(&cddl, &options);
+      let tree = generator.json().unwrap();
+      assert!(cddl.validate(&tree).is_ok(), "{}", tree);
+      assert!(generator.json_for_rule("list").is_ok());
+    }
+  }
+
+  #[test]
+  fn verify_unsatisfiable_rules() {
+    let input = indoc!(
+      r#"
+        message = { payload: bstr }
+      "#
+    );
+    let cddl = cddl_from_str(&mut Lexer::new(input), input, false).unwrap();
+    let mut generator = Generator::new(&cddl, &GeneratorOptions::default());
+
+    assert_eq!(
+      generator.json(),
+      Err(Error::Unsatisfiable("message".to_string()))
+    );
+    assert!(generator.cbor().is_ok());
+    assert_eq!(
+      generator.cbor_for_rule("missing"),
+      Err(Error::MissingRule("missing".to_string()))
+    );
+  }
+}
@@ -0,0 +1,518 @@
+use super::{
+  ast::*,
+  prelude::is_prelude_type,
+  token::SocketPlug,
+  visitor::{self, Visitor},
+};
+use std::fmt::Write;
+
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+  collections::BTreeMap,
+  format,
+  string::{String, ToString},
+  vec,
+  vec::Vec,
+};
+
+/// Format of an exported graph
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphFormat {
+  /// Graphviz DOT
+  Dot,
+  /// Mermaid flowchart
+  Mermaid,
+}
+
+/// Exports the references between the rules of a CDDL definition as a graph
+///
+/// Each rule is a node, with an edge to every rule it refers to. Edges to
+/// generic rules are labelled with their generic arguments, and references to
+/// the rule's own generic parameters and to prelude types are left out. Group
+/// rules are drawn with rounded corners and sockets with dashed outlines.
+/// Names that are referred to but not defined, other than sockets that have
+/// yet to be plugged, are drawn in red, as are the edges of any cycles between
+/// rules.
+///
+/// # Example
+///
+/// ```
+/// use cddl::{graph::{rule_graph, GraphFormat}, lexer_from_str, parser::cddl_from_str};
+///
+/// let input = r#"person = { name: tstr, friends: [* person], pet: pet }
+/// pet = { name: tstr, * $pet-extension }
+/// "#;
+/// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+///
+/// let output = rule_graph(&cddl, GraphFormat::Dot);
+/// assert!(output.contains(r#""person" -> "pet";"#));
+/// assert!(output.contains(r#""person" -> "person" [color=red];"#));
+/// assert!(output.contains(r#""$pet-extension" [style=dashed];"#));
+/// ```
+pub fn rule_graph(cddl: &CDDL, format: GraphFormat) -> String {
+  let graph = Graph::new(cddl);
+
+  match format {
+    GraphFormat::Dot => graph.dot(),
+    GraphFormat::Mermaid => graph.mermaid(),
+  }
+}
+
+struct Node {
+  name: String,
+  label: String,
+  is_group: bool,
+  is_socket: bool,
+  is_defined: bool,
+}
+
+struct Edge {
+  from: usize,
+  to: usize,
+  generic_args: Option<String>,
+  is_cyclic: bool,
+}
+
+struct Graph {
+  nodes: Vec<Node>,
+  edges: Vec<Edge>,
+}
+
+impl Graph {
+  fn new(cddl: &CDDL) -> Self {
+    let mut graph = Graph {
+      nodes: Vec::new(),
+      edges: Vec::new(),
+    };
+    let mut indices = BTreeMap::new();
+
+    for rule in cddl.rules.iter() {
+      let (name, generic_param, is_group) = match rule {
+        Rule::Type { rule, .. } => (&rule.name, &rule.generic_param, false),
+        Rule::Group { rule, .. } => (&rule.name, &rule.generic_param, true),
+      };
+
+      let name = name.to_string();
+      if indices.contains_key(&name) {
+        continue;
+      }
+
+      indices.insert(name.clone(), graph.nodes.len());
+      graph.nodes.push(Node {
+        label: match generic_param {
+          Some(gp) => format!("{}{}", name, gp),
+          None => name.clone(),
+        },
+        name,
+        is_group,
+        is_socket: rule_socket(rule).is_some(),
+        is_defined: true,
+      });
+    }
+
+    for rule in cddl.rules.iter() {
+      let mut references = References {
+        generic_params: Vec::new(),
+        references: Vec::new(),
+      };
+      visitor::walk_rule(&mut references, rule);
+
+      let from = indices[&rule_name(rule)];
+      for (ident, generic_args) in references.references {
+        let name = ident.to_string();
+        let to = match indices.get(&name) {
+          Some(idx) => *idx,
+          None => {
+            let idx = graph.nodes.len();
+            indices.insert(name.clone(), idx);
+            graph.nodes.push(Node {
+              label: name.clone(),
+              name,
+              is_group: ident.socket == Some(SocketPlug::GROUP),
+              is_socket: ident.socket.is_some(),
+              is_defined: false,
+            });
+            idx
+          }
+        };
+
+        if !graph
+          .edges
+          .iter()
+          .any(|e| e.from == from && e.to == to && e.generic_args == generic_args)
+        {
+          graph.edges.push(Edge {
+            from,
+            to,
+            generic_args,
+            is_cyclic: false,
+          });
+        }
+      }
+    }
+
+    graph.mark_cycles();
+
+    graph
+  }
+
+  // Marks the edges within strongly connected components, found with
+  // Tarjan's algorithm, along with any edges from a rule to itself
+  fn mark_cycles(&mut self) {
+    let mut tarjan = Tarjan {
+      graph: self,
+      index: 0,
+      indices: vec![None; self.nodes.len()],
+      lowlinks: vec![0; self.nodes.len()],
+      stack: Vec::new(),
+      on_stack: vec![false; self.nodes.len()],
+      components: vec![0; self.nodes.len()],
+      component_count: 0,
+    };
+    for node in 0..self.nodes.len() {
+      if tarjan.indices[node].is_none() {
+        tarjan.connect(node);
+      }
+    }
+
+    let components = tarjan.components;
+    for edge in self.edges.iter_mut() {
+      edge.is_cyclic = components[edge.from] == components[edge.to];
+    }
+  }
+
+  fn dot(&self) -> String {
+    let mut output = String::from("digraph cddl {\n  node [shape=box];\n");
+
+    for node in self.nodes.iter() {
+      let mut attributes = Vec::new();
+      if node.label != node.name {
+        attributes.push(format!("label={}", dot_string(&node.label)));
+      }
+
+      let mut styles = Vec::new();
+      if node.is_group {
+        styles.push("rounded");
+      }
+      if node.is_socket {
+        styles.push("dashed");
+      }
+      match styles.len() {
+        0 => (),
+        1 => attributes.push(format!("style={}", styles[0])),
+        _ => attributes.push(format!("style=\"{}\"", styles.join(","))),
+      }
+
+      if !node.is_defined && !node.is_socket {
+        attributes.push("color=red".to_string());
+      }
+
+      let _ = writeln!(
+        output,
+        "  {}{};",
+        dot_string(&node.name),
+        dot_attributes(&attributes)
+      );
+    }
+
+    for edge in self.edges.iter() {
+      let mut attributes = Vec::new();
+      if let Some(ga) = &edge.generic_args {
+        attributes.push(format!("label={}", dot_string(ga)));
+      }
+      if edge.is_cyclic {
+        attributes.push("color=red".to_string());
+      }
+
+      let _ = writeln!(
+        output,
+        "  {} -> {}{};",
+        dot_string(&self.nodes[edge.from].name),
+        dot_string(&self.nodes[edge.to].name),
+        dot_attributes(&attributes)
+      );
+    }
+
+    output.push_str("}\n");
+
+    output
+  }
+
+  fn mermaid(&self) -> String {
+    let mut output = String::from("flowchart LR\n");
+
+    // Names aren't necessarily valid Mermaid IDs, so nodes are identified by
+    // their position instead
+    for (idx, node) in self.nodes.iter().enumerate() {
+      let label = mermaid_string(&node.label);
+      if node.is_group {
+        let _ = writeln!(output, "  n{}({})", idx, label);
+      } else {
+        let _ = writeln!(output, "  n{}[{}]", idx, label);
+      }
+    }
+
+    for edge in self.edges.iter() {
+      match &edge.generic_args {
+        Some(ga) => {
+          let _ = writeln!(
+            output,
+            "  n{} -->|{}| n{}",
+            edge.from,
+            mermaid_string(ga),
+            edge.to
+          );
+        }
+        None => {
+          let _ = writeln!(output, "  n{} --> n{}", edge.from, edge.to);
+        }
+      }
+    }
+
+    let nodes_where = |f: &dyn Fn(&Node) -> bool| {
+      self
+        .nodes
+        .iter()
+        .enumerate()
+        .filter(|(_, n)| f(n))
+        .map(|(idx, _)| format!("n{}", idx))
+        .collect::<Vec<_>>()
+    };
+
+    let sockets = nodes_where(&|n| n.is_socket);
+    if !sockets.is_empty() {
+      output.push_str("  classDef socket stroke-dasharray: 5 5\n");
+      let _ = writeln!(output, "  class {} socket", sockets.join(","));
+    }
+
+    let undefined = nodes_where(&|n| !n.is_defined && !n.is_socket);
+    if !undefined.is_empty() {
+      output.push_str("  classDef undefined stroke:red\n");
+      let _ = writeln!(output, "  class {} undefined", undefined.join(","));
+    }
+
+    let cyclic = self
+      .edges
+      .iter()
+      .enumerate()
+      .filter(|(_, e)| e.is_cyclic)
+      .map(|(idx, _)| idx.to_string())
+      .collect::<Vec<_>>();
+    if !cyclic.is_empty() {
+      let _ = writeln!(output, "  linkStyle {} stroke:red", cyclic.join(","));
+    }
+
+    output
+  }
+}
+
+struct Tarjan<'g> {
+  graph: &'g Graph,
+  index: usize,
+  indices: Vec<Option<usize>>,
+  lowlinks: Vec<usize>,
+  stack: Vec<usize>,
+  on_stack: Vec<bool>,
+  components: Vec<usize>,
+  component_count: usize,
+}
+
+impl<'g> Tarjan<'g> {
+  fn connect(&mut self, node: usize) {
+    self.indices[node] = Some(self.index);
+    self.lowlinks[node] = self.index;
+    self.index += 1;
+    self.stack.push(node);
+    self.on_stack[node] = true;
+
+    let graph = self.graph;
+    for edge in graph.edges.iter().filter(|e| e.from == node) {
+      match self.indices[edge.to] {
+        None => {
+          self.connect(edge.to);
+          self.lowlinks[node] = self.lowlinks[node].min(self.lowlinks[edge.to]);
+        }
+        Some(idx) if self.on_stack[edge.to] => {
+          self.lowlinks[node] = self.lowlinks[node].min(idx);
+        }
+        _ => (),
+      }
+    }
+
+    if Some(self.lowlinks[node]) == self.indices[node] {
+      while let Some(n) = self.stack.pop() {
+        self.on_stack[n] = false;
+        self.components[n] = self.component_count;
+        if n == node {
+          break;
+        }
+      }
+      self.component_count += 1;
+    }
+  }
+}
+
+// Names referred to by a rule, along with any generic arguments, excluding
+// its generic parameters and prelude types
+struct References<'a> {
+  generic_params: Vec<&'a str>,
+  references: Vec<(Identifier<'a>, Option<String>)>,
+}
+
+impl<'a> References<'a> {
+  fn push(&mut self, ident: &Identifier<'a>, generic_arg: &Option<GenericArg<'a>>) {
+    if ident.socket.is_none()
+      && (self.generic_params.contains(&ident.ident) || is_prelude_type(ident.ident))
+    {
+      return;
+    }
+
+    self
+      .references
+      .push((ident.clone(), generic_arg.as_ref().map(|ga| ga.to_string())));
+  }
+}
+
+impl<'a> Visitor<'a> for References<'a> {
+  fn visit_generic_params(&mut self, gp: &GenericParm<'a>) {
+    self.generic_params = gp.params.iter().map(|p| p.ident).collect();
+  }
+
+  fn visit_type2(&mut self, t2: &Type2<'a>) {
+    match t2 {
+      Type2::Typename {
+        ident, generic_arg, ..
+      }
+      | Type2::Unwrap {
+        ident, generic_arg, ..
+      }
+      | Type2::ChoiceFromGroup {
+        ident, generic_arg, ..
+      } => {
+        self.push(ident, generic_arg);
+        if let Some(ga) = generic_arg {
+          visitor::walk_generic_args(self, ga);
+        }
+      }
+      _ => visitor::walk_type2(self, t2),
+    }
+  }
+
+  fn visit_type_groupname_entry(&mut self, tge: &TypeGroupnameEntry<'a>) {
+    self.push(&tge.name, &tge.generic_arg);
+    if let Some(ga) = &tge.generic_arg {
+      visitor::walk_generic_args(self, ga);
+    }
+  }
+}
+
+fn rule_socket(rule: &Rule) -> Option<SocketPlug> {
+  match rule {
+    Rule::Type { rule, .. } => rule.name.socket,
+    Rule::Group { rule, .. } => rule.name.socket,
+  }
+}
+
+fn rule_name(rule: &Rule) -> String {
+  match rule {
+    Rule::Type { rule, .. } => rule.name.to_string(),
+    Rule::Group { rule, .. } => rule.name.to_string(),
+  }
+}
+
+fn dot_string(text: &str) -> String {
+  format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn dot_attributes(attributes: &[String]) -> String {
+  if attributes.is_empty() {
+    return String::new();
+  }
+
+  format!(" [{}]", attributes.join(", "))
+}
+
+fn mermaid_string(text: &str) -> String {
+  format!(
+    "\"{}\"",
+    text
+      .replace('"', "#quot;")
+      .replace('<', "#lt;")
+      .replace('>', "#gt;")
+  )
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+  use super::*;
+  use crate::{lexer_from_str, parser::cddl_from_str};
+
+  const INPUT: &str = r#"message = { header: header, body: pair<entry, uint> }
+header = { id: uint, * $$header-extension }
+pair<K, V> = [K, V]
+entry = (key: tstr, ? next: message)
+tree = [* tree] / missing
+$$header-extension //= (version: uint)
+"#;
+
+  #[test]
+  fn verify_dot_output() {
+    let cddl = cddl_from_str(&mut lexer_from_str(INPUT), INPUT, false).unwrap();
+
+    assert_eq!(
+      rule_graph(&cddl, GraphFormat::Dot),
+      r#"digraph cddl {
+  node [shape=box];
+  "message";
+  "header";
+  "pair" [label="pair<K, V>"];
+  "entry" [style=rounded];
+  "tree";
+  "$$header-extension" [style="rounded,dashed"];
+  "missing" [color=red];
+  "message" -> "header";
+  "message" -> "pair" [label="<entry, uint>"];
+  "message" -> "entry" [color=red];
+  "header" -> "$$header-extension";
+  "entry" -> "message" [color=red];
+  "tree" -> "tree" [color=red];
+  "tree" -> "missing";
+}
+"#
+    );
+  }
+
+  #[test]
+  fn verify_mermaid_output() {
+    let cddl = cddl_from_str(&mut lexer_from_str(INPUT), INPUT, false).unwrap();
+
+    assert_eq!(
+      rule_graph(&cddl, GraphFormat::Mermaid),
+      r##"flowchart LR
+  n0["message"]
+  n1["header"]
+  n2["pair#lt;K, V#gt;"]
+  n3("entry")
+  n4["tree"]
+  n5("$$header-extension")
+  n6["missing"]
+  n0 --> n1
+  n0 -->|"#lt;entry, uint#gt;"| n2
+  n0 --> n3
+  n1 --> n5
+  n3 --> n0
+  n4 --> n4
+  n4 --> n6
+  classDef socket stroke-dasharray: 5 5
+  class n5 socket
+  classDef undefined stroke:red
+  class n6 undefined
+  linkStyle 2,4,5 stroke:red
+"##
+    );
+  }
+}
@@ -0,0 +1,1620 @@
+use super::{
+  ast::*,
+  formatter::{format_str, FormatterOptions},
+  token::{self, Token},
+};
+use serde_json::{json, Map, Value};
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  convert::TryFrom,
+  fmt,
+};
+
+/// URI of the JSON Schema dialect generated by `from_cddl`
+pub const DRAFT_2020_12: &str = "https://json-schema.org/draft/2020-12/schema";
+
+// Generic rules are inlined at each reference, so recursive generic rules are
+// cut off at this depth
+const MAX_INLINE_DEPTH: usize = 32;
+
+/// Converts a CDDL definition into a JSON Schema (draft 2020-12) document
+///
+/// Each non-generic type rule is emitted under `$defs` and referenced via
+/// `$ref`, with the first type rule used as the root of the document. Group
+/// rules and generic rules are inlined wherever they're referenced. Maps become
+/// closed objects, arrays become `prefixItems` or `items` depending on whether
+/// they're made up of a single repeated entry, type choices become `anyOf` (or
+/// `enum` when each choice is a literal value) and occurrence indicators become
+/// `required`, `minItems` and `maxItems`. Ranges and the `.size`, `.regexp`,
+/// `.pcre`, `.lt`, `.le`, `.gt`, `.ge`, `.eq`, `.ne`, `.default` and `.and`
+/// controls are translated to their equivalent keywords, and text strings
+/// constrained by `.json` carry the schema of their content in `contentSchema`.
+///
+/// Constructs without a JSON equivalent (e.g. non-text map keys or the `.cbor`
+/// control) are either skipped or left unconstrained. Also note that `.size`
+/// limits the number of bytes of a text string whereas `minLength` and
+/// `maxLength` count characters, so the two only agree for ASCII text.
+///
+/// # Example
+///
+/// ```
+/// use cddl::{json_schema::from_cddl, lexer_from_str, parser::cddl_from_str};
+///
+/// let input = r#"person = { name: tstr, ? age: uint }"#;
+/// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+///
+/// let schema = from_cddl(&cddl);
+/// assert_eq!(schema["$ref"], "#/$defs/person");
+/// assert_eq!(schema["$defs"]["person"]["required"], serde_json::json!(["name"]));
+/// ```
+pub fn from_cddl(cddl: &CDDL) -> Value {
+  let mut converter = Converter {
+    cddl,
+    bindings: Vec::new(),
+    depth: 0,
+  };
+
+  let mut defs = Map::new();
+  let mut root = None;
+
+  for rule in cddl.rules.iter().filter(|r| !r.is_choice_alternate()) {
+    if let Rule::Type { rule, .. } = rule {
+      if root.is_none() {
+        root = Some(rule);
+      }
+
+      if rule.generic_param.is_none() {
+        defs.insert(
+          rule.name.ident.to_string(),
+          converter.type_rule(rule.name.ident),
+        );
+      }
+    }
+  }
+
+  let mut schema = Map::new();
+  schema.insert("$schema".to_string(), json!(DRAFT_2020_12));
+
+  if let Some(root) = root.filter(|r| r.generic_param.is_none()) {
+    schema.insert(
+      "$ref".to_string(),
+      json!(format!("#/$defs/{}", root.name.ident)),
+    );
+  }
+
+  schema.insert("$defs".to_string(), Value::Object(defs));
+
+  Value::Object(schema)
+}
+
+struct Converter<'a, 'b> {
+  cddl: &'b CDDL<'a>,
+  // Schemas of the generic arguments for the generic rules being inlined
+  bindings: Vec<(&'a str, Value)>,
+  depth: usize,
+}
+
+// Properties accumulated from the entries of a map
+#[derive(Default)]
+struct Object {
+  properties: Map<String, Value>,
+  pattern_properties: Map<String, Value>,
+  additional_properties: Option<Value>,
+  required: Vec<Value>,
+}
+
+impl<'a, 'b> Converter<'a, 'b> {
+  fn type_rule(&mut self, name: &str) -> Value {
+    // Rules extended via /= contribute additional type choices
+    let mut schemas = Vec::new();
+    for rule in self.cddl.rules_named(name) {
+      if let Rule::Type { rule, .. } = rule {
+        for tc in rule.value.type_choices.iter() {
+          schemas.push(self.type1(tc));
+        }
+      }
+    }
+
+    any_of(schemas)
+  }
+
+  fn r#type(&mut self, t: &Type<'a>) -> Value {
+    let schemas = t
+      .type_choices
+      .iter()
+      .map(|tc| self.type1(tc))
+      .collect::<Vec<_>>();
+
+    any_of(schemas)
+  }
+
+  fn type1(&mut self, t1: &Type1<'a>) -> Value {
+    match &t1.operator {
+      Some((RangeCtlOp::RangeOp { is_inclusive, .. }, upper)) => {
+        self.range(&t1.type2, upper, *is_inclusive)
+      }
+      Some((RangeCtlOp::CtlOp { ctrl, .. }, controller)) => {
+        self.control(&t1.type2, ctrl, controller)
+      }
+      None => self.type2(&t1.type2),
+    }
+  }
+
+  fn type2(&mut self, t2: &Type2<'a>) -> Value {
+    match t2 {
+      Type2::IntValue { value, .. } => json!({ "const": int(*value) }),
+      Type2::UintValue { value, .. } => json!({ "const": uint(*value) }),
+      Type2::FloatValue { value, .. } => json!({ "const": value }),
+      Type2::TextValue { value, .. } => json!({ "const": value }),
+      Type2::UTF8ByteString { .. } | Type2::B16ByteString { .. } | Type2::B64ByteString { .. } => {
+        bytes()
+      }
+      Type2::Typename {
+        ident, generic_arg, ..
+      } => self.typename(ident, generic_arg.as_ref()),
+      Type2::ParenthesizedType { pt, .. } => self.r#type(pt),
+      Type2::Map { group, .. } => {
+        let schemas = group
+          .group_choices
+          .iter()
+          .map(|gc| self.map(&gc.group_entries))
+          .collect();
+
+        any_of(schemas)
+      }
+      Type2::Array { group, .. } => {
+        let schemas = group
+          .group_choices
+          .iter()
+          .map(|gc| self.array(&gc.group_entries))
+          .collect();
+
+        any_of(schemas)
+      }
+      Type2::ChoiceFromInlineGroup { group, .. } => self.enumeration(group),
+      Type2::ChoiceFromGroup { ident, .. } => {
+        let mut schemas = Vec::new();
+        for rule in self.cddl.rules_named(ident.ident) {
+          if let Rule::Group { rule, .. } = rule {
+            match &rule.entry {
+              GroupEntry::InlineGroup { group, .. } => schemas.push(self.enumeration(group)),
+              GroupEntry::ValueMemberKey { ge, .. } => schemas.push(self.r#type(&ge.entry_type)),
+              GroupEntry::TypeGroupname { .. } => schemas.push(json!({})),
+            }
+          }
+        }
+
+        any_of(schemas)
+      }
+      Type2::TaggedData { t, .. } => self.r#type(t),
+      Type2::TaggedDataMajorType { mt, constraint, .. } => match (mt, constraint) {
+        (0, _) => json!({ "type": "integer", "minimum": 0 }),
+        (1, _) => json!({ "type": "integer", "maximum": -1 }),
+        (2, _) => bytes(),
+        (3, _) => json!({ "type": "string" }),
+        (4, _) => json!({ "type": "array" }),
+        (5, _) => json!({ "type": "object" }),
+        (7, Some(20)) => json!({ "const": false }),
+        (7, Some(21)) => json!({ "const": true }),
+        (7, Some(22)) => json!({ "type": "null" }),
+        (7, Some(25)) | (7, Some(26)) | (7, Some(27)) => json!({ "type": "number" }),
+        _ => json!({}),
+      },
+      Type2::Unwrap { .. } | Type2::Any(_) => json!({}),
+    }
+  }
+
+  fn typename(&mut self, ident: &Identifier<'a>, generic_arg: Option<&GenericArg<'a>>) -> Value {
+    if let Some((_, schema)) = self
+      .bindings
+      .iter()
+      .rev()
+      .find(|(param, _)| *param == ident.ident)
+    {
+      return schema.clone();
+    }
+
+    let rule = match self.cddl.rule(ident.ident) {
+      Some(Rule::Type { rule, .. }) => rule,
+      Some(Rule::Group { .. }) => return json!({}),
+      None => return prelude(ident.ident),
+    };
+
+    let gp = match &rule.generic_param {
+      Some(gp) => gp,
+      None => return json!({ "$ref": format!("#/$defs/{}", ident.ident) }),
+    };
+
+    if self.depth >= MAX_INLINE_DEPTH {
+      return json!({});
+    }
+
+    self.with_generic_args(gp, generic_arg, |c| c.type_rule(ident.ident))
+  }
+
+  // Converts a type within the scope of the given generic arguments
+  fn with_generic_args<F>(
+    &mut self,
+    gp: &GenericParm<'a>,
+    generic_arg: Option<&GenericArg<'a>>,
+    f: F,
+  ) -> Value
+  where
+    F: FnOnce(&mut Self) -> Value,
+  {
+    let args = generic_arg
+      .iter()
+      .flat_map(|ga| ga.args.iter())
+      .map(|arg| self.type1(arg))
+      .collect::<Vec<_>>();
+
+    let len = self.bindings.len();
+    for (param, arg) in gp.params.iter().zip(args) {
+      self.bindings.push((param.ident, arg));
+    }
+
+    self.depth += 1;
+    let schema = f(self);
+    self.depth -= 1;
+
+    self.bindings.truncate(len);
+
+    schema
+  }
+
+  fn range(&mut self, lower: &Type2<'a>, upper: &Type2<'a>, is_inclusive: bool) -> Value {
+    let (lower, upper) = match (self.numeric_value(lower), self.numeric_value(upper)) {
+      (Some(lower), Some(upper)) => (lower, upper),
+      _ => return json!({ "type": "number" }),
+    };
+
+    let is_integer = lower.is_i64() || lower.is_u64();
+    let is_integer = is_integer && (upper.is_i64() || upper.is_u64());
+
+    let mut schema = Map::new();
+    schema.insert(
+      "type".to_string(),
+      json!(if is_integer { "integer" } else { "number" }),
+    );
+    schema.insert("minimum".to_string(), lower);
+
+    if is_inclusive {
+      schema.insert("maximum".to_string(), upper);
+    } else {
+      schema.insert("exclusiveMaximum".to_string(), upper);
+    }
+
+    Value::Object(schema)
+  }
+
+  fn control(&mut self, target: &Type2<'a>, ctrl: &str, controller: &Type2<'a>) -> Value {
+    let mut schema = self.type2(target);
+    let is_integer = schema["type"] == "integer";
+
+    let mut constrain = |keyword: &str, value: Value| {
+      if let Value::Object(o) = &mut schema {
+        o.insert(keyword.to_string(), value);
+      }
+    };
+
+    match token::lookup_control_from_str(ctrl) {
+      Some(Token::SIZE) => {
+        let (min, max) = match self.size(controller) {
+          Some(size) => size,
+          None => return schema,
+        };
+
+        if is_integer {
+          // The size of an unsigned integer is the number of bytes it occupies
+          if max < 8 {
+            constrain("maximum", json!((1u64 << (max * 8)) - 1));
+          }
+        } else {
+          constrain("minLength", json!(min));
+          constrain("maxLength", json!(max));
+        }
+      }
+      Some(Token::CREGEXP) | Some(Token::PCRE) => {
+        if let Type2::TextValue { value, .. } = controller {
+          constrain("pattern", json!(value));
+        }
+      }
+      Some(Token::LT) | Some(Token::LE) | Some(Token::GT) | Some(Token::GE) => {
+        let keyword = match ctrl {
+          ".lt" => "exclusiveMaximum",
+          ".le" => "maximum",
+          ".gt" => "exclusiveMinimum",
+          _ => "minimum",
+        };
+
+        if let Some(value) = self.numeric_value(controller) {
+          constrain(keyword, value);
+        }
+      }
+      Some(Token::EQ) => {
+        if let Some(value) = self.literal(controller) {
+          constrain("const", value);
+        }
+      }
+      Some(Token::NE) => {
+        if let Some(value) = self.literal(controller) {
+          constrain("not", json!({ "const": value }));
+        }
+      }
+      Some(Token::DEFAULT) => {
+        if let Some(value) = self.literal(controller) {
+          constrain("default", value);
+        }
+      }
+      Some(Token::AND) | Some(Token::WITHIN) => {
+        let controller = self.type2(controller);
+        return json!({ "allOf": [schema, controller] });
+      }
+      Some(Token::JSON) => {
+        constrain("contentMediaType", json!("application/json"));
+        constrain("contentSchema", self.type2(controller));
+      }
+      _ => (),
+    }
+
+    schema
+  }
+
+  // Returns the lower and upper bounds of a .size controller
+  fn size(&mut self, controller: &Type2<'a>) -> Option<(u64, u64)> {
+    if let Type2::ParenthesizedType { pt, .. } = controller {
+      if let [Type1 {
+        type2: lower,
+        operator: Some((RangeCtlOp::RangeOp { is_inclusive, .. }, upper)),
+        ..
+      }] = pt.type_choices.as_slice()
+      {
+        let lower = self.numeric_value(lower)?.as_u64()?;
+        let upper = self.numeric_value(upper)?.as_u64()?;
+
+        if *is_inclusive {
+          return Some((lower, upper));
+        }
+
+        return Some((lower, upper.checked_sub(1)?));
+      }
+    }
+
+    let size = self.numeric_value(controller)?.as_u64()?;
+    Some((0, size))
+  }
+
+  // Resolves a type to a literal value, following rules that consist of a
+  // single value
+  fn literal(&self, t2: &Type2<'a>) -> Option<Value> {
+    let mut t2 = t2;
+
+    for _ in 0..MAX_INLINE_DEPTH {
+      match t2 {
+        Type2::IntValue { value, .. } => return Some(int(*value)),
+        Type2::UintValue { value, .. } => return Some(uint(*value)),
+        Type2::FloatValue { value, .. } => return Some(json!(value)),
+        Type2::TextValue { value, .. } => return Some(json!(value)),
+        Type2::Typename { ident, .. } => match self.cddl.rule(ident.ident) {
+          Some(Rule::Type { rule, .. }) => match rule.value.type_choices.as_slice() {
+            [tc] if tc.operator.is_none() => t2 = &tc.type2,
+            _ => return None,
+          },
+          _ => return None,
+        },
+        _ => return None,
+      }
+    }
+
+    None
+  }
+
+  fn numeric_value(&self, t2: &Type2<'a>) -> Option<Value> {
+    self.literal(t2).filter(|v| v.is_number())
+  }
+
+  fn map(&mut self, entries: &[(GroupEntry<'a>, bool)]) -> Value {
+    let mut object = Object::default();
+    for (ge, _) in entries.iter() {
+      self.map_entry(&mut object, ge, false);
+    }
+
+    let mut schema = Map::new();
+    schema.insert("type".to_string(), json!("object"));
+    schema.insert("properties".to_string(), Value::Object(object.properties));
+
+    if !object.pattern_properties.is_empty() {
+      schema.insert(
+        "patternProperties".to_string(),
+        Value::Object(object.pattern_properties),
+      );
+    }
+
+    if !object.required.is_empty() {
+      schema.insert("required".to_string(), Value::Array(object.required));
+    }
+
+    // Maps only allow the entries they define
+    schema.insert(
+      "additionalProperties".to_string(),
+      object.additional_properties.unwrap_or(Value::Bool(false)),
+    );
+
+    Value::Object(schema)
+  }
+
+  fn map_entry(&mut self, object: &mut Object, ge: &GroupEntry<'a>, is_optional: bool) {
+    match ge {
+      GroupEntry::ValueMemberKey { ge, .. } => {
+        let is_optional = is_optional || bounds(ge.occur.as_ref()).0 == 0;
+
+        let key = match &ge.member_key {
+          Some(MemberKey::Bareword { ident, .. }) => ident.ident,
+          Some(MemberKey::Value {
+            value: token::Value::TEXT(text),
+            ..
+          }) => text,
+          Some(MemberKey::Type1 { t1, .. }) => {
+            let value = self.r#type(&ge.entry_type);
+
+            match (&t1.type2, &t1.operator) {
+              (Type2::TextValue { value: key, .. }, None) => {
+                object.properties.insert(key.to_string(), value);
+                if !is_optional {
+                  object.required.push(json!(key));
+                }
+              }
+              (
+                _,
+                Some((RangeCtlOp::CtlOp { ctrl, .. }, Type2::TextValue { value: pattern, .. })),
+              ) if *ctrl == ".regexp" || *ctrl == ".pcre" => {
+                object.pattern_properties.insert(pattern.to_string(), value);
+              }
+              _ => object.additional_properties = Some(value),
+            }
+
+            return;
+          }
+          // JSON object keys can only be strings
+          _ => return,
+        };
+
+        let value = self.r#type(&ge.entry_type);
+        object.properties.insert(key.to_string(), value);
+        if !is_optional {
+          object.required.push(json!(key));
+        }
+      }
+      GroupEntry::TypeGroupname { ge, .. } => {
+        let is_optional = is_optional || bounds(ge.occur.as_ref()).0 == 0;
+
+        let rules = self
+          .cddl
+          .rules_named(ge.name.ident)
+          .filter_map(|r| match r {
+            Rule::Group { rule, .. } => Some(rule),
+            _ => None,
+          })
+          .collect::<Vec<_>>();
+
+        if self.depth >= MAX_INLINE_DEPTH {
+          return;
+        }
+
+        // Entries of a group with alternates (//=) may or may not be present
+        let is_optional = is_optional || rules.len() > 1;
+
+        for rule in rules {
+          match &rule.generic_param {
+            Some(gp) => {
+              self.with_generic_args(gp, ge.generic_arg.as_ref(), |c| {
+                c.map_entry(object, &rule.entry, is_optional);
+                Value::Null
+              });
+            }
+            None => {
+              self.depth += 1;
+              self.map_entry(object, &rule.entry, is_optional);
+              self.depth -= 1;
+            }
+          }
+        }
+      }
+      GroupEntry::InlineGroup { occur, group, .. } => {
+        // Entries of a group choice may or may not be present
+        let is_optional =
+          is_optional || bounds(occur.as_ref()).0 == 0 || group.group_choices.len() > 1;
+
+        for gc in group.group_choices.iter() {
+          for (ge, _) in gc.group_entries.iter() {
+            self.map_entry(object, ge, is_optional);
+          }
+        }
+      }
+    }
+  }
+
+  fn array(&mut self, entries: &[(GroupEntry<'a>, bool)]) -> Value {
+    let mut items = Vec::new();
+    for (ge, _) in entries.iter() {
+      self.array_entry(&mut items, ge, (1, Some(1)));
+    }
+
+    let mut schema = Map::new();
+    schema.insert("type".to_string(), json!("array"));
+
+    // Arrays made up of a single entry that can occur any number of times
+    if let [(item, (min, max))] = items.as_slice() {
+      if *max != Some(1) {
+        schema.insert("items".to_string(), item.clone());
+        if *min > 0 {
+          schema.insert("minItems".to_string(), json!(min));
+        }
+        if let Some(max) = max {
+          schema.insert("maxItems".to_string(), json!(max));
+        }
+
+        return Value::Object(schema);
+      }
+    }
+
+    let mut prefix_items = Vec::new();
+    let mut min_items = 0;
+    let mut rest = Some(Value::Bool(false));
+
+    for (idx, (item, (min, max))) in items.iter().enumerate() {
+      if *max == Some(1) {
+        prefix_items.push(item.clone());
+        min_items += min;
+        continue;
+      }
+
+      // Only a repeated trailing entry can be expressed by "items". Any entries
+      // after a repeated entry are left unconstrained.
+      min_items += min;
+      rest = if idx == items.len() - 1 {
+        Some(item.clone())
+      } else {
+        None
+      };
+      break;
+    }
+
+    if !prefix_items.is_empty() {
+      schema.insert("prefixItems".to_string(), Value::Array(prefix_items));
+    }
+    if let Some(rest) = rest {
+      schema.insert("items".to_string(), rest);
+    }
+    if min_items > 0 {
+      schema.insert("minItems".to_string(), json!(min_items));
+    }
+
+    Value::Object(schema)
+  }
+
+  fn array_entry(
+    &mut self,
+    items: &mut Vec<(Value, (usize, Option<usize>))>,
+    ge: &GroupEntry<'a>,
+    occurrence: (usize, Option<usize>),
+  ) {
+    match ge {
+      GroupEntry::ValueMemberKey { ge, .. } => {
+        let schema = self.r#type(&ge.entry_type);
+        items.push((schema, nested(occurrence, bounds(ge.occur.as_ref()))));
+      }
+      GroupEntry::TypeGroupname { ge, .. } => {
+        let occurrence = nested(occurrence, bounds(ge.occur.as_ref()));
+
+        match self.cddl.rule(ge.name.ident) {
+          Some(Rule::Group { rule, .. }) if self.depth < MAX_INLINE_DEPTH => {
+            match &rule.generic_param {
+              Some(gp) => {
+                self.with_generic_args(gp, ge.generic_arg.as_ref(), |c| {
+                  c.array_entry(items, &rule.entry, occurrence);
+                  Value::Null
+                });
+              }
+              None => {
+                self.depth += 1;
+                self.array_entry(items, &rule.entry, occurrence);
+                self.depth -= 1;
+              }
+            }
+          }
+          Some(Rule::Group { .. }) => items.push((json!({}), occurrence)),
+          _ => {
+            let schema = self.typename(&ge.name, ge.generic_arg.as_ref());
+            items.push((schema, occurrence));
+          }
+        }
+      }
+      GroupEntry::InlineGroup { occur, group, .. } => {
+        let occurrence = nested(occurrence, bounds(occur.as_ref()));
+
+        if let [gc] = group.group_choices.as_slice() {
+          for (ge, _) in gc.group_entries.iter() {
+            self.array_entry(items, ge, occurrence);
+          }
+        } else {
+          // Group choices nested within an array are treated as a choice
+          // between arrays
+          let schemas = group
+            .group_choices
+            .iter()
+            .map(|gc| self.array(&gc.group_entries))
+            .collect();
+
+          items.push((any_of(schemas), occurrence));
+        }
+      }
+    }
+  }
+
+  // Returns the choice of values of the entries of a group, e.g. &(a: 1, b: 2)
+  fn enumeration(&mut self, group: &Group<'a>) -> Value {
+    let mut schemas = Vec::new();
+    for gc in group.group_choices.iter() {
+      for (ge, _) in gc.group_entries.iter() {
+        match ge {
+          GroupEntry::ValueMemberKey { ge, .. } => schemas.push(self.r#type(&ge.entry_type)),
+          GroupEntry::TypeGroupname { ge, .. } => {
+            schemas.push(self.typename(&ge.name, ge.generic_arg.as_ref()))
+          }
+          GroupEntry::InlineGroup { group, .. } => schemas.push(self.enumeration(group)),
+        }
+      }
+    }
+
+    any_of(schemas)
+  }
+}
+
+// Combines the schemas of a choice into a single schema
+fn any_of(mut schemas: Vec<Value>) -> Value {
+  if schemas.len() == 1 {
+    return schemas.remove(0);
+  }
+
+  let consts = schemas
+    .iter()
+    .map(|s| match s.as_object() {
+      Some(o) if o.len() == 1 => o.get("const").cloned(),
+      _ => None,
+    })
+    .collect::<Option<Vec<_>>>();
+
+  match consts {
+    Some(consts) => json!({ "enum": consts }),
+    None => json!({ "anyOf": schemas }),
+  }
+}
+
+// Byte strings are represented as base64url-encoded text strings in JSON per
+// section 6.1 of RFC 8949
+fn bytes() -> Value {
+  json!({ "type": "string", "contentEncoding": "base64url" })
+}
+
+// JSON numbers are only exact within 64 bits, so larger integer literals are
+// approximated by floats
+fn int(value: i128) -> Value {
+  i64::try_from(value)
+    .map(Value::from)
+    .or_else(|_| u64::try_from(value).map(Value::from))
+    .unwrap_or_else(|_| Value::from(value as f64))
+}
+
+fn uint(value: u128) -> Value {
+  u64::try_from(value)
+    .map(Value::from)
+    .unwrap_or_else(|_| Value::from(value as f64))
+}
+
+fn prelude(ident: &str) -> Value {
+  match ident {
+    "bool" => json!({ "type": "boolean" }),
+    "true" => json!({ "const": true }),
+    "false" => json!({ "const": false }),
+    "uint" | "unsigned" => json!({ "type": "integer", "minimum": 0 }),
+    "nint" => json!({ "type": "integer", "maximum": -1 }),
+    "int" | "integer" => json!({ "type": "integer" }),
+    "float16" | "float32" | "float64" | "float16-32" | "float32-64" | "float" | "number"
+    | "time" => json!({ "type": "number" }),
+    "tstr" | "text" => json!({ "type": "string" }),
+    "tdate" => json!({ "type": "string", "format": "date-time" }),
+    "uri" => json!({ "type": "string", "format": "uri" }),
+    "regexp" => json!({ "type": "string", "format": "regex" }),
+    "bstr" | "bytes" | "b64url" => bytes(),
+    "null" | "nil" => json!({ "type": "null" }),
+    _ => json!({}),
+  }
+}
+
+// Returns the minimum and maximum number of times an entry can occur
+fn bounds(occur: Option<&Occur>) -> (usize, Option<usize>) {
+  match occur {
+    Some(Occur::Optional(_)) => (0, Some(1)),
+    Some(Occur::ZeroOrMore(_)) => (0, None),
+    Some(Occur::OneOrMore(_)) => (1, None),
+    Some(Occur::Exact { lower, upper, .. }) => (lower.unwrap_or(0), *upper),
+    None => (1, Some(1)),
+  }
+}
+
+// Combines the occurrence of a group with the occurrence of one of its entries
+fn nested(outer: (usize, Option<usize>), inner: (usize, Option<usize>)) -> (usize, Option<usize>) {
+  let max = match (outer.1, inner.1) {
+    (Some(outer), Some(inner)) => Some(outer * inner),
+    _ => None,
+  };
+
+  (outer.0 * inner.0, max)
+}
+
+/// A construct of a JSON Schema document that couldn't be converted to CDDL
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversionWarning {
+  /// Kind of warning
+  pub kind: ConversionWarningKind,
+  /// JSON pointer to the schema containing the construct
+  pub pointer: String,
+}
+
+/// Kinds of conversion warnings
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConversionWarningKind {
+  /// A keyword that has no CDDL equivalent and was ignored
+  UnsupportedKeyword(String),
+  /// A keyword whose value has no CDDL equivalent and was ignored
+  UnsupportedValue {
+    /// Keyword
+    keyword: String,
+    /// Value of the keyword
+    value: String,
+  },
+  /// A `$ref` that doesn't point to an entry of `$defs` or `definitions`
+  UnresolvedReference(String),
+  /// The generated CDDL failed to parse and is returned unformatted
+  InvalidCddl(String),
+}
+
+impl fmt::Display for ConversionWarning {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "#{}: ", self.pointer)?;
+
+    match &self.kind {
+      ConversionWarningKind::UnsupportedKeyword(keyword) => {
+        write!(f, "unsupported keyword \"{}\"", keyword)
+      }
+      ConversionWarningKind::UnsupportedValue { keyword, value } => {
+        write!(f, "unsupported value {} for keyword \"{}\"", value, keyword)
+      }
+      ConversionWarningKind::UnresolvedReference(reference) => {
+        write!(f, "unresolved reference \"{}\"", reference)
+      }
+      ConversionWarningKind::InvalidCddl(error) => {
+        write!(f, "generated CDDL is invalid: {}", error)
+      }
+    }
+  }
+}
+
+// Keywords that don't affect validation
+const ANNOTATIONS: &[&str] = &[
+  "$schema",
+  "$id",
+  "$anchor",
+  "$comment",
+  "$defs",
+  "definitions",
+  "title",
+  "description",
+  "default",
+  "examples",
+  "deprecated",
+  "readOnly",
+  "writeOnly",
+];
+
+/// Converts a JSON Schema document into CDDL text, along with warnings for any
+/// constructs that couldn't be converted
+///
+/// Each entry of `$defs` (or `definitions`) becomes a rule, and references to
+/// these entries become references to the rules. If the document itself
+/// describes a type, it's emitted as the first rule so that it's used as the
+/// root of the definition. The rule is named after the `title` of the
+/// document, or `root` if it doesn't have one.
+///
+/// Objects become maps, arrays become arrays with occurrence indicators,
+/// `anyOf`, `oneOf`, `enum` and lists of types become type choices, and
+/// numeric bounds, string lengths and patterns become ranges and controls.
+/// Since CDDL has no notion of exclusive choices, `oneOf` is treated the same
+/// as `anyOf`. Keywords without a CDDL equivalent are ignored and reported as
+/// warnings, as are references outside of the document. Annotations such as
+/// `title` and `description` are ignored silently.
+///
+/// # Example
+///
+/// ```
+/// use cddl::json_schema::to_cddl;
+/// use serde_json::json;
+///
+/// let (cddl, warnings) = to_cddl(&json!({
+///   "title": "person",
+///   "type": "object",
+///   "properties": {
+///     "age": { "type": "integer", "minimum": 0 },
+///     "name": { "type": "string" },
+///   },
+///   "required": ["name"],
+///   "additionalProperties": false,
+/// }));
+///
+/// assert_eq!(cddl, "person = {\n  ? age: uint,\n  name:  tstr,\n}\n");
+/// assert!(warnings.is_empty());
+/// ```
+pub fn to_cddl(schema: &Value) -> (String, Vec<ConversionWarning>) {
+  let mut importer = Importer {
+    references: BTreeMap::new(),
+    warnings: Vec::new(),
+  };
+
+  let mut names = BTreeSet::new();
+  let mut definitions = Vec::new();
+
+  for keyword in ["$defs", "definitions"].iter() {
+    if let Some(Value::Object(defs)) = schema.get(keyword) {
+      for (key, def) in defs.iter() {
+        let name = unique_identifier(key, &mut names);
+        let pointer = format!("/{}/{}", keyword, escape_pointer(key));
+
+        importer
+          .references
+          .insert(format!("#{}", pointer), name.clone());
+        definitions.push((name, def, pointer));
+      }
+    }
+  }
+
+  let mut rules = Vec::new();
+
+  let is_root_type = match schema {
+    Value::Object(o) => o.keys().any(|k| !ANNOTATIONS.contains(&k.as_str())),
+    _ => true,
+  };
+
+  if is_root_type {
+    let title = schema.get("title").and_then(Value::as_str);
+    let name = unique_identifier(title.unwrap_or("root"), &mut names);
+    let rule = format!("{} = {}", name, importer.schema(schema, ""));
+    rules.push(rule);
+  }
+
+  for (name, def, pointer) in definitions {
+    let rule = format!("{} = {}", name, importer.schema(def, &pointer));
+    rules.push(rule);
+  }
+
+  let mut cddl = rules.join("\n");
+  cddl.push('\n');
+
+  match format_str(&cddl, &FormatterOptions::default()) {
+    Ok(formatted) => (formatted, importer.warnings),
+    Err(error) => {
+      importer.warn("", ConversionWarningKind::InvalidCddl(error));
+      (cddl, importer.warnings)
+    }
+  }
+}
+
+struct Importer {
+  // Rule names of the definitions keyed by their reference, e.g. #/$defs/name
+  references: BTreeMap<String, String>,
+  warnings: Vec<ConversionWarning>,
+}
+
+impl Importer {
+  fn warn(&mut self, pointer: &str, kind: ConversionWarningKind) {
+    self.warnings.push(ConversionWarning {
+      kind,
+      pointer: pointer.to_string(),
+    });
+  }
+
+  fn unsupported_value(&mut self, pointer: &str, keyword: &str, value: &Value) {
+    self.warn(
+      pointer,
+      ConversionWarningKind::UnsupportedValue {
+        keyword: keyword.to_string(),
+        value: value.to_string(),
+      },
+    );
+  }
+
+  fn schema(&mut self, schema: &Value, pointer: &str) -> String {
+    match schema {
+      Value::Object(o) => self.object(o, pointer),
+      Value::Bool(true) => "any".to_string(),
+      _ => {
+        self.unsupported_value(pointer, "schema", schema);
+        "any".to_string()
+      }
+    }
+  }
+
+  fn object(&mut self, o: &Map<String, Value>, pointer: &str) -> String {
+    let mut used = vec!["nullable"];
+    let mut choices = Vec::new();
+
+    if let Some(reference) = o.get("$ref") {
+      used.push("$ref");
+      choices.push(self.reference(reference, pointer));
+    } else if let Some(value) = o.get("const") {
+      used.push("const");
+      choices.push(self.literal(value, pointer, "const"));
+    } else if let Some(Value::Array(values)) = o.get("enum") {
+      used.push("enum");
+      for value in values.iter() {
+        choices.push(self.literal(value, pointer, "enum"));
+      }
+    } else if let Some((keyword, Value::Array(schemas))) = o
+      .get_key_value("anyOf")
+      .or_else(|| o.get_key_value("oneOf"))
+    {
+      used.push(keyword);
+      for (idx, schema) in schemas.iter().enumerate() {
+        let pointer = format!("{}/{}/{}", pointer, keyword, idx);
+        choices.push(self.schema(schema, &pointer));
+      }
+    } else if let Some(Value::Array(schemas)) = o.get("allOf") {
+      used.push("allOf");
+      choices.push(self.all_of(schemas, pointer));
+    } else {
+      let types = match o.get("type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+        _ => vec![inferred_type(o)],
+      };
+      used.push("type");
+
+      for t in types {
+        choices.push(self.typed(t, o, pointer, &mut used));
+      }
+    }
+
+    if let Some(Value::Bool(true)) = o.get("nullable") {
+      choices.push("null".to_string());
+    }
+
+    for keyword in o.keys() {
+      if !used.contains(&keyword.as_str()) && !ANNOTATIONS.contains(&keyword.as_str()) {
+        self.warn(
+          pointer,
+          ConversionWarningKind::UnsupportedKeyword(keyword.clone()),
+        );
+      }
+    }
+
+    if choices.is_empty() {
+      return "any".to_string();
+    }
+
+    choices.join(" / ")
+  }
+
+  fn reference(&mut self, reference: &Value, pointer: &str) -> String {
+    let name = reference
+      .as_str()
+      .and_then(|r| self.references.get(r))
+      .cloned();
+
+    match name {
+      Some(name) => name,
+      None => {
+        let reference = reference.as_str().unwrap_or_default().to_string();
+        self.warn(
+          pointer,
+          ConversionWarningKind::UnresolvedReference(reference),
+        );
+        "any".to_string()
+      }
+    }
+  }
+
+  // Objects are merged into a single map. Any other combination can only be
+  // expressed by a single type, so only the first schema is used.
+  fn all_of(&mut self, schemas: &[Value], pointer: &str) -> String {
+    let is_objects = schemas
+      .iter()
+      .all(|s| inferred_type(s.as_object().unwrap_or(&Map::new())) == "object");
+
+    if is_objects && schemas.len() > 1 {
+      let mut merged = Map::new();
+      let mut required = Vec::new();
+
+      for schema in schemas.iter() {
+        for (key, value) in schema.as_object().into_iter().flatten() {
+          match (key.as_str(), value) {
+            ("properties", Value::Object(properties)) => {
+              let entry = merged
+                .entry("properties")
+                .or_insert_with(|| Value::Object(Map::new()));
+              if let Value::Object(entry) = entry {
+                entry.extend(properties.clone());
+              }
+            }
+            ("required", Value::Array(keys)) => required.extend(keys.iter().cloned()),
+            _ => {
+              merged.insert(key.clone(), value.clone());
+            }
+          }
+        }
+      }
+
+      merged.insert("required".to_string(), Value::Array(required));
+
+      return self.object(&merged, &format!("{}/allOf", pointer));
+    }
+
+    if schemas.len() > 1 {
+      self.warn(
+        pointer,
+        ConversionWarningKind::UnsupportedKeyword("allOf".to_string()),
+      );
+    }
+
+    match schemas.first() {
+      Some(schema) => self.schema(schema, &format!("{}/allOf/0", pointer)),
+      None => "any".to_string(),
+    }
+  }
+
+  fn typed<'k>(
+    &mut self,
+    t: &str,
+    o: &'k Map<String, Value>,
+    pointer: &str,
+    used: &mut Vec<&'k str>,
+  ) -> String {
+    match t {
+      "string" => {
+        used.extend(&[
+          "minLength",
+          "maxLength",
+          "pattern",
+          "format",
+          "contentEncoding",
+        ]);
+
+        let mut base = "tstr";
+        if let Some(format) = o.get("format") {
+          match format.as_str() {
+            Some("date-time") => base = "tdate",
+            Some("uri") => base = "uri",
+            Some("regex") => base = "regexp",
+            _ => self.unsupported_value(pointer, "format", format),
+          }
+        }
+        if let Some(encoding) = o.get("contentEncoding") {
+          match encoding.as_str() {
+            Some("base64url") => base = "b64url",
+            Some("base64") => base = "b64legacy",
+            _ => self.unsupported_value(pointer, "contentEncoding", encoding),
+          }
+        }
+
+        let mut controls = Vec::new();
+        match (o.get("minLength"), o.get("maxLength")) {
+          (min, Some(max)) => {
+            let min = min.cloned().unwrap_or_else(|| json!(0));
+            controls.push(format!(".size ({}..{})", min, max));
+          }
+          (Some(min), None) => self.unsupported_value(pointer, "minLength", min),
+          (None, None) => (),
+        }
+        if let Some(pattern) = o.get("pattern") {
+          controls.push(format!(".regexp {}", pattern));
+        }
+
+        with_controls(base, controls)
+      }
+      "integer" => {
+        used.extend(&["minimum", "maximum", "exclusiveMinimum", "exclusiveMaximum"]);
+
+        let lower = bound(o, "minimum", "exclusiveMinimum").map(|(n, is_exclusive)| {
+          let n = n.ceil() as i64;
+          if is_exclusive {
+            n + 1
+          } else {
+            n
+          }
+        });
+        let upper = bound(o, "maximum", "exclusiveMaximum").map(|(n, is_exclusive)| {
+          let n = n.floor() as i64;
+          if is_exclusive {
+            n - 1
+          } else {
+            n
+          }
+        });
+
+        match (lower, upper) {
+          (Some(lower), Some(upper)) => format!("{}..{}", lower, upper),
+          (Some(0), None) => "uint".to_string(),
+          (Some(lower), None) => format!("int .ge {}", lower),
+          (None, Some(-1)) => "nint".to_string(),
+          (None, Some(upper)) => format!("int .le {}", upper),
+          (None, None) => "int".to_string(),
+        }
+      }
+      "number" => {
+        used.extend(&["minimum", "maximum", "exclusiveMinimum", "exclusiveMaximum"]);
+
+        let lower = bound(o, "minimum", "exclusiveMinimum");
+        let upper = bound(o, "maximum", "exclusiveMaximum");
+
+        if let (Some((lower, false)), Some((upper, is_exclusive))) = (lower, upper) {
+          let op = if is_exclusive { "..." } else { ".." };
+          return format!("{}{}{}", float(lower), op, float(upper));
+        }
+
+        let mut controls = Vec::new();
+        if let Some((lower, is_exclusive)) = lower {
+          let ctrl = if is_exclusive { ".gt" } else { ".ge" };
+          controls.push(format!("{} {}", ctrl, float(lower)));
+        }
+        if let Some((upper, is_exclusive)) = upper {
+          let ctrl = if is_exclusive { ".lt" } else { ".le" };
+          controls.push(format!("{} {}", ctrl, float(upper)));
+        }
+
+        with_controls("number", controls)
+      }
+      "boolean" => "bool".to_string(),
+      "null" => "null".to_string(),
+      "array" => {
+        used.extend(&[
+          "items",
+          "prefixItems",
+          "additionalItems",
+          "minItems",
+          "maxItems",
+        ]);
+        self.array(o, pointer)
+      }
+      "object" => {
+        used.extend(&[
+          "properties",
+          "required",
+          "patternProperties",
+          "additionalProperties",
+        ]);
+        self.map(o, pointer)
+      }
+      "any" => "any".to_string(),
+      _ => {
+        self.unsupported_value(pointer, "type", &json!(t));
+        "any".to_string()
+      }
+    }
+  }
+
+  fn array(&mut self, o: &Map<String, Value>, pointer: &str) -> String {
+    // Prior to draft 2020-12, tuples were defined by an array of "items" and
+    // the remaining items by "additionalItems"
+    let (prefix, rest, rest_keyword) = match (o.get("prefixItems"), o.get("items")) {
+      (Some(Value::Array(prefix)), rest) => (prefix.as_slice(), rest, "items"),
+      (_, Some(Value::Array(prefix))) => (
+        prefix.as_slice(),
+        o.get("additionalItems"),
+        "additionalItems",
+      ),
+      (_, rest) => (&[][..], rest, "items"),
+    };
+
+    let min = o.get("minItems").and_then(Value::as_u64).unwrap_or(0) as usize;
+    let max = o
+      .get("maxItems")
+      .and_then(Value::as_u64)
+      .map(|max| max as usize);
+
+    let mut entries = Vec::new();
+    for (idx, schema) in prefix.iter().enumerate() {
+      let keyword = if rest_keyword == "items" {
+        "prefixItems"
+      } else {
+        "items"
+      };
+      let entry = self.schema(schema, &format!("{}/{}/{}", pointer, keyword, idx));
+
+      if idx < min {
+        entries.push(entry);
+      } else {
+        entries.push(format!("? {}", entry));
+      }
+    }
+
+    if rest != Some(&Value::Bool(false)) {
+      let entry = match rest {
+        Some(schema) => self.schema(schema, &format!("{}/{}", pointer, rest_keyword)),
+        None => "any".to_string(),
+      };
+
+      let min = min.saturating_sub(prefix.len());
+      let max = max.map(|max| max.saturating_sub(prefix.len()));
+
+      entries.push(format!("{}{}", occurrence(min, max), entry));
+    }
+
+    format!("[{}]", entries.join(", "))
+  }
+
+  fn map(&mut self, o: &Map<String, Value>, pointer: &str) -> String {
+    let required = o
+      .get("required")
+      .and_then(Value::as_array)
+      .map(|r| r.iter().filter_map(Value::as_str).collect::<Vec<_>>())
+      .unwrap_or_default();
+
+    let mut entries = Vec::new();
+
+    if let Some(Value::Object(properties)) = o.get("properties") {
+      for (key, schema) in properties.iter() {
+        let pointer = format!("{}/properties/{}", pointer, escape_pointer(key));
+        let value = self.schema(schema, &pointer);
+        let occur = if required.contains(&key.as_str()) {
+          ""
+        } else {
+          "? "
+        };
+
+        entries.push(format!("{}{}: {}", occur, member_key(key), value));
+      }
+    }
+
+    for key in required.iter() {
+      let is_defined = o.get("properties").and_then(|p| p.get(key)).is_some();
+      if !is_defined {
+        entries.push(format!("{}: any", member_key(key)));
+      }
+    }
+
+    if let Some(Value::Object(patterns)) = o.get("patternProperties") {
+      for (pattern, schema) in patterns.iter() {
+        let pointer = format!("{}/patternProperties/{}", pointer, escape_pointer(pattern));
+        let value = self.schema(schema, &pointer);
+        entries.push(format!("* tstr .regexp {} => {}", json!(pattern), value));
+      }
+    }
+
+    match o.get("additionalProperties") {
+      Some(Value::Bool(false)) => (),
+      Some(schema) => {
+        let value = self.schema(schema, &format!("{}/additionalProperties", pointer));
+        entries.push(format!("* tstr => {}", value));
+      }
+      None => entries.push("* tstr => any".to_string()),
+    }
+
+    if entries.is_empty() {
+      return "{}".to_string();
+    }
+
+    // Entries are written on separate lines so that the formatter lays the map
+    // out one entry per line
+    format!("{{\n{}\n}}", entries.join(",\n"))
+  }
+
+  fn literal(&mut self, value: &Value, pointer: &str, keyword: &str) -> String {
+    match value {
+      Value::Null => "null".to_string(),
+      Value::Bool(b) => b.to_string(),
+      Value::Number(n) if n.is_f64() => float(n.as_f64().unwrap_or_default()),
+      // JSON string escapes are also used by CDDL text strings
+      Value::Number(_) | Value::String(_) => value.to_string(),
+      _ => {
+        self.unsupported_value(pointer, keyword, value);
+        "any".to_string()
+      }
+    }
+  }
+}
+
+fn inferred_type(o: &Map<String, Value>) -> &'static str {
+  let has = |keywords: &[&str]| keywords.iter().any(|k| o.contains_key(*k));
+
+  if has(&[
+    "properties",
+    "required",
+    "patternProperties",
+    "additionalProperties",
+  ]) {
+    "object"
+  } else if has(&["items", "prefixItems", "minItems", "maxItems"]) {
+    "array"
+  } else if has(&["minLength", "maxLength", "pattern", "format"]) {
+    "string"
+  } else if has(&["minimum", "maximum", "exclusiveMinimum", "exclusiveMaximum"]) {
+    "number"
+  } else {
+    "any"
+  }
+}
+
+// Returns a numeric bound and whether or not it's exclusive. Prior to draft 6,
+// exclusiveMinimum and exclusiveMaximum were booleans modifying minimum and
+// maximum.
+fn bound(o: &Map<String, Value>, inclusive: &str, exclusive: &str) -> Option<(f64, bool)> {
+  match (o.get(inclusive).and_then(Value::as_f64), o.get(exclusive)) {
+    (_, Some(Value::Number(n))) => n.as_f64().map(|n| (n, true)),
+    (Some(n), Some(Value::Bool(is_exclusive))) => Some((n, *is_exclusive)),
+    (Some(n), _) => Some((n, false)),
+    (None, _) => None,
+  }
+}
+
+fn with_controls(base: &str, controls: Vec<String>) -> String {
+  match controls.as_slice() {
+    [] => base.to_string(),
+    [control] => format!("{} {}", base, control),
+    _ => controls
+      .iter()
+      .map(|c| format!("({} {})", base, c))
+      .collect::<Vec<_>>()
+      .join(" .and "),
+  }
+}
+
+fn occurrence(min: usize, max: Option<usize>) -> String {
+  match (min, max) {
+    (0, Some(1)) => "? ".to_string(),
+    (0, None) => "* ".to_string(),
+    (1, None) => "+ ".to_string(),
+    (1, Some(1)) => String::new(),
+    (0, Some(max)) => format!("*{} ", max),
+    (min, None) => format!("{}* ", min),
+    (min, Some(max)) => format!("{}*{} ", min, max),
+  }
+}
+
+// Floating-point values must have a fractional part to be lexed as floats
+fn float(n: f64) -> String {
+  let float = n.to_string();
+  if float.contains('.') {
+    float
+  } else {
+    format!("{}.0", float)
+  }
+}
+
+fn member_key(key: &str) -> String {
+  if is_identifier(key) {
+    return key.to_string();
+  }
+
+  json!(key).to_string()
+}
+
+// id = EALPHA *(*("-" / ".") (EALPHA / DIGIT))
+fn is_identifier(s: &str) -> bool {
+  let is_ealpha = |c: char| c.is_ascii_alphabetic() || c == '@' || c == '_' || c == '$';
+
+  match s.chars().next() {
+    Some(c) if is_ealpha(c) => (),
+    _ => return false,
+  }
+
+  if s.ends_with('-') || s.ends_with('.') {
+    return false;
+  }
+
+  s.chars()
+    .all(|c| is_ealpha(c) || c.is_ascii_digit() || c == '-' || c == '.')
+}
+
+// Returns a valid rule name for the given name that doesn't shadow a prelude
+// type or an existing rule
+fn unique_identifier(name: &str, names: &mut BTreeSet<String>) -> String {
+  let mut identifier = name
+    .chars()
+    .map(|c| {
+      if c.is_ascii_alphanumeric() || "@_$-.".contains(c) {
+        c
+      } else {
+        '-'
+      }
+    })
+    .collect::<String>()
+    .trim_end_matches(&['-', '.'][..])
+    .to_string();
+
+  if !is_identifier(&identifier) {
+    identifier.insert(0, '_');
+  }
+
+  if let Token::IDENT(_) = token::lookup_ident(&identifier) {
+  } else {
+    identifier.push_str("-type");
+  }
+
+  let mut unique = identifier.clone();
+  let mut suffix = 2;
+  while names.contains(&unique) {
+    unique = format!("{}{}", identifier, suffix);
+    suffix += 1;
+  }
+
+  names.insert(unique.clone());
+  unique
+}
+
+fn escape_pointer(key: &str) -> String {
+  key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{lexer::Lexer, parser::cddl_from_str};
+  use indoc::indoc;
+  use pretty_assertions::assert_eq;
+
+  #[test]
+  fn verify_from_cddl() {
+    let input = indoc!(
+      r#"
+        reputation-object = {
+          application: text .size (1..64),
+          reputons: [* reputon],
+        }
+        reputon = {
+          rater: text .regexp "[a-z]+",
+          assertion: "spam" / "ham",
+          ? rating: 0.0..1.0,
+          pair<uint>,
+          * tstr => any,
+        }
+        pair<t> = ( key: tstr, value: t )
+        point = [x: int, y: int, ? label: tstr]
+        message<t> = { payload: t }
+        envelope = message<bstr> / null
+      "#
+    );
+
+    let mut lexer = Lexer::new(input);
+    let cddl = cddl_from_str(&mut lexer, input, false).unwrap();
+
+    assert_eq!(
+      from_cddl(&cddl),
+      json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$ref": "#/$defs/reputation-object",
+        "$defs": {
+          "reputation-object": {
+            "type": "object",
+            "properties": {
+              "application": { "type": "string", "minLength": 1, "maxLength": 64 },
+              "reputons": { "type": "array", "items": { "$ref": "#/$defs/reputon" } },
+            },
+            "required": ["application", "reputons"],
+            "additionalProperties": false,
+          },
+          "reputon": {
+            "type": "object",
+            "properties": {
+              "rater": { "type": "string", "pattern": "[a-z]+" },
+              "assertion": { "enum": ["spam", "ham"] },
+              "rating": { "type": "number", "minimum": 0.0, "maximum": 1.0 },
+              "key": { "type": "string" },
+              "value": { "type": "integer", "minimum": 0 },
+            },
+            "required": ["rater", "assertion", "key", "value"],
+            "additionalProperties": {},
+          },
+          "point": {
+            "type": "array",
+            "prefixItems": [
+              { "type": "integer" },
+              { "type": "integer" },
+              { "type": "string" },
+            ],
+            "items": false,
+            "minItems": 2,
+          },
+          "envelope": {
+            "anyOf": [
+              {
+                "type": "object",
+                "properties": {
+                  "payload": { "type": "string", "contentEncoding": "base64url" },
+                },
+                "required": ["payload"],
+                "additionalProperties": false,
+              },
+              { "type": "null" },
+            ],
+          },
+        },
+      })
+    );
+  }
+
+  #[test]
+  fn verify_to_cddl() {
+    let schema = json!({
+      "$schema": "https://json-schema.org/draft/2020-12/schema",
+      "$ref": "#/$defs/reputation-object",
+      // Definitions and properties in sorted order, so that the output doesn't
+      // depend on whether serde_json preserves their order
+      "$defs": {
+        "point": {
+          "type": "array",
+          "prefixItems": [{ "type": "integer" }, { "type": "integer" }, { "type": "string" }],
+          "items": false,
+          "minItems": 2,
+        },
+        "reputation-object": {
+          "type": "object",
+          "properties": {
+            "application": { "type": "string", "minLength": 1, "maxLength": 64 },
+            "reputons": { "type": "array", "items": { "$ref": "#/$defs/reputon" } },
+          },
+          "required": ["application", "reputons"],
+          "additionalProperties": false,
+        },
+        "reputon": {
+          "type": "object",
+          "properties": {
+            "assertion": { "enum": ["spam", "ham"] },
+            "owner": { "$ref": "https://example.com/owner.json" },
+            "rater": { "type": "string", "pattern": "^\\w+$" },
+            "rating": { "type": "number", "minimum": 0, "exclusiveMaximum": 1 },
+            "sample-size": { "type": "integer", "minimum": 0 },
+            "tags": { "type": ["string", "null"], "format": "hostname" },
+          },
+          "required": ["rater", "assertion"],
+          "not": { "required": ["owner"] },
+        },
+      },
+    });
+
+    let (cddl, warnings) = to_cddl(&schema);
+
+    assert_eq!(
+      cddl,
+      indoc!(
+        r#"
+          root = reputation-object
+          point = [int, int, ? tstr]
+          reputation-object = {
+            application: tstr .size (1..64),
+            reputons:    [* reputon],
+          }
+          reputon = {
+            assertion:     "spam" / "ham",
+            ? owner:       any,
+            rater:         tstr .regexp "^\\w+$",
+            ? rating:      0.0...1.0,
+            ? sample-size: uint,
+            ? tags:        tstr / null,
+            * tstr =>      any,
+          }
+        "#
+      )
+    );
+
+    assert_eq!(
+      warnings.iter().map(|w| w.to_string()).collect::<Vec<_>>(),
+      vec![
+        "#/$defs/reputon/properties/owner: unresolved reference \"https://example.com/owner.json\"",
+        "#/$defs/reputon/properties/tags: unsupported value \"hostname\" for keyword \"format\"",
+        "#/$defs/reputon: unsupported keyword \"not\"",
+      ]
+    );
+
+    let mut lexer = Lexer::new(&cddl);
+    assert!(cddl_from_str(&mut lexer, &cddl, false).is_ok());
+  }
+}
@@ -1,10 +1,15 @@
-use super::token::{self, ByteValue, Token, Value};
+use super::{
+  ast::Span,
+  error::Error,
+  token::{self, ByteValue, Token, Value},
+};
 use codespan_reporting::{
   diagnostic::{Diagnostic, Label},
   files::SimpleFiles,
   term,
 };
 use std::{
+  convert::TryFrom,
   fmt,
   iter::Peekable,
   num, result,
@@ -12,12 +17,13 @@ use std::{
 };
 
 #[cfg(feature = "std")]
-use std::{borrow::Cow, error::Error, string};
+use std::{borrow::Cow, string};
 
 #[cfg(not(feature = "std"))]
 use alloc::{
   borrow::Cow,
   string::{self, String, ToString},
+  vec,
   vec::Vec,
 };
 use lexical_core as lexical;
@@ -74,7 +80,6 @@ pub enum LexerErrorType {
   PARSEFLOAT(lexical::Error),
 }
 
-#[cfg(feature = "std")]
 impl Error for LexerError {
   fn source(&self) -> Option<&(dyn Error + 'static)> {
     match &self.error_type {
@@ -85,6 +90,25 @@ impl Error for LexerError {
   }
 }
 
+impl LexerError {
+  /// Returns the position of the input the error applies to
+  pub fn position(&self) -> Position {
+    self.position
+  }
+
+  /// Returns a description of the error without any source context
+  pub fn message(&self) -> String {
+    match &self.error_type {
+      LexerErrorType::LEXER(le) => le.to_string(),
+      LexerErrorType::UTF8(utf8e) => utf8e.to_string(),
+      LexerErrorType::BASE16(b16e) => b16e.to_string(),
+      LexerErrorType::BASE64(b64e) => b64e.to_string(),
+      LexerErrorType::PARSEINT(pie) => pie.to_string(),
+      LexerErrorType::PARSEFLOAT(pfe) => format!("{:#?}", pfe),
+    }
+  }
+}
+
 impl fmt::Display for LexerError {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     let mut files = SimpleFiles::new();
@@ -249,14 +273,14 @@ pub struct Lexer<'a> {
 }
 
 /// Iterator over a lexer
-pub struct IterLexer<'a> {
-  l: &'a mut Lexer<'a>,
+pub struct IterLexer<'a, 'b> {
+  l: &'b mut Lexer<'a>,
 }
 
 /// Iterated lexer token item
 pub type Item<'a> = std::result::Result<(Position, Token<'a>), LexerError>;
 
-impl<'a> Iterator for IterLexer<'a> {
+impl<'a, 'b> Iterator for IterLexer<'a, 'b> {
   type Item = Item<'a>;
 
   fn next(&mut self) -> Option<Self::Item> {
@@ -271,10 +295,107 @@ impl<'a> Iterator for IterLexer<'a> {
 /// # Arguments
 ///
 /// `str_input` - String slice with input
-pub fn lexer_from_str(str_input: &str) -> Lexer {
+pub fn lexer_from_str(str_input: &str) -> Lexer<'_> {
   Lexer::new(str_input)
 }
 
+/// Returns an iterator over the tokens of CDDL input along with their spans,
+/// including the comments and whitespace the parser discards, e.g. for syntax
+/// highlighting. Concatenating the source of each span reproduces the input.
+/// Iteration ends at the end of input or after the first lexer error.
+///
+/// # Example
+///
+/// ```
+/// use cddl::{lexer::tokens_with_trivia, token::Token};
+///
+/// let tokens = tokens_with_trivia("a = tstr ; text")
+///   .collect::<Result<Vec<_>, _>>()
+///   .unwrap();
+///
+/// assert_eq!(tokens[1], (Token::WHITESPACE(" "), (1, 2, 1)));
+/// assert_eq!(tokens[6], (Token::COMMENT(" text"), (9, 15, 1)));
+/// ```
+pub fn tokens_with_trivia(str_input: &str) -> TokensWithTrivia<'_> {
+  TokensWithTrivia {
+    lexer: Lexer::new(str_input),
+    offset: 0,
+    line: 1,
+    pending: None,
+    done: false,
+  }
+}
+
+/// Iterator over tokens and trivia returned by `tokens_with_trivia`
+#[derive(Debug)]
+pub struct TokensWithTrivia<'a> {
+  lexer: Lexer<'a>,
+  // Offset of the end of the last token or trivia returned
+  offset: usize,
+  // Line of the offset
+  line: usize,
+  // Token following whitespace that's been returned first
+  pending: Option<(Token<'a>, usize, usize)>,
+  done: bool,
+}
+
+impl<'a> TokensWithTrivia<'a> {
+  fn spanned(&mut self, token: Token<'a>, start: usize, end: usize) -> (Token<'a>, Span) {
+    let span = (start, end, self.line);
+
+    self.line += self.lexer.str_input[start..end].matches('\n').count();
+    self.offset = end;
+
+    (token, span)
+  }
+}
+
+impl<'a> Iterator for TokensWithTrivia<'a> {
+  type Item = Result<(Token<'a>, Span)>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if let Some((token, start, end)) = self.pending.take() {
+      return Some(Ok(self.spanned(token, start, end)));
+    }
+
+    if self.done {
+      return None;
+    }
+
+    let input = self.lexer.str_input;
+
+    let (token, start, end) = match self.lexer.next_token() {
+      // The range of the end of input points at the last token instead
+      Ok((_, Token::EOF)) => {
+        self.done = true;
+        (Token::EOF, input.len(), input.len())
+      }
+      Ok((position, token)) => (token, position.range.0, position.range.1),
+      Err(e) => {
+        self.done = true;
+        return Some(Err(e));
+      }
+    };
+
+    if start > self.offset {
+      let whitespace = Token::WHITESPACE(&input[self.offset..start]);
+      let offset = self.offset;
+
+      if token != Token::EOF {
+        self.pending = Some((token, start, end));
+      }
+
+      return Some(Ok(self.spanned(whitespace, offset, start)));
+    }
+
+    if token == Token::EOF {
+      return None;
+    }
+
+    Some(Ok(self.spanned(token, start, end)))
+  }
+}
+
 impl<'a> Lexer<'a> {
   /// Creates a new `Lexer` from a given `&str` input
   pub fn new(str_input: &'a str) -> Lexer<'a> {
@@ -291,8 +412,9 @@ impl<'a> Lexer<'a> {
     }
   }
 
-  /// Returns an iterator over a lexer
-  pub fn iter(&'a mut self) -> IterLexer<'a> {
+  /// Returns an iterator over a lexer. Tokens borrow from the lexer's input
+  /// rather than from the lexer itself, so they can outlive the iterator.
+  pub fn iter(&mut self) -> IterLexer<'a, '_> {
     IterLexer { l: self }
   }
 
@@ -302,7 +424,7 @@ impl<'a> Lexer<'a> {
     self
       .input
       .next()
-      .and_then(|c| {
+      .map(|c| {
         if c.1 == '\n' {
           self.position.line += 1;
           self.position.column = 1;
@@ -314,7 +436,7 @@ impl<'a> Lexer<'a> {
           self.position.index = c.0;
         }
 
-        Some(c)
+        c
       })
       .ok_or_else(|| {
         (
@@ -450,15 +572,29 @@ impl<'a> Lexer<'a> {
           Some(&c) if is_digit(c.1) => {
             let (idx, _) = self.read_char()?;
             let t = self.read_number(idx)?.1;
-            let (_, c) = self.read_char()?;
-            if c == '.' {
+
+            if let Some(&(_, '.')) = self.peek_char() {
+              let _ = self.read_char()?;
+
+              if !self.peek_nth(0).map(is_digit).unwrap_or(false) {
+                return Err(
+                  (
+                    self.str_input,
+                    self.position,
+                    "Expected a tag number after '.'",
+                  )
+                    .into(),
+                );
+              }
+
               let (idx, _) = self.read_char()?;
+              let constraint = self.read_number(idx)?.1;
 
               self.position.range = (token_offset, self.position.index + 1);
 
               return Ok((
                 self.position,
-                Token::TAG((Some(t as u8), Some(self.read_number(idx)?.1))),
+                Token::TAG((Some(t as u8), Some(constraint as usize))),
               ));
             }
 
@@ -471,15 +607,16 @@ impl<'a> Lexer<'a> {
             Ok((self.position, Token::TAG((None, None))))
           }
         },
-        (_, '\'') => {
-          let (idx, _) = self.read_char()?;
-
-          let bsv = self.read_byte_string(idx)?;
+        (idx, '\'') => {
+          let bsv = match self.read_byte_string(idx)? {
+            Cow::Borrowed(b) => Cow::Borrowed(b.as_bytes()),
+            Cow::Owned(b) => Cow::Owned(b.into_bytes()),
+          };
           self.position.range = (token_offset, self.position.index + 1);
 
           Ok((
             self.position,
-            Token::VALUE(Value::BYTE(ByteValue::UTF8(bsv.as_bytes().into()))),
+            Token::VALUE(Value::BYTE(ByteValue::UTF8(bsv))),
           ))
         }
         (idx, '.') => {
@@ -504,7 +641,7 @@ impl<'a> Lexer<'a> {
             } else if is_ealpha(c.1) {
               // Controlop
               let ctrlop =
-                token::lookup_control_from_str(&self.read_identifier(idx)?).ok_or_else(|| {
+                token::lookup_control_from_str(self.read_identifier(idx)?).ok_or_else(|| {
                   self.position.range = (token_offset, self.position.index + 1);
 
                   LexerError::from((self.str_input, self.position, "Invalid control operator"))
@@ -529,13 +666,13 @@ impl<'a> Lexer<'a> {
 
                   // Ensure that the byte string has been properly encoded.
                   let b = self.read_prefixed_byte_string(idx)?;
-                  let mut buf = [0u8; 1024];
+                  let mut buf = vec![0; b.len() / 2 + 1];
                   return base16::decode_slice(&b[..], &mut buf)
                     .map_err(|e| (self.str_input, self.position, e).into())
-                    .and_then(|_| {
+                    .map(|_| {
                       self.position.range = (token_offset, self.position.index + 1);
 
-                      Ok((self.position, Token::VALUE(Value::BYTE(ByteValue::B16(b)))))
+                      (self.position, Token::VALUE(Value::BYTE(ByteValue::B16(b))))
                     });
                 }
               }
@@ -557,13 +694,13 @@ impl<'a> Lexer<'a> {
                           // Ensure that the byte string has been properly
                           // encoded
                           let bs = self.read_prefixed_byte_string(idx)?;
-                          let mut buf = [0u8; 1024];
+                          let mut buf = vec![0; bs.len() * 3 / 4 + 3];
                           return base64::decode_config_slice(&bs, base64::URL_SAFE, &mut buf)
                             .map_err(|e| (self.str_input, self.position, e).into())
-                            .and_then(|_| {
+                            .map(|_| {
                               self.position.range = (token_offset, self.position.index + 1);
 
-                              Ok((self.position, Token::VALUE(Value::BYTE(ByteValue::B64(bs)))))
+                              (self.position, Token::VALUE(Value::BYTE(ByteValue::B64(bs))))
                             });
                         }
                       }
@@ -573,7 +710,7 @@ impl<'a> Lexer<'a> {
               }
             }
 
-            let ident = token::lookup_ident(&self.read_identifier(idx)?);
+            let ident = token::lookup_ident(self.read_identifier(idx)?);
 
             self.position.range = (token_offset, self.position.index + 1);
 
@@ -588,7 +725,10 @@ impl<'a> Lexer<'a> {
 
           self.position.range = (token_offset, self.position.index + 1);
 
-          Ok((self.position, Token::ILLEGAL(&self.str_input[idx..=idx])))
+          Ok((
+            self.position,
+            Token::ILLEGAL(&self.str_input[idx..idx + ch.len_utf8()]),
+          ))
         }
       }
     } else {
@@ -597,62 +737,75 @@ impl<'a> Lexer<'a> {
     }
   }
 
+  // Reads a name per the id rule of RFC 8610, i.e. EALPHA *(*("-" / ".")
+  // (EALPHA / DIGIT)), extended to Unicode letters and digits. A run of "-"
+  // and "." is only part of the name if a letter or digit follows it, so
+  // "min.. max" is the name "min" followed by a range operator.
   fn read_identifier(&mut self, idx: usize) -> Result<&'a str> {
-    let mut end_idx = idx;
+    // Offset just past the last character read, which may span several bytes
+    let mut end_idx = idx
+      + self.str_input[idx..]
+        .chars()
+        .next()
+        .map_or(1, char::len_utf8);
+
+    loop {
+      let mut separators = 0;
+      while let Some('-') | Some('.') = self.peek_nth(separators) {
+        separators += 1;
+      }
 
-    while let Some(&c) = self.peek_char() {
-      if is_ealpha(c.1) || is_digit(c.1) || c.1 == '.' || c.1 == '-' {
-        match c.1 {
-          // Check for range
-          '.' => {
-            end_idx = self.read_char()?.0;
-
-            if let Some(&c) = self.peek_char() {
-              if c.1 == '\u{0020}' {
-                return Ok(&self.str_input[idx..end_idx]);
-              }
-            }
+      match self.peek_nth(separators) {
+        Some(ch) if is_ealpha(ch) || ch.is_alphanumeric() => {
+          for _ in 0..=separators {
+            let (char_idx, ch) = self.read_char()?;
+            end_idx = char_idx + ch.len_utf8();
           }
-          _ => end_idx = self.read_char()?.0,
         }
-      } else {
-        break;
+        _ => return Ok(&self.str_input[idx..end_idx]),
       }
     }
-    Ok(&self.str_input[idx..=end_idx])
   }
 
-  fn read_text_value(&mut self, idx: usize) -> Result<&'a str> {
-    while let Some(&(_, ch)) = self.peek_char() {
+  fn read_text_value(&mut self, idx: usize) -> Result<Cow<'a, str>> {
+    // Escape sequences are decoded into an owned string, so text without any
+    // is borrowed from the input as is
+    let mut decoded: Option<String> = None;
+    let mut run_start = idx + 1;
+
+    while let Some(&(pos, ch)) = self.peek_char() {
       match ch {
-        // SCHAR
-        '\x20'..='\x21' | '\x23'..='\x5b' | '\x5d'..='\x7e' | '\u{0128}'..='\u{10FFFD}' => {
+        // SCHAR, including line breaks of multi-line text
+        '\x20'..='\x21'
+        | '\x23'..='\x5b'
+        | '\x5d'..='\x7e'
+        | '\u{00A0}'..='\u{10FFFD}'
+        | '\n'
+        | '\r' => {
           let _ = self.read_char()?;
         }
         // SESC
         '\\' => {
-          let _ = self.read_char();
-          if let Some(&(_, ch)) = self.peek_char() {
-            match ch {
-              '\x20'..='\x7e' | '\u{0128}'..='\u{10FFFD}' => {
-                let _ = self.read_char()?;
-              }
-              _ => {
-                return Err(
-                  (
-                    self.str_input,
-                    self.position,
-                    "Unexpected escape character in text string",
-                  )
-                    .into(),
-                )
-              }
-            }
-          }
+          let text = decoded.get_or_insert_with(String::new);
+          text.push_str(&self.str_input[run_start..pos]);
+          let _ = self.read_char()?;
+          self.read_text_escape(text)?;
+          run_start = self
+            .peek_char()
+            .map(|&(pos, _)| pos)
+            .unwrap_or_else(|| self.str_input.len());
         }
         // Closing "
         '\x22' => {
-          return Ok(&self.str_input[idx + 1..self.read_char()?.0]);
+          let end = self.read_char()?.0;
+
+          return Ok(match decoded {
+            Some(mut text) => {
+              text.push_str(&self.str_input[run_start..end]);
+              Cow::Owned(text)
+            }
+            None => Cow::Borrowed(&self.str_input[idx + 1..end]),
+          });
         }
         _ => {
           return Err(
@@ -670,15 +823,155 @@ impl<'a> Lexer<'a> {
     Err((self.str_input, self.position, "Empty text value").into())
   }
 
-  fn read_byte_string(&mut self, idx: usize) -> Result<&'a str> {
-    while let Some(&(_, ch)) = self.peek_char() {
+  // Decodes the escape sequence following a backslash. Unrecognized escapes,
+  // such as those of regular expressions like "\d", are kept as written.
+  fn read_text_escape(&mut self, text: &mut String) -> Result<()> {
+    let ch = match self.peek_char() {
+      Some(&(_, ch)) => ch,
+      None => return Err((self.str_input, self.position, "Empty text value").into()),
+    };
+
+    match ch {
+      '"' | '\'' | '\\' | '/' => text.push(ch),
+      'b' => text.push('\u{8}'),
+      'f' => text.push('\u{c}'),
+      'n' => text.push('\n'),
+      'r' => text.push('\r'),
+      't' => text.push('\t'),
+      'u' => {
+        let _ = self.read_char()?;
+        let c = self.read_unicode_escape()?;
+        text.push(c);
+        return Ok(());
+      }
+      '\x20'..='\x7e' | '\u{00A0}'..='\u{10FFFD}' => {
+        text.push('\\');
+        text.push(ch);
+      }
+      _ => {
+        return Err(
+          (
+            self.str_input,
+            self.position,
+            "Unexpected escape character in text string",
+          )
+            .into(),
+        )
+      }
+    }
+
+    let _ = self.read_char()?;
+
+    Ok(())
+  }
+
+  // Reads the code point of a "\u{1F600}" or "\uD83D\uDE00" style escape,
+  // combining UTF-16 surrogate pairs
+  fn read_unicode_escape(&mut self) -> Result<char> {
+    let code = if let Some(&(_, '{')) = self.peek_char() {
+      let _ = self.read_char()?;
+      let code = self.read_hex_digits(1, 6)?;
+
+      match self.peek_char() {
+        Some(&(_, '}')) => {
+          let _ = self.read_char()?;
+        }
+        _ => return Err(self.invalid_unicode_escape()),
+      }
+
+      code
+    } else {
+      let code = self.read_hex_digits(4, 4)?;
+
+      match code {
+        0xD800..=0xDBFF => {
+          for expected in ['\\', 'u'].iter() {
+            match self.peek_char() {
+              Some(&(_, ch)) if ch == *expected => {
+                let _ = self.read_char()?;
+              }
+              _ => return Err(self.invalid_unicode_escape()),
+            }
+          }
+
+          match self.read_hex_digits(4, 4)? {
+            low @ 0xDC00..=0xDFFF => 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00),
+            _ => return Err(self.invalid_unicode_escape()),
+          }
+        }
+        _ => code,
+      }
+    };
+
+    std::char::from_u32(code).ok_or_else(|| self.invalid_unicode_escape())
+  }
+
+  // Reads between `min` and `max` hex digits as a number
+  fn read_hex_digits(&mut self, min: usize, max: usize) -> Result<u32> {
+    let mut code = 0;
+    let mut digits = 0;
+
+    while digits < max {
+      match self.peek_char() {
+        Some(&(_, ch)) if is_hexdig(ch) => {
+          let _ = self.read_char()?;
+          code = code * 16 + ch.to_digit(16).unwrap_or_default();
+          digits += 1;
+        }
+        _ => break,
+      }
+    }
+
+    if digits < min {
+      return Err(self.invalid_unicode_escape());
+    }
+
+    Ok(code)
+  }
+
+  fn invalid_unicode_escape(&self) -> LexerError {
+    (
+      self.str_input,
+      self.position,
+      "Invalid Unicode escape in text string",
+    )
+      .into()
+  }
+
+  fn read_byte_string(&mut self, idx: usize) -> Result<Cow<'a, str>> {
+    // As with text, escape sequences are decoded into an owned string
+    let mut decoded: Option<String> = None;
+    let mut run_start = idx + 1;
+
+    while let Some(&(pos, ch)) = self.peek_char() {
       match ch {
         // BCHAR
         '\x20'..='\x26' | '\x28'..='\x5b' | '\x5d'..='\u{10FFFD}' => {
           let _ = self.read_char();
         }
+        // SESC
+        '\\' => {
+          let bytes = decoded.get_or_insert_with(String::new);
+          bytes.push_str(&self.str_input[run_start..pos]);
+          let _ = self.read_char()?;
+          self.read_text_escape(bytes)?;
+          run_start = self
+            .peek_char()
+            .map(|&(pos, _)| pos)
+            .unwrap_or_else(|| self.str_input.len());
+        }
         // Closing '
-        '\x27' => return Ok(&self.str_input[idx..self.read_char()?.0]),
+        '\x27' => {
+          let end = self.read_char()?.0;
+
+          return Ok(match decoded {
+            Some(mut bytes) => {
+              bytes.push_str(&self.str_input[run_start..end]);
+              Cow::Owned(bytes)
+            }
+            None => Cow::Borrowed(&self.str_input[idx + 1..end]),
+          });
+        }
         _ => {
           return Err(
             (
@@ -738,7 +1031,7 @@ impl<'a> Lexer<'a> {
             );
           }
 
-          return Ok(self.str_input[idx..self.read_char()?.0].as_bytes().into());
+          return Ok(self.str_input.as_bytes()[idx..self.read_char()?.0].into());
         }
         // CRLF
         _ => {
@@ -773,7 +1066,7 @@ impl<'a> Lexer<'a> {
       }
     }
 
-    Ok("")
+    Ok(&self.str_input[idx + 1..])
   }
 
   fn skip_whitespace(&mut self) -> Result<()> {
@@ -790,65 +1083,158 @@ impl<'a> Lexer<'a> {
   }
 
   fn read_int_or_float(&mut self, mut idx: usize) -> Result<Token<'a>> {
-    let mut is_signed = false;
-    let mut signed_idx = 0;
+    let start_idx = idx;
+    let is_signed = self.str_input.as_bytes()[idx] == b'-';
 
-    if self.str_input.as_bytes()[idx] == b'-' {
-      is_signed = true;
-      signed_idx = idx;
+    if is_signed {
+      if !self.peek_nth(0).map(is_digit).unwrap_or(false) {
+        return Err((self.str_input, self.position, "Expected a digit after '-'").into());
+      }
 
       idx = self.read_char()?.0;
     }
 
-    let (end_idx, i) = self.read_number(idx)?;
+    if self.str_input.as_bytes()[idx] == b'0' {
+      let radix = match self.peek_nth(0) {
+        Some('x') | Some('X') => 16,
+        Some('b') | Some('B') => 2,
+        // Octal literals are an extension to the grammar of RFC 8610
+        Some('o') | Some('O') => 8,
+        _ => 10,
+      };
+
+      if radix != 10 && self.peek_nth(1).map(|c| c.is_digit(radix)).unwrap_or(false) {
+        return self.read_prefixed_number(is_signed, radix);
+      }
+    }
 
-    if let Some(&c) = self.multipeek.peek() {
-      if c.1 == '.' {
-        if let Some(&c) = self.multipeek.peek() {
-          if is_digit(c.1) {
-            let _ = self.read_char()?;
-            let (fraction_idx, _) = self.read_number(c.0)?;
+    let mut end_idx = self.read_digits(idx, 10)?;
+    let mut is_float = false;
 
-            if is_signed {
-              return Ok(Token::VALUE(Value::FLOAT(
-                lexical::parse::<f64>(&self.str_input[signed_idx..=fraction_idx].as_bytes())
-                  .map_err(|e| LexerError::from((self.str_input, self.position, e)))?,
-              )));
-            }
+    if self.peek_nth(0) == Some('.') && self.peek_nth(1).map(is_digit).unwrap_or(false) {
+      let _ = self.read_char()?;
+      let (fraction_idx, _) = self.read_char()?;
+      end_idx = self.read_digits(fraction_idx, 10)?;
+      is_float = true;
+    }
 
-            return Ok(Token::VALUE(Value::FLOAT(
-              lexical::parse::<f64>(&self.str_input[idx..=fraction_idx].as_bytes())
-                .map_err(|e| LexerError::from((self.str_input, self.position, e)))?,
-            )));
-          }
-        }
-      }
+    if let Some((exponent_end_idx, _)) = self.read_exponent('e')? {
+      end_idx = exponent_end_idx;
+      is_float = true;
     }
 
-    if is_signed {
-      return Ok(Token::VALUE(Value::INT(
-        self.str_input[signed_idx..=end_idx]
-          .parse()
+    if is_float {
+      return Ok(Token::VALUE(Value::FLOAT(
+        lexical::parse::<f64>(&self.str_input.as_bytes()[start_idx..=end_idx])
           .map_err(|e| LexerError::from((self.str_input, self.position, e)))?,
       )));
     }
 
-    Ok(Token::VALUE(Value::UINT(i)))
+    let literal = &self.str_input[start_idx..=end_idx];
+
+    if is_signed {
+      return Ok(Token::VALUE(Value::INT(literal.parse().map_err(|e| {
+        LexerError::from((self.str_input, self.position, e))
+      })?)));
+    }
+
+    Ok(Token::VALUE(Value::UINT(literal.parse().map_err(|e| {
+      LexerError::from((self.str_input, self.position, e))
+    })?)))
   }
 
-  fn read_number(&mut self, idx: usize) -> Result<(usize, usize)> {
-    let mut end_index = idx;
+  // Reads an integer prefixed with "0x", "0b" or "0o", or a hexfloat. The next
+  // character is expected to be the letter of the prefix.
+  fn read_prefixed_number(&mut self, is_signed: bool, radix: u32) -> Result<Token<'a>> {
+    let _ = self.read_char()?;
+    let (digits_idx, _) = self.read_char()?;
+    let digits = &self.str_input[digits_idx..=self.read_digits(digits_idx, radix)?];
 
-    while let Some(&c) = self.peek_char() {
-      if is_digit(c.1) {
-        let (ei, _) = self.read_char()?;
+    // hexfloat = ["-"] "0x" 1*HEXDIG ["." 1*HEXDIG] "p" exponent
+    if radix == 16 {
+      let mut fraction = "";
 
-        end_index = ei;
-      } else {
-        break;
+      if self.peek_nth(0) == Some('.') && self.peek_nth(1).map(is_hexdig).unwrap_or(false) {
+        let _ = self.read_char()?;
+        let (fraction_idx, _) = self.read_char()?;
+        fraction = &self.str_input[fraction_idx..=self.read_digits(fraction_idx, 16)?];
+      }
+
+      match self.read_exponent('p')? {
+        Some((_, exponent)) => {
+          let exponent = exponent
+            .parse::<i32>()
+            .map_err(|e| LexerError::from((self.str_input, self.position, e)))?;
+          let value = hexfloat(digits, fraction, exponent);
+
+          return Ok(Token::VALUE(Value::FLOAT(if is_signed {
+            -value
+          } else {
+            value
+          })));
+        }
+        None if !fraction.is_empty() => {
+          return Err(
+            (
+              self.str_input,
+              self.position,
+              "Hexfloat is missing its exponent",
+            )
+              .into(),
+          )
+        }
+        None => (),
       }
     }
 
+    let value = u128::from_str_radix(digits, radix)
+      .map_err(|e| LexerError::from((self.str_input, self.position, e)))?;
+
+    if !is_signed {
+      return Ok(Token::VALUE(Value::UINT(value)));
+    }
+
+    match i128::try_from(value).ok().and_then(i128::checked_neg) {
+      Some(value) => Ok(Token::VALUE(Value::INT(value))),
+      None => Err(
+        (
+          self.str_input,
+          self.position,
+          "Integer literal out of range",
+        )
+          .into(),
+      ),
+    }
+  }
+
+  // Reads the exponent of a float if the next character is the given marker,
+  // in either case, followed by an optionally signed decimal number. Returns
+  // the index of the last character of the exponent and the exponent itself.
+  fn read_exponent(&mut self, marker: char) -> Result<Option<(usize, &'a str)>> {
+    match self.peek_nth(0) {
+      Some(c) if c.to_ascii_lowercase() == marker => (),
+      _ => return Ok(None),
+    }
+
+    let digit_offset = match self.peek_nth(1) {
+      Some('+') | Some('-') => 2,
+      _ => 1,
+    };
+
+    if !self.peek_nth(digit_offset).map(is_digit).unwrap_or(false) {
+      return Ok(None);
+    }
+
+    let _ = self.read_char()?;
+    let (exponent_idx, _) = self.read_char()?;
+    let end_idx = self.read_digits(exponent_idx, 10)?;
+
+    Ok(Some((end_idx, &self.str_input[exponent_idx..=end_idx])))
+  }
+
+  fn read_number(&mut self, idx: usize) -> Result<(usize, u128)> {
+    let end_index = self.read_digits(idx, 10)?;
+
     Ok((
       end_index,
       self.str_input[idx..=end_index]
@@ -857,6 +1243,35 @@ impl<'a> Lexer<'a> {
     ))
   }
 
+  // Reads the digits of the given radix following the one at the given index
+  // and returns the index of the last one
+  fn read_digits(&mut self, idx: usize, radix: u32) -> Result<usize> {
+    let mut end_index = idx;
+
+    while let Some(&c) = self.peek_char() {
+      if c.1.is_digit(radix) {
+        let (ei, _) = self.read_char()?;
+
+        end_index = ei;
+      } else {
+        break;
+      }
+    }
+
+    Ok(end_index)
+  }
+
+  // Peeks at the character the given number of characters past the next one
+  fn peek_nth(&mut self, n: usize) -> Option<char> {
+    self.multipeek.reset_peek();
+
+    for _ in 0..n {
+      self.multipeek.peek();
+    }
+
+    self.multipeek.peek().map(|c| c.1)
+  }
+
   fn peek_char(&mut self) -> Option<&(usize, char)> {
     self.input.peek()
   }
@@ -867,7 +1282,35 @@ fn is_ealpha(ch: char) -> bool {
 }
 
 fn is_digit(ch: char) -> bool {
-  ch.is_digit(10)
+  ch.is_ascii_digit()
+}
+
+fn is_hexdig(ch: char) -> bool {
+  ch.is_ascii_hexdigit()
+}
+
+// Computes the value of a hexfloat from the hexadecimal digits of its integer
+// and fractional parts and its binary exponent
+fn hexfloat(integer: &str, fraction: &str, exponent: i32) -> f64 {
+  let mut value = integer
+    .chars()
+    .chain(fraction.chars())
+    .filter_map(|c| c.to_digit(16))
+    .fold(0.0, |value, digit| value * 16.0 + f64::from(digit));
+  let mut exponent = i64::from(exponent) - 4 * fraction.len() as i64;
+
+  // Scaling by powers of two is exact until the value overflows or underflows
+  while exponent > 0 && value.is_finite() {
+    value *= 2.0;
+    exponent -= 1;
+  }
+
+  while exponent < 0 && value != 0.0 {
+    value /= 2.0;
+    exponent += 1;
+  }
+
+  value
 }
 
 #[cfg(test)]
@@ -931,30 +1374,30 @@ mod tests {
     );
 
     let expected_tok = [
-      (COMMENT(" this is a comment".into()), "; this is a comment"),
+      (COMMENT(" this is a comment"), "; this is a comment"),
       (
-        COMMENT(" this is another comment".into()),
+        COMMENT(" this is another comment"),
         "; this is another comment",
       ),
-      (IDENT(("mynumber".into(), None)), "mynumber"),
+      (IDENT(("mynumber", None)), "mynumber"),
       (ASSIGN, "="),
       (VALUE(Value::FLOAT(10.5)), "10.5"),
-      (IDENT(("mytag".into(), None)), "mytag"),
+      (IDENT(("mytag", None)), "mytag"),
       (ASSIGN, "="),
       (TAG((Some(6), Some(1234))), "#6.1234"),
       (LPAREN, "("),
       (TSTR, "tstr"),
       (RPAREN, ")"),
-      (IDENT(("myfirstrule".into(), None)), "myfirstrule"),
+      (IDENT(("myfirstrule", None)), "myfirstrule"),
       (ASSIGN, "="),
       (VALUE(Value::TEXT("myotherrule".into())), "\"myotherrule\""),
-      (IDENT(("mybytestring".into(), None)), "mybytestring"),
+      (IDENT(("mybytestring", None)), "mybytestring"),
       (ASSIGN, "="),
       (
         VALUE(Value::BYTE(ByteValue::UTF8(b"hello there".as_ref().into()))),
         "'hello there'",
       ),
-      (IDENT(("mybase16rule".into(), None)), "mybase16rule"),
+      (IDENT(("mybase16rule", None)), "mybase16rule"),
       (ASSIGN, "="),
       (
         VALUE(Value::BYTE(ByteValue::B16(
@@ -962,7 +1405,7 @@ mod tests {
         ))),
         "h'68656c6c6f20776f726c64'",
       ),
-      (IDENT(("mybase64rule".into(), None)), "mybase64rule"),
+      (IDENT(("mybase64rule", None)), "mybase64rule"),
       (ASSIGN, "="),
       (
         VALUE(Value::BYTE(ByteValue::B64(
@@ -970,50 +1413,50 @@ mod tests {
         ))),
         "b64'aGVsbG8gd29ybGQ='",
       ),
-      (IDENT(("mysecondrule".into(), None)), "mysecondrule"),
+      (IDENT(("mysecondrule", None)), "mysecondrule"),
       (ASSIGN, "="),
-      (IDENT(("mynumber".into(), None)), "mynumber"),
+      (IDENT(("mynumber", None)), "mynumber"),
       (RANGEOP(true), ".."),
       (VALUE(Value::FLOAT(100.5)), "100.5"),
-      (IDENT(("myintrule".into(), None)), "myintrule"),
+      (IDENT(("myintrule", None)), "myintrule"),
       (ASSIGN, "="),
       (VALUE(Value::INT(-10)), "-10"),
-      (IDENT(("mysignedfloat".into(), None)), "mysignedfloat"),
+      (IDENT(("mysignedfloat", None)), "mysignedfloat"),
       (ASSIGN, "="),
       (VALUE(Value::FLOAT(-10.5)), "-10.5"),
-      (IDENT(("myintrange".into(), None)), "myintrange"),
+      (IDENT(("myintrange", None)), "myintrange"),
       (ASSIGN, "="),
       (VALUE(Value::INT(-10)), "-10"),
       (RANGEOP(true), ".."),
       (VALUE(Value::UINT(10)), "10"),
-      (IDENT(("mycontrol".into(), None)), "mycontrol"),
+      (IDENT(("mycontrol", None)), "mycontrol"),
       (ASSIGN, "="),
-      (IDENT(("mynumber".into(), None)), "mynumber"),
+      (IDENT(("mynumber", None)), "mynumber"),
       (GT, ".gt"),
       (VALUE(Value::UINT(0)), "0"),
-      (IDENT(("@terminal-color".into(), None)), "@terminal-color"),
+      (IDENT(("@terminal-color", None)), "@terminal-color"),
       (ASSIGN, "="),
-      (IDENT(("basecolors".into(), None)), "basecolors"),
+      (IDENT(("basecolors", None)), "basecolors"),
       (TCHOICE, "/"),
-      (IDENT(("othercolors".into(), None)), "othercolors"),
-      (COMMENT(" an inline comment".into()), "; an inline comment"),
-      (IDENT(("messages".into(), None)), "messages"),
+      (IDENT(("othercolors", None)), "othercolors"),
+      (COMMENT(" an inline comment"), "; an inline comment"),
+      (IDENT(("messages", None)), "messages"),
       (ASSIGN, "="),
-      (IDENT(("message".into(), None)), "message"),
+      (IDENT(("message", None)), "message"),
       (LANGLEBRACKET, "<"),
       (VALUE(Value::TEXT("reboot".into())), "\"reboot\""),
       (COMMA, ","),
       (VALUE(Value::TEXT("now".into())), "\"now\""),
       (RANGLEBRACKET, ">"),
-      (IDENT(("address".into(), None)), "address"),
+      (IDENT(("address", None)), "address"),
       (ASSIGN, "="),
       (LBRACE, "{"),
-      (IDENT(("delivery".into(), None)), "delivery"),
+      (IDENT(("delivery", None)), "delivery"),
       (RBRACE, "}"),
-      (IDENT(("delivery".into(), None)), "delivery"),
+      (IDENT(("delivery", None)), "delivery"),
       (ASSIGN, "="),
       (LPAREN, "("),
-      (IDENT(("street".into(), None)), "street"),
+      (IDENT(("street", None)), "street"),
       (COLON, ":"),
       (TSTR, "tstr"),
       (COMMA, ","),
@@ -1023,32 +1466,32 @@ mod tests {
       (ARROWMAP, "=>"),
       (UINT, "uint"),
       (COMMA, ","),
-      (IDENT(("city".into(), None)), "city"),
+      (IDENT(("city", None)), "city"),
       (GCHOICE, "//"),
-      (IDENT(("po-box".into(), None)), "po-box"),
+      (IDENT(("po-box", None)), "po-box"),
       (COLON, ":"),
       (UINT, "uint"),
       (COMMA, ","),
-      (IDENT(("city".into(), None)), "city"),
+      (IDENT(("city", None)), "city"),
       (GCHOICE, "//"),
-      (IDENT(("per-pickup".into(), None)), "per-pickup"),
+      (IDENT(("per-pickup", None)), "per-pickup"),
       (COLON, ":"),
       (TRUE, "true"),
       (RPAREN, ")"),
-      (IDENT(("city".into(), None)), "city"),
+      (IDENT(("city", None)), "city"),
       (ASSIGN, "="),
       (LPAREN, "("),
-      (IDENT(("name".into(), None)), "name"),
+      (IDENT(("name", None)), "name"),
       (COLON, ":"),
       (TSTR, "tstr"),
-      (IDENT(("zip-code".into(), None)), "zip-code"),
+      (IDENT(("zip-code", None)), "zip-code"),
       (COLON, ":"),
       (UINT, "uint"),
       (VALUE(Value::UINT(1)), "1"),
       (ASTERISK, "*"),
       (VALUE(Value::UINT(3)), "3"),
       (
-        IDENT(("tcp-option".into(), Some(SocketPlug::GROUP))),
+        IDENT(("tcp-option", Some(SocketPlug::GROUP))),
         "$$tcp-option",
       ),
       (COMMA, ","),
@@ -1097,6 +1540,73 @@ mod tests {
     Ok(())
   }
 
+  #[test]
+  fn verify_big_integers() -> Result<()> {
+    let input = r#"18446744073709551615 -18446744073709551616"#;
+
+    let mut l = Lexer::new(input);
+
+    assert_eq!(
+      l.next_token()?.1,
+      VALUE(Value::UINT(18_446_744_073_709_551_615))
+    );
+    assert_eq!(
+      l.next_token()?.1,
+      VALUE(Value::INT(-18_446_744_073_709_551_616))
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn verify_number_literals() -> Result<()> {
+    let input = r#"0x1F -0X10 0b1010 0o17 0 1e3 -2.5E-2 1.5e+2 0x1.8p1 -0x1p-2 0x0..0xff"#;
+
+    let mut l = Lexer::new(input);
+
+    let expected_tokens = [
+      VALUE(Value::UINT(31)),
+      VALUE(Value::INT(-16)),
+      VALUE(Value::UINT(10)),
+      VALUE(Value::UINT(15)),
+      VALUE(Value::UINT(0)),
+      VALUE(Value::FLOAT(1000.0)),
+      VALUE(Value::FLOAT(-0.025)),
+      VALUE(Value::FLOAT(150.0)),
+      VALUE(Value::FLOAT(3.0)),
+      VALUE(Value::FLOAT(-0.25)),
+      VALUE(Value::UINT(0)),
+      RANGEOP(true),
+      VALUE(Value::UINT(255)),
+    ];
+
+    for expected_tok in expected_tokens.iter() {
+      assert_eq!(expected_tok, &l.next_token()?.1);
+    }
+
+    assert!(Lexer::new("0x1.8").next_token().is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn verify_malformed_input() -> Result<()> {
+    // Multi-byte characters are sliced on character boundaries
+    assert_eq!(Lexer::new("né").next_token()?.1, IDENT(("né", None)));
+    assert_eq!(Lexer::new("\u{2022}").next_token()?.1, ILLEGAL("\u{2022}"));
+
+    assert!(Lexer::new("-\u{477}").next_token().is_err());
+    assert!(Lexer::new("#6.\u{5fc}").next_token().is_err());
+
+    // Byte strings aren't limited in length
+    let input = format!("h'{}'", "ab".repeat(2048));
+    assert!(Lexer::new(&input).next_token().is_ok());
+    let input = format!("b64'{}'", "abcd".repeat(1024));
+    assert!(Lexer::new(&input).next_token().is_ok());
+
+    Ok(())
+  }
+
   #[test]
   fn verify_lexer_diagnostic() -> Result<()> {
     let input = r#"myrule = number .asdf 10"#;
@@ -1133,4 +1643,169 @@ mod tests {
       }
     }
   }
+  #[test]
+  fn verify_tokens_with_trivia() -> Result<()> {
+    let input = indoc!(
+      r#"
+        ; header
+        person = {
+          ? age: uint .le 120, ; inline
+          tag: #6.32(tstr) / #7.25,
+        }
+      "#
+    );
+
+    let tokens = tokens_with_trivia(input).collect::<Result<Vec<_>>>()?;
+
+    // Every byte of input is covered by exactly one token or trivia
+    assert_eq!(
+      tokens
+        .iter()
+        .map(|(_, span)| &input[span.0..span.1])
+        .collect::<String>(),
+      input
+    );
+
+    for (token, span) in tokens.iter() {
+      match token {
+        WHITESPACE(ws) => assert!(ws.chars().all(char::is_whitespace)),
+        _ => assert_eq!(token.to_string(), &input[span.0..span.1]),
+      }
+    }
+
+    assert_eq!(tokens[0], (COMMENT(" header"), (0, 8, 1)));
+    assert_eq!(
+      tokens
+        .iter()
+        .find(|(token, _)| *token == COMMENT(" inline"))
+        .map(|(_, span)| span.2),
+      Some(3)
+    );
+    assert_eq!(
+      tokens
+        .iter()
+        .find(|(token, _)| *token == TAG((Some(6), Some(32))))
+        .map(|(_, span)| &input[span.0..span.1]),
+      Some("#6.32")
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn verify_identifiers() -> Result<()> {
+    let input = "tcp-option@iana $$ext.v1 a.b--c..d @type _x foo$bar x1-2 café-größe lo.. hi... 3";
+
+    let mut l = Lexer::new(input);
+
+    let expected_tokens = [
+      IDENT(("tcp-option@iana", None)),
+      IDENT(("ext.v1", Some(SocketPlug::GROUP))),
+      IDENT(("a.b--c..d", None)),
+      IDENT(("@type", None)),
+      IDENT(("_x", None)),
+      IDENT(("foo$bar", None)),
+      IDENT(("x1-2", None)),
+      IDENT(("café-größe", None)),
+      // Trailing "-" and "." aren't part of a name
+      IDENT(("lo", None)),
+      RANGEOP(true),
+      IDENT(("hi", None)),
+      RANGEOP(false),
+      VALUE(Value::UINT(3)),
+    ];
+
+    for expected_tok in expected_tokens.iter() {
+      assert_eq!(expected_tok, &l.next_token()?.1);
+    }
+
+    assert_eq!(Lexer::new("x-").next_token()?.1, IDENT(("x", None)));
+
+    Ok(())
+  }
+
+  #[test]
+  fn verify_text_escapes() -> Result<()> {
+    let input = r#""plain" "a\"b\\c\/d" "\b\f\n\r\t" "é\u{1F600}\uD83D\uDE00😀" "\d+\.x" "é""#;
+
+    let mut l = Lexer::new(input);
+
+    let expected = [
+      "plain",
+      "a\"b\\c/d",
+      "\u{8}\u{c}\n\r\t",
+      "\u{e9}\u{1F600}\u{1F600}\u{1F600}",
+      r"\d+\.x",
+      "é",
+    ];
+
+    for text in expected.iter() {
+      assert_eq!(l.next_token()?.1, VALUE(Value::TEXT((*text).into())));
+    }
+
+    // Text without escapes is borrowed from the input
+    match Lexer::new(r#""plain""#).next_token()?.1 {
+      VALUE(Value::TEXT(Cow::Borrowed("plain"))) => (),
+      tok => panic!("unexpected token {:?}", tok),
+    }
+
+    // Line breaks may appear in multi-line text
+    assert_eq!(
+      Lexer::new("\"line one\n  line two\"").next_token()?.1,
+      VALUE(Value::TEXT("line one\n  line two".into()))
+    );
+
+    for invalid in [
+      r#""\u12""#,
+      r#""\u{}""#,
+      r#""\u{110000}""#,
+      r#""\uD83D""#,
+      r#""\uDE00""#,
+    ]
+    .iter()
+    {
+      assert!(Lexer::new(invalid).next_token().is_err(), "{}", invalid);
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn verify_byte_string_escapes() -> Result<()> {
+    let input = r#"'plain' 'by\'te' 'a\\b\n' '\u{1F600}' '' '\''"#;
+
+    let mut l = Lexer::new(input);
+
+    let expected: [&[u8]; 6] = [
+      b"plain",
+      b"by'te",
+      b"a\\b\n",
+      "\u{1F600}".as_bytes(),
+      b"",
+      b"'",
+    ];
+
+    for bytes in expected.iter() {
+      assert_eq!(
+        l.next_token()?.1,
+        VALUE(Value::BYTE(ByteValue::UTF8((*bytes).into())))
+      );
+    }
+
+    // Byte strings without escapes are borrowed from the input
+    match Lexer::new("'plain'").next_token()?.1 {
+      VALUE(Value::BYTE(ByteValue::UTF8(Cow::Borrowed(b"plain")))) => (),
+      tok => panic!("unexpected token {:?}", tok),
+    }
+
+    // Quotes and backslashes are escaped when displayed
+    let bytes = ByteValue::UTF8(b"it's a \\".as_ref().into());
+    assert_eq!(bytes.to_string(), r"'it\'s a \\'");
+    assert_eq!(
+      Lexer::new(&bytes.to_string()).next_token()?.1,
+      VALUE(Value::BYTE(bytes))
+    );
+
+    Ok(())
+  }
 }
@@ -27,7 +27,7 @@
 //!
 //! This crate supports validation of both CBOR and JSON data structures. An
 //! extremely basic REPL is included as well, along with a compiled WebAssembly
-//! target. The minimum version of Rust that this crate supports is 1.37.0.
+//! target. The minimum version of Rust that this crate supports is 1.40.0.
 //!
 //! ## Goals
 //!
@@ -39,7 +39,7 @@
 //! - Generate dummy JSON from conformant CDDL
 //! - Close to zero-copy as possible
 //! - Compile WebAssembly target for browser and Node.js
-//! - `no_std` support (lexing and parsing only)
+//! - `no_std` support (lexing, parsing and CBOR validation)
 //!
 //! ## Non-goals
 //!
@@ -181,6 +181,7 @@
 //! | `.bits`          | Unsupported for JSON validation                                                                                                                                                             |
 //! | `.cbor`          | Unsupported for JSON validation                                                                                                                                                             |
 //! | `.cborseq`       | Unsupported for JSON validation                                                                                                                                                             |
+//! | `.json`          | <g-emoji class="g-emoji" alias="heavy_check_mark" fallback-src="https://github.githubassets.com/images/icons/emoji/unicode/2714.png">✔️</g-emoji>                                           |
 //! | `.within`        | Incomplete                                                                                                                                                                                  |
 //! | `.and`           | Incomplete                                                                                                                                                                                  |
 //! | `.lt`            | <g-emoji class="g-emoji" alias="heavy_check_mark" fallback-src="https://github.githubassets.com/images/icons/emoji/unicode/2714.png">✔️</g-emoji>                                           |
@@ -190,6 +191,7 @@
 //! | `.eq`            | Partial (text and numeric values)                                                                                                                                                           |
 //! | `.ne`            | Incomplete                                                                                                                                                                                  |
 //! | `.default`       | Incomplete                                                                                                                                                                                  |
+//! | `.capture`       | <g-emoji class="g-emoji" alias="heavy_check_mark" fallback-src="https://github.githubassets.com/images/icons/emoji/unicode/2714.png">✔️</g-emoji>                                           |
 //!
 //! <a name="number">1</a>: While JSON itself does not distinguish between
 //! integers and floating-point numbers, this crate does provide the ability to
@@ -233,10 +235,26 @@
 //! Serde](https://github.com/pyfisch/cbor/issues/3), CBOR tags are ignored
 //! during deserialization.
 //!
+//! ## Validation features
+//!
+//! JSON and CBOR validation are enabled by the `json-validation` and
+//! `cbor-validation` features respectively, both of which are on by default.
+//! Consumers that only need the lexer, parser, formatter or code generation
+//! can opt out of them to avoid depending on `serde_json` and `serde_cbor`:
+//!
+//! ```toml
+//! [dependencies]
+//! cddl = { version = "<version>", default-features = false, features = ["std"] }
+//! ```
+//!
+//! The `validation` module and `Validator` trait are available with either
+//! feature, while `validation::json` and `validation::cbor` each require their
+//! own.
+//!
 //! ## `no_std` support
 //!
-//! Only the lexer and parser can be used in a `no_std` context provided that a
-//! heap allocator is available. This can be enabled by opting out of the
+//! The lexer, parser and a CBOR validator can be used in a `no_std` context
+//! provided that a heap allocator is available. This can be enabled by opting out of the
 //! default features in your `Cargo.toml` file as follows:
 //!
 //! ```toml
@@ -249,10 +267,30 @@
 //! where allocation is required. Allocation is also used for error handling and
 //! diagnostics.
 //!
-//! Both JSON and CBOR validation are dependent on their respective heap
-//! allocated `Value` types, but since these types aren't supported in a
-//! `no_std` context, they subsequently aren't supported in a `no_std` context
-//! in this crate.
+//! The JSON and CBOR validators in the `validation` module are dependent on
+//! the `Value` types of `serde_json` and `serde_cbor`, which aren't supported
+//! in a `no_std` context. Instead, the `cbor_core` module decodes CBOR into
+//! its own `Value` type and validates it using only the `alloc` crate:
+//!
+//! ```
+//! use cddl::cbor_core::validate_from_slice;
+//!
+//! let cddl = r#"reading = [sensor: uint, value: int]"#;
+//!
+//! assert!(validate_from_slice(cddl, &[0x82, 0x01, 0x38, 0x63]).is_ok());
+//! ```
+//!
+//! It reaches the same verdicts as the CBOR validator of the `validation`
+//! module, except that it reports an `Error::Unsupported` in place of a
+//! verdict once the data meets a construct it doesn't check: the `.regexp`,
+//! `.pcre` and `.json` control operators, controls applied to targets or
+//! controllers that the standard validators don't check them against, e.g.
+//! `bstr .eq h'01'`, the `~` unwrap operator, the formats of text strings
+//! tagged as `tdate` (or as `uri` and the like with the `formats` feature) and
+//! the `duration` extension type. It also follows fewer nested rule references
+//! (`cbor_core::MAX_DEPTH`) to suit smaller stacks. Its errors, like the others of this crate, implement
+//! `error::Error`, which is `std::error::Error` when the `std` feature is
+//! enabled and a minimal stand-in otherwise.
 
 #![allow(dead_code)]
 #![cfg_attr(not(feature = "std"), no_std)]
@@ -265,29 +303,129 @@ extern crate alloc;
 #[cfg(not(feature = "std"))]
 extern crate core as std;
 
-#[cfg(feature = "std")]
+#[cfg(feature = "serde_json")]
 extern crate serde_json;
 
-#[cfg(feature = "std")]
+#[cfg(feature = "serde_cbor")]
 extern crate serde_cbor;
 
-#[cfg(feature = "nightly")]
+#[cfg(feature = "formats")]
 extern crate uriparse;
 
 /// Abstract syntax tree representing a CDDL definition
 pub mod ast;
+/// Builders for constructing CDDL definitions programmatically
+pub mod builder;
+/// C bindings for JSON and CBOR validation, declared by `include/cddl.h`
+#[cfg(feature = "capi")]
+#[cfg(not(target_arch = "wasm32"))]
+pub mod capi;
+/// Validation of CBOR requiring only a heap allocator, for use in `no_std`
+/// environments
+pub mod cbor_core;
+/// Rust code generation from CDDL
+pub mod codegen;
+/// Compatibility checks between versions of a CDDL definition
+pub mod compat;
+/// Semantic checks over a parsed CDDL definition
+pub mod compiler;
+/// Conformance suites pairing a CDDL file with instances expected to conform
+/// to it and ones that aren't, described by `.cddltest` manifests
+#[cfg(feature = "json-validation")]
+#[cfg(feature = "cbor-validation")]
+#[cfg(not(target_arch = "wasm32"))]
+pub mod conformance;
+/// Structural differences between two CDDL definitions
+pub mod diff;
+/// Documentation generation from CDDL
+#[cfg(feature = "std")]
+#[cfg(not(target_arch = "wasm32"))]
+pub mod docgen;
+/// Parser for CBOR diagnostic notation
+#[cfg(feature = "cbor-validation")]
+#[cfg(not(target_arch = "wasm32"))]
+pub mod edn;
+/// Error trait implemented by the errors of this crate and the limits they
+/// report
+pub mod error;
+/// Loading of CDDL definitions from URLs with pluggable fetchers
+#[cfg(feature = "std")]
+#[cfg(not(target_arch = "wasm32"))]
+pub mod fetch;
+mod fingerprint;
+/// Formatter for CDDL
+#[cfg(feature = "std")]
+#[cfg(not(target_arch = "wasm32"))]
+pub mod formatter;
+/// Generation of JSON and CBOR instances from CDDL
+#[cfg(feature = "json-validation")]
+#[cfg(feature = "cbor-validation")]
+#[cfg(not(target_arch = "wasm32"))]
+pub mod generator;
+/// Export of the references between rules as a graph
+pub mod graph;
+/// Conversion between CDDL and JSON Schema
+#[cfg(feature = "json-validation")]
+#[cfg(not(target_arch = "wasm32"))]
+pub mod json_schema;
 /// Lexer for CDDL
 pub mod lexer;
+/// Configurable lints for CDDL definitions
+pub mod lint;
+/// Loading of CDDL definitions split across multiple schemas
+#[cfg(feature = "std")]
+#[cfg(not(target_arch = "wasm32"))]
+pub mod loader;
+/// Language Server Protocol support for CDDL editor tooling
+#[cfg(feature = "lsp")]
+pub mod lsp;
+/// Introspection of the rules of a CDDL definition, their kinds and the rules
+/// they refer to
+pub mod metadata;
+mod normalize;
 /// Parser for CDDL
 pub mod parser;
+/// Standard prelude defined by RFC 8610
+pub mod prelude;
+/// Ready-made validators for data formats whose CDDL is published by IETF
+/// specifications, built on `cbor_core` so that they check tags and `.cbor`
+/// controls and are available in `no_std` environments
+pub mod profiles;
+mod prune;
+/// Python bindings for parsing CDDL and validating JSON and CBOR
+#[cfg(feature = "python")]
+#[cfg(not(target_arch = "wasm32"))]
+pub mod python;
+/// Publishing and fetching of schemas by fingerprint
+#[cfg(feature = "std")]
+#[cfg(not(target_arch = "wasm32"))]
+pub mod registry;
+/// Incremental re-parsing of CDDL definitions as their text is edited
+pub mod reparse;
 /// Basic REPL for CDDL lexing
 pub mod repl;
+mod rewrite;
+/// Owned CDDL schemas that can be parsed once and reused
+#[cfg(not(target_arch = "wasm32"))]
+pub mod schema;
+/// Well-known CDDL definitions published by the IETF, bundled by the
+/// `schemas` feature
+#[cfg(feature = "schemas")]
+pub mod schemas;
+/// Property testing strategies for CDDL-defined data
+#[cfg(feature = "proptest")]
+#[cfg(feature = "json-validation")]
+#[cfg(feature = "cbor-validation")]
+#[cfg(not(target_arch = "wasm32"))]
+pub mod testing;
 /// CDDL tokens for lexing
 pub mod token;
 /// Validation against various data structures (e.g. JSON, CBOR)
-#[cfg(feature = "std")]
+#[cfg(any(feature = "json-validation", feature = "cbor-validation"))]
 #[cfg(not(target_arch = "wasm32"))]
 pub mod validation;
+/// Visitor for traversing a CDDL AST
+pub mod visitor;
 
 #[doc(inline)]
 pub use self::{
@@ -297,10 +435,41 @@ pub use self::{
 };
 
 #[doc(inline)]
-#[cfg(feature = "std")]
+#[cfg(any(feature = "json-validation", feature = "cbor-validation"))]
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::validation::{Error as ValidationError, Validator};
+
+#[doc(inline)]
+#[cfg(feature = "cbor-validation")]
 #[cfg(not(target_arch = "wasm32"))]
-pub use self::validation::{
-  cbor::{self as cbor_validator, validate_cbor_from_slice},
-  json::{self as json_validator, validate_json_from_str},
-  Error as ValidationError, Validator,
+pub use self::validation::cbor::{
+  self as cbor_validator, validate_cbor_diag_from_str, validate_cbor_from_slice,
+  validate_cbor_from_slice_for_rule, validate_cbor_seq_from_slice,
 };
+
+#[doc(inline)]
+#[cfg(feature = "msgpack")]
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::validation::cbor::validate_msgpack_from_slice;
+
+#[doc(inline)]
+#[cfg(feature = "bson")]
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::validation::cbor::validate_bson_from_slice;
+
+#[doc(inline)]
+#[cfg(feature = "json-validation")]
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::validation::json::{
+  self as json_validator, validate_json_from_reader, validate_json_from_str,
+  validate_json_from_str_for_rule,
+};
+
+#[doc(inline)]
+#[cfg(feature = "yaml")]
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::validation::json::{validate_yaml_from_str, validate_yaml_from_str_with_options};
+
+#[doc(inline)]
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::schema::CddlSchema;
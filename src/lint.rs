@@ -0,0 +1,407 @@
+use super::{ast::*, prelude::is_prelude_type, visitor::*};
+use std::fmt;
+
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+  collections::BTreeMap,
+  string::{String, ToString},
+  vec::Vec,
+};
+
+/// Severity of a lint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+  /// Matter of style that doesn't affect what the definition describes
+  Note,
+  /// Construct that's valid but most likely not what was intended
+  Warning,
+  /// Construct that should fail checks in CI
+  Error,
+}
+
+impl fmt::Display for Severity {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Severity::Note => write!(f, "note"),
+      Severity::Warning => write!(f, "warning"),
+      Severity::Error => write!(f, "error"),
+    }
+  }
+}
+
+/// Stylistic or suspicious construct found in a CDDL definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+  /// Kind of construct
+  pub kind: LintKind,
+  /// Severity the lint is reported at
+  pub severity: Severity,
+  /// Span of the rule name, type, group or occurrence indicator the lint
+  /// applies to
+  pub span: Span,
+}
+
+/// Kinds of constructs reported by the linter
+#[derive(Debug, Clone, PartialEq)]
+pub enum LintKind {
+  /// Rule named like a type of the standard prelude, e.g. `Tstr`. The parser
+  /// only rejects exact matches, which can still occur in ASTs that weren't
+  /// parsed.
+  ShadowedPrelude(String),
+  /// Type or group choice that can never be chosen since an earlier
+  /// alternative is identical or matches anything
+  UnreachableChoice(String),
+  /// Group without any entries, other than that of an empty map or array
+  EmptyGroup(String),
+  /// Occurrence indicator that can never be satisfied, allows no occurrences
+  /// at all or is equivalent to having none, e.g. `0*0`, `3*2` or `1*1`
+  SuspiciousOccurrence(String),
+  /// Rule name that isn't written in lowercase kebab-case, e.g. `fooBar`
+  NamingConvention(String),
+}
+
+impl LintKind {
+  /// Returns the stable identifier of the lint, used to configure it
+  pub fn id(&self) -> &'static str {
+    match self {
+      LintKind::ShadowedPrelude(_) => "shadowed-prelude",
+      LintKind::UnreachableChoice(_) => "unreachable-choice",
+      LintKind::EmptyGroup(_) => "empty-group",
+      LintKind::SuspiciousOccurrence(_) => "suspicious-occurrence",
+      LintKind::NamingConvention(_) => "naming-convention",
+    }
+  }
+
+  /// Returns the severity the lint is reported at unless configured otherwise
+  pub fn default_severity(&self) -> Severity {
+    match self {
+      LintKind::NamingConvention(_) => Severity::Note,
+      _ => Severity::Warning,
+    }
+  }
+}
+
+impl fmt::Display for Lint {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match &self.kind {
+      LintKind::ShadowedPrelude(name) => {
+        write!(
+          f,
+          "rule '{}' shadows the prelude type of the same name",
+          name
+        )
+      }
+      LintKind::UnreachableChoice(choice) => write!(
+        f,
+        "choice '{}' is unreachable since an earlier alternative already matches it",
+        choice
+      ),
+      LintKind::EmptyGroup(group) => write!(f, "group '{}' has no entries", group),
+      LintKind::SuspiciousOccurrence(occur) => {
+        write!(f, "occurrence indicator '{}' is suspicious", occur)
+      }
+      LintKind::NamingConvention(name) => {
+        write!(f, "rule '{}' isn't named in lowercase kebab-case", name)
+      }
+    }
+  }
+}
+
+/// Configuration of the lints reported by `lint_with_config`, keyed by the
+/// IDs returned by `LintKind::id`
+///
+/// # Example
+///
+/// ```
+/// use cddl::{lexer_from_str, lint::{lint_with_config, LintConfig, Severity}, parser::cddl_from_str};
+///
+/// let input = r#"myRule = [0*0 int]"#;
+/// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+///
+/// let config = LintConfig::default()
+///   .allow("naming-convention")
+///   .severity("suspicious-occurrence", Severity::Error);
+///
+/// let lints = lint_with_config(&cddl, &config);
+/// assert_eq!(lints.len(), 1);
+/// assert_eq!(lints[0].severity, Severity::Error);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LintConfig {
+  severities: BTreeMap<String, Option<Severity>>,
+}
+
+impl LintConfig {
+  /// Disables the lint with the given ID
+  pub fn allow(mut self, id: &str) -> Self {
+    self.severities.insert(id.to_string(), None);
+    self
+  }
+
+  /// Reports the lint with the given ID at the given severity
+  pub fn severity(mut self, id: &str, severity: Severity) -> Self {
+    self.severities.insert(id.to_string(), Some(severity));
+    self
+  }
+
+  // Returns the severity the given kind of lint is reported at, if it's
+  // enabled
+  fn severity_of(&self, kind: &LintKind) -> Option<Severity> {
+    match self.severities.get(kind.id()) {
+      Some(severity) => *severity,
+      None => Some(kind.default_severity()),
+    }
+  }
+}
+
+/// Checks a CDDL definition for stylistic and suspicious constructs with every
+/// lint enabled at its default severity
+///
+/// # Example
+///
+/// ```
+/// use cddl::{lexer_from_str, lint::{lint, LintKind}, parser::cddl_from_str};
+///
+/// let input = r#"color = "red" / "green" / "red""#;
+/// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+///
+/// let lints = lint(&cddl);
+/// assert_eq!(lints[0].kind, LintKind::UnreachableChoice("\"red\"".into()));
+/// ```
+pub fn lint(cddl: &CDDL) -> Vec<Lint> {
+  lint_with_config(cddl, &LintConfig::default())
+}
+
+/// Checks a CDDL definition for stylistic and suspicious constructs, reporting
+/// each lint as configured
+pub fn lint_with_config(cddl: &CDDL, config: &LintConfig) -> Vec<Lint> {
+  let mut linter = Linter {
+    config,
+    lints: Vec::new(),
+  };
+  walk_cddl(&mut linter, cddl);
+
+  linter.lints
+}
+
+struct Linter<'b> {
+  config: &'b LintConfig,
+  lints: Vec<Lint>,
+}
+
+impl<'b> Linter<'b> {
+  fn report(&mut self, kind: LintKind, span: Span) {
+    if let Some(severity) = self.config.severity_of(&kind) {
+      self.lints.push(Lint {
+        kind,
+        severity,
+        span,
+      });
+    }
+  }
+}
+
+impl<'a, 'b> Visitor<'a> for Linter<'b> {
+  fn visit_rule(&mut self, rule: &Rule<'a>) {
+    let name = match rule {
+      Rule::Type { rule, .. } => &rule.name,
+      Rule::Group { rule, .. } => &rule.name,
+    };
+
+    // Extending a rule via /= or //= isn't a new definition
+    if !rule.is_choice_alternate() {
+      if is_prelude_type(&name.ident.to_ascii_lowercase()) {
+        self.report(LintKind::ShadowedPrelude(name.to_string()), name.span);
+      }
+
+      if !is_kebab_case(name.ident) {
+        self.report(LintKind::NamingConvention(name.to_string()), name.span);
+      }
+    }
+
+    walk_rule(self, rule);
+  }
+
+  fn visit_type(&mut self, t: &Type<'a>) {
+    for (idx, tc) in t.type_choices.iter().enumerate() {
+      let choice = tc.to_string();
+
+      if t.type_choices[..idx]
+        .iter()
+        .any(|earlier| matches_anything(earlier) || earlier.to_string() == choice)
+      {
+        self.report(LintKind::UnreachableChoice(choice), tc.span);
+      }
+    }
+
+    walk_type(self, t);
+  }
+
+  fn visit_group(&mut self, g: &Group<'a>) {
+    for (idx, gc) in g.group_choices.iter().enumerate() {
+      let choice = gc.to_string();
+
+      // { a: int // } has an empty alternative, whereas {} is an empty map
+      if gc.group_entries.is_empty() && g.group_choices.len() > 1 {
+        self.report(LintKind::EmptyGroup(g.to_string()), gc.span);
+      } else if g.group_choices[..idx]
+        .iter()
+        .any(|earlier| earlier.to_string() == choice)
+      {
+        self.report(LintKind::UnreachableChoice(choice), gc.span);
+      }
+    }
+
+    walk_group(self, g);
+  }
+
+  fn visit_group_entry(&mut self, ge: &GroupEntry<'a>) {
+    if let GroupEntry::InlineGroup { group, span, .. } = ge {
+      if group
+        .group_choices
+        .iter()
+        .all(|gc| gc.group_entries.is_empty())
+      {
+        self.report(LintKind::EmptyGroup(ge.to_string()), *span);
+      }
+    }
+
+    walk_group_entry(self, ge);
+  }
+
+  fn visit_occurrence(&mut self, o: &Occur) {
+    if let Occur::Exact { lower, upper, span } = o {
+      let lower = lower.unwrap_or(0);
+
+      if let Some(upper) = upper {
+        if *upper == 0 || lower > *upper || (lower == 1 && *upper == 1) {
+          self.report(LintKind::SuspiciousOccurrence(o.to_string()), *span);
+        }
+      }
+    }
+  }
+}
+
+// Returns whether a type choice matches any data item, making the alternatives
+// that follow it unreachable
+fn matches_anything(t1: &Type1) -> bool {
+  if t1.operator.is_some() {
+    return false;
+  }
+
+  match &t1.type2 {
+    Type2::Any(_) => true,
+    Type2::Typename {
+      ident,
+      generic_arg: None,
+      ..
+    } => ident.ident == "any",
+    _ => false,
+  }
+}
+
+// Returns whether a name consists of lowercase words separated by hyphens or
+// dots, e.g. reputon-list or tag.value
+fn is_kebab_case(name: &str) -> bool {
+  name
+    .chars()
+    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '.')
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+  use super::*;
+  use crate::{lexer::Lexer, parser::cddl_from_str};
+
+  fn lint_kinds(input: &str) -> Vec<(LintKind, Severity)> {
+    let mut lexer = Lexer::new(input);
+    let cddl = cddl_from_str(&mut lexer, input, false).unwrap();
+
+    lint(&cddl)
+      .into_iter()
+      .map(|l| (l.kind, l.severity))
+      .collect()
+  }
+
+  #[test]
+  fn verify_clean_definition() {
+    let input = r#"
+      reputon = { rater: tstr, ? confidence: float16, * tstr => any }
+      ratings = [+ rating]
+      rating = 0..10 / "n/a"
+      tag.value = #6.32(tstr)
+    "#;
+
+    assert_eq!(lint_kinds(input), vec![]);
+  }
+
+  #[test]
+  fn verify_lints() {
+    let input = r#"
+      Uint = tstr
+      myRule = [0*0 int, 3*2 tstr, 1*1 bool]
+      color = "red" / "green" / "red"
+      anything = any / int
+      record = { a: int // a: int // }
+      empty = ( )
+    "#;
+
+    assert_eq!(
+      lint_kinds(input),
+      vec![
+        (LintKind::ShadowedPrelude("Uint".into()), Severity::Warning),
+        (LintKind::NamingConvention("Uint".into()), Severity::Note),
+        (LintKind::NamingConvention("myRule".into()), Severity::Note),
+        (
+          LintKind::SuspiciousOccurrence("0*0".into()),
+          Severity::Warning
+        ),
+        (
+          LintKind::SuspiciousOccurrence("3*2".into()),
+          Severity::Warning
+        ),
+        (
+          LintKind::SuspiciousOccurrence("1*1".into()),
+          Severity::Warning
+        ),
+        (
+          LintKind::UnreachableChoice("\"red\"".into()),
+          Severity::Warning
+        ),
+        (LintKind::UnreachableChoice("int".into()), Severity::Warning),
+        (
+          LintKind::UnreachableChoice("a: int".into()),
+          Severity::Warning
+        ),
+        (
+          LintKind::EmptyGroup("a: int // a: int // ".into()),
+          Severity::Warning
+        ),
+        (LintKind::EmptyGroup("()".into()), Severity::Warning),
+      ]
+    );
+  }
+
+  #[test]
+  fn verify_config() {
+    let input = r#"myRule = [0*0 int]"#;
+    let mut lexer = Lexer::new(input);
+    let cddl = cddl_from_str(&mut lexer, input, false).unwrap();
+
+    let config = LintConfig::default()
+      .allow("naming-convention")
+      .severity("suspicious-occurrence", Severity::Error);
+
+    assert_eq!(
+      lint_with_config(&cddl, &config)
+        .into_iter()
+        .map(|l| (l.kind.id(), l.severity))
+        .collect::<Vec<_>>(),
+      vec![("suspicious-occurrence", Severity::Error)]
+    );
+  }
+}
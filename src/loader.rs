@@ -0,0 +1,556 @@
+use super::{
+  ast::CDDL, lexer::Lexer, parser::cddl_from_str, prune::References, schema::CddlSchema,
+  visitor::Visitor,
+};
+use std::{
+  borrow::Borrow,
+  collections::HashMap,
+  fmt, fs,
+  hash::{BuildHasher, Hash},
+  path::{Path, PathBuf},
+};
+
+/// Text of a CDDL schema returned by a [`CddlLoader`](trait.CddlLoader.html)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Source {
+  /// Identifier that's the same whenever the same schema is loaded, however
+  /// it's referenced (e.g. a canonical file path)
+  pub id: String,
+  /// CDDL text of the schema
+  pub text: String,
+}
+
+/// Resolves the names given to `;# include` and `;# import` directives to the
+/// text of the schemas they refer to
+pub trait CddlLoader {
+  /// Loads the schema with the given name. `importer` is the identifier of
+  /// the schema containing the directive, or `None` when loading the root
+  /// schema or resolving directives in CDDL given as a string.
+  fn load(&self, name: &str, importer: Option<&str>) -> Result<Source, String>;
+}
+
+/// Loads schemas from the filesystem
+///
+/// Names are paths, with the `.cddl` extension added if they don't have one.
+/// Relative paths are resolved against the directory of the schema containing
+/// the directive (or the current directory for the root schema) and then
+/// against each search path in turn. Schemas are identified by their canonical
+/// path.
+#[derive(Debug, Clone, Default)]
+pub struct FileLoader {
+  search_paths: Vec<PathBuf>,
+}
+
+impl FileLoader {
+  /// Creates a loader without any search paths
+  pub fn new() -> Self {
+    FileLoader::default()
+  }
+
+  /// Adds a directory to search for schemas that aren't found relative to the
+  /// schema referencing them
+  pub fn search_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+    self.search_paths.push(path.into());
+    self
+  }
+}
+
+impl CddlLoader for FileLoader {
+  fn load(&self, name: &str, importer: Option<&str>) -> Result<Source, String> {
+    let mut path = PathBuf::from(name);
+    if path.extension().is_none() {
+      path.set_extension("cddl");
+    }
+
+    let first = match importer.and_then(|i| Path::new(i).parent()) {
+      Some(dir) => dir.join(&path),
+      None => path.clone(),
+    };
+
+    let found = Some(first)
+      .into_iter()
+      .chain(self.search_paths.iter().map(|dir| dir.join(&path)))
+      .find(|candidate| candidate.is_file())
+      .ok_or_else(|| format!("no file found for \"{}\"", path.display()))?;
+
+    let id = fs::canonicalize(&found).map_err(|e| e.to_string())?;
+    let text = fs::read_to_string(&id).map_err(|e| e.to_string())?;
+
+    Ok(Source {
+      id: id.to_string_lossy().into_owned(),
+      text,
+    })
+  }
+}
+
+/// Loads schemas held in memory, keyed by name
+impl<K, V, S> CddlLoader for HashMap<K, V, S>
+where
+  K: Borrow<str> + Hash + Eq,
+  V: AsRef<str>,
+  S: BuildHasher,
+{
+  fn load(&self, name: &str, _importer: Option<&str>) -> Result<Source, String> {
+    self
+      .get(name)
+      .map(|text| Source {
+        id: name.to_string(),
+        text: text.as_ref().to_string(),
+      })
+      .ok_or_else(|| format!("no schema named \"{}\"", name))
+  }
+}
+
+/// Error resolving the schemas referenced by a CDDL definition
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+  /// Schema that the loader failed to load
+  Load {
+    /// Name given to the directive
+    name: String,
+    /// Identifier of the schema containing the directive, if any
+    importer: Option<String>,
+    /// Error returned by the loader
+    message: String,
+  },
+  /// Schema that isn't conformant CDDL
+  Parse {
+    /// Identifier of the schema
+    id: String,
+    /// Error returned by the parser
+    message: String,
+  },
+  /// Rule defined by more than one schema
+  Conflict {
+    /// Name of the rule
+    rule: String,
+    /// Identifier of the schema defining the rule first
+    first: String,
+    /// Identifier of the schema defining the rule again
+    second: String,
+  },
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Error::Load {
+        name,
+        importer: Some(importer),
+        message,
+      } => write!(
+        f,
+        "error loading \"{}\" referenced by {}: {}",
+        name, importer, message
+      ),
+      Error::Load { name, message, .. } => write!(f, "error loading \"{}\": {}", name, message),
+      Error::Parse { id, message } => write!(f, "error parsing {}: {}", id, message),
+      Error::Conflict {
+        rule,
+        first,
+        second,
+      } => write!(
+        f,
+        "rule '{}' is defined by both {} and {}. Use /= or //= to add choices to an existing rule",
+        rule, first, second
+      ),
+    }
+  }
+}
+
+impl std::error::Error for Error {}
+
+/// Loads the schema with the given name along with every schema it references
+/// via directives, merging their rules into a single schema
+///
+/// Directives are comments at the start of a line:
+///
+/// ```text
+/// ;# include common
+/// ;# import rfc9052
+/// ```
+///
+/// `include` merges every rule of the referenced schema, while `import` only
+/// merges the rules needed to define the names referenced by the other merged
+/// rules. Names defined by the root schema or by included schemas take
+/// precedence over those of imported schemas. Each schema is loaded once no
+/// matter how many times it's referenced, so schemas can reference each other.
+///
+/// The rules of the root schema come first, so its first type rule remains the
+/// root type. Defining a rule in more than one schema is an error unless all
+/// but the first definition add choices to it via `/=` or `//=`.
+///
+/// # Example
+///
+/// ```
+/// use cddl::{loader::load_schema, validation::Validator};
+/// use std::collections::HashMap;
+///
+/// let mut schemas = HashMap::new();
+/// schemas.insert("message", ";# include common\nmessage = { id: id, body: tstr }");
+/// schemas.insert("common", "id = uint");
+///
+/// let schema = load_schema(&schemas, "message").unwrap();
+///
+/// let json = serde_json::json!({ "id": 1, "body": "hello" });
+/// assert!(schema.cddl().validate(&json).is_ok());
+/// ```
+pub fn load_schema<L: CddlLoader + ?Sized>(loader: &L, name: &str) -> Result<CddlSchema, Error> {
+  let root = loader.load(name, None).map_err(|message| Error::Load {
+    name: name.to_string(),
+    importer: None,
+    message,
+  })?;
+
+  resolve(
+    loader,
+    Document {
+      id: Some(root.id),
+      text: root.text,
+      included: true,
+    },
+  )
+}
+
+/// Merges the rules of the given CDDL with those of every schema it references
+/// via directives. See [`load_schema`](fn.load_schema.html) for details.
+pub fn schema_from_str<L: CddlLoader + ?Sized>(
+  input: &str,
+  loader: &L,
+) -> Result<CddlSchema, Error> {
+  resolve(
+    loader,
+    Document {
+      id: None,
+      text: input.to_string(),
+      included: true,
+    },
+  )
+}
+
+struct Document {
+  // `None` for CDDL given as a string
+  id: Option<String>,
+  text: String,
+  // Whether every rule is merged, as opposed to only those that are referenced
+  included: bool,
+}
+
+impl Document {
+  fn name(&self) -> &str {
+    match &self.id {
+      Some(id) => id,
+      None => "<input>",
+    }
+  }
+}
+
+fn resolve<L: CddlLoader + ?Sized>(loader: &L, root: Document) -> Result<CddlSchema, Error> {
+  let documents = load_documents(loader, root)?;
+
+  let cddls = documents
+    .iter()
+    .map(|d| {
+      cddl_from_str(&mut Lexer::new(&d.text), &d.text, false).map_err(|message| Error::Parse {
+        id: d.name().to_string(),
+        message,
+      })
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+
+  let selected = select_rules(&documents, &cddls);
+
+  // Definitions are checked in document order so that conflicts are reported
+  // deterministically
+  let mut definitions: HashMap<&str, usize> = HashMap::new();
+  for (doc, rule) in selected.iter() {
+    let rule = &cddls[*doc].rules[*rule];
+    if rule.is_choice_alternate() {
+      continue;
+    }
+
+    match definitions.get(rule.name_ident()) {
+      Some(first) if first != doc => {
+        return Err(Error::Conflict {
+          rule: rule.name_ident().to_string(),
+          first: documents[*first].name().to_string(),
+          second: documents[*doc].name().to_string(),
+        })
+      }
+      Some(_) => (),
+      None => {
+        definitions.insert(rule.name_ident(), *doc);
+      }
+    }
+  }
+
+  let merged = selected
+    .iter()
+    .map(|(doc, rule)| {
+      let (start, end, _) = cddls[*doc].rules[*rule].span();
+      &documents[*doc].text[start..end]
+    })
+    .collect::<Vec<_>>()
+    .join("\n\n");
+
+  CddlSchema::new(merged).map_err(|message| Error::Parse {
+    id: documents[0].name().to_string(),
+    message,
+  })
+}
+
+// Loads the root document followed by every document it references, directly
+// or indirectly
+fn load_documents<L: CddlLoader + ?Sized>(
+  loader: &L,
+  root: Document,
+) -> Result<Vec<Document>, Error> {
+  let mut documents = vec![root];
+
+  let mut idx = 0;
+  while idx < documents.len() {
+    let importer = documents[idx].id.clone();
+    let references = directives(&documents[idx].text);
+
+    for (included, name) in references {
+      let source = loader
+        .load(&name, importer.as_ref().map(|i| &i[..]))
+        .map_err(|message| Error::Load {
+          name,
+          importer: importer.clone(),
+          message,
+        })?;
+
+      match documents
+        .iter_mut()
+        .find(|d| d.id.as_ref() == Some(&source.id))
+      {
+        Some(document) => document.included |= included,
+        None => documents.push(Document {
+          id: Some(source.id),
+          text: source.text,
+          included,
+        }),
+      }
+    }
+
+    idx += 1;
+  }
+
+  Ok(documents)
+}
+
+// Returns whether each directive is an include, along with the name it
+// references
+pub(crate) fn directives(text: &str) -> Vec<(bool, String)> {
+  text
+    .lines()
+    .filter_map(|line| {
+      let line = line.trim_start();
+      if !line.starts_with(";#") {
+        return None;
+      }
+
+      let mut words = line[2..].split_whitespace();
+      match (words.next(), words.next()) {
+        (Some("include"), Some(name)) => Some((true, name.to_string())),
+        (Some("import"), Some(name)) => Some((false, name.to_string())),
+        _ => None,
+      }
+    })
+    .collect()
+}
+
+// Returns the document and rule indices of the rules to merge, in order.
+// Every rule of an included document is merged, along with the rules of
+// imported documents that are reachable from them.
+fn select_rules<'a>(documents: &[Document], cddls: &[CDDL<'a>]) -> Vec<(usize, usize)> {
+  let mut defined: HashMap<&'a str, Vec<(usize, usize)>> = HashMap::new();
+  let mut selected = Vec::with_capacity(cddls.len());
+  let mut pending = Vec::new();
+
+  for (doc, cddl) in cddls.iter().enumerate() {
+    selected.push(vec![documents[doc].included; cddl.rules.len()]);
+
+    for (idx, rule) in cddl.rules.iter().enumerate() {
+      defined
+        .entry(rule.name_ident())
+        .or_default()
+        .push((doc, idx));
+
+      if documents[doc].included {
+        pending.push((doc, idx));
+      }
+    }
+  }
+
+  while let Some((doc, idx)) = pending.pop() {
+    let mut references = References::default();
+    references.visit_rule(&cddls[doc].rules[idx]);
+
+    for name in references.names {
+      let rules = match defined.get(name) {
+        Some(rules) => rules,
+        None => continue,
+      };
+
+      if rules.iter().any(|(doc, _)| documents[*doc].included) {
+        continue;
+      }
+
+      for (doc, idx) in rules.iter() {
+        if !selected[*doc][*idx] {
+          selected[*doc][*idx] = true;
+          pending.push((*doc, *idx));
+        }
+      }
+    }
+  }
+
+  selected
+    .iter()
+    .enumerate()
+    .flat_map(|(doc, rules)| {
+      rules
+        .iter()
+        .enumerate()
+        .filter(|(_, selected)| **selected)
+        .map(move |(idx, _)| (doc, idx))
+    })
+    .collect()
+}
+
+#[cfg(test)]
+#[cfg(feature = "json-validation")]
+mod tests {
+  use super::*;
+  use crate::validation::Validator;
+  use serde_json::json;
+
+  fn rule_names(schema: &CddlSchema) -> Vec<String> {
+    schema.cddl().rules.iter().map(|r| r.name()).collect()
+  }
+
+  #[test]
+  fn verify_include() -> Result<(), Error> {
+    let mut schemas = HashMap::new();
+    schemas.insert(
+      "message",
+      ";# include common\nmessage = { id: id, sent: time }",
+    );
+    schemas.insert("common", "id = uint\nunused = tstr");
+
+    let schema = load_schema(&schemas, "message")?;
+    assert_eq!(rule_names(&schema), ["message", "id", "unused"]);
+
+    assert!(schema
+      .cddl()
+      .validate(&json!({ "id": 1, "sent": 1_600_000_000 }))
+      .is_ok());
+
+    Ok(())
+  }
+
+  #[test]
+  fn verify_import_merges_referenced_rules() -> Result<(), Error> {
+    let mut schemas = HashMap::new();
+    schemas.insert(
+      "common",
+      "id = uint\nlabel<T> = { name: T, id: id }\nunused = tstr\nT = bool",
+    );
+
+    let schema = schema_from_str(
+      ";# import common\nmessage = { label: label<tstr> }",
+      &schemas,
+    )?;
+    assert_eq!(rule_names(&schema), ["message", "id", "label"]);
+
+    Ok(())
+  }
+
+  #[test]
+  fn verify_import_defers_to_included_rules() -> Result<(), Error> {
+    let mut schemas = HashMap::new();
+    schemas.insert("common", "id = uint\nname = tstr");
+
+    let schema = schema_from_str(
+      ";# import common\nmessage = { id: id, name: name }\nid = tstr",
+      &schemas,
+    )?;
+    assert_eq!(rule_names(&schema), ["message", "id", "name"]);
+
+    Ok(())
+  }
+
+  #[test]
+  fn verify_cyclic_includes() -> Result<(), Error> {
+    let mut schemas = HashMap::new();
+    schemas.insert("a", ";# include b\na = [* b]");
+    schemas.insert("b", ";# include a\nb = a / uint\nb /= tstr");
+
+    let schema = load_schema(&schemas, "a")?;
+    assert_eq!(rule_names(&schema), ["a", "b", "b"]);
+
+    Ok(())
+  }
+
+  #[test]
+  fn verify_conflicting_rules() {
+    let mut schemas = HashMap::new();
+    schemas.insert("common", "id = uint");
+
+    assert_eq!(
+      schema_from_str(
+        ";# include common\nmessage = { id: id }\nid = tstr",
+        &schemas
+      )
+      .unwrap_err(),
+      Error::Conflict {
+        rule: "id".into(),
+        first: "<input>".into(),
+        second: "common".into(),
+      }
+    );
+
+    // Adding choices to a rule defined elsewhere is fine
+    assert!(schema_from_str(
+      ";# include common\nmessage = { id: id }\nid /= tstr",
+      &schemas
+    )
+    .is_ok());
+  }
+
+  #[test]
+  fn verify_load_errors() {
+    let mut schemas = HashMap::new();
+    schemas.insert("message", ";# include common\nmessage = { id: id }");
+    schemas.insert("invalid", "invalid = ");
+
+    match load_schema(&schemas, "message") {
+      Err(Error::Load { name, importer, .. }) => {
+        assert_eq!(name, "common");
+        assert_eq!(importer.as_deref(), Some("message"));
+      }
+      result => panic!("unexpected result: {:?}", result),
+    }
+
+    match load_schema(&schemas, "invalid") {
+      Err(Error::Parse { id, .. }) => assert_eq!(id, "invalid"),
+      result => panic!("unexpected result: {:?}", result),
+    }
+  }
+
+  #[test]
+  fn verify_file_loader() -> Result<(), Error> {
+    let loader = FileLoader::new().search_path("tests/data/loader/shared");
+    let schema = load_schema(&loader, "tests/data/loader/reputation")?;
+
+    assert_eq!(rule_names(&schema), ["reputation", "reputon", "rating"]);
+
+    let json =
+      json!({ "application": "conchometry", "reputons": [{ "rater": "Ninja", "rating": 0.5 }] });
+    assert!(schema.cddl().validate(&json).is_ok());
+
+    Ok(())
+  }
+}
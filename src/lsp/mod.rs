@@ -0,0 +1,598 @@
+mod server;
+
+pub use server::run;
+
+use crate::{
+  ast::*,
+  lexer::{Lexer, Position as LexerPosition},
+  lint::{lint, Severity},
+  parser::{self, Parser},
+  prelude::{is_prelude_type, with_prelude, PRELUDE},
+  token::Token,
+  visitor::*,
+};
+
+/// Zero-based line and UTF-16 code unit offset into a document, as used by the
+/// Language Server Protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Position {
+  /// Line number
+  pub line: usize,
+  /// Offset into the line in UTF-16 code units
+  pub character: usize,
+}
+
+/// Range of a document between two positions, exclusive of the end
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Range {
+  /// Start position
+  pub start: Position,
+  /// End position
+  pub end: Position,
+}
+
+/// Issue found in a document, either a syntax error, a semantic issue reported
+/// by the compiler or a lint
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+  /// Range of the document the issue applies to
+  pub range: Range,
+  /// Severity of the issue
+  pub severity: Severity,
+  /// ID of the lint, if the issue is one
+  pub code: Option<&'static str>,
+  /// Description of the issue
+  pub message: String,
+}
+
+/// Replacement of a range of a document
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEdit {
+  /// Range to replace
+  pub range: Range,
+  /// Text to replace the range with
+  pub new_text: String,
+}
+
+/// Returns the syntax errors of a document or, if it parses, the diagnostics
+/// of the compiler and the linter. The parser recovers from syntax errors at
+/// the start of the next rule, so each malformed rule is reported.
+///
+/// # Example
+///
+/// ```
+/// use cddl::{lint::Severity, lsp::diagnostics};
+///
+/// let diagnostics = diagnostics("person = { name: tstr, address: addr }");
+/// assert_eq!(diagnostics[0].severity, Severity::Error);
+/// assert_eq!(diagnostics[0].range.start.character, 32);
+/// ```
+pub fn diagnostics(input: &str) -> Vec<Diagnostic> {
+  let (cddl, errors) = parse(input);
+
+  // Rules skipped due to syntax errors would make the compiler report spurious
+  // undefined references
+  if !errors.is_empty() {
+    return errors
+      .into_iter()
+      .map(|(position, message)| Diagnostic {
+        range: range_of(input, position.range.0, position.range.1),
+        severity: Severity::Error,
+        code: None,
+        message,
+      })
+      .collect();
+  }
+
+  let compiled = match cddl.compile() {
+    Ok(diagnostics) | Err(diagnostics) => diagnostics,
+  };
+
+  compiled
+    .into_iter()
+    .map(|d| Diagnostic {
+      range: range_of(input, d.span.0, d.span.1),
+      severity: if d.is_error() {
+        Severity::Error
+      } else {
+        Severity::Warning
+      },
+      code: None,
+      message: d.to_string(),
+    })
+    .chain(lint(&cddl).into_iter().map(|l| Diagnostic {
+      range: range_of(input, l.span.0, l.span.1),
+      severity: l.severity,
+      code: Some(l.kind.id()),
+      message: l.to_string(),
+    }))
+    .collect()
+}
+
+/// Returns the ranges of the names of the rules defining the name at the given
+/// position, including those extending it via `/=` or `//=`. Rules with syntax
+/// errors are skipped.
+///
+/// # Example
+///
+/// ```
+/// use cddl::lsp::{definition, Position};
+///
+/// let input = "person = { address: addr }\naddr = tstr";
+///
+/// let ranges = definition(input, Position { line: 0, character: 21 });
+/// assert_eq!(ranges[0].start, Position { line: 1, character: 0 });
+/// ```
+pub fn definition(input: &str, position: Position) -> Vec<Range> {
+  let (cddl, _) = parse(input);
+
+  let symbols = Symbols::collect(&cddl);
+  match symbols.name_at(offset_of(input, position)) {
+    Some(name) => symbols
+      .rule_names
+      .iter()
+      .filter(|(n, _)| *n == name)
+      .map(|(_, span)| range_of(input, span.0, span.1))
+      .collect(),
+    None => Vec::new(),
+  }
+}
+
+/// Returns the definitions of the rule named at the given position, followed
+/// by those of the rules it's an alias for, if any. Types of the standard
+/// prelude are resolved against its definitions.
+///
+/// # Example
+///
+/// ```
+/// use cddl::lsp::{hover, Position};
+///
+/// let input = "name = text";
+///
+/// let contents = hover(input, Position { line: 0, character: 8 }).unwrap();
+/// assert_eq!(contents, "text = tstr\ntstr = #3");
+/// ```
+pub fn hover(input: &str, position: Position) -> Option<String> {
+  let (cddl, _) = parse(input);
+  let name = Symbols::collect(&cddl)
+    .name_at(offset_of(input, position))?
+    .to_string();
+
+  let mut definitions = Vec::new();
+  let mut seen = Vec::new();
+  let mut next = Some(name);
+
+  // Aliases are followed until a rule that isn't one, guarding against cycles
+  while let Some(name) = next.take() {
+    if seen.contains(&name) {
+      break;
+    }
+
+    let (rules, alias) = if cddl.rules.iter().any(|r| rule_ident(r).to_string() == name) {
+      definitions_of(&cddl, input, &name)
+    } else if is_prelude_type(&name) {
+      with_prelude(|p| definitions_of(p, PRELUDE, &name))
+    } else {
+      (Vec::new(), None)
+    };
+
+    definitions.extend(rules);
+    seen.push(name);
+    next = alias;
+  }
+
+  if definitions.is_empty() {
+    return None;
+  }
+
+  Some(definitions.join("\n"))
+}
+
+/// Returns the edits renaming the rule named at the given position, along
+/// with every reference to it. Fails if there's no rule at the position or the
+/// new name isn't a valid rule name.
+///
+/// # Example
+///
+/// ```
+/// use cddl::lsp::{rename, Position};
+///
+/// let input = "person = { address: addr }\naddr = tstr";
+///
+/// let edits = rename(input, Position { line: 1, character: 0 }, "address").unwrap();
+/// assert_eq!(edits.len(), 2);
+/// ```
+pub fn rename(input: &str, position: Position, new_name: &str) -> Result<Vec<TextEdit>, String> {
+  // References in rules skipped due to syntax errors would be left behind
+  let (cddl, errors) = parse(input);
+  if !errors.is_empty() {
+    return Err("document has syntax errors".to_string());
+  }
+
+  let symbols = Symbols::collect(&cddl);
+
+  let name = symbols
+    .name_at(offset_of(input, position))
+    .filter(|name| symbols.rule_names.iter().any(|(n, _)| n == name))
+    .ok_or_else(|| "no rule defined at the given position".to_string())?;
+
+  if !is_rule_name(new_name) {
+    return Err(format!("'{}' isn't a valid rule name", new_name));
+  }
+
+  Ok(
+    symbols
+      .rule_names
+      .iter()
+      .chain(symbols.references.iter())
+      .filter(|(n, _)| *n == name)
+      .map(|(_, span)| TextEdit {
+        range: range_of(input, span.0, span.1),
+        new_text: new_name.to_string(),
+      })
+      .collect(),
+  )
+}
+
+// Parses as much of a document as possible, returning the rules that could be
+// parsed along with the position and message of each syntax error
+fn parse(input: &str) -> (CDDL<'_>, Vec<(LexerPosition, String)>) {
+  let mut lexer = Lexer::new(input);
+
+  let e = match Parser::new(lexer.iter(), input) {
+    Ok(mut p) => {
+      let cddl = p.parse_cddl_recovering();
+      let errors = p
+        .errors
+        .iter()
+        .map(|pe| (pe.position(), pe.message().to_string()))
+        .collect();
+
+      return (cddl, errors);
+    }
+    Err(e) => e,
+  };
+
+  let error = match e {
+    parser::Error::LEXER(le) => (le.position(), le.message()),
+    e => (
+      LexerPosition {
+        line: 1,
+        column: 1,
+        range: (0, input.len()),
+        index: 0,
+      },
+      e.to_string(),
+    ),
+  };
+
+  (CDDL::default(), vec![error])
+}
+
+// Returns the source of each rule with the given name and, if the first is a
+// bare reference to another type, the name of that type
+fn definitions_of(cddl: &CDDL, source: &str, name: &str) -> (Vec<String>, Option<String>) {
+  let rules = cddl
+    .rules
+    .iter()
+    .filter(|r| rule_ident(r).to_string() == name)
+    .collect::<Vec<_>>();
+
+  let alias = rules.first().and_then(|r| match r {
+    Rule::Type { rule, .. } if rule.value.type_choices.len() == 1 => {
+      match &rule.value.type_choices[0] {
+        Type1 {
+          type2:
+            Type2::Typename {
+              ident,
+              generic_arg: None,
+              ..
+            },
+          operator: None,
+          ..
+        } => Some(ident.to_string()),
+        _ => None,
+      }
+    }
+    _ => None,
+  });
+
+  (
+    rules
+      .iter()
+      .map(|r| {
+        let span = r.span();
+        source[span.0..span.1].to_string()
+      })
+      .collect(),
+    alias,
+  )
+}
+
+fn rule_ident<'a, 'b>(rule: &'b Rule<'a>) -> &'b Identifier<'a> {
+  match rule {
+    Rule::Type { rule, .. } => &rule.name,
+    Rule::Group { rule, .. } => &rule.name,
+  }
+}
+
+// Returns whether a name lexes as a single identifier that isn't reserved by
+// the standard prelude
+fn is_rule_name(name: &str) -> bool {
+  let mut lexer = Lexer::new(name);
+  let mut tokens = lexer.iter();
+
+  if let (Some(Ok((_, Token::IDENT(_)))), Some(Ok((_, Token::EOF)))) =
+    (tokens.next(), tokens.next())
+  {
+    return true;
+  }
+
+  false
+}
+
+// Names of the rules of a definition and of the references to them, along
+// with their spans. References to generic parameters aren't included.
+struct Symbols {
+  rule_names: Vec<(String, Span)>,
+  references: Vec<(String, Span)>,
+  generic_params: Vec<String>,
+}
+
+impl Symbols {
+  fn collect(cddl: &CDDL) -> Symbols {
+    let mut symbols = Symbols {
+      rule_names: Vec::new(),
+      references: Vec::new(),
+      generic_params: Vec::new(),
+    };
+    walk_cddl(&mut symbols, cddl);
+
+    symbols
+  }
+
+  // Returns the name of the rule or reference at the given byte offset
+  fn name_at(&self, offset: usize) -> Option<&str> {
+    self
+      .rule_names
+      .iter()
+      .chain(self.references.iter())
+      .find(|(_, span)| span.0 <= offset && offset <= span.1)
+      .map(|(name, _)| name.as_str())
+  }
+}
+
+impl<'a> Visitor<'a> for Symbols {
+  fn visit_rule(&mut self, rule: &Rule<'a>) {
+    self.generic_params.clear();
+
+    walk_rule(self, rule);
+  }
+
+  fn visit_rule_name(&mut self, ident: &Identifier<'a>) {
+    self.rule_names.push((ident.to_string(), ident.span));
+  }
+
+  fn visit_generic_param(&mut self, ident: &Identifier<'a>) {
+    self.generic_params.push(ident.to_string());
+  }
+
+  fn visit_typename(&mut self, ident: &Identifier<'a>) {
+    let name = ident.to_string();
+
+    if !self.generic_params.contains(&name) {
+      self.references.push((name, ident.span));
+    }
+  }
+}
+
+// Returns the byte offset of a position, clamped to the end of its line
+fn offset_of(input: &str, position: Position) -> usize {
+  let mut offset = 0;
+
+  for (idx, line) in input.split('\n').enumerate() {
+    if idx == position.line {
+      let mut units = 0;
+
+      for (byte_idx, c) in line.char_indices() {
+        if units >= position.character {
+          return offset + byte_idx;
+        }
+
+        units += c.len_utf16();
+      }
+
+      return offset + line.len();
+    }
+
+    offset += line.len() + 1;
+  }
+
+  input.len()
+}
+
+// Returns the position of a byte offset
+fn position_of(input: &str, offset: usize) -> Position {
+  let mut position = Position::default();
+
+  for (byte_idx, c) in input.char_indices() {
+    if byte_idx >= offset {
+      break;
+    }
+
+    if c == '\n' {
+      position.line += 1;
+      position.character = 0;
+    } else {
+      position.character += c.len_utf16();
+    }
+  }
+
+  position
+}
+
+fn range_of(input: &str, start: usize, end: usize) -> Range {
+  Range {
+    start: position_of(input, start),
+    end: position_of(input, end),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn verify_diagnostics() {
+    assert_eq!(
+      diagnostics("a = [\n  b\nc = { d: }\ne = int")
+        .iter()
+        .map(|d| (d.range.start.line, d.severity, d.code))
+        .collect::<Vec<_>>(),
+      vec![(2, Severity::Error, None), (2, Severity::Error, None)]
+    );
+
+    let input = "a = { b: c }\nc = 1 / 1\nd = int";
+    let diagnostics = diagnostics(input);
+
+    assert_eq!(
+      diagnostics
+        .iter()
+        .map(|d| (d.range.start, d.severity, d.code))
+        .collect::<Vec<_>>(),
+      vec![
+        (
+          Position {
+            line: 2,
+            character: 0
+          },
+          Severity::Warning,
+          None
+        ),
+        (
+          Position {
+            line: 1,
+            character: 8
+          },
+          Severity::Warning,
+          Some("unreachable-choice")
+        ),
+      ]
+    );
+  }
+
+  #[test]
+  fn verify_definition_and_rename() {
+    let input = "a = [* $$g, c<int>]\n$$g //= (x: c<tstr>)\n$$g //= (y: int)\nc<t> = [t]";
+
+    assert_eq!(
+      definition(
+        input,
+        Position {
+          line: 0,
+          character: 8
+        }
+      ),
+      vec![range_of(input, 20, 23), range_of(input, 41, 44)]
+    );
+
+    // Generic parameters aren't rules
+    assert_eq!(
+      definition(
+        input,
+        Position {
+          line: 3,
+          character: 8
+        }
+      ),
+      vec![]
+    );
+
+    let edits = rename(
+      input,
+      Position {
+        line: 3,
+        character: 0,
+      },
+      "list",
+    )
+    .unwrap();
+    assert_eq!(
+      edits.iter().map(|e| e.range.start).collect::<Vec<_>>(),
+      vec![
+        Position {
+          line: 3,
+          character: 0
+        },
+        Position {
+          line: 0,
+          character: 12
+        },
+        Position {
+          line: 1,
+          character: 12
+        },
+      ]
+    );
+
+    assert!(rename(
+      input,
+      Position {
+        line: 3,
+        character: 0,
+      },
+      "tstr",
+    )
+    .is_err());
+    assert!(rename(
+      input,
+      Position {
+        line: 0,
+        character: 14,
+      },
+      "number",
+    )
+    .is_err());
+  }
+
+  #[test]
+  fn verify_hover() {
+    let input = "a = b\nb = bytes / int\nc = a";
+
+    assert_eq!(
+      hover(
+        input,
+        Position {
+          line: 2,
+          character: 4
+        }
+      ),
+      Some("a = b\nb = bytes / int".into())
+    );
+    assert_eq!(
+      hover(
+        input,
+        Position {
+          line: 1,
+          character: 5
+        }
+      ),
+      Some("bytes = bstr\nbstr = #2".into())
+    );
+  }
+
+  #[test]
+  fn verify_utf16_positions() {
+    let input = "a = \"\u{1F600}\" / b\nb = int";
+
+    let position = position_of(input, 11);
+    assert_eq!(
+      position,
+      Position {
+        line: 0,
+        character: 9
+      }
+    );
+    assert_eq!(offset_of(input, position), 11);
+  }
+}
@@ -0,0 +1,392 @@
+use super::{definition, diagnostics, hover, rename, Position, Range};
+use crate::lint::Severity;
+use serde_json::{json, Map, Value};
+use std::{
+  collections::HashMap,
+  io::{self, BufRead, Write},
+};
+
+// JSON-RPC error codes
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const REQUEST_FAILED: i64 = -32803;
+
+/// Runs a Language Server Protocol server exchanging messages over the given
+/// reader and writer, typically stdin and stdout, until the client sends the
+/// `exit` notification or closes the reader.
+///
+/// Documents are synchronized in full. Diagnostics are published when a
+/// document is opened or saved, and go-to-definition, hover and rename
+/// requests are answered for rule names and references.
+pub fn run<R: BufRead, W: Write>(mut reader: R, mut writer: W) -> io::Result<()> {
+  let mut documents = HashMap::new();
+
+  while let Some(message) = read_message(&mut reader)? {
+    let method = match message.get("method").and_then(Value::as_str) {
+      Some(method) => method,
+      // Responses to requests sent by the server aren't expected
+      None => continue,
+    };
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+    if method == "exit" {
+      return Ok(());
+    }
+
+    let response = handle(&mut documents, method, &params, &mut writer)?;
+
+    // Notifications don't have an ID and never get a response
+    if let Some(id) = message.get("id") {
+      let message = match response {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, error)) => json!({
+          "jsonrpc": "2.0",
+          "id": id,
+          "error": { "code": code, "message": error },
+        }),
+      };
+
+      write_message(&mut writer, &message)?;
+    }
+  }
+
+  Ok(())
+}
+
+fn handle<W: Write>(
+  documents: &mut HashMap<String, String>,
+  method: &str,
+  params: &Value,
+  writer: &mut W,
+) -> io::Result<Result<Value, (i64, String)>> {
+  let uri = params
+    .pointer("/textDocument/uri")
+    .and_then(Value::as_str)
+    .map(str::to_string);
+
+  let result = match (method, uri) {
+    ("initialize", _) => json!({
+      "capabilities": {
+        "textDocumentSync": {
+          "openClose": true,
+          "change": 1,
+          "save": { "includeText": true },
+        },
+        "definitionProvider": true,
+        "hoverProvider": true,
+        "renameProvider": true,
+      },
+      "serverInfo": { "name": "cddl", "version": env!("CARGO_PKG_VERSION") },
+    }),
+    ("initialized", _) | ("shutdown", _) => Value::Null,
+    ("textDocument/didOpen", Some(uri)) => {
+      if let Some(text) = params.pointer("/textDocument/text").and_then(Value::as_str) {
+        documents.insert(uri.clone(), text.to_string());
+      }
+
+      publish_diagnostics(writer, &uri, documents.get(&uri))?;
+      Value::Null
+    }
+    ("textDocument/didChange", Some(uri)) => {
+      // Only full synchronization is supported, so the last change holds the
+      // whole document
+      let text = params
+        .get("contentChanges")
+        .and_then(Value::as_array)
+        .and_then(|changes| changes.last())
+        .and_then(|change| change.get("text"))
+        .and_then(Value::as_str);
+
+      if let Some(text) = text {
+        documents.insert(uri, text.to_string());
+      }
+
+      Value::Null
+    }
+    ("textDocument/didSave", Some(uri)) => {
+      if let Some(text) = params.get("text").and_then(Value::as_str) {
+        documents.insert(uri.clone(), text.to_string());
+      }
+
+      publish_diagnostics(writer, &uri, documents.get(&uri))?;
+      Value::Null
+    }
+    ("textDocument/didClose", Some(uri)) => {
+      documents.remove(&uri);
+
+      publish_diagnostics(writer, &uri, None)?;
+      Value::Null
+    }
+    ("textDocument/definition", Some(uri)) => {
+      let (text, position) = match document_position(documents, &uri, params) {
+        Ok(dp) => dp,
+        Err(e) => return Ok(Err(e)),
+      };
+
+      Value::Array(
+        definition(text, position)
+          .into_iter()
+          .map(|range| json!({ "uri": uri, "range": range_to_json(range) }))
+          .collect(),
+      )
+    }
+    ("textDocument/hover", Some(uri)) => {
+      let (text, position) = match document_position(documents, &uri, params) {
+        Ok(dp) => dp,
+        Err(e) => return Ok(Err(e)),
+      };
+
+      match hover(text, position) {
+        Some(contents) => json!({
+          "contents": {
+            "kind": "markdown",
+            "value": format!("```cddl\n{}\n```", contents),
+          },
+        }),
+        None => Value::Null,
+      }
+    }
+    ("textDocument/rename", Some(uri)) => {
+      let (text, position) = match document_position(documents, &uri, params) {
+        Ok(dp) => dp,
+        Err(e) => return Ok(Err(e)),
+      };
+      let new_name = match params.get("newName").and_then(Value::as_str) {
+        Some(new_name) => new_name,
+        None => return Ok(Err((INVALID_PARAMS, "missing new name".to_string()))),
+      };
+
+      match rename(text, position, new_name) {
+        Ok(edits) => {
+          let edits = edits
+            .into_iter()
+            .map(|e| json!({ "range": range_to_json(e.range), "newText": e.new_text }))
+            .collect::<Vec<_>>();
+
+          let mut changes = Map::new();
+          changes.insert(uri, Value::Array(edits));
+
+          json!({ "changes": changes })
+        }
+        Err(e) => return Ok(Err((REQUEST_FAILED, e))),
+      }
+    }
+    (method, _) => {
+      return Ok(Err((
+        METHOD_NOT_FOUND,
+        format!("unsupported method '{}'", method),
+      )))
+    }
+  };
+
+  Ok(Ok(result))
+}
+
+// Returns the text of the document a request applies to and the position
+// given by the request
+fn document_position<'a>(
+  documents: &'a HashMap<String, String>,
+  uri: &str,
+  params: &Value,
+) -> Result<(&'a str, Position), (i64, String)> {
+  let text = documents
+    .get(uri)
+    .ok_or_else(|| (REQUEST_FAILED, format!("unknown document '{}'", uri)))?;
+
+  let position = params
+    .get("position")
+    .and_then(position_from_json)
+    .ok_or_else(|| (INVALID_PARAMS, "missing position".to_string()))?;
+
+  Ok((text, position))
+}
+
+// Publishes the diagnostics of a document, clearing them if it's been closed
+fn publish_diagnostics<W: Write>(
+  writer: &mut W,
+  uri: &str,
+  text: Option<&String>,
+) -> io::Result<()> {
+  let diagnostics = text
+    .map(|text| diagnostics(text))
+    .unwrap_or_default()
+    .into_iter()
+    .map(|d| {
+      let mut diagnostic = json!({
+        "range": range_to_json(d.range),
+        "severity": match d.severity {
+          Severity::Error => 1,
+          Severity::Warning => 2,
+          Severity::Note => 3,
+        },
+        "source": "cddl",
+        "message": d.message,
+      });
+
+      if let Some(code) = d.code {
+        diagnostic["code"] = json!(code);
+      }
+
+      diagnostic
+    })
+    .collect::<Vec<_>>();
+
+  write_message(
+    writer,
+    &json!({
+      "jsonrpc": "2.0",
+      "method": "textDocument/publishDiagnostics",
+      "params": { "uri": uri, "diagnostics": diagnostics },
+    }),
+  )
+}
+
+fn position_from_json(position: &Value) -> Option<Position> {
+  Some(Position {
+    line: position.get("line")?.as_u64()? as usize,
+    character: position.get("character")?.as_u64()? as usize,
+  })
+}
+
+fn range_to_json(range: Range) -> Value {
+  json!({
+    "start": { "line": range.start.line, "character": range.start.character },
+    "end": { "line": range.end.line, "character": range.end.character },
+  })
+}
+
+// Reads a message framed by a Content-Length header, returning None once the
+// reader is exhausted
+fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Value>> {
+  let mut content_length = None;
+
+  loop {
+    let mut header = String::new();
+    if reader.read_line(&mut header)? == 0 {
+      return Ok(None);
+    }
+
+    let header = header.trim_end();
+    if header.is_empty() {
+      break;
+    }
+
+    let mut parts = header.splitn(2, ':');
+    if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+      if name.eq_ignore_ascii_case("Content-Length") {
+        content_length = value.trim().parse::<usize>().ok();
+      }
+    }
+  }
+
+  let content_length = content_length
+    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header"))?;
+
+  let mut content = vec![0; content_length];
+  reader.read_exact(&mut content)?;
+
+  serde_json::from_slice(&content)
+    .map(Some)
+    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_message<W: Write>(writer: &mut W, message: &Value) -> io::Result<()> {
+  let content = message.to_string();
+
+  write!(
+    writer,
+    "Content-Length: {}\r\n\r\n{}",
+    content.len(),
+    content
+  )?;
+  writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn frame(message: Value) -> String {
+    let content = message.to_string();
+
+    format!("Content-Length: {}\r\n\r\n{}", content.len(), content)
+  }
+
+  #[test]
+  fn verify_session() {
+    let input = [
+      frame(json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} })),
+      frame(json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": {
+          "textDocument": {
+            "uri": "file:///a.cddl",
+            "languageId": "cddl",
+            "version": 1,
+            "text": "a = { b: c }\nc = tstr / tstr",
+          },
+        },
+      })),
+      frame(json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "textDocument/definition",
+        "params": {
+          "textDocument": { "uri": "file:///a.cddl" },
+          "position": { "line": 0, "character": 9 },
+        },
+      })),
+      frame(json!({
+        "jsonrpc": "2.0",
+        "id": 3,
+        "method": "textDocument/rename",
+        "params": {
+          "textDocument": { "uri": "file:///a.cddl" },
+          "position": { "line": 1, "character": 0 },
+          "newName": "d",
+        },
+      })),
+      frame(json!({ "jsonrpc": "2.0", "id": 4, "method": "shutdown" })),
+      frame(json!({ "jsonrpc": "2.0", "method": "exit" })),
+    ]
+    .concat();
+
+    let mut output = Vec::new();
+    run(input.as_bytes(), &mut output).unwrap();
+
+    let mut reader = &output[..];
+    let mut messages = Vec::new();
+    while let Some(message) = read_message(&mut reader).unwrap() {
+      messages.push(message);
+    }
+
+    assert_eq!(messages.len(), 5);
+    assert_eq!(
+      messages[0]["result"]["capabilities"]["renameProvider"],
+      true
+    );
+
+    let diagnostics = &messages[1]["params"]["diagnostics"];
+    assert_eq!(diagnostics[0]["code"], "unreachable-choice");
+    assert_eq!(diagnostics[0]["severity"], 2);
+
+    assert_eq!(
+      messages[2]["result"],
+      json!([{
+        "uri": "file:///a.cddl",
+        "range": {
+          "start": { "line": 1, "character": 0 },
+          "end": { "line": 1, "character": 1 },
+        },
+      }])
+    );
+    assert_eq!(
+      messages[3]["result"]["changes"]["file:///a.cddl"]
+        .as_array()
+        .map(Vec::len),
+      Some(2)
+    );
+    assert_eq!(messages[4]["result"], Value::Null);
+  }
+}
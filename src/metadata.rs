@@ -0,0 +1,284 @@
+use super::{
+  ast::{Rule, Span, Type2, CDDL},
+  prelude::is_prelude_type,
+  prune::References,
+  visitor::Visitor,
+};
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// Kind of a rule, as told apart by tooling
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RuleKind {
+  /// Type rule whose type is a map, e.g. `person = { name: tstr }`
+  Map,
+  /// Type rule whose type is an array, e.g. `point = [x: int, y: int]`
+  Array,
+  /// Type rule with more than one type choice, e.g. `id = uint / tstr`,
+  /// including choices added via `/=`
+  TypeChoice,
+  /// Any other type rule, e.g. `id = uint` or `port = 1..65535`
+  Type,
+  /// Group rule, e.g. `header = (id: uint, ? ttl: uint)`
+  Group,
+}
+
+/// Reference to a named rule of a definition, through which its kind, the
+/// rules it refers to and the rules referring to it can be retrieved. Covers
+/// every definition of the name, including those extending the first with
+/// additional choices via `/=` or `//=`.
+#[derive(Clone, Copy)]
+pub struct RuleRef<'c, 'a> {
+  cddl: &'c CDDL<'a>,
+  rule: &'c Rule<'a>,
+}
+
+impl<'a> CDDL<'a> {
+  /// Returns the distinct names of the rules in the order they're first
+  /// defined. As with `Rule::name`, socket names don't include their `$` or
+  /// `$$` prefix.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{lexer_from_str, parser::cddl_from_str};
+  ///
+  /// let input = "color = \"red\"\nshape = { color: color }\ncolor /= \"blue\"";
+  /// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+  ///
+  /// assert_eq!(cddl.rule_names(), ["color", "shape"]);
+  /// ```
+  pub fn rule_names(&self) -> Vec<&'a str> {
+    let mut names = Vec::new();
+
+    for rule in self.rules.iter() {
+      if !rule.is_choice_alternate() && !names.contains(&rule.name_ident()) {
+        names.push(rule.name_ident());
+      }
+    }
+
+    // Rules only defined via `/=` or `//=`, as sockets can be
+    for rule in self.rules.iter() {
+      if !names.contains(&rule.name_ident()) {
+        names.push(rule.name_ident());
+      }
+    }
+
+    names
+  }
+
+  /// Returns a reference to the rule with the given name for introspection,
+  /// or `None` if no rule has that name. Unlike `CDDL::rule`, which returns
+  /// the first definition as is, the reference spans every definition of the
+  /// name.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{lexer_from_str, metadata::RuleKind, parser::cddl_from_str};
+  ///
+  /// let input = r#"
+  ///   message = { id: id, ? error: error<tstr> }
+  ///   error<T> = { code: uint, message: T }
+  ///   id = uint / tstr
+  /// "#;
+  /// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+  ///
+  /// let message = cddl.rule_ref("message").unwrap();
+  /// assert_eq!(message.kind(), RuleKind::Map);
+  /// assert_eq!(message.references(), ["id", "error"]);
+  ///
+  /// let id = cddl.rule_ref("id").unwrap();
+  /// assert_eq!(id.kind(), RuleKind::TypeChoice);
+  /// assert_eq!(id.referrers(), ["message"]);
+  ///
+  /// assert_eq!(cddl.rule_ref("error").unwrap().generic_params(), ["T"]);
+  /// ```
+  pub fn rule_ref<'c>(&'c self, name: &str) -> Option<RuleRef<'c, 'a>> {
+    // The first definition that isn't an extension takes precedence, as it
+    // determines the kind of the rule
+    let rule = self
+      .rules_named(name)
+      .find(|r| !r.is_choice_alternate())
+      .or_else(|| self.rule(name))?;
+
+    Some(RuleRef { cddl: self, rule })
+  }
+}
+
+impl<'c, 'a> RuleRef<'c, 'a> {
+  /// Returns the name of the rule
+  pub fn name(&self) -> &'a str {
+    self.rule.name_ident()
+  }
+
+  /// Returns the kind of the rule
+  pub fn kind(&self) -> RuleKind {
+    let rule = match self.rule {
+      Rule::Type { rule, .. } => rule,
+      Rule::Group { .. } => return RuleKind::Group,
+    };
+
+    let choices = self
+      .definitions()
+      .map(|r| match r {
+        Rule::Type { rule, .. } => rule.value.type_choices.len(),
+        Rule::Group { .. } => 1,
+      })
+      .sum::<usize>();
+
+    if choices > 1 {
+      return RuleKind::TypeChoice;
+    }
+
+    match rule.value.type_choices.first() {
+      Some(t1) if t1.operator.is_none() => match t1.type2 {
+        Type2::Map { .. } => RuleKind::Map,
+        Type2::Array { .. } => RuleKind::Array,
+        _ => RuleKind::Type,
+      },
+      _ => RuleKind::Type,
+    }
+  }
+
+  /// Returns the first definition of the rule
+  pub fn rule(&self) -> &'c Rule<'a> {
+    self.rule
+  }
+
+  /// Returns every definition of the rule in the order they're defined
+  pub fn definitions(&self) -> impl Iterator<Item = &'c Rule<'a>> + 'c {
+    self.cddl.rules_named(self.rule.name_ident())
+  }
+
+  /// Returns the span of the first definition of the rule
+  pub fn span(&self) -> Span {
+    self.rule.span()
+  }
+
+  /// Returns the names of the generic parameters of the rule, if it's generic
+  pub fn generic_params(&self) -> Vec<&'a str> {
+    let generic_param = match self.rule {
+      Rule::Type { rule, .. } => &rule.generic_param,
+      Rule::Group { rule, .. } => &rule.generic_param,
+    };
+
+    generic_param
+      .iter()
+      .flat_map(|gp| gp.params.iter().map(|p| p.ident))
+      .collect()
+  }
+
+  /// Returns the distinct names of the types and groups that any definition
+  /// of the rule refers to, in the order they're first referred to. Prelude
+  /// types and the rule's own generic parameters are left out, whereas names
+  /// that the definition doesn't define are included.
+  pub fn references(&self) -> Vec<&'a str> {
+    let mut names = Vec::new();
+
+    for rule in self.definitions() {
+      let mut references = References::default();
+      references.visit_rule(rule);
+
+      for name in references.names {
+        if !is_prelude_type(name) && !names.contains(&name) {
+          names.push(name);
+        }
+      }
+    }
+
+    names
+  }
+
+  /// Returns the names of the rules that refer to this rule, in the order
+  /// they're defined
+  pub fn referrers(&self) -> Vec<&'a str> {
+    let name = self.name();
+
+    self
+      .cddl
+      .rule_names()
+      .into_iter()
+      .filter(|&other| {
+        self
+          .cddl
+          .rule_ref(other)
+          .into_iter()
+          .any(|r| r.references().contains(&name))
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+  use super::*;
+  use crate::{lexer::Lexer, parser::cddl_from_str};
+
+  #[test]
+  fn verify_rule_kinds() {
+    let input = r#"
+      person = { name: tstr, address: address }
+      point = [x: int, y: int]
+      id = uint / tstr
+      color = "red"
+      color /= "blue"
+      port = 1..65535
+      header = (id: id, ? ttl: uint)
+      address = tstr
+      $extension /= int
+    "#;
+    let cddl = cddl_from_str(&mut Lexer::new(input), input, true).unwrap();
+
+    let kinds = cddl
+      .rule_names()
+      .into_iter()
+      .map(|name| (name, cddl.rule_ref(name).unwrap().kind()))
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      kinds,
+      [
+        ("person", RuleKind::Map),
+        ("point", RuleKind::Array),
+        ("id", RuleKind::TypeChoice),
+        ("color", RuleKind::TypeChoice),
+        ("port", RuleKind::Type),
+        ("header", RuleKind::Group),
+        ("address", RuleKind::Type),
+        ("extension", RuleKind::Type),
+      ]
+    );
+
+    assert_eq!(cddl.rule_ref("color").unwrap().definitions().count(), 2);
+    assert!(cddl.rule_ref("missing").is_none());
+  }
+
+  #[test]
+  fn verify_rule_references() {
+    let input = r#"
+      message = request / response
+      request = { id: id, ~meta, params: [* param] }
+      response = { id: id, ? error: error<tstr> }
+      meta = { version: uint }
+      error<T> = { code: code, message: T, ? detail: undefined-rule }
+      code = 1 / 2
+      param = tstr
+      id = uint
+    "#;
+    let cddl = cddl_from_str(&mut Lexer::new(input), input, true).unwrap();
+
+    let references = |name| cddl.rule_ref(name).unwrap().references();
+    assert_eq!(references("message"), ["request", "response"]);
+    assert_eq!(references("request"), ["id", "meta", "param"]);
+    assert_eq!(references("error"), ["code", "undefined-rule"]);
+    assert!(references("id").is_empty());
+
+    let referrers = |name| cddl.rule_ref(name).unwrap().referrers();
+    assert_eq!(referrers("id"), ["request", "response"]);
+    assert_eq!(referrers("code"), ["error"]);
+    assert!(referrers("message").is_empty());
+  }
+}
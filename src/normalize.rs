@@ -0,0 +1,1265 @@
+use super::{
+  ast::*,
+  compat::literal_key,
+  token::{ByteValue, Value},
+};
+
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, collections::BTreeMap, string::ToString, vec::Vec};
+
+impl<'a> CDDL<'a> {
+  /// Returns a canonical form of the definition that accepts the same data,
+  /// so that definitions differing only in how they're written compare,
+  /// format and hash the same. Normalization:
+  ///
+  /// - merges the choices added via `/=` and `//=` into the rules they extend
+  /// - expands references to generic rules with their arguments substituted,
+  ///   dropping generic rules that are no longer referenced
+  /// - expands unwrapped maps, arrays and tags, e.g. `{ ~base, b: int }`
+  /// - inlines rules other than the root that are referenced exactly once
+  /// - flattens nested type and group choices and removes duplicate choices
+  /// - sorts the entries of maps with literal keys by key, ahead of any other
+  ///   entries
+  ///
+  /// Socket rules are never inlined since they may be extended elsewhere, and
+  /// recursive generic rules are left as references.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{lexer_from_str, parser::cddl_from_str};
+  ///
+  /// let input = r#"
+  ///   message = { id: id, kind: kind, ~base }
+  ///   base = { version: uint }
+  ///   kind = "ping" / ("pong" / "reset")
+  ///   id = pair<uint>
+  ///   pair<T> = [T, T]
+  /// "#;
+  /// let expected = r#"
+  ///   message = { id: [uint, uint], kind: "ping" / "pong" / "reset", version: uint }
+  ///   base = { version: uint }
+  /// "#;
+  ///
+  /// let cddl = cddl_from_str(&mut lexer_from_str(input), input, true).unwrap();
+  /// let expected = cddl_from_str(&mut lexer_from_str(expected), expected, true).unwrap();
+  ///
+  /// assert_eq!(cddl.normalize().to_string(), expected.normalize().to_string());
+  /// ```
+  pub fn normalize(&self) -> CDDL<'a> {
+    let mut defs = definitions(self);
+
+    // Generic rules are expanded using their original bodies, which are
+    // expanded in turn as they're substituted
+    let originals = defs.clone();
+    let index = defs
+      .iter()
+      .enumerate()
+      .map(|(idx, d)| (d.name.ident, idx))
+      .collect::<BTreeMap<_, _>>();
+    let recursive = References::of(&originals).recursive(&index);
+
+    for def in defs.iter_mut() {
+      let mut expander = Expander {
+        defs: &originals,
+        index: &index,
+        recursive: &recursive,
+      };
+      def.body.walk(&mut expander);
+    }
+
+    let references = References::of(&defs);
+    let mut removed = defs
+      .iter()
+      .enumerate()
+      .map(|(idx, d)| idx != 0 && d.generic_param.is_some() && references.count(d.name.ident) == 0)
+      .collect::<Vec<_>>();
+
+    // Rules that have been inlined into another, by index, so that references
+    // they contained can still be found after they've moved
+    let mut moved = BTreeMap::new();
+
+    for idx in 1..defs.len() {
+      let def = &defs[idx];
+      if removed[idx]
+        || def.generic_param.is_some()
+        || def.name.socket.is_some()
+        || references.count(def.name.ident) != 1
+      {
+        continue;
+      }
+
+      let mut owner = match references.owners.get(def.name.ident) {
+        Some(owner) => *owner,
+        None => continue,
+      };
+      while let Some(o) = moved.get(&owner) {
+        owner = *o;
+      }
+      if owner == idx || removed[owner] && !moved.contains_key(&owner) {
+        continue;
+      }
+
+      let mut inliner = Inliner {
+        name: def.name.ident,
+        body: def.body.clone(),
+        is_inlined: false,
+      };
+      defs[owner].body.walk(&mut inliner);
+
+      if inliner.is_inlined {
+        removed[idx] = true;
+        moved.insert(idx, owner);
+      }
+    }
+
+    let mut cddl = CDDL::default();
+    #[cfg(any(feature = "json-validation", feature = "cbor-validation"))]
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+      cddl.validation_options = self.validation_options.clone();
+    }
+
+    for (mut def, removed) in defs.into_iter().zip(removed) {
+      if removed {
+        continue;
+      }
+
+      match &mut def.body {
+        Body::Type(t) => flatten_type(t),
+        Body::Group(g) => flatten_group(g),
+      }
+
+      cddl.push_rule(def.into_rule());
+    }
+
+    cddl
+  }
+
+  /// Returns a self-contained definition of the rule with the given name, in
+  /// which every reference to another rule is replaced by that rule's
+  /// definition, with any generic arguments substituted, or `None` if no rule
+  /// has that name. Useful for showing the complete structure of a type in
+  /// documentation or error messages. The inlined rule is simplified in the
+  /// same way as by [`normalize`](#method.normalize).
+  ///
+  /// A recursive rule is inlined into its own definition at most `max_depth`
+  /// times. Any references left once the limit is reached are kept, along
+  /// with the rules they refer to, after the inlined rule. References to
+  /// prelude types and to rules that aren't defined are kept too.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{lexer_from_str, parser::cddl_from_str};
+  ///
+  /// let input = r#"
+  ///   message = { id: id, ~header, body: [* entry<tstr>] }
+  ///   header = { version: uint }
+  ///   entry<T> = (key: T, value: any)
+  ///   id = uint / tstr
+  /// "#;
+  ///
+  /// let cddl = cddl_from_str(&mut lexer_from_str(input), input, true).unwrap();
+  ///
+  /// let inlined = cddl.inline("message", 0).unwrap();
+  /// assert_eq!(inlined.rules.len(), 1);
+  ///
+  /// // message = {
+  /// //   body: [* (key: tstr, value: any)],
+  /// //   id: uint / tstr,
+  /// //   version: uint
+  /// // }
+  /// println!("{}", inlined);
+  /// ```
+  pub fn inline(&self, rule: &str, max_depth: usize) -> Option<CDDL<'a>> {
+    let defs = definitions(self);
+    let index = defs
+      .iter()
+      .enumerate()
+      .map(|(idx, d)| (d.name.ident, idx))
+      .collect::<BTreeMap<_, _>>();
+
+    let mut def = defs.get(*index.get(rule)?)?.clone();
+    let mut inlining = Inlining {
+      defs: &defs,
+      index: &index,
+      stack: vec![def.name.ident],
+      max_depth,
+    };
+    def.body.walk(&mut inlining);
+    match &mut def.body {
+      Body::Type(t) => flatten_type(t),
+      Body::Group(g) => flatten_group(g),
+    }
+
+    let mut cddl = CDDL::default();
+    #[cfg(any(feature = "json-validation", feature = "cbor-validation"))]
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+      cddl.validation_options = self.validation_options.clone();
+    }
+
+    let name = def.name.ident;
+    cddl.push_rule(def.into_rule());
+    for rule in self.rules.iter().filter(|r| r.name_ident() != name) {
+      cddl.push_rule(rule.clone());
+    }
+    cddl.prune(name);
+
+    Some(cddl)
+  }
+}
+
+// Rule with the choices added by its alternates merged in
+#[derive(Clone)]
+struct Definition<'a> {
+  name: Identifier<'a>,
+  generic_param: Option<GenericParm<'a>>,
+  body: Body<'a>,
+  span: Span,
+}
+
+#[derive(Clone)]
+enum Body<'a> {
+  Type(Type<'a>),
+  Group(Group<'a>),
+}
+
+impl<'a> Definition<'a> {
+  fn into_rule(self) -> Rule<'a> {
+    match self.body {
+      Body::Type(value) => Rule::Type {
+        rule: TypeRule {
+          name: self.name,
+          generic_param: self.generic_param,
+          is_type_choice_alternate: false,
+          value,
+        },
+        span: self.span,
+      },
+      Body::Group(group) => {
+        let span = group.span;
+
+        Rule::Group {
+          rule: Box::new(GroupRule {
+            name: self.name,
+            generic_param: self.generic_param,
+            is_group_choice_alternate: false,
+            entry: GroupEntry::InlineGroup {
+              occur: None,
+              group,
+              span,
+            },
+          }),
+          span: self.span,
+        }
+      }
+    }
+  }
+}
+
+impl<'a> Body<'a> {
+  fn walk<P: Pass<'a>>(&mut self, pass: &mut P) {
+    match self {
+      Body::Type(t) => walk_type(pass, t),
+      Body::Group(g) => walk_group(pass, g),
+    }
+  }
+}
+
+fn definitions<'a>(cddl: &CDDL<'a>) -> Vec<Definition<'a>> {
+  let mut defs: Vec<Definition<'a>> = Vec::new();
+  let mut index = BTreeMap::new();
+
+  for rule in cddl.rules.iter() {
+    let (name, generic_param, body) = match rule {
+      Rule::Type { rule, .. } => (
+        &rule.name,
+        &rule.generic_param,
+        Body::Type(rule.value.clone()),
+      ),
+      Rule::Group { rule, .. } => (
+        &rule.name,
+        &rule.generic_param,
+        Body::Group(group_of_entry(&rule.entry)),
+      ),
+    };
+
+    match (index.get(name.ident).map(|idx| &mut defs[*idx]), body) {
+      (
+        Some(Definition {
+          body: Body::Type(t),
+          ..
+        }),
+        Body::Type(alternate),
+      ) => t.type_choices.extend(alternate.type_choices),
+      (
+        Some(Definition {
+          body: Body::Group(g),
+          ..
+        }),
+        Body::Group(alternate),
+      ) => g.group_choices.extend(alternate.group_choices),
+      // Alternates of a different kind than the rule they extend are rejected
+      // by the compiler, so they're simply dropped
+      (Some(_), _) => {}
+      (None, body) => {
+        index.insert(name.ident, defs.len());
+        defs.push(Definition {
+          name: name.clone(),
+          generic_param: generic_param.clone(),
+          body,
+          span: rule.span(),
+        });
+      }
+    }
+  }
+
+  defs
+}
+
+// Group rules assign either a parenthesized group or a single entry
+pub(crate) fn group_of_entry<'a>(entry: &GroupEntry<'a>) -> Group<'a> {
+  if let GroupEntry::InlineGroup {
+    occur: None, group, ..
+  } = entry
+  {
+    return group.clone();
+  }
+
+  let span = match entry {
+    GroupEntry::ValueMemberKey { span, .. }
+    | GroupEntry::TypeGroupname { span, .. }
+    | GroupEntry::InlineGroup { span, .. } => *span,
+  };
+
+  Group {
+    group_choices: vec![GroupChoice {
+      group_entries: vec![(entry.clone(), false)],
+      span,
+    }],
+    span,
+  }
+}
+
+// Rewrite of the nodes of an AST that can refer to other rules. Nodes are
+// visited after their children, so a replacement isn't visited again.
+trait Pass<'a> {
+  fn type2(&mut self, _t2: &mut Type2<'a>) {}
+
+  fn group_entry(&mut self, _ge: &mut GroupEntry<'a>) {}
+
+  fn visits_member_keys(&self) -> bool {
+    true
+  }
+}
+
+fn walk_type<'a, P: Pass<'a>>(pass: &mut P, t: &mut Type<'a>) {
+  for t1 in t.type_choices.iter_mut() {
+    walk_type1(pass, t1);
+  }
+}
+
+fn walk_type1<'a, P: Pass<'a>>(pass: &mut P, t1: &mut Type1<'a>) {
+  walk_type2(pass, &mut t1.type2);
+
+  if let Some((_, t2)) = &mut t1.operator {
+    walk_type2(pass, t2);
+  }
+}
+
+fn walk_type2<'a, P: Pass<'a>>(pass: &mut P, t2: &mut Type2<'a>) {
+  match t2 {
+    Type2::ParenthesizedType { pt: t, .. } | Type2::TaggedData { t, .. } => walk_type(pass, t),
+    Type2::Map { group, .. }
+    | Type2::Array { group, .. }
+    | Type2::ChoiceFromInlineGroup { group, .. } => walk_group(pass, group),
+    Type2::Typename {
+      generic_arg: Some(ga),
+      ..
+    }
+    | Type2::Unwrap {
+      generic_arg: Some(ga),
+      ..
+    }
+    | Type2::ChoiceFromGroup {
+      generic_arg: Some(ga),
+      ..
+    } => walk_generic_arg(pass, ga),
+    _ => {}
+  }
+
+  pass.type2(t2);
+}
+
+fn walk_generic_arg<'a, P: Pass<'a>>(pass: &mut P, ga: &mut GenericArg<'a>) {
+  for arg in ga.args.iter_mut() {
+    walk_type1(pass, arg);
+  }
+}
+
+fn walk_group<'a, P: Pass<'a>>(pass: &mut P, group: &mut Group<'a>) {
+  for gc in group.group_choices.iter_mut() {
+    for (ge, _) in gc.group_entries.iter_mut() {
+      walk_group_entry(pass, ge);
+    }
+  }
+}
+
+fn walk_group_entry<'a, P: Pass<'a>>(pass: &mut P, ge: &mut GroupEntry<'a>) {
+  match ge {
+    GroupEntry::ValueMemberKey { ge: vmke, .. } => {
+      match &mut vmke.member_key {
+        _ if !pass.visits_member_keys() => {}
+        Some(MemberKey::Type1 { t1, .. }) => walk_type1(pass, t1),
+        Some(MemberKey::NonMemberKey(NonMemberKey::Type(t))) => walk_type(pass, t),
+        Some(MemberKey::NonMemberKey(NonMemberKey::Group(g))) => walk_group(pass, g),
+        _ => {}
+      }
+
+      walk_type(pass, &mut vmke.entry_type);
+    }
+    GroupEntry::TypeGroupname { ge: tge, .. } => {
+      if let Some(ga) = &mut tge.generic_arg {
+        walk_generic_arg(pass, ga);
+      }
+    }
+    GroupEntry::InlineGroup { group, .. } => walk_group(pass, group),
+  }
+
+  pass.group_entry(ge);
+}
+
+// Expands references to generic rules and unwrapped types
+struct Expander<'a, 'b> {
+  defs: &'b [Definition<'a>],
+  index: &'b BTreeMap<&'a str, usize>,
+  // Whether each rule refers to itself, in which case it's never expanded so
+  // that expansion terminates
+  recursive: &'b [bool],
+}
+
+impl<'a, 'b> Expander<'a, 'b> {
+  // Returns the body of a rule with any generic arguments substituted and
+  // references within it expanded
+  fn expand(&mut self, name: &str, generic_arg: Option<&GenericArg<'a>>) -> Option<Body<'a>> {
+    let idx = *self.index.get(name)?;
+    if self.recursive[idx] {
+      return None;
+    }
+
+    let def = &self.defs[idx];
+
+    let mut body = def.body.clone();
+    match (&def.generic_param, generic_arg) {
+      (Some(gp), Some(ga)) if gp.params.len() == ga.args.len() => {
+        let mut substituter = Substituter {
+          params: gp.params.iter().map(|p| p.ident).collect(),
+          args: &ga.args,
+        };
+        body.walk(&mut substituter);
+      }
+      (None, None) => {}
+      _ => return None,
+    }
+
+    body.walk(self);
+
+    Some(body)
+  }
+
+  fn is_generic(&self, name: &str) -> bool {
+    match self.index.get(name) {
+      Some(idx) => self.defs[*idx].generic_param.is_some(),
+      None => false,
+    }
+  }
+}
+
+impl<'a, 'b> Pass<'a> for Expander<'a, 'b> {
+  fn type2(&mut self, t2: &mut Type2<'a>) {
+    let expanded = match t2 {
+      Type2::Typename {
+        ident,
+        generic_arg: Some(ga),
+        span,
+      } if self.is_generic(ident.ident) => match self.expand(ident.ident, Some(ga)) {
+        Some(Body::Type(pt)) => Type2::ParenthesizedType { pt, span: *span },
+        _ => return,
+      },
+      Type2::ChoiceFromGroup {
+        ident,
+        generic_arg: Some(ga),
+        span,
+      } if self.is_generic(ident.ident) => match self.expand(ident.ident, Some(ga)) {
+        Some(Body::Group(group)) => Type2::ChoiceFromInlineGroup { group, span: *span },
+        _ => return,
+      },
+      Type2::Unwrap {
+        ident,
+        generic_arg,
+        span,
+      } => match unwrapped(self.expand(ident.ident, generic_arg.as_ref())) {
+        Some(Type2::TaggedData { t: pt, .. }) => Type2::ParenthesizedType { pt, span: *span },
+        _ => return,
+      },
+      _ => return,
+    };
+
+    *t2 = expanded;
+  }
+
+  fn group_entry(&mut self, ge: &mut GroupEntry<'a>) {
+    let expanded = match ge {
+      GroupEntry::TypeGroupname { ge: tge, span } if tge.generic_arg.is_some() => {
+        match self.expand(tge.name.ident, tge.generic_arg.as_ref()) {
+          Some(body) => entry_of(body, tge.occur.take(), *span),
+          None => return,
+        }
+      }
+      GroupEntry::ValueMemberKey { ge: vmke, span } if vmke.member_key.is_none() => {
+        let group = match unwrapped_reference(&vmke.entry_type) {
+          Some((ident, generic_arg)) => {
+            match unwrapped(self.expand(ident.ident, generic_arg.as_ref())) {
+              Some(Type2::Map { group, .. }) | Some(Type2::Array { group, .. }) => group,
+              _ => return,
+            }
+          }
+          None => return,
+        };
+
+        GroupEntry::InlineGroup {
+          occur: vmke.occur.take(),
+          group,
+          span: *span,
+        }
+      }
+      _ => return,
+    };
+
+    *ge = expanded;
+  }
+}
+
+// Returns the rule referenced by an entry type consisting of an unwrap alone
+fn unwrapped_reference<'a>(t: &Type<'a>) -> Option<(Identifier<'a>, Option<GenericArg<'a>>)> {
+  if let [Type1 {
+    type2: Type2::Unwrap {
+      ident, generic_arg, ..
+    },
+    operator: None,
+    ..
+  }] = &t.type_choices[..]
+  {
+    return Some((ident.clone(), generic_arg.clone()));
+  }
+
+  None
+}
+
+// Returns the map, array or tag an unwrapped type rule consists of
+fn unwrapped(body: Option<Body>) -> Option<Type2> {
+  if let Some(Body::Type(t)) = body {
+    if let [Type1 {
+      type2,
+      operator: None,
+      ..
+    }] = &t.type_choices[..]
+    {
+      if let Type2::Map { .. } | Type2::Array { .. } | Type2::TaggedData { .. } = type2 {
+        return Some(type2.clone());
+      }
+    }
+  }
+
+  None
+}
+
+// Entry referring to the body of a rule within a group
+fn entry_of(body: Body, occur: Option<Occur>, span: Span) -> GroupEntry {
+  match body {
+    Body::Type(entry_type) => GroupEntry::ValueMemberKey {
+      ge: Box::new(ValueMemberKeyEntry {
+        occur,
+        member_key: None,
+        entry_type,
+      }),
+      span,
+    },
+    Body::Group(group) => GroupEntry::InlineGroup { occur, group, span },
+  }
+}
+
+// Returns a rule with its generic parameters replaced by the given arguments,
+// e.g. (alg: int) for header<int> with header<t> = (alg: t). Returns None if
+// the rule doesn't take that many arguments.
+pub(crate) fn substitute_generic_args<'a>(
+  rule: &Rule<'a>,
+  generic_arg: &GenericArg<'a>,
+) -> Option<Rule<'a>> {
+  let mut rule = rule.clone();
+
+  let generic_param = match &mut rule {
+    Rule::Type { rule, .. } => rule.generic_param.take(),
+    Rule::Group { rule, .. } => rule.generic_param.take(),
+  };
+  let gp = generic_param.filter(|gp| gp.params.len() == generic_arg.args.len())?;
+
+  let mut substituter = Substituter {
+    params: gp.params.iter().map(|p| p.ident).collect(),
+    args: &generic_arg.args,
+  };
+  match &mut rule {
+    Rule::Type { rule, .. } => walk_type(&mut substituter, &mut rule.value),
+    Rule::Group { rule, .. } => walk_group_entry(&mut substituter, &mut rule.entry),
+  }
+
+  Some(rule)
+}
+
+// Replaces the generic parameters of a rule with its arguments
+struct Substituter<'a, 'b> {
+  params: Vec<&'a str>,
+  args: &'b [Type1<'a>],
+}
+
+impl<'a, 'b> Substituter<'a, 'b> {
+  fn arg(&self, ident: &Identifier) -> Option<Type<'a>> {
+    let idx = self.params.iter().position(|p| *p == ident.ident)?;
+    let arg = self.args[idx].clone();
+    let span = arg.span;
+
+    Some(Type {
+      type_choices: vec![arg],
+      span,
+    })
+  }
+}
+
+impl<'a, 'b> Pass<'a> for Substituter<'a, 'b> {
+  fn type2(&mut self, t2: &mut Type2<'a>) {
+    if let Type2::Typename {
+      ident,
+      generic_arg: None,
+      span,
+    } = t2
+    {
+      if let Some(mut pt) = self.arg(ident) {
+        // Arguments without operators are substituted as they are, so that
+        // literal member keys remain literals, e.g. "alg" in key<"alg">
+        *t2 = match pt.type_choices.as_mut_slice() {
+          [Type1 {
+            type2,
+            operator: None,
+            ..
+          }] => std::mem::replace(type2, Type2::Any(*span)),
+          _ => Type2::ParenthesizedType { pt, span: *span },
+        };
+      }
+    }
+  }
+
+  fn group_entry(&mut self, ge: &mut GroupEntry<'a>) {
+    if let GroupEntry::TypeGroupname { ge: tge, span } = ge {
+      if tge.generic_arg.is_none() {
+        if let Some(entry_type) = self.arg(&tge.name) {
+          *ge = entry_of(Body::Type(entry_type), tge.occur.take(), *span);
+        }
+      }
+    }
+  }
+}
+
+// Number of references to each rule and, for rules referenced once, the index
+// of the rule containing the reference
+#[derive(Default)]
+struct References<'a> {
+  counts: BTreeMap<&'a str, usize>,
+  owners: BTreeMap<&'a str, usize>,
+  owner: usize,
+  // Names referred to by each rule
+  names: Vec<Vec<&'a str>>,
+}
+
+impl<'a> References<'a> {
+  fn of(defs: &[Definition<'a>]) -> Self {
+    let mut references = References::default();
+
+    for (idx, def) in defs.iter().enumerate() {
+      references.owner = idx;
+      references.names.push(Vec::new());
+      def.body.clone().walk(&mut references);
+    }
+
+    references
+  }
+
+  // Returns whether each rule refers to itself, directly or via other rules
+  fn recursive(&self, index: &BTreeMap<&'a str, usize>) -> Vec<bool> {
+    (0..self.names.len())
+      .map(|idx| {
+        let mut visited = vec![false; self.names.len()];
+        let mut pending = vec![idx];
+
+        while let Some(current) = pending.pop() {
+          for name in self.names[current].iter() {
+            match index.get(name) {
+              Some(&next) if next == idx => return true,
+              Some(&next) if !visited[next] => {
+                visited[next] = true;
+                pending.push(next);
+              }
+              _ => {}
+            }
+          }
+        }
+
+        false
+      })
+      .collect()
+  }
+
+  fn count(&self, name: &str) -> usize {
+    self.counts.get(name).copied().unwrap_or(0)
+  }
+
+  fn insert(&mut self, name: &'a str) {
+    *self.counts.entry(name).or_insert(0) += 1;
+    self.owners.insert(name, self.owner);
+    self.names[self.owner].push(name);
+  }
+}
+
+impl<'a> Pass<'a> for References<'a> {
+  fn type2(&mut self, t2: &mut Type2<'a>) {
+    match t2 {
+      Type2::Typename { ident, .. }
+      | Type2::Unwrap { ident, .. }
+      | Type2::ChoiceFromGroup { ident, .. } => self.insert(ident.ident),
+      _ => {}
+    }
+  }
+
+  fn group_entry(&mut self, ge: &mut GroupEntry<'a>) {
+    if let GroupEntry::TypeGroupname { ge: tge, .. } = ge {
+      self.insert(tge.name.ident);
+    }
+  }
+}
+
+// Replaces the only reference to a rule with its body. References within
+// member keys are left alone since the parser doesn't accept parenthesized
+// keys or keys with operators when they're followed by `=>`.
+struct Inliner<'a> {
+  name: &'a str,
+  body: Body<'a>,
+  is_inlined: bool,
+}
+
+impl<'a> Pass<'a> for Inliner<'a> {
+  fn visits_member_keys(&self) -> bool {
+    false
+  }
+
+  fn type2(&mut self, t2: &mut Type2<'a>) {
+    let inlined = match (&*t2, &self.body) {
+      (
+        Type2::Typename {
+          ident,
+          generic_arg: None,
+          span,
+        },
+        Body::Type(pt),
+      ) if ident.ident == self.name => Type2::ParenthesizedType {
+        pt: pt.clone(),
+        span: *span,
+      },
+      (
+        Type2::ChoiceFromGroup {
+          ident,
+          generic_arg: None,
+          span,
+        },
+        Body::Group(group),
+      ) if ident.ident == self.name => Type2::ChoiceFromInlineGroup {
+        group: group.clone(),
+        span: *span,
+      },
+      _ => return,
+    };
+
+    *t2 = inlined;
+    self.is_inlined = true;
+  }
+
+  fn group_entry(&mut self, ge: &mut GroupEntry<'a>) {
+    if let GroupEntry::TypeGroupname { ge: tge, span } = ge {
+      if tge.name.ident == self.name && tge.generic_arg.is_none() {
+        *ge = entry_of(self.body.clone(), tge.occur.take(), *span);
+        self.is_inlined = true;
+      }
+    }
+  }
+}
+
+// Replaces every reference to a rule with its body, in which references are
+// replaced in turn. A rule is inlined within its own body at most `max_depth`
+// times. References within member keys are left alone as they are by
+// `Inliner`.
+struct Inlining<'a, 'b> {
+  defs: &'b [Definition<'a>],
+  index: &'b BTreeMap<&'a str, usize>,
+  // Names of the rules whose bodies are being inlined, outermost first
+  stack: Vec<&'a str>,
+  max_depth: usize,
+}
+
+impl<'a, 'b> Inlining<'a, 'b> {
+  fn body(&mut self, name: &'a str, generic_arg: Option<&GenericArg<'a>>) -> Option<Body<'a>> {
+    let def = &self.defs[*self.index.get(name)?];
+    if self.stack.iter().filter(|n| **n == name).count() > self.max_depth {
+      return None;
+    }
+
+    let mut body = def.body.clone();
+    match (&def.generic_param, generic_arg) {
+      (Some(gp), Some(ga)) if gp.params.len() == ga.args.len() => {
+        let mut substituter = Substituter {
+          params: gp.params.iter().map(|p| p.ident).collect(),
+          args: &ga.args,
+        };
+        body.walk(&mut substituter);
+      }
+      (None, None) => {}
+      _ => return None,
+    }
+
+    self.stack.push(name);
+    body.walk(self);
+    self.stack.pop();
+
+    Some(body)
+  }
+}
+
+impl<'a, 'b> Pass<'a> for Inlining<'a, 'b> {
+  fn visits_member_keys(&self) -> bool {
+    false
+  }
+
+  fn type2(&mut self, t2: &mut Type2<'a>) {
+    let inlined = match t2 {
+      Type2::Typename {
+        ident,
+        generic_arg,
+        span,
+      } => match self.body(ident.ident, generic_arg.as_ref()) {
+        // Parentheses are only needed around choices and operators
+        Some(Body::Type(mut pt)) => match pt.type_choices.as_mut_slice() {
+          [Type1 {
+            type2,
+            operator: None,
+            ..
+          }] => std::mem::replace(type2, Type2::Any(*span)),
+          _ => Type2::ParenthesizedType { pt, span: *span },
+        },
+        _ => return,
+      },
+      Type2::ChoiceFromGroup {
+        ident,
+        generic_arg,
+        span,
+      } => match self.body(ident.ident, generic_arg.as_ref()) {
+        Some(Body::Group(group)) => Type2::ChoiceFromInlineGroup { group, span: *span },
+        _ => return,
+      },
+      Type2::Unwrap {
+        ident,
+        generic_arg,
+        span,
+      } => match unwrapped(self.body(ident.ident, generic_arg.as_ref())) {
+        Some(Type2::TaggedData { t: pt, .. }) => Type2::ParenthesizedType { pt, span: *span },
+        _ => return,
+      },
+      _ => return,
+    };
+
+    *t2 = inlined;
+  }
+
+  fn group_entry(&mut self, ge: &mut GroupEntry<'a>) {
+    let inlined = match ge {
+      GroupEntry::TypeGroupname { ge: tge, span } => {
+        match self.body(tge.name.ident, tge.generic_arg.as_ref()) {
+          Some(body) => entry_of(body, tge.occur.take(), *span),
+          None => return,
+        }
+      }
+      GroupEntry::ValueMemberKey { ge: vmke, span } if vmke.member_key.is_none() => {
+        let group = match unwrapped_reference(&vmke.entry_type) {
+          Some((ident, generic_arg)) => {
+            match unwrapped(self.body(ident.ident, generic_arg.as_ref())) {
+              Some(Type2::Map { group, .. }) | Some(Type2::Array { group, .. }) => group,
+              _ => return,
+            }
+          }
+          None => return,
+        };
+
+        GroupEntry::InlineGroup {
+          occur: vmke.occur.take(),
+          group,
+          span: *span,
+        }
+      }
+      _ => return,
+    };
+
+    *ge = inlined;
+  }
+}
+
+fn flatten_type(t: &mut Type) {
+  let mut type_choices: Vec<Type1> = Vec::with_capacity(t.type_choices.len());
+
+  for mut t1 in t.type_choices.drain(..) {
+    flatten_type2(&mut t1.type2);
+    if let Some((_, t2)) = &mut t1.operator {
+      flatten_type2(t2);
+    }
+
+    let choices = match (t1.operator.is_none(), t1.type2) {
+      (true, Type2::ParenthesizedType { pt, .. }) => pt.type_choices,
+      (_, type2) => vec![Type1 { type2, ..t1 }],
+    };
+
+    // Choices are compared as written since their spans differ
+    for choice in choices {
+      let text = choice.to_string();
+      if !type_choices.iter().any(|t1| t1.to_string() == text) {
+        type_choices.push(choice);
+      }
+    }
+  }
+
+  t.type_choices = type_choices;
+}
+
+fn flatten_type2(t2: &mut Type2) {
+  match t2 {
+    Type2::ParenthesizedType { pt, .. } => {
+      flatten_type(pt);
+
+      // Parentheses are only needed around choices and operators
+      if let [Type1 {
+        type2,
+        operator: None,
+        ..
+      }] = &mut pt.type_choices[..]
+      {
+        let type2 = std::mem::replace(type2, Type2::Any((0, 0, 0)));
+        *t2 = type2;
+      }
+    }
+    Type2::TaggedData { t, .. } => flatten_type(t),
+    Type2::Map { group, .. } => {
+      flatten_group(group);
+      sort_entries(group);
+    }
+    Type2::Array { group, .. } | Type2::ChoiceFromInlineGroup { group, .. } => flatten_group(group),
+    Type2::Typename {
+      generic_arg: Some(ga),
+      ..
+    }
+    | Type2::Unwrap {
+      generic_arg: Some(ga),
+      ..
+    }
+    | Type2::ChoiceFromGroup {
+      generic_arg: Some(ga),
+      ..
+    } => {
+      for arg in ga.args.iter_mut() {
+        flatten_type2(&mut arg.type2);
+      }
+    }
+    _ => {}
+  }
+}
+
+fn flatten_group(group: &mut Group) {
+  let mut group_choices: Vec<GroupChoice> = Vec::with_capacity(group.group_choices.len());
+
+  for gc in group.group_choices.drain(..) {
+    let mut group_entries = Vec::with_capacity(gc.group_entries.len());
+
+    for (mut ge, _) in gc.group_entries {
+      flatten_group_entry(&mut ge);
+
+      match ge {
+        GroupEntry::InlineGroup {
+          occur: None,
+          mut group,
+          ..
+        } if group.group_choices.len() == 1 => {
+          group_entries.append(&mut group.group_choices.remove(0).group_entries)
+        }
+        ge => group_entries.push((ge, true)),
+      }
+    }
+
+    // A group choice made up of nothing but a nested group with choices of
+    // its own is replaced by those choices
+    let choices = match &mut group_entries[..] {
+      [(
+        GroupEntry::InlineGroup {
+          occur: None, group, ..
+        },
+        _,
+      )] => group.group_choices.drain(..).collect(),
+      _ => vec![GroupChoice {
+        group_entries,
+        span: gc.span,
+      }],
+    };
+
+    for mut choice in choices {
+      // Entries are separated by commas, other than the last
+      let len = choice.group_entries.len();
+      for (idx, (_, comma)) in choice.group_entries.iter_mut().enumerate() {
+        *comma = idx + 1 < len;
+      }
+
+      let text = choice.to_string();
+      if !group_choices.iter().any(|gc| gc.to_string() == text) {
+        group_choices.push(choice);
+      }
+    }
+  }
+
+  group.group_choices = group_choices;
+}
+
+fn flatten_group_entry(ge: &mut GroupEntry) {
+  match ge {
+    GroupEntry::ValueMemberKey { ge: vmke, .. } => {
+      match &mut vmke.member_key {
+        Some(MemberKey::Type1 { t1, .. }) => {
+          flatten_type2(&mut t1.type2);
+          if let Some((_, t2)) = &mut t1.operator {
+            flatten_type2(t2);
+          }
+
+          // Parentheses around a key with an operator aren't needed
+          let inner = match (&t1.operator, &t1.type2) {
+            (None, Type2::ParenthesizedType { pt, .. }) if pt.type_choices.len() == 1 => {
+              Some(pt.type_choices[0].clone())
+            }
+            _ => None,
+          };
+          if let Some(inner) = inner {
+            **t1 = inner;
+          }
+        }
+        Some(MemberKey::NonMemberKey(NonMemberKey::Type(t))) => flatten_type(t),
+        Some(MemberKey::NonMemberKey(NonMemberKey::Group(g))) => flatten_group(g),
+        _ => {}
+      }
+
+      // Literal keys are parsed as values whether they're followed by `:` or
+      // `=>`, so keys inlined from other rules are written the same way
+      if let Some(MemberKey::Type1 { t1, span, .. }) = &vmke.member_key {
+        if let Some(value) = literal_value(t1) {
+          vmke.member_key = Some(MemberKey::Value { value, span: *span });
+        }
+      }
+
+      flatten_type(&mut vmke.entry_type);
+    }
+    GroupEntry::InlineGroup { group, .. } => flatten_group(group),
+    GroupEntry::TypeGroupname { ge, .. } => {
+      if let Some(ga) = &mut ge.generic_arg {
+        for arg in ga.args.iter_mut() {
+          flatten_type2(&mut arg.type2);
+        }
+      }
+    }
+  }
+}
+
+fn literal_value<'a>(t1: &Type1<'a>) -> Option<Value<'a>> {
+  if t1.operator.is_some() {
+    return None;
+  }
+
+  match &t1.type2 {
+    Type2::IntValue { value, .. } => Some(Value::INT(*value)),
+    Type2::UintValue { value, .. } => Some(Value::UINT(*value)),
+    Type2::FloatValue { value, .. } => Some(Value::FLOAT(*value)),
+    Type2::TextValue { value, .. } => Some(Value::TEXT(value.clone())),
+    Type2::UTF8ByteString { value, .. } => Some(Value::BYTE(ByteValue::UTF8(value.clone()))),
+    Type2::B16ByteString { value, .. } => Some(Value::BYTE(ByteValue::B16(value.clone()))),
+    Type2::B64ByteString { value, .. } => Some(Value::BYTE(ByteValue::B64(value.clone()))),
+    _ => None,
+  }
+}
+
+// Sorts entries with literal keys by key, leaving the others after them in
+// their original order since they may match any key not already matched
+fn sort_entries(group: &mut Group) {
+  for gc in group.group_choices.iter_mut() {
+    let key = |ge: &GroupEntry| match ge {
+      GroupEntry::ValueMemberKey { ge, .. } => ge.member_key.as_ref().and_then(literal_key),
+      _ => None,
+    };
+
+    let (mut keyed, others): (Vec<_>, Vec<_>) = gc
+      .group_entries
+      .drain(..)
+      .partition(|(ge, _)| key(ge).is_some());
+    keyed.sort_by_key(|(ge, _)| key(ge));
+    keyed.extend(others);
+
+    let len = keyed.len();
+    for (idx, (_, comma)) in keyed.iter_mut().enumerate() {
+      *comma = idx + 1 < len;
+    }
+
+    gc.group_entries = keyed;
+  }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+  use crate::{lexer::Lexer, parser::cddl_from_str};
+
+  fn normalize(input: &str) -> String {
+    let cddl = cddl_from_str(&mut Lexer::new(input), input, true).unwrap();
+
+    cddl.normalize().to_string()
+  }
+
+  #[test]
+  fn verify_normalize() {
+    assert_eq!(
+      normalize(
+        r#"
+          root = { b: kind, a: labels, * tstr => any }
+          kind = "x"
+          kind /= "y" / ("z" / "x")
+          labels = [* label]
+          label = tstr .size (1..10)
+          unused = int
+        "#
+      ),
+      "root = {\ta: [* tstr .size (1..10)],\n\tb: \"x\" / \"y\" / \"z\",\n\t* tstr => any\n}\n\nunused = int\n\n"
+    );
+  }
+
+  #[test]
+  fn verify_normalize_generics_and_unwraps() {
+    assert_eq!(
+      normalize(
+        r#"
+          message = [header, body, body]
+          header = { ~base, id: uint }
+          base = { version: uint }
+          body = tagged<tstr> / tagged<bstr>
+          tagged<T> = #6.24(T)
+          node = [* node]
+          list<T> = [T, ? list<T>]
+          strings = list<tstr>
+        "#
+      ),
+      normalize(
+        r#"
+          message = [{ id: uint, version: uint }, body, body]
+          base = { version: uint }
+          body = #6.24(tstr) / #6.24(bstr)
+          node = [* node]
+          list<T> = [T, ? list<T>]
+          strings = list<tstr>
+        "#
+      )
+    );
+  }
+
+  #[test]
+  fn verify_normalize_group_choices() {
+    assert_eq!(
+      normalize("a = { (b // c) }\nb = (x: int)\nc = (y: int)\nc //= (z: int)"),
+      "a = {x: int // y: int // z: int}\n\n"
+    );
+  }
+
+  fn inline(input: &str, rule: &str, max_depth: usize) -> Option<String> {
+    let cddl = cddl_from_str(&mut Lexer::new(input), input, true).unwrap();
+
+    cddl.inline(rule, max_depth).map(|c| c.to_string())
+  }
+
+  #[test]
+  fn verify_inline() {
+    let input = r#"
+      message = [header, * body]
+      header = { ~base, id: id, ? kind: kind }
+      base = { version: uint }
+      body = tagged<tstr> / tagged<bstr>
+      tagged<T> = #6.24(T)
+      kind = &colors
+      colors = (red: 1, blue: 2)
+      id = uint .size 8
+      unused = int
+    "#;
+
+    assert_eq!(
+      inline(input, "message", 0).unwrap(),
+      "message = [\t{\tid: uint .size 8,\n\t? kind: &(\tred: 1,\n\tblue: 2\n),\n\tversion: uint\n},\n\t* #6.24(tstr) / #6.24(bstr)\n]\n\n"
+    );
+    assert_eq!(
+      inline(input, "base", 0).unwrap(),
+      "base = {version: uint}\n\n"
+    );
+    assert_eq!(inline(input, "missing", 0), None);
+  }
+
+  #[test]
+  fn verify_inline_recursive_rules() {
+    let input = r#"
+      tree = { value: uint, children: [* tree], ? label: label }
+      label = tstr
+    "#;
+
+    assert_eq!(
+      inline(input, "tree", 0).unwrap(),
+      "tree = {\tchildren: [* tree],\n\t? label: tstr,\n\tvalue: uint\n}\n\n"
+    );
+    assert_eq!(
+      inline(input, "tree", 1).unwrap(),
+      normalize(
+        "tree = { value: uint, children: [* { value: uint, children: [* tree], ? label: tstr }], ? label: tstr }"
+      )
+    );
+
+    // Rules still referenced once the limit is reached are kept
+    let input = r#"
+      document = { root: node }
+      node = [* node] / leaf
+      leaf = tstr
+    "#;
+
+    assert_eq!(
+      inline(input, "document", 0).unwrap(),
+      "document = {root: [* node] / tstr}\n\nnode = [* node] / leaf\n\nleaf = tstr\n\n"
+    );
+    assert_eq!(
+      inline(input, "document", 1).unwrap(),
+      "document = {root: [* [* node] / tstr] / tstr}\n\nnode = [* node] / leaf\n\nleaf = tstr\n\n"
+    );
+  }
+}
@@ -1,5 +1,6 @@
 use super::{
   ast::*,
+  error::{self, Limit},
   lexer::{self, Lexer, LexerError, Position},
   token::{self, Token},
 };
@@ -14,12 +15,16 @@ use codespan_reporting::{
 #[cfg(feature = "std")]
 use std::borrow::Cow;
 
-use std::{cmp::Ordering, fmt, mem, result};
+use std::{cmp::Ordering, convert::TryFrom, fmt, mem, result};
+
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
 
 #[cfg(not(feature = "std"))]
 use alloc::{
   borrow::{Cow, ToOwned},
   boxed::Box,
+  collections::BTreeMap,
   string::{String, ToString},
   vec::Vec,
 };
@@ -30,6 +35,49 @@ use wasm_bindgen::prelude::*;
 /// Alias for `Result` with an error of type `cddl::ParserError`
 pub type Result<T> = result::Result<T, Error>;
 
+/// Default maximum nesting depth of types and groups, used unless
+/// `ParserOptions::max_nesting_depth` is set
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 128;
+
+/// Limits on the CDDL accepted by a parser, for parsing definitions from
+/// untrusted sources. Parsing stops with `Error::LimitExceeded` as soon as a
+/// limit is exceeded.
+///
+/// ```
+/// use cddl::{
+///   error::Limit,
+///   lexer::Lexer,
+///   parser::{Error, Parser, ParserOptions},
+/// };
+///
+/// let input = r#"a = int / tstr / bstr"#;
+/// let mut lexer = Lexer::new(input);
+/// let mut p = Parser::new(lexer.iter(), input).unwrap();
+///
+/// p.set_options(ParserOptions {
+///   max_choices: Some(2),
+///   ..Default::default()
+/// });
+///
+/// match p.parse_cddl() {
+///   Err(Error::LimitExceeded { limit, max }) => {
+///     assert_eq!(limit, Limit::Choices);
+///     assert_eq!(max, 2);
+///   }
+///   _ => unreachable!(),
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ParserOptions {
+  /// Maximum nesting depth of types and groups, guarding against stack
+  /// exhaustion. Defaults to `DEFAULT_MAX_NESTING_DEPTH` if not set.
+  pub max_nesting_depth: Option<usize>,
+  /// Maximum number of rules, including choice alternates
+  pub max_rules: Option<usize>,
+  /// Maximum number of alternatives of a single type or group
+  pub max_choices: Option<usize>,
+}
+
 /// Parser type
 pub struct Parser<'a, I>
 where
@@ -42,8 +90,16 @@ where
   lexer_position: Position,
   peek_lexer_position: Position,
   parser_position: Position,
+  options: ParserOptions,
+  // Current nesting depth of types and groups
+  depth: usize,
+  // Limit that stopped parsing, if any
+  limit_exceeded: Option<(Limit, usize)>,
   /// Vec of collected parsing errors
   pub errors: Vec<ParserError>,
+  // Whether or not the standard prelude itself is being parsed, in which case
+  // the reserved prelude type names are permitted as rule identifiers
+  pub(crate) is_prelude: bool,
 }
 
 /// Parsing error types
@@ -57,6 +113,13 @@ pub enum Error {
   LEXER(LexerError),
   /// Regex error
   REGEX(regex::Error),
+  /// A limit set by `ParserOptions` was exceeded
+  LimitExceeded {
+    /// Limit that was exceeded
+    limit: Limit,
+    /// Maximum in effect
+    max: usize,
+  },
 }
 
 /// Parser error information and position
@@ -66,6 +129,18 @@ pub struct ParserError {
   message: String,
 }
 
+impl ParserError {
+  /// Returns the position of the input the error applies to
+  pub fn position(&self) -> Position {
+    self.position
+  }
+
+  /// Returns a description of the error without any source context
+  pub fn message(&self) -> &str {
+    &self.message
+  }
+}
+
 impl fmt::Display for Error {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match self {
@@ -73,15 +148,16 @@ impl fmt::Display for Error {
       Error::PARSER => write!(f, "Parser error"),
       Error::LEXER(e) => write!(f, "{}", e),
       Error::REGEX(e) => write!(f, "{}", e),
+      Error::LimitExceeded { limit, max } => write!(f, "maximum {} of {} exceeded", limit, max),
     }
   }
 }
 
-#[cfg(feature = "std")]
-impl std::error::Error for Error {
-  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+impl error::Error for Error {
+  fn source(&self) -> Option<&(dyn error::Error + 'static)> {
     match self {
       Error::LEXER(le) => Some(le),
+      #[cfg(feature = "std")]
       Error::REGEX(re) => Some(re),
       _ => None,
     }
@@ -104,16 +180,20 @@ where
   /// let input = r#"mycddl = ( int / float )"#;
   /// let p = Parser::new(Lexer::new(input).iter(), input);
   /// ```
-  pub fn new(tokens: I, str_input: &'a str) -> Result<Parser<I>> {
+  pub fn new(tokens: I, str_input: &'a str) -> Result<Parser<'a, I>> {
     let mut p = Parser {
       tokens,
       str_input,
       cur_token: Token::EOF,
       peek_token: Token::EOF,
       errors: Vec::default(),
+      is_prelude: false,
       lexer_position: Position::default(),
       peek_lexer_position: Position::default(),
       parser_position: Position::default(),
+      options: ParserOptions::default(),
+      depth: 0,
+      limit_exceeded: None,
     };
 
     p.next_token()?;
@@ -122,6 +202,11 @@ where
     Ok(p)
   }
 
+  /// Limits the CDDL accepted by the parser. See `ParserOptions`.
+  pub fn set_options(&mut self, options: ParserOptions) {
+    self.options = options;
+  }
+
   /// Print parser errors if there are any. Used with the `Error::PARSER`
   /// variant
   ///
@@ -232,54 +317,157 @@ where
 
     term::emit(&mut writer, &config, &files, &diagnostic).ok()?;
 
-    Some(String::from_utf8(buffer).ok()?)
+    String::from_utf8(buffer).ok()
   }
 
   fn next_token(&mut self) -> Result<()> {
     mem::swap(&mut self.cur_token, &mut self.peek_token);
     mem::swap(&mut self.lexer_position, &mut self.peek_lexer_position);
 
-    if let Some(next_token) = self.tokens.next() {
-      let nt = next_token.map_err(Error::LEXER)?;
-      self.peek_lexer_position = nt.0;
-      self.peek_token = nt.1;
+    match self.tokens.next() {
+      Some(Ok(nt)) => {
+        self.peek_lexer_position = nt.0;
+        self.peek_token = nt.1;
+      }
+      Some(Err(e)) => {
+        // The lexer skips past the offending input, so parsing can resume
+        // after it. Until then, the error stands in for the token that
+        // couldn't be lexed.
+        self.peek_lexer_position = e.position();
+        self.peek_token = Token::ILLEGAL("");
+
+        return Err(Error::LEXER(e));
+      }
+      None => (),
     }
 
     Ok(())
   }
 
-  fn advance_to_next_rule(&mut self) -> Result<()> {
-    let mut is_possible_rule = false;
+  // Skips tokens until the start of a rule other than the one beginning at the
+  // given offset, recording any lexer errors along the way
+  fn advance_to_next_rule(&mut self, rule_start: usize) {
+    loop {
+      if self.cur_token_is(Token::EOF)
+        || (self.is_rule_start() && self.lexer_position.range.0 > rule_start)
+      {
+        return;
+      }
 
-    while !is_possible_rule {
-      self.next_token()?;
-      if let Token::IDENT(_) = self.cur_token {
-        match self.peek_token {
-          Token::ASSIGN | Token::TCHOICEALT | Token::GCHOICEALT => is_possible_rule = true,
-          _ => continue,
-        }
-      } else if let Token::EOF = self.cur_token {
-        is_possible_rule = true;
+      if let Err(e) = self.next_token() {
+        self.record_error(e);
+      }
+    }
+  }
+
+  // Returns whether the current token is the name of a rule being defined.
+  // Rules with generic parameters aren't recognized, since telling them apart
+  // from references with generic arguments takes more than one token of
+  // lookahead.
+  fn is_rule_start(&self) -> bool {
+    if let Token::IDENT(_) = self.cur_token {
+      if let Token::ASSIGN | Token::TCHOICEALT | Token::GCHOICEALT = self.peek_token {
+        return true;
       }
     }
 
+    false
+  }
+
+  // Descends into a nested type or group, failing if that exceeds the maximum
+  // nesting depth. The depth is restored by the caller once it's done.
+  fn enter_nesting(&mut self) -> Result<()> {
+    let max = self
+      .options
+      .max_nesting_depth
+      .unwrap_or(DEFAULT_MAX_NESTING_DEPTH);
+
+    if self.depth >= max {
+      return Err(Error::LimitExceeded {
+        limit: Limit::NestingDepth,
+        max,
+      });
+    }
+
+    self.depth += 1;
+
     Ok(())
   }
 
+  fn check_choices(&self, choices: usize) -> Result<()> {
+    match self.options.max_choices {
+      Some(max) if choices > max => Err(Error::LimitExceeded {
+        limit: Limit::Choices,
+        max,
+      }),
+      _ => Ok(()),
+    }
+  }
+
+  // Records an error that isn't already recorded as a parser error
+  fn record_error(&mut self, e: Error) {
+    let (position, message) = match e {
+      Error::PARSER => return,
+      Error::LEXER(le) => (le.position(), le.message()),
+      Error::LimitExceeded { limit, max } => {
+        self.limit_exceeded = Some((limit, max));
+
+        (self.lexer_position, e.to_string())
+      }
+      e => (self.lexer_position, e.to_string()),
+    };
+
+    self.errors.push(ParserError { position, message });
+  }
+
   /// Parses into a `CDDL` AST
   pub fn parse_cddl(&mut self) -> Result<CDDL<'a>> {
+    let c = self.parse_cddl_recovering();
+
+    if let Some((limit, max)) = self.limit_exceeded {
+      return Err(Error::LimitExceeded { limit, max });
+    }
+
+    if !self.errors.is_empty() {
+      return Err(Error::PARSER);
+    }
+
+    Ok(c)
+  }
+
+  /// Parses into a `CDDL` AST, recovering from syntax errors by skipping to
+  /// the start of the next rule. Returns the rules that could be parsed, with
+  /// every error recorded in `errors`, which is useful when a partial AST is
+  /// better than none, e.g. in editors. Parsing stops altogether once a limit
+  /// set by `ParserOptions` is exceeded.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{lexer::Lexer, parser::Parser};
+  ///
+  /// let input = "a = [ int\nb = { c: }\nd = tstr";
+  /// let mut lexer = Lexer::new(input);
+  /// let mut p = Parser::new(lexer.iter(), input).unwrap();
+  ///
+  /// let c = p.parse_cddl_recovering();
+  /// assert_eq!(c.rules.len(), 1);
+  /// assert_eq!(p.errors.len(), 2);
+  /// ```
+  pub fn parse_cddl_recovering(&mut self) -> CDDL<'a> {
     let mut c = CDDL::default();
 
     while self.cur_token != Token::EOF {
-      while let Token::COMMENT(_) = self.cur_token {
-        self.next_token()?;
-      }
-      match self.parse_rule() {
+      let rule_start = self.lexer_position.range.0;
+
+      match self.parse_rule_skipping_comments() {
         Ok(r) => {
-          let rule_exists = |existing_rule: &Rule| {
-            r.name() == existing_rule.name() && !existing_rule.is_choice_alternate()
-          };
-          if c.rules.iter().any(rule_exists) {
+          let name = r.name();
+          if !r.is_choice_alternate()
+            && c
+              .rules_named(&name)
+              .any(|existing_rule| !existing_rule.is_choice_alternate())
+          {
             self.parser_position.range = (r.span().0, r.span().1);
             self.parser_position.line = r.span().2;
 
@@ -291,18 +479,28 @@ where
             continue;
           }
 
-          c.rules.push(r);
+          c.push_rule(r);
 
-          while let Token::COMMENT(_) = self.cur_token {
-            self.next_token()?;
+          if let Some(max) = self.options.max_rules {
+            if c.rules.len() > max {
+              self.record_error(Error::LimitExceeded {
+                limit: Limit::Rules,
+                max,
+              });
+
+              return c;
+            }
           }
         }
-        Err(Error::PARSER) => {
-          if !self.cur_token_is(Token::EOF) {
-            self.advance_to_next_rule()?;
-          }
+        Err(e @ Error::LimitExceeded { .. }) => {
+          self.record_error(e);
+
+          return c;
+        }
+        Err(e) => {
+          self.record_error(e);
+          self.advance_to_next_rule(rule_start);
         }
-        _ => continue,
       }
     }
 
@@ -320,11 +518,42 @@ where
     // a type, or as a group both when "b" is a group and when "b" is a type (a
     // good convention to make the latter case stand out to the human reader is
     // to write "a = (b,)")."
-    if !self.errors.is_empty() {
-      return Err(Error::PARSER);
+    for cycle in unproductive_cycles(&c) {
+      if let Some(r) = c
+        .rules
+        .iter()
+        .find(|r| rule_identifier(r).to_string() == cycle[0])
+      {
+        self.parser_position.range = (r.span().0, r.span().1);
+        self.parser_position.line = r.span().2;
+
+        self.errors.push(ParserError {
+          position: self.parser_position,
+          message: format!(
+            "Rule '{}' references itself without an intermediate map, array or tag: {}",
+            cycle[0],
+            cycle.join(" -> ")
+          ),
+        });
+      }
     }
 
-    Ok(c)
+    c
+  }
+
+  // Parses a rule along with the comments surrounding it
+  fn parse_rule_skipping_comments(&mut self) -> Result<Rule<'a>> {
+    while let Token::COMMENT(_) = self.cur_token {
+      self.next_token()?;
+    }
+
+    let r = self.parse_rule()?;
+
+    while let Token::COMMENT(_) = self.cur_token {
+      self.next_token()?;
+    }
+
+    Ok(r)
   }
 
   fn parse_rule(&mut self) -> Result<Rule<'a>> {
@@ -335,8 +564,15 @@ where
       self.next_token()?;
     }
 
-    let ident = match &self.cur_token {
-      Token::IDENT(i) => self.identifier_from_ident_token(*i),
+    let prelude_ident = if self.is_prelude {
+      self.cur_token.in_standard_prelude()
+    } else {
+      None
+    };
+
+    let ident = match (&self.cur_token, prelude_ident) {
+      (Token::IDENT(i), _) => self.identifier_from_ident_token(*i),
+      (_, Some(ident)) => self.identifier_from_ident_token((ident, None)),
       _ => {
         self.parser_position.range = self.lexer_position.range;
         self.parser_position.line = self.lexer_position.line;
@@ -397,47 +633,29 @@ where
 
     match self.cur_token {
       // Check for an occurrence indicator of uint followed by an asterisk '*'
-      Token::VALUE(token::Value::UINT(_)) => {
-        if self.peek_token_is(&Token::ASTERISK) {
-          let ge = self.parse_grpent(true)?;
+      Token::VALUE(token::Value::UINT(_)) if self.peek_token_is(&Token::ASTERISK) => {
+        let ge = self.parse_grpent(true)?;
 
-          let span = (
-            begin_rule_range,
-            self.parser_position.range.1,
-            begin_rule_line,
-          );
+        let span = (
+          begin_rule_range,
+          self.parser_position.range.1,
+          begin_rule_line,
+        );
 
-          Ok(Rule::Group {
-            rule: Box::from(GroupRule {
-              name: ident,
-              generic_param: gp,
-              is_group_choice_alternate,
-              entry: ge,
-            }),
-            span,
-          })
-        } else {
-          let t = self.parse_type(None)?;
-          let span = (
-            begin_rule_range,
-            self.parser_position.range.1,
-            begin_rule_line,
-          );
-          Ok(Rule::Type {
-            rule: TypeRule {
-              name: ident,
-              generic_param: gp,
-              is_type_choice_alternate,
-              value: t,
-            },
-            span,
-          })
-        }
+        Ok(Rule::Group {
+          rule: Box::from(GroupRule {
+            name: ident,
+            generic_param: gp,
+            is_group_choice_alternate,
+            entry: ge,
+          }),
+          span,
+        })
       }
       Token::LPAREN | Token::ASTERISK | Token::ONEORMORE | Token::OPTIONAL => {
         let begin_pt_range = self.lexer_position.range.0;
 
-        let ge = self.parse_grpent(true)?;
+        let mut ge = self.parse_grpent(true)?;
 
         let mut end_rule_range = self.parser_position.range.1;
 
@@ -445,48 +663,28 @@ where
         // indicator, and its group has only a single element that is not
         // preceded by an occurrence indicator nor member key, treat it as a
         // parenthesized type, subsequently parsing the remaining type and
-        // returning the type rule. This is one of the few situations where
-        // `clone` is required
-        if let GroupEntry::InlineGroup {
-          occur: None, group, ..
-        } = &ge
-        {
-          if group.group_choices.len() == 1 {
-            if let Some(gc) = group.group_choices.get(0) {
-              if gc.group_entries.len() == 1 {
-                if let Some(ge) = gc.group_entries.get(0) {
-                  // Check that there is no trailing comma
-                  if !ge.1 {
-                    // TODO: Replace with box pattern destructuring once supported in stable
-                    if let GroupEntry::ValueMemberKey { ge, .. } = &ge.0 {
-                      if ge.occur.is_none() && ge.member_key.is_none() {
-                        let value = self.parse_type(Some(Type2::ParenthesizedType {
-                          pt: ge.entry_type.clone(),
-                          span: (
-                            begin_pt_range,
-                            self.parser_position.range.1,
-                            begin_rule_line,
-                          ),
-                        }))?;
-
-                        end_rule_range = self.parser_position.range.1;
-
-                        return Ok(Rule::Type {
-                          rule: TypeRule {
-                            name: ident,
-                            generic_param: gp,
-                            is_type_choice_alternate,
-                            value,
-                          },
-                          span: (begin_rule_range, end_rule_range, begin_rule_line),
-                        });
-                      }
-                    }
-                  }
-                }
-              }
-            }
-          }
+        // returning the type rule
+        if let Some(pt) = take_parenthesized_type(&mut ge) {
+          let value = self.parse_type(Some(Type2::ParenthesizedType {
+            pt,
+            span: (
+              begin_pt_range,
+              self.parser_position.range.1,
+              begin_rule_line,
+            ),
+          }))?;
+
+          end_rule_range = self.parser_position.range.1;
+
+          return Ok(Rule::Type {
+            rule: TypeRule {
+              name: ident,
+              generic_param: gp,
+              is_type_choice_alternate,
+              value,
+            },
+            span: (begin_rule_range, end_rule_range, begin_rule_line),
+          });
         }
 
         Ok(Rule::Group {
@@ -664,6 +862,7 @@ where
       }
 
       t.type_choices.push(self.parse_type1(None)?);
+      self.check_choices(t.type_choices.len())?;
     }
 
     t.span.1 = self.parser_position.range.1;
@@ -742,6 +941,14 @@ where
   }
 
   fn parse_type2(&mut self) -> Result<Type2<'a>> {
+    self.enter_nesting()?;
+    let t2 = self.parse_type2_nested();
+    self.depth -= 1;
+
+    t2
+  }
+
+  fn parse_type2_nested(&mut self) -> Result<Type2<'a>> {
     let t2 = match &self.cur_token {
       // value
       Token::VALUE(value) => {
@@ -755,7 +962,10 @@ where
         );
 
         match value {
-          token::Value::TEXT(t) => Ok(Type2::TextValue { value: t, span }),
+          token::Value::TEXT(t) => Ok(Type2::TextValue {
+            value: t.clone(),
+            span,
+          }),
           token::Value::INT(i) => Ok(Type2::IntValue { value: *i, span }),
           token::Value::UINT(ui) => Ok(Type2::UintValue { value: *ui, span }),
           token::Value::FLOAT(f) => Ok(Type2::FloatValue { value: *f, span }),
@@ -890,6 +1100,9 @@ where
 
       // ~ typename [genericarg]
       Token::UNWRAP => {
+        let begin_type2_range = self.lexer_position.range.0;
+        let begin_type2_line = self.lexer_position.line;
+
         self.next_token()?;
 
         if let Token::IDENT(ident) = &self.cur_token {
@@ -898,26 +1111,36 @@ where
           if self.peek_token_is(&Token::LANGLEBRACKET) {
             self.next_token()?;
 
+            let generic_arg = Some(self.parse_genericarg()?);
+
             return Ok(Type2::Unwrap {
               ident,
-              generic_arg: Some(self.parse_genericarg()?),
-              span: (0, 0, 0),
+              generic_arg,
+              span: (
+                begin_type2_range,
+                self.parser_position.range.1,
+                begin_type2_line,
+              ),
             });
           }
 
-          return Ok(Type2::Unwrap {
+          Ok(Type2::Unwrap {
             ident,
             generic_arg: None,
-            span: (0, 0, 0),
+            span: (
+              begin_type2_range,
+              self.lexer_position.range.1,
+              begin_type2_line,
+            ),
+          })
+        } else {
+          self.errors.push(ParserError {
+            position: self.parser_position,
+            message: "Invalid unwrap syntax".into(),
           });
-        }
-
-        self.errors.push(ParserError {
-          position: self.parser_position,
-          message: "Invalid unwrap syntax".into(),
-        });
 
-        Err(Error::PARSER)
+          Err(Error::PARSER)
+        }
       }
 
       // & ( group )
@@ -1096,6 +1319,14 @@ where
   }
 
   fn parse_group(&mut self) -> Result<Group<'a>> {
+    self.enter_nesting()?;
+    let group = self.parse_group_nested();
+    self.depth -= 1;
+
+    group
+  }
+
+  fn parse_group_nested(&mut self) -> Result<Group<'a>> {
     let begin_group_range = if self.cur_token_is(Token::LBRACE)
       || self.cur_token_is(Token::LPAREN)
       || self.cur_token_is(Token::LBRACKET)
@@ -1125,6 +1356,7 @@ where
       }
 
       group.group_choices.push(self.parse_grpchoice()?);
+      self.check_choices(group.group_choices.len())?;
     }
 
     group.span.1 = self.parser_position.range.1;
@@ -1158,6 +1390,10 @@ where
     {
       self.next_token()?;
 
+      while let Token::COMMENT(_) = self.cur_token {
+        self.next_token()?;
+      }
+
       grpchoice.span.0 = self.lexer_position.range.0;
     };
 
@@ -1169,6 +1405,23 @@ where
       && !self.cur_token_is(Token::RBRACKET)
       && !self.cur_token_is(Token::EOF)
     {
+      // A group can't contain an assignment, so the group must be missing its
+      // closing delimiter and the next rule has been reached
+      if self.is_rule_start() {
+        self.parser_position.range = self.lexer_position.range;
+        self.parser_position.line = self.lexer_position.line;
+
+        self.errors.push(ParserError {
+          position: self.parser_position,
+          message: format!(
+            "missing closing delimiter before the definition of '{}'",
+            self.cur_token
+          ),
+        });
+
+        return Err(Error::PARSER);
+      }
+
       let ge = self.parse_grpent(false)?;
 
       if self.cur_token_is(Token::GCHOICE) {
@@ -1190,6 +1443,7 @@ where
         && !self.peek_token_is(&Token::COLON)
         && !self.peek_token_is(&Token::ARROWMAP)
         && !self.cur_token_is(Token::EOF)
+        && !self.is_rule_start()
       {
         self.parser_position.range.1 = self.lexer_position.range.1;
         self.next_token()?;
@@ -1234,7 +1488,9 @@ where
       self.next_token()?;
     }
 
-    if self.cur_token_is(Token::LPAREN) {
+    // Parentheses following a member key enclose a type rather than a group,
+    // e.g. ? step: (number .gt 0) .default 1
+    if member_key.is_none() && self.cur_token_is(Token::LPAREN) {
       self.next_token()?;
 
       while let Token::COMMENT(_) = self.cur_token {
@@ -1334,7 +1590,65 @@ where
         })
       }
       _ => {
-        let entry_type = self.parse_type(None)?;
+        let mut entry_type = self.parse_type(None)?;
+
+        while let Token::COMMENT(_) = self.cur_token {
+          self.next_token()?;
+        }
+
+        // Member keys referring to generic rules, e.g. label<int> => bstr,
+        // aren't known to be member keys until the "=>" following their
+        // generic arguments is reached
+        if entry_type.type_choices.len() == 1
+          && (self.cur_token_is(Token::ARROWMAP) || self.cur_token_is(Token::CUT))
+        {
+          let is_cut = self.cur_token_is(Token::CUT);
+          if is_cut {
+            self.next_token()?;
+
+            while let Token::COMMENT(_) = self.cur_token {
+              self.next_token()?;
+            }
+
+            if !self.cur_token_is(Token::ARROWMAP) {
+              self.errors.push(ParserError {
+                position: self.lexer_position,
+                message: "Malformed memberkey. Missing \"=>\"".into(),
+              });
+              return Err(Error::PARSER);
+            }
+          }
+
+          let t1 = entry_type.type_choices.remove(0);
+          let member_key = MemberKey::Type1 {
+            span: (t1.span.0, self.lexer_position.range.1, t1.span.2),
+            t1: Box::from(t1),
+            is_cut,
+          };
+
+          self.next_token()?;
+
+          while let Token::COMMENT(_) = self.cur_token {
+            self.next_token()?;
+          }
+
+          let entry_type = self.parse_type(None)?;
+
+          span.1 = self.parser_position.range.1;
+
+          if self.cur_token_is(Token::COMMA) {
+            span.1 = self.lexer_position.range.1;
+          }
+
+          return Ok(GroupEntry::ValueMemberKey {
+            ge: Box::from(ValueMemberKeyEntry {
+              occur,
+              member_key: Some(member_key),
+              entry_type,
+            }),
+            span,
+          });
+        }
 
         span.1 = self.parser_position.range.1;
 
@@ -1384,7 +1698,7 @@ where
 
     let end_t1_range = self.lexer_position.range.1;
 
-    let mut ident = self.identifier_from_ident_token((&ident.0, ident.1));
+    let mut ident = self.identifier_from_ident_token((ident.0, ident.1));
     ident.span = (begin_memberkey_range, end_t1_range, begin_memberkey_line);
 
     self.next_token()?;
@@ -1621,6 +1935,8 @@ where
         // Parse tokens vec as group
         if has_group_entries {
           let mut p = Parser::new(tokens.into_iter(), self.str_input)?;
+          p.options = self.options;
+          p.depth = self.depth;
           let group = match p.parse_group() {
             Ok(g) => g,
             Err(Error::PARSER) => {
@@ -1644,7 +1960,9 @@ where
 
         // Parse tokens vec as type
         let mut p = Parser::new(tokens.into_iter(), self.str_input)?;
-        let t = match p.parse_type(None) {
+        p.options = self.options;
+        p.depth = self.depth;
+        let mut t = match p.parse_type(None) {
           Ok(t) => t,
           Err(Error::PARSER) => {
             for e in p.errors.into_iter() {
@@ -1678,7 +1996,7 @@ where
           let end_memberkey_range = self.lexer_position.range.1;
 
           let t1 = Some(MemberKey::Type1 {
-            t1: Box::from(t.type_choices[0].clone()),
+            t1: Box::from(t.type_choices.swap_remove(0)),
             is_cut: true,
             span: (
               begin_memberkey_range,
@@ -1696,7 +2014,7 @@ where
           self.parser_position.range.1 = self.lexer_position.range.1;
 
           Some(MemberKey::Type1 {
-            t1: Box::from(t.type_choices[0].clone()),
+            t1: Box::from(t.type_choices.swap_remove(0)),
             is_cut: false,
             span: (
               begin_memberkey_range,
@@ -1777,8 +2095,21 @@ where
             ),
           })
         } else {
+          // The type of an entry without a member key, e.g. [int] / 1
+          let mut type_choices = vec![t1];
+          while self.cur_token_is(Token::TCHOICE) {
+            self.next_token()?;
+
+            while let Token::COMMENT(_) = self.cur_token {
+              self.next_token()?;
+            }
+
+            type_choices.push(self.parse_type1(None)?);
+            self.check_choices(type_choices.len())?;
+          }
+
           Some(MemberKey::NonMemberKey(NonMemberKey::Type(Type {
-            type_choices: vec![t1],
+            type_choices,
             span: (
               begin_memberkey_range,
               self.parser_position.range.1,
@@ -1825,7 +2156,7 @@ where
 
           Occur::Exact {
             lower: None,
-            upper: Some(*u),
+            upper: Some(*u as usize),
             span: (
               self.parser_position.range.0,
               self.parser_position.range.1,
@@ -1850,12 +2181,8 @@ where
         Ok(Some(o))
       }
       Token::VALUE(_) => {
-        let lower = if let Token::VALUE(value) = &self.cur_token {
-          if let token::Value::UINT(li) = value {
-            Some(*li)
-          } else {
-            None
-          }
+        let lower = if let Token::VALUE(token::Value::UINT(li)) = &self.cur_token {
+          Some(*li as usize)
         } else {
           None
         };
@@ -1879,17 +2206,18 @@ where
 
         self.next_token()?;
 
-        let upper = if let Token::VALUE(value) = &self.cur_token {
-          if let token::Value::UINT(ui) = value {
-            self.parser_position.range.1 = self.lexer_position.range.1;
-            Some(*ui)
-          } else {
-            None
-          }
+        let upper = if let Token::VALUE(token::Value::UINT(ui)) = &self.cur_token {
+          Some(*ui as usize)
         } else {
           None
         };
 
+        // The upper bound of n*m isn't part of the entry that follows
+        if upper.is_some() {
+          self.parser_position.range.1 = self.lexer_position.range.1;
+          self.next_token()?;
+        }
+
         Ok(Some(Occur::Exact {
           lower,
           upper,
@@ -1909,7 +2237,7 @@ where
   }
 
   fn peek_token_is(&self, t: &Token) -> bool {
-    mem::discriminant(&self.peek_token) == mem::discriminant(&t)
+    mem::discriminant(&self.peek_token) == mem::discriminant(t)
   }
 
   fn expect_peek(&mut self, t: &Token) -> Result<bool> {
@@ -1937,6 +2265,172 @@ where
   }
 }
 
+// Returns the cycles of rules that reference one another without consuming
+// any data along the way, e.g. `a = b` and `b = a`. Such rules can't be
+// resolved and would otherwise recurse indefinitely during validation. Each
+// cycle begins and ends with the name of the same rule.
+// Takes the type out of an inline group entry that's only made up of a type,
+// e.g. `(tstr / int)`
+fn take_parenthesized_type<'a>(ge: &mut GroupEntry<'a>) -> Option<Type<'a>> {
+  let group = match ge {
+    GroupEntry::InlineGroup {
+      occur: None, group, ..
+    } => group,
+    _ => return None,
+  };
+
+  let is_type = match group.group_choices.as_slice() {
+    [gc] => match gc.group_entries.as_slice() {
+      // A trailing comma makes it a group
+      [(GroupEntry::ValueMemberKey { ge, .. }, false)] => {
+        ge.occur.is_none() && ge.member_key.is_none()
+      }
+      _ => false,
+    },
+    _ => false,
+  };
+
+  if !is_type {
+    return None;
+  }
+
+  match group
+    .group_choices
+    .pop()
+    .and_then(|mut gc| gc.group_entries.pop())
+  {
+    Some((GroupEntry::ValueMemberKey { ge, .. }, _)) => Some(ge.entry_type),
+    _ => None,
+  }
+}
+
+// Returns the cycles of rules that reference each other without an
+// intermediate map, array or tag, each as the names along the cycle. Rules are
+// identified by their names including any socket prefix, as `$a` and `a` are
+// distinct names even though rules are looked up by the name without it.
+fn unproductive_cycles(cddl: &CDDL) -> Vec<Vec<String>> {
+  let mut names: Vec<String> = Vec::new();
+  let mut references: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+  for rule in cddl.rules.iter() {
+    let generic_param = match rule {
+      Rule::Type { rule, .. } => &rule.generic_param,
+      Rule::Group { rule, .. } => &rule.generic_param,
+    };
+    let name = rule_identifier(rule).to_string();
+
+    let mut refs = Vec::new();
+    match rule {
+      Rule::Type { rule, .. } => type_references(&rule.value, &mut refs),
+      Rule::Group { rule, .. } => group_entry_references(&rule.entry, &mut refs),
+    }
+
+    // References to generic parameters are substituted with the generic
+    // arguments rather than resolved to rules
+    refs.retain(|r| {
+      cddl.rule(r.ident).is_some()
+        && !generic_param
+          .iter()
+          .flat_map(|gp| gp.params.iter())
+          .any(|p| p.ident == r.ident)
+    });
+
+    if !references.contains_key(&name) {
+      names.push(name.clone());
+    }
+    references
+      .entry(name)
+      .or_default()
+      .extend(refs.into_iter().map(|r| r.to_string()));
+  }
+
+  let mut visited = BTreeMap::new();
+  let mut path = Vec::new();
+  let mut cycles = Vec::new();
+
+  for name in names.iter() {
+    if !visited.contains_key(name.as_str()) {
+      find_cycles(name, &references, &mut visited, &mut path, &mut cycles);
+    }
+  }
+
+  cycles
+}
+
+fn rule_identifier<'a, 'b>(rule: &'b Rule<'a>) -> &'b Identifier<'a> {
+  match rule {
+    Rule::Type { rule, .. } => &rule.name,
+    Rule::Group { rule, .. } => &rule.name,
+  }
+}
+
+// Depth-first search for cycles, where a rule maps to `false` while the rules
+// it references are being searched and `true` once they've all been searched
+fn find_cycles<'b>(
+  name: &'b str,
+  references: &'b BTreeMap<String, Vec<String>>,
+  visited: &mut BTreeMap<&'b str, bool>,
+  path: &mut Vec<&'b str>,
+  cycles: &mut Vec<Vec<String>>,
+) {
+  visited.insert(name, false);
+  path.push(name);
+
+  if let Some(refs) = references.get(name) {
+    for r in refs.iter() {
+      match visited.get(r.as_str()) {
+        Some(false) => {
+          if let Some(start) = path.iter().position(|p| p == r) {
+            let mut cycle = path[start..]
+              .iter()
+              .map(|p| p.to_string())
+              .collect::<Vec<_>>();
+            cycle.push(r.clone());
+            cycles.push(cycle);
+          }
+        }
+        Some(true) => (),
+        None => find_cycles(r, references, visited, path, cycles),
+      }
+    }
+  }
+
+  path.pop();
+  visited.insert(name, true);
+}
+
+// Collects the names referenced by the choices of a type that would be
+// validated against the same data as the type itself
+fn type_references<'a, 'b>(t: &'b Type<'a>, refs: &mut Vec<&'b Identifier<'a>>) {
+  for tc in t.type_choices.iter() {
+    match &tc.type2 {
+      Type2::Typename { ident, .. } | Type2::Unwrap { ident, .. } => refs.push(ident),
+      Type2::ParenthesizedType { pt, .. } => type_references(pt, refs),
+      _ => (),
+    }
+  }
+}
+
+// Collects the names referenced by a group entry that would be validated
+// against the same data as the entry itself. Entries accompanied by sibling
+// entries consume data of their own and are therefore skipped.
+fn group_entry_references<'a, 'b>(ge: &'b GroupEntry<'a>, refs: &mut Vec<&'b Identifier<'a>>) {
+  match ge {
+    GroupEntry::TypeGroupname { ge, .. } => refs.push(&ge.name),
+    GroupEntry::ValueMemberKey { ge, .. } if ge.member_key.is_none() => {
+      type_references(&ge.entry_type, refs)
+    }
+    GroupEntry::InlineGroup { group, .. } => {
+      for gc in group.group_choices.iter() {
+        if gc.group_entries.len() == 1 {
+          group_entry_references(&gc.group_entries[0].0, refs);
+        }
+      }
+    }
+    _ => (),
+  }
+}
+
 /// Returns a `ast::CDDL` from a `&str`
 ///
 /// # Arguments
@@ -1956,7 +2450,7 @@ where
 #[cfg(not(target_arch = "wasm32"))]
 #[cfg(feature = "std")]
 pub fn cddl_from_str<'a>(
-  lexer: &'a mut Lexer<'a>,
+  lexer: &mut Lexer<'a>,
   input: &'a str,
   print_stderr: bool,
 ) -> std::result::Result<CDDL<'a>, String> {
@@ -2002,7 +2496,7 @@ pub fn cddl_from_str<'a>(
 #[cfg(not(target_arch = "wasm32"))]
 #[cfg(not(feature = "std"))]
 pub fn cddl_from_str<'a>(
-  lexer: &'a mut Lexer<'a>,
+  lexer: &mut Lexer<'a>,
   input: &'a str,
 ) -> std::result::Result<CDDL<'a>, String> {
   match Parser::new(lexer.iter(), input).map_err(|e| e.to_string()) {
@@ -2021,6 +2515,32 @@ pub fn cddl_from_str<'a>(
   }
 }
 
+/// Parses CDDL text without printing errors to stderr, as an alternative to
+/// `cddl_from_str` that doesn't require creating a lexer
+///
+/// # Example
+///
+/// ```
+/// use cddl::ast::CDDL;
+/// use std::convert::TryFrom;
+///
+/// let cddl = CDDL::try_from("myrule = int").unwrap();
+/// assert_eq!(cddl.rules.len(), 1);
+///
+/// assert!(CDDL::try_from("myrule = ").is_err());
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+impl<'a> TryFrom<&'a str> for CDDL<'a> {
+  type Error = String;
+
+  fn try_from(input: &'a str) -> result::Result<Self, Self::Error> {
+    #[cfg(feature = "std")]
+    return cddl_from_str(&mut Lexer::new(input), input, false);
+    #[cfg(not(feature = "std"))]
+    return cddl_from_str(&mut Lexer::new(input), input);
+  }
+}
+
 /// Returns a `ast::CDDL` wrapped in `JsValue` from a `&str`
 ///
 /// # Arguments
@@ -2197,7 +2717,7 @@ mod tests {
               Rule::Type {
                 rule: TypeRule {
                   name: Identifier {
-                    ident: "myrule".into(),
+                    ident: "myrule",
                     socket: None,
                     span: (0, 6, 1),
                   },
@@ -2207,7 +2727,7 @@ mod tests {
                     type_choices: vec![Type1 {
                       type2: Type2::Typename {
                         ident: Identifier {
-                          ident: "secondrule".into(),
+                          ident: "secondrule",
                           socket: None,
                           span: (9, 19, 1),
                         },
@@ -2225,7 +2745,7 @@ mod tests {
               Rule::Type {
                 rule: TypeRule {
                   name: Identifier {
-                    ident: "myrange".into(),
+                    ident: "myrange",
                     socket: None,
                     span: (20, 27, 2),
                   },
@@ -2244,7 +2764,7 @@ mod tests {
                         },
                         Type2::Typename {
                           ident: Identifier {
-                            ident: "upper".into(),
+                            ident: "upper",
                             socket: None,
                             span: (34, 39, 2),
                           },
@@ -2262,7 +2782,7 @@ mod tests {
               Rule::Type {
                 rule: TypeRule {
                   name: Identifier {
-                    ident: "upper".into(),
+                    ident: "upper",
                     socket: None,
                     span: (40, 45, 3),
                   },
@@ -2295,7 +2815,7 @@ mod tests {
               Rule::Group {
                 rule: Box::from(GroupRule {
                   name: Identifier {
-                    ident: "gr".into(),
+                    ident: "gr",
                     socket: None,
                     span: (58, 60, 4),
                   },
@@ -2314,7 +2834,7 @@ mod tests {
                             ge: TypeGroupnameEntry {
                               occur: None,
                               name: Identifier {
-                                ident: "test".into(),
+                                ident: "test",
                                 socket: None,
                                 span: (68, 72, 4),
                               },
@@ -2336,7 +2856,7 @@ mod tests {
               Rule::Type {
                 rule: TypeRule {
                   name: Identifier {
-                    ident: "messages".into(),
+                    ident: "messages",
                     socket: None,
                     span: (75, 83, 5),
                   },
@@ -2346,7 +2866,7 @@ mod tests {
                     type_choices: vec![Type1 {
                       type2: Type2::Typename {
                         ident: Identifier {
-                          ident: "message".into(),
+                          ident: "message",
                           socket: None,
                           span: (86, 93, 5),
                         },
@@ -2384,19 +2904,19 @@ mod tests {
               Rule::Type {
                 rule: TypeRule {
                   name: Identifier {
-                    ident: "message".into(),
+                    ident: "message",
                     socket: None,
                     span: (111, 118, 6),
                   },
                   generic_param: Some(GenericParm {
                     params: vec![
                       Identifier {
-                        ident: "t".into(),
+                        ident: "t",
                         socket: None,
                         span: (119, 120, 6),
                       },
                       Identifier {
-                        ident: "v".into(),
+                        ident: "v",
                         socket: None,
                         span: (122, 123, 6),
                       },
@@ -2416,7 +2936,7 @@ mod tests {
                                     occur: None,
                                     member_key: Some(MemberKey::Bareword {
                                       ident: Identifier {
-                                        ident: "type".into(),
+                                        ident: "type",
                                         socket: None,
                                         span: (128, 132, 6),
                                       },
@@ -2444,7 +2964,7 @@ mod tests {
                                     occur: None,
                                     member_key: Some(MemberKey::Bareword {
                                       ident: Identifier {
-                                        ident: "value".into(),
+                                        ident: "value",
                                         socket: None,
                                         span: (137, 142, 6),
                                       },
@@ -2454,7 +2974,7 @@ mod tests {
                                       type_choices: vec![Type1 {
                                         type2: Type2::Typename {
                                           ident: Identifier {
-                                            ident: "v".into(),
+                                            ident: "v",
                                             socket: None,
                                             span: (144, 145, 6),
                                           },
@@ -2489,7 +3009,7 @@ mod tests {
               Rule::Type {
                 rule: TypeRule {
                   name: Identifier {
-                    ident: "color".into(),
+                    ident: "color",
                     socket: None,
                     span: (147, 152, 7),
                   },
@@ -2499,7 +3019,7 @@ mod tests {
                     type_choices: vec![Type1 {
                       type2: Type2::ChoiceFromGroup {
                         ident: Identifier {
-                          ident: "colors".into(),
+                          ident: "colors",
                           socket: None,
                           span: (156, 162, 7),
                         },
@@ -2517,7 +3037,7 @@ mod tests {
               Rule::Group {
                 rule: Box::from(GroupRule {
                   name: Identifier {
-                    ident: "colors".into(),
+                    ident: "colors",
                     socket: None,
                     span: (163, 169, 8),
                   },
@@ -2533,7 +3053,7 @@ mod tests {
                               occur: None,
                               member_key: Some(MemberKey::Bareword {
                                 ident: Identifier {
-                                  ident: "red".into(),
+                                  ident: "red",
                                   socket: None,
                                   span: (174, 177, 8),
                                 },
@@ -2567,7 +3087,7 @@ mod tests {
               Rule::Type {
                 rule: TypeRule {
                   name: Identifier {
-                    ident: "thing".into(),
+                    ident: "thing",
                     socket: None,
                     span: (187, 192, 9),
                   },
@@ -2581,7 +3101,7 @@ mod tests {
                             Type1 {
                               type2: Type2::Typename {
                                 ident: Identifier {
-                                  ident: "int".into(),
+                                  ident: "int",
                                   socket: None,
                                   span: (197, 200, 9),
                                 },
@@ -2594,7 +3114,7 @@ mod tests {
                             Type1 {
                               type2: Type2::Typename {
                                 ident: Identifier {
-                                  ident: "float".into(),
+                                  ident: "float",
                                   socket: None,
                                   span: (203, 208, 9),
                                 },
@@ -2619,6 +3139,7 @@ mod tests {
                 span: (187, 210, 9),
               },
             ],
+            ..Default::default()
           };
 
           assert_eq!(cddl, expected_output);
@@ -2645,6 +3166,48 @@ mod tests {
     }
   }
 
+  #[test]
+  fn verify_unproductive_cycles() -> Result<()> {
+    let input = indoc!(
+      r#"
+        a = b / int
+        b = (a)
+        tree = [* tree] / leaf
+        leaf = uint
+        list = (item: int, ? list)
+        wrapper<t> = t
+        t = wrapper<t>
+      "#
+    );
+
+    let mut lexer = Lexer::new(input);
+    let mut p = Parser::new(lexer.iter(), input)?;
+    assert!(p.parse_cddl().is_err());
+    assert_eq!(p.errors.len(), 1);
+    assert_eq!(
+      p.errors[0].message,
+      "Rule 'a' references itself without an intermediate map, array or tag: a -> b -> a"
+    );
+
+    let input = r#"group = (a: int, group // b: int)"#;
+    let mut lexer = Lexer::new(input);
+    let mut p = Parser::new(lexer.iter(), input)?;
+    assert!(p.parse_cddl().is_ok());
+
+    let input = r#"group = (group // b: int)"#;
+    let mut lexer = Lexer::new(input);
+    let mut p = Parser::new(lexer.iter(), input)?;
+    assert!(p.parse_cddl().is_err());
+
+    // A socket is distinct from a rule of the same name without the sigil
+    let input = "message = $message .within tstr\n$message /= \"hello\"";
+    let mut lexer = Lexer::new(input);
+    let mut p = Parser::new(lexer.iter(), input)?;
+    assert!(p.parse_cddl().is_ok());
+
+    Ok(())
+  }
+
   #[test]
   fn verify_rule_diagnostic() -> Result<()> {
     let input = indoc!(
@@ -2716,12 +3279,12 @@ mod tests {
     let expected_output = GenericParm {
       params: vec![
         Identifier {
-          ident: "t".into(),
+          ident: "t",
           socket: None,
           span: (1, 2, 1),
         },
         Identifier {
-          ident: "v".into(),
+          ident: "v",
           socket: None,
           span: (4, 5, 1),
         },
@@ -2918,7 +3481,7 @@ mod tests {
               Type1 {
                 type2: Type2::Typename {
                   ident: Identifier {
-                    ident: "tchoice1".into(),
+                    ident: "tchoice1",
                     socket: None,
                     span: (2, 10, 1),
                   },
@@ -2931,7 +3494,7 @@ mod tests {
               Type1 {
                 type2: Type2::Typename {
                   ident: Identifier {
-                    ident: "tchoice2".into(),
+                    ident: "tchoice2",
                     socket: None,
                     span: (13, 21, 1),
                   },
@@ -3024,7 +3587,7 @@ mod tests {
       Type1 {
         type2: Type2::Typename {
           ident: Identifier {
-            ident: "my..lower".into(),
+            ident: "my..lower",
             socket: None,
             span: (0, 9, 1),
           },
@@ -3038,7 +3601,7 @@ mod tests {
           },
           Type2::Typename {
             ident: Identifier {
-              ident: "upper".into(),
+              ident: "upper",
               socket: None,
               span: (14, 19, 1),
             },
@@ -3051,7 +3614,7 @@ mod tests {
       Type1 {
         type2: Type2::Typename {
           ident: Identifier {
-            ident: "target".into(),
+            ident: "target",
             socket: None,
             span: (0, 6, 1),
           },
@@ -3065,7 +3628,7 @@ mod tests {
           },
           Type2::Typename {
             ident: Identifier {
-              ident: "controller".into(),
+              ident: "controller",
               socket: None,
               span: (11, 21, 1),
             },
@@ -3082,7 +3645,7 @@ mod tests {
               Type1 {
                 type2: Type2::Typename {
                   ident: Identifier {
-                    ident: "text".into(),
+                    ident: "text",
                     socket: None,
                     span: (2, 6, 1),
                   },
@@ -3095,7 +3658,7 @@ mod tests {
               Type1 {
                 type2: Type2::Typename {
                   ident: Identifier {
-                    ident: "tstr".into(),
+                    ident: "tstr",
                     socket: None,
                     span: (9, 13, 1),
                   },
@@ -3160,7 +3723,7 @@ mod tests {
       },
       Type2::Typename {
         ident: Identifier {
-          ident: "message".into(),
+          ident: "message",
           socket: None,
           span: (0, 7, 1),
         },
@@ -3189,7 +3752,7 @@ mod tests {
       },
       Type2::Typename {
         ident: Identifier {
-          ident: "tcp-option".into(),
+          ident: "tcp-option",
           socket: Some(SocketPlug::GROUP),
           span: (0, 12, 1),
         },
@@ -3198,12 +3761,12 @@ mod tests {
       },
       Type2::Unwrap {
         ident: Identifier {
-          ident: "group1".into(),
+          ident: "group1",
           socket: None,
           span: (1, 7, 1),
         },
         generic_arg: None,
-        span: (0, 0, 0),
+        span: (0, 7, 1),
       },
       Type2::TaggedData {
         tag: Some(997),
@@ -3211,7 +3774,7 @@ mod tests {
           type_choices: vec![Type1 {
             type2: Type2::Typename {
               ident: Identifier {
-                ident: "tstr".into(),
+                ident: "tstr",
                 socket: None,
                 span: (7, 11, 1),
               },
@@ -3242,7 +3805,7 @@ mod tests {
                     span: (1, 3, 1),
                   }),
                   name: Identifier {
-                    ident: "reputon".into(),
+                    ident: "reputon",
                     socket: None,
                     span: (4, 11, 1),
                   },
@@ -3266,7 +3829,7 @@ mod tests {
                 ge: TypeGroupnameEntry {
                   occur: Some(Occur::OneOrMore((1, 2, 1))),
                   name: Identifier {
-                    ident: "reputon".into(),
+                    ident: "reputon",
                     socket: None,
                     span: (3, 10, 1),
                   },
@@ -3284,7 +3847,7 @@ mod tests {
       },
       Type2::ChoiceFromGroup {
         ident: Identifier {
-          ident: "groupname".into(),
+          ident: "groupname",
           socket: None,
           span: (1, 10, 1),
         },
@@ -3299,7 +3862,7 @@ mod tests {
                 ge: TypeGroupnameEntry {
                   occur: None,
                   name: Identifier {
-                    ident: "inlinegroup".into(),
+                    ident: "inlinegroup",
                     socket: None,
                     span: (3, 14, 1),
                   },
@@ -3338,7 +3901,7 @@ mod tests {
                     type_choices: vec![Type1 {
                       type2: Type2::Typename {
                         ident: Identifier {
-                          ident: "int".into(),
+                          ident: "int",
                           socket: None,
                           span: (24, 27, 1),
                         },
@@ -3375,7 +3938,7 @@ mod tests {
                             occur: None,
                             member_key: Some(MemberKey::Bareword {
                               ident: Identifier {
-                                ident: "a".into(),
+                                ident: "a",
                                 socket: None,
                                 span: (4, 5, 1),
                               },
@@ -3385,7 +3948,7 @@ mod tests {
                               type_choices: vec![Type1 {
                                 type2: Type2::Typename {
                                   ident: Identifier {
-                                    ident: "int".into(),
+                                    ident: "int",
                                     socket: None,
                                     span: (7, 10, 1),
                                   },
@@ -3408,7 +3971,7 @@ mod tests {
                             occur: None,
                             member_key: Some(MemberKey::Bareword {
                               ident: Identifier {
-                                ident: "b".into(),
+                                ident: "b",
                                 socket: None,
                                 span: (12, 13, 1),
                               },
@@ -3418,7 +3981,7 @@ mod tests {
                               type_choices: vec![Type1 {
                                 type2: Type2::Typename {
                                   ident: Identifier {
-                                    ident: "tstr".into(),
+                                    ident: "tstr",
                                     socket: None,
                                     span: (15, 19, 1),
                                   },
@@ -3492,7 +4055,7 @@ mod tests {
                                   ge: TypeGroupnameEntry {
                                     occur: Some(Occur::ZeroOrMore((3, 4, 1))),
                                     name: Identifier {
-                                      ident: "file-entry".into(),
+                                      ident: "file-entry",
                                       socket: None,
                                       span: (5, 15, 1),
                                     },
@@ -3533,7 +4096,7 @@ mod tests {
                                   ge: TypeGroupnameEntry {
                                     occur: Some(Occur::ZeroOrMore((19, 20, 1))),
                                     name: Identifier {
-                                      ident: "directory-entry".into(),
+                                      ident: "directory-entry",
                                       socket: None,
                                       span: (21, 36, 1),
                                     },
@@ -3576,7 +4139,7 @@ mod tests {
                     ge: TypeGroupnameEntry {
                       occur: None,
                       name: Identifier {
-                        ident: "int".into(),
+                        ident: "int",
                         socket: None,
                         span: (2, 5, 1),
                       },
@@ -3591,7 +4154,7 @@ mod tests {
                     ge: TypeGroupnameEntry {
                       occur: None,
                       name: Identifier {
-                        ident: "int".into(),
+                        ident: "int",
                         socket: None,
                         span: (7, 10, 1),
                       },
@@ -3611,7 +4174,7 @@ mod tests {
                     ge: TypeGroupnameEntry {
                       occur: None,
                       name: Identifier {
-                        ident: "int".into(),
+                        ident: "int",
                         socket: None,
                         span: (14, 17, 1),
                       },
@@ -3626,7 +4189,7 @@ mod tests {
                     ge: TypeGroupnameEntry {
                       occur: None,
                       name: Identifier {
-                        ident: "tstr".into(),
+                        ident: "tstr",
                         socket: None,
                         span: (19, 23, 1),
                       },
@@ -3653,7 +4216,7 @@ mod tests {
                   ge: TypeGroupnameEntry {
                     occur: None,
                     name: Identifier {
-                      ident: "int".into(),
+                      ident: "int",
                       socket: None,
                       span: (2, 5, 1),
                     },
@@ -3668,7 +4231,7 @@ mod tests {
                   ge: TypeGroupnameEntry {
                     occur: None,
                     name: Identifier {
-                      ident: "int".into(),
+                      ident: "int",
                       socket: None,
                       span: (7, 10, 1),
                     },
@@ -3683,7 +4246,7 @@ mod tests {
                   ge: TypeGroupnameEntry {
                     occur: None,
                     name: Identifier {
-                      ident: "int".into(),
+                      ident: "int",
                       socket: None,
                       span: (12, 15, 1),
                     },
@@ -3698,7 +4261,7 @@ mod tests {
                   ge: TypeGroupnameEntry {
                     occur: None,
                     name: Identifier {
-                      ident: "tstr".into(),
+                      ident: "tstr",
                       socket: None,
                       span: (17, 21, 1),
                     },
@@ -3737,6 +4300,10 @@ mod tests {
       r#"? 0: addrdistr"#,
       r#"0: finite_set<transaction_input>"#,
       r#"* [credential] => coin"#,
+      r#"[int] / 1"#,
+      r#"label<int> => bstr"#,
+      r#"2*4 int"#,
+      r#"1*3 name: tstr"#,
     ];
 
     let expected_outputs = [
@@ -3747,7 +4314,7 @@ mod tests {
             t1: Box::from(Type1 {
               type2: Type2::Typename {
                 ident: Identifier {
-                  ident: "type1".into(),
+                  ident: "type1",
                   socket: None,
                   span: (2, 7, 1),
                 },
@@ -3779,7 +4346,7 @@ mod tests {
           occur: None,
           member_key: Some(MemberKey::Bareword {
             ident: Identifier {
-              ident: "type1".into(),
+              ident: "type1",
               socket: None,
               span: (0, 5, 1),
             },
@@ -3789,7 +4356,7 @@ mod tests {
             type_choices: vec![Type1 {
               type2: Type2::Typename {
                 ident: Identifier {
-                  ident: "type2".into(),
+                  ident: "type2",
                   socket: None,
                   span: (7, 12, 1),
                 },
@@ -3808,7 +4375,7 @@ mod tests {
         ge: TypeGroupnameEntry {
           occur: None,
           name: Identifier {
-            ident: "typename".into(),
+            ident: "typename",
             socket: None,
             span: (0, 8, 1),
           },
@@ -3827,7 +4394,7 @@ mod tests {
             type_choices: vec![Type1 {
               type2: Type2::Typename {
                 ident: Identifier {
-                  ident: "addrdistr".into(),
+                  ident: "addrdistr",
                   socket: None,
                   span: (5, 14, 1),
                 },
@@ -3853,7 +4420,7 @@ mod tests {
             type_choices: vec![Type1 {
               type2: Type2::Typename {
                 ident: Identifier {
-                  ident: "finite_set".into(),
+                  ident: "finite_set",
                   socket: None,
                   span: (3, 13, 1),
                 },
@@ -3861,7 +4428,7 @@ mod tests {
                   args: vec![Type1 {
                     type2: Type2::Typename {
                       ident: Identifier {
-                        ident: "transaction_input".into(),
+                        ident: "transaction_input",
                         socket: None,
                         span: (14, 31, 1),
                       },
@@ -3896,7 +4463,7 @@ mod tests {
                         ge: TypeGroupnameEntry {
                           occur: None,
                           name: Identifier {
-                            ident: "credential".into(),
+                            ident: "credential",
                             socket: None,
                             span: (3, 13, 1),
                           },
@@ -3922,7 +4489,7 @@ mod tests {
             type_choices: vec![Type1 {
               type2: Type2::Typename {
                 ident: Identifier {
-                  ident: "coin".into(),
+                  ident: "coin",
                   socket: None,
                   span: (18, 22, 1),
                 },
@@ -3937,6 +4504,158 @@ mod tests {
         }),
         span: (0, 22, 1),
       },
+      GroupEntry::ValueMemberKey {
+        ge: Box::from(ValueMemberKeyEntry {
+          occur: None,
+          member_key: None,
+          entry_type: Type {
+            type_choices: vec![
+              Type1 {
+                type2: Type2::Array {
+                  group: Group {
+                    group_choices: vec![GroupChoice {
+                      group_entries: vec![(
+                        GroupEntry::TypeGroupname {
+                          ge: TypeGroupnameEntry {
+                            occur: None,
+                            name: Identifier {
+                              ident: "int",
+                              socket: None,
+                              span: (1, 4, 1),
+                            },
+                            generic_arg: None,
+                          },
+                          span: (1, 4, 1),
+                        },
+                        false,
+                      )],
+                      span: (1, 4, 1),
+                    }],
+                    span: (1, 4, 1),
+                  },
+                  span: (0, 5, 1),
+                },
+                operator: None,
+                span: (0, 5, 1),
+              },
+              Type1 {
+                type2: Type2::UintValue {
+                  value: 1,
+                  span: (8, 9, 1),
+                },
+                operator: None,
+                span: (8, 9, 1),
+              },
+            ],
+            span: (0, 9, 1),
+          },
+        }),
+        span: (0, 9, 1),
+      },
+      GroupEntry::ValueMemberKey {
+        ge: Box::from(ValueMemberKeyEntry {
+          occur: None,
+          member_key: Some(MemberKey::Type1 {
+            t1: Box::from(Type1 {
+              type2: Type2::Typename {
+                ident: Identifier {
+                  ident: "label",
+                  socket: None,
+                  span: (0, 5, 1),
+                },
+                generic_arg: Some(GenericArg {
+                  args: vec![Type1 {
+                    type2: Type2::Typename {
+                      ident: Identifier {
+                        ident: "int",
+                        socket: None,
+                        span: (6, 9, 1),
+                      },
+                      generic_arg: None,
+                      span: (6, 9, 1),
+                    },
+                    operator: None,
+                    span: (6, 9, 1),
+                  }],
+                  span: (5, 10, 1),
+                }),
+                span: (0, 10, 1),
+              },
+              operator: None,
+              span: (0, 10, 1),
+            }),
+            is_cut: false,
+            span: (0, 13, 1),
+          }),
+          entry_type: Type {
+            type_choices: vec![Type1 {
+              type2: Type2::Typename {
+                ident: Identifier {
+                  ident: "bstr",
+                  socket: None,
+                  span: (14, 18, 1),
+                },
+                generic_arg: None,
+                span: (14, 18, 1),
+              },
+              operator: None,
+              span: (14, 18, 1),
+            }],
+            span: (14, 18, 1),
+          },
+        }),
+        span: (0, 18, 1),
+      },
+      GroupEntry::TypeGroupname {
+        ge: TypeGroupnameEntry {
+          occur: Some(Occur::Exact {
+            lower: Some(2),
+            upper: Some(4),
+            span: (0, 3, 1),
+          }),
+          name: Identifier {
+            ident: "int",
+            socket: None,
+            span: (4, 7, 1),
+          },
+          generic_arg: None,
+        },
+        span: (0, 7, 1),
+      },
+      GroupEntry::ValueMemberKey {
+        ge: Box::from(ValueMemberKeyEntry {
+          occur: Some(Occur::Exact {
+            lower: Some(1),
+            upper: Some(3),
+            span: (0, 3, 1),
+          }),
+          member_key: Some(MemberKey::Bareword {
+            ident: Identifier {
+              ident: "name",
+              socket: None,
+              span: (4, 8, 1),
+            },
+            span: (4, 9, 1),
+          }),
+          entry_type: Type {
+            type_choices: vec![Type1 {
+              type2: Type2::Typename {
+                ident: Identifier {
+                  ident: "tstr",
+                  socket: None,
+                  span: (10, 14, 1),
+                },
+                generic_arg: None,
+                span: (10, 14, 1),
+              },
+              operator: None,
+              span: (10, 14, 1),
+            }],
+            span: (10, 14, 1),
+          },
+        }),
+        span: (0, 14, 1),
+      },
     ];
 
     for (idx, expected_output) in expected_outputs.iter().enumerate() {
@@ -3966,7 +4685,7 @@ mod tests {
         t1: Box::from(Type1 {
           type2: Type2::Typename {
             ident: Identifier {
-              ident: "type1".into(),
+              ident: "type1",
               socket: None,
               span: (0, 5, 1),
             },
@@ -3993,7 +4712,7 @@ mod tests {
       },
       MemberKey::Bareword {
         ident: Identifier {
-          ident: "mybareword".into(),
+          ident: "mybareword",
           socket: None,
           span: (0, 10, 1),
         },
@@ -4001,7 +4720,7 @@ mod tests {
       },
       MemberKey::Bareword {
         ident: Identifier {
-          ident: "my..bareword".into(),
+          ident: "my..bareword",
           socket: None,
           span: (0, 12, 1),
         },
@@ -4064,6 +4783,85 @@ mod tests {
       }
     }
 
+    Ok(())
+  }
+  #[test]
+  fn verify_error_recovery() -> Result<()> {
+    let input = indoc!(
+      r#"
+        a = [ int
+        b = { c: }
+        d = tstr .asdf 3
+        e = uint / nint
+        e = tstr
+        f = { g: e }
+      "#
+    );
+
+    let mut lexer = Lexer::new(input);
+    let mut p = Parser::new(lexer.iter(), input)?;
+    let c = p.parse_cddl_recovering();
+
+    assert_eq!(
+      c.rules.iter().map(|r| r.name()).collect::<Vec<_>>(),
+      vec!["e", "f"]
+    );
+    assert_eq!(
+      p.errors
+        .iter()
+        .map(|e| (e.position().range.0, e.message()))
+        .collect::<Vec<_>>(),
+      vec![
+        (10, "missing closing delimiter before the definition of 'b'"),
+        (19, "Entry type syntax error"),
+        (30, "Invalid control operator"),
+        (54, "Rule with name 'e' already defined"),
+      ]
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn verify_limits() -> Result<()> {
+    let inputs = [
+      ("a = [ [ [ int ] ] ]", Limit::NestingDepth),
+      ("a = { b: { c: int } }", Limit::NestingDepth),
+      ("a = int\nb = tstr\nc = bstr", Limit::Rules),
+      ("a = int / tstr / bstr", Limit::Choices),
+      ("a = [ b // c // d ]", Limit::Choices),
+      ("a = { b: int / tstr / bstr }", Limit::Choices),
+    ];
+
+    for (input, expected_limit) in inputs.iter() {
+      let mut lexer = Lexer::new(input);
+      let mut p = Parser::new(lexer.iter(), input)?;
+      p.set_options(ParserOptions {
+        max_nesting_depth: Some(4),
+        max_rules: Some(2),
+        max_choices: Some(2),
+      });
+
+      match p.parse_cddl() {
+        Err(Error::LimitExceeded { limit, .. }) => assert_eq!(limit, *expected_limit),
+        r => panic!(
+          "expected {} to be exceeded, got {:?}",
+          expected_limit,
+          r.map(|_| ())
+        ),
+      }
+    }
+
+    let input = "a = [ int / tstr ]\nb = { c: uint }";
+    let mut lexer = Lexer::new(input);
+    let mut p = Parser::new(lexer.iter(), input)?;
+    p.set_options(ParserOptions {
+      max_nesting_depth: Some(4),
+      max_rules: Some(2),
+      max_choices: Some(2),
+    });
+    assert_eq!(p.parse_cddl()?.rules.len(), 2);
+
     Ok(())
   }
 }
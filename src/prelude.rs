@@ -0,0 +1,146 @@
+use super::{
+  ast::CDDL,
+  lexer::Lexer,
+  parser::{self, Parser},
+  token,
+};
+
+#[cfg(feature = "std")]
+use lazy_static::lazy_static;
+
+/// The standard prelude as defined in [Appendix D of RFC
+/// 8610](https://tools.ietf.org/html/rfc8610#appendix-D)
+pub const PRELUDE: &str = r#"any = #
+
+uint = #0
+nint = #1
+int = uint / nint
+
+bstr = #2
+bytes = bstr
+tstr = #3
+text = tstr
+
+tdate = #6.0(tstr)
+time = #6.1(number)
+number = int / float
+biguint = #6.2(bstr)
+bignint = #6.3(bstr)
+bigint = biguint / bignint
+integer = int / bigint
+unsigned = uint / biguint
+decfrac = #6.4([e10: int, m: integer])
+bigfloat = #6.5([e2: int, m: integer])
+eb64url = #6.21(any)
+eb64legacy = #6.22(any)
+eb16 = #6.23(any)
+encoded-cbor = #6.24(bstr)
+uri = #6.32(tstr)
+b64url = #6.33(tstr)
+b64legacy = #6.34(tstr)
+regexp = #6.35(tstr)
+mime-message = #6.36(tstr)
+cbor-any = #6.55799(any)
+
+float16 = #7.25
+float32 = #7.26
+float64 = #7.27
+float16-32 = float16 / float32
+float32-64 = float32 / float64
+float = float16-32 / float64
+
+false = #7.20
+true = #7.21
+bool = false / true
+nil = #7.22
+null = nil
+undefined = #7.23
+"#;
+
+/// Returns whether or not the given type name is defined by the standard
+/// prelude
+///
+/// # Example
+///
+/// ```
+/// use cddl::prelude::is_prelude_type;
+///
+/// assert!(is_prelude_type("mime-message"));
+/// assert!(!is_prelude_type("mytype"));
+/// ```
+pub fn is_prelude_type(ident: &str) -> bool {
+  token::lookup_ident(ident).in_standard_prelude().is_some()
+}
+
+// Returns whether the type name is one that the validators check the semantics
+// of when the definition doesn't define it, despite not being part of the
+// standard prelude of RFC 8610, e.g. `duration` as described by RFC 9581
+pub(crate) fn is_extension_type(ident: &str) -> bool {
+  ident == "duration"
+}
+
+// Returns whether the type name is one of the numeric types of the standard
+// prelude, whose values the validators compare numerically
+pub(crate) fn is_numeric_data_type(ident: &str) -> bool {
+  match ident {
+    "uint" | "nint" | "int" | "integer" | "unsigned" | "biguint" | "bignint" | "bigint"
+    | "number" | "float" | "float16" | "float32" | "float64" | "float16-32" | "float32-64" => true,
+    _ => false,
+  }
+}
+
+// Parses the standard prelude. That it parses without errors is checked by the
+// tests, so callers fall back to an empty definition rather than panicking
+fn parse_prelude() -> parser::Result<CDDL<'static>> {
+  let mut lexer = Lexer::new(PRELUDE);
+  let mut p = Parser::new(lexer.iter(), PRELUDE)?;
+  p.is_prelude = true;
+
+  p.parse_cddl()
+}
+
+#[cfg(feature = "std")]
+lazy_static! {
+  static ref STANDARD_PRELUDE: CDDL<'static> = parse_prelude().unwrap_or_default();
+}
+
+/// Calls the given closure with the AST of the standard prelude. With the
+/// `std` feature the prelude is parsed once and shared across calls; without
+/// it, it's parsed on each call, so callers should hold on to the AST for the
+/// duration of their work rather than calling this repeatedly.
+///
+/// # Example
+///
+/// ```
+/// use cddl::prelude::with_prelude;
+///
+/// assert!(with_prelude(|p| p.rules.iter().any(|r| r.name() == "tdate")));
+/// ```
+pub fn with_prelude<R, F: FnOnce(&CDDL) -> R>(f: F) -> R {
+  #[cfg(feature = "std")]
+  return f(&STANDARD_PRELUDE);
+
+  #[cfg(not(feature = "std"))]
+  f(&parse_prelude().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn verify_prelude_parses() {
+    if let Err(e) = parse_prelude() {
+      panic!("error parsing the standard prelude: {}", e);
+    }
+  }
+
+  #[test]
+  fn verify_prelude() {
+    with_prelude(|p| {
+      for rule in p.rules.iter() {
+        assert!(is_prelude_type(&rule.name()), "{}", rule.name());
+      }
+    });
+  }
+}
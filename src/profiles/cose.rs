@@ -0,0 +1,296 @@
+use crate::{
+  ast::CDDL,
+  cbor_core::{decode, validate_for_rule, Error, Value},
+  lexer::Lexer,
+  parser::Parser,
+};
+
+/// CDDL of the COSE structures from RFC 9052 appendix C, followed by that of
+/// CWTs signed or MACed with `COSE_Sign1` or `COSE_Mac0` and of their claims
+/// sets (RFC 8392 section 3). Claims registered by RFC 8392 are checked with
+/// cuts, so that e.g. an `exp` claim that isn't a `NumericDate` isn't instead
+/// matched as an unregistered claim.
+pub const CDDL_INPUT: &str = r#"
+COSE_Messages = COSE_Untagged_Message / COSE_Tagged_Message
+
+COSE_Untagged_Message = COSE_Sign / COSE_Sign1 /
+    COSE_Encrypt / COSE_Encrypt0 /
+    COSE_Mac / COSE_Mac0
+
+COSE_Tagged_Message = COSE_Sign_Tagged / COSE_Sign1_Tagged /
+    COSE_Encrypt_Tagged / COSE_Encrypt0_Tagged /
+    COSE_Mac_Tagged / COSE_Mac0_Tagged
+
+Headers = (
+    protected : empty_or_serialized_map,
+    unprotected : header_map
+)
+
+header_map = {
+    Generic_Headers,
+    * label => values
+}
+
+empty_or_serialized_map = bstr .cbor header_map / bstr .size 0
+
+Generic_Headers = (
+    ? 1 => int / tstr,  ; algorithm identifier
+    ? 2 => [+label],    ; criticality
+    ? 3 => tstr / int,  ; content type
+    ? 4 => bstr,        ; key identifier
+    ? ( 5 => bstr //    ; IV
+        6 => bstr )     ; Partial IV
+)
+
+label = int / tstr
+values = any
+
+COSE_Sign_Tagged = #6.98(COSE_Sign)
+
+COSE_Sign = [
+    Headers,
+    payload : bstr / nil,
+    signatures : [+ COSE_Signature]
+]
+
+COSE_Signature = [
+    Headers,
+    signature : bstr
+]
+
+COSE_Sign1_Tagged = #6.18(COSE_Sign1)
+
+COSE_Sign1 = [
+    Headers,
+    payload : bstr / nil,
+    signature : bstr
+]
+
+COSE_Encrypt_Tagged = #6.96(COSE_Encrypt)
+
+COSE_Encrypt = [
+    Headers,
+    ciphertext : bstr / nil,
+    recipients : [+COSE_recipient]
+]
+
+COSE_recipient = [
+    Headers,
+    ciphertext : bstr / nil,
+    ? recipients : [+COSE_recipient]
+]
+
+COSE_Encrypt0_Tagged = #6.16(COSE_Encrypt0)
+
+COSE_Encrypt0 = [
+    Headers,
+    ciphertext : bstr / nil,
+]
+
+COSE_Mac_Tagged = #6.97(COSE_Mac)
+
+COSE_Mac = [
+   Headers,
+   payload : bstr / nil,
+   tag : bstr,
+   recipients : [+COSE_recipient]
+]
+
+COSE_Mac0_Tagged = #6.17(COSE_Mac0)
+
+COSE_Mac0 = [
+   Headers,
+   payload : bstr / nil,
+   tag : bstr,
+]
+
+COSE_Key = {
+    1 => tstr / int,          ; kty
+    ? 2 => bstr,              ; kid
+    ? 3 => tstr / int,        ; alg
+    ? 4 => [+ (tstr / int) ], ; key_ops
+    ? 5 => bstr,              ; Base IV
+    * label => values
+}
+
+COSE_KeySet = [+COSE_Key]
+
+; CBOR Web Tokens (RFC 8392)
+
+CWT = #6.61(CWT_Untagged) / CWT_Untagged
+CWT_Untagged = CWT_Message / #6.18(CWT_Message) / #6.17(CWT_Message)
+CWT_Message = [
+    Headers,
+    payload : bstr .cbor Claims,
+    signature_or_tag : bstr
+]
+Claims = {
+    ? 1 ^ => tstr,         ; iss
+    ? 2 ^ => tstr,         ; sub
+    ? 3 ^ => tstr,         ; aud
+    ? 4 ^ => NumericDate,  ; exp
+    ? 5 ^ => NumericDate,  ; nbf
+    ? 6 ^ => NumericDate,  ; iat
+    ? 7 ^ => bstr,         ; cti
+    * label => values
+}
+NumericDate = int / float"#;
+
+#[cfg(feature = "std")]
+thread_local! {
+  #[allow(clippy::missing_const_for_thread_local)]
+  static PARSED: CDDL<'static> = cddl();
+}
+
+/// Parses `CDDL_INPUT`, e.g. to validate data against other rules of it with
+/// `cbor_core::validate_for_rule`. The validation functions of this module
+/// parse it only once per thread when the standard library is available.
+pub fn cddl() -> CDDL<'static> {
+  Parser::new(Lexer::new(CDDL_INPUT).iter(), CDDL_INPUT)
+    .and_then(|mut p| p.parse_cddl())
+    .expect("COSE CDDL is conformant")
+}
+
+fn validate(rule: &str, value: &Value) -> Result<(), Error> {
+  #[cfg(feature = "std")]
+  return PARSED.with(|cddl| validate_for_rule(cddl, rule, value));
+
+  #[cfg(not(feature = "std"))]
+  validate_for_rule(&cddl(), rule, value)
+}
+
+/// Validates an encoded `COSE_Sign1` message, tagged with tag 18 or untagged
+///
+/// # Example
+///
+/// ```
+/// use cddl::profiles::cose::validate_cose_sign1;
+///
+/// // 18([h'a10126', {4: h'3131'}, h'', h''])
+/// let cbor = [
+///   0xd2, 0x84, 0x43, 0xa1, 0x01, 0x26, 0xa1, 0x04, 0x42, 0x31, 0x31, 0x40, 0x40,
+/// ];
+/// assert!(validate_cose_sign1(&cbor).is_ok());
+///
+/// // The protected header bucket must be an encoded map
+/// let cbor = [0xd2, 0x84, 0x41, 0x01, 0xa0, 0x40, 0x40];
+/// assert!(validate_cose_sign1(&cbor).is_err());
+/// ```
+pub fn validate_cose_sign1(cbor: &[u8]) -> Result<(), Error> {
+  let value = decode(cbor)?;
+  if let Value::Tag(..) = value {
+    return validate("COSE_Sign1_Tagged", &value);
+  }
+
+  validate("COSE_Sign1", &value)
+}
+
+/// Validates an encoded COSE message of any type (`COSE_Messages`). Untagged
+/// messages are accepted as any type they match.
+pub fn validate_cose(cbor: &[u8]) -> Result<(), Error> {
+  validate("COSE_Messages", &decode(cbor)?)
+}
+
+/// Validates an encoded `COSE_Key`
+pub fn validate_cose_key(cbor: &[u8]) -> Result<(), Error> {
+  validate("COSE_Key", &decode(cbor)?)
+}
+
+/// Validates an encoded CWT signed with `COSE_Sign1` or MACed with
+/// `COSE_Mac0`, along with the claims set it carries as payload. The CWT may
+/// be tagged with the CWT tag 61 and the COSE tag of its message type.
+/// Encrypted CWTs can only be validated as COSE messages with
+/// `validate_cose`.
+pub fn validate_cwt(cbor: &[u8]) -> Result<(), Error> {
+  validate("CWT", &decode(cbor)?)
+}
+
+/// Validates an encoded CWT claims set
+pub fn validate_cwt_claims(cbor: &[u8]) -> Result<(), Error> {
+  validate("Claims", &decode(cbor)?)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[cfg(not(feature = "std"))]
+  use alloc::{string::ToString, vec, vec::Vec};
+
+  fn hex(input: &str) -> Vec<u8> {
+    let mut bytes = vec![0; input.len() / 2];
+    base16::decode_slice(input, &mut bytes).unwrap();
+    bytes
+  }
+
+  #[test]
+  fn validate_cose_messages() {
+    // RFC 9052 appendix C.2.1
+    let sign1 = hex(concat!(
+      "d28443a10126a10442313154546869732069732074686520636f6e74656e742e5840",
+      "8eb33e4ca31d1c465ab05aac34cc6b23d58fef5c083106c4d25a91aef0b0117e2af9",
+      "a291aa32e14ab834dc56ed2a223444547e01f11d3b0916e5a4c345cacb36"
+    ));
+
+    assert_eq!(validate_cose_sign1(&sign1), Ok(()));
+    assert_eq!(validate_cose_sign1(&sign1[1..]), Ok(()));
+    assert_eq!(validate_cose(&sign1), Ok(()));
+    assert_eq!(validate_cose(&hex("d18440a0f640")), Ok(()));
+    assert_eq!(
+      validate_cose(&hex("d28340a0f6")),
+      Err(Error::Mismatch {
+        path: "".to_string(),
+        expected: "COSE_Messages".to_string(),
+      })
+    );
+    assert_eq!(
+      validate_cose_sign1(&hex("8343a10126a0f6")),
+      Err(Error::MissingEntry {
+        path: "".to_string(),
+        entry: "signature: bstr".to_string(),
+      })
+    );
+  }
+
+  #[test]
+  fn validate_cose_keys() {
+    assert_eq!(validate_cose_key(&hex("a3010203262142aaaa")), Ok(()));
+    assert!(validate_cose_key(&hex("a1024100")).is_err());
+  }
+
+  #[test]
+  fn validate_cwts() {
+    // RFC 8392 appendix A.3 and A.4
+    let claims = concat!(
+      "a70175636f61703a2f2f61732e6578616d706c652e636f6d02656572696b77037818",
+      "636f61703a2f2f6c696768742e6578616d706c652e636f6d041a5612aeb0051a5610",
+      "d9f0061a5610d9f007420b71"
+    );
+    let signed = hex(&format!(
+      "{}{}{}",
+      "d28443a10126a104524173796d6d657472696345434453413235365850",
+      claims,
+      concat!(
+        "58405427c1ff28d23fbad1f29c4c7c6a555e601d6fa29f9179bc3d7438bacaca5acd",
+        "08c8d4d4f96131680c429a01f85951ecee743a52b9b63632c57209120e1c9e30"
+      )
+    ));
+    let maced = hex(&format!(
+      "{}{}{}",
+      "d83dd18443a10104a1044c53796d6d65747269633235365850", claims, "48093101ef6d789200"
+    ));
+
+    assert_eq!(validate_cwt_claims(&hex(claims)), Ok(()));
+    assert_eq!(validate_cwt(&signed), Ok(()));
+    assert_eq!(validate_cwt(&maced), Ok(()));
+    assert!(validate_cose(&maced).is_err());
+
+    assert_eq!(
+      validate_cwt_claims(&hex("a1046161")),
+      Err(Error::Mismatch {
+        path: "/4".to_string(),
+        expected: "NumericDate".to_string(),
+      })
+    );
+  }
+}
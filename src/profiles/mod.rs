@@ -0,0 +1,2 @@
+/// CBOR Object Signing and Encryption (COSE) and CBOR Web Tokens (CWT)
+pub mod cose;
@@ -0,0 +1,174 @@
+use super::{
+  ast::{GenericParm, Identifier, Rule, CDDL},
+  visitor::{self, Visitor},
+};
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+impl<'a> CDDL<'a> {
+  /// Removes the rules that aren't reachable from the rule named `root`,
+  /// i.e. that neither it nor any rule it refers to, directly or through
+  /// other rules, refers to. The root rule, along with any rules extending it
+  /// via `/=` or `//=`, is moved to the front so that data is validated
+  /// against it by default. Useful for extracting the definition of a single
+  /// message from a larger specification.
+  ///
+  /// Returns `false`, leaving the definition unchanged, if no rule is named
+  /// `root`.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{lexer_from_str, parser::cddl_from_str};
+  ///
+  /// let input = r#"
+  ///   request = { id: id, method: tstr }
+  ///   response = { id: id, result: result }
+  ///   result = tstr / error
+  ///   error = { code: int }
+  ///   id = uint
+  /// "#;
+  ///
+  /// let mut cddl = cddl_from_str(&mut lexer_from_str(input), input, true).unwrap();
+  /// assert!(cddl.prune("response"));
+  ///
+  /// let names = cddl.rules.iter().map(|r| r.name()).collect::<Vec<_>>();
+  /// assert_eq!(names, ["response", "result", "error", "id"]);
+  /// ```
+  pub fn prune(&mut self, root: &str) -> bool {
+    if self.rule(root).is_none() {
+      return false;
+    }
+
+    let mut reachable: Vec<&'a str> = Vec::new();
+    let mut pending = Vec::new();
+
+    for rule in self.rules_named(root) {
+      reachable.push(rule.name_ident());
+      pending.push(rule.name_ident());
+    }
+
+    while let Some(name) = pending.pop() {
+      for rule in self.rules_named(name) {
+        let mut references = References::default();
+        references.visit_rule(rule);
+
+        for name in references.names {
+          if !reachable.contains(&name) {
+            reachable.push(name);
+            pending.push(name);
+          }
+        }
+      }
+    }
+
+    let (roots, others): (Vec<_>, Vec<_>) = self
+      .rules
+      .drain(..)
+      .filter(|r| reachable.contains(&r.name_ident()))
+      .partition(|r| r.name_ident() == root);
+
+    for rule in roots.into_iter().chain(others) {
+      self.push_rule(rule);
+    }
+
+    true
+  }
+}
+
+// Names referenced by a rule, other than its generic parameters
+#[derive(Default)]
+pub(crate) struct References<'a> {
+  params: Vec<&'a str>,
+  pub(crate) names: Vec<&'a str>,
+}
+
+impl<'a> Visitor<'a> for References<'a> {
+  fn visit_rule(&mut self, rule: &Rule<'a>) {
+    let generic_param = match rule {
+      Rule::Type { rule, .. } => &rule.generic_param,
+      Rule::Group { rule, .. } => &rule.generic_param,
+    };
+
+    if let Some(GenericParm { params, .. }) = generic_param {
+      self.params = params.iter().map(|p| p.ident).collect();
+    }
+
+    visitor::walk_rule(self, rule);
+  }
+
+  fn visit_typename(&mut self, ident: &Identifier<'a>) {
+    if !self.params.contains(&ident.ident) {
+      self.names.push(ident.ident);
+    }
+  }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+  use crate::{lexer::Lexer, parser::cddl_from_str};
+
+  fn prune(input: &str, root: &str) -> Option<Vec<String>> {
+    let mut cddl = cddl_from_str(&mut Lexer::new(input), input, true).unwrap();
+
+    if !cddl.prune(root) {
+      return None;
+    }
+
+    Some(cddl.rules.iter().map(|r| r.name()).collect())
+  }
+
+  #[test]
+  fn verify_prune() {
+    let input = r#"
+      message = request / response
+      request = { id: id, params: [* param] }
+      response = { id: id, ~meta, ? error: error<tstr> }
+      meta = { version: uint }
+      error<T> = { code: code, message: T }
+      code = 1 / 2
+      code /= 3
+      param = tstr
+      id = uint
+      T = bool
+      unused = { id: id }
+    "#;
+
+    assert_eq!(
+      prune(input, "response").unwrap(),
+      ["response", "meta", "error", "code", "code", "id"]
+    );
+    assert_eq!(prune(input, "request").unwrap(), ["request", "param", "id"]);
+    assert_eq!(prune(input, "message").unwrap().len(), 9);
+    assert_eq!(prune(input, "missing"), None);
+  }
+
+  #[test]
+  fn verify_prune_recursive_and_group_rules() {
+    let input = r#"
+      tree = { value: uint, children: [* tree], * attribute }
+      attribute = (tstr => label)
+      label = tstr / uint
+      other = int
+    "#;
+
+    assert_eq!(
+      prune(input, "tree").unwrap(),
+      ["tree", "attribute", "label"]
+    );
+    assert_eq!(prune(input, "label").unwrap(), ["label"]);
+  }
+
+  #[test]
+  fn verify_pruned_rules_are_indexed() {
+    let input = "a = b\nunused = int\nb = c\nc = tstr";
+    let mut cddl = cddl_from_str(&mut Lexer::new(input), input, true).unwrap();
+
+    assert!(cddl.prune("b"));
+    assert!(cddl.rule("a").is_none());
+    assert_eq!(cddl.rule("c").map(|r| r.name()), Some("c".to_string()));
+    assert_eq!(cddl.rules[0].name(), "b");
+  }
+}
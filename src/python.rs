@@ -0,0 +1,282 @@
+use crate::{
+  schema::CddlSchema,
+  validation::{cbor::decode_cbor, CompilationError, Error, Validator},
+};
+use pyo3::{
+  create_exception,
+  exceptions::PyValueError,
+  prelude::*,
+  types::{PyDict, PyList},
+};
+
+create_exception!(
+  cddl,
+  ParseError,
+  PyValueError,
+  "Raised when a CDDL definition can't be parsed"
+);
+create_exception!(
+  cddl,
+  ValidationError,
+  PyValueError,
+  "Raised when data doesn't conform to a CDDL definition, with each failure listed by its `errors` attribute"
+);
+
+/// Parsed CDDL definition that data can be validated against any number of
+/// times
+#[pyclass(name = "Schema", module = "cddl")]
+pub struct Schema {
+  schema: CddlSchema,
+}
+
+#[pymethods]
+impl Schema {
+  /// Validates JSON text against the root rule, or against the rule with the
+  /// given name
+  #[pyo3(signature = (json, rule = None))]
+  fn validate_json(&self, py: Python, json: &str, rule: Option<&str>) -> PyResult<()> {
+    let value = serde_json::from_str::<serde_json::Value>(json)
+      .map_err(|e| PyValueError::new_err(format!("error parsing JSON: {}", e)))?;
+
+    let result = match rule {
+      Some(rule) => self.schema.cddl().validate_with_root(rule, &value),
+      None => self.schema.cddl().validate(&value),
+    };
+
+    result.map_err(|e| to_py_err(py, e))
+  }
+
+  /// Validates encoded CBOR against the root rule, or against the rule with
+  /// the given name
+  #[pyo3(signature = (cbor, rule = None))]
+  fn validate_cbor(&self, py: Python, cbor: &[u8], rule: Option<&str>) -> PyResult<()> {
+    let result = match rule {
+      Some(rule) => {
+        decode_cbor(cbor).and_then(|value| self.schema.cddl().validate_with_root(rule, &value))
+      }
+      None => self.schema.cddl().validate_cbor_slice(cbor),
+    };
+
+    result.map_err(|e| to_py_err(py, e))
+  }
+
+  /// Names of the rules of the definition in the order they're defined
+  fn rules(&self) -> Vec<String> {
+    self.schema.cddl().rules.iter().map(|r| r.name()).collect()
+  }
+
+  fn __str__(&self) -> String {
+    self.schema.source().to_string()
+  }
+}
+
+/// Parses a CDDL definition
+#[pyfunction]
+fn parse(cddl: &str) -> PyResult<Schema> {
+  CddlSchema::new(cddl)
+    .map(|schema| Schema { schema })
+    .map_err(ParseError::new_err)
+}
+
+/// Validates JSON text against a CDDL definition
+#[pyfunction]
+#[pyo3(signature = (cddl, json, rule = None))]
+fn validate_json(py: Python, cddl: &str, json: &str, rule: Option<&str>) -> PyResult<()> {
+  parse(cddl)?.validate_json(py, json, rule)
+}
+
+/// Validates encoded CBOR against a CDDL definition
+#[pyfunction]
+#[pyo3(signature = (cddl, cbor, rule = None))]
+fn validate_cbor(py: Python, cddl: &str, cbor: &[u8], rule: Option<&str>) -> PyResult<()> {
+  parse(cddl)?.validate_cbor(py, cbor, rule)
+}
+
+/// Python module exposing the parser and validators
+#[pymodule]
+fn cddl(py: Python, m: &PyModule) -> PyResult<()> {
+  m.add_class::<Schema>()?;
+  m.add_function(wrap_pyfunction!(parse, m)?)?;
+  m.add_function(wrap_pyfunction!(validate_json, m)?)?;
+  m.add_function(wrap_pyfunction!(validate_cbor, m)?)?;
+  m.add("ParseError", py.get_type::<ParseError>())?;
+  m.add("ValidationError", py.get_type::<ValidationError>())?;
+
+  Ok(())
+}
+
+fn to_py_err(py: Python, error: Error) -> PyErr {
+  match error {
+    Error::Compilation(CompilationError::CDDL(e)) => ParseError::new_err(e.to_string()),
+    Error::Compilation(e) => PyValueError::new_err(e.to_string()),
+    Error::Target(e) => PyValueError::new_err(format!("error decoding data: {}", e)),
+    error => {
+      let message = error.to_string();
+      let errors = error.into_errors();
+      let err = ValidationError::new_err(message);
+
+      match errors_to_py(py, &errors).and_then(|e| err.value(py).setattr("errors", e)) {
+        Ok(()) => err,
+        Err(e) => e,
+      }
+    }
+  }
+}
+
+fn errors_to_py<'py>(py: Python<'py>, errors: &[Error]) -> PyResult<&'py PyList> {
+  let list = PyList::empty(py);
+
+  for error in errors {
+    let dict = PyDict::new(py);
+    dict.set_item("message", error.to_string())?;
+
+    if let Some(e) = error.validation_error() {
+      dict.set_item("path", &e.path)?;
+      dict.set_item("rule", &e.rule)?;
+      dict.set_item("expected", &e.expected.value)?;
+      dict.set_item("member_key", &e.expected.member_key)?;
+      dict.set_item(
+        "actual",
+        serde_json::to_value(&e.actual)
+          .ok()
+          .map(|v| json_to_py(py, &v)),
+      )?;
+      dict.set_item(
+        "reason",
+        serde_json::to_value(e.reason)
+          .ok()
+          .and_then(|v| v.as_str().map(String::from)),
+      )?;
+    }
+
+    list.append(dict)?;
+  }
+
+  Ok(list)
+}
+
+fn json_to_py(py: Python, value: &serde_json::Value) -> PyObject {
+  match value {
+    serde_json::Value::Null => py.None(),
+    serde_json::Value::Bool(b) => b.into_py(py),
+    serde_json::Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+      (Some(i), _) => i.into_py(py),
+      (_, Some(u)) => u.into_py(py),
+      _ => n.as_f64().unwrap_or_default().into_py(py),
+    },
+    serde_json::Value::String(s) => s.into_py(py),
+    serde_json::Value::Array(a) => a
+      .iter()
+      .map(|v| json_to_py(py, v))
+      .collect::<Vec<_>>()
+      .into_py(py),
+    serde_json::Value::Object(o) => {
+      let dict = PyDict::new(py);
+      for (k, v) in o {
+        // Keys are strings and values are converted objects, neither of which
+        // can fail to be inserted
+        let _ = dict.set_item(k, json_to_py(py, v));
+      }
+      dict.into_py(py)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn errors(py: Python, err: &PyErr) -> Vec<(Option<String>, Option<String>)> {
+    err
+      .value(py)
+      .getattr("errors")
+      .unwrap()
+      .extract::<Vec<&PyDict>>()
+      .unwrap()
+      .into_iter()
+      .map(|e| {
+        (
+          e.get_item("path").and_then(|p| p.extract().ok()),
+          e.get_item("reason").and_then(|r| r.extract().ok()),
+        )
+      })
+      .collect()
+  }
+
+  #[test]
+  fn validate_json_with_paths() {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+      let cddl = "reputation = { reputons: [* reputon] }\nreputon = { rater: tstr, rating: float }";
+      let schema = parse(cddl).unwrap();
+      assert_eq!(schema.rules(), ["reputation", "reputon"]);
+
+      assert!(schema
+        .validate_json(
+          py,
+          r#"{ "reputons": [{ "rater": "Ninja", "rating": 0.5 }] }"#,
+          None
+        )
+        .is_ok());
+      assert!(schema
+        .validate_json(
+          py,
+          r#"{ "rater": "Ninja", "rating": 0.5 }"#,
+          Some("reputon")
+        )
+        .is_ok());
+
+      let err = validate_json(
+        py,
+        cddl,
+        r#"{ "reputons": [{ "rater": 1, "rating": 0.5 }] }"#,
+        None,
+      )
+      .unwrap_err();
+      assert!(err.is_instance_of::<ValidationError>(py));
+      assert_eq!(
+        errors(py, &err),
+        [(
+          Some("/reputons/0/rater".to_string()),
+          Some("mismatch".to_string())
+        )]
+      );
+
+      let err = validate_json(py, cddl, "{", None).unwrap_err();
+      assert!(err.is_instance_of::<PyValueError>(py));
+      assert!(!err.is_instance_of::<ValidationError>(py));
+    });
+  }
+
+  #[test]
+  fn validate_cbor_with_paths() {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+      let cddl = "reading = [sensor: uint, value: int]";
+      assert!(validate_cbor(py, cddl, &[0x82, 0x01, 0x38, 0x63], None).is_ok());
+
+      let err = validate_cbor(py, cddl, &[0x82, 0x01, 0x61, 0x61], None).unwrap_err();
+      assert!(err.is_instance_of::<ValidationError>(py));
+      assert_eq!(
+        errors(py, &err),
+        [(Some("/1".to_string()), Some("mismatch".to_string()))]
+      );
+
+      let err = validate_cbor(py, cddl, &[0x82, 0x01], Some("reading")).unwrap_err();
+      assert!(!err.is_instance_of::<ValidationError>(py));
+    });
+  }
+
+  #[test]
+  fn verify_parse_error() {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+      let err = parse("thing = = uint").err().unwrap();
+      assert!(err.is_instance_of::<ParseError>(py));
+      assert!(err.is_instance_of::<PyValueError>(py));
+    });
+  }
+}
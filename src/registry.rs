@@ -0,0 +1,383 @@
+use super::schema::CddlSchema;
+use std::{
+  fmt, fs,
+  future::Future,
+  io,
+  path::{Path, PathBuf},
+  pin::Pin,
+  str::FromStr,
+};
+
+/// SHA-256 fingerprint of a schema, as returned by `CDDL::fingerprint`,
+/// displayed and parsed as 64 lowercase hex digits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint(pub [u8; 32]);
+
+impl Fingerprint {
+  /// Returns the fingerprint of a schema
+  pub fn of(schema: &CddlSchema) -> Self {
+    Fingerprint(schema.cddl().fingerprint())
+  }
+}
+
+impl fmt::Display for Fingerprint {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for byte in self.0.iter() {
+      write!(f, "{:02x}", byte)?;
+    }
+
+    Ok(())
+  }
+}
+
+impl FromStr for Fingerprint {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut bytes = [0; 32];
+
+    if s.len() != 64 {
+      return Err(format!(
+        "fingerprint must be 64 hex digits, found {}",
+        s.len()
+      ));
+    }
+
+    base16::decode_slice(s, &mut bytes).map_err(|e| e.to_string())?;
+
+    Ok(Fingerprint(bytes))
+  }
+}
+
+/// Error publishing or fetching a schema
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+  /// No schema is stored under the fingerprint
+  NotFound(Fingerprint),
+  /// Schema stored under the fingerprint isn't conformant CDDL
+  Parse {
+    /// Fingerprint the schema is stored under
+    fingerprint: Fingerprint,
+    /// Error returned by the parser
+    message: String,
+  },
+  /// Schema stored under a fingerprint that isn't its own, e.g. because it was
+  /// modified after being published
+  Mismatch {
+    /// Fingerprint the schema is stored under
+    expected: Fingerprint,
+    /// Fingerprint of the stored schema
+    actual: Fingerprint,
+  },
+  /// Registry failed to store or retrieve a schema
+  Registry(String),
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Error::NotFound(fingerprint) => write!(f, "no schema with fingerprint {}", fingerprint),
+      Error::Parse {
+        fingerprint,
+        message,
+      } => write!(f, "error parsing schema {}: {}", fingerprint, message),
+      Error::Mismatch { expected, actual } => write!(
+        f,
+        "schema stored as {} has fingerprint {}",
+        expected, actual
+      ),
+      Error::Registry(message) => write!(f, "registry error: {}", message),
+    }
+  }
+}
+
+impl std::error::Error for Error {}
+
+/// Future returned by a [`Registry`](trait.Registry.html)
+pub type RegistryFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, String>> + Send + 'a>>;
+
+/// Storage of CDDL text addressed by the fingerprint of the schema it defines
+pub trait Registry {
+  /// Returns the text stored under the fingerprint, if any
+  fn get<'a>(&'a self, fingerprint: &'a Fingerprint) -> RegistryFuture<'a, Option<String>>;
+
+  /// Stores the text under the fingerprint
+  fn put<'a>(&'a self, fingerprint: &'a Fingerprint, text: &'a str) -> RegistryFuture<'a, ()>;
+}
+
+/// Registry storing each schema in a directory as a file named after its
+/// fingerprint, e.g. `3f0a…9c.cddl`, which can also be served as is by an HTTP
+/// server for [`HttpRegistry`](struct.HttpRegistry.html)
+#[derive(Debug, Clone)]
+pub struct DirectoryRegistry {
+  dir: PathBuf,
+}
+
+impl DirectoryRegistry {
+  /// Creates a registry backed by the given directory, which is created when
+  /// the first schema is published if it doesn't exist
+  pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+    DirectoryRegistry { dir: dir.into() }
+  }
+
+  /// Returns the path of the file storing the schema with the fingerprint
+  pub fn path(&self, fingerprint: &Fingerprint) -> PathBuf {
+    self.dir.join(format!("{}.cddl", fingerprint))
+  }
+}
+
+impl Registry for DirectoryRegistry {
+  fn get<'a>(&'a self, fingerprint: &'a Fingerprint) -> RegistryFuture<'a, Option<String>> {
+    let result = match fs::read_to_string(self.path(fingerprint)) {
+      Ok(text) => Ok(Some(text)),
+      Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+      Err(e) => Err(e.to_string()),
+    };
+
+    Box::pin(async move { result })
+  }
+
+  fn put<'a>(&'a self, fingerprint: &'a Fingerprint, text: &'a str) -> RegistryFuture<'a, ()> {
+    let result =
+      write_atomically(&self.dir, &self.path(fingerprint), text).map_err(|e| e.to_string());
+
+    Box::pin(async move { result })
+  }
+}
+
+// Writes the file under a temporary name first so that readers never see a
+// partially written schema
+fn write_atomically(dir: &Path, path: &Path, text: &str) -> io::Result<()> {
+  fs::create_dir_all(dir)?;
+
+  let tmp = path.with_extension("cddl.tmp");
+  fs::write(&tmp, text)?;
+  fs::rename(&tmp, path)
+}
+
+/// Registry served over HTTP(S), where the schema with a given fingerprint is
+/// retrieved from `<base URL>/<fingerprint>.cddl` and published with a `PUT`
+/// request to the same URL. Requires the `http` feature.
+#[cfg(feature = "http")]
+#[derive(Debug, Clone)]
+pub struct HttpRegistry {
+  base_url: String,
+  client: reqwest::Client,
+}
+
+#[cfg(feature = "http")]
+impl HttpRegistry {
+  /// Creates a registry served from the given base URL
+  pub fn new<S: Into<String>>(base_url: S) -> Self {
+    HttpRegistry::with_client(base_url, reqwest::Client::new())
+  }
+
+  /// Creates a registry served from the given base URL that's accessed with
+  /// the given client, e.g. one that authenticates requests
+  pub fn with_client<S: Into<String>>(base_url: S, client: reqwest::Client) -> Self {
+    let mut base_url = base_url.into();
+    while base_url.ends_with('/') {
+      base_url.pop();
+    }
+
+    HttpRegistry { base_url, client }
+  }
+
+  fn url(&self, fingerprint: &Fingerprint) -> String {
+    format!("{}/{}.cddl", self.base_url, fingerprint)
+  }
+}
+
+#[cfg(feature = "http")]
+impl Registry for HttpRegistry {
+  fn get<'a>(&'a self, fingerprint: &'a Fingerprint) -> RegistryFuture<'a, Option<String>> {
+    Box::pin(async move {
+      let response = self
+        .client
+        .get(self.url(fingerprint))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+      if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+      }
+
+      let response = response.error_for_status().map_err(|e| e.to_string())?;
+      response.text().await.map(Some).map_err(|e| e.to_string())
+    })
+  }
+
+  fn put<'a>(&'a self, fingerprint: &'a Fingerprint, text: &'a str) -> RegistryFuture<'a, ()> {
+    Box::pin(async move {
+      self
+        .client
+        .put(self.url(fingerprint))
+        .body(text.to_string())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+    })
+  }
+}
+
+/// Publishes a schema to a registry under its fingerprint, which is returned
+/// for peers to request the schema by. Publishing a schema that's already
+/// stored replaces its text, e.g. with a differently formatted definition
+/// accepting the same data.
+pub async fn publish<R: Registry + ?Sized>(
+  registry: &R,
+  schema: &CddlSchema,
+) -> Result<Fingerprint, Error> {
+  let fingerprint = Fingerprint::of(schema);
+
+  registry
+    .put(&fingerprint, schema.source())
+    .await
+    .map_err(Error::Registry)?;
+
+  Ok(fingerprint)
+}
+
+/// Fetches the schema with the given fingerprint from a registry, verifying
+/// that the schema stored under it does have that fingerprint
+///
+/// # Example
+///
+/// ```
+/// use cddl::{
+///   registry::{fetch, publish, DirectoryRegistry},
+///   schema::CddlSchema,
+/// };
+/// # use std::{future::Future, task::{Context, Poll, RawWaker, RawWakerVTable, Waker}};
+/// # fn noop(_: *const ()) {}
+/// # fn clone(_: *const ()) -> RawWaker {
+/// #   RawWaker::new(std::ptr::null(), &VTABLE)
+/// # }
+/// # static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+/// # fn block_on<T>(future: impl Future<Output = T>) -> T {
+/// #   let waker = unsafe { Waker::from_raw(clone(std::ptr::null())) };
+/// #   match Box::pin(future).as_mut().poll(&mut Context::from_waker(&waker)) {
+/// #     Poll::Ready(value) => value,
+/// #     Poll::Pending => unreachable!(),
+/// #   }
+/// # }
+///
+/// let registry = DirectoryRegistry::new(std::env::temp_dir().join("cddl-registry-doc"));
+/// let schema = CddlSchema::new("reading = [sensor: uint, value: int]").unwrap();
+///
+/// // Usually awaited within an async runtime
+/// let fingerprint = block_on(publish(&registry, &schema)).unwrap();
+///
+/// // A peer receiving the fingerprint, e.g. in a handshake, fetches the
+/// // schema it identifies
+/// let fetched = block_on(fetch(&registry, &fingerprint)).unwrap();
+/// assert_eq!(fetched.source(), schema.source());
+/// ```
+pub async fn fetch<R: Registry + ?Sized>(
+  registry: &R,
+  fingerprint: &Fingerprint,
+) -> Result<CddlSchema, Error> {
+  let text = registry
+    .get(fingerprint)
+    .await
+    .map_err(Error::Registry)?
+    .ok_or(Error::NotFound(*fingerprint))?;
+
+  let schema = CddlSchema::new(text).map_err(|message| Error::Parse {
+    fingerprint: *fingerprint,
+    message,
+  })?;
+
+  let actual = Fingerprint::of(&schema);
+  if actual != *fingerprint {
+    return Err(Error::Mismatch {
+      expected: *fingerprint,
+      actual,
+    });
+  }
+
+  Ok(schema)
+}
+
+#[cfg(test)]
+#[cfg(feature = "json-validation")]
+mod tests {
+  use super::*;
+  use crate::fetch::tests::ready;
+
+  fn registry(name: &str) -> DirectoryRegistry {
+    let dir = std::env::temp_dir().join(format!("cddl-registry-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+
+    DirectoryRegistry::new(dir)
+  }
+
+  #[test]
+  fn verify_fingerprint_text() {
+    let schema = CddlSchema::new("id = uint").unwrap();
+    let fingerprint = Fingerprint::of(&schema);
+
+    let text = fingerprint.to_string();
+    assert_eq!(text.len(), 64);
+    assert_eq!(text.parse::<Fingerprint>(), Ok(fingerprint));
+
+    assert!("abc".parse::<Fingerprint>().is_err());
+    assert!("z".repeat(64).parse::<Fingerprint>().is_err());
+  }
+
+  #[test]
+  fn verify_publish_and_fetch() -> Result<(), Error> {
+    let registry = registry("publish");
+    let schema = CddlSchema::new("reading = [sensor: uint, value: int]").unwrap();
+
+    let fingerprint = ready(publish(&registry, &schema))?;
+    assert!(registry.path(&fingerprint).is_file());
+
+    let fetched = ready(fetch(&registry, &fingerprint))?;
+    assert_eq!(fetched.source(), schema.source());
+
+    // Definitions accepting the same data share a fingerprint
+    let reformatted =
+      CddlSchema::new("reading = [\n  sensor: uint, ; id\n  value: int,\n]").unwrap();
+    assert_eq!(ready(publish(&registry, &reformatted))?, fingerprint);
+
+    let other = Fingerprint::of(&CddlSchema::new("reading = [sensor: uint]").unwrap());
+    assert_eq!(
+      ready(fetch(&registry, &other)).err(),
+      Some(Error::NotFound(other))
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn verify_tampered_schema() {
+    let registry = registry("tampered");
+    let schema = CddlSchema::new("limit = 0..10").unwrap();
+    let fingerprint = ready(publish(&registry, &schema)).unwrap();
+
+    fs::write(registry.path(&fingerprint), "limit = 0..1000").unwrap();
+    match ready(fetch(&registry, &fingerprint)) {
+      Err(Error::Mismatch { expected, actual }) => {
+        assert_eq!(expected, fingerprint);
+        assert_ne!(actual, fingerprint);
+      }
+      result => panic!(
+        "unexpected result: {:?}",
+        result.map(|s| s.source().to_string())
+      ),
+    }
+
+    fs::write(registry.path(&fingerprint), "limit = ").unwrap();
+    match ready(fetch(&registry, &fingerprint)) {
+      Err(Error::Parse { .. }) => (),
+      result => panic!(
+        "unexpected result: {:?}",
+        result.map(|s| s.source().to_string())
+      ),
+    }
+  }
+}
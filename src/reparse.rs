@@ -0,0 +1,304 @@
+use super::{
+  ast::*,
+  lexer::Lexer,
+  parser::{Parser, Result},
+  rewrite::{Rewrite, Rewriter},
+};
+
+use std::mem;
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// A change to the text of a CDDL definition, e.g. as reported by an editor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextEdit<'a> {
+  /// Byte offsets of the start and end of the replaced text in the text
+  /// before the edit
+  pub range: (usize, usize),
+  /// Replacement text
+  pub text: &'a str,
+  /// Full text after the edit, which the reparsed rules borrow from
+  pub input: &'a str,
+}
+
+impl<'a> CDDL<'a> {
+  /// Updates the definition after an edit to its text, re-lexing and
+  /// re-parsing only the rules the edit touches along with their immediate
+  /// neighbours, which are included in case the edit merges or splits rules.
+  /// Every other rule is kept as is, with its spans shifted past the edit, so
+  /// the result is the same as parsing `edit.input` from scratch without the
+  /// cost of doing so for large definitions.
+  ///
+  /// Falls back to parsing the whole of `edit.input` when the affected rules
+  /// can't be parsed on their own. If that fails too, the error is returned
+  /// and the definition is left unchanged.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{lexer_from_str, parser::cddl_from_str, reparse::TextEdit};
+  ///
+  /// let before = "a = int\nb = tstr\nc = bool";
+  /// let after = "a = int\nb = bstr\nc = bool";
+  ///
+  /// let mut cddl = cddl_from_str(&mut lexer_from_str(before), before, true).unwrap();
+  /// cddl
+  ///   .reparse(TextEdit {
+  ///     range: (12, 13),
+  ///     text: "b",
+  ///     input: after,
+  ///   })
+  ///   .unwrap();
+  ///
+  /// assert_eq!(cddl.rules[1].to_string(), "b = bstr");
+  /// ```
+  pub fn reparse(&mut self, edit: TextEdit<'a>) -> Result<()> {
+    match self.reparse_rules(edit) {
+      Some(Reparsed {
+        rules: reparsed,
+        replaced,
+        bytes,
+        lines,
+      }) => {
+        for rule in self.rules[replaced.1..].iter_mut() {
+          rule.rewrite(&mut Shift { bytes, lines });
+        }
+
+        let rest = self.rules.split_off(replaced.1);
+        self.rules.truncate(replaced.0);
+        self.rules.extend(reparsed);
+        self.rules.extend(rest);
+      }
+      None => {
+        let mut lexer = Lexer::new(edit.input);
+        let mut p = Parser::new(lexer.iter(), edit.input)?;
+        self.rules = p.parse_cddl()?.rules;
+      }
+    }
+
+    let mut rules = Vec::new();
+    mem::swap(&mut rules, &mut self.rules);
+    self.rule_index = RuleIndex::default();
+    for rule in rules {
+      self.push_rule(rule);
+    }
+
+    Ok(())
+  }
+
+  // Parses the rules touched by an edit on their own, returning `None` if
+  // they can't be
+  fn reparse_rules(&self, edit: TextEdit<'a>) -> Option<Reparsed<'a>> {
+    let (start, end) = edit.range;
+    if start > end {
+      return None;
+    }
+    let bytes = edit.text.len() as isize - (end - start) as isize;
+    let shift_by = |offset: usize| {
+      let offset = offset as isize + bytes;
+      if offset < 0 {
+        None
+      } else {
+        Some(offset as usize)
+      }
+    };
+
+    // Rules from the one before the first rule ending at or after the edit
+    // start, through the one after the last rule starting at or before the
+    // edit end, along with any rules following it on the same line in case the
+    // edit starts a comment
+    let len = self.rules.len();
+    let first = self
+      .rules
+      .iter()
+      .position(|r| r.span().1 >= start)
+      .unwrap_or(len)
+      .saturating_sub(1);
+    let mut last = match self.rules.iter().position(|r| r.span().0 > end) {
+      Some(idx) => idx + 1,
+      None => len,
+    };
+    while last < len {
+      let gap = shift_by(self.rules[last - 1].span().1)?..shift_by(self.rules[last].span().0)?;
+      if edit.input.get(gap)?.contains('\n') {
+        break;
+      }
+      last += 1;
+    }
+
+    // Byte range of the text to reparse, in the text before the edit. It
+    // starts at the end of the preceding rule rather than at the first
+    // reparsed rule in case the edit ends a comment
+    let region = (
+      match first.checked_sub(1) {
+        Some(idx) => self.rules[idx].span().1,
+        None => 0,
+      },
+      match last.checked_sub(1) {
+        Some(idx) => self.rules[idx].span().1.max(end),
+        None => end,
+      },
+    );
+
+    let text = edit.input.get(region.0..shift_by(region.1)?)?;
+    let mut lexer = Lexer::new(text);
+    let mut p = Parser::new(lexer.iter(), text).ok()?;
+    let mut rules = p.parse_cddl().ok()?.rules;
+
+    let line_offset = line_count(edit.input.get(..region.0)?);
+    for rule in rules.iter_mut() {
+      rule.rewrite(&mut Shift {
+        bytes: region.0 as isize,
+        lines: line_offset as isize,
+      });
+    }
+
+    // A reparsed rule may redefine a rule defined elsewhere, which is left to
+    // a full parse to report
+    let kept = self.rules[..first].iter().chain(&self.rules[last..]);
+    for rule in rules.iter().filter(|r| !r.is_choice_alternate()) {
+      if kept
+        .clone()
+        .any(|r| !r.is_choice_alternate() && r.name_ident() == rule.name_ident())
+      {
+        return None;
+      }
+    }
+
+    let lines = match self.rules.get(last) {
+      Some(next) => {
+        let line = line_count(edit.input.get(..shift_by(next.span().0)?)?) + 1;
+        line as isize - next.span().2 as isize
+      }
+      None => 0,
+    };
+
+    Some(Reparsed {
+      rules,
+      replaced: (first, last),
+      bytes,
+      lines,
+    })
+  }
+}
+
+// Rules parsed from the text touched by an edit, along with the positions of
+// the rules they replace and how far the rules after them have moved
+struct Reparsed<'a> {
+  rules: Vec<Rule<'a>>,
+  replaced: (usize, usize),
+  bytes: isize,
+  lines: isize,
+}
+
+// Moves spans by a number of bytes and lines
+struct Shift {
+  bytes: isize,
+  lines: isize,
+}
+
+impl<'a> Rewriter<'a> for Shift {
+  fn span(&mut self, span: &mut Span) {
+    span.0 = (span.0 as isize + self.bytes) as usize;
+    span.1 = (span.1 as isize + self.bytes) as usize;
+    span.2 = (span.2 as isize + self.lines) as usize;
+  }
+}
+
+fn line_count(text: &str) -> usize {
+  text.bytes().filter(|&b| b == b'\n').count()
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+  use super::*;
+
+  const INPUT: &str = r#"; header
+person = {
+  name: tstr,
+  ? age: uint .le 150,   ; years
+  * tstr => any
+}
+
+pair<T> = [T, T]
+coords = pair<float>
+
+; a comment between rules
+kind = "a" / "b"
+kind /= "c"
+entries = (a: int, b: bool)
+"#;
+
+  fn parse(input: &str) -> Result<CDDL<'_>> {
+    let mut lexer = Lexer::new(input);
+    Parser::new(lexer.iter(), input)?.parse_cddl()
+  }
+
+  fn edited(input: &str, range: (usize, usize), text: &str) -> String {
+    format!("{}{}{}", &input[..range.0], text, &input[range.1..])
+  }
+
+  // Checks that reparsing after the edit gives the same rules, spans
+  // included, as parsing the edited text from scratch
+  fn assert_reparses(input: &str, range: (usize, usize), text: &str) {
+    let after = edited(input, range, text);
+    let mut cddl = parse(input).unwrap();
+    let result = cddl.reparse(TextEdit {
+      range,
+      text,
+      input: &after,
+    });
+
+    match parse(&after) {
+      Ok(expected) => {
+        assert!(result.is_ok(), "{:?}", after);
+        assert_eq!(cddl.rules, expected.rules, "{:?}", after);
+        for rule in expected.rules.iter() {
+          assert!(cddl.rule(&rule.name()).is_some());
+        }
+      }
+      Err(_) => {
+        assert!(result.is_err(), "{:?}", after);
+        assert_eq!(cddl.rules, parse(input).unwrap().rules);
+      }
+    }
+  }
+
+  #[test]
+  fn reparse_edits() {
+    let edits = [
+      // Within a rule
+      ((28, 32), "bstr"),
+      // Adding lines within a rule
+      ((33, 33), "\n  id: uint,"),
+      // Inserting a rule between two others
+      ((103, 103), "other = int\n"),
+      // Renaming a rule to one that's already defined
+      ((103, 109), "kind"),
+      // Merging rules by removing an assignment
+      ((180, 190), ""),
+      // Splitting a rule
+      ((34, 34), "}\nb = {"),
+      // Appending a rule
+      ((INPUT.len(), INPUT.len()), "last = nil\n"),
+      // Replacing everything
+      ((0, INPUT.len()), "a = int"),
+    ];
+
+    for (range, text) in edits.iter() {
+      assert_reparses(INPUT, *range, text);
+    }
+  }
+
+  #[test]
+  fn reparse_every_position() {
+    for idx in (0..INPUT.len()).filter(|&idx| INPUT.is_char_boundary(idx)) {
+      assert_reparses(INPUT, (idx, idx + 1), "");
+      for text in [" ", "\n", "x", "; ", "=", "]", "\""].iter() {
+        assert_reparses(INPUT, (idx, idx), text);
+      }
+    }
+  }
+}
@@ -0,0 +1,211 @@
+use super::ast::*;
+
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+
+// Rewrites the spans and identifiers within a node of the AST in place
+pub(crate) trait Rewrite<'a> {
+  fn rewrite(&mut self, r: &mut dyn Rewriter<'a>);
+}
+
+pub(crate) trait Rewriter<'a> {
+  fn span(&mut self, _span: &mut Span) {}
+
+  fn identifier(&mut self, _ident: &mut Identifier<'a>) {}
+}
+
+impl<'a, T: Rewrite<'a>> Rewrite<'a> for Option<T> {
+  fn rewrite(&mut self, r: &mut dyn Rewriter<'a>) {
+    if let Some(node) = self {
+      node.rewrite(r);
+    }
+  }
+}
+
+impl<'a, T: Rewrite<'a>> Rewrite<'a> for Box<T> {
+  fn rewrite(&mut self, r: &mut dyn Rewriter<'a>) {
+    (**self).rewrite(r);
+  }
+}
+
+impl<'a> Rewrite<'a> for Rule<'a> {
+  fn rewrite(&mut self, r: &mut dyn Rewriter<'a>) {
+    match self {
+      Rule::Type { rule, span } => {
+        rule.name.rewrite(r);
+        rule.generic_param.rewrite(r);
+        rule.value.rewrite(r);
+        r.span(span);
+      }
+      Rule::Group { rule, span } => {
+        rule.name.rewrite(r);
+        rule.generic_param.rewrite(r);
+        rule.entry.rewrite(r);
+        r.span(span);
+      }
+    }
+  }
+}
+
+impl<'a> Rewrite<'a> for Identifier<'a> {
+  fn rewrite(&mut self, r: &mut dyn Rewriter<'a>) {
+    r.identifier(self);
+    r.span(&mut self.span);
+  }
+}
+
+impl<'a> Rewrite<'a> for GenericParm<'a> {
+  fn rewrite(&mut self, r: &mut dyn Rewriter<'a>) {
+    for param in self.params.iter_mut() {
+      param.rewrite(r);
+    }
+    r.span(&mut self.span);
+  }
+}
+
+impl<'a> Rewrite<'a> for GenericArg<'a> {
+  fn rewrite(&mut self, r: &mut dyn Rewriter<'a>) {
+    for arg in self.args.iter_mut() {
+      arg.rewrite(r);
+    }
+    r.span(&mut self.span);
+  }
+}
+
+impl<'a> Rewrite<'a> for Type<'a> {
+  fn rewrite(&mut self, r: &mut dyn Rewriter<'a>) {
+    for choice in self.type_choices.iter_mut() {
+      choice.rewrite(r);
+    }
+    r.span(&mut self.span);
+  }
+}
+
+impl<'a> Rewrite<'a> for Type1<'a> {
+  fn rewrite(&mut self, r: &mut dyn Rewriter<'a>) {
+    self.type2.rewrite(r);
+    if let Some((op, t2)) = &mut self.operator {
+      match op {
+        RangeCtlOp::RangeOp { span, .. } | RangeCtlOp::CtlOp { span, .. } => r.span(span),
+      }
+      t2.rewrite(r);
+    }
+    r.span(&mut self.span);
+  }
+}
+
+impl<'a> Rewrite<'a> for Type2<'a> {
+  fn rewrite(&mut self, r: &mut dyn Rewriter<'a>) {
+    match self {
+      Type2::IntValue { span, .. }
+      | Type2::UintValue { span, .. }
+      | Type2::FloatValue { span, .. }
+      | Type2::TextValue { span, .. }
+      | Type2::UTF8ByteString { span, .. }
+      | Type2::B16ByteString { span, .. }
+      | Type2::B64ByteString { span, .. }
+      | Type2::TaggedDataMajorType { span, .. }
+      | Type2::Any(span) => r.span(span),
+      Type2::Typename {
+        ident,
+        generic_arg,
+        span,
+      }
+      | Type2::Unwrap {
+        ident,
+        generic_arg,
+        span,
+      }
+      | Type2::ChoiceFromGroup {
+        ident,
+        generic_arg,
+        span,
+      } => {
+        ident.rewrite(r);
+        generic_arg.rewrite(r);
+        r.span(span);
+      }
+      Type2::ParenthesizedType { pt: t, span } | Type2::TaggedData { t, span, .. } => {
+        t.rewrite(r);
+        r.span(span);
+      }
+      Type2::Map { group, span }
+      | Type2::Array { group, span }
+      | Type2::ChoiceFromInlineGroup { group, span } => {
+        group.rewrite(r);
+        r.span(span);
+      }
+    }
+  }
+}
+
+impl<'a> Rewrite<'a> for Group<'a> {
+  fn rewrite(&mut self, r: &mut dyn Rewriter<'a>) {
+    for choice in self.group_choices.iter_mut() {
+      choice.rewrite(r);
+    }
+    r.span(&mut self.span);
+  }
+}
+
+impl<'a> Rewrite<'a> for GroupChoice<'a> {
+  fn rewrite(&mut self, r: &mut dyn Rewriter<'a>) {
+    for (entry, _) in self.group_entries.iter_mut() {
+      entry.rewrite(r);
+    }
+    r.span(&mut self.span);
+  }
+}
+
+impl<'a> Rewrite<'a> for GroupEntry<'a> {
+  fn rewrite(&mut self, r: &mut dyn Rewriter<'a>) {
+    match self {
+      GroupEntry::ValueMemberKey { ge, span } => {
+        ge.occur.rewrite(r);
+        ge.member_key.rewrite(r);
+        ge.entry_type.rewrite(r);
+        r.span(span);
+      }
+      GroupEntry::TypeGroupname { ge, span } => {
+        ge.occur.rewrite(r);
+        ge.name.rewrite(r);
+        ge.generic_arg.rewrite(r);
+        r.span(span);
+      }
+      GroupEntry::InlineGroup { occur, group, span } => {
+        occur.rewrite(r);
+        group.rewrite(r);
+        r.span(span);
+      }
+    }
+  }
+}
+
+impl<'a> Rewrite<'a> for MemberKey<'a> {
+  fn rewrite(&mut self, r: &mut dyn Rewriter<'a>) {
+    match self {
+      MemberKey::Type1 { t1, span, .. } => {
+        t1.rewrite(r);
+        r.span(span);
+      }
+      MemberKey::Bareword { ident, span } => {
+        ident.rewrite(r);
+        r.span(span);
+      }
+      MemberKey::Value { span, .. } => r.span(span),
+      MemberKey::NonMemberKey(NonMemberKey::Group(group)) => group.rewrite(r),
+      MemberKey::NonMemberKey(NonMemberKey::Type(t)) => t.rewrite(r),
+    }
+  }
+}
+
+impl<'a> Rewrite<'a> for Occur {
+  fn rewrite(&mut self, r: &mut dyn Rewriter<'a>) {
+    match self {
+      Occur::Exact { span, .. }
+      | Occur::ZeroOrMore(span)
+      | Occur::OneOrMore(span)
+      | Occur::Optional(span) => r.span(span),
+    }
+  }
+}
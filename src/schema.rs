@@ -0,0 +1,339 @@
+use super::{
+  ast::{Identifier, CDDL},
+  lexer::Lexer,
+  parser::cddl_from_str,
+  rewrite::{Rewrite, Rewriter},
+};
+use std::{convert::TryFrom, fmt, str::FromStr};
+
+#[cfg(feature = "std")]
+use std::{collections::BTreeSet, sync::Arc};
+
+#[cfg(not(feature = "std"))]
+use alloc::{collections::BTreeSet, string::String, sync::Arc, vec::Vec};
+
+/// A parsed CDDL schema that owns its source text
+///
+/// `ast::CDDL` borrows from the text it was parsed from, which requires the
+/// text to be kept alive alongside it. `CddlSchema` stores both together so
+/// that a schema can be parsed once, stored (e.g. in a `lazy_static` or
+/// `OnceCell`) and reused for any number of validations. Cloning a schema is
+/// cheap since the parsed AST is shared, and schemas can be sent to and shared
+/// between threads.
+///
+/// Identifiers are interned into a single allocation owned by the schema, so
+/// every occurrence of a name in the AST refers to the same string and can be
+/// compared by address, e.g. with the one returned by `identifier`.
+///
+/// # Example
+///
+/// ```
+/// use cddl::{schema::CddlSchema, validation::Validator};
+///
+/// let schema = CddlSchema::new("person = { name: tstr, age: uint }").unwrap();
+///
+/// let handles = (0..2)
+///   .map(|age| {
+///     let schema = schema.clone();
+///
+///     std::thread::spawn(move || {
+///       let json = serde_json::json!({ "name": "Alice", "age": age });
+///       schema.cddl().validate(&json).is_ok()
+///     })
+///   })
+///   .collect::<Vec<_>>();
+///
+/// for handle in handles {
+///   assert!(handle.join().unwrap());
+/// }
+/// ```
+#[derive(Clone)]
+pub struct CddlSchema {
+  inner: Arc<OwnedCDDL>,
+}
+
+struct OwnedCDDL {
+  // Fields are dropped in declaration order, so the AST is always dropped
+  // before the strings it borrows from
+  cddl: CDDL<'static>,
+  identifiers: Interned,
+  source: Arc<str>,
+}
+
+// Distinct identifiers stored back to back, with the byte range of each in
+// sorted order
+struct Interned {
+  names: Arc<str>,
+  ranges: Vec<(usize, usize)>,
+}
+
+// A schema is only useful to multi-threaded servers if it can be shared
+// between threads, which this fails to compile without
+const _: fn() = || {
+  fn assert_send_sync<T: Send + Sync>() {}
+  assert_send_sync::<CddlSchema>();
+};
+
+impl CddlSchema {
+  /// Parses the given CDDL text into a schema, returning an error message if
+  /// the text isn't conformant
+  pub fn new<S: Into<String>>(input: S) -> Result<Self, String> {
+    let source: Arc<str> = Arc::from(input.into());
+
+    // The AST borrows from the heap allocation that `source` points to, which
+    // is never mutated. Unlike a `Box`, an `Arc` only holds a raw pointer to its
+    // allocation, so moving it into the schema leaves borrows of the text
+    // valid. Extending the borrow is sound since the allocation is stored
+    // alongside the AST for as long as it lives, and the AST is only ever
+    // handed out with a lifetime bound to the schema.
+    let input: &'static str = unsafe { &*(&*source as *const str) };
+
+    let mut lexer = Lexer::new(input);
+    #[cfg(feature = "std")]
+    let mut cddl = cddl_from_str(&mut lexer, input, false)?;
+    #[cfg(not(feature = "std"))]
+    let mut cddl = cddl_from_str(&mut lexer, input)?;
+
+    let identifiers = Interned::new(&mut cddl);
+
+    Ok(CddlSchema {
+      inner: Arc::new(OwnedCDDL {
+        cddl,
+        identifiers,
+        source,
+      }),
+    })
+  }
+
+  /// Returns the parsed AST
+  pub fn cddl(&self) -> &CDDL<'_> {
+    &self.inner.cddl
+  }
+
+  /// Returns the CDDL text the schema was parsed from
+  pub fn source(&self) -> &str {
+    &self.inner.source
+  }
+
+  /// Returns the interned copy of an identifier used in the schema, which
+  /// every occurrence of the identifier in the AST refers to
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{ast::Rule, schema::CddlSchema};
+  /// use std::ptr;
+  ///
+  /// let schema = CddlSchema::new("a = [b, b]\nb = tstr").unwrap();
+  /// let b = schema.identifier("b").unwrap();
+  ///
+  /// if let Rule::Type { rule, .. } = &schema.cddl().rules[1] {
+  ///   assert!(ptr::eq(rule.name.ident, b));
+  /// }
+  /// assert!(schema.identifier("c").is_none());
+  /// ```
+  pub fn identifier(&self, name: &str) -> Option<&str> {
+    let interned = &self.inner.identifiers;
+
+    interned
+      .ranges
+      .binary_search_by(|&(start, end)| interned.names[start..end].cmp(name))
+      .ok()
+      .map(|idx| {
+        let (start, end) = interned.ranges[idx];
+        &interned.names[start..end]
+      })
+  }
+
+  /// Returns the distinct identifiers used in the schema in sorted order
+  pub fn identifiers(&self) -> impl Iterator<Item = &str> {
+    let interned = &self.inner.identifiers;
+
+    interned
+      .ranges
+      .iter()
+      .map(move |&(start, end)| &interned.names[start..end])
+  }
+}
+
+impl Interned {
+  // Copies every distinct identifier in the AST into a single allocation and
+  // points the identifiers at their copies
+  fn new(cddl: &mut CDDL<'static>) -> Self {
+    struct Collect(BTreeSet<&'static str>);
+
+    impl Rewriter<'static> for Collect {
+      fn identifier(&mut self, ident: &mut Identifier<'static>) {
+        self.0.insert(ident.ident);
+      }
+    }
+
+    struct Replace<'b> {
+      names: &'static str,
+      ranges: &'b [(usize, usize)],
+    }
+
+    impl<'b> Rewriter<'static> for Replace<'b> {
+      fn identifier(&mut self, ident: &mut Identifier<'static>) {
+        let names = self.names;
+        if let Ok(idx) = self
+          .ranges
+          .binary_search_by(|&(start, end)| names[start..end].cmp(ident.ident))
+        {
+          let (start, end) = self.ranges[idx];
+          ident.ident = &names[start..end];
+        }
+      }
+    }
+
+    let mut collect = Collect(BTreeSet::new());
+    for rule in cddl.rules.iter_mut() {
+      rule.rewrite(&mut collect);
+    }
+
+    let mut names = String::new();
+    let mut ranges = Vec::with_capacity(collect.0.len());
+    for name in collect.0 {
+      ranges.push((names.len(), names.len() + name.len()));
+      names.push_str(name);
+    }
+    let names: Arc<str> = Arc::from(names);
+
+    // As with the source, the allocation is stored alongside the AST and
+    // outlives it
+    let mut replace = Replace {
+      names: unsafe { &*(&*names as *const str) },
+      ranges: &ranges,
+    };
+    for rule in cddl.rules.iter_mut() {
+      rule.rewrite(&mut replace);
+    }
+
+    Interned { names, ranges }
+  }
+}
+
+impl FromStr for CddlSchema {
+  type Err = String;
+
+  /// Parses a schema, as with `CddlSchema::new`
+  fn from_str(input: &str) -> Result<Self, Self::Err> {
+    CddlSchema::new(input)
+  }
+}
+
+impl TryFrom<&str> for CddlSchema {
+  type Error = String;
+
+  fn try_from(input: &str) -> Result<Self, Self::Error> {
+    CddlSchema::new(input)
+  }
+}
+
+impl TryFrom<String> for CddlSchema {
+  type Error = String;
+
+  fn try_from(input: String) -> Result<Self, Self::Error> {
+    CddlSchema::new(input)
+  }
+}
+
+impl fmt::Debug for CddlSchema {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("CddlSchema")
+      .field("cddl", self.cddl())
+      .finish()
+  }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+  use super::*;
+  use crate::visitor::Visitor;
+
+  #[cfg(feature = "json-validation")]
+  #[cfg(not(target_arch = "wasm32"))]
+  use crate::validation::Validator;
+  use std::ptr;
+
+  fn is_send_sync<T: Send + Sync>() {}
+
+  #[test]
+  fn verify_schema_outlives_source() {
+    is_send_sync::<CddlSchema>();
+
+    let schema = {
+      let input = String::from("reputon = { rater: text, rating: float16 }");
+      CddlSchema::new(input.as_str()).unwrap()
+    };
+
+    assert_eq!(
+      schema.source(),
+      "reputon = { rater: text, rating: float16 }"
+    );
+    assert_eq!(schema.cddl().rules[0].name(), "reputon");
+
+    let cloned = schema.clone();
+    drop(schema);
+    assert_eq!(cloned.cddl().rules[0].name(), "reputon");
+
+    #[cfg(feature = "json-validation")]
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+      let json = serde_json::json!({ "rater": "Ninja", "rating": 1.0 });
+      assert!(cloned.cddl().validate(&json).is_ok());
+
+      let json = serde_json::json!({ "rater": "Ninja" });
+      assert!(cloned.cddl().validate(&json).is_err());
+    }
+  }
+
+  #[test]
+  fn verify_interned_identifiers() {
+    let schema = CddlSchema::new("a = { b: b, c: [* b] }\nb = tstr / c\nc = uint").unwrap();
+
+    assert_eq!(
+      schema.identifiers().collect::<Vec<_>>(),
+      ["a", "b", "c", "tstr", "uint"]
+    );
+
+    // Collects every identifier in the AST as written
+    struct Identifiers<'a>(Vec<&'a str>);
+
+    impl<'a> Visitor<'a> for Identifiers<'a> {
+      fn visit_rule_name(&mut self, ident: &Identifier<'a>) {
+        self.0.push(ident.ident);
+      }
+
+      fn visit_typename(&mut self, ident: &Identifier<'a>) {
+        self.0.push(ident.ident);
+      }
+    }
+
+    let mut identifiers = Identifiers(Vec::new());
+    identifiers.visit_cddl(schema.cddl());
+
+    assert_eq!(identifiers.0.len(), 8);
+    for ident in identifiers.0 {
+      assert!(ptr::eq(ident, schema.identifier(ident).unwrap()));
+    }
+  }
+
+  #[test]
+  fn verify_schema_error() {
+    assert!(CddlSchema::new("myrule = ").is_err());
+  }
+
+  #[test]
+  fn verify_schema_conversions() {
+    let schema = "myrule = int".parse::<CddlSchema>().unwrap();
+    assert_eq!(schema.source(), "myrule = int");
+
+    let schema = CddlSchema::try_from(String::from("myrule = tstr")).unwrap();
+    assert_eq!(schema.cddl().rules[0].name(), "myrule");
+
+    assert!("myrule = ".parse::<CddlSchema>().is_err());
+    assert!(CddlSchema::try_from("myrule = ").is_err());
+  }
+}
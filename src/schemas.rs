@@ -0,0 +1,265 @@
+use crate::{ast::CDDL, lexer::Lexer, parser::Parser};
+
+// Representation-independent part of the SenML definition (RFC 8428 section
+// 11), to which the JSON and CBOR representations add their labels
+macro_rules! senml {
+  () => {
+    r#"
+SenML-Pack = [1* record]
+
+record = {
+  ? bn => tstr,        ; Base Name
+  ? bt => numeric,     ; Base Time
+  ? bu => tstr,        ; Base Units
+  ? bv => numeric,     ; Base Value
+  ? bs => numeric,     ; Base Sum
+  ? bver => uint,      ; Base Version
+  ? n => tstr,        ; Name
+  ? u => tstr,        ; Units
+  ? s => numeric,     ; Sum
+  ? t => numeric,     ; Time
+  ? ut => numeric,    ; Update Time
+  ? ( v => numeric // ; Numeric Value
+      vs => tstr //   ; String Value
+      vb => bool //   ; Boolean Value
+      vd => binary-value ) ; Data Value
+  * key-value-pair
+}
+
+; now define the generic versions
+key-value-pair = ( label => value )
+
+label = non-b-label / b-label
+non-b-label = tstr .regexp  "[A-Zac-z0-9][-_:.A-Za-z0-9]*" / uint
+b-label = tstr .regexp  "b[-_:.A-Za-z0-9]+" / nint
+
+value = tstr / binary-value / numeric / bool
+numeric = number / decfrac
+"#
+  };
+}
+
+/// SenML Packs in their JSON representation (RFC 8428 sections 5 and 11)
+pub const SENML_JSON: &str = concat!(
+  senml!(),
+  r#"
+bver = "bver" n  = "n"   s  = "s"
+bn  = "bn"  u  = "u"   t  = "t"
+bt  = "bt"  v  = "v"   ut = "ut"
+bu  = "bu"  vs = "vs"  vd = "vd"
+bs  = "bs"  bv = "bv"  vb = "vb"
+
+binary-value = tstr ; base64url encoded
+"#
+);
+
+/// SenML Packs in their CBOR representation (RFC 8428 sections 6 and 11)
+pub const SENML_CBOR: &str = concat!(
+  senml!(),
+  r#"
+bver = -1  n  = 0   s  = 5
+bn  = -2  u  = 1   t  = 6
+bt  = -3  v  = 2   ut = 7
+bu  = -4  vs = 3   vd = 8
+bv  = -5  vb = 4
+bs  = -6
+
+binary-value = bstr
+"#
+);
+
+/// GeneRic Autonomic Signaling Protocol messages (RFC 8990 section 2.8)
+pub const GRASP: &str = r#"
+grasp-message = (message .within message-structure) / noop-message
+
+message-structure = [MESSAGE_TYPE, session-id, ?initiator,
+                     *grasp-option]
+
+MESSAGE_TYPE = 0..255
+session-id = 0..4294967295 ; up to 32 bits
+grasp-option = any
+
+message /= discovery-message
+discovery-message = [M_DISCOVERY, session-id, initiator, objective]
+
+message /= response-message ; response to Discovery
+response-message = [M_RESPONSE, session-id, initiator, ttl,
+                    (+locator-option // divert-option), ?objective]
+
+message /= synch-message ; response to Synchronization request
+synch-message = [M_SYNCH, session-id, objective]
+
+message /= flood-message
+flood-message = [M_FLOOD, session-id, initiator, ttl,
+                 +[objective, (locator-option / [])]]
+
+message /= request-negotiation-message
+request-negotiation-message = [M_REQ_NEG, session-id, objective]
+
+message /= request-synchronization-message
+request-synchronization-message = [M_REQ_SYN, session-id, objective]
+
+message /= negotiation-message
+negotiation-message = [M_NEGOTIATE, session-id, objective]
+
+message /= end-message
+end-message = [M_END, session-id, accept-option / decline-option ]
+
+message /= wait-message
+wait-message = [M_WAIT, session-id, waiting-time]
+
+message /= invalid-message
+invalid-message = [M_INVALID, session-id, ?any]
+
+noop-message = [M_NOOP]
+
+divert-option = [O_DIVERT, +locator-option]
+
+accept-option = [O_ACCEPT]
+
+decline-option = [O_DECLINE, ?reason]
+reason = text  ; optional UTF-8 error message
+
+waiting-time = 0..4294967295 ; in milliseconds
+ttl = 0..4294967295 ; in milliseconds
+
+locator-option = ipv4-locator-option / ipv6-locator-option /
+                 fqdn-locator-option / uri-locator-option
+
+ipv4-locator-option = [O_IPv4_LOCATOR, ipv4-address,
+                       transport-proto, port-number]
+ipv4-address = bytes .size 4
+
+ipv6-locator-option = [O_IPv6_LOCATOR, ipv6-address,
+                       transport-proto, port-number]
+ipv6-address = bytes .size 16
+
+fqdn-locator-option = [O_FQDN_LOCATOR, text, transport-proto,
+                       port-number]
+
+uri-locator-option = [O_URI_LOCATOR, text,
+                      transport-proto / null, port-number / null]
+
+transport-proto = IPPROTO_TCP / IPPROTO_UDP
+IPPROTO_TCP = 6
+IPPROTO_UDP = 17
+port-number = 0..65535
+
+initiator = ipv4-address / ipv6-address
+
+objective-flags = uint .bits objective-flag
+
+objective = [objective-name, objective-flags,
+             loop-count, ?objective-value]
+
+objective-name = text ; see section "Format of Objective Options"
+
+objective-value = any
+
+loop-count = 0..255
+
+objective-flag = &(
+  F_DISC: 0    ; valid for discovery
+  F_NEG: 1     ; valid for negotiation
+  F_SYNCH: 2   ; valid for synchronization
+  F_NEG_DRY: 3 ; negotiation is a dry run
+)
+
+; Message types and option types are assigned by IANA
+
+M_NOOP = 0
+M_DISCOVERY = 1
+M_RESPONSE = 2
+M_REQ_NEG = 3
+M_REQ_SYN = 4
+M_NEGOTIATE = 5
+M_END = 6
+M_WAIT = 7
+M_SYNCH = 8
+M_FLOOD = 9
+M_INVALID = 99
+
+O_DIVERT = 100
+O_ACCEPT = 101
+O_DECLINE = 102
+O_IPv6_LOCATOR = 103
+O_IPv4_LOCATOR = 104
+O_FQDN_LOCATOR = 105
+O_URI_LOCATOR = 106
+"#;
+
+/// Names of the bundled schemas along with their CDDL
+pub const SCHEMAS: &[(&str, &str)] = &[
+  ("senml-json", SENML_JSON),
+  ("senml-cbor", SENML_CBOR),
+  ("grasp", GRASP),
+];
+
+/// Returns the CDDL of the bundled schema with the given name
+pub fn source(name: &str) -> Option<&'static str> {
+  SCHEMAS
+    .iter()
+    .find(|(n, _)| *n == name)
+    .map(|(_, source)| *source)
+}
+
+/// Returns the bundled schema with the given name, parsed. Since the schema is
+/// parsed on every call, the result should be kept for as long as it's used.
+///
+/// # Example
+///
+/// ```
+/// use cddl::{cbor_core, schemas};
+///
+/// let grasp = schemas::get("grasp").unwrap();
+///
+/// // [M_NOOP]
+/// let message = cbor_core::decode(&[0x81, 0x00]).unwrap();
+/// assert!(cbor_core::validate(&grasp, &message).is_ok());
+/// ```
+pub fn get(name: &str) -> Option<CDDL<'static>> {
+  source(name).map(|input| {
+    Parser::new(Lexer::new(input).iter(), input)
+      .and_then(|mut p| p.parse_cddl())
+      .expect("bundled schemas are conformant")
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::cbor_core;
+
+  fn validate_hex(cddl: &CDDL, cbor_hex: &str) -> Result<(), cbor_core::Error> {
+    let mut cbor = vec![0; cbor_hex.len() / 2];
+    base16::decode_slice(cbor_hex, &mut cbor).unwrap();
+
+    cbor_core::validate(cddl, &cbor_core::decode(&cbor)?)
+  }
+
+  #[test]
+  fn parse_schemas() {
+    for (name, _) in SCHEMAS.iter() {
+      assert!(get(name).is_some());
+    }
+
+    assert!(source("coral").is_none());
+  }
+
+  #[test]
+  fn validate_grasp() {
+    let grasp = get("grasp").unwrap();
+
+    // [M_NOOP]
+    assert_eq!(validate_hex(&grasp, "8100"), Ok(()));
+    // [M_END, 1, [O_ACCEPT]]
+    assert_eq!(validate_hex(&grasp, "830601811865"), Ok(()));
+    // [M_DISCOVERY, 1, h'0a000001', ["EX1", 2, 4]]
+    assert_eq!(
+      validate_hex(&grasp, "840101440a00000183634558310204"),
+      Ok(())
+    );
+    // An initiator must be an IPv4 or IPv6 address
+    assert!(validate_hex(&grasp, "8401014a0a00000183634558310204").is_err());
+  }
+}
@@ -0,0 +1,217 @@
+use super::{
+  ast::CDDL,
+  generator::{Error, Generator, GeneratorOptions, Mode},
+};
+use proptest::{
+  prelude::any,
+  strategy::{NewTree, Strategy, ValueTree},
+  test_runner::TestRunner,
+};
+use std::fmt;
+
+/// Returns a proptest strategy generating JSON values that conform to the
+/// rule with the given name
+///
+/// Values are produced by the [`Generator`](../generator/struct.Generator.html)
+/// from a seed drawn from the test runner, so failing cases can be reproduced
+/// with proptest's persisted seeds. Failing values are shrunk to the smallest
+/// instance of the rule. Creating a value tree fails if the rule isn't defined
+/// or no conforming JSON value can be generated.
+///
+/// # Example
+///
+/// ```
+/// use cddl::{lexer_from_str, parser::cddl_from_str, testing::strategy_for_rule};
+/// use proptest::test_runner::TestRunner;
+///
+/// let input = r#"person = { name: tstr, age: 0..120 }"#;
+/// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+///
+/// let mut runner = TestRunner::default();
+/// runner
+///   .run(&strategy_for_rule(&cddl, "person"), |person| {
+///     assert!(person["age"].as_u64().unwrap() <= 120);
+///     Ok(())
+///   })
+///   .unwrap();
+/// ```
+pub fn strategy_for_rule<'a, 'b>(
+  cddl: &'b CDDL<'a>,
+  rule: &str,
+) -> RuleStrategy<'a, 'b, serde_json::Value> {
+  RuleStrategy {
+    cddl,
+    rule: rule.to_string(),
+    generate: Generator::json_for_rule,
+  }
+}
+
+/// Returns a proptest strategy generating CBOR values that conform to the
+/// rule with the given name
+///
+/// See [`strategy_for_rule`](fn.strategy_for_rule.html) for details.
+pub fn cbor_strategy_for_rule<'a, 'b>(
+  cddl: &'b CDDL<'a>,
+  rule: &str,
+) -> RuleStrategy<'a, 'b, serde_cbor::Value> {
+  RuleStrategy {
+    cddl,
+    rule: rule.to_string(),
+    generate: Generator::cbor_for_rule,
+  }
+}
+
+/// Strategy generating values that conform to a CDDL rule
+pub struct RuleStrategy<'a, 'b, T> {
+  cddl: &'b CDDL<'a>,
+  rule: String,
+  generate: fn(&mut Generator<'a, 'b>, &str) -> Result<T, Error>,
+}
+
+impl<'a, 'b, T> fmt::Debug for RuleStrategy<'a, 'b, T> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("RuleStrategy")
+      .field("rule", &self.rule)
+      .finish()
+  }
+}
+
+impl<'a, 'b, T> Strategy for RuleStrategy<'a, 'b, T>
+where
+  T: Clone + fmt::Debug + PartialEq,
+{
+  type Tree = InstanceTree<T>;
+  type Value = T;
+
+  fn new_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
+    let seed = any::<u64>().new_tree(runner)?.current();
+
+    let options = GeneratorOptions {
+      seed: Some(seed),
+      ..Default::default()
+    };
+    let value = (self.generate)(&mut Generator::new(self.cddl, &options), &self.rule)
+      .map_err(|e| e.to_string())?;
+
+    let options = GeneratorOptions {
+      mode: Mode::Minimal,
+      ..Default::default()
+    };
+    let smallest = (self.generate)(&mut Generator::new(self.cddl, &options), &self.rule).ok();
+
+    Ok(InstanceTree {
+      value,
+      smallest,
+      state: Shrink::Initial,
+    })
+  }
+}
+
+/// Value tree of a [`RuleStrategy`](struct.RuleStrategy.html), which shrinks
+/// to the smallest instance of the rule
+#[derive(Debug, Clone)]
+pub struct InstanceTree<T> {
+  value: T,
+  smallest: Option<T>,
+  state: Shrink,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Shrink {
+  Initial,
+  Smallest,
+  Exhausted,
+}
+
+impl<T> ValueTree for InstanceTree<T>
+where
+  T: Clone + fmt::Debug + PartialEq,
+{
+  type Value = T;
+
+  fn current(&self) -> T {
+    match (&self.state, &self.smallest) {
+      (Shrink::Smallest, Some(smallest)) => smallest.clone(),
+      _ => self.value.clone(),
+    }
+  }
+
+  fn simplify(&mut self) -> bool {
+    if self.state != Shrink::Initial {
+      return false;
+    }
+
+    match &self.smallest {
+      Some(smallest) if *smallest != self.value => {
+        self.state = Shrink::Smallest;
+        true
+      }
+      _ => {
+        self.state = Shrink::Exhausted;
+        false
+      }
+    }
+  }
+
+  fn complicate(&mut self) -> bool {
+    if self.state != Shrink::Smallest {
+      return false;
+    }
+
+    // The smallest instance passed, so the value can't be shrunk any further
+    self.state = Shrink::Exhausted;
+    true
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{lexer::Lexer, parser::cddl_from_str, validation::Validator};
+  use proptest::test_runner::TestError;
+  use serde_json::json;
+
+  const INPUT: &str = r#"
+    message = { id: uint, body: tstr, recipients: [+ tstr] }
+  "#;
+
+  #[test]
+  fn verify_strategy_for_rule() {
+    let cddl = cddl_from_str(&mut Lexer::new(INPUT), INPUT, false).unwrap();
+
+    let mut runner = TestRunner::default();
+    let result = runner.run(&strategy_for_rule(&cddl, "message"), |message| {
+      assert!(cddl.validate(&message).is_ok(), "{}", message);
+      Ok(())
+    });
+
+    assert!(result.is_ok());
+  }
+
+  #[test]
+  fn verify_failures_shrink_to_smallest_instance() {
+    let cddl = cddl_from_str(&mut Lexer::new(INPUT), INPUT, false).unwrap();
+
+    let mut runner = TestRunner::default();
+    let result = runner.run(&strategy_for_rule(&cddl, "message"), |_| {
+      Err(proptest::test_runner::TestCaseError::fail("always fails"))
+    });
+
+    match result {
+      Err(TestError::Fail(_, message)) => {
+        assert_eq!(message, json!({ "id": 0, "body": "", "recipients": [""] }))
+      }
+      result => panic!("unexpected result: {:?}", result),
+    }
+  }
+
+  #[test]
+  fn verify_cbor_strategy_for_missing_rule() {
+    let cddl = cddl_from_str(&mut Lexer::new(INPUT), INPUT, false).unwrap();
+
+    let mut runner = TestRunner::default();
+    let result = runner.run(&cbor_strategy_for_rule(&cddl, "missing"), |_| Ok(()));
+
+    assert!(result.is_err());
+  }
+}
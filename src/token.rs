@@ -3,8 +3,8 @@ use std::{convert::TryFrom, fmt};
 #[cfg(feature = "std")]
 use std::borrow::Cow;
 
-#[cfg(target_arch = "wasm32")]
-use serde::Serialize;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
 
 #[cfg(not(feature = "std"))]
 use alloc::{borrow::Cow, string::String};
@@ -44,6 +44,8 @@ pub enum Token<'a> {
 
   /// Comment text
   COMMENT(&'a str),
+  /// Whitespace between tokens. Only produced by `lexer::tokens_with_trivia`.
+  WHITESPACE(&'a str),
 
   /// Type choice indicator '/'
   TCHOICE,
@@ -93,6 +95,9 @@ pub enum Token<'a> {
   CBOR,
   /// .cborseq control operator
   CBORSEQ,
+  /// .json control operator
+  /// Text string holding JSON, the analogue of .cbor for text
+  JSON,
   /// .within control operator
   WITHIN,
   /// .and control operator
@@ -111,6 +116,10 @@ pub enum Token<'a> {
   NE,
   /// .default control operator
   DEFAULT,
+  /// .capture control operator
+  /// Marks values of the target type for extraction under the name given by
+  /// the controller
+  CAPTURE,
   /// .pcre control operator
   /// Proposed control extension to support Perl-Compatible Regular Expressions
   /// (PCREs). See https://tools.ietf.org/html/rfc8610#section-3.8.3.2s
@@ -266,9 +275,9 @@ pub enum RangeValue<'a> {
   /// Identifier with optional socket/plug prefix
   IDENT((&'a str, Option<SocketPlug>)),
   /// Integer
-  INT(isize),
+  INT(i128),
   /// Unsigned integer
-  UINT(usize),
+  UINT(u128),
   /// Float
   FLOAT(f64),
 }
@@ -292,7 +301,7 @@ impl<'a> TryFrom<Token<'a>> for RangeValue<'a> {
 
 impl<'a> RangeValue<'a> {
   /// Returns `Value` from given `RangeValue`
-  pub fn as_value(&self) -> Option<Value> {
+  pub fn as_value(&self) -> Option<Value<'_>> {
     match &self {
       RangeValue::UINT(ui) => Some(Value::UINT(*ui)),
       RangeValue::FLOAT(f) => Some(Value::FLOAT(*f)),
@@ -313,18 +322,18 @@ impl<'a> fmt::Display for RangeValue<'a> {
 }
 
 /// Literal value
-// TODO: support hexfloat and exponent
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(deserialize = "'de: 'a")))]
 #[derive(Debug, PartialEq, Clone)]
 pub enum Value<'a> {
   /// Integer value
-  INT(isize),
+  INT(i128),
   /// Unsigned integer value
-  UINT(usize),
+  UINT(u128),
   /// Float value
   FLOAT(f64),
-  /// Text value
-  TEXT(&'a str),
+  /// Text value, with any escape sequences decoded
+  TEXT(Cow<'a, str>),
   /// Byte value
   BYTE(ByteValue<'a>),
 }
@@ -333,9 +342,9 @@ pub enum Value<'a> {
 #[derive(Debug, PartialEq)]
 pub enum Numeric {
   /// Integer
-  INT(isize),
+  INT(i128),
   /// Unsigned integer
-  UINT(usize),
+  UINT(u128),
   /// Float
   FLOAT(f64),
 }
@@ -343,7 +352,7 @@ pub enum Numeric {
 impl<'a> fmt::Display for Value<'a> {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match self {
-      Value::TEXT(text) => write!(f, "\"{}\"", text),
+      Value::TEXT(text) => write!(f, "{}", TextLiteral(text)),
       Value::INT(i) => write!(f, "{}", i),
       Value::UINT(ui) => write!(f, "{}", ui),
       Value::FLOAT(float) => write!(f, "{}", float),
@@ -354,12 +363,51 @@ impl<'a> fmt::Display for Value<'a> {
 
 impl<'a> From<&'static str> for Value<'a> {
   fn from(value: &'static str) -> Self {
-    Value::TEXT(value)
+    Value::TEXT(value.into())
+  }
+}
+
+// Quotes text as a CDDL text string, escaping the characters that can't
+// appear in one as is
+pub(crate) struct TextLiteral<'a>(pub &'a str);
+
+impl<'a> fmt::Display for TextLiteral<'a> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write_quoted(f, self.0, '"')
+  }
+}
+
+// Quotes UTF-8 text as a CDDL byte string, e.g. 'bytes', escaping the
+// characters that can't appear in one as is
+pub(crate) struct ByteStringLiteral<'a>(pub &'a str);
+
+impl<'a> fmt::Display for ByteStringLiteral<'a> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write_quoted(f, self.0, '\'')
   }
 }
 
+fn write_quoted(f: &mut fmt::Formatter, text: &str, quote: char) -> fmt::Result {
+  write!(f, "{}", quote)?;
+
+  for ch in text.chars() {
+    match ch {
+      '\\' => f.write_str("\\\\")?,
+      '\n' => f.write_str("\\n")?,
+      '\r' => f.write_str("\\r")?,
+      '\t' => f.write_str("\\t")?,
+      '\u{0}'..='\u{1f}' | '\u{7f}'..='\u{9f}' => write!(f, "\\u{:04x}", ch as u32)?,
+      _ if ch == quote => write!(f, "\\{}", ch)?,
+      _ => write!(f, "{}", ch)?,
+    }
+  }
+
+  write!(f, "{}", quote)
+}
+
 /// Byte string values
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(deserialize = "'de: 'a")))]
 #[derive(Debug, PartialEq, Clone)]
 pub enum ByteValue<'a> {
   /// Unprefixed byte string value
@@ -373,7 +421,11 @@ pub enum ByteValue<'a> {
 impl<'a> fmt::Display for ByteValue<'a> {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match self {
-      ByteValue::UTF8(b) => write!(f, "'{}'", std::str::from_utf8(b).map_err(|_| fmt::Error)?),
+      ByteValue::UTF8(b) => write!(
+        f,
+        "{}",
+        ByteStringLiteral(std::str::from_utf8(b).map_err(|_| fmt::Error)?)
+      ),
       ByteValue::B16(b) => write!(
         f,
         "h'{}'",
@@ -393,7 +445,7 @@ impl<'a> fmt::Display for ByteValue<'a> {
 }
 
 /// Socket/plug prefix
-#[cfg_attr(target_arch = "wasm32", derive(Serialize))]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum SocketPlug {
   /// Type socket `$`
@@ -422,7 +474,7 @@ impl std::str::FromStr for SocketPlug {
   }
 }
 
-impl<'a> fmt::Display for SocketPlug {
+impl fmt::Display for SocketPlug {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match self {
       SocketPlug::TYPE => write!(f, "$"),
@@ -457,6 +509,7 @@ impl<'a> fmt::Display for Token<'a> {
       Token::GCHOICEALT => write!(f, "//="),
       Token::COMMA => write!(f, ","),
       Token::COMMENT(c) => write!(f, ";{}", c),
+      Token::WHITESPACE(ws) => write!(f, "{}", ws),
       Token::COLON => write!(f, ":"),
       Token::CUT => write!(f, "^"),
       Token::EOF => write!(f, ""),
@@ -468,10 +521,12 @@ impl<'a> fmt::Display for Token<'a> {
       Token::ARROWMAP => write!(f, "=>"),
       Token::SIZE => write!(f, ".size"),
       Token::BITS => write!(f, ".bits"),
-      Token::REGEXP => write!(f, ".regexp"),
+      Token::CREGEXP => write!(f, ".regexp"),
+      Token::REGEXP => write!(f, "regexp"),
       Token::PCRE => write!(f, ".pcre"),
       Token::CBOR => write!(f, ".cbor"),
       Token::CBORSEQ => write!(f, ".cborseq"),
+      Token::JSON => write!(f, ".json"),
       Token::WITHIN => write!(f, ".within"),
       Token::AND => write!(f, ".and"),
       Token::LT => write!(f, ".lt"),
@@ -481,6 +536,7 @@ impl<'a> fmt::Display for Token<'a> {
       Token::EQ => write!(f, ".eq"),
       Token::NE => write!(f, ".ne"),
       Token::DEFAULT => write!(f, ".default"),
+      Token::CAPTURE => write!(f, ".capture"),
       Token::NUMBER => write!(f, "number"),
       Token::BSTR => write!(f, "bstr"),
       Token::BYTES => write!(f, "bytes"),
@@ -541,6 +597,7 @@ pub fn lookup_control_from_str<'a>(ident: &str) -> Option<Token<'a>> {
     ".regexp" => Some(Token::CREGEXP),
     ".cbor" => Some(Token::CBOR),
     ".cborseq" => Some(Token::CBORSEQ),
+    ".json" => Some(Token::JSON),
     ".within" => Some(Token::WITHIN),
     ".and" => Some(Token::AND),
     ".lt" => Some(Token::LT),
@@ -550,6 +607,7 @@ pub fn lookup_control_from_str<'a>(ident: &str) -> Option<Token<'a>> {
     ".eq" => Some(Token::EQ),
     ".ne" => Some(Token::NE),
     ".default" => Some(Token::DEFAULT),
+    ".capture" => Some(Token::CAPTURE),
     ".pcre" => Some(Token::PCRE),
     _ => None,
   }
@@ -573,9 +631,10 @@ pub fn control_str_from_token(t: &Token) -> Option<&'static str> {
   match t {
     Token::SIZE => Some(".size"),
     Token::BITS => Some(".bits"),
-    Token::REGEXP => Some(".regexp"),
+    Token::CREGEXP => Some(".regexp"),
     Token::CBOR => Some(".cbor"),
     Token::CBORSEQ => Some(".cborseq"),
+    Token::JSON => Some(".json"),
     Token::WITHIN => Some(".within"),
     Token::AND => Some(".and"),
     Token::LT => Some(".lt"),
@@ -585,6 +644,7 @@ pub fn control_str_from_token(t: &Token) -> Option<&'static str> {
     Token::EQ => Some(".eq"),
     Token::NE => Some(".ne"),
     Token::DEFAULT => Some(".default"),
+    Token::CAPTURE => Some(".capture"),
     Token::PCRE => Some(".pcre"),
     _ => None,
   }
@@ -603,7 +663,7 @@ pub fn control_str_from_token(t: &Token) -> Option<&'static str> {
 ///
 /// assert_eq!(lookup_ident("false"), Token::FALSE);
 /// ```
-pub fn lookup_ident(ident: &str) -> Token {
+pub fn lookup_ident(ident: &str) -> Token<'_> {
   match ident {
     "false" => Token::FALSE,
     "true" => Token::TRUE,
@@ -629,6 +689,7 @@ pub fn lookup_ident(ident: &str) -> Token {
     "number" => Token::NUMBER,
     "biguint" => Token::BIGUINT,
     "bignint" => Token::BIGNINT,
+    "bigint" => Token::BIGINT,
     "integer" => Token::INTEGER,
     "unsigned" => Token::UNSIGNED,
     "decfrac" => Token::DECFRAC,
@@ -0,0 +1,455 @@
+use super::{
+  at, is_collecting, is_deferring, occurrence_bounds, tentatively, ActualValue, Error, Result,
+  ValidationError, Validator,
+};
+use crate::{ast::*, normalize::substitute_generic_args};
+
+impl<'a> CDDL<'a> {
+  // Validates the elements of an array against the entries of a group choice
+  // in order. Failures are reported for each element if every entry matches
+  // exactly one element and there are as many elements as entries, once
+  // references to group rules are expanded into their entries, and for the
+  // array as a whole otherwise.
+  pub(super) fn validate_array_elements<T>(
+    &self,
+    gc: &GroupChoice,
+    occur: Option<&Occur>,
+    values: &[T],
+    value: &T,
+  ) -> Result
+  where
+    CDDL<'a>: Validator<T>,
+    T: Clone + Into<ActualValue>,
+  {
+    if self.matches_elements(gc, values) {
+      return Ok(());
+    }
+
+    // [ basic-header, extra ] with basic-header = (field1: int, field2: tstr)
+    // has an entry for each element
+    let mut entries = Vec::new();
+    if gc
+      .group_entries
+      .iter()
+      .all(|(ge, _)| self.expand_group_entry(ge, values.len(), &mut entries))
+      && entries.len() == values.len()
+    {
+      let mut errors = Vec::new();
+
+      for (idx, (ge, v)) in entries.into_iter().zip(values).enumerate() {
+        if let Err(e) = at(idx, || self.validate_group_entry(ge, false, None, occur, v)) {
+          if !is_collecting() {
+            return Err(e);
+          }
+
+          errors.push(e);
+        }
+      }
+
+      match errors.len() {
+        0 => (),
+        1 => return Err(errors.remove(0)),
+        _ => return Err(Error::MultiError(errors)),
+      }
+    }
+
+    // [* int, * tstr] doesn't validate [1, "a", 2], whose last element is only
+    // left for * tstr to consume
+    if !is_deferring() {
+      if let Some((idx, expected)) = self.unmatched_element(gc, values) {
+        let expected = expected
+          .iter()
+          .map(|ge| ge.to_string())
+          .collect::<Vec<_>>()
+          .join(", ");
+
+        return at(idx, || {
+          Err(ValidationError::new(None, &expected, None, &values[idx]).into())
+        });
+      }
+    }
+
+    Err(ValidationError::new(None, gc, None, value).into())
+  }
+
+  // Returns the position of the first element that no division of the
+  // elements of an array into consecutive segments, each consumed by an entry
+  // of a group choice in turn, gets past, along with the entries that fail to
+  // consume it. None is returned if the elements run out first, e.g. for
+  // [+ int, + tstr] and [1], or if every entry is done by then.
+  fn unmatched_element<'b, T>(
+    &self,
+    gc: &'b GroupChoice<'b>,
+    values: &[T],
+  ) -> Option<(usize, Vec<&'b GroupEntry<'b>>)>
+  where
+    CDDL<'a>: Validator<T>,
+  {
+    let mut attempts = Vec::new();
+    let mut positions = vec![0];
+
+    for (ge, _) in gc.group_entries.iter() {
+      let mut attempted = Vec::new();
+      let mut next = Vec::new();
+      for position in positions {
+        for end in self.match_occurrences(ge, values, position, &mut attempted) {
+          if !next.contains(&end) {
+            next.push(end);
+          }
+        }
+      }
+
+      attempts.push((ge, attempted));
+      positions = next;
+    }
+
+    let furthest = attempts
+      .iter()
+      .flat_map(|(_, attempted)| attempted.iter())
+      .chain(positions.iter())
+      .copied()
+      .max()?;
+    if furthest >= values.len() {
+      return None;
+    }
+
+    let expected = attempts
+      .into_iter()
+      .filter(|(_, attempted)| attempted.contains(&furthest))
+      .map(|(ge, _)| ge)
+      .collect::<Vec<_>>();
+    if expected.is_empty() {
+      return None;
+    }
+
+    Some((furthest, expected))
+  }
+
+  // Returns whether the elements of an array match the entries of a group
+  // choice in order. Each entry consumes as many elements as its occurrence
+  // allows, with inline groups and group rules consuming the elements matched
+  // by one of their group choices, so that e.g. [ (int, int) // (tstr) ]
+  // matches either two integers or a single text string.
+  fn matches_elements<T>(&self, gc: &GroupChoice, values: &[T]) -> bool
+  where
+    CDDL<'a>: Validator<T>,
+  {
+    self
+      .match_group_entries(&gc.group_entries, values, 0)
+      .contains(&values.len())
+  }
+
+  // Returns every position at which matching the given entries against the
+  // elements from the given position onwards can end
+  fn match_group_entries<T>(
+    &self,
+    entries: &[(GroupEntry, bool)],
+    values: &[T],
+    start: usize,
+  ) -> Vec<usize>
+  where
+    CDDL<'a>: Validator<T>,
+  {
+    let mut positions = vec![start];
+
+    for (ge, _) in entries.iter() {
+      let mut next = Vec::new();
+      for position in positions {
+        for end in self.match_group_entry(ge, values, position) {
+          if !next.contains(&end) {
+            next.push(end);
+          }
+        }
+      }
+
+      if next.is_empty() {
+        return next;
+      }
+
+      positions = next;
+    }
+
+    positions
+  }
+
+  // Returns every position at which matching the given entry, as many times as
+  // its occurrence allows, can end
+  fn match_group_entry<T>(&self, ge: &GroupEntry, values: &[T], start: usize) -> Vec<usize>
+  where
+    CDDL<'a>: Validator<T>,
+  {
+    self.match_occurrences(ge, values, start, &mut Vec::new())
+  }
+
+  // Returns every position at which matching the given entry, as many times as
+  // its occurrence allows, can end, recording each position at which an
+  // occurrence of it is matched against the elements
+  fn match_occurrences<T>(
+    &self,
+    ge: &GroupEntry,
+    values: &[T],
+    start: usize,
+    attempted: &mut Vec<usize>,
+  ) -> Vec<usize>
+  where
+    CDDL<'a>: Validator<T>,
+  {
+    let occur = match ge {
+      GroupEntry::ValueMemberKey { ge, .. } => ge.occur.as_ref(),
+      GroupEntry::TypeGroupname { ge, .. } => ge.occur.as_ref(),
+      GroupEntry::InlineGroup { occur, .. } => occur.as_ref(),
+    };
+
+    let (lower, upper) = occurrence_bounds(occur);
+
+    let mut ends = if lower == 0 { vec![start] } else { Vec::new() };
+    let mut frontier = vec![start];
+    let mut repetitions = 0;
+
+    while !frontier.is_empty() && upper.map(|upper| repetitions < upper).unwrap_or(true) {
+      repetitions += 1;
+
+      let mut next = Vec::new();
+      for position in frontier {
+        if !attempted.contains(&position) {
+          attempted.push(position);
+        }
+
+        for end in self.match_group_entry_once(ge, values, position) {
+          if end == position {
+            // An occurrence that consumes no elements, e.g. of a group of
+            // optional entries, can be repeated until the lower bound is met
+            if !ends.contains(&end) {
+              ends.push(end);
+            }
+          } else if !next.contains(&end) {
+            // Until the lower bound is met, positions reached by fewer
+            // repetitions are revisited. Each repetition consumes elements, so
+            // this takes no more rounds than there are elements.
+            if repetitions >= lower {
+              if ends.contains(&end) {
+                continue;
+              }
+
+              ends.push(end);
+            }
+
+            next.push(end);
+          }
+        }
+      }
+
+      frontier = next;
+    }
+
+    ends
+  }
+
+  // Returns every position at which matching a single occurrence of the given
+  // entry can end
+  fn match_group_entry_once<T>(&self, ge: &GroupEntry, values: &[T], start: usize) -> Vec<usize>
+  where
+    CDDL<'a>: Validator<T>,
+  {
+    match ge {
+      // [ a: int, b: tstr ] validates [ 1, "b" ], ignoring the member keys
+      GroupEntry::ValueMemberKey { ge, .. } => match values.get(start) {
+        Some(v)
+          if tentatively(|| {
+            at(start, || {
+              self.validate_type(&ge.entry_type, None, None, None, v)
+            })
+          })
+          .is_ok() =>
+        {
+          vec![start + 1]
+        }
+        _ => Vec::new(),
+      },
+      GroupEntry::TypeGroupname {
+        ge:
+          TypeGroupnameEntry {
+            name,
+            generic_arg: Some(ga),
+            ..
+          },
+        ..
+      } if self.is_group_rule(name) => self.match_generic_group_name(name, ga, values, start),
+      GroupEntry::TypeGroupname { ge, .. } => {
+        let group_rules = self
+          .rules_for(&ge.name)
+          .filter_map(|r| match r {
+            Rule::Group { rule, .. } => Some(rule),
+            _ => None,
+          })
+          .collect::<Vec<_>>();
+
+        if group_rules.is_empty() {
+          return match values.get(start) {
+            Some(v) if tentatively(|| at(start, || self.validate_group_name(ge, v))).is_ok() => {
+              vec![start + 1]
+            }
+            _ => Vec::new(),
+          };
+        }
+
+        // Group rules are followed like any other rule reference, so that
+        // recursive groups are bounded by the maximum validation depth
+        let _guard = match self.enter_rule(&ge.name) {
+          Ok(guard) => guard,
+          Err(_) => return Vec::new(),
+        };
+
+        let mut ends = Vec::new();
+        for rule in group_rules {
+          for end in self.match_group_entry(&rule.entry, values, start) {
+            if !ends.contains(&end) {
+              ends.push(end);
+            }
+          }
+        }
+
+        ends
+      }
+      GroupEntry::InlineGroup { group, .. } => {
+        let mut ends = Vec::new();
+        for gc in group.group_choices.iter() {
+          for end in self.match_group_entries(&gc.group_entries, values, start) {
+            if !ends.contains(&end) {
+              ends.push(end);
+            }
+          }
+        }
+
+        ends
+      }
+    }
+  }
+
+  // Returns every position at which matching a single occurrence of a
+  // reference to a generic group rule can end, e.g. pair<uint> with
+  // pair<t> = (t, t)
+  fn match_generic_group_name<T>(
+    &self,
+    name: &Identifier,
+    generic_arg: &GenericArg,
+    values: &[T],
+    start: usize,
+  ) -> Vec<usize>
+  where
+    CDDL<'a>: Validator<T>,
+  {
+    let _guard = match self.enter_rule(name) {
+      Ok(guard) => guard,
+      Err(_) => return Vec::new(),
+    };
+
+    let mut ends = Vec::new();
+    for rule in self.rules_for(name) {
+      let entry = match substitute_generic_args(rule, generic_arg) {
+        Some(Rule::Group { rule, .. }) => rule.entry,
+        _ => return Vec::new(),
+      };
+
+      for end in self.match_group_entry(&entry, values, start) {
+        if !ends.contains(&end) {
+          ends.push(end);
+        }
+      }
+    }
+
+    ends
+  }
+
+  // Returns whether the given name refers to a group rule, whose entries span
+  // as many elements of an array as they match
+  pub(super) fn is_group_rule(&self, ident: &Identifier) -> bool {
+    self.rules_for(ident).any(|r| match r {
+      Rule::Group { .. } => true,
+      _ => false,
+    })
+  }
+
+  // Appends the entries matching a single element each that the given entry
+  // consists of, expanding references to group rules and inline groups,
+  // unless it has any others or there would be more than the given number
+  fn expand_group_entry<'b>(
+    &'b self,
+    ge: &'b GroupEntry<'a>,
+    max: usize,
+    entries: &mut Vec<&'b GroupEntry<'a>>,
+  ) -> bool {
+    match ge {
+      GroupEntry::TypeGroupname {
+        ge:
+          TypeGroupnameEntry {
+            occur: None,
+            name,
+            generic_arg: None,
+          },
+        ..
+      } if self.is_group_rule(name) => {
+        match self.rules_for(name).collect::<Vec<_>>().as_slice() {
+          [Rule::Group { rule, .. }] if rule.generic_param.is_none() => {
+            // Bounds recursive groups that match no elements, e.g. a = (a)
+            let _guard = match self.enter_rule(name) {
+              Ok(guard) => guard,
+              Err(_) => return false,
+            };
+
+            self.expand_group_entry(&rule.entry, max, entries)
+          }
+          _ => false,
+        }
+      }
+      GroupEntry::InlineGroup {
+        occur: None, group, ..
+      } => match group.group_choices.as_slice() {
+        [gc] => gc
+          .group_entries
+          .iter()
+          .all(|(ge, _)| self.expand_group_entry(ge, max, entries)),
+        _ => false,
+      },
+      _ if self.is_single_element_entry(ge) && entries.len() < max => {
+        entries.push(ge);
+        true
+      }
+      _ => false,
+    }
+  }
+
+  // Returns whether the given entry matches exactly one element
+  fn is_single_element_entry(&self, ge: &GroupEntry) -> bool {
+    match ge {
+      GroupEntry::ValueMemberKey { ge, .. } => ge.occur.is_none(),
+      GroupEntry::TypeGroupname { ge, .. } => ge.occur.is_none() && !self.is_group_rule(&ge.name),
+      GroupEntry::InlineGroup { .. } => false,
+    }
+  }
+
+  // Validates an element against the type named by the given entry
+  pub(super) fn validate_group_name<T>(&self, ge: &TypeGroupnameEntry, value: &T) -> Result
+  where
+    CDDL<'a>: Validator<T>,
+  {
+    if self.is_builtin_type(&ge.name) {
+      return self.validate_type2(
+        &Type2::Typename {
+          ident: ge.name.clone(),
+          generic_arg: ge.generic_arg.clone(),
+          span: (0, 0, 0),
+        },
+        None,
+        None,
+        None,
+        value,
+      );
+    }
+
+    match &ge.generic_arg {
+      Some(ga) => self.validate_generic_rule(&ge.name, ga, false, None, None, None, value),
+      None => self.validate_rule_for_ident(&ge.name, false, None, None, None, value),
+    }
+  }
+}
@@ -1,77 +1,78 @@
+/// Validation of BSON documents as CBOR
+#[cfg(feature = "bson")]
+pub mod bson;
+/// Validation of MessagePack data items as CBOR
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+#[cfg(feature = "tokio")]
+mod stream;
+
 use crate::{
   ast::*,
-  lexer, parser,
-  validation::{CompilationError, Error, Result, Validator},
+  cbor_core::{
+    self, decode_b16, decode_b64, decode_deterministic, decode_seq, duplicate_keys,
+    first_unsorted_key, remove_duplicate_keys, EncodingViolationKind,
+  },
+  edn, lexer, parser,
+  prelude::{is_extension_type, is_prelude_type},
+  token,
+  validation::{
+    at, close_step, dedup_errors,
+    engine::{is_checked_control, unsupported_control},
+    group_choice, is_collecting, is_f16_representable, is_f32_representable,
+    is_float_literal_match, is_numeric_data_type, is_unwrap, most_plausible, occurrence_bounds,
+    open_step, pointer_index, time, transiently, validate_elements, validate_occurrence_count,
+    value::{DataKind, DataValue},
+    CompilationError, Error, Reason, Result, ValidationError, Validator,
+  },
 };
 use serde_cbor::{self, Value};
-use std::{borrow::Cow, f64, fmt};
-
-/// Error type when validating CDDL
-#[derive(Debug)]
-pub struct CBORError {
-  expected_memberkey: Option<String>,
-  expected_value: String,
-  actual_memberkey: Option<String>,
-  actual_value: Value,
-}
-
-impl std::error::Error for CBORError {
-  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-    None
-  }
-}
-
-impl fmt::Display for CBORError {
-  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    let actual_value = serde_json::to_string_pretty(&self.actual_value).map_err(|_| fmt::Error)?;
-
-    if let Some(emk) = &self.expected_memberkey {
-      if let Some(amk) = &self.actual_memberkey {
-        return write!(
-          f,
-          "expected: ( {} {} )\nactual: \"{}\": {}",
-          emk, self.expected_value, amk, actual_value
-        );
-      }
-
-      return write!(
-        f,
-        "expected: ( {} {} )\nactual: {}",
-        emk, self.expected_value, actual_value
-      );
-    }
+use std::{borrow::Cow, collections::BTreeMap, convert::TryFrom, result};
 
-    if let Some(amk) = &self.actual_memberkey {
-      return write!(
-        f,
-        "expected: ( {} )\nactual: {}: {}",
-        self.expected_value, amk, actual_value
-      );
-    }
+#[cfg(feature = "bson")]
+pub use self::bson::validate_bson_from_slice;
+#[cfg(feature = "msgpack")]
+pub use self::msgpack::validate_msgpack_from_slice;
+#[cfg(feature = "tokio")]
+pub use self::stream::CborStream;
 
-    write!(
-      f,
-      "expected: ( {} )\nactual: {}\n",
-      self.expected_value, actual_value,
-    )
-  }
+/// How maps with more than one entry with the same key, which CBOR encoders
+/// may emit, are treated when validating encoded CBOR. Set via
+/// `ValidationOptions::cbor_duplicate_keys`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DuplicateKeyPolicy {
+  /// Each duplicated key fails validation with an `Error::DuplicateKey`
+  Reject,
+  /// The first entry with the key is validated and the others are ignored
+  First,
+  /// The last entry with the key is validated and the others are ignored
+  Last,
 }
 
-impl Into<Error> for CBORError {
-  fn into(self) -> Error {
-    Error::Target(Box::from(self))
+// Deriving Default for enums isn't supported by the minimum supported Rust
+// version
+#[allow(clippy::derivable_impls)]
+impl Default for DuplicateKeyPolicy {
+  fn default() -> Self {
+    DuplicateKeyPolicy::Last
   }
 }
 
+/// Error type when validating CBOR
+#[deprecated(note = "validation failures are reported as `validation::ValidationError`s")]
+pub type CBORError = ValidationError;
+
 impl<'a> Validator<Value> for CDDL<'a> {
   fn validate(&self, value: &Value) -> Result {
-    for r in self.rules.iter() {
-      if let Rule::Type { rule, .. } = r {
-        return self.validate_type_rule(rule, None, None, None, value);
-      }
-    }
+    self.validate_data(value)
+  }
 
-    Ok(())
+  fn validate_with_root(&self, rule_name: &str, value: &Value) -> Result {
+    self.validate_root_rule(self.root_ident(rule_name)?, value)
+  }
+
+  fn validate_value_at(&self, rule_name: &str, pointer: &str, value: &Value) -> Result {
+    self.validate_value_at_pointer(rule_name, pointer, value)
   }
 
   fn validate_rule_for_ident(
@@ -83,22 +84,36 @@ impl<'a> Validator<Value> for CDDL<'a> {
     occur: Option<&Occur>,
     value: &Value,
   ) -> Result {
-    for rule in self.rules.iter() {
-      match rule {
-        Rule::Type { rule, .. } if rule.name.ident == ident.ident => {
-          return self.validate_type_rule(&rule, expected_memberkey, actual_memberkey, occur, value)
-        }
-        Rule::Group { rule, .. } if rule.name.ident == ident.ident => {
-          return self.validate_group_rule(&rule, is_enumeration, occur, value)
-        }
-        _ => continue,
-      }
-    }
+    self.validate_rule_definitions(
+      ident,
+      None,
+      is_enumeration,
+      expected_memberkey,
+      actual_memberkey,
+      occur,
+      value,
+    )
+  }
 
-    Err(Error::Syntax(format!(
-      "No rule with name {} defined\n",
-      ident.ident,
-    )))
+  fn validate_generic_rule(
+    &self,
+    ident: &Identifier,
+    generic_arg: &GenericArg,
+    is_enumeration: bool,
+    expected_memberkey: Option<String>,
+    actual_memberkey: Option<String>,
+    occur: Option<&Occur>,
+    value: &Value,
+  ) -> Result {
+    self.validate_rule_definitions(
+      ident,
+      Some(generic_arg),
+      is_enumeration,
+      expected_memberkey,
+      actual_memberkey,
+      occur,
+      value,
+    )
   }
 
   fn validate_type_rule(
@@ -136,28 +151,7 @@ impl<'a> Validator<Value> for CDDL<'a> {
     occur: Option<&Occur>,
     value: &Value,
   ) -> Result {
-    let mut validation_errors: Vec<Error> = Vec::new();
-
-    // Find the first type choice that validates to true
-    let find_type_choice = |t1| match self.validate_type1(
-      t1,
-      expected_memberkey.clone(),
-      actual_memberkey.clone(),
-      occur,
-      value,
-    ) {
-      Ok(()) => true,
-      Err(e) => {
-        validation_errors.push(e);
-        false
-      }
-    };
-
-    if t.type_choices.iter().any(find_type_choice) {
-      return Ok(());
-    }
-
-    Err(Error::MultiError(validation_errors))
+    self.validate_type_choices(t, expected_memberkey, actual_memberkey, occur, value)
   }
 
   fn validate_type1(
@@ -168,33 +162,49 @@ impl<'a> Validator<Value> for CDDL<'a> {
     occur: Option<&Occur>,
     value: &Value,
   ) -> Result {
-    self.validate_type2(
-      &t1.type2,
-      expected_memberkey,
-      actual_memberkey,
-      occur,
-      value,
-    )
+    // Controls other than those checked here or by the shared engine, such as
+    // .cat, aren't checked yet, so data fails to match them
+    if let Some((RangeCtlOp::CtlOp { ctrl, .. }, controller)) = &t1.operator {
+      if is_encoding_control(ctrl) {
+        self.validate_type2(
+          &t1.type2,
+          expected_memberkey,
+          actual_memberkey,
+          occur,
+          value,
+        )?;
+
+        return self
+          .validate_encoding_control(&t1.type2, ctrl, controller, value)
+          .map_err(|e| e.with_reason(Reason::Control));
+      }
+
+      if !is_checked_control(ctrl) {
+        return Err(unsupported_control(&t1.type2, ctrl, controller, value));
+      }
+    }
+
+    self.validate_type1_operator(t1, expected_memberkey, actual_memberkey, occur, value)
   }
 
   fn validate_range(
     &self,
-    _lower: &Type2,
-    _upper: &Type2,
-    _is_inclusive: bool,
-    _value: &Value,
+    lower: &Type2,
+    upper: &Type2,
+    is_inclusive: bool,
+    value: &Value,
   ) -> Result {
-    unimplemented!()
+    self.validate_numeric_range(lower, upper, is_inclusive, value)
   }
 
   fn validate_control_operator(
     &self,
-    _target: &Type2,
-    _operator: &'static str,
-    _controller: &Type2,
-    _value: &Value,
+    target: &Type2,
+    operator: &'static str,
+    controller: &Type2,
+    value: &Value,
   ) -> Result {
-    unimplemented!()
+    self.validate_control(target, operator, controller, value)
   }
 
   fn validate_type2(
@@ -208,153 +218,121 @@ impl<'a> Validator<Value> for CDDL<'a> {
     match t2 {
       Type2::TextValue { value: t, .. } => match value {
         Value::Text(s) if t == s => Ok(()),
-        _ => Err(
-          CBORError {
-            expected_memberkey,
-            expected_value: t2.to_string(),
-            actual_memberkey,
-            actual_value: value.clone(),
-          }
-          .into(),
-        ),
+        _ => Err(ValidationError::new(expected_memberkey, t2, actual_memberkey, value).into()),
       },
       Type2::IntValue { value: iv, .. } => match value {
-        Value::Integer(i) if *iv as i128 == *i => Ok(()),
-        _ => Err(
-          CBORError {
-            expected_memberkey,
-            expected_value: t2.to_string(),
-            actual_memberkey,
-            actual_value: value.clone(),
-          }
-          .into(),
-        ),
+        Value::Integer(i) if *iv == *i => Ok(()),
+        _ => Err(ValidationError::new(expected_memberkey, t2, actual_memberkey, value).into()),
       },
       Type2::UintValue { value: uiv, .. } => match value {
-        Value::Integer(i) if *uiv as u128 == *i as u128 => Ok(()),
-        _ => Err(
-          CBORError {
-            expected_memberkey,
-            expected_value: t2.to_string(),
-            actual_memberkey,
-            actual_value: value.clone(),
-          }
-          .into(),
-        ),
+        Value::Integer(i) if *i >= 0 && *i as u128 == *uiv => Ok(()),
+        _ => Err(ValidationError::new(expected_memberkey, t2, actual_memberkey, value).into()),
       },
       Type2::FloatValue { value: fv, .. } => match value {
-        Value::Float(f) if (fv - f).abs() < f64::EPSILON => Ok(()),
-        _ => Err(
-          CBORError {
-            expected_memberkey,
-            expected_value: t2.to_string(),
-            actual_memberkey,
-            actual_value: value.clone(),
-          }
-          .into(),
-        ),
-      },
-      Type2::B16ByteString {
-        value: Cow::Borrowed(bs),
-        ..
-      } => match value {
-        Value::Bytes(b) if b == bs => Ok(()),
-        _ => Err(
-          CBORError {
-            expected_memberkey,
-            expected_value: t2.to_string(),
-            actual_memberkey,
-            actual_value: value.clone(),
-          }
-          .into(),
-        ),
+        Value::Float(f)
+          if is_float_literal_match(self.validation_options.float_comparison, *fv, *f) =>
+        {
+          Ok(())
+        }
+        _ => Err(ValidationError::new(expected_memberkey, t2, actual_memberkey, value).into()),
       },
-      Type2::B16ByteString {
-        value: Cow::Owned(bs),
-        ..
-      } => match value {
-        Value::Bytes(b) if b == bs => Ok(()),
-        _ => Err(
-          CBORError {
-            expected_memberkey,
-            expected_value: t2.to_string(),
-            actual_memberkey,
-            actual_value: value.clone(),
-          }
-          .into(),
-        ),
+      // Byte string literals in base16 or base64 are compared once decoded
+      Type2::B16ByteString { value: bs, .. } => match value {
+        Value::Bytes(b) if decode_b16(bs).as_ref() == Some(b) => Ok(()),
+        _ => Err(ValidationError::new(expected_memberkey, t2, actual_memberkey, value).into()),
       },
-      Type2::B64ByteString {
-        value: Cow::Borrowed(bs),
-        ..
-      } => match value {
-        Value::Bytes(b) if b == bs => Ok(()),
-        _ => Err(
-          CBORError {
-            expected_memberkey,
-            expected_value: t2.to_string(),
-            actual_memberkey,
-            actual_value: value.clone(),
-          }
-          .into(),
-        ),
+      Type2::B64ByteString { value: bs, .. } => match value {
+        Value::Bytes(b) if decode_b64(bs).as_ref() == Some(b) => Ok(()),
+        _ => Err(ValidationError::new(expected_memberkey, t2, actual_memberkey, value).into()),
       },
-      Type2::B64ByteString {
-        value: Cow::Owned(bs),
+      // any matches every data item, including null and booleans, which
+      // aren't resolved via the prelude below
+      Type2::Typename { ident, .. } if ident.ident == "any" => Ok(()),
+      // References to generic rules, e.g. pair<int>
+      Type2::Typename {
+        ident,
+        generic_arg: Some(ga),
         ..
-      } => match value {
-        Value::Bytes(b) if b == bs => Ok(()),
-        _ => Err(
-          CBORError {
+      } => self.validate_generic_rule(
+        ident,
+        ga,
+        false,
+        expected_memberkey,
+        actual_memberkey,
+        occur,
+        value,
+      ),
+      Type2::Typename { ident, .. } => match value {
+        Value::Null | Value::Bool(_) | Value::Tag(SIMPLE_VALUE_TAG, _) => {
+          self.validate_simple_value(ident, expected_memberkey, actual_memberkey, occur, value)
+        }
+        // Text of the types the standard prelude defines as tagged, e.g. tdate,
+        // is left to their rules, which require the tag
+        Value::Text(s) if prelude_tag(ident.ident).is_none() => match self.validate_prelude_text(
+          ident.ident,
+          s,
+          expected_memberkey.clone(),
+          actual_memberkey.clone(),
+          value,
+        ) {
+          Some(result) => result,
+          None => self.validate_rule_for_ident(
+            ident,
+            false,
             expected_memberkey,
-            expected_value: t2.to_string(),
             actual_memberkey,
-            actual_value: value.clone(),
-          }
-          .into(),
-        ),
-      },
-      // TODO: evaluate genericarg
-      Type2::Typename { ident, .. } => match value {
-        Value::Null => expect_null(&ident.ident),
-        Value::Bool(_) => self.expect_bool(&ident.ident, value),
-        Value::Text(_) => {
-          if ident.ident == "tstr" || ident.ident == "text" {
-            Ok(())
-          } else if is_type_prelude(&ident.ident) {
-            // Expecting non-text type but got text
-            Err(
-              CBORError {
-                expected_memberkey,
-                expected_value: ident.ident.to_string(),
-                actual_memberkey,
-                actual_value: value.clone(),
-              }
-              .into(),
-            )
-          } else {
-            self.validate_rule_for_ident(
+            occur,
+            value,
+          ),
+        },
+        // The content of data items tagged as the standard prelude defines the
+        // type is checked against the format the tag stands for
+        Value::Tag(tag, tagged) if prelude_tag(ident.ident) == Some(*tag) => match &**tagged {
+          Value::Text(s) => match self.validate_prelude_text(
+            ident.ident,
+            s,
+            expected_memberkey.clone(),
+            actual_memberkey.clone(),
+            value,
+          ) {
+            Some(result) => result,
+            None => self.validate_rule_for_ident(
               ident,
               false,
               expected_memberkey,
               actual_memberkey,
               occur,
               value,
+            ),
+          },
+          Value::Float(f) if ident.ident == "time" && time::check_time(*f).is_err() => Err(
+            ValidationError::new(expected_memberkey, ident.ident, actual_memberkey, value).into(),
+          ),
+          _ => self.validate_rule_for_ident(
+            ident,
+            false,
+            expected_memberkey,
+            actual_memberkey,
+            occur,
+            value,
+          ),
+        },
+        Value::Tag(DURATION_TAG, tagged)
+          if is_extension_type(ident.ident) && self.rules_for(ident).next().is_none() =>
+        {
+          if is_duration_map(tagged) {
+            Ok(())
+          } else {
+            Err(
+              ValidationError::new(expected_memberkey, ident.ident, actual_memberkey, value).into(),
             )
           }
         }
-        Value::Integer(_) | Value::Float(_) => {
-          self.validate_numeric_data_type(expected_memberkey, actual_memberkey, &ident.ident, value)
+        Value::Integer(_) | Value::Float(_) if is_numeric_data_type(ident.ident) => {
+          self.validate_numeric_data_type(expected_memberkey, actual_memberkey, ident.ident, value)
         }
-        Value::Map(_) => self.validate_rule_for_ident(
-          ident,
-          false,
-          expected_memberkey,
-          actual_memberkey,
-          occur,
-          value,
-        ),
-        Value::Array(_) => self.validate_rule_for_ident(
+        Value::Bytes(_) if ident.ident == "bstr" || ident.ident == "bytes" => Ok(()),
+        _ => self.validate_rule_for_ident(
           ident,
           false,
           expected_memberkey,
@@ -362,60 +340,35 @@ impl<'a> Validator<Value> for CDDL<'a> {
           occur,
           value,
         ),
-        Value::Bytes(_) => {
-          if ident.ident == "bstr" || ident.ident == "bytes" {
-            Ok(())
-          } else if is_type_prelude(&ident.ident) {
-            // Expecting non-bytes type but got bytes
-            Err(
-              CBORError {
-                expected_memberkey,
-                expected_value: ident.ident.to_string(),
-                actual_memberkey,
-                actual_value: value.clone(),
-              }
-              .into(),
-            )
-          } else {
-            self.validate_rule_for_ident(
-              ident,
-              false,
-              expected_memberkey,
-              actual_memberkey,
-              occur,
-              value,
-            )
-          }
-        }
-        _ => unimplemented!(),
       },
       Type2::Array { group, .. } => match value {
-        Value::Array(_) => self.validate_group(group, occur, value),
-        _ => Err(
-          CBORError {
-            expected_memberkey,
-            expected_value: t2.to_string(),
-            actual_memberkey,
-            actual_value: value.clone(),
-          }
-          .into(),
-        ),
+        Value::Array(values) => {
+          self.check_array_length(values.len())?;
+
+          self.validate_group(group, occur, value)
+        }
+        _ => Err(ValidationError::new(expected_memberkey, t2, actual_memberkey, value).into()),
       },
       Type2::Map { group, .. } => match value {
-        Value::Map(_) => self.validate_group(group, occur, value),
-        _ => Err(
-          CBORError {
-            expected_memberkey,
-            expected_value: t2.to_string(),
-            actual_memberkey,
-            actual_value: value.clone(),
-          }
-          .into(),
-        ),
+        Value::Map(om) => self.validate_map(group, occur, om, value),
+        _ => Err(ValidationError::new(expected_memberkey, t2, actual_memberkey, value).into()),
       },
       Type2::ChoiceFromInlineGroup { group, .. } => {
         self.validate_group_to_choice_enum(group, occur, value)
       }
+      Type2::ChoiceFromGroup {
+        ident,
+        generic_arg: Some(ga),
+        ..
+      } => self.validate_generic_rule(
+        ident,
+        ga,
+        true,
+        expected_memberkey,
+        actual_memberkey,
+        occur,
+        value,
+      ),
       Type2::ChoiceFromGroup { ident, .. } => self.validate_rule_for_ident(
         ident,
         true,
@@ -424,41 +377,52 @@ impl<'a> Validator<Value> for CDDL<'a> {
         occur,
         value,
       ),
-      _ => Err(Error::Syntax(format!(
-        "CDDL type {} can't be used to validate CBOR {:?}",
-        t2, value
-      ))),
-    }
-  }
+      Type2::TaggedDataMajorType { mt, constraint, .. } => {
+        if is_major_type(*mt, *constraint, value) {
+          return Ok(());
+        }
 
-  fn validate_group(&self, g: &Group, occur: Option<&Occur>, value: &Value) -> Result {
-    let mut validation_errors: Vec<Error> = Vec::new();
+        Err(ValidationError::new(expected_memberkey, t2, actual_memberkey, value).into())
+      }
+      Type2::TaggedData { tag, t, .. } => match value {
+        Value::Tag(actual_tag, tagged_value) => {
+          if let Some(tag) = tag {
+            if *tag as u64 != *actual_tag {
+              return Err(
+                ValidationError::new(expected_memberkey, t2, actual_memberkey, value).into(),
+              );
+            }
+          }
 
-    // Find the first group choice that validates to true
-    if g
-      .group_choices
-      .iter()
-      .any(|gc| match self.validate_group_choice(gc, occur, value) {
-        Ok(()) => true,
-        Err(e) => {
-          validation_errors.push(e);
-          false
+          self.validate_type(
+            t,
+            expected_memberkey,
+            actual_memberkey,
+            occur,
+            &**tagged_value,
+          )
         }
-      })
-    {
-      return Ok(());
+        _ => Err(ValidationError::new(expected_memberkey, t2, actual_memberkey, value).into()),
+      },
+      Type2::ParenthesizedType { pt, .. } => {
+        self.validate_type(pt, expected_memberkey, actual_memberkey, occur, value)
+      }
+      Type2::Any(_) => Ok(()),
+      _ => Err(ValidationError::new(expected_memberkey, t2, actual_memberkey, value).into()),
     }
+  }
 
-    Err(Error::MultiError(validation_errors))
+  fn validate_group(&self, g: &Group, occur: Option<&Occur>, value: &Value) -> Result {
+    self.validate_group_choices(g, occur, value)
   }
 
   fn validate_group_to_choice_enum(
     &self,
-    _g: &Group,
+    g: &Group,
     _occur: Option<&Occur>,
-    _value: &Value,
+    value: &Value,
   ) -> Result {
-    unimplemented!()
+    self.validate_choice_from_group(g, value)
   }
 
   fn validate_group_choice(
@@ -467,105 +431,59 @@ impl<'a> Validator<Value> for CDDL<'a> {
     occur: Option<&Occur>,
     value: &Value,
   ) -> Result {
-    let mut errors: Vec<Error> = Vec::new();
-
-    for (ge_index, ge) in gc.group_entries.iter().enumerate() {
-      match value {
-        Value::Array(values) => {
-          if let GroupEntry::TypeGroupname { ge: tge, .. } = &ge.0 {
-            if let Some(o) = &tge.occur {
-              if gc.group_entries.len() != 1 {
-                // Arrays with multiple occurrences are too hard to parse
-                // correctly.  For now, just return an error instead.
-                return Err(
-                  CBORError {
-                    expected_memberkey: None,
-                    expected_value: gc.to_string(),
-                    actual_memberkey: None,
-                    actual_value: value.clone(),
-                  }
-                  .into(),
-                );
-              }
+    if let Value::Array(values) = value {
+      // [ * reputon ]
+      if let [(GroupEntry::TypeGroupname { ge: tge, .. }, _)] = gc.group_entries.as_slice() {
+        if let Some(o) = &tge.occur {
+          if !self.is_group_rule(&tge.name) {
+            // Validate that the number of values is correct according to the
+            // occurrence.
+            self.validate_array_occurrence(o, &tge.name.to_string(), values)?;
 
-              // Validate that the number of values is correct according to the
-              // occurrence.
-              self.validate_array_occurrence(o, &tge.name.to_string(), values)?;
-
-              // Validate that each member of the value array matches
-              // the groupentry.
-              for value in values {
-                self.validate_group_entry(&ge.0, false, None, occur, value)?;
-              }
-              return Ok(());
-            }
+            // Validate that each member of the value array matches
+            // the groupentry.
+            return validate_elements(values, |v| {
+              self.validate_group_entry(&gc.group_entries[0].0, false, None, occur, v)
+            });
           }
+        }
+      }
 
-          if let GroupEntry::InlineGroup {
-            occur: geo,
-            group: g,
-            ..
-          } = &ge.0
-          {
-            if let Some(o) = geo {
-              self.validate_array_occurrence(&o, &g.to_string(), values)?;
-            }
-          }
+      // [ a: int, b: tstr ] and [ (int, int) // (tstr) ]
+      return self.validate_array_elements(gc, occur, values, value);
+    }
 
-          let validate_all_entries =
-            |v: &Value| match self.validate_group_entry(&ge.0, false, None, occur, v) {
-              Ok(()) => true,
-              Err(e) => {
-                errors.push(e);
+    let mut errors: Vec<Error> = Vec::new();
+    let literal_keys = match value {
+      Value::Map(_) => literal_keys(gc),
+      _ => Vec::new(),
+    };
 
-                false
+    for ge in gc.group_entries.iter() {
+      match value {
+        Value::Map(om) => {
+          // Entries whose keys are matched by a type, e.g. * int => any, only
+          // apply to the keys that no entry with a literal key matches
+          let result = match &ge.0 {
+            GroupEntry::ValueMemberKey { ge: vmke, .. } => match &vmke.member_key {
+              Some(mk @ MemberKey::Type1 { t1, .. }) if literal_key(mk).is_none() => {
+                self.validate_entries_with_key_type(vmke, mk, t1, occur, om, &literal_keys, value)
               }
-            };
-
-          if let GroupEntry::TypeGroupname { ge: tge, .. } = &ge.0 {
-            if self.rules.iter().any(|r| match r {
-              Rule::Type { rule, .. } if rule.name.ident == tge.name.ident => true,
-              _ => false,
-            }) && values.iter().all(validate_all_entries)
-            {
-              return Ok(());
-            }
-          }
+              // CDDL { x: int, ? [int] } validates CBOR { "x": 1 }
+              None if !is_unwrap(&vmke.entry_type) => self.validate_keyless_entry(vmke, value),
+              _ => self.validate_group_entry(&ge.0, false, None, occur, value),
+            },
+            _ => self.validate_group_entry(&ge.0, false, None, occur, value),
+          };
 
-          // Match array element 1-on-1
-          // first verify that the array lengths match.
-          if values.len() != gc.group_entries.len() {
-            return Err(
-              CBORError {
-                expected_memberkey: None,
-                expected_value: gc.to_string(),
-                actual_memberkey: None,
-                actual_value: value.clone(),
-              }
-              .into(),
-            );
-          }
-          let value_at_index = values.get(ge_index).unwrap();
-          self.validate_group_entry(&ge.0, false, None, occur, value_at_index)?;
-        }
-        Value::Map(_) => {
           // Validate the object key/value pairs against each group entry,
           // collecting errors along the way
-          match self.validate_group_entry(&ge.0, false, None, occur, value) {
-            Ok(()) => continue,
-            Err(e) => errors.push(e),
+          if let Err(e) = result {
+            errors.push(e);
           }
         }
         _ => {
-          return Err(
-            CBORError {
-              expected_memberkey: None,
-              expected_value: gc.to_string(),
-              actual_memberkey: None,
-              actual_value: value.clone(),
-            }
-            .into(),
-          );
+          return Err(ValidationError::new(None, gc, None, value).into());
         }
       }
     }
@@ -593,15 +511,21 @@ impl<'a> Validator<Value> for CDDL<'a> {
               Type2::TextValue { value: t, .. } => match value {
                 // CDDL { "my-key" => tstr, } validates JSON { "my-key": "myvalue" }
                 Value::Map(om) => {
-                  if !is_type_prelude(&vmke.entry_type.to_string()) {
+                  if !is_prelude_type(&vmke.entry_type.to_string()) {
                     if let Some(v) = om.get(&Value::Text((*t).to_string())) {
-                      return self.validate_type(
-                        &vmke.entry_type,
-                        Some(mk.to_string()),
-                        Some((*t).to_string()),
-                        occur,
-                        v,
-                      );
+                      return at(&**t, || {
+                        self.validate_type(
+                          &vmke.entry_type,
+                          Some(mk.to_string()),
+                          Some((*t).to_string()),
+                          occur,
+                          v,
+                        )
+                      });
+                    }
+
+                    if occurrence_bounds(vmke.occur.as_ref()).0 == 0 {
+                      return Ok(());
                     }
 
                     return self.validate_type(
@@ -614,23 +538,17 @@ impl<'a> Validator<Value> for CDDL<'a> {
                   }
 
                   if let Some(v) = om.get(&Value::Text((*t).to_string())) {
-                    self.validate_type(
-                      &vmke.entry_type,
-                      Some(mk.to_string()),
-                      Some((*t).to_string()),
-                      occur,
-                      v,
-                    )
+                    at(&**t, || {
+                      self.validate_type(
+                        &vmke.entry_type,
+                        Some(mk.to_string()),
+                        Some((*t).to_string()),
+                        occur,
+                        v,
+                      )
+                    })
                   } else {
-                    Err(
-                      CBORError {
-                        expected_memberkey: Some(mk.to_string()),
-                        expected_value: ge.to_string(),
-                        actual_memberkey: None,
-                        actual_value: value.clone(),
-                      }
-                      .into(),
-                    )
+                    Err(ValidationError::new(Some(mk.to_string()), ge, None, value).into())
                   }
                 }
                 // Otherwise, validate JSON against the type of the entry.
@@ -639,31 +557,37 @@ impl<'a> Validator<Value> for CDDL<'a> {
                 // CDDL [ city: tstr, ] validates JSON [ "city" ]
                 _ => self.validate_type(&vmke.entry_type, Some(mk.to_string()), None, occur, value),
               },
-              // CDDL { * tstr => any } validates { "otherkey1": "anyvalue", "otherkey2": true }
-              Type2::Typename { ident, .. } if ident.ident == "tstr" || ident.ident == "text" => {
-                Ok(())
-              }
-              _ => Err(Error::Syntax(
-                "CDDL member key must be quoted string or bareword for validating JSON objects"
-                  .to_string(),
-              )),
+              _ => match (value, literal_key(mk)) {
+                // CDDL { 1 ^ => tstr } validates { 1: "value" }
+                (Value::Map(om), Some(key)) => {
+                  self.validate_literal_key_entry(vmke, mk, &key, om, value)
+                }
+                // CDDL { * int => any } validates { 1: "anyvalue", 2: true }
+                (Value::Map(om), None) => {
+                  self.validate_entries_with_key_type(vmke, mk, t1, occur, om, &[], value)
+                }
+                _ => self.validate_type(&vmke.entry_type, Some(mk.to_string()), None, occur, value),
+              },
             },
             MemberKey::Bareword { ident, .. } => match value {
               Value::Map(om) => {
-                if !is_type_prelude(&vmke.entry_type.to_string()) {
+                if !is_prelude_type(&vmke.entry_type.to_string()) {
                   if let Some(v) = om.get(&Value::Text(ident.ident.to_string())) {
-                    return self.validate_type(
-                      &vmke.entry_type,
-                      Some(mk.to_string()),
-                      Some(ident.ident.to_string()),
-                      vmke.occur.as_ref(),
-                      v,
-                    );
-                  } // REVIEW NEEDED: should there be an "else" path that handles optional
-                    // keys (vmke.occur) ?
-                    // I don't understand why there is different behavior based on
-                    // is_type_prelude().
-
+                    return at(ident.ident, || {
+                      self.validate_type(
+                        &vmke.entry_type,
+                        Some(mk.to_string()),
+                        Some(ident.ident.to_string()),
+                        vmke.occur.as_ref(),
+                        v,
+                      )
+                    });
+                  }
+
+                  if occurrence_bounds(vmke.occur.as_ref()).0 == 0 {
+                    return Ok(());
+                  }
+
                   return self.validate_type(
                     &vmke.entry_type,
                     Some(mk.to_string()),
@@ -674,44 +598,34 @@ impl<'a> Validator<Value> for CDDL<'a> {
                 }
 
                 match om.get(&Value::Text(ident.ident.to_string())) {
-                  Some(v) => self.validate_type(
-                    &vmke.entry_type,
-                    Some(mk.to_string()),
-                    Some(ident.ident.to_string()),
-                    vmke.occur.as_ref(),
-                    v,
+                  Some(v) => at(ident.ident, || {
+                    self.validate_type(
+                      &vmke.entry_type,
+                      Some(mk.to_string()),
+                      Some(ident.ident.to_string()),
+                      vmke.occur.as_ref(),
+                      v,
+                    )
+                  }),
+                  // We failed to find a matching key-value pair in the
+                  // value map.  This is OK if the occurrence would allow
+                  // it.
+                  // Occurrences in a map have quirky behavior; see
+                  // rfc8610 section 3.2.
+                  // "? acts as expected: this key is optional
+                  // "*" and "*n" act just like ?
+                  // "+" and "n*" with n > 0 have no effect; this key is
+                  // required
+                  None if occurrence_bounds(vmke.occur.as_ref()).0 == 0 => Ok(()),
+                  None => Err(
+                    ValidationError::new(
+                      Some(mk.to_string()),
+                      &format_args!("{} {}", mk, vmke.entry_type),
+                      None,
+                      value,
+                    )
+                    .into(),
                   ),
-                  None => match &vmke.occur {
-                    // We failed to find a matching key-value pair in the
-                    // value map.  This is OK if the occurrence would allow
-                    // it.
-                    // Occurrences in a map have quirky behavior; see
-                    // rfc8610 section 3.2.
-                    // "? acts as expected: this key is optional
-                    // "*" acts just like ?
-                    // "+" has no effect; this key is required
-                    Some(o) => match o {
-                      Occur::Optional(_) | Occur::ZeroOrMore(_) => Ok(()),
-                      _ => Err(
-                        CBORError {
-                          expected_memberkey: Some(mk.to_string()),
-                          expected_value: format!("{} {}", mk, vmke.entry_type),
-                          actual_memberkey: None,
-                          actual_value: value.clone(),
-                        }
-                        .into(),
-                      ),
-                    },
-                    None => Err(
-                      CBORError {
-                        expected_memberkey: Some(mk.to_string()),
-                        expected_value: format!("{} {}", mk, vmke.entry_type),
-                        actual_memberkey: None,
-                        actual_value: value.clone(),
-                      }
-                      .into(),
-                    ),
-                  },
                 }
               }
               _ => self.validate_type(
@@ -722,18 +636,22 @@ impl<'a> Validator<Value> for CDDL<'a> {
                 value,
               ),
             },
-            _ => Err(Error::Syntax(
-              "CDDL member key must be quoted string or bareword for validating JSON objects"
-                .to_string(),
-            )),
+            // CDDL { 1: tstr, h'01' => uint } validates { 1: "value", h'01': 2 }
+            MemberKey::Value { .. } => match (value, literal_key(mk)) {
+              (Value::Map(om), Some(key)) => {
+                self.validate_literal_key_entry(vmke, mk, &key, om, value)
+              }
+              _ => self.validate_unmatched_key_entry(vmke, mk, value),
+            },
+            _ => self.validate_unmatched_key_entry(vmke, mk, value),
           }
         } else {
-          // TODO: Inline type
-          unimplemented!()
+          // CDDL [ [int], { x: int } ] validates CBOR [ [1], { "x": 1 } ]
+          self.validate_type(&vmke.entry_type, None, None, occur, value)
         }
       }
       GroupEntry::TypeGroupname { ge: tge, span } => {
-        if is_type_prelude(&tge.name.ident) {
+        if self.is_builtin_type(&tge.name) {
           // Substitute a new AST node for the groupentry validation.
           // FIXME: this seems like an awkward thing to do.
           self.validate_type2(
@@ -748,14 +666,32 @@ impl<'a> Validator<Value> for CDDL<'a> {
             value,
           )
         } else {
-          self.validate_rule_for_ident(
-            &tge.name,
-            is_enumeration,
-            None,
-            None,
-            tge.occur.as_ref(),
-            value,
-          )
+          match (&tge.occur, value) {
+            // { 1: int, ? ext } where ext = (2: int, 3: tstr) validates maps
+            // with either both or neither of the entries of ext
+            (Some(o), Value::Map(_)) if !is_enumeration && self.is_group_rule(&tge.name) => {
+              self.validate_group_name_in_map(tge, o, *span, value)
+            }
+            _ => match &tge.generic_arg {
+              Some(ga) => self.validate_generic_rule(
+                &tge.name,
+                ga,
+                is_enumeration,
+                None,
+                None,
+                tge.occur.as_ref(),
+                value,
+              ),
+              None => self.validate_rule_for_ident(
+                &tge.name,
+                is_enumeration,
+                None,
+                None,
+                tge.occur.as_ref(),
+                value,
+              ),
+            },
+          }
         }
       }
       GroupEntry::InlineGroup {
@@ -763,10 +699,25 @@ impl<'a> Validator<Value> for CDDL<'a> {
         group: g,
         ..
       } => {
-        if igo.is_some() {
+        if let Some(o) = igo {
           if is_enumeration {
             return self.validate_group_to_choice_enum(g, igo.as_ref(), value);
           }
+
+          // { ? (1: int, 2: tstr) } validates maps with either both entries
+          // or neither, since a group of entries with literal keys can't be
+          // repeated within the same map
+          if let Value::Map(om) = value {
+            let keys = group_literal_keys(g);
+            if !keys.is_empty() {
+              if occurrence_bounds(Some(o)).0 == 0 && !keys.iter().any(|k| om.contains_key(k)) {
+                return Ok(());
+              }
+
+              return self.validate_group(g, None, value);
+            }
+          }
+
           self.validate_group(g, igo.as_ref(), value)
         } else {
           if is_enumeration {
@@ -779,232 +730,553 @@ impl<'a> Validator<Value> for CDDL<'a> {
   }
 
   fn validate_array_occurrence(&self, occur: &Occur, group: &str, values: &[Value]) -> Result {
-    match occur {
-      Occur::ZeroOrMore(_) => Ok(()),
-      Occur::Optional(_) => {
-        if values.len() > 1 {
-          Err(Error::Occurrence(format!(
-            "Expecting zero or one values of group {}",
-            group
-          )))
-        } else {
-          Ok(())
-        }
-      }
-      Occur::OneOrMore(_) => {
-        if values.is_empty() {
-          Err(Error::Occurrence(format!(
-            "Expecting one or more values of group {}",
-            group
-          )))
-        } else {
-          Ok(())
-        }
-      }
-      Occur::Exact { lower, upper, .. } => {
-        if let Some(li) = lower {
-          if let Some(ui) = upper {
-            if values.len() < *li || values.len() > *ui {
-              if li == ui {
-                return Err(Error::Occurrence(format!(
-                  "Expecting exactly {} values of group {}. Got {} values",
-                  li,
-                  group,
-                  values.len()
-                )));
-              }
+    validate_occurrence_count(occur, group, values.len())
+  }
 
-              return Err(Error::Occurrence(format!(
-                "Expecting between {} and {} values of group {}. Got {} values",
-                li,
-                ui,
-                group,
-                values.len()
-              )));
-            }
-          }
+  fn expect_bool(&self, ident: &str, value: &Value) -> Result {
+    self.validate_bool(ident, value)
+  }
 
-          if values.len() < *li {
-            return Err(Error::Occurrence(format!(
-              "Expecting at least {} values of group {}. Got {} values",
-              li,
-              group,
-              values.len()
-            )));
-          }
-        }
+  fn validate_numeric_data_type(
+    &self,
+    expected_memberkey: Option<String>,
+    actual_memberkey: Option<String>,
+    ident: &str,
+    value: &Value,
+  ) -> Result {
+    let is_valid = match value {
+      Value::Integer(n) => match ident {
+        "uint" | "unsigned" | "biguint" => *n >= 0,
+        "nint" | "bignint" => *n < 0,
+        // Bignums are interchangeable with integers of the same value, so any
+        // decoded integer satisfies the unbounded integer types
+        "int" | "integer" | "bigint" | "number" => true,
+        _ => false,
+      },
+      Value::Float(f) if !self.is_float_accepted(*f) => false,
+      Value::Float(f) => match ident {
+        "float16" => is_f16_representable(*f),
+        "float32" | "float16-32" => is_f32_representable(*f),
+        "float64" | "float32-64" | "float" | "number" => true,
+        _ => false,
+      },
+      _ => false,
+    };
 
-        if let Some(ui) = upper {
-          if values.len() > *ui {
-            return Err(Error::Occurrence(format!(
-              "Expecting no more than {} values of group {}. Got {} values",
-              ui,
-              group,
-              values.len()
-            )));
-          }
-        }
+    if is_valid {
+      return Ok(());
+    }
 
-        Ok(())
-      }
+    Err(ValidationError::new(expected_memberkey, ident, actual_memberkey, value).into())
+  }
+}
+
+impl DataValue for Value {
+  const FORMAT: &'static str = "CBOR";
+  const HAS_UNDEFINED: bool = true;
+
+  fn kind(&self) -> DataKind<'_, Value> {
+    match self {
+      Value::Null => DataKind::Null,
+      Value::Bool(b) => DataKind::Bool(*b),
+      Value::Integer(i) => DataKind::Integer(*i),
+      Value::Float(f) => DataKind::Float(*f),
+      Value::Text(s) => DataKind::Text(s),
+      Value::Array(values) => DataKind::Array(values),
+      _ if simple_value_number(self) == Some(23) => DataKind::Undefined,
+      _ => DataKind::Other,
     }
   }
 
-  fn expect_bool(&self, ident: &str, value: &Value) -> Result {
-    match value {
-      Value::Bool(b) => {
-        if ident == "bool" {
-          return Ok(());
-        }
+  fn bytes<'v>(&'v self, _cddl: &CDDL) -> Option<Cow<'v, [u8]>> {
+    match self {
+      Value::Bytes(b) => Some(Cow::Borrowed(b)),
+      _ => None,
+    }
+  }
 
-        if let Ok(bfs) = ident.parse::<bool>() {
-          if bfs == *b {
-            return Ok(());
-          }
+  // Map keys other than text strings are located by the same tokens used in
+  // the paths of failures
+  fn lookup(&self, token: &str) -> Option<&Value> {
+    match self {
+      Value::Map(m) => m.get(&Value::Text(token.to_string())).or_else(|| {
+        m.iter()
+          .find(|(k, _)| key_token(k) == token)
+          .map(|(_, v)| v)
+      }),
+      Value::Array(a) => pointer_index(token).and_then(|idx| a.get(idx)),
+      Value::Tag(_, v) => v.lookup(token),
+      _ => None,
+    }
+  }
 
-          return Err(
-            CBORError {
-              expected_memberkey: None,
-              expected_value: ident.to_string(),
-              actual_memberkey: None,
-              actual_value: value.clone(),
-            }
-            .into(),
-          );
-        }
+  fn from_size(size: usize) -> Value {
+    Value::Integer(size as i128)
+  }
+}
 
-        Err(
-          CBORError {
-            expected_memberkey: None,
-            expected_value: ident.to_string(),
-            actual_memberkey: None,
-            actual_value: value.clone(),
-          }
-          .into(),
+impl<'a> CDDL<'a> {
+  // Validates a byte string that has matched the target of a .cbor or .cborseq
+  // control against its controller, once decoded
+  fn validate_encoding_control(
+    &self,
+    target: &Type2,
+    ctrl: &str,
+    controller: &Type2,
+    value: &Value,
+  ) -> Result {
+    let error = || -> Error {
+      ValidationError::new(
+        None,
+        &format_args!("{} {} {}", target, ctrl, controller),
+        None,
+        value,
+      )
+      .into()
+    };
+
+    match (token::lookup_control_from_str(ctrl), value) {
+      (Some(token::Token::CBOR), Value::Bytes(b)) => {
+        let item = decode_cbor(b).map_err(|_| error())?;
+
+        transiently(|| self.validate_type2(controller, None, None, None, &item))
+      }
+      // The data items of the sequence are matched as if they were the elements
+      // of an array, e.g. `bstr .cborseq [* uint]`
+      (Some(token::Token::CBORSEQ), Value::Bytes(b)) => {
+        let items = decode_cbor_seq(b).map_err(|_| error())?;
+
+        transiently(|| self.validate_type2(controller, None, None, None, &Value::Array(items)))
+      }
+      _ => Err(error()),
+    }
+  }
+
+  // Validates data against a group entry whose member key no map key can
+  // match, such as a byte string key that isn't valid hex, so maps are only
+  // valid if the entry is optional. The member key doesn't apply to array
+  // elements.
+  fn validate_unmatched_key_entry(
+    &self,
+    vmke: &ValueMemberKeyEntry,
+    mk: &MemberKey,
+    value: &Value,
+  ) -> Result {
+    match value {
+      Value::Map(_) if occurrence_bounds(vmke.occur.as_ref()).0 == 0 => Ok(()),
+      Value::Map(_) => Err(
+        ValidationError::new(
+          Some(mk.to_string()),
+          &format_args!("{} {}", mk, vmke.entry_type),
+          None,
+          value,
         )
+        .into(),
+      ),
+      _ => self.validate_type(
+        &vmke.entry_type,
+        Some(mk.to_string()),
+        None,
+        vmke.occur.as_ref(),
+        value,
+      ),
+    }
+  }
+
+  // Validates the value of the map entry with the given key against the type of
+  // the group entry, unless the key is absent and the entry is optional
+  fn validate_literal_key_entry(
+    &self,
+    vmke: &ValueMemberKeyEntry,
+    mk: &MemberKey,
+    key: &Value,
+    om: &BTreeMap<Value, Value>,
+    value: &Value,
+  ) -> Result {
+    match om.get(key) {
+      Some(v) => {
+        let token = key_token(key);
+
+        at(&*token, || {
+          self.validate_type(
+            &vmke.entry_type,
+            Some(mk.to_string()),
+            Some(token.clone()),
+            vmke.occur.as_ref(),
+            v,
+          )
+        })
       }
-      _ => Err(
-        CBORError {
-          expected_memberkey: None,
-          expected_value: ident.to_string(),
-          actual_memberkey: None,
-          actual_value: value.clone(),
-        }
+      None if occurrence_bounds(vmke.occur.as_ref()).0 == 0 => Ok(()),
+      None => Err(
+        ValidationError::new(
+          Some(mk.to_string()),
+          &format_args!("{} {}", mk, vmke.entry_type),
+          None,
+          value,
+        )
         .into(),
       ),
     }
   }
 
-  fn validate_numeric_data_type(
+  // Validates the values of the map entries whose keys match the given type
+  // against the type of the group entry, skipping the given keys of entries
+  // with literal keys. The number of entries matched must satisfy the
+  // occurrence of the group entry, or else that of the entry referencing its
+  // group, which is exactly one if neither has any.
+  #[allow(clippy::too_many_arguments)]
+  fn validate_entries_with_key_type(
     &self,
-    expected_memberkey: Option<String>,
-    actual_memberkey: Option<String>,
-    ident: &str,
+    vmke: &ValueMemberKeyEntry,
+    mk: &MemberKey,
+    key_type: &Type1,
+    occur: Option<&Occur>,
+    om: &BTreeMap<Value, Value>,
+    literal_keys: &[Value],
     value: &Value,
   ) -> Result {
-    match value {
-      Value::Integer(n) => match ident {
-        "uint" => {
-          if *n >= 0 {
-            Ok(())
-          } else {
-            Err(
-              CBORError {
-                expected_memberkey,
-                expected_value: ident.to_string(),
-                actual_memberkey,
-                actual_value: value.clone(),
-              }
-              .into(),
-            )
-          }
-        }
-        "nint" => {
-          if *n < 0 {
-            Ok(())
-          } else {
-            Err(
-              CBORError {
-                expected_memberkey,
-                expected_value: ident.to_string(),
-                actual_memberkey,
-                actual_value: value.clone(),
-              }
-              .into(),
-            )
-          }
+    let mut matched = 0;
+    let mut errors = Vec::new();
+
+    for (k, v) in om.iter().filter(|(k, _)| !literal_keys.contains(k)) {
+      if self.validate_type1(key_type, None, None, None, k).is_err() {
+        continue;
+      }
+
+      matched += 1;
+
+      let token = key_token(k);
+      if let Err(e) = at(&*token, || {
+        self.validate_type(
+          &vmke.entry_type,
+          Some(mk.to_string()),
+          Some(token.clone()),
+          vmke.occur.as_ref(),
+          v,
+        )
+      }) {
+        if !is_collecting() {
+          return Err(e);
         }
-        "int" | "number" => Ok(()),
-        // TODO: Finish rest of numerical data types
-        _ => Err(
-          CBORError {
-            expected_memberkey,
-            expected_value: ident.to_string(),
-            actual_memberkey,
-            actual_value: value.clone(),
-          }
-          .into(),
-        ),
-      },
-      Value::Float(_n) => match ident {
-        "number" | "float" | "float16" | "float32" | "float64" => Ok(()),
-        // TODO: Finish rest of numerical data types
-        _ => Err(
-          CBORError {
-            expected_memberkey,
-            expected_value: ident.to_string(),
-            actual_memberkey,
-            actual_value: value.clone(),
-          }
-          .into(),
-        ),
-      },
-      _ => Err(
-        CBORError {
-          expected_memberkey,
-          expected_value: ident.to_string(),
-          actual_memberkey,
-          actual_value: value.clone(),
+
+        errors.push(e);
+      }
+    }
+
+    let (lower, upper) = occurrence_bounds(vmke.occur.as_ref().or(occur));
+
+    if matched < lower || upper.map(|upper| matched > upper).unwrap_or(false) {
+      // The entry is rendered whole, so the member key is left out of the error
+      // to not repeat it
+      let mut e = ValidationError::new(None, &format_args!("{}", vmke), None, value);
+      if matched < lower {
+        e.reason = Reason::MissingEntry;
+      }
+      errors.push(e.into());
+    }
+
+    match errors.len() {
+      0 => Ok(()),
+      1 => Err(errors.remove(0)),
+      _ => Err(Error::MultiError(errors)),
+    }
+  }
+
+  // Validates a map against the group of a map type. Maps are closed, so each
+  // of their entries must also be matched by a member key of the group choice
+  // matched.
+  fn validate_map(
+    &self,
+    g: &Group,
+    occur: Option<&Occur>,
+    om: &BTreeMap<Value, Value>,
+    value: &Value,
+  ) -> Result {
+    let mut validation_errors: Vec<Error> = Vec::new();
+
+    if g.group_choices.iter().enumerate().any(|(idx, gc)| {
+      let is_traced = open_step(|| group_choice(g, idx), value);
+      let result = self
+        .validate_group_choice(gc, occur, value)
+        .and_then(|_| self.validate_unexpected_entries(gc, om));
+      close_step(is_traced, &result);
+
+      match result {
+        Ok(()) => true,
+        Err(e) => {
+          validation_errors.push(e);
+          false
         }
-        .into(),
-      ),
+      }
+    }) {
+      return Ok(());
+    }
+
+    let validation_errors = dedup_errors(most_plausible(validation_errors));
+
+    Err(Error::MultiError(validation_errors))
+  }
+
+  // Rejects the entries of a map that no member key of the group choice
+  // matches. Entries are only checked when the group choice consists of entries
+  // with member keys once the groups it references are expanded, since the keys
+  // matched by groups with several choices aren't known here.
+  fn validate_unexpected_entries(&self, gc: &GroupChoice, om: &BTreeMap<Value, Value>) -> Result {
+    let mut entries = Vec::new();
+    if !gc
+      .group_entries
+      .iter()
+      .all(|(ge, _)| self.keyed_entries(ge, &mut entries))
+    {
+      return Ok(());
+    }
+
+    let mut literal_keys = Vec::new();
+    let mut key_types = Vec::new();
+    for vmke in entries {
+      match &vmke.member_key {
+        Some(mk) => match (literal_key(mk), mk) {
+          (Some(key), _) => literal_keys.push(key),
+          (None, MemberKey::Type1 { t1, .. }) => key_types.push(&**t1),
+          _ => return Ok(()),
+        },
+        None => return Ok(()),
+      }
+    }
+
+    let mut errors = Vec::new();
+
+    for (k, v) in om.iter().filter(|(k, _)| !literal_keys.contains(k)) {
+      if key_types
+        .iter()
+        .any(|t1| self.validate_type1(t1, None, None, None, k).is_ok())
+      {
+        continue;
+      }
+
+      let token = key_token(k);
+      let error = at(&*token, || {
+        Err(
+          Error::from(ValidationError::new(None, gc, Some(token.clone()), v))
+            .with_reason(Reason::UnexpectedEntry),
+        )
+      });
+
+      if !is_collecting() {
+        return error;
+      }
+
+      if let Err(e) = error {
+        errors.push(e);
+      }
+    }
+
+    match errors.len() {
+      0 => Ok(()),
+      1 => Err(errors.remove(0)),
+      _ => Err(Error::MultiError(errors)),
     }
   }
 }
 
-fn expect_null(ident: &str) -> Result {
+// Tag of the durations described by RFC 9581
+const DURATION_TAG: u64 = 1002;
+
+// Returns the tag of the data items of a standard prelude type whose semantics
+// are checked beyond those of its tagged content
+fn prelude_tag(ident: &str) -> Option<u64> {
   match ident {
-    "null" | "nil" => Ok(()),
-    _ => Err(
-      CBORError {
-        expected_memberkey: None,
-        expected_value: ident.to_string(),
-        actual_memberkey: None,
-        actual_value: Value::Null,
+    "tdate" => Some(0),
+    "time" => Some(1),
+    "uri" => Some(32),
+    "b64url" => Some(33),
+    "b64legacy" => Some(34),
+    "regexp" => Some(35),
+    "mime-message" => Some(36),
+    _ => None,
+  }
+}
+
+// Returns whether the content of a tagged duration is a map with integer keys
+// holding at least the number of seconds under key 1, as RFC 9581 requires
+fn is_duration_map(value: &Value) -> bool {
+  match value {
+    Value::Map(m) => {
+      m.contains_key(&Value::Integer(1))
+        && m.iter().all(|(k, v)| match (k, v) {
+          (Value::Integer(1), Value::Integer(_)) => true,
+          (Value::Integer(1), Value::Float(f)) => time::check_time(*f).is_ok(),
+          (Value::Integer(1), _) => false,
+          (Value::Integer(_), _) => true,
+          _ => false,
+        })
+    }
+    _ => false,
+  }
+}
+
+// Returns whether the control operator describes the CBOR encoded within a byte
+// string, which only the CBOR validator checks
+fn is_encoding_control(operator: &str) -> bool {
+  let ctrl = token::lookup_control_from_str(operator);
+
+  ctrl == Some(token::Token::CBOR) || ctrl == Some(token::Token::CBORSEQ)
+}
+
+// Returns the map keys matched by the member keys with literal values of the
+// entries of a group
+fn literal_keys(gc: &GroupChoice) -> Vec<Value> {
+  gc.group_entries
+    .iter()
+    .filter_map(|ge| match &ge.0 {
+      GroupEntry::ValueMemberKey { ge, .. } => ge.member_key.as_ref().and_then(literal_key),
+      _ => None,
+    })
+    .collect()
+}
+
+// Returns the map keys matched by the entries of a group with literal keys,
+// including those of nested inline groups
+fn group_literal_keys(g: &Group) -> Vec<Value> {
+  let mut keys = Vec::new();
+
+  for gc in g.group_choices.iter() {
+    for (ge, _) in gc.group_entries.iter() {
+      match ge {
+        GroupEntry::ValueMemberKey { ge, .. } => {
+          keys.extend(ge.member_key.as_ref().and_then(literal_key))
+        }
+        GroupEntry::InlineGroup { group, .. } => keys.extend(group_literal_keys(group)),
+        _ => (),
       }
-      .into(),
+    }
+  }
+
+  keys
+}
+
+// Returns the map key matched by a member key with a literal value, such as
+// bareword: or 1 =>
+fn literal_key(mk: &MemberKey) -> Option<Value> {
+  match mk {
+    MemberKey::Bareword { ident, .. } => Some(Value::Text(ident.ident.to_string())),
+    MemberKey::Value { value, .. } => match value {
+      token::Value::TEXT(t) => Some(Value::Text((*t).to_string())),
+      token::Value::INT(i) => Some(Value::Integer(*i)),
+      token::Value::UINT(u) => i128::try_from(*u).ok().map(Value::Integer),
+      token::Value::FLOAT(f) => Some(Value::Float(*f)),
+      token::Value::BYTE(token::ByteValue::UTF8(b)) => Some(Value::Bytes(b.to_vec())),
+      token::Value::BYTE(token::ByteValue::B16(b)) => decode_b16(b).map(Value::Bytes),
+      token::Value::BYTE(token::ByteValue::B64(b)) => decode_b64(b).map(Value::Bytes),
+    },
+    MemberKey::Type1 { t1, .. } if t1.operator.is_none() => match &t1.type2 {
+      Type2::TextValue { value, .. } => Some(Value::Text((*value).to_string())),
+      Type2::IntValue { value, .. } => Some(Value::Integer(*value)),
+      Type2::UintValue { value, .. } => i128::try_from(*value).ok().map(Value::Integer),
+      Type2::FloatValue { value, .. } => Some(Value::Float(*value)),
+      Type2::UTF8ByteString { value, .. } => Some(Value::Bytes(value.to_vec())),
+      Type2::B16ByteString { value, .. } => decode_b16(value).map(Value::Bytes),
+      Type2::B64ByteString { value, .. } => decode_b64(value).map(Value::Bytes),
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+// Renders a map key as a reference token of the path to its value
+fn key_token(key: &Value) -> String {
+  match key {
+    Value::Text(t) => t.clone(),
+    Value::Integer(i) => i.to_string(),
+    Value::Float(f) => f.to_string(),
+    Value::Bytes(b) => format!(
+      "h'{}'",
+      b.iter().map(|b| format!("{:02x}", b)).collect::<String>()
     ),
+    _ => format!("{:?}", key),
   }
 }
 
-fn is_type_prelude(t: &str) -> bool {
-  match t {
-    "any" | "uint" | "nint" | "int" | "bstr" | "bytes" | "tstr" | "text" | "tdate" | "time"
-    | "number" | "biguint" | "bignint" | "bigint" | "integer" | "unsigned" | "decfrac"
-    | "bigfloat" | "eb64url" | "eb64legacy" | "eb16" | "encoded-cbor" | "uri" | "b64url"
-    | "b64legacy" | "regexp" | "mime-mesage" | "cbor-any" | "float16" | "float32" | "float64"
-    | "float16-32" | "float32-64" | "float" | "false" | "true" | "bool" | "nil" | "null"
-    | "undefined" => true,
-    _ => false,
+// Checks whether or not a CBOR value is a data item of the given major type
+// and, if present, whether its additional information matches the given
+// constraint. The constraint applies to the argument of the data item, i.e. the
+// unsigned value for major type 0, the value -1-n for major type 1, the length
+// of byte strings, text strings, arrays and maps, the tag number for major type
+// 6 and the simple value or float width for major type 7
+fn is_major_type(mt: u8, constraint: Option<usize>, value: &Value) -> bool {
+  let argument = match (mt, value) {
+    (0, Value::Integer(i)) if *i >= 0 => *i as u128,
+    (1, Value::Integer(i)) if *i < 0 => (-1 - *i) as u128,
+    (2, Value::Bytes(b)) => b.len() as u128,
+    (3, Value::Text(t)) => t.len() as u128,
+    (4, Value::Array(a)) => a.len() as u128,
+    (5, Value::Map(m)) => m.len() as u128,
+    (6, Value::Tag(SIMPLE_VALUE_TAG, _)) => return false,
+    (6, Value::Tag(tag, _)) => *tag as u128,
+    (7, Value::Bool(false)) => 20,
+    (7, Value::Bool(true)) => 21,
+    (7, Value::Null) => 22,
+    (7, Value::Tag(SIMPLE_VALUE_TAG, _)) => match simple_value_number(value) {
+      Some(n) => n.into(),
+      None => return false,
+    },
+    // The encoded width of a float isn't retained once decoded, so a float is
+    // considered to satisfy a width constraint if it can be represented in that
+    // width without loss of precision
+    (7, Value::Float(f)) => {
+      return match constraint {
+        None | Some(27) => true,
+        Some(26) => is_f32_representable(*f),
+        Some(25) => is_f16_representable(*f),
+        _ => false,
+      }
+    }
+    _ => return false,
+  };
+
+  match constraint {
+    Some(c) => c as u128 == argument,
+    None => true,
   }
 }
 
 /// Validates CBOR input against given CDDL input
 pub fn validate_cbor_from_slice(cddl_input: &str, cbor_input: &[u8]) -> Result {
+  parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+    .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?
+    .validate_cbor_slice(cbor_input)
+}
+
+/// Validates CBOR input against the rule with the given name in the given
+/// CDDL input, rather than against the root type
+pub fn validate_cbor_from_slice_for_rule(
+  cddl_input: &str,
+  cbor_input: &[u8],
+  rule_name: &str,
+) -> Result {
+  parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+    .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?
+    .validate_with_root(rule_name, &decode_cbor(cbor_input)?)
+}
+
+/// Validates CBOR written in diagnostic notation (RFC 8949 section 8) against
+/// given CDDL input. See `edn::parse` for the supported notation.
+///
+/// # Example
+///
+/// ```
+/// use cddl::validate_cbor_diag_from_str;
+///
+/// let cddl_input = r#"message = { text: tstr, data: bstr }"#;
+///
+/// assert!(validate_cbor_diag_from_str(cddl_input, r#"{"text": "hello", "data": h'0102'}"#).is_ok());
+/// ```
+pub fn validate_cbor_diag_from_str(cddl_input: &str, diag_input: &str) -> Result {
   validate_cbor(
     &parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
       .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?,
-    &serde_cbor::from_slice(cbor_input).map_err(|e| Error::Target(e.into()))?,
+    &edn::parse(diag_input).map_err(|e| Error::Compilation(CompilationError::Target(e.into())))?,
   )
 }
 
@@ -1012,24 +1284,1357 @@ fn validate_cbor<V: Validator<Value>>(cddl: &V, cbor: &Value) -> Result {
   cddl.validate(cbor)
 }
 
-#[cfg(test)]
-mod tests {
-  use super::*;
-  use serde_cbor;
+/// Validates a CBOR sequence (RFC 8742), i.e. the concatenation of zero or more
+/// encoded data items, against given CDDL input. See `CDDL::validate_cbor_seq`
+/// for how the data items are matched against the CDDL.
+pub fn validate_cbor_seq_from_slice(cddl_input: &str, cbor_seq: &[u8]) -> Result {
+  let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+    .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
 
-  #[test]
-  fn validate_cbor_null() -> Result {
-    let cbor_value = Value::Null;
-    let cddl_input = r#"mynullrule = null"#;
+  cddl.validate_cbor_seq(&decode_cbor_seq(cbor_seq)?)
+}
+
+/// Tag under which the simple values that `serde_cbor::Value` has no variant
+/// for, e.g. `undefined` (23), are held as `Value::Tag(SIMPLE_VALUE_TAG,
+/// Box::new(Value::Integer(n)))` by `decode_cbor` and `edn::parse`. Tag number
+/// 18446744073709551615 is registered as invalid, so it can't stand for
+/// anything else in well-formed data.
+pub const SIMPLE_VALUE_TAG: u64 = 0xffff_ffff_ffff_ffff;
+
+/// Returns the `serde_cbor::Value` standing for the given simple value, i.e.
+/// `false`, `true` and `null` for 20, 21 and 22 and the number of any other
+/// simple value tagged with `SIMPLE_VALUE_TAG`
+///
+/// # Example
+///
+/// ```
+/// use cddl::{cbor_validator::simple_value, validate_cbor_from_slice};
+/// use serde_cbor::Value;
+///
+/// assert_eq!(simple_value(22), Value::Null);
+/// assert!(validate_cbor_from_slice("root = undefined", &[0xf7]).is_ok());
+/// assert!(validate_cbor_from_slice("root = nil", &[0xf7]).is_err());
+/// ```
+pub fn simple_value(n: u8) -> Value {
+  match n {
+    20 => Value::Bool(false),
+    21 => Value::Bool(true),
+    22 => Value::Null,
+    n => Value::Tag(SIMPLE_VALUE_TAG, Box::new(Value::Integer(n.into()))),
+  }
+}
+
+// Returns the number of the simple value other than false, true and null that
+// the value stands for, if any
+fn simple_value_number(value: &Value) -> Option<u8> {
+  match value {
+    Value::Tag(SIMPLE_VALUE_TAG, n) => match **n {
+      Value::Integer(n) => u8::try_from(n).ok(),
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+/// Decodes a single encoded CBOR data item the way the validators of this
+/// module do. Unlike decoding via serde_cbor, simple values other than
+/// `false`, `true` and `null` are retained as described for `SIMPLE_VALUE_TAG`,
+/// and map entries with duplicate keys are resolved in favor of the last one.
+pub fn decode_cbor(cbor: &[u8]) -> result::Result<Value, Error> {
+  cbor_core::decode(cbor)
+    .map_err(|e| Error::Target(e.into()))
+    .and_then(from_core)
+}
+
+// Decodes a CBOR sequence like decode_cbor does a single data item
+fn decode_cbor_seq(cbor_seq: &[u8]) -> result::Result<Vec<Value>, Error> {
+  decode_seq(cbor_seq)
+    .map_err(|e| Error::Target(e.into()))?
+    .into_iter()
+    .map(from_core)
+    .collect()
+}
+
+// Converts a data item decoded by cbor_core into a serde_cbor::Value
+pub(super) fn from_core(value: cbor_core::Value) -> result::Result<Value, Error> {
+  Ok(match value {
+    cbor_core::Value::Unsigned(n) => Value::Integer(n.into()),
+    cbor_core::Value::Negative(n) => Value::Integer(-1 - i128::from(n)),
+    cbor_core::Value::Bytes(b) => Value::Bytes(b),
+    cbor_core::Value::Text(t) => Value::Text(t),
+    cbor_core::Value::Array(a) => Value::Array(
+      a.into_iter()
+        .map(from_core)
+        .collect::<result::Result<_, Error>>()?,
+    ),
+    cbor_core::Value::Map(m) => Value::Map(
+      m.into_iter()
+        .map(|(k, v)| Ok((from_core(k)?, from_core(v)?)))
+        .collect::<result::Result<_, Error>>()?,
+    ),
+    cbor_core::Value::Tag(SIMPLE_VALUE_TAG, _) => {
+      return Err(Error::Target(
+        format!("tag {} is registered as invalid", SIMPLE_VALUE_TAG).into(),
+      ))
+    }
+    cbor_core::Value::Tag(tag, v) => Value::Tag(tag, Box::new(from_core(*v)?)),
+    cbor_core::Value::Simple(n) => simple_value(n),
+    cbor_core::Value::Float(f, _) => Value::Float(f),
+  })
+}
+
+impl<'a> CDDL<'a> {
+  /// Validates an in-memory Rust value against the CDDL. The value is converted
+  /// to a `serde_cbor::Value` via its `Serialize` implementation rather than
+  /// being encoded first. Since the CBOR data model is used, byte buffers
+  /// serialized via `serde_bytes` and non-text map keys are retained, unlike
+  /// with JSON.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{lexer_from_str, parser::cddl_from_str};
+  /// use serde::Serialize;
+  ///
+  /// #[derive(Serialize)]
+  /// struct Person {
+  ///   name: String,
+  ///   age: u8,
+  /// }
+  ///
+  /// let input = r#"person = { name: tstr, age: uint }"#;
+  /// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+  ///
+  /// let person = Person { name: "Alice".into(), age: 30 };
+  /// assert!(cddl.validate_serde(&person).is_ok());
+  /// ```
+  pub fn validate_serde<T: serde::Serialize + ?Sized>(&self, value: &T) -> Result {
+    let value = serde_cbor::value::to_value(value)
+      .map_err(|e| Error::Compilation(CompilationError::Target(e.into())))?;
+
+    Validator::<Value>::validate(self, &value)
+  }
+
+  /// Validates the data items of a CBOR sequence (RFC 8742) as if they were
+  /// the elements of an array. If the first rule is a group rule, e.g.
+  /// `log = (* entry)`, it describes the items of the sequence. Otherwise the
+  /// root type rule describes the sequence as an array, e.g.
+  /// `log = [* entry]`.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{lexer_from_str, parser::cddl_from_str};
+  /// use serde_cbor::Value;
+  ///
+  /// let input = r#"
+  ///   log = (* entry)
+  ///   entry = { message: tstr }
+  /// "#;
+  /// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+  ///
+  /// let mut entry = std::collections::BTreeMap::new();
+  /// entry.insert(Value::Text("message".into()), Value::Text("started".into()));
+  ///
+  /// assert!(cddl
+  ///   .validate_cbor_seq(&[Value::Map(entry.clone()), Value::Map(entry)])
+  ///   .is_ok());
+  /// ```
+  pub fn validate_cbor_seq(&self, items: &[Value]) -> Result {
+    let value = Value::Array(items.to_vec());
+
+    if let Some(Rule::Group { rule, .. }) = self.rules.first() {
+      if let GroupEntry::InlineGroup {
+        group, occur: None, ..
+      } = &rule.entry
+      {
+        if rule.generic_param.is_none() {
+          return self.validate_root(|| self.validate_group(group, None, &value));
+        }
+      }
+    }
+
+    Validator::<Value>::validate(self, &value)
+  }
+
+  /// Decodes and validates encoded CBOR. Indefinite-length strings are
+  /// validated as the concatenation of their chunks, and maps with duplicate
+  /// keys are treated according to `ValidationOptions::cbor_duplicate_keys`.
+  /// If `ValidationOptions::cbor_deterministic`
+  /// is set, every departure of the encoding from RFC 8949 deterministic
+  /// encoding is also reported as an `Error::Encoding`, in the order they
+  /// occur and ahead of any structural failures, as is every indefinite-length
+  /// item if `ValidationOptions::cbor_definite_lengths` is set. The first map
+  /// key out of order is reported as an `Error::UnsortedKey` if
+  /// `ValidationOptions::canonical_key_order` is set.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{
+  ///   cbor_core::EncodingViolationKind,
+  ///   lexer_from_str,
+  ///   parser::cddl_from_str,
+  ///   validation::{Error, ValidationOptions},
+  /// };
+  ///
+  /// let input = r#"counter = uint"#;
+  /// let mut cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+  ///
+  /// // 1 encoded in two bytes rather than one
+  /// assert!(cddl.validate_cbor_slice(&[0x18, 0x01]).is_ok());
+  ///
+  /// cddl.set_validation_options(ValidationOptions {
+  ///   cbor_deterministic: true,
+  ///   ..Default::default()
+  /// });
+  ///
+  /// match cddl.validate_cbor_slice(&[0x18, 0x01]) {
+  ///   Err(Error::Encoding(e)) => assert_eq!(e.kind, EncodingViolationKind::NonShortestArgument),
+  ///   r => panic!("unexpected result {:?}", r),
+  /// }
+  /// ```
+  pub fn validate_cbor_slice(&self, cbor: &[u8]) -> Result {
+    let options = &self.validation_options;
+    let mut errors = Vec::new();
+
+    // Decoding keeps the last entry with each key
+    let value = match options.cbor_duplicate_keys {
+      DuplicateKeyPolicy::First => {
+        decode_cbor(&remove_duplicate_keys(cbor).map_err(|e| Error::Target(e.into()))?)?
+      }
+      _ => decode_cbor(cbor)?,
+    };
+
+    if options.cbor_duplicate_keys == DuplicateKeyPolicy::Reject {
+      let duplicates = duplicate_keys(cbor).map_err(|e| Error::Target(e.into()))?;
+      errors.extend(duplicates.into_iter().map(|d| Error::DuplicateKey {
+        key: d.key.to_string(),
+        positions: d.positions,
+      }));
+    }
+
+    if options.cbor_deterministic || options.cbor_definite_lengths {
+      let (_, violations) = decode_deterministic(cbor).map_err(|e| Error::Target(e.into()))?;
+      errors.extend(
+        violations
+          .into_iter()
+          .filter(|v| match v.kind {
+            EncodingViolationKind::IndefiniteLength => true,
+            // Reported as an Error::UnsortedKey instead, which locates the key
+            EncodingViolationKind::UnsortedMapKey => {
+              options.cbor_deterministic && !options.canonical_key_order
+            }
+            _ => options.cbor_deterministic,
+          })
+          .map(Error::Encoding),
+      );
+    }
+
+    if options.canonical_key_order {
+      if let Some(unsorted) = first_unsorted_key(cbor).map_err(|e| Error::Target(e.into()))? {
+        errors.push(Error::UnsortedKey(Box::new(super::UnsortedKey {
+          key: unsorted.key.to_string(),
+          path: unsorted.path,
+        })));
+      }
+    }
+
+    let result = Validator::<Value>::validate(self, &value);
+    if errors.is_empty() {
+      return result;
+    }
+
+    if let Err(e) = result {
+      errors.extend(e.into_errors());
+    }
+
+    match errors.len() {
+      0 => Ok(()),
+      1 => Err(errors.remove(0)),
+      _ => Err(Error::MultiError(errors)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    error::Limit,
+    validation::{ActualValue, FloatComparison, NonFiniteFloats, Severity, ValidationOptions},
+  };
+  use serde_cbor;
+  use std::f64;
+
+  #[test]
+  fn validate_cbor_null() -> Result {
+    let cbor_value = Value::Null;
+    let cddl_input = r#"mynullrule = null"#;
 
     validate_cbor_from_slice(cddl_input, &serde_cbor::to_vec(&cbor_value).unwrap())
   }
 
   #[test]
   fn validate_cbor_bytes_value() -> Result {
-    let cbor_value = Value::Bytes(b"68656c6c6f20776f726c64".to_vec());
+    let cbor_value = Value::Bytes(b"hello world".to_vec());
     let cddl_input = r#"mybytesstring = h'68656c6c6f20776f726c64'"#;
 
     validate_cbor_from_slice(cddl_input, &serde_cbor::to_vec(&cbor_value).unwrap())
   }
+
+  #[test]
+  fn validate_cbor_integer_literals() -> Result {
+    let cbor = |i: i128| serde_cbor::to_vec(&Value::Integer(i)).unwrap();
+
+    validate_cbor_from_slice(
+      "root = 18446744073709551615",
+      &cbor(18_446_744_073_709_551_615),
+    )?;
+    validate_cbor_from_slice(
+      "root = -18446744073709551616",
+      &cbor(-18_446_744_073_709_551_616),
+    )?;
+
+    assert!(validate_cbor_from_slice("root = 18446744073709551615", &cbor(-1)).is_err());
+    assert!(validate_cbor_from_slice(
+      "root = 18446744073709551615",
+      &cbor(18_446_744_073_709_551_614)
+    )
+    .is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_cbor_number_literals() -> Result {
+    let cbor = |value: Value| serde_cbor::to_vec(&value).unwrap();
+
+    validate_cbor_from_slice("root = 0x1F", &cbor(Value::Integer(31)))?;
+    validate_cbor_from_slice("root = -0b11", &cbor(Value::Integer(-3)))?;
+    validate_cbor_from_slice("root = 2.5e-1", &cbor(Value::Float(0.25)))?;
+    validate_cbor_from_slice("root = 0x1p4", &cbor(Value::Float(16.0)))?;
+
+    assert!(validate_cbor_from_slice("root = 0x1F", &cbor(Value::Integer(32))).is_err());
+    assert!(validate_cbor_from_slice("root = 0x1p4", &cbor(Value::Float(4.0))).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_cbor_text_escapes() -> Result {
+    let cddl_input = r#"root = { "say \"hi\"": "\u{1F600}" / "a\\b", ? "tab\t": "\d" }"#;
+    let cbor = |entries: Vec<(&str, &str)>| {
+      serde_cbor::to_vec(&Value::Map(
+        entries
+          .into_iter()
+          .map(|(k, v)| (Value::Text(k.into()), Value::Text(v.into())))
+          .collect(),
+      ))
+      .unwrap()
+    };
+
+    validate_cbor_from_slice(cddl_input, &cbor(vec![("say \"hi\"", "😀")]))?;
+    validate_cbor_from_slice(
+      cddl_input,
+      &cbor(vec![("say \"hi\"", "a\\b"), ("tab\t", "\\d")]),
+    )?;
+
+    assert!(validate_cbor_from_slice(cddl_input, &cbor(vec![("say \\\"hi\\\"", "😀")])).is_err());
+    assert!(
+      validate_cbor_from_slice(cddl_input, &cbor(vec![("say \"hi\"", "\\u{1F600}")])).is_err()
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_empty_containers() -> Result {
+    let map = |entries: Vec<(&str, u64)>| {
+      Value::Map(
+        entries
+          .into_iter()
+          .map(|(k, v)| (Value::Text(k.into()), Value::Integer(v.into())))
+          .collect(),
+      )
+    };
+    let array = |elements: Vec<u64>| {
+      Value::Array(
+        elements
+          .into_iter()
+          .map(|e| Value::Integer(e.into()))
+          .collect(),
+      )
+    };
+
+    for (cddl_input, valid, invalid) in vec![
+      ("root = {}", map(vec![]), map(vec![("a", 1)])),
+      ("root = []", array(vec![]), array(vec![1])),
+      ("root = { ? 1 => uint }", map(vec![]), map(vec![("a", 1)])),
+      (
+        "root = { empty }\nempty = ()",
+        map(vec![]),
+        map(vec![("a", 1)]),
+      ),
+      (
+        "root = [ empty ]\nempty = ()",
+        array(vec![]),
+        array(vec![1]),
+      ),
+      (
+        "root = { opts }\nopts = ( ? a: uint )",
+        map(vec![]),
+        map(vec![("b", 1)]),
+      ),
+      (
+        "root = [ opts ]\nopts = ( ? uint )",
+        array(vec![]),
+        array(vec![1, 2]),
+      ),
+      ("root = [ ( * uint ) ]", array(vec![]), map(vec![])),
+    ] {
+      validate_cbor_from_slice(cddl_input, &serde_cbor::to_vec(&valid).unwrap())?;
+
+      assert!(
+        validate_cbor_from_slice(cddl_input, &serde_cbor::to_vec(&invalid).unwrap()).is_err(),
+        "{} {:?}",
+        cddl_input,
+        invalid
+      );
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_array_segments() -> Result {
+    let cddl_input = r#"root = [* int, + bstr]"#;
+    let cbor = |elements: Vec<Value>| serde_cbor::to_vec(&Value::Array(elements)).unwrap();
+
+    validate_cbor_from_slice(
+      cddl_input,
+      &cbor(vec![
+        Value::Integer(1),
+        Value::Bytes(vec![1]),
+        Value::Bytes(vec![]),
+      ]),
+    )?;
+
+    assert!(validate_cbor_from_slice(
+      cddl_input,
+      &cbor(vec![
+        Value::Bytes(vec![1]),
+        Value::Integer(1),
+        Value::Bytes(vec![])
+      ])
+    )
+    .is_err());
+    assert!(validate_cbor_from_slice(cddl_input, &cbor(vec![Value::Integer(1)])).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_tagged_date_and_time() -> Result {
+    let cddl_input = r#"event = [tdate, time, ? duration]"#;
+
+    for valid in &[
+      r#"[0("2013-03-21T20:04:00Z"), 1(1363896240)]"#,
+      r#"[0("2013-03-21T20:04:00Z"), 1(1363896240.5)]"#,
+      r#"[0("2013-03-21T20:04:00Z"), 1(1363896240), 1002({1: 3600})]"#,
+      r#"[0("2013-03-21T20:04:00Z"), 1(1363896240), "P1D"]"#,
+    ] {
+      validate_cbor_diag_from_str(cddl_input, valid)?;
+    }
+
+    for invalid in &[
+      r#"[0("2013-03-21"), 1(1363896240)]"#,
+      r#"[1("2013-03-21T20:04:00Z"), 1(1363896240)]"#,
+      r#"["2013-03-21T20:04:00Z", 1(1363896240)]"#,
+      r#"[0("2013-03-21T20:04:00Z"), 1363896240.5]"#,
+      r#"[0("2013-03-21T20:04:00Z"), 0(1363896240)]"#,
+      r#"[0("2013-03-21T20:04:00Z"), 1(NaN)]"#,
+      r#"[0("2013-03-21T20:04:00Z"), Infinity]"#,
+      r#"[0("2013-03-21T20:04:00Z"), 1(1363896240), 1002({-9: 1})]"#,
+      r#"[0("2013-03-21T20:04:00Z"), 1(1363896240), 1002(3600)]"#,
+    ] {
+      assert!(
+        validate_cbor_diag_from_str(cddl_input, invalid).is_err(),
+        "{}",
+        invalid
+      );
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_tags_from_slice() -> Result {
+    // 32("http://x.y"), 33("http://x.y") and "http://x.y"
+    let uri = b"\xd8\x20\x6ahttp://x.y";
+    let wrong_tag = b"\xd8\x21\x6ahttp://x.y";
+    let untagged = b"\x6ahttp://x.y";
+
+    for cddl_input in &[r#"root = #6.32(tstr)"#, r#"root = uri"#] {
+      validate_cbor_from_slice(cddl_input, uri)?;
+      assert!(validate_cbor_from_slice(cddl_input, wrong_tag).is_err());
+      assert!(validate_cbor_from_slice(cddl_input, untagged).is_err());
+    }
+
+    // Any tag is accepted by #6, but the data item must still be tagged
+    validate_cbor_from_slice(r#"root = #6(tstr)"#, wrong_tag)?;
+    assert!(validate_cbor_from_slice(r#"root = #6(tstr)"#, untagged).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_non_finite_floats() -> Result {
+    let cddl_input = r#"sample = [float, float16, number, ? #7.27]"#;
+    let mut cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    let samples = ["[NaN, Infinity, -Infinity]", "[1.5, 0.5, 2]"];
+    for sample in &samples {
+      cddl.validate(&edn::parse(sample).unwrap())?;
+    }
+
+    cddl.set_validation_options(ValidationOptions {
+      non_finite_floats: NonFiniteFloats::Reject,
+      ..Default::default()
+    });
+    assert!(cddl.validate(&edn::parse(samples[0]).unwrap()).is_err());
+    cddl.validate(&edn::parse(samples[1]).unwrap())?;
+    // Major type constraints still match them
+    cddl.validate(&edn::parse("[1.5, 0.5, 2, NaN]").unwrap())?;
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_float_literal_comparison() -> Result {
+    let cddl_input = r#"ratio = 0.3 / 0.0"#;
+    let mut cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    // 0.1 + 0.2 is a unit in the last place away from 0.3
+    let sum = Value::Float(0.1 + 0.2);
+    cddl.validate(&Value::Float(0.3))?;
+    cddl.validate(&Value::Float(-0.0))?;
+    assert!(cddl.validate(&sum).is_err());
+    assert!(cddl.validate(&Value::Float(f64::NAN)).is_err());
+
+    cddl.set_validation_options(ValidationOptions {
+      float_comparison: FloatComparison::Ulps(1),
+      ..Default::default()
+    });
+    cddl.validate(&sum)?;
+    cddl.validate(&Value::Float(-0.0))?;
+    assert!(cddl.validate(&Value::Float(0.3 + 1e-15)).is_err());
+    assert!(cddl.validate(&Value::Float(f64::NAN)).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_ranges_and_controls() -> Result {
+    let cddl_input = r#"reading = {
+      temp: -40..85,
+      ratio: 0.0...1.0,
+      id: bstr .size 4,
+      ? label: bstr .size (1..3),
+      ? unit: tstr .pcre "[A-Z]+",
+      count: uint .lt 10,
+    }"#;
+
+    validate_cbor_diag_from_str(
+      cddl_input,
+      r#"{"temp": 21, "ratio": 0.5, "id": h'01020304', "label": h'01', "unit": "C", "count": 3}"#,
+    )?;
+
+    for invalid in &[
+      r#"{"temp": 90, "ratio": 0.5, "id": h'01020304', "count": 3}"#,
+      r#"{"temp": 21, "ratio": 1.0, "id": h'01020304', "count": 3}"#,
+      r#"{"temp": 21, "ratio": 0.5, "id": h'0102', "count": 3}"#,
+      r#"{"temp": 21, "ratio": 0.5, "id": h'01020304', "unit": "c", "count": 3}"#,
+      r#"{"temp": 21, "ratio": 0.5, "id": h'01020304', "count": 10}"#,
+      r#"{"temp": 21, "ratio": 0.5, "id": h'01020304', "label": h'', "count": 3}"#,
+    ] {
+      assert!(validate_cbor_diag_from_str(cddl_input, invalid).is_err());
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_numeric_controls_of_any_numeric_type() -> Result {
+    validate_cbor_diag_from_str(r#"root = int .lt 10"#, r#"-5"#)?;
+    validate_cbor_diag_from_str(r#"root = int .le 10"#, r#"10"#)?;
+    validate_cbor_diag_from_str(r#"root = int .eq 5"#, r#"5"#)?;
+    validate_cbor_diag_from_str(r#"root = float .lt 10"#, r#"9.5"#)?;
+
+    for (cddl_input, diag) in &[
+      (r#"root = int .lt 10"#, r#"10"#),
+      (r#"root = int .le 10"#, r#"11"#),
+      (r#"root = int .eq 5"#, r#"6"#),
+      (r#"root = int .lt 10"#, r#"5.5"#),
+      (r#"root = float .lt 10"#, r#"10.5"#),
+      (r#"root = uint .le 10"#, r#"-1"#),
+    ] {
+      assert!(
+        validate_cbor_diag_from_str(cddl_input, diag).is_err(),
+        "{} {}",
+        cddl_input,
+        diag
+      );
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_ne_control() -> Result {
+    validate_cbor_diag_from_str(r#"port = uint .ne 0"#, "8080")?;
+    validate_cbor_diag_from_str(r#"name = tstr .ne "root""#, r#""admin""#)?;
+    validate_cbor_diag_from_str(r#"ratio = float .ne 1.0"#, "0.5")?;
+
+    for (cddl_input, diag) in &[
+      (r#"port = uint .ne 0"#, "0"),
+      (r#"port = uint .ne 0"#, r#""8080""#),
+      (r#"name = tstr .ne "root""#, r#""root""#),
+      (r#"ratio = float .ne 1.0"#, "1.0"),
+    ] {
+      match validate_cbor_diag_from_str(cddl_input, diag) {
+        Err(e) => assert!(e.into_errors().iter().all(|e| match e {
+          Error::Control(_) => true,
+          _ => false,
+        })),
+        Ok(()) => panic!("{} accepted {}", cddl_input, diag),
+      }
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  #[cfg(not(feature = "json-validation"))]
+  fn validate_unsupported_control() {
+    let cddl_input = r#"config = tstr .json { name: tstr }"#;
+
+    let error = validate_cbor_diag_from_str(cddl_input, r#""{\"name\": \"a\"}""#).unwrap_err();
+    assert!(error.into_errors().iter().any(|e| match e {
+      Error::Control(e) =>
+        e.expected.value == "tstr .json {name: tstr}, which can't be checked when validating CBOR",
+      _ => false,
+    }));
+  }
+
+  #[test]
+  fn validate_bits_and_cbor_controls() -> Result {
+    let cddl_input = r#"message = {
+      flags: bstr .bits flag,
+      mode: uint .bits flag,
+      ? payload: bstr .cbor [uint, tstr],
+      ? log: bstr .cborseq [* uint],
+      ? config: tstr .json { name: tstr },
+    }
+    flag = &(fin: 0, syn: 1, ack: 9)"#;
+
+    validate_cbor_diag_from_str(
+      cddl_input,
+      r#"{"flags": h'0302', "mode": 3, "payload": h'82016161', "log": h'0102'}"#,
+    )?;
+    #[cfg(feature = "json-validation")]
+    validate_cbor_diag_from_str(
+      cddl_input,
+      r#"{"flags": h'00', "mode": 0, "config": "{\"name\": \"a\"}"}"#,
+    )?;
+
+    for invalid in &[
+      r#"{"flags": h'04', "mode": 0}"#,
+      r#"{"flags": h'00', "mode": 4}"#,
+      r#"{"flags": h'00', "mode": 0, "payload": h'01'}"#,
+      r#"{"flags": h'00', "mode": 0, "payload": h'8201'}"#,
+      r#"{"flags": h'00', "mode": 0, "log": h'0161'}"#,
+      r#"{"flags": h'00', "mode": 0, "config": "{\"name\": 1}"}"#,
+    ] {
+      assert!(validate_cbor_diag_from_str(cddl_input, invalid).is_err());
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_non_text_map_keys() -> Result {
+    let cddl_input = r#"message = { 1 => tstr, 2: uint, ? h'01' => bool, "text": int }"#;
+
+    validate_cbor_diag_from_str(cddl_input, r#"{1: "a", 2: 3, "text": -1}"#)?;
+    validate_cbor_diag_from_str(cddl_input, r#"{1: "a", 2: 3, h'01': true, "text": -1}"#)?;
+
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"{1: "a", 2: "b", "text": -1}"#).is_err());
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"{1: "a", "text": -1}"#).is_err());
+    assert!(
+      validate_cbor_diag_from_str(cddl_input, r#"{1: "a", 2: 3, h'01': 1, "text": -1}"#).is_err()
+    );
+    assert!(
+      validate_cbor_diag_from_str(cddl_input, r#"{1: "a", 2: 3, "text": -1, 3: 4}"#).is_err()
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_any_and_undefined() -> Result {
+    let cbor_input = r#"[null, true, h'01', 1("a"), {1: 2}, 1.5, [false]]"#;
+
+    validate_cbor_diag_from_str(r#"root = [* any]"#, cbor_input)?;
+    validate_cbor_diag_from_str(r#"root = [* #]"#, cbor_input)?;
+    validate_cbor_diag_from_str(
+      r#"root = { * any => any }"#,
+      r#"{1: null, h'01': true, "a": 2(h'00')}"#,
+    )?;
+    validate_cbor_diag_from_str(r#"root = any"#, r#"false"#)?;
+
+    assert!(validate_cbor_diag_from_str(r#"root = undefined"#, r#"false"#).is_err());
+    assert!(validate_cbor_diag_from_str(r#"root = undefined"#, r#"0"#).is_err());
+
+    // {"a": undefined} and {"a": null}
+    let undefined = [0xa1, 0x61, b'a', 0xf7];
+    let null = [0xa1, 0x61, b'a', 0xf6];
+
+    validate_cbor_from_slice(r#"root = { a: undefined }"#, &undefined)?;
+    validate_cbor_from_slice(r#"root = { a: #7.23 }"#, &undefined)?;
+    validate_cbor_from_slice(r#"root = { a: any }"#, &undefined)?;
+    validate_cbor_from_slice(r#"root = { a: nil }"#, &null)?;
+
+    for cddl_input in &[
+      r#"root = { a: nil }"#,
+      r#"root = { a: null }"#,
+      r#"root = { a: #7.22 }"#,
+      r#"root = { a: #6 }"#,
+    ] {
+      assert!(validate_cbor_from_slice(cddl_input, &undefined).is_err());
+    }
+    assert!(validate_cbor_from_slice(r#"root = { a: undefined }"#, &null).is_err());
+    assert!(validate_cbor_from_slice(r#"root = { a: #7.23 }"#, &null).is_err());
+
+    // Unassigned simple values are only matched by their major type
+    validate_cbor_from_slice(r#"root = #7.16"#, &[0xf0])?;
+    validate_cbor_from_slice(r#"root = #7"#, &[0xf8, 0xff])?;
+    assert!(validate_cbor_from_slice(r#"root = undefined / nil"#, &[0xf0]).is_err());
+    assert!(validate_cbor_from_slice(r#"root = #7.23"#, &[0xf0]).is_err());
+
+    // The tag holding simple values once decoded can't occur in the input
+    assert!(decode_cbor(&[0xdb, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x17]).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_simple_value_rules() -> Result {
+    let cddl_input = r#"settings = [flag, nothing, bool, off / nothing]
+    flag = true
+    off = false
+    nothing = nil"#;
+
+    validate_cbor_diag_from_str(cddl_input, "[true, null, false, false]")?;
+    validate_cbor_diag_from_str(cddl_input, "[true, null, true, null]")?;
+    validate_cbor_diag_from_str(r#"root = [#7.20, #7.21, #7.22]"#, "[false, true, null]")?;
+
+    assert!(validate_cbor_diag_from_str(cddl_input, "[false, null, false, false]").is_err());
+    assert!(validate_cbor_diag_from_str(cddl_input, "[true, false, false, false]").is_err());
+    assert!(validate_cbor_diag_from_str(cddl_input, "[true, null, null, true]").is_err());
+    assert!(validate_cbor_diag_from_str(r#"root = true"#, "1").is_err());
+    assert!(validate_cbor_diag_from_str(r#"root = nil"#, "0").is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_generic_group_rules() -> Result {
+    let cddl_input = r#"protected = { header<int>, ? 4 => bstr }
+    header<t> = (1 => t, 2 => [* tstr])"#;
+
+    validate_cbor_diag_from_str(cddl_input, r#"{1: -7, 2: []}"#)?;
+    validate_cbor_diag_from_str(cddl_input, r#"{1: -7, 2: ["b64"], 4: h'01'}"#)?;
+
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"{1: "ES256", 2: []}"#).is_err());
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"{1: -7}"#).is_err());
+
+    let cddl_input = r#"signed = [signature<bstr>, bstr]
+    signature<t> = (t, { label<int> => t })
+    label<t> = t"#;
+
+    validate_cbor_diag_from_str(cddl_input, r#"[h'01', {1: h'02'}, h'03']"#)?;
+
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"[h'01', {1: 2}, h'03']"#).is_err());
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"[h'01', {"a": h'02'}, h'03']"#).is_err());
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"[h'01', {1: h'02'}]"#).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_optional_inline_groups() -> Result {
+    let cddl_input = r#"message = { ? (1 => int, 2 => tstr), 3 => bool }"#;
+
+    validate_cbor_diag_from_str(cddl_input, r#"{3: true}"#)?;
+    validate_cbor_diag_from_str(cddl_input, r#"{1: -1, 2: "a", 3: true}"#)?;
+
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"{1: -1, 3: true}"#).is_err());
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"{2: "a", 3: true}"#).is_err());
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"{1: "a", 2: "a", 3: true}"#).is_err());
+
+    let cddl_input = r#"message = { + (1 => int, 2 => tstr) }"#;
+    validate_cbor_diag_from_str(cddl_input, r#"{1: -1, 2: "a"}"#)?;
+
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"{}"#).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_open_ended_occurrences() -> Result {
+    let cddl_input = r#"message = [*2 int, 1* (tstr, bool)]"#;
+
+    validate_cbor_diag_from_str(cddl_input, r#"["a", true]"#)?;
+    validate_cbor_diag_from_str(cddl_input, r#"[1, 2, "a", true, "b", false]"#)?;
+
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"[1, 2, 3, "a", true]"#).is_err());
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"[1, 2]"#).is_err());
+
+    let cddl_input = r#"message = [1*2 bstr]"#;
+
+    validate_cbor_diag_from_str(cddl_input, r#"[h'01', h'02']"#)?;
+
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"[]"#).is_err());
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"[h'01', h'02', h'03']"#).is_err());
+
+    let cddl_input = r#"message = { 1 => int, *1 2 => tstr, *1 name: tstr, 2* int => bool }"#;
+
+    validate_cbor_diag_from_str(cddl_input, r#"{1: -1, 3: true, 4: false}"#)?;
+    validate_cbor_diag_from_str(
+      cddl_input,
+      r#"{1: -1, 2: "a", "name": "n", 3: true, 4: false}"#,
+    )?;
+
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"{1: -1, 3: true}"#).is_err());
+    assert!(
+      validate_cbor_diag_from_str(cddl_input, r#"{1: -1, 2: 0, 3: true, 4: false}"#).is_err()
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_optional_group_names() -> Result {
+    let cddl_input = r#"message = { 1 => int, ? extension }
+    extension = (2 => tstr, 3 => uint)"#;
+
+    validate_cbor_diag_from_str(cddl_input, r#"{1: -1}"#)?;
+    validate_cbor_diag_from_str(cddl_input, r#"{1: -1, 2: "a", 3: 4}"#)?;
+
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"{1: -1, 2: "a"}"#).is_err());
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"{1: -1, 3: 4}"#).is_err());
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"{1: -1, 2: "a", 3: -4}"#).is_err());
+
+    let cddl_input = r#"message = { 1 => int, ? extension<bool> }
+    extension<t> = (2 => t)"#;
+
+    validate_cbor_diag_from_str(cddl_input, r#"{1: -1}"#)?;
+    validate_cbor_diag_from_str(cddl_input, r#"{1: -1, 2: true}"#)?;
+
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"{1: -1, 2: 0}"#).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_keyless_entries() -> Result {
+    let cddl_input = r#"pair = [ [int], { x: int } / 1 ]"#;
+
+    validate_cbor_diag_from_str(cddl_input, r#"[[1], {"x": 1}]"#)?;
+    validate_cbor_diag_from_str(cddl_input, r#"[[2], 1]"#)?;
+
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"[[1], {"x": "a"}]"#).is_err());
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"[1, 1]"#).is_err());
+
+    // Nothing in a map can match an entry without a member key
+    validate_cbor_diag_from_str(r#"point = { 1 => int, ? [int] }"#, r#"{1: 1}"#)?;
+    assert!(validate_cbor_diag_from_str(r#"point = { 1 => int, [int] }"#, r#"{1: 1}"#).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_choice_from_group() -> Result {
+    let cddl_input = r#"message = { kind: &kinds, flags: [* &(urgent: 1, &(quiet: 2))] }
+kinds = ( request: 0, response: 1 )"#;
+
+    validate_cbor_diag_from_str(cddl_input, r#"{"kind": 1, "flags": []}"#)?;
+    validate_cbor_diag_from_str(cddl_input, r#"{"kind": 0, "flags": [2, 1]}"#)?;
+
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"{"kind": 2, "flags": []}"#).is_err());
+    assert!(
+      validate_cbor_diag_from_str(cddl_input, r#"{"kind": "request", "flags": []}"#).is_err()
+    );
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"{"kind": 0, "flags": [3]}"#).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_map_keys_of_type() -> Result {
+    let cddl_input = r#"labels = { 1 => tstr, * int => uint, ? bstr => any }"#;
+
+    validate_cbor_diag_from_str(cddl_input, r#"{1: "a", 2: 3, -4: 5}"#)?;
+    validate_cbor_diag_from_str(cddl_input, r#"{1: "a", h'ff': [1]}"#)?;
+
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"{1: "a", 2: "b"}"#).is_err());
+    assert!(validate_cbor_diag_from_str(cddl_input, r#"{1: "a", h'00': 1, h'ff': 2}"#).is_err());
+    assert!(validate_cbor_diag_from_str(r#"labels = { + int => tstr }"#, r#"{}"#).is_err());
+
+    // The member key is rendered once, as part of the entry
+    let errors = validate_cbor_diag_from_str(r#"labels = { + int => tstr }"#, r#"{}"#)
+      .unwrap_err()
+      .into_errors();
+    let e = errors[0].validation_error().unwrap();
+    assert_eq!(
+      (&e.expected.member_key, e.expected.value.as_str(), e.reason),
+      (&None, "+ int => tstr", Reason::MissingEntry)
+    );
+
+    assert!(
+      validate_cbor_diag_from_str(r#"labels = { int => tstr }"#, r#"{1: "a", 2: "b"}"#).is_err()
+    );
+
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+    let errors = cddl
+      .validate_all(&edn::parse(r#"{1: "a", 2: "b", 3: "c"}"#).unwrap())
+      .unwrap_err();
+    let paths = errors
+      .iter()
+      .filter_map(|e| e.validation_error())
+      .map(|e| e.path.as_str())
+      .collect::<Vec<_>>();
+    assert_eq!(paths, ["/2", "/3"]);
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_max_array_length() -> Result {
+    let cddl_input = r#"records = [* [* uint]] / tstr"#;
+    let mut cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+    cddl.set_validation_options(ValidationOptions {
+      max_array_length: Some(3),
+      ..Default::default()
+    });
+
+    cddl.validate(&edn::parse("[[1, 2, 3], []]").unwrap())?;
+
+    for input in ["[[], [], [], []]", "[[1, 2, 3, 4]]"].iter() {
+      match cddl.validate(&edn::parse(input).unwrap()) {
+        Err(Error::Limit { limit, max }) => {
+          assert_eq!(limit, Limit::ArrayLength);
+          assert_eq!(max, 3);
+        }
+        r => panic!("expected array length to be exceeded, got {:?}", r),
+      }
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_cbor_indefinite_lengths() -> Result {
+    let cddl_input = r#"record = { name: "abc", data: bstr, tags: [+ uint] }"#;
+    let mut cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    // {_ "name": (_ "ab", "c"), "data": (_ h'01', h'0203'), "tags": [_ 1, 2]}
+    let record = |name: &[u8]| {
+      let mut cbor = vec![0xbf, 0x64, b'n', b'a', b'm', b'e', 0x7f];
+      cbor.extend_from_slice(name);
+      cbor.extend_from_slice(&[
+        0xff, 0x64, b'd', b'a', b't', b'a', 0x5f, 0x41, 0x01, 0x42, 0x02, 0x03, 0xff, 0x64, b't',
+        b'a', b'g', b's', 0x9f, 0x01, 0x02, 0xff, 0xff,
+      ]);
+      cbor
+    };
+
+    cddl.validate_cbor_slice(&record(&[0x62, b'a', b'b', 0x61, b'c']))?;
+    assert!(cddl
+      .validate_cbor_slice(&record(&[0x62, b'a', b'b', 0x62, b'c', b'd']))
+      .is_err());
+
+    cddl.set_validation_options(ValidationOptions {
+      cbor_definite_lengths: true,
+      ..Default::default()
+    });
+
+    let positions = cddl
+      .validate_cbor_slice(&record(&[0x62, b'a', b'b', 0x61, b'c']))
+      .unwrap_err()
+      .into_errors()
+      .into_iter()
+      .map(|e| match e {
+        Error::Encoding(ee) => {
+          assert_eq!(ee.kind, EncodingViolationKind::IndefiniteLength);
+          ee.position
+        }
+        e => panic!("unexpected error {}", e),
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(positions, [0, 6, 18, 30]);
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_cbor_duplicate_keys() -> Result {
+    let cddl_input = r#"config = { port: uint, * tstr => any }"#;
+    let mut cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    // {"port": 80, "host": "a", "port": "80"}
+    let cbor = [
+      0xa3, 0x64, b'p', b'o', b'r', b't', 0x18, 0x50, 0x64, b'h', b'o', b's', b't', 0x61, b'a',
+      0x64, b'p', b'o', b'r', b't', 0x62, b'8', b'0',
+    ];
+
+    assert!(cddl.validate_cbor_slice(&cbor).is_err());
+
+    cddl.set_validation_options(ValidationOptions {
+      cbor_duplicate_keys: DuplicateKeyPolicy::First,
+      ..Default::default()
+    });
+    cddl.validate_cbor_slice(&cbor)?;
+
+    cddl.set_validation_options(ValidationOptions {
+      cbor_duplicate_keys: DuplicateKeyPolicy::Reject,
+      ..Default::default()
+    });
+    match cddl
+      .validate_cbor_slice(&cbor)
+      .unwrap_err()
+      .into_errors()
+      .first()
+    {
+      Some(Error::DuplicateKey { key, positions }) => {
+        assert_eq!(key, "\"port\"");
+        assert_eq!(positions, &[1, 15]);
+      }
+      e => panic!("expected a duplicate key, got {:?}", e),
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_cbor_deterministic() -> Result {
+    let cddl_input = r#"record = { id: uint, name: tstr }"#;
+    let mut cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+    cddl.set_validation_options(ValidationOptions {
+      cbor_deterministic: true,
+      ..Default::default()
+    });
+
+    // {"id": 1, "name": "a"}
+    cddl.validate_cbor_slice(&[
+      0xa2, 0x62, b'i', b'd', 0x01, 0x64, b'n', b'a', b'm', b'e', 0x61, b'a',
+    ])?;
+
+    // {_ "name": "a", "id": -1}
+    let errors = cddl
+      .validate_cbor_slice(&[
+        0xbf, 0x64, b'n', b'a', b'm', b'e', 0x61, b'a', 0x62, b'i', b'd', 0x20, 0xff,
+      ])
+      .unwrap_err()
+      .into_errors();
+    let violations = errors
+      .iter()
+      .filter_map(|e| match e {
+        Error::Encoding(ee) => Some((ee.position, ee.kind)),
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(
+      violations,
+      [
+        (0, EncodingViolationKind::IndefiniteLength),
+        (8, EncodingViolationKind::UnsortedMapKey),
+      ]
+    );
+    assert!(errors.iter().any(|e| e.validation_error().is_some()));
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_canonical_key_order() -> Result {
+    let cddl_input = r#"record = { 1 => { * tstr => uint }, x: uint }"#;
+    let mut cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    // {1: {"b": 1, "a": 2}, "x": 1}
+    let cbor = [
+      0xa2, 0x01, 0xa2, 0x61, b'b', 0x01, 0x61, b'a', 0x02, 0x61, b'x', 0x01,
+    ];
+    cddl.validate_cbor_slice(&cbor)?;
+
+    cddl.set_validation_options(ValidationOptions {
+      canonical_key_order: true,
+      cbor_deterministic: true,
+      ..Default::default()
+    });
+
+    // {1: {"a": 1}, "x": 1}
+    cddl.validate_cbor_slice(&[0xa2, 0x01, 0xa1, 0x61, b'a', 0x01, 0x61, b'x', 0x01])?;
+
+    // Reported once rather than also as an Error::Encoding
+    match cddl.validate_cbor_slice(&cbor) {
+      Err(Error::UnsortedKey(uk)) => {
+        assert_eq!(uk.key, r#""a""#);
+        assert_eq!(uk.path, "/1/a");
+      }
+      r => panic!("unexpected result {:?}", r),
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_with_diagnostics() -> Result {
+    let cddl_input = r#"names = [* tstr .regexp "[a-z]+"] / tstr .size 3"#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    let diagnostics = cddl
+      .validate_with_diagnostics(&edn::parse(r#"["a"]"#).unwrap())
+      .into_result()
+      .unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Severity::Note);
+    assert_eq!(diagnostics[0].path, "/0");
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_with_trace() -> Result {
+    let cddl_input = r#"id = tstr / uint .size 2"#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    assert_eq!(
+      cddl.explain(&edn::parse("300").unwrap()),
+      "valid\nrule id matched at / 300\n  type choice 2 of 2 `uint .size 2` matched at / 300\n"
+    );
+    assert_eq!(
+      cddl.explain(&edn::parse("h'01'").unwrap()),
+      "invalid\nrule id failed at / [1]\n  type choice 1 of 2 `tstr` failed at / [1]\n  type choice 2 of 2 `uint .size 2` failed at / [1]\n"
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_and_extract() -> Result {
+    let cddl_input = r#"record = [id .capture "id", * entry]
+id = bstr .size 2
+entry = { 1 => tstr }"#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    let extraction =
+      cddl.validate_and_extract(&edn::parse(r#"[h'0102', {1: "a"}, {1: "b"}]"#).unwrap())?;
+
+    assert_eq!(
+      extraction.value_of("id"),
+      Some(&ActualValue::CBOR(Value::Bytes(vec![1, 2])))
+    );
+    assert_eq!(extraction.rules_at("/2"), ["entry"]);
+    assert_eq!(extraction.captures.get("id"), extraction.value_of("id"));
+    assert_eq!(
+      extraction.values_of("entry")[0].deserialize::<BTreeMap<u8, String>>()?,
+      vec![(1, "a".to_string())]
+        .into_iter()
+        .collect::<BTreeMap<_, _>>()
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_serde_value() -> Result {
+    #[derive(serde::Serialize)]
+    struct Reputon {
+      rater: String,
+      rating: f32,
+      tags: Vec<&'static str>,
+      confidence: Option<f32>,
+    }
+
+    let cddl_input = r#"reputon = {
+  rater: tstr,
+  rating: float,
+  tags: [* tag],
+  confidence: float / null,
+}
+
+tag = "fast" / "friendly""#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    cddl.validate_serde(&Reputon {
+      rater: "Ninja".into(),
+      rating: 0.5,
+      tags: vec!["fast"],
+      confidence: None,
+    })?;
+
+    assert!(cddl
+      .validate_serde(&Reputon {
+        rater: "Ninja".into(),
+        rating: 1.0,
+        tags: vec!["slow"],
+        confidence: Some(0.5),
+      })
+      .is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_cbor_diag() -> Result {
+    let cddl_input = r#"reputon = { rater: tstr, rating: float, tags: [* tstr] }"#;
+
+    validate_cbor_diag_from_str(
+      cddl_input,
+      r#"{"rater": "Ninja", "rating": 0.5, "tags": ["fast" / first tag /]}"#,
+    )?;
+
+    assert!(validate_cbor_diag_from_str(
+      cddl_input,
+      r#"{"rater": h'00', "rating": 0.5, "tags": []}"#
+    )
+    .is_err());
+
+    match validate_cbor_diag_from_str(cddl_input, r#"{"rater": "Ninja""#) {
+      Err(Error::Compilation(CompilationError::Target(_))) => Ok(()),
+      result => panic!("unexpected result: {:?}", result),
+    }
+  }
+
+  #[test]
+  fn validate_cbor_seq() -> Result {
+    let mut cbor_seq = Vec::new();
+    for item in [Value::Integer(1), Value::Text("a".into())].iter() {
+      cbor_seq.extend(serde_cbor::to_vec(item).unwrap());
+    }
+
+    validate_cbor_seq_from_slice("log = (version: uint, message: tstr)", &cbor_seq)?;
+    validate_cbor_seq_from_slice("log = [version: uint, message: tstr]", &cbor_seq)?;
+    validate_cbor_seq_from_slice("log = (* tstr)", &[])?;
+
+    assert!(validate_cbor_seq_from_slice("log = (* tstr)", &cbor_seq).is_err());
+    assert!(validate_cbor_seq_from_slice("log = [+ tstr]", &[]).is_err());
+
+    // Truncated data item
+    assert!(
+      validate_cbor_seq_from_slice("log = [* tstr]", &cbor_seq[..cbor_seq.len() - 1]).is_err()
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_with_root() -> Result {
+    let cddl_input = r#"request = [method: tstr, id: uint]
+response = [result: bool, id: uint]"#;
+
+    let response = serde_cbor::to_vec(&(true, 1)).unwrap();
+    validate_cbor_from_slice_for_rule(cddl_input, &response, "response")?;
+    assert!(validate_cbor_from_slice_for_rule(cddl_input, &response, "request").is_err());
+    assert!(validate_cbor_from_slice_for_rule(cddl_input, &response, "error").is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_value_at() -> Result {
+    let cddl_input = r#"reading = { 1: uint, ? 2: [* sample] }
+sample = [time: uint, value: float]"#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    let sample = |time: i128, value: Value| Value::Array(vec![Value::Integer(time), value]);
+    let mut reading = BTreeMap::new();
+    reading.insert(Value::Integer(1), Value::Integer(7));
+    reading.insert(
+      Value::Integer(2),
+      Value::Array(vec![
+        sample(10, Value::Float(0.5)),
+        sample(20, Value::Text("high".to_string())),
+      ]),
+    );
+    let reading = Value::Tag(40, Box::new(Value::Map(reading)));
+
+    // Integer keys are located by the same tokens used in the paths of failures
+    cddl.validate_value_at("sample", "/2/0", &reading)?;
+    let errors = cddl
+      .validate_value_at("sample", "/2/1", &reading)
+      .unwrap_err()
+      .into_errors();
+    let e = errors[0].validation_error().unwrap();
+    assert_eq!(e.path, "/2/1/1");
+
+    match cddl.validate_value_at("sample", "/3", &reading) {
+      Err(Error::Pointer(p)) => assert_eq!(p, "/3"),
+      r => panic!("expected pointer error, got {:?}", r),
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_all_errors() -> Result {
+    let cddl_input = r#"log = [* entry]
+entry = [level: "info" / "error", message: tstr]"#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    let log = Value::Array(vec![
+      Value::Array(vec![Value::Text("debug".into()), Value::Text("a".into())]),
+      Value::Array(vec![Value::Text("info".into()), Value::Text("b".into())]),
+      Value::Array(vec![Value::Text("error".into()), Value::Integer(3)]),
+    ]);
+
+    let errors = cddl.validate_all(&log).unwrap_err();
+    let failures = errors
+      .iter()
+      .map(|e| {
+        let e = e.validation_error().unwrap();
+        (
+          e.path.as_str(),
+          e.rule.as_ref().map(|r| &r[..]),
+          e.expected.value.as_str(),
+        )
+      })
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      failures,
+      [
+        ("/0/0", Some("entry"), "\"info\" / \"error\""),
+        ("/2/1", Some("entry"), "tstr"),
+      ]
+    );
+
+    Ok(())
+  }
 }
@@ -0,0 +1,354 @@
+use super::{Error, Result};
+use crate::{
+  ast::CDDL,
+  lexer, parser,
+  validation::{CompilationError, Validator},
+};
+use bson_crate::{Bson, Decimal128, Document};
+use serde_cbor::Value;
+use std::{collections::BTreeMap, fmt};
+
+/// How Decimal128 values, which have no counterpart in CBOR other than
+/// decimal fractions, are converted. Set via
+/// `ValidationOptions::bson_decimal`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecimalPolicy {
+  /// Decimals are converted to the nearest float, so that they match `number`
+  /// and `float`
+  Float,
+  /// Decimals are converted exactly to decimal fractions (tag 4), so that
+  /// they match the prelude's `decfrac`, e.g. `1.50` to `4([-2, 150])`. NaN
+  /// and the infinities can't be converted.
+  Decfrac,
+  /// Decimals are converted to their text, e.g. `"1.50"`
+  Text,
+  /// Documents containing decimals are rejected
+  Reject,
+}
+
+// Deriving Default for enums isn't supported by the minimum supported Rust
+// version
+#[allow(clippy::derivable_impls)]
+impl Default for DecimalPolicy {
+  fn default() -> Self {
+    DecimalPolicy::Float
+  }
+}
+
+/// Error converting BSON to the CBOR data model
+#[derive(Debug)]
+pub enum BsonError {
+  /// Input isn't a well-formed BSON document
+  Decode(bson_crate::de::Error),
+  /// Value of a deprecated or MongoDB-specific type, e.g. `MinKey`
+  Unsupported(&'static str),
+  /// Decimal128 value rejected by the `DecimalPolicy`
+  Decimal(Decimal128),
+}
+
+impl fmt::Display for BsonError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      BsonError::Decode(e) => write!(f, "error decoding BSON: {}", e),
+      BsonError::Unsupported(kind) => write!(f, "unsupported BSON type {}", kind),
+      BsonError::Decimal(d) => write!(f, "unsupported decimal {}", d),
+    }
+  }
+}
+
+impl std::error::Error for BsonError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      BsonError::Decode(e) => Some(e),
+      _ => None,
+    }
+  }
+}
+
+/// Converts a BSON value to the CBOR data model
+///
+/// Documents become maps keyed by text strings, and binaries and ObjectIds
+/// become byte strings, so that ObjectIds match `bstr .size 12`. Dates
+/// become epoch-based date/times (tag 1) matching `time`, and regular
+/// expressions become tag 35 matching `regexp`, without their options.
+/// Decimal128 values are converted according to the given policy. JavaScript
+/// code and symbols become text strings, and undefined becomes null. Other
+/// deprecated or MongoDB-specific types, such as `MinKey` or internal
+/// timestamps, can't be converted.
+///
+/// # Example
+///
+/// ```
+/// # extern crate bson_crate as bson;
+/// use bson::{oid::ObjectId, Bson};
+/// use cddl::validation::cbor::bson::{bson_to_cbor, DecimalPolicy};
+/// use serde_cbor::Value;
+///
+/// let id = ObjectId::new();
+/// let value = bson_to_cbor(&Bson::ObjectId(id), DecimalPolicy::Float).unwrap();
+///
+/// assert_eq!(value, Value::Bytes(id.bytes().to_vec()));
+/// ```
+pub fn bson_to_cbor(value: &Bson, decimal: DecimalPolicy) -> std::result::Result<Value, BsonError> {
+  Ok(match value {
+    Bson::Null | Bson::Undefined => Value::Null,
+    Bson::Boolean(b) => Value::Bool(*b),
+    Bson::Int32(i) => Value::Integer(i128::from(*i)),
+    Bson::Int64(i) => Value::Integer(i128::from(*i)),
+    Bson::Double(f) => Value::Float(*f),
+    Bson::Decimal128(d) => decimal_to_cbor(d, decimal)?,
+    Bson::String(s) | Bson::Symbol(s) | Bson::JavaScriptCode(s) => Value::Text(s.clone()),
+    Bson::Binary(b) => Value::Bytes(b.bytes.clone()),
+    Bson::ObjectId(id) => Value::Bytes(id.bytes().to_vec()),
+    Bson::DateTime(dt) => {
+      let millis = dt.timestamp_millis();
+      let time = if millis % 1000 == 0 {
+        Value::Integer(i128::from(millis / 1000))
+      } else {
+        Value::Float(millis as f64 / 1000.0)
+      };
+
+      Value::Tag(1, Box::new(time))
+    }
+    Bson::RegularExpression(regex) => Value::Tag(35, Box::new(Value::Text(regex.pattern.clone()))),
+    Bson::Array(values) => Value::Array(
+      values
+        .iter()
+        .map(|v| bson_to_cbor(v, decimal))
+        .collect::<std::result::Result<_, _>>()?,
+    ),
+    Bson::Document(document) => document_to_cbor(document, decimal)?,
+    Bson::JavaScriptCodeWithScope(_) => {
+      return Err(BsonError::Unsupported("JavaScriptCodeWithScope"))
+    }
+    Bson::Timestamp(_) => return Err(BsonError::Unsupported("Timestamp")),
+    Bson::MaxKey => return Err(BsonError::Unsupported("MaxKey")),
+    Bson::MinKey => return Err(BsonError::Unsupported("MinKey")),
+    Bson::DbPointer(_) => return Err(BsonError::Unsupported("DbPointer")),
+  })
+}
+
+fn document_to_cbor(
+  document: &Document,
+  decimal: DecimalPolicy,
+) -> std::result::Result<Value, BsonError> {
+  let mut map = BTreeMap::new();
+  for (k, v) in document {
+    map.insert(Value::Text(k.clone()), bson_to_cbor(v, decimal)?);
+  }
+
+  Ok(Value::Map(map))
+}
+
+fn decimal_to_cbor(d: &Decimal128, policy: DecimalPolicy) -> std::result::Result<Value, BsonError> {
+  let text = d.to_string();
+
+  match policy {
+    // Rust parses the notation used for decimals, including `NaN` and
+    // `Infinity`
+    DecimalPolicy::Float => text
+      .parse()
+      .map(Value::Float)
+      .map_err(|_| BsonError::Decimal(*d)),
+    DecimalPolicy::Decfrac => match decimal_fraction(&text) {
+      Some((exponent, mantissa)) => Ok(Value::Tag(
+        4,
+        Box::new(Value::Array(vec![
+          Value::Integer(exponent),
+          Value::Integer(mantissa),
+        ])),
+      )),
+      None => Err(BsonError::Decimal(*d)),
+    },
+    DecimalPolicy::Text => Ok(Value::Text(text)),
+    DecimalPolicy::Reject => Err(BsonError::Decimal(*d)),
+  }
+}
+
+// Splits the text of a finite decimal, e.g. `-1.50` or `1.2E+7`, into its
+// base 10 exponent and its mantissa
+fn decimal_fraction(text: &str) -> Option<(i128, i128)> {
+  let (number, exponent) = match text.find('E') {
+    Some(idx) => (&text[..idx], text[idx + 1..].parse::<i128>().ok()?),
+    None => (text, 0),
+  };
+
+  let (integer, fraction) = match number.find('.') {
+    Some(idx) => (&number[..idx], &number[idx + 1..]),
+    None => (number, ""),
+  };
+
+  // A Decimal128 has at most 34 digits, which always fit in an i128
+  let mantissa = format!("{}{}", integer, fraction).parse::<i128>().ok()?;
+
+  Some((exponent - fraction.len() as i128, mantissa))
+}
+
+impl<'a> CDDL<'a> {
+  /// Validates a BSON document against the root rule, as if it were the
+  /// equivalent CBOR map. See `bson_to_cbor` for how BSON is converted, and
+  /// `ValidationOptions::bson_decimal` for how Decimal128 values are.
+  pub fn validate_bson_document(&self, document: &Document) -> Result {
+    let value = document_to_cbor(document, self.validation_options.bson_decimal)
+      .map_err(|e| Error::Target(e.into()))?;
+
+    Validator::validate(self, &value)
+  }
+
+  /// Validates an encoded BSON document against the root rule. See
+  /// `CDDL::validate_bson_document`.
+  pub fn validate_bson_slice(&self, mut bson: &[u8]) -> Result {
+    let document =
+      Document::from_reader(&mut bson).map_err(|e| Error::Target(BsonError::Decode(e).into()))?;
+
+    self.validate_bson_document(&document)
+  }
+}
+
+/// Validates an encoded BSON document against given CDDL input
+///
+/// # Example
+///
+/// ```
+/// # extern crate bson_crate as bson;
+/// use bson::doc;
+/// use cddl::validate_bson_from_slice;
+///
+/// let cddl_input = "user = { _id: bstr .size 12, name: tstr }";
+///
+/// let mut bson = Vec::new();
+/// doc! { "_id": bson::oid::ObjectId::new(), "name": "Alice" }
+///   .to_writer(&mut bson)
+///   .unwrap();
+/// assert!(validate_bson_from_slice(cddl_input, &bson).is_ok());
+/// ```
+pub fn validate_bson_from_slice(cddl_input: &str, bson: &[u8]) -> Result {
+  parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+    .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?
+    .validate_bson_slice(bson)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::validation::ValidationOptions;
+  use bson_crate::{doc, oid::ObjectId, DateTime, Regex};
+
+  fn decimal(text: &str) -> Decimal128 {
+    text.parse().unwrap()
+  }
+
+  #[test]
+  fn verify_bson_to_cbor() {
+    let id = ObjectId::new();
+    let document = doc! {
+      "_id": id,
+      "count": 3i32,
+      "total": 3i64,
+      "ratio": 0.5,
+      "at": DateTime::from_millis(1_600_000_000_500),
+      "tags": ["a", Bson::Null],
+      "pattern": Regex { pattern: "^a".to_string(), options: "i".to_string() },
+    };
+
+    let value = bson_to_cbor(&Bson::Document(document), DecimalPolicy::default()).unwrap();
+    let map = match value {
+      Value::Map(map) => map,
+      value => panic!("unexpected value {:?}", value),
+    };
+    let entry = |key: &str| map[&Value::Text(key.to_string())].clone();
+
+    assert_eq!(entry("_id"), Value::Bytes(id.bytes().to_vec()));
+    assert_eq!(entry("count"), Value::Integer(3));
+    assert_eq!(entry("total"), Value::Integer(3));
+    assert_eq!(entry("ratio"), Value::Float(0.5));
+    assert_eq!(
+      entry("at"),
+      Value::Tag(1, Box::new(Value::Float(1_600_000_000.5)))
+    );
+    assert_eq!(
+      entry("tags"),
+      Value::Array(vec![Value::Text("a".to_string()), Value::Null])
+    );
+    assert_eq!(
+      entry("pattern"),
+      Value::Tag(35, Box::new(Value::Text("^a".to_string())))
+    );
+
+    assert!(
+      match bson_to_cbor(&Bson::MinKey, DecimalPolicy::default()) {
+        Err(BsonError::Unsupported("MinKey")) => true,
+        _ => false,
+      }
+    );
+  }
+
+  #[test]
+  fn verify_decimal_policies() {
+    let d = Bson::Decimal128(decimal("-1.50"));
+
+    assert_eq!(
+      bson_to_cbor(&d, DecimalPolicy::Float).unwrap(),
+      Value::Float(-1.5)
+    );
+    assert_eq!(
+      bson_to_cbor(&d, DecimalPolicy::Decfrac).unwrap(),
+      Value::Tag(
+        4,
+        Box::new(Value::Array(vec![Value::Integer(-2), Value::Integer(-150)]))
+      )
+    );
+    assert_eq!(
+      bson_to_cbor(&d, DecimalPolicy::Text).unwrap(),
+      Value::Text("-1.50".to_string())
+    );
+    assert!(bson_to_cbor(&d, DecimalPolicy::Reject).is_err());
+
+    assert_eq!(decimal_fraction("1.2E+7"), Some((6, 12)));
+    assert_eq!(decimal_fraction("1E-10"), Some((-10, 1)));
+    assert_eq!(decimal_fraction("42"), Some((0, 42)));
+    assert!(bson_to_cbor(&Bson::Decimal128(decimal("NaN")), DecimalPolicy::Decfrac).is_err());
+  }
+
+  #[test]
+  fn validate_bson() {
+    let cddl = r#"
+      order = { _id: bstr .size 12, placed: time, total: decfrac / number }
+    "#;
+
+    let mut bson = Vec::new();
+    doc! {
+      "_id": ObjectId::new(),
+      "placed": DateTime::from_millis(1_600_000_000_000),
+      "total": decimal("19.99"),
+    }
+    .to_writer(&mut bson)
+    .unwrap();
+
+    let mut cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl), cddl, false).unwrap();
+    assert!(cddl.validate_bson_slice(&bson).is_ok());
+
+    for policy in [DecimalPolicy::Decfrac, DecimalPolicy::Float].iter() {
+      cddl.set_validation_options(ValidationOptions {
+        bson_decimal: *policy,
+        ..Default::default()
+      });
+      assert!(cddl.validate_bson_slice(&bson).is_ok());
+    }
+
+    cddl.set_validation_options(ValidationOptions {
+      bson_decimal: DecimalPolicy::Text,
+      ..Default::default()
+    });
+    let errors = cddl.validate_bson_slice(&bson).unwrap_err().into_errors();
+    assert_eq!(
+      errors[0].validation_error().map(|e| e.path.as_str()),
+      Some("/total")
+    );
+
+    assert!(match cddl.validate_bson_slice(&bson[1..]) {
+      Err(Error::Target(_)) => true,
+      _ => false,
+    });
+  }
+}
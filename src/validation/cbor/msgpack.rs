@@ -0,0 +1,290 @@
+use super::{Error, Result};
+use crate::{ast::CDDL, lexer, parser, validation::CompilationError};
+use serde_cbor::Value;
+use std::{collections::BTreeMap, convert::TryInto, fmt};
+
+// Extension type reserved by the MessagePack specification for timestamps
+const TIMESTAMP_EXT: i8 = -1;
+
+/// Error converting MessagePack to the CBOR data model
+#[derive(Debug)]
+pub enum MsgpackError {
+  /// Input isn't well-formed MessagePack
+  Decode(rmpv::decode::Error),
+  /// Input continues after the first data item, at the given offset
+  TrailingData(usize),
+  /// String that isn't valid UTF-8
+  InvalidUtf8,
+  /// Timestamp extension whose data has an invalid length
+  Timestamp(usize),
+  /// Extension type other than timestamps, whose meaning is defined by
+  /// applications
+  Extension(i8),
+}
+
+impl fmt::Display for MsgpackError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      MsgpackError::Decode(e) => write!(f, "error decoding MessagePack: {}", e),
+      MsgpackError::TrailingData(offset) => {
+        write!(
+          f,
+          "unexpected data after the first item at offset {}",
+          offset
+        )
+      }
+      MsgpackError::InvalidUtf8 => write!(f, "string isn't valid UTF-8"),
+      MsgpackError::Timestamp(len) => write!(f, "timestamp of invalid length {}", len),
+      MsgpackError::Extension(t) => write!(f, "unsupported extension type {}", t),
+    }
+  }
+}
+
+impl std::error::Error for MsgpackError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      MsgpackError::Decode(e) => Some(e),
+      _ => None,
+    }
+  }
+}
+
+/// Decodes a MessagePack data item into the CBOR data model, which it's a
+/// subset of. Strings, binaries, arrays and maps become text strings, byte
+/// strings, arrays and maps, and timestamps become epoch-based date/time
+/// values (tag 1), so that they match `time`. Extension types other than
+/// timestamps are rejected.
+///
+/// # Example
+///
+/// ```
+/// use cddl::validation::cbor::msgpack::msgpack_to_cbor;
+/// use serde_cbor::Value;
+///
+/// // ["a", 1]
+/// assert_eq!(
+///   msgpack_to_cbor(&[0x92, 0xa1, 0x61, 0x01]).unwrap(),
+///   Value::Array(vec![Value::Text("a".to_string()), Value::Integer(1)])
+/// );
+/// ```
+pub fn msgpack_to_cbor(msgpack: &[u8]) -> std::result::Result<Value, MsgpackError> {
+  let mut reader = msgpack;
+  let value = rmpv::decode::read_value(&mut reader).map_err(MsgpackError::Decode)?;
+
+  if !reader.is_empty() {
+    return Err(MsgpackError::TrailingData(msgpack.len() - reader.len()));
+  }
+
+  convert(value)
+}
+
+fn convert(value: rmpv::Value) -> std::result::Result<Value, MsgpackError> {
+  Ok(match value {
+    rmpv::Value::Nil => Value::Null,
+    rmpv::Value::Boolean(b) => Value::Bool(b),
+    rmpv::Value::Integer(i) => Value::Integer(match i.as_i64() {
+      Some(i) => i128::from(i),
+      // Integers that don't fit in an i64 are positive and fit in a u64
+      None => i128::from(i.as_u64().unwrap_or_default()),
+    }),
+    rmpv::Value::F32(f) => Value::Float(f64::from(f)),
+    rmpv::Value::F64(f) => Value::Float(f),
+    rmpv::Value::String(s) => Value::Text(s.into_str().ok_or(MsgpackError::InvalidUtf8)?),
+    rmpv::Value::Binary(b) => Value::Bytes(b),
+    rmpv::Value::Array(values) => Value::Array(
+      values
+        .into_iter()
+        .map(convert)
+        .collect::<std::result::Result<_, _>>()?,
+    ),
+    rmpv::Value::Map(entries) => {
+      // Like serde_cbor, the last entry with each key is kept
+      let mut map = BTreeMap::new();
+      for (k, v) in entries {
+        map.insert(convert(k)?, convert(v)?);
+      }
+      Value::Map(map)
+    }
+    rmpv::Value::Ext(TIMESTAMP_EXT, data) => timestamp(&data)?,
+    rmpv::Value::Ext(t, _) => return Err(MsgpackError::Extension(t)),
+  })
+}
+
+// Converts the data of a timestamp extension, in any of its three formats,
+// to an epoch-based date/time
+fn timestamp(data: &[u8]) -> std::result::Result<Value, MsgpackError> {
+  let (seconds, nanoseconds) = match data.len() {
+    4 => (i64::from(u32::from_be_bytes(be(data))), 0),
+    8 => {
+      let n = u64::from_be_bytes(be(data));
+      ((n & 0x3_ffff_ffff) as i64, (n >> 34) as u32)
+    }
+    12 => (
+      i64::from_be_bytes(be(&data[4..])),
+      u32::from_be_bytes(be(&data[..4])),
+    ),
+    len => return Err(MsgpackError::Timestamp(len)),
+  };
+
+  let time = if nanoseconds == 0 {
+    Value::Integer(i128::from(seconds))
+  } else {
+    Value::Float(seconds as f64 + f64::from(nanoseconds) / 1e9)
+  };
+
+  Ok(Value::Tag(1, Box::new(time)))
+}
+
+fn be<A>(bytes: &[u8]) -> A
+where
+  for<'b> &'b [u8]: TryInto<A>,
+{
+  // Only called with slices of the length of the array
+  match bytes.try_into() {
+    Ok(array) => array,
+    Err(_) => unreachable!(),
+  }
+}
+
+impl<'a> CDDL<'a> {
+  /// Validates an encoded MessagePack data item against the root rule, as if
+  /// it were the equivalent CBOR data item. See `msgpack_to_cbor` for how
+  /// MessagePack is converted.
+  pub fn validate_msgpack_slice(&self, msgpack: &[u8]) -> Result {
+    let value = msgpack_to_cbor(msgpack).map_err(|e| Error::Target(e.into()))?;
+
+    crate::validation::Validator::validate(self, &value)
+  }
+}
+
+/// Validates an encoded MessagePack data item against given CDDL input
+///
+/// # Example
+///
+/// ```
+/// use cddl::validate_msgpack_from_slice;
+///
+/// let cddl_input = "reading = [sensor: uint, value: float]";
+///
+/// // [1, 21.5]
+/// let msgpack = [0x92, 0x01, 0xcb, 0x40, 0x35, 0x80, 0, 0, 0, 0, 0];
+/// assert!(validate_msgpack_from_slice(cddl_input, &msgpack).is_ok());
+/// ```
+pub fn validate_msgpack_from_slice(cddl_input: &str, msgpack: &[u8]) -> Result {
+  parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+    .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?
+    .validate_msgpack_slice(msgpack)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn verify_msgpack_to_cbor() {
+    // {"a": [nil, true, -1, 255, 1.5], h'01': "x"}
+    let msgpack = [
+      0x82, 0xa1, 0x61, 0x95, 0xc0, 0xc3, 0xff, 0xcc, 0xff, 0xca, 0x3f, 0xc0, 0x00, 0x00, 0xc4,
+      0x01, 0x01, 0xa1, 0x78,
+    ];
+
+    let mut map = BTreeMap::new();
+    map.insert(
+      Value::Text("a".to_string()),
+      Value::Array(vec![
+        Value::Null,
+        Value::Bool(true),
+        Value::Integer(-1),
+        Value::Integer(255),
+        Value::Float(1.5),
+      ]),
+    );
+    map.insert(Value::Bytes(vec![1]), Value::Text("x".to_string()));
+    assert_eq!(msgpack_to_cbor(&msgpack).unwrap(), Value::Map(map));
+
+    // 2^64 - 1
+    assert_eq!(
+      msgpack_to_cbor(&[0xcf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]).unwrap(),
+      Value::Integer(0xffff_ffff_ffff_ffff)
+    );
+
+    assert!(match msgpack_to_cbor(&[0x01, 0x02]) {
+      Err(MsgpackError::TrailingData(1)) => true,
+      _ => false,
+    });
+    assert!(match msgpack_to_cbor(&[0x92, 0x01]) {
+      Err(MsgpackError::Decode(_)) => true,
+      _ => false,
+    });
+    assert!(match msgpack_to_cbor(&[0xa1, 0xff]) {
+      Err(MsgpackError::InvalidUtf8) => true,
+      _ => false,
+    });
+    assert!(match msgpack_to_cbor(&[0xd4, 0x05, 0x00]) {
+      Err(MsgpackError::Extension(5)) => true,
+      _ => false,
+    });
+  }
+
+  #[test]
+  fn verify_msgpack_timestamps() {
+    let tag = |v| Value::Tag(1, Box::new(v));
+
+    // timestamp 32
+    assert_eq!(
+      msgpack_to_cbor(&[0xd6, 0xff, 0x5f, 0x5e, 0x10, 0x00]).unwrap(),
+      tag(Value::Integer(1_600_000_000))
+    );
+    // timestamp 64 with half a second
+    let n = (500_000_000u64 << 34) | 1_600_000_000;
+    let mut msgpack = vec![0xd7, 0xff];
+    msgpack.extend_from_slice(&n.to_be_bytes());
+    assert_eq!(
+      msgpack_to_cbor(&msgpack).unwrap(),
+      tag(Value::Float(1_600_000_000.5))
+    );
+    // timestamp 96 before the epoch
+    let mut msgpack = vec![0xc7, 0x0c, 0xff, 0, 0, 0, 0];
+    msgpack.extend_from_slice(&(-1i64).to_be_bytes());
+    assert_eq!(msgpack_to_cbor(&msgpack).unwrap(), tag(Value::Integer(-1)));
+
+    assert!(match msgpack_to_cbor(&[0xd5, 0xff, 0x00, 0x00]) {
+      Err(MsgpackError::Timestamp(2)) => true,
+      _ => false,
+    });
+  }
+
+  #[test]
+  fn validate_msgpack() {
+    let cddl = r#"
+      event = { name: tstr, at: time, ? payload: bstr }
+    "#;
+
+    // {"name": "boot", "at": timestamp 32}
+    let mut msgpack = vec![0x82, 0xa4];
+    msgpack.extend_from_slice(b"name");
+    msgpack.extend_from_slice(&[0xa4]);
+    msgpack.extend_from_slice(b"boot");
+    msgpack.extend_from_slice(&[0xa2]);
+    msgpack.extend_from_slice(b"at");
+    msgpack.extend_from_slice(&[0xd6, 0xff, 0x5f, 0x5e, 0x10, 0x00]);
+    assert!(validate_msgpack_from_slice(cddl, &msgpack).is_ok());
+
+    // Timestamps are only accepted where times are expected
+    let msgpack = [
+      0x82, 0xa4, b'n', b'a', b'm', b'e', 0xd6, 0xff, 0, 0, 0, 0, 0xa2, b'a', b't', 0x00,
+    ];
+    let errors = validate_msgpack_from_slice(cddl, &msgpack)
+      .unwrap_err()
+      .into_errors();
+    assert_eq!(
+      errors[0].validation_error().map(|e| e.path.as_str()),
+      Some("/name")
+    );
+
+    assert!(match validate_msgpack_from_slice(cddl, &[0x82, 0xa4]) {
+      Err(Error::Target(_)) => true,
+      _ => false,
+    });
+  }
+}
@@ -0,0 +1,360 @@
+use super::from_core;
+use crate::{
+  ast::*,
+  cbor_core::decode_prefix,
+  validation::{
+    at, repeated_entry, repeated_group_entry, report_lazily, validate_occurrence_count, Error,
+    RepeatedEntry, Result,
+  },
+};
+use futures_core::Stream;
+use serde_cbor::Value;
+use std::{
+  pin::Pin,
+  result,
+  task::{Context, Poll},
+};
+use tokio_crate::io::{AsyncRead, ReadBuf};
+
+// Number of bytes requested from the reader at a time
+const CHUNK_SIZE: usize = 4096;
+
+impl<'a> CDDL<'a> {
+  /// Decodes and validates the data items of a CBOR sequence (RFC 8742) as
+  /// they're read from the given reader, e.g. as they arrive over the network.
+  /// Requires the `tokio` feature.
+  ///
+  /// The data items are matched against the CDDL as with
+  /// `CDDL::validate_cbor_seq`. If the sequence is described by a single
+  /// repeated entry, e.g. `log = (* entry)` or `log = [* entry]`, each item is
+  /// validated as soon as it's decoded and the returned stream yields a result
+  /// per item, followed by an `Error::Occurrence` if the sequence ends with too
+  /// few or too many items. Otherwise the items are validated as a whole once
+  /// the reader is exhausted and the stream yields a single result.
+  ///
+  /// Failures are located by the index of the item within the sequence. The
+  /// stream ends after yielding an error for data that can't be decoded or
+  /// that can't be read.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{lexer_from_str, parser::cddl_from_str};
+  /// use futures_core::Stream;
+  /// use std::{pin::Pin, task::Poll};
+  /// # use std::task::{Context, RawWaker, RawWakerVTable, Waker};
+  /// # fn noop(_: *const ()) {}
+  /// # fn clone(_: *const ()) -> RawWaker {
+  /// #   RawWaker::new(std::ptr::null(), &VTABLE)
+  /// # }
+  /// # static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+  ///
+  /// let input = r#"log = (* entry)  entry = { message: tstr }"#;
+  /// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+  ///
+  /// // { "message": "started" } followed by { "message": 1 }
+  /// let cbor_seq: &[u8] = b"\xa1\x67message\x67started\xa1\x67message\x01";
+  /// let mut stream = cddl.validate_cbor_stream(cbor_seq);
+  ///
+  /// # let waker = unsafe { Waker::from_raw(clone(std::ptr::null())) };
+  /// # let mut cx = Context::from_waker(&waker);
+  /// // Usually driven by an async runtime, e.g. via `StreamExt::next`
+  /// let mut next = || match Pin::new(&mut stream).poll_next(&mut cx) {
+  ///   Poll::Ready(item) => item,
+  ///   Poll::Pending => unreachable!("slices are always ready"),
+  /// };
+  /// assert!(next().unwrap().is_ok());
+  /// assert!(next().unwrap().is_err());
+  /// assert!(next().is_none());
+  /// ```
+  pub fn validate_cbor_stream<R: AsyncRead + Unpin>(&self, reader: R) -> CborStream<'a, '_, R> {
+    CborStream {
+      cddl: self,
+      entry: streamed_entry(self),
+      reader,
+      buffer: Vec::new(),
+      items: Vec::new(),
+      count: 0,
+      is_done: false,
+    }
+  }
+}
+
+/// Stream of the results of validating the data items of a CBOR sequence as
+/// they're read, returned by `CDDL::validate_cbor_stream`
+pub struct CborStream<'a, 'b, R> {
+  cddl: &'b CDDL<'a>,
+  entry: Option<RepeatedEntry<'a, 'b>>,
+  reader: R,
+  // Bytes read but not yet decoded
+  buffer: Vec<u8>,
+  // Data items held until the end of the sequence, if they can't be validated
+  // one at a time
+  items: Vec<Value>,
+  count: usize,
+  is_done: bool,
+}
+
+impl<'a, 'b, R: AsyncRead + Unpin> Stream for CborStream<'a, 'b, R> {
+  type Item = Result;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result>> {
+    let stream = self.get_mut();
+
+    while !stream.is_done {
+      if let Some(value) = stream.decode_item() {
+        match value {
+          Ok(value) => {
+            if let Some(result) = stream.validate_item(value) {
+              return Poll::Ready(Some(result));
+            }
+          }
+          Err(e) => {
+            stream.is_done = true;
+            return Poll::Ready(Some(Err(e)));
+          }
+        }
+
+        continue;
+      }
+
+      let mut chunk = [0; CHUNK_SIZE];
+      let mut buf = ReadBuf::new(&mut chunk);
+      match Pin::new(&mut stream.reader).poll_read(cx, &mut buf) {
+        Poll::Pending => return Poll::Pending,
+        Poll::Ready(Ok(())) if buf.filled().is_empty() => {
+          stream.is_done = true;
+          return Poll::Ready(stream.finish());
+        }
+        Poll::Ready(Ok(())) => stream.buffer.extend_from_slice(buf.filled()),
+        Poll::Ready(Err(e)) => {
+          stream.is_done = true;
+          return Poll::Ready(Some(Err(Error::Target(e.into()))));
+        }
+      }
+    }
+
+    Poll::Ready(None)
+  }
+}
+
+impl<'a, 'b, R> CborStream<'a, 'b, R> {
+  // Decodes the next data item from the bytes read so far, if they include a
+  // complete one
+  fn decode_item(&mut self) -> Option<result::Result<Value, Error>> {
+    if self.buffer.is_empty() {
+      return None;
+    }
+
+    match decode_prefix(&self.buffer) {
+      Ok(Some((value, len))) => {
+        self.buffer.drain(..len);
+
+        Some(from_core(value))
+      }
+      Ok(None) => None,
+      Err(e) => Some(Err(Error::Target(e.into()))),
+    }
+  }
+
+  // Validates a data item if items are validated one at a time, holding onto
+  // it until the end of the sequence otherwise
+  fn validate_item(&mut self, value: Value) -> Option<Result> {
+    let entry = match self.entry {
+      Some(entry) => entry,
+      None => {
+        self.items.push(value);
+        return None;
+      }
+    };
+
+    let idx = self.count;
+    self.count += 1;
+
+    let cddl = self.cddl;
+    Some(cddl.validate_root(|| report_lazily(|| at(idx, || cddl.validate_element(entry, &value)))))
+  }
+
+  // Returns the outcome of reaching the end of the sequence, if there's one to
+  // report
+  fn finish(&mut self) -> Option<Result> {
+    // The sequence ends partway through a data item
+    if !self.buffer.is_empty() {
+      return super::decode_cbor(&self.buffer).err().map(Err);
+    }
+
+    let result = match self.entry {
+      Some(RepeatedEntry::Typename { occur, name, .. }) => {
+        validate_occurrence_count(occur, &name.to_string(), self.count)
+      }
+      Some(RepeatedEntry::Type { occur, t }) => {
+        validate_occurrence_count(occur, &t.to_string(), self.count)
+      }
+      None => return Some(self.cddl.validate_cbor_seq(&self.items)),
+    };
+
+    result.err().map(Err)
+  }
+}
+
+// Returns the repeated entry describing every data item of the sequence, if
+// there is one
+fn streamed_entry<'a, 'b>(cddl: &'b CDDL<'a>) -> Option<RepeatedEntry<'a, 'b>> {
+  match cddl.rules.first()? {
+    Rule::Group { rule, .. } if rule.generic_param.is_none() => match &rule.entry {
+      GroupEntry::InlineGroup {
+        group, occur: None, ..
+      } => repeated_group_entry(cddl, group),
+      _ => None,
+    },
+    Rule::Type { rule, .. } if rule.generic_param.is_none() => repeated_entry(cddl, &rule.value),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{lexer_from_str, parser::cddl_from_str, validation::ValidationError};
+  use std::{
+    io,
+    task::{RawWaker, RawWakerVTable, Waker},
+  };
+
+  static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+  fn clone(_: *const ()) -> RawWaker {
+    RawWaker::new(std::ptr::null(), &VTABLE)
+  }
+
+  fn noop(_: *const ()) {}
+
+  // Reads a byte at a time, with every other read not ready yet
+  struct Trickle<'a> {
+    bytes: &'a [u8],
+    is_ready: bool,
+  }
+
+  impl<'a> AsyncRead for Trickle<'a> {
+    fn poll_read(
+      mut self: Pin<&mut Self>,
+      cx: &mut Context<'_>,
+      buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+      self.is_ready = !self.is_ready;
+      if !self.is_ready {
+        cx.waker().wake_by_ref();
+        return Poll::Pending;
+      }
+
+      if let Some((byte, rest)) = self.bytes.split_first() {
+        buf.put_slice(&[*byte]);
+        self.bytes = rest;
+      }
+
+      Poll::Ready(Ok(()))
+    }
+  }
+
+  // Polls the stream to completion, counting the times it wasn't ready
+  fn poll_to_end<S: Stream<Item = Result> + Unpin>(mut stream: S) -> (Vec<Result>, usize) {
+    let waker = unsafe { Waker::from_raw(clone(std::ptr::null())) };
+    let mut cx = Context::from_waker(&waker);
+
+    let mut results = Vec::new();
+    let mut pending = 0;
+    loop {
+      match Pin::new(&mut stream).poll_next(&mut cx) {
+        Poll::Ready(Some(result)) => results.push(result),
+        Poll::Ready(None) => return (results, pending),
+        Poll::Pending => pending += 1,
+      }
+    }
+  }
+
+  fn cbor_seq(items: &[Value]) -> Vec<u8> {
+    items
+      .iter()
+      .flat_map(|item| serde_cbor::to_vec(item).unwrap())
+      .collect()
+  }
+
+  #[test]
+  fn validate_streamed_items() {
+    let input = r#"log = (* entry)  entry = [time: uint, message: tstr]"#;
+    let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+
+    let entry = |time, message: Value| Value::Array(vec![Value::Integer(time), message]);
+    let bytes = cbor_seq(&[
+      entry(1, Value::Text("started".into())),
+      entry(2, Value::Integer(3)),
+      entry(3, Value::Text("stopped".into())),
+    ]);
+
+    let (results, pending) = poll_to_end(cddl.validate_cbor_stream(Trickle {
+      bytes: &bytes,
+      is_ready: false,
+    }));
+    assert!(pending > bytes.len() / 2);
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_ok() && results[2].is_ok());
+
+    // Failures are located by the index of the item within the sequence
+    let errors = results
+      .into_iter()
+      .nth(1)
+      .unwrap()
+      .unwrap_err()
+      .into_errors();
+    let e = errors[0].validation_error().unwrap();
+    assert_eq!(e.path, "/1/1");
+  }
+
+  #[test]
+  fn validate_streamed_occurrence() {
+    let input = r#"log = [+ uint]"#;
+    let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+
+    let (results, _) = poll_to_end(cddl.validate_cbor_stream(&[][..]));
+    match results.as_slice() {
+      [Err(Error::Occurrence(_))] => (),
+      r => panic!("unexpected results {:?}", r),
+    }
+
+    let bytes = cbor_seq(&[Value::Integer(1), Value::Integer(2)]);
+    let (results, _) = poll_to_end(cddl.validate_cbor_stream(&bytes[..]));
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|r| r.is_ok()));
+  }
+
+  #[test]
+  fn validate_streamed_sequence_as_a_whole() {
+    let input = r#"log = (version: uint, message: tstr)"#;
+    let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+
+    let bytes = cbor_seq(&[Value::Integer(1), Value::Text("started".into())]);
+    let (results, _) = poll_to_end(cddl.validate_cbor_stream(&bytes[..]));
+    assert_eq!(results.len(), 1);
+    assert!(results[0].is_ok());
+
+    let bytes = cbor_seq(&[Value::Text("started".into()), Value::Integer(1)]);
+    let (results, _) = poll_to_end(cddl.validate_cbor_stream(&bytes[..]));
+    match results.as_slice() {
+      [Err(_)] => (),
+      r => panic!("unexpected results {:?}", r),
+    }
+  }
+
+  #[test]
+  fn validate_streamed_truncated_item() {
+    let input = r#"log = (* tstr)"#;
+    let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+
+    let bytes = cbor_seq(&[Value::Text("started".into()), Value::Text("stopped".into())]);
+    let (results, _) = poll_to_end(cddl.validate_cbor_stream(&bytes[..bytes.len() - 1]));
+    match results.as_slice() {
+      [Ok(()), Err(Error::Target(e))] => assert!(e.downcast_ref::<ValidationError>().is_none()),
+      r => panic!("unexpected results {:?}", r),
+    }
+  }
+}
@@ -0,0 +1,200 @@
+use super::{
+  uint_literal,
+  value::{DataKind, DataValue},
+  CompilationError, Error, Result, ValidationError,
+};
+use crate::{ast::Type2, token::Numeric};
+use regex::Regex;
+use std::cmp::Ordering;
+
+/// Validates a value against a given Perl-Compatible regex controller
+pub fn validate_pcre_control<T: DataValue>(controller: &str, value: &T) -> Result {
+  match value.kind() {
+    DataKind::Text(s) => {
+      // Escape sequences of the text string have already been decoded by the
+      // lexer, so the controller is the regex itself
+      let re = Regex::new(controller)
+        .map_err(|e| Error::Compilation(CompilationError::CDDL(e.to_string())))?;
+
+      if re.is_match(s) {
+        return Ok(());
+      }
+
+      Err(
+        ValidationError::new(
+          None,
+          &format_args!("text .pcre {}", controller),
+          None,
+          value,
+        )
+        .into(),
+      )
+    }
+    _ => Err(
+      ValidationError::new(
+        None,
+        &format_args!("text .pcre {:?}", controller),
+        None,
+        value,
+      )
+      .into(),
+    ),
+  }
+}
+
+/// Validates whether or not a value is less than a given numeric controller
+pub fn validate_lt_control<T: DataValue>(target: &Type2, controller: Numeric, value: &T) -> Result {
+  validate_numeric_control(target, ".lt", controller, value, |o| o == Ordering::Less)
+}
+
+/// Validates whether or not a value is greater than a given numeric controller
+pub fn validate_gt_control<T: DataValue>(target: &Type2, controller: Numeric, value: &T) -> Result {
+  validate_numeric_control(target, ".gt", controller, value, |o| o == Ordering::Greater)
+}
+
+/// Validates whether or not a value is greater than or equal to a given
+/// numeric controller
+pub fn validate_ge_control<T: DataValue>(target: &Type2, controller: Numeric, value: &T) -> Result {
+  validate_numeric_control(target, ".ge", controller, value, |o| o != Ordering::Less)
+}
+
+/// Validates whether or not a value is less than or equal to a given numeric
+/// controller
+pub fn validate_le_control<T: DataValue>(target: &Type2, controller: Numeric, value: &T) -> Result {
+  validate_numeric_control(target, ".le", controller, value, |o| o != Ordering::Greater)
+}
+
+/// Validates whether or not a value is equal to a given numeric controller
+pub fn validate_eq_numeric_control<T: DataValue>(
+  target: &Type2,
+  controller: Numeric,
+  value: &T,
+) -> Result {
+  validate_numeric_control(target, ".eq", controller, value, |o| o == Ordering::Equal)
+}
+
+/// Validates whether or not a value is equal to a given text controller
+pub fn validate_eq_text_control<T: DataValue>(controller: &str, value: &T) -> Result {
+  match value.kind() {
+    DataKind::Text(s) if s == controller => Ok(()),
+    _ => Err(
+      ValidationError::new(
+        None,
+        &format_args!("( text / tstr ) .eq \"{}\"", controller),
+        None,
+        value,
+      )
+      .into(),
+    ),
+  }
+}
+
+// Validates a number by how it compares to a numeric controller. Values are
+// compared numerically, so integer controllers apply to floats and vice versa.
+fn validate_numeric_control<T: DataValue>(
+  target: &Type2,
+  operator: &str,
+  controller: Numeric,
+  value: &T,
+  is_satisfied: fn(Ordering) -> bool,
+) -> Result {
+  let kind = value.kind();
+  let ordering = match (&controller, &kind) {
+    (Numeric::INT(i), DataKind::Integer(n)) => Some(n.cmp(i)),
+    (Numeric::UINT(ui), DataKind::Integer(n)) => Some(n.cmp(&uint_literal(*ui))),
+    (Numeric::INT(i), _) => kind.as_f64().and_then(|n| n.partial_cmp(&(*i as f64))),
+    (Numeric::UINT(ui), _) => kind.as_f64().and_then(|n| n.partial_cmp(&(*ui as f64))),
+    (Numeric::FLOAT(f), _) => kind.as_f64().and_then(|n| n.partial_cmp(f)),
+  };
+
+  match ordering {
+    Some(o) if is_satisfied(o) => Ok(()),
+    _ => Err(
+      match controller {
+        Numeric::INT(i) => ValidationError::new(
+          None,
+          &format_args!("{} {} {}", target, operator, i),
+          None,
+          value,
+        ),
+        Numeric::UINT(ui) => ValidationError::new(
+          None,
+          &format_args!("{} {} {}", target, operator, ui),
+          None,
+          value,
+        ),
+        Numeric::FLOAT(f) => ValidationError::new(
+          None,
+          &format_args!("{} {} {}", target, operator, f),
+          None,
+          value,
+        ),
+      }
+      .into(),
+    ),
+  }
+}
+
+#[cfg(test)]
+#[cfg(feature = "json-validation")]
+mod tests {
+  use super::{super::json::validate_json_from_str, Result};
+
+  #[test]
+  fn validate_pcre_control() -> Result {
+    let json_input = r#""N1@CH57HF.4Znqe0.dYJRN.igjf""#;
+    let cddl_input = r#"mypcre = tstr .pcre regexoptions
+    
+    regexoptions = "^[A-Z]$" / "[A-Za-z0-9]+@[A-Za-z0-9]+(\\.[A-Za-z0-9]+)+""#;
+
+    validate_json_from_str(cddl_input, json_input)
+  }
+
+  #[test]
+  fn validate_lt_control() -> Result {
+    let json_input = r#"10.5"#;
+    let cddl_input = r#"ltrule = float .lt 15.5"#;
+
+    validate_json_from_str(cddl_input, json_input)
+  }
+
+  #[test]
+  fn validate_le_control() -> Result {
+    let json_input = r#"10"#;
+    let cddl_input = r#"lerule = uint .le 15"#;
+
+    validate_json_from_str(cddl_input, json_input)
+  }
+
+  #[test]
+  fn validate_gt_control() -> Result {
+    let json_input = r#"-10"#;
+    let cddl_input = r#"gtrule = int .gt -20"#;
+
+    validate_json_from_str(cddl_input, json_input)
+  }
+
+  #[test]
+  fn validate_ge_control() -> Result {
+    let json_input = r#"10.5"#;
+    let cddl_input = r#"gerule = float .ge 10.5"#;
+
+    validate_json_from_str(cddl_input, json_input)
+  }
+
+  #[test]
+  fn validate_eq_numeric_control() -> Result {
+    let json_input = r#"100"#;
+    let cddl_input = r#"eqrule = uint .eq 100"#;
+
+    validate_json_from_str(cddl_input, json_input)
+  }
+
+  #[test]
+  fn validate_eq_text_control() -> Result {
+    let json_input = r#""hello""#;
+    let cddl_input = r#"eqrule = text .eq "hello""#;
+
+    validate_json_from_str(cddl_input, json_input)
+  }
+}
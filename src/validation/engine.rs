@@ -0,0 +1,1065 @@
+use super::{
+  close_step,
+  controls::*,
+  dedup_errors, diagnose, group_choice, is_deferring, is_value_mismatch, memoized, most_plausible,
+  open_step, tentatively, time, type_choice, uint_literal,
+  value::{DataKind, DataValue},
+  ActualValue, CollectGuard, Error, Reason, Result, Severity, TraceTarget, ValidationError,
+  Validator,
+};
+use crate::{
+  ast::*,
+  normalize::{group_of_entry, substitute_generic_args},
+  prelude::{is_extension_type, is_prelude_type, with_prelude},
+  token::{self, Numeric, Token},
+};
+use std::fmt;
+
+#[cfg(feature = "json-validation")]
+use super::transiently;
+
+impl<'a> CDDL<'a> {
+  // Validates data against the first type rule, which is the root
+  pub(super) fn validate_data<T>(&self, value: &T) -> Result
+  where
+    CDDL<'a>: Validator<T>,
+    T: DataValue,
+  {
+    for r in self.rules.iter() {
+      if let Rule::Type { rule, .. } = r {
+        return self.validate_root_rule(&rule.name, value);
+      }
+    }
+
+    Ok(())
+  }
+
+  // Validates data against the definitions of the rule with the given
+  // identifier, with any generic arguments substituted for its parameters, or
+  // the standard prelude type of that name if the CDDL doesn't define it
+  #[allow(clippy::too_many_arguments)]
+  pub(super) fn validate_rule_definitions<T>(
+    &self,
+    ident: &Identifier,
+    generic_arg: Option<&GenericArg>,
+    is_enumeration: bool,
+    expected_memberkey: Option<String>,
+    actual_memberkey: Option<String>,
+    occur: Option<&Occur>,
+    value: &T,
+  ) -> Result
+  where
+    for<'b> CDDL<'b>: Validator<T>,
+    T: DataValue,
+  {
+    let key = self.memo_key(
+      ident,
+      generic_arg,
+      is_enumeration,
+      &expected_memberkey,
+      &actual_memberkey,
+      occur,
+      value,
+    );
+
+    let is_traced = open_step(|| self.rule_target(ident), value);
+    let result = memoized(key, || {
+      let _guard = self.enter_rule(ident)?;
+
+      // Rules extended via /= or //= are satisfied by any of their definitions
+      let mut errors = Vec::new();
+      for rule in self.rules_for(ident) {
+        let result = match (generic_arg, rule) {
+          (Some(ga), _) => self.validate_generic_definition(
+            rule,
+            ga,
+            is_enumeration,
+            expected_memberkey.clone(),
+            actual_memberkey.clone(),
+            occur,
+            value,
+          ),
+          (None, Rule::Type { rule, .. }) => self.validate_type_rule(
+            rule,
+            expected_memberkey.clone(),
+            actual_memberkey.clone(),
+            occur,
+            value,
+          ),
+          (None, Rule::Group { rule, .. }) => {
+            self.validate_group_rule(rule, is_enumeration, occur, value)
+          }
+        };
+
+        match result {
+          Ok(()) => return Ok(()),
+          Err(e) => errors.push(e),
+        }
+      }
+
+      if errors.len() == 1 {
+        return Err(errors.remove(0));
+      } else if !errors.is_empty() {
+        return Err(Error::MultiError(errors));
+      }
+
+      // Fall back to the standard prelude for type names not defined by the
+      // given CDDL
+      if is_prelude_type(ident.ident) {
+        return with_prelude(|p| {
+          p.validate_rule_for_ident(
+            ident,
+            is_enumeration,
+            expected_memberkey.clone(),
+            actual_memberkey.clone(),
+            occur,
+            value,
+          )
+        })
+        .map_err(|_| {
+          ValidationError::new(expected_memberkey, ident.ident, actual_memberkey, value).into()
+        });
+      }
+
+      self.resolve_unknown_rule(ident, expected_memberkey, actual_memberkey, value)
+    });
+    close_step(is_traced, &result);
+
+    result
+  }
+
+  // Validates data against a type name that neither the CDDL nor the standard
+  // prelude defines, either as the extension type of that name or using the
+  // callback registered by the application, if any. Kept out of
+  // `validate_rule_definitions` so that it doesn't add to the stack used by
+  // every rule reference.
+  #[inline(never)]
+  fn resolve_unknown_rule<T>(
+    &self,
+    ident: &Identifier,
+    expected_memberkey: Option<String>,
+    actual_memberkey: Option<String>,
+    value: &T,
+  ) -> Result
+  where
+    T: DataValue,
+  {
+    if is_extension_type(ident.ident) && ident.socket.is_none() {
+      return time::check_duration(value).map_err(|_| {
+        ValidationError::new(expected_memberkey, ident.ident, actual_memberkey, value).into()
+      });
+    }
+
+    let resolved = match &self.validation_options.on_unknown_rule {
+      Some(resolver) => (resolver.0)(ident.ident, &value.clone().into()),
+      None => None,
+    };
+
+    match resolved {
+      Some(true) => Ok(()),
+      Some(false) => {
+        Err(ValidationError::new(expected_memberkey, ident.ident, actual_memberkey, value).into())
+      }
+      None => Err(Error::UnknownRule(ident.ident.to_string())),
+    }
+  }
+
+  // Validates data against a single definition of a generic rule, instantiated
+  // with the arguments of the reference being followed
+  #[allow(clippy::too_many_arguments)]
+  fn validate_generic_definition<T>(
+    &self,
+    rule: &Rule,
+    generic_arg: &GenericArg,
+    is_enumeration: bool,
+    expected_memberkey: Option<String>,
+    actual_memberkey: Option<String>,
+    occur: Option<&Occur>,
+    value: &T,
+  ) -> Result
+  where
+    for<'b> CDDL<'b>: Validator<T>,
+    T: DataValue,
+  {
+    match substitute_generic_args(rule, generic_arg) {
+      Some(Rule::Type { rule, .. }) => {
+        self.validate_type_rule(&rule, expected_memberkey, actual_memberkey, occur, value)
+      }
+      Some(Rule::Group { rule, .. }) => {
+        self.validate_group_rule(&rule, is_enumeration, occur, value)
+      }
+      None => {
+        let (name, generic_param) = match rule {
+          Rule::Type { rule, .. } => (&rule.name, &rule.generic_param),
+          Rule::Group { rule, .. } => (&rule.name, &rule.generic_param),
+        };
+
+        Err(Error::Syntax(format!(
+          "Rule \"{}\" expects {} generic argument(s) but {} were given",
+          name,
+          generic_param.as_ref().map_or(0, |gp| gp.params.len()),
+          generic_arg.args.len()
+        )))
+      }
+    }
+  }
+
+  // Validates a map against a reference to a group rule with an occurrence
+  // indicator, such as { ? extension }, as though the entries of the group
+  // were written inline in its place, so that an optional group is satisfied
+  // when none of its members are present
+  pub(super) fn validate_group_name_in_map<T>(
+    &self,
+    tge: &TypeGroupnameEntry,
+    occur: &Occur,
+    span: Span,
+    value: &T,
+  ) -> Result
+  where
+    for<'b> CDDL<'b>: Validator<T>,
+    T: DataValue,
+  {
+    let _guard = self.enter_rule(&tge.name)?;
+
+    let mut errors = Vec::new();
+    for rule in self.rules_for(&tge.name) {
+      let rule = match &tge.generic_arg {
+        Some(ga) => match substitute_generic_args(rule, ga) {
+          Some(rule) => rule,
+          None => {
+            return self.validate_generic_definition(
+              rule,
+              ga,
+              false,
+              None,
+              None,
+              Some(occur),
+              value,
+            )
+          }
+        },
+        None => rule.clone(),
+      };
+
+      if let Rule::Group { rule, .. } = rule {
+        let entry = GroupEntry::InlineGroup {
+          occur: Some(occur.clone()),
+          group: group_of_entry(&rule.entry),
+          span,
+        };
+
+        match self.validate_group_entry(&entry, false, None, None, value) {
+          Ok(()) => return Ok(()),
+          Err(e) => errors.push(e),
+        }
+      }
+    }
+
+    if errors.len() == 1 {
+      Err(errors.remove(0))
+    } else {
+      Err(Error::MultiError(errors))
+    }
+  }
+
+  // Appends the entries with member keys that the given entry of a map
+  // consists of, expanding references to group rules and inline groups, unless
+  // it has an entry without a member key or a group with several group
+  // choices, whose members aren't known up front. E.g. { header, ? body: bstr }
+  // with header = (id: uint) consists of id: and body:, and { empty } with
+  // empty = () of no entries at all.
+  pub(super) fn keyed_entries<'b>(
+    &'b self,
+    ge: &'b GroupEntry<'a>,
+    entries: &mut Vec<&'b ValueMemberKeyEntry<'a>>,
+  ) -> bool {
+    match ge {
+      GroupEntry::ValueMemberKey { ge, .. } if ge.member_key.is_some() => {
+        entries.push(ge);
+        true
+      }
+      GroupEntry::TypeGroupname {
+        ge: TypeGroupnameEntry {
+          name,
+          generic_arg: None,
+          ..
+        },
+        ..
+      } => match self.rules_for(name).collect::<Vec<_>>().as_slice() {
+        [Rule::Group { rule, .. }] if rule.generic_param.is_none() => {
+          // Bounds recursive groups, e.g. a = (a)
+          let _guard = match self.enter_rule(name) {
+            Ok(guard) => guard,
+            Err(_) => return false,
+          };
+
+          self.keyed_entries(&rule.entry, entries)
+        }
+        _ => false,
+      },
+      GroupEntry::InlineGroup { group, .. } => match group.group_choices.as_slice() {
+        [gc] => gc
+          .group_entries
+          .iter()
+          .all(|(ge, _)| self.keyed_entries(ge, entries)),
+        _ => false,
+      },
+      _ => false,
+    }
+  }
+
+  // Validates data against the choices of a type, succeeding if any of them
+  // matches
+  pub(super) fn validate_type_choices<T>(
+    &self,
+    t: &Type,
+    expected_memberkey: Option<String>,
+    actual_memberkey: Option<String>,
+    occur: Option<&Occur>,
+    value: &T,
+  ) -> Result
+  where
+    CDDL<'a>: Validator<T>,
+    T: DataValue,
+  {
+    let mut validation_errors: Vec<Error> = Vec::new();
+
+    // Choices that fail are compared by every failure they report, so that the
+    // one coming closest to matching can be told apart
+    let _guard = if t.type_choices.len() > 1 && !is_deferring() {
+      Some(CollectGuard::new())
+    } else {
+      None
+    };
+
+    // Find the first type choice that validates to true
+    let find_type_choice = |(idx, t1)| match tentatively(|| {
+      let is_traced = open_step(|| type_choice(t, idx), value);
+      let result = self.validate_type1(
+        t1,
+        expected_memberkey.clone(),
+        actual_memberkey.clone(),
+        occur,
+        value,
+      );
+      close_step(is_traced, &result);
+
+      result
+    }) {
+      Ok(()) => true,
+      Err(e) => {
+        validation_errors.push(e);
+        false
+      }
+    };
+
+    if t.type_choices.iter().enumerate().any(find_type_choice) {
+      return Ok(());
+    }
+
+    // Report a single failure when none of the choices matched the value
+    // itself, rather than a failure per choice. Otherwise only the failures of
+    // the choices that got furthest are reported, with those of nested values
+    // shared by several choices reported once.
+    let validation_errors = dedup_errors(most_plausible(validation_errors));
+    if t.type_choices.len() > 1 && validation_errors.iter().all(is_value_mismatch) {
+      return Err(ValidationError::new(expected_memberkey, t, actual_memberkey, value).into());
+    }
+
+    Err(Error::MultiError(validation_errors))
+  }
+
+  // Validates data against a type, applying its range or control operator if
+  // it has one
+  pub(super) fn validate_type1_operator<T>(
+    &self,
+    t1: &Type1,
+    expected_memberkey: Option<String>,
+    actual_memberkey: Option<String>,
+    occur: Option<&Occur>,
+    value: &T,
+  ) -> Result
+  where
+    CDDL<'a>: Validator<T>,
+    T: DataValue,
+  {
+    if let Some((rco, t2)) = &t1.operator {
+      match rco {
+        RangeCtlOp::RangeOp { is_inclusive, .. } => {
+          return self
+            .validate_range(&t1.type2, t2, *is_inclusive, value)
+            .map_err(|e| e.with_reason(Reason::Range))
+        }
+        RangeCtlOp::CtlOp { ctrl, .. } => {
+          return self
+            .validate_control_operator(&t1.type2, ctrl, t2, value)
+            .map_err(|e| e.with_reason(Reason::Control))
+        }
+      }
+    }
+
+    self.validate_type2(
+      &t1.type2,
+      expected_memberkey,
+      actual_memberkey,
+      occur,
+      value,
+    )
+  }
+
+  // Validates a number against a range. Bounds named by a type name are
+  // resolved to the numeric values the type is defined as.
+  pub(super) fn validate_numeric_range<T>(
+    &self,
+    lower: &Type2,
+    upper: &Type2,
+    is_inclusive: bool,
+    value: &T,
+  ) -> Result
+  where
+    T: DataValue,
+  {
+    // TODO: Per spec, if lower bound exceeds upper bound, resulting type is
+    // empty set. Not sure how this translates to numerical validation.
+    let kind = value.kind();
+    let integer = match kind {
+      DataKind::Integer(i) => Some(i),
+      DataKind::Float(_) => None,
+      _ => {
+        return Err(
+          ValidationError::new(
+            None,
+            &format_args!("Expected numerical value between {} and {}", lower, upper),
+            None,
+            value,
+          )
+          .into(),
+        )
+      }
+    };
+
+    // Bounds that aren't both integers or both floats, or rules defined as
+    // such, don't describe a range any number falls within
+    let invalid_range = || {
+      let mut e = ValidationError::new(
+        None,
+        &format_args!(
+          "{}{}{}, which isn't a range of numbers",
+          lower,
+          if is_inclusive { ".." } else { "..." },
+          upper
+        ),
+        None,
+        value,
+      );
+      e.reason = Reason::Range;
+
+      Error::from(e)
+    };
+
+    match (lower, upper) {
+      (Type2::IntValue { value: li, .. }, Type2::IntValue { value: ui, .. }) => range_result(
+        integer.map(|n| is_in_range(n, *li, *ui, is_inclusive)),
+        li,
+        ui,
+        is_inclusive,
+        value,
+      ),
+      (Type2::IntValue { value: li, .. }, Type2::UintValue { value: ui, .. }) => range_result(
+        integer.map(|n| is_in_range(n, *li, uint_literal(*ui), is_inclusive)),
+        li,
+        ui,
+        is_inclusive,
+        value,
+      ),
+      (Type2::UintValue { value: li, .. }, Type2::UintValue { value: ui, .. }) => range_result(
+        integer.map(|n| is_in_range(n, uint_literal(*li), uint_literal(*ui), is_inclusive)),
+        li,
+        ui,
+        is_inclusive,
+        value,
+      ),
+      (Type2::FloatValue { value: lf, .. }, Type2::FloatValue { value: uf, .. }) => range_result(
+        kind
+          .as_f64()
+          .map(|n| is_in_range(n, *lf, *uf, is_inclusive)),
+        lf,
+        uf,
+        is_inclusive,
+        value,
+      ),
+      // Bounds given by name are resolved to the values of the rules they
+      // name, e.g. 1..max-port with max-port = 65535
+      _ if !is_numeric_literal(lower) => match self.range_bound_values(lower) {
+        Some(bounds) => any_range(&bounds, |tc| {
+          self.validate_numeric_range(tc, upper, is_inclusive, value)
+        }),
+        None => Err(invalid_range()),
+      },
+      _ if !is_numeric_literal(upper) => match self.range_bound_values(upper) {
+        Some(bounds) => any_range(&bounds, |tc| {
+          self.validate_numeric_range(lower, tc, is_inclusive, value)
+        }),
+        None => Err(invalid_range()),
+      },
+      _ => Err(invalid_range()),
+    }
+  }
+
+  // Validates data against a control operator. Operators that aren't among
+  // those checked by `is_checked_control` fail as unsupported.
+  pub(super) fn validate_control<T>(
+    &self,
+    target: &Type2,
+    operator: &'static str,
+    controller: &Type2,
+    value: &T,
+  ) -> Result
+  where
+    CDDL<'a>: Validator<T>,
+    T: DataValue,
+  {
+    let mut errors: Vec<Error> = Vec::new();
+    let unsupported = || unsupported_control(target, operator, controller, value);
+
+    match token::lookup_control_from_str(operator) {
+      t @ Some(Token::PCRE) | t @ Some(Token::CREGEXP) => {
+        if t == Some(Token::CREGEXP) {
+          diagnose(Severity::Note, || {
+            "the .regexp control is evaluated as a Perl-compatible regular expression, like .pcre"
+              .to_string()
+          });
+        }
+
+        if !self.is_type_string_data_type(target) {
+          return Err(unsupported());
+        }
+
+        let find_valid_value = |c: &str| -> bool {
+          match validate_pcre_control(c, value) {
+            Ok(()) => true,
+            Err(e) => {
+              errors.push(e);
+
+              false
+            }
+          }
+        };
+
+        if self
+          .text_values_from_type(controller)
+          .ok_or_else(unsupported)?
+          .into_iter()
+          .any(find_valid_value)
+        {
+          Ok(())
+        } else {
+          Err(Error::MultiError(errors))
+        }
+      }
+      Some(ctrl @ Token::LT)
+      | Some(ctrl @ Token::LE)
+      | Some(ctrl @ Token::GT)
+      | Some(ctrl @ Token::GE) => {
+        if !self.is_type_numeric_data_type(target) {
+          return Err(unsupported());
+        }
+
+        // The controller is compared numerically, so the value has to be of
+        // the target type first, e.g. `int .lt 10` doesn't allow 5.5
+        self.validate_type2(target, None, None, None, value)?;
+
+        let validate_control: fn(&Type2, Numeric, &T) -> Result = match ctrl {
+          Token::LT => validate_lt_control,
+          Token::LE => validate_le_control,
+          Token::GT => validate_gt_control,
+          _ => validate_ge_control,
+        };
+
+        let find_valid_value = |n: Numeric| -> bool {
+          match validate_control(target, n, value) {
+            Ok(()) => true,
+            Err(e) => {
+              errors.push(e);
+
+              false
+            }
+          }
+        };
+
+        if self
+          .numeric_values_from_type(controller)
+          .ok_or_else(unsupported)?
+          .into_iter()
+          .any(find_valid_value)
+        {
+          Ok(())
+        } else {
+          Err(Error::MultiError(errors))
+        }
+      }
+      Some(Token::EQ) => {
+        if self.is_type_numeric_data_type(target) {
+          self.validate_type2(target, None, None, None, value)?;
+
+          let find_valid_value = |n: Numeric| -> bool {
+            match validate_eq_numeric_control(target, n, value) {
+              Ok(()) => true,
+              Err(e) => {
+                errors.push(e);
+
+                false
+              }
+            }
+          };
+
+          if self
+            .numeric_values_from_type(controller)
+            .ok_or_else(unsupported)?
+            .into_iter()
+            .any(find_valid_value)
+          {
+            Ok(())
+          } else {
+            Err(Error::MultiError(errors))
+          }
+        } else if self.is_type_string_data_type(target) {
+          let find_valid_value = |c: &str| -> bool {
+            match validate_eq_text_control(c, value) {
+              Ok(()) => true,
+              Err(e) => {
+                errors.push(e);
+
+                false
+              }
+            }
+          };
+
+          if self
+            .text_values_from_type(controller)
+            .ok_or_else(unsupported)?
+            .into_iter()
+            .any(find_valid_value)
+          {
+            Ok(())
+          } else {
+            Err(Error::MultiError(errors))
+          }
+        } else {
+          Err(unsupported())
+        }
+      }
+      // The negation of .eq, e.g. `port = uint .ne 0`
+      Some(Token::NE) => {
+        let is_equal = if self.is_type_numeric_data_type(target) {
+          self.validate_type2(target, None, None, None, value)?;
+
+          self
+            .numeric_values_from_type(controller)
+            .ok_or_else(unsupported)?
+            .into_iter()
+            .any(|n| validate_eq_numeric_control(target, n, value).is_ok())
+        } else if self.is_type_string_data_type(target) {
+          self.validate_type2(target, None, None, None, value)?;
+
+          self
+            .text_values_from_type(controller)
+            .ok_or_else(unsupported)?
+            .into_iter()
+            .any(|c| validate_eq_text_control(c, value).is_ok())
+        } else {
+          return Err(unsupported());
+        };
+
+        if is_equal {
+          return Err(
+            ValidationError::new(
+              None,
+              &format_args!("{} .ne {}", target, controller),
+              None,
+              value,
+            )
+            .into(),
+          );
+        }
+
+        Ok(())
+      }
+      Some(Token::SIZE) => {
+        let failure = || {
+          ValidationError::new(
+            None,
+            &format_args!("{} .size {}", target, controller),
+            None,
+            value,
+          )
+          .into()
+        };
+
+        match value.kind() {
+          // The size of a text string is its length in bytes once encoded as
+          // UTF-8, unless it's been opted to count characters instead, which
+          // is matched against the controller
+          DataKind::Text(s) if self.is_type_string_data_type(target) => {
+            let size = if self.validation_options.text_size_in_chars {
+              s.chars().count()
+            } else {
+              s.len()
+            };
+
+            self
+              .validate_type2(controller, None, None, None, &T::from_size(size))
+              .map_err(|_| failure())
+          }
+          // An unsigned integer must fit in the given number of bytes
+          DataKind::Integer(i) if self.is_type_numeric_data_type(target) => match controller {
+            Type2::UintValue { value: size, .. }
+              if i >= 0 && (*size >= 16 || (i as u128) >> (8 * *size as u32) == 0) =>
+            {
+              Ok(())
+            }
+            _ => Err(failure()),
+          },
+          // The size of a byte string is its number of bytes, once decoded
+          // from the text it's represented by in formats without byte strings
+          _ if self.is_type_byte_string_data_type(target) => match value.bytes(self) {
+            Some(bytes) => self
+              .validate_type2(controller, None, None, None, &T::from_size(bytes.len()))
+              .map_err(|_| failure()),
+            None => Err(failure()),
+          },
+          _ => Err(failure()),
+        }
+      }
+      Some(Token::AND) => {
+        self.validate_type2(target, None, None, None, value)?;
+
+        self.validate_type2(controller, None, None, None, value)
+      }
+      // The target is meant to be a subset of the controller, so a value that
+      // only matches the target points to a mistake in the definition rather
+      // than in the data
+      Some(Token::WITHIN) => {
+        self.validate_type2(target, None, None, None, value)?;
+
+        if tentatively(|| self.validate_type2(controller, None, None, None, value)).is_err() {
+          diagnose(Severity::Warning, || {
+            format!(
+              "{} matches {} but not {}, which .within requires it to be a subset of",
+              Into::<ActualValue>::into(value.clone()),
+              target,
+              controller
+            )
+          });
+        }
+
+        Ok(())
+      }
+      // The text string has to be well-formed JSON that matches the controller
+      // once parsed, e.g. `payload = tstr .json config`
+      #[cfg(feature = "json-validation")]
+      Some(Token::JSON) => {
+        self.validate_type2(target, None, None, None, value)?;
+
+        let json = match value.kind() {
+          DataKind::Text(s) => serde_json::from_str::<serde_json::Value>(s).ok(),
+          _ => None,
+        };
+
+        match json {
+          Some(json) => transiently(|| {
+            Validator::<serde_json::Value>::validate_type2(
+              self, controller, None, None, None, &json,
+            )
+          }),
+          None => Err(
+            ValidationError::new(
+              None,
+              &format_args!("{} .json {}", target, controller),
+              None,
+              value,
+            )
+            .into(),
+          ),
+        }
+      }
+      // Every bit that's set has to be one of the bit numbers the controller
+      // allows. Bits of byte strings are numbered from the least significant
+      // bit of the first byte.
+      Some(Token::BITS) => {
+        self.validate_type2(target, None, None, None, value)?;
+
+        let bits: Vec<usize> = match value.kind() {
+          DataKind::Integer(i) if i >= 0 => (0..128).filter(|bit| (i >> bit) & 1 == 1).collect(),
+          _ => match value.bytes(self) {
+            Some(bytes) => bytes
+              .iter()
+              .enumerate()
+              .flat_map(|(i, byte)| {
+                (0..8)
+                  .filter(move |bit| (byte >> bit) & 1 == 1)
+                  .map(move |bit| i * 8 + bit)
+              })
+              .collect(),
+            None => Vec::new(),
+          },
+        };
+
+        match bits.into_iter().find(|bit| {
+          self
+            .validate_type2(controller, None, None, None, &T::from_size(*bit))
+            .is_err()
+        }) {
+          Some(bit) => Err(
+            ValidationError::new(
+              None,
+              &format_args!(
+                "{} .bits {}, which doesn't allow bit {}",
+                target, controller, bit
+              ),
+              None,
+              value,
+            )
+            .into(),
+          ),
+          None => Ok(()),
+        }
+      }
+      // The controller is the value assumed for an optional entry that's
+      // absent, so it doesn't constrain data that's present
+      Some(Token::DEFAULT) => self.validate_type2(target, None, None, None, value),
+      // Values are captured by recording them in the trace that
+      // `Validator::validate_and_extract` collects captures from, so that
+      // captures made within choices the data didn't take are dropped
+      Some(Token::CAPTURE) => {
+        // The controller has to be a single text string naming the capture
+        let name = match self.text_values_from_type(controller).as_deref() {
+          Some([name]) => name.to_string(),
+          _ => return Err(unsupported()),
+        };
+
+        let is_open = open_step(|| Some(TraceTarget::Capture(name)), value);
+        let result = self.validate_type2(target, None, None, None, value);
+        close_step(is_open, &result);
+
+        result
+      }
+      _ => Err(unsupported()),
+    }
+  }
+
+  // Validates data against the choices of a group, succeeding if any of them
+  // matches
+  pub(super) fn validate_group_choices<T>(
+    &self,
+    g: &Group,
+    occur: Option<&Occur>,
+    value: &T,
+  ) -> Result
+  where
+    CDDL<'a>: Validator<T>,
+    T: DataValue,
+  {
+    let mut validation_errors: Vec<Error> = Vec::new();
+
+    // Find the first group choice that validates to true
+    if g.group_choices.iter().enumerate().any(|(idx, gc)| {
+      let is_traced = open_step(|| group_choice(g, idx), value);
+      let result = self.validate_group_choice(gc, occur, value);
+      close_step(is_traced, &result);
+
+      match result {
+        Ok(()) => true,
+        Err(e) => {
+          validation_errors.push(e);
+          false
+        }
+      }
+    }) {
+      return Ok(());
+    }
+
+    // As for type choices, only the failures of the choices that got furthest
+    // are reported
+    let validation_errors = dedup_errors(most_plausible(validation_errors));
+
+    Err(Error::MultiError(validation_errors))
+  }
+
+  // Validates null or a boolean against a type name. The standard prelude
+  // defines null, undefined, true and false as simple values, which aren't
+  // retained by the data models of the supported formats, so those names and
+  // bool are matched directly while any other name is resolved as a rule, e.g.
+  // `flag = true`.
+  pub(super) fn validate_simple_value<T>(
+    &self,
+    ident: &Identifier,
+    expected_memberkey: Option<String>,
+    actual_memberkey: Option<String>,
+    occur: Option<&Occur>,
+    value: &T,
+  ) -> Result
+  where
+    CDDL<'a>: Validator<T>,
+    T: DataValue,
+  {
+    match (value.kind(), ident.ident) {
+      (DataKind::Null, "null") | (DataKind::Null, "nil") => Ok(()),
+      (DataKind::Undefined, "undefined") => Ok(()),
+      // JSON has no undefined value, so there it's approximated by null, which
+      // is what CBOR's undefined converts to (RFC 8949, section 6.1)
+      (DataKind::Null, "undefined") if !T::HAS_UNDEFINED => Ok(()),
+      (DataKind::Bool(_), "bool") | (DataKind::Bool(_), "true") | (DataKind::Bool(_), "false") => {
+        self.expect_bool(ident.ident, value)
+      }
+      (_, "null") | (_, "nil") | (_, "undefined") | (_, "bool") | (_, "true") | (_, "false") => {
+        Err(ValidationError::new(expected_memberkey, ident.ident, actual_memberkey, value).into())
+      }
+      _ => self.validate_rule_for_ident(
+        ident,
+        false,
+        expected_memberkey,
+        actual_memberkey,
+        occur,
+        value,
+      ),
+    }
+  }
+
+  // Validates data against bool or one of its values, given by name
+  pub(super) fn validate_bool<T: DataValue>(&self, ident: &str, value: &T) -> Result {
+    match value.kind() {
+      DataKind::Bool(b) => {
+        if ident == "bool" {
+          return Ok(());
+        }
+
+        if let Ok(bfs) = ident.parse::<bool>() {
+          if bfs == b {
+            return Ok(());
+          }
+
+          return Err(ValidationError::new(None, ident, None, value).into());
+        }
+
+        Err(ValidationError::new(None, ident, None, value).into())
+      }
+      _ => Err(ValidationError::new(None, ident, None, value).into()),
+    }
+  }
+}
+
+// Returns whether the control operator is one checked by the shared validation
+// engine, regardless of the format of the data
+pub(super) fn is_checked_control(operator: &str) -> bool {
+  match token::lookup_control_from_str(operator) {
+    Some(Token::PCRE) | Some(Token::CREGEXP) | Some(Token::LT) | Some(Token::LE)
+    | Some(Token::GT) | Some(Token::GE) | Some(Token::EQ) | Some(Token::NE) | Some(Token::SIZE)
+    | Some(Token::AND) | Some(Token::WITHIN) | Some(Token::BITS) | Some(Token::DEFAULT)
+    | Some(Token::CAPTURE) => true,
+    #[cfg(feature = "json-validation")]
+    Some(Token::JSON) => true,
+    _ => false,
+  }
+}
+
+// Fails data against a control operator that isn't checked, or that's applied
+// to a target or controller it isn't defined for, e.g. `bool .lt 1`, rather
+// than letting it through on the strength of its target alone
+pub(super) fn unsupported_control<T: DataValue>(
+  target: &Type2,
+  operator: &str,
+  controller: &Type2,
+  value: &T,
+) -> Error {
+  let mut e = ValidationError::new(
+    None,
+    &format_args!(
+      "{} {} {}, which can't be checked when validating {}",
+      target,
+      operator,
+      controller,
+      T::FORMAT
+    ),
+    None,
+    value,
+  );
+  e.reason = Reason::Control;
+
+  e.into()
+}
+
+fn is_numeric_literal(t2: &Type2) -> bool {
+  match t2 {
+    Type2::IntValue { .. } | Type2::UintValue { .. } | Type2::FloatValue { .. } => true,
+    _ => false,
+  }
+}
+
+fn is_in_range<N: PartialOrd>(n: N, lower: N, upper: N, is_inclusive: bool) -> bool {
+  n >= lower && if is_inclusive { n <= upper } else { n < upper }
+}
+
+// Returns the result of checking a number against the bounds of a range, given
+// whether it's within them or None if the bounds don't apply to the number
+fn range_result<T, L, U>(
+  is_within: Option<bool>,
+  lower: &L,
+  upper: &U,
+  is_inclusive: bool,
+  value: &T,
+) -> Result
+where
+  T: DataValue,
+  L: fmt::Display,
+  U: fmt::Display,
+{
+  match is_within {
+    Some(true) => Ok(()),
+    Some(false) if is_inclusive => Err(
+      ValidationError::new(
+        None,
+        &format_args!("Range: {} <= value <= {}", lower, upper),
+        None,
+        value,
+      )
+      .into(),
+    ),
+    Some(false) => Err(
+      ValidationError::new(
+        None,
+        &format_args!("Range: {} <= value < {}", lower, upper),
+        None,
+        value,
+      )
+      .into(),
+    ),
+    None => Err(
+      ValidationError::new(
+        None,
+        &format_args!("Range between {} and {}", lower, upper),
+        None,
+        value,
+      )
+      .into(),
+    ),
+  }
+}
+
+// Validates a number against ranges with each of the numeric values a type
+// name bound resolves to, succeeding if any of them matches
+fn any_range<F: FnMut(&Type2) -> Result>(bounds: &[&Type2], mut validate: F) -> Result {
+  let mut validation_errors: Vec<Error> = Vec::new();
+
+  if bounds.iter().any(|tc| match validate(tc) {
+    Ok(()) => true,
+    Err(e) => {
+      validation_errors.push(e);
+      false
+    }
+  }) {
+    Ok(())
+  } else {
+    Err(Error::MultiError(validation_errors))
+  }
+}
@@ -0,0 +1,113 @@
+use std::{convert::TryFrom, result};
+
+/// Checks a text string against the format of a standard prelude type whose
+/// values are conveyed as text strings of a particular format, i.e. `uri`,
+/// `b64url`, `b64legacy`, `regexp` or `mime-message`
+pub fn check_format(ident: &str, value: &str) -> result::Result<(), String> {
+  match ident {
+    "uri" => check_uri(value),
+    // Unlike base64 of the legacy alphabet, base64url is conveyed unpadded
+    "b64url" if value.contains('=') => Err(format!("padded base64url value {}", value)),
+    "b64url" => check_base64(value, base64::URL_SAFE_NO_PAD),
+    "b64legacy" => check_base64(value, base64::STANDARD),
+    "regexp" => check_regexp(value),
+    "mime-message" => check_mime_message(value),
+    _ => Ok(()),
+  }
+}
+
+// Checks that a text string is a URI or a relative reference per RFC 3986, as
+// RFC 8949 requires of the content of tag 32
+fn check_uri(value: &str) -> result::Result<(), String> {
+  uriparse::uri_reference::URIReference::try_from(value)
+    .map(|_| ())
+    .map_err(|e| format!("error parsing URI value {}: {}", value, e))
+}
+
+// Checks that a text string only consists of characters of the given base64
+// alphabet and decodes, which also rules out stray padding
+fn check_base64(value: &str, config: base64::Config) -> result::Result<(), String> {
+  let mut buf = vec![0; value.len()];
+
+  base64::decode_config_slice(value, config, &mut buf)
+    .map(|_| ())
+    .map_err(|e| format!("error decoding base64 value {}: {}", value, e))
+}
+
+// Checks that a text string compiles as a regular expression. RFC 8610 refers
+// to XSD regular expressions, which are compiled by the same engine as `.pcre`
+// controllers since the syntax of the two largely overlaps.
+fn check_regexp(value: &str) -> result::Result<(), String> {
+  regex::Regex::new(value)
+    .map(|_| ())
+    .map_err(|e| format!("error parsing regular expression {}: {}", value, e))
+}
+
+// Checks that the header section of a MIME message consists of well-formed
+// header fields per RFC 5322, each of which is a field name followed by a colon
+// and optionally folded onto lines beginning with whitespace
+fn check_mime_message(value: &str) -> result::Result<(), String> {
+  let mut has_header = false;
+
+  for line in value.lines() {
+    if line.is_empty() {
+      break;
+    }
+
+    if line.starts_with(' ') || line.starts_with('\t') {
+      if has_header {
+        continue;
+      }
+    } else if let Some(idx) = line.find(':') {
+      if idx > 0 && line[..idx].chars().all(|c| c.is_ascii_graphic()) {
+        has_header = true;
+        continue;
+      }
+    }
+
+    return Err(format!("malformed MIME message header field \"{}\"", line));
+  }
+
+  if has_header {
+    Ok(())
+  } else {
+    Err("MIME message must include at least one header field".into())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn verify_formats() {
+    for (ident, valid) in &[
+      ("uri", "https://example.com/a?b=c#d"),
+      ("uri", "urn:ietf:rfc:8610"),
+      ("uri", "../relative/path"),
+      ("b64url", "aGVsbG8_d29ybGQ"),
+      ("b64legacy", "aGVsbG8/d29ybGQ="),
+      ("regexp", "^[a-z]+$"),
+      ("mime-message", "Content-Type: text/plain\r\n\r\nhello"),
+    ] {
+      assert!(check_format(ident, valid).is_ok(), "{} {}", ident, valid);
+    }
+
+    for (ident, invalid) in &[
+      ("uri", "http://exa mple.com"),
+      ("uri", "https://example.com/%zz"),
+      ("b64url", "aGVsbG8/d29ybGQ"),
+      ("b64url", "aGVsbG8_d29ybGQ="),
+      ("b64legacy", "aGVsbG8_d29ybGQ="),
+      ("regexp", "[a-z"),
+      ("mime-message", "hello"),
+    ] {
+      assert!(
+        check_format(ident, invalid).is_err(),
+        "{} {}",
+        ident,
+        invalid
+      );
+    }
+  }
+}
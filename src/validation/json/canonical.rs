@@ -0,0 +1,157 @@
+use super::super::UnsortedKey;
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use std::{cmp::Ordering, fmt};
+
+/// Returns the first member name of JSON text that doesn't sort after the name
+/// before it in the same object in order of their UTF-16 code units, as the
+/// JSON Canonicalization Scheme (RFC 8785) requires, if any
+pub fn first_unsorted_key(json: &str) -> serde_json::Result<Option<UnsortedKey>> {
+  let mut unsorted = None;
+  let mut deserializer = serde_json::Deserializer::from_str(json);
+
+  Members {
+    path: &mut Vec::new(),
+    unsorted: &mut unsorted,
+  }
+  .deserialize(&mut deserializer)?;
+  deserializer.end()?;
+
+  Ok(unsorted)
+}
+
+// Visits every value of JSON text, recording the first member name out of
+// order. Object members are read as they're deserialized, since
+// `serde_json::Value` doesn't retain their order.
+struct Members<'p> {
+  // Reference tokens locating the value being visited
+  path: &'p mut Vec<String>,
+  unsorted: &'p mut Option<UnsortedKey>,
+}
+
+impl<'p> Members<'p> {
+  fn nested(&mut self) -> Members<'_> {
+    Members {
+      path: self.path,
+      unsorted: self.unsorted,
+    }
+  }
+
+  fn pointer(&self) -> String {
+    self.path.iter().fold(String::new(), |mut path, token| {
+      path.push('/');
+      path.push_str(&token.replace('~', "~0").replace('/', "~1"));
+      path
+    })
+  }
+}
+
+impl<'de, 'p> DeserializeSeed<'de> for Members<'p> {
+  type Value = ();
+
+  fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+    deserializer.deserialize_any(self)
+  }
+}
+
+impl<'de, 'p> Visitor<'de> for Members<'p> {
+  type Value = ();
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "a JSON value")
+  }
+
+  fn visit_bool<E: de::Error>(self, _: bool) -> Result<(), E> {
+    Ok(())
+  }
+
+  fn visit_i64<E: de::Error>(self, _: i64) -> Result<(), E> {
+    Ok(())
+  }
+
+  fn visit_u64<E: de::Error>(self, _: u64) -> Result<(), E> {
+    Ok(())
+  }
+
+  fn visit_f64<E: de::Error>(self, _: f64) -> Result<(), E> {
+    Ok(())
+  }
+
+  fn visit_str<E: de::Error>(self, _: &str) -> Result<(), E> {
+    Ok(())
+  }
+
+  fn visit_unit<E: de::Error>(self) -> Result<(), E> {
+    Ok(())
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<(), A::Error> {
+    let mut idx = 0;
+
+    loop {
+      self.path.push(idx.to_string());
+      let element = seq.next_element_seed(self.nested());
+      self.path.pop();
+
+      if element?.is_none() {
+        return Ok(());
+      }
+      idx += 1;
+    }
+  }
+
+  fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<(), A::Error> {
+    let mut previous: Option<String> = None;
+
+    while let Some(key) = map.next_key::<String>()? {
+      self.path.push(key);
+
+      let key = &self.path[self.path.len() - 1];
+      let is_unsorted = previous
+        .as_ref()
+        .map(|p| key.encode_utf16().cmp(p.encode_utf16()) != Ordering::Greater)
+        .unwrap_or(false);
+      if is_unsorted && self.unsorted.is_none() {
+        *self.unsorted = Some(UnsortedKey {
+          key: key.clone(),
+          path: self.pointer(),
+        });
+      }
+
+      let value = map.next_value_seed(self.nested());
+      previous = self.path.pop();
+      value?;
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn verify_first_unsorted_key() {
+    let unsorted = |json: &str| first_unsorted_key(json).unwrap().map(|u| (u.key, u.path));
+
+    assert!(unsorted(r#"{"a": 1, "b": [{"c": 1, "d": 2}], "c": null}"#).is_none());
+    assert!(unsorted(r#"[1, "b", {}]"#).is_none());
+    assert_eq!(
+      unsorted(r#"{"a": [{"x": 1}, {"y": {"b": 1, "a/b": 2}}], "b": 1}"#),
+      Some(("a/b".to_string(), "/a/1/y/a~1b".to_string()))
+    );
+    assert_eq!(
+      unsorted(r#"{"b": 1, "a": 2, "c": {"z": 1, "y": 2}}"#),
+      Some(("a".to_string(), "/a".to_string()))
+    );
+    // Duplicate names don't sort after one another either
+    assert_eq!(
+      unsorted(r#"{"a": 1, "a": 2}"#),
+      Some(("a".to_string(), "/a".to_string()))
+    );
+    // U+1F600 sorts before U+FB01 by UTF-16 code units, unlike by code points
+    assert!(unsorted("{\"\u{1F600}\": 1, \"\u{FB01}\": 2}").is_none());
+
+    assert!(first_unsorted_key(r#"{"a": 1"#).is_err());
+  }
+}
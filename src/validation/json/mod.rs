@@ -1,88 +1,64 @@
-mod controls;
-
-use super::{CompilationError, Error, Result, Validator};
+mod canonical;
+mod stream;
+/// Validation of YAML documents as JSON
+#[cfg(feature = "yaml")]
+pub mod yaml;
+
+use super::{
+  at, close_step, dedup_errors, diagnose, group_choice, is_collecting, is_f16_representable,
+  is_f32_representable, is_float_literal_match, is_numeric_data_type, is_unwrap, most_plausible,
+  occurrence_bounds, open_step, pointer_index, validate_elements, validate_occurrence_count,
+  value::{DataKind, DataValue},
+  CompilationError, Error, FloatComparison, Reason, Result, Severity, ValidationError, Validator,
+};
 use crate::{
   ast::*,
-  lexer, parser,
-  token::{self, Numeric, Token},
+  cbor_core::{decode_b16, decode_b64},
+  lexer, parser, token,
 };
-use controls::*;
-use serde_json::{self, Value};
-use std::{f64, fmt};
-
-#[cfg(feature = "nightly")]
-use std::convert::TryFrom;
-
-#[cfg(feature = "nightly")]
-use uriparse;
-
-/// Error type when validating JSON
-#[derive(Debug)]
-pub struct JSONError {
-  expected_memberkey: Option<String>,
-  expected_value: String,
-  actual_memberkey: Option<String>,
-  actual_value: Value,
+use serde_json::{self, Map, Value};
+use std::{borrow::Cow, f64};
+pub use stream::validate_json_from_reader;
+#[cfg(feature = "yaml")]
+pub use yaml::{validate_yaml_from_str, validate_yaml_from_str_with_options};
+
+/// How byte strings, which JSON has no type for, are represented in JSON data
+/// being validated. Set via `ValidationOptions::json_bytes_encoding`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BytesEncoding {
+  /// Strings encoded as base64url, as recommended for converting CBOR to JSON
+  /// (RFC 8949, section 6.1). Padding is optional.
+  Base64Url,
+  /// Strings encoded as base16
+  Base16,
+  /// Byte strings don't match any JSON data
+  Forbid,
 }
 
-impl std::error::Error for JSONError {
-  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-    None
+// Deriving Default for enums isn't supported by the minimum supported Rust
+// version
+#[allow(clippy::derivable_impls)]
+impl Default for BytesEncoding {
+  fn default() -> Self {
+    BytesEncoding::Forbid
   }
 }
 
-impl fmt::Display for JSONError {
-  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    let actual_value = serde_json::to_string_pretty(&self.actual_value).map_err(|_| fmt::Error)?;
-
-    if let Some(emk) = &self.expected_memberkey {
-      if let Some(amk) = &self.actual_memberkey {
-        return write!(
-          f,
-          "expected: ( {} {} )\nactual: \"{}\": {}",
-          emk, self.expected_value, amk, actual_value
-        );
-      }
-
-      return write!(
-        f,
-        "expected: ( {} {} )\nactual: {}",
-        emk, self.expected_value, actual_value
-      );
-    }
-
-    if let Some(amk) = &self.actual_memberkey {
-      return write!(
-        f,
-        "expected: ( {} )\nactual: {}: {}",
-        self.expected_value, amk, actual_value
-      );
-    }
+/// Error type when validating JSON
+#[deprecated(note = "validation failures are reported as `validation::ValidationError`s")]
+pub type JSONError = ValidationError;
 
-    write!(
-      f,
-      "expected: ( {} )\nactual: {}\n",
-      self.expected_value, actual_value,
-    )
+impl<'a> Validator<Value> for CDDL<'a> {
+  fn validate(&self, value: &Value) -> Result {
+    self.validate_data(value)
   }
-}
 
-impl Into<Error> for JSONError {
-  fn into(self) -> Error {
-    Error::Target(Box::from(self))
+  fn validate_with_root(&self, rule_name: &str, value: &Value) -> Result {
+    self.validate_root_rule(self.root_ident(rule_name)?, value)
   }
-}
-
-impl<'a> Validator<Value> for CDDL<'a> {
-  fn validate(&self, value: &Value) -> Result {
-    for r in self.rules.iter() {
-      // First type rule is root
-      if let Rule::Type { rule, .. } = r {
-        return self.validate_type_rule(rule, None, None, None, value);
-      }
-    }
 
-    Ok(())
+  fn validate_value_at(&self, rule_name: &str, pointer: &str, value: &Value) -> Result {
+    self.validate_value_at_pointer(rule_name, pointer, value)
   }
 
   fn validate_rule_for_ident(
@@ -94,22 +70,36 @@ impl<'a> Validator<Value> for CDDL<'a> {
     occur: Option<&Occur>,
     value: &Value,
   ) -> Result {
-    for rule in self.rules.iter() {
-      match rule {
-        Rule::Type { rule, .. } if rule.name.ident == ident.ident => {
-          return self.validate_type_rule(&rule, expected_memberkey, actual_memberkey, occur, value)
-        }
-        Rule::Group { rule, .. } if rule.name.ident == ident.ident => {
-          return self.validate_group_rule(&rule, is_enumeration, occur, value)
-        }
-        _ => continue,
-      }
-    }
+    self.validate_rule_definitions(
+      ident,
+      None,
+      is_enumeration,
+      expected_memberkey,
+      actual_memberkey,
+      occur,
+      value,
+    )
+  }
 
-    Err(Error::Syntax(format!(
-      "No rule with name \"{}\" defined",
-      ident.ident
-    )))
+  fn validate_generic_rule(
+    &self,
+    ident: &Identifier,
+    generic_arg: &GenericArg,
+    is_enumeration: bool,
+    expected_memberkey: Option<String>,
+    actual_memberkey: Option<String>,
+    occur: Option<&Occur>,
+    value: &Value,
+  ) -> Result {
+    self.validate_rule_definitions(
+      ident,
+      Some(generic_arg),
+      is_enumeration,
+      expected_memberkey,
+      actual_memberkey,
+      occur,
+      value,
+    )
   }
 
   fn validate_type_rule(
@@ -147,28 +137,7 @@ impl<'a> Validator<Value> for CDDL<'a> {
     occur: Option<&Occur>,
     value: &Value,
   ) -> Result {
-    let mut validation_errors: Vec<Error> = Vec::new();
-
-    // Find the first type choice that validates to true
-    let find_type_choice = |t1| match self.validate_type1(
-      t1,
-      expected_memberkey.clone(),
-      actual_memberkey.clone(),
-      occur,
-      value,
-    ) {
-      Ok(()) => true,
-      Err(e) => {
-        validation_errors.push(e);
-        false
-      }
-    };
-
-    if t.type_choices.iter().any(find_type_choice) {
-      return Ok(());
-    }
-
-    Err(Error::MultiError(validation_errors))
+    self.validate_type_choices(t, expected_memberkey, actual_memberkey, occur, value)
   }
 
   fn validate_type1(
@@ -179,28 +148,9 @@ impl<'a> Validator<Value> for CDDL<'a> {
     occur: Option<&Occur>,
     value: &Value,
   ) -> Result {
-    if let Some((rco, t2)) = &t1.operator {
-      match rco {
-        RangeCtlOp::RangeOp { is_inclusive, .. } => {
-          return self.validate_range(&t1.type2, t2, *is_inclusive, value)
-        }
-        RangeCtlOp::CtlOp { ctrl, .. } => {
-          return self.validate_control_operator(&t1.type2, ctrl, t2, value)
-        }
-      }
-    }
-
-    self.validate_type2(
-      &t1.type2,
-      expected_memberkey,
-      actual_memberkey,
-      occur,
-      value,
-    )
+    self.validate_type1_operator(t1, expected_memberkey, actual_memberkey, occur, value)
   }
 
-  // TODO: Reduce cognitive complexity of this function
-  #[allow(clippy::cognitive_complexity)]
   fn validate_range(
     &self,
     lower: &Type2,
@@ -208,300 +158,7 @@ impl<'a> Validator<Value> for CDDL<'a> {
     is_inclusive: bool,
     value: &Value,
   ) -> Result {
-    if let Value::Number(n) = value {
-      // TODO: Per spec, if lower bound exceeds upper bound, resulting type is
-      // empty set. Not sure how this translates to numerical JSON validation.
-      match lower {
-        Type2::IntValue { value: li, .. } => match upper {
-          Type2::IntValue { value: ui, .. } => match n.as_i64() {
-            Some(ni) if is_inclusive => {
-              if ni >= *li as i64 && ni <= *ui as i64 {
-                Ok(())
-              } else {
-                Err(
-                  JSONError {
-                    expected_memberkey: None,
-                    expected_value: format!("Range: {} <= value <= {}", li, ui),
-                    actual_memberkey: None,
-                    actual_value: value.clone(),
-                  }
-                  .into(),
-                )
-              }
-            }
-            Some(ni) => {
-              if ni >= *li as i64 && ni < *ui as i64 {
-                Ok(())
-              } else {
-                Err(
-                  JSONError {
-                    expected_memberkey: None,
-                    expected_value: format!("Range: {} <= value < {}", li, ui),
-                    actual_memberkey: None,
-                    actual_value: value.clone(),
-                  }
-                  .into(),
-                )
-              }
-            }
-            None => Err(
-              JSONError {
-                expected_memberkey: None,
-                expected_value: format!("Range: {} <= value <= {}", li, ui),
-                actual_memberkey: None,
-                actual_value: value.clone(),
-              }
-              .into(),
-            ),
-          },
-          Type2::UintValue { value: ui, .. } => match n.as_i64() {
-            Some(ni) if is_inclusive => {
-              if ni >= *li as i64 && ni <= *ui as i64 {
-                Ok(())
-              } else {
-                Err(
-                  JSONError {
-                    expected_memberkey: None,
-                    expected_value: format!("Range: {} <= value <= {}", li, ui),
-                    actual_memberkey: None,
-                    actual_value: value.clone(),
-                  }
-                  .into(),
-                )
-              }
-            }
-            Some(ni) => {
-              if ni >= *li as i64 && ni < *ui as i64 {
-                Ok(())
-              } else {
-                Err(
-                  JSONError {
-                    expected_memberkey: None,
-                    expected_value: format!("Range: {} <= value < {}", li, ui),
-                    actual_memberkey: None,
-                    actual_value: value.clone(),
-                  }
-                  .into(),
-                )
-              }
-            }
-            None => Err(
-              JSONError {
-                expected_memberkey: None,
-                expected_value: format!("Range between {} and {}", li, ui),
-                actual_memberkey: None,
-                actual_value: value.clone(),
-              }
-              .into(),
-            ),
-          },
-          Type2::Typename { ident, .. } => match self.numerical_value_type_from_ident(ident) {
-            Some(t2) => {
-              let mut validation_errors: Vec<Error> = Vec::new();
-
-              if t2.iter().any(
-                |tc| match self.validate_range(lower, tc, is_inclusive, value) {
-                  Ok(()) => true,
-                  Err(e) => {
-                    validation_errors.push(e);
-                    false
-                  }
-                },
-              ) {
-                Ok(())
-              } else {
-                Err(Error::MultiError(validation_errors))
-              }
-            }
-            None => Err(Error::Syntax(format!(
-              "Invalid upper range value. Type {} not defined",
-              ident
-            ))),
-          },
-          _ => Err(Error::Syntax(format!(
-            "Invalid upper range value: Got {}",
-            upper
-          ))),
-        },
-        Type2::UintValue { value: li, .. } => match upper {
-          Type2::UintValue { value: ui, .. } => match n.as_u64() {
-            Some(ni) if is_inclusive => {
-              if ni >= *li as u64 && ni <= *ui as u64 {
-                Ok(())
-              } else {
-                Err(
-                  JSONError {
-                    expected_memberkey: None,
-                    expected_value: format!("Range: {} <= value <= {}", li, ui),
-                    actual_memberkey: None,
-                    actual_value: value.clone(),
-                  }
-                  .into(),
-                )
-              }
-            }
-            Some(ni) => {
-              if ni >= *li as u64 && ni < *ui as u64 {
-                Ok(())
-              } else {
-                Err(
-                  JSONError {
-                    expected_memberkey: None,
-                    expected_value: format!("Range: {} <= value < {}", li, ui),
-                    actual_memberkey: None,
-                    actual_value: value.clone(),
-                  }
-                  .into(),
-                )
-              }
-            }
-            None => Err(
-              JSONError {
-                expected_memberkey: None,
-                expected_value: format!("Range between {} and {}", li, ui),
-                actual_memberkey: None,
-                actual_value: value.clone(),
-              }
-              .into(),
-            ),
-          },
-          Type2::Typename { ident, .. } => match self.numerical_value_type_from_ident(ident) {
-            Some(t2) => {
-              let mut validation_errors: Vec<Error> = Vec::new();
-
-              if t2.iter().any(
-                |tc| match self.validate_range(lower, tc, is_inclusive, value) {
-                  Ok(()) => true,
-                  Err(e) => {
-                    validation_errors.push(e);
-                    false
-                  }
-                },
-              ) {
-                Ok(())
-              } else {
-                Err(Error::MultiError(validation_errors))
-              }
-            }
-            None => Err(Error::Syntax(format!(
-              "Invalid upper range value. Type {} not defined",
-              ident
-            ))),
-          },
-          _ => Err(Error::Syntax(format!(
-            "Invalid upper range value: Got {}",
-            upper
-          ))),
-        },
-        Type2::FloatValue { value: lf, .. } => match upper {
-          Type2::FloatValue { value: uf, .. } => match n.as_f64() {
-            Some(nf) if is_inclusive => {
-              if nf >= *lf && nf <= *uf {
-                Ok(())
-              } else {
-                Err(
-                  JSONError {
-                    expected_memberkey: None,
-                    expected_value: format!("Range: {} <= value <= {}", lf, uf),
-                    actual_memberkey: None,
-                    actual_value: value.clone(),
-                  }
-                  .into(),
-                )
-              }
-            }
-            Some(nf) => {
-              if nf >= *lf && nf < *uf {
-                Ok(())
-              } else {
-                Err(
-                  JSONError {
-                    expected_memberkey: None,
-                    expected_value: format!("Range: {} <= value < {}", lf, uf),
-                    actual_memberkey: None,
-                    actual_value: value.clone(),
-                  }
-                  .into(),
-                )
-              }
-            }
-            None => Err(
-              JSONError {
-                expected_memberkey: None,
-                expected_value: format!("Range between {} and {}", lf, uf),
-                actual_memberkey: None,
-                actual_value: value.clone(),
-              }
-              .into(),
-            ),
-          },
-          Type2::Typename { ident, .. } => match self.numerical_value_type_from_ident(ident) {
-            Some(t2) => {
-              let mut validation_errors: Vec<Error> = Vec::new();
-
-              if t2.iter().any(
-                |tc| match self.validate_range(lower, tc, is_inclusive, value) {
-                  Ok(()) => true,
-                  Err(e) => {
-                    validation_errors.push(e);
-                    false
-                  }
-                },
-              ) {
-                Ok(())
-              } else {
-                Err(Error::MultiError(validation_errors))
-              }
-            }
-            None => Err(Error::Syntax(format!(
-              "Invalid upper range value. Type {} not defined",
-              ident
-            ))),
-          },
-          _ => Err(Error::Syntax(format!(
-            "Invalid upper range value: Got {}",
-            upper
-          ))),
-        },
-        Type2::Typename { ident, .. } => match self.numerical_value_type_from_ident(ident) {
-          Some(t2) => {
-            let mut validation_errors: Vec<Error> = Vec::new();
-
-            if t2.iter().any(
-              |tc| match self.validate_range(tc, upper, is_inclusive, value) {
-                Ok(()) => true,
-                Err(e) => {
-                  validation_errors.push(e);
-                  false
-                }
-              },
-            ) {
-              Ok(())
-            } else {
-              Err(Error::MultiError(validation_errors))
-            }
-          }
-          None => Err(Error::Syntax(format!(
-            "Invalid lower range value. Type with name {} not defined",
-            lower
-          ))),
-        },
-        _ => Err(Error::Syntax(format!(
-          "Invalid lower range value: Got {}",
-          lower
-        ))),
-      }
-    } else {
-      Err(
-        JSONError {
-          expected_memberkey: None,
-          expected_value: format!("Expected numerical value between {} and {}", lower, upper),
-          actual_memberkey: None,
-          actual_value: value.clone(),
-        }
-        .into(),
-      )
-    }
+    self.validate_numeric_range(lower, upper, is_inclusive, value)
   }
 
   fn validate_control_operator(
@@ -511,212 +168,7 @@ impl<'a> Validator<Value> for CDDL<'a> {
     controller: &Type2,
     value: &Value,
   ) -> Result {
-    let mut errors: Vec<Error> = Vec::new();
-
-    match token::lookup_control_from_str(operator) {
-      t @ Some(Token::PCRE) | t @ Some(Token::CREGEXP) => {
-        if t == Some(Token::CREGEXP) {
-          println!("NOTE: Only Perl-compatible regex is supported.\nThis crate evaluates the .regexp operator as an alias for the .pcre extension operator\n");
-        }
-
-        if !self.is_type_string_data_type(target) {
-          return Err(Error::Syntax(format!(
-            "the {} control operator is only defined for the text type. Got {}",
-            Token::PCRE,
-            target
-          )));
-        }
-
-        let find_valid_value = |c: &str| -> bool {
-          match validate_pcre_control(&c, value) {
-            Ok(()) => true,
-            Err(e) => {
-              errors.push(e);
-
-              false
-            }
-          }
-        };
-
-        if self
-          .text_values_from_type(controller)?
-          .into_iter()
-          .any(find_valid_value)
-        {
-          Ok(())
-        } else {
-          Err(Error::MultiError(errors))
-        }
-      }
-      Some(Token::LT) => {
-        if !self.is_type_numeric_data_type(target) {
-          return Err(Error::Syntax(format!(
-            "the {} control operator is only defined for the numeric type. Got {}",
-            Token::LT,
-            target
-          )));
-        }
-
-        let find_valid_value = |n: Numeric| -> bool {
-          match validate_lt_control(n, value) {
-            Ok(()) => true,
-            Err(e) => {
-              errors.push(e);
-
-              false
-            }
-          }
-        };
-
-        if self
-          .numeric_values_from_type(target, controller)?
-          .into_iter()
-          .any(find_valid_value)
-        {
-          Ok(())
-        } else {
-          Err(Error::MultiError(errors))
-        }
-      }
-      Some(Token::LE) => {
-        if !self.is_type_numeric_data_type(target) {
-          return Err(Error::Syntax(format!(
-            "the {} control operator is only defined for the numeric type. Got {}",
-            Token::LT,
-            target
-          )));
-        }
-
-        let find_valid_value = |n: Numeric| -> bool {
-          match validate_le_control(n, value) {
-            Ok(()) => true,
-            Err(e) => {
-              errors.push(e);
-
-              false
-            }
-          }
-        };
-
-        if self
-          .numeric_values_from_type(target, controller)?
-          .into_iter()
-          .any(find_valid_value)
-        {
-          Ok(())
-        } else {
-          Err(Error::MultiError(errors))
-        }
-      }
-      Some(Token::GT) => {
-        if !self.is_type_numeric_data_type(target) {
-          return Err(Error::Syntax(format!(
-            "the {} control operator is only defined for the numeric type. Got {}",
-            Token::LT,
-            target
-          )));
-        }
-
-        let find_valid_value = |n: Numeric| -> bool {
-          match validate_gt_control(n, value) {
-            Ok(()) => true,
-            Err(e) => {
-              errors.push(e);
-
-              false
-            }
-          }
-        };
-
-        if self
-          .numeric_values_from_type(target, controller)?
-          .into_iter()
-          .any(find_valid_value)
-        {
-          Ok(())
-        } else {
-          Err(Error::MultiError(errors))
-        }
-      }
-      Some(Token::GE) => {
-        if !self.is_type_numeric_data_type(target) {
-          return Err(Error::Syntax(format!(
-            "the {} control operator is only defined for the numeric type. Got {}",
-            Token::LT,
-            target
-          )));
-        }
-
-        let find_valid_value = |n: Numeric| -> bool {
-          match validate_ge_control(n, value) {
-            Ok(()) => true,
-            Err(e) => {
-              errors.push(e);
-
-              false
-            }
-          }
-        };
-
-        if self
-          .numeric_values_from_type(target, controller)?
-          .into_iter()
-          .any(find_valid_value)
-        {
-          Ok(())
-        } else {
-          Err(Error::MultiError(errors))
-        }
-      }
-      Some(Token::EQ) => {
-        if self.is_type_numeric_data_type(target) {
-          let find_valid_value = |n: Numeric| -> bool {
-            match validate_eq_numeric_control(n, value) {
-              Ok(()) => true,
-              Err(e) => {
-                errors.push(e);
-
-                false
-              }
-            }
-          };
-
-          if self
-            .numeric_values_from_type(target, controller)?
-            .into_iter()
-            .any(find_valid_value)
-          {
-            Ok(())
-          } else {
-            Err(Error::MultiError(errors))
-          }
-        } else if self.is_type_string_data_type(target) {
-          let find_valid_value = |c: &str| -> bool {
-            match validate_eq_text_control(&c, value) {
-              Ok(()) => true,
-              Err(e) => {
-                errors.push(e);
-
-                false
-              }
-            }
-          };
-
-          if self
-            .text_values_from_type(controller)?
-            .into_iter()
-            .any(find_valid_value)
-          {
-            Ok(())
-          } else {
-            Err(Error::MultiError(errors))
-          }
-        } else {
-          unimplemented!()
-        }
-      }
-      _ => unimplemented!(),
-    }
+    self.validate_control(target, operator, controller, value)
   }
 
   fn validate_type2(
@@ -730,55 +182,75 @@ impl<'a> Validator<Value> for CDDL<'a> {
     match t2 {
       Type2::TextValue { value: t, .. } => match value {
         Value::String(s) if t == s => Ok(()),
-        _ => Err(
-          JSONError {
-            expected_memberkey,
-            expected_value: t2.to_string(),
-            actual_memberkey,
-            actual_value: value.clone(),
-          }
-          .into(),
-        ),
+        _ => Err(ValidationError::new(expected_memberkey, t2, actual_memberkey, value).into()),
       },
       Type2::IntValue { .. } | Type2::UintValue { .. } | Type2::FloatValue { .. } => match value {
-        Value::Number(_) => validate_numeric_value(t2, value),
-        _ => Err(
-          JSONError {
-            expected_memberkey,
-            expected_value: t2.to_string(),
-            actual_memberkey,
-            actual_value: value.clone(),
-          }
-          .into(),
-        ),
+        Value::Number(_) => {
+          validate_numeric_value(self.validation_options.float_comparison, t2, value)
+        }
+        _ => Err(ValidationError::new(expected_memberkey, t2, actual_memberkey, value).into()),
       },
+      Type2::UTF8ByteString { .. } | Type2::B16ByteString { .. } | Type2::B64ByteString { .. }
+        if self.validation_options.json_bytes_encoding != BytesEncoding::Forbid =>
+      {
+        let matches = match value {
+          Value::String(s) => {
+            let bytes = self.json_bytes(s);
+            bytes.is_some() && bytes == byte_string_literal(t2)
+          }
+          _ => false,
+        };
+
+        if matches {
+          return Ok(());
+        }
+
+        Err(ValidationError::new(expected_memberkey, t2, actual_memberkey, value).into())
+      }
       // If type name identifier is 'any'
       Type2::Typename { ident, .. } if ident.ident == "any" => Ok(()),
-      // TODO: evaluate genericarg
+      // References to generic rules, e.g. pair<int>
+      Type2::Typename {
+        ident,
+        generic_arg: Some(ga),
+        ..
+      } => self.validate_generic_rule(
+        ident,
+        ga,
+        false,
+        expected_memberkey,
+        actual_memberkey,
+        occur,
+        value,
+      ),
       Type2::Typename { ident, .. } => match value {
-        Value::Null => expect_null(&ident.ident),
-        Value::Bool(_) => self.expect_bool(&ident.ident, value),
-        Value::String(s) => match ident.ident {
-          "tstr" | "text" => Ok(()),
-          "tdate" => validate_tdate(s),
-          #[cfg(feature = "nightly")]
-          "uri" => validate_uri(s),
-          #[cfg(not(feature = "nightly"))]
-          "uri" => {
-            println!("NOTE: Validation against the \"uri\" data type is only supported by versions of this crate built with unstable Rust\n");
-
-            Ok(())
+        Value::Null | Value::Bool(_) => {
+          self.validate_simple_value(ident, expected_memberkey, actual_memberkey, occur, value)
+        }
+        Value::String(s)
+          if is_byte_string_type(ident.ident)
+            && self.validation_options.json_bytes_encoding != BytesEncoding::Forbid =>
+        {
+          match self.json_bytes(s) {
+            Some(_) => Ok(()),
+            None => Err(
+              ValidationError::new(expected_memberkey, ident.ident, actual_memberkey, value).into(),
+            ),
           }
-          _ => {
-            if is_type_json_prelude(&ident.ident) {
+        }
+        Value::String(s) => match self.validate_prelude_text(
+          ident.ident,
+          s,
+          expected_memberkey.clone(),
+          actual_memberkey.clone(),
+          value,
+        ) {
+          Some(result) => result,
+          None => {
+            if is_type_json_prelude(ident.ident) {
               return Err(
-                JSONError {
-                  expected_memberkey,
-                  expected_value: ident.ident.to_string(),
-                  actual_memberkey,
-                  actual_value: value.clone(),
-                }
-                .into(),
+                ValidationError::new(expected_memberkey, ident.ident, actual_memberkey, value)
+                  .into(),
               );
             }
 
@@ -792,10 +264,18 @@ impl<'a> Validator<Value> for CDDL<'a> {
             )
           }
         },
-        Value::Number(_) => {
-          self.validate_numeric_data_type(expected_memberkey, actual_memberkey, &ident.ident, value)
+        Value::Number(_) if is_numeric_data_type(ident.ident) => {
+          self.validate_numeric_data_type(expected_memberkey, actual_memberkey, ident.ident, value)
         }
-        Value::Object(_) => self.validate_rule_for_ident(
+        Value::Number(_) => self.validate_rule_for_ident(
+          ident,
+          false,
+          expected_memberkey,
+          actual_memberkey,
+          occur,
+          value,
+        ),
+        Value::Object(_) => self.validate_rule_for_ident(
           ident,
           false,
           expected_memberkey,
@@ -813,32 +293,33 @@ impl<'a> Validator<Value> for CDDL<'a> {
         ),
       },
       Type2::Array { group, .. } => match value {
-        Value::Array(_) => self.validate_group(group, occur, value),
-        _ => Err(
-          JSONError {
-            expected_memberkey,
-            expected_value: t2.to_string(),
-            actual_memberkey,
-            actual_value: value.clone(),
-          }
-          .into(),
-        ),
+        Value::Array(values) => {
+          self.check_array_length(values.len())?;
+
+          self.validate_group(group, occur, value)
+        }
+        _ => Err(ValidationError::new(expected_memberkey, t2, actual_memberkey, value).into()),
       },
       Type2::Map { group, .. } => match value {
-        Value::Object(_) => self.validate_group(group, occur, value),
-        _ => Err(
-          JSONError {
-            expected_memberkey,
-            expected_value: t2.to_string(),
-            actual_memberkey,
-            actual_value: value.clone(),
-          }
-          .into(),
-        ),
+        Value::Object(om) => self.validate_object(group, occur, om, value),
+        _ => Err(ValidationError::new(expected_memberkey, t2, actual_memberkey, value).into()),
       },
       Type2::ChoiceFromInlineGroup { group, .. } => {
         self.validate_group_to_choice_enum(group, occur, value)
       }
+      Type2::ChoiceFromGroup {
+        ident,
+        generic_arg: Some(ga),
+        ..
+      } => self.validate_generic_rule(
+        ident,
+        ga,
+        true,
+        expected_memberkey,
+        actual_memberkey,
+        occur,
+        value,
+      ),
       Type2::ChoiceFromGroup { ident, .. } => self.validate_rule_for_ident(
         ident,
         true,
@@ -847,64 +328,30 @@ impl<'a> Validator<Value> for CDDL<'a> {
         occur,
         value,
       ),
-      _ => Err(Error::Syntax(format!(
-        "CDDL type {} can't be used to validate JSON {}",
-        t2, value
-      ))),
+      // JSON has no notion of tags, so tagged data items (e.g. those used by the
+      // standard prelude) are validated against their tagged value
+      Type2::TaggedData { t, .. } => {
+        self.validate_type(t, expected_memberkey, actual_memberkey, occur, value)
+      }
+      Type2::ParenthesizedType { pt, .. } => {
+        self.validate_type(pt, expected_memberkey, actual_memberkey, occur, value)
+      }
+      Type2::Any(_) => Ok(()),
+      _ => Err(ValidationError::new(expected_memberkey, t2, actual_memberkey, value).into()),
     }
   }
 
   fn validate_group_to_choice_enum(
     &self,
     g: &Group,
-    occur: Option<&Occur>,
+    _occur: Option<&Occur>,
     value: &Value,
   ) -> Result {
-    let mut validation_errors: Vec<Error> = Vec::new();
-
-    let validate_type_from_group_entry = |gc: &GroupChoice| {
-      gc.group_entries.iter().any(|ge| match &ge.0 {
-        // Member names have only "documentary value" when evaluating an
-        // enumeration expression
-        GroupEntry::ValueMemberKey { ge, .. } => {
-          match self.validate_type(&ge.entry_type, None, None, occur, value) {
-            Ok(()) => true,
-            Err(e) => {
-              validation_errors.push(e);
-              false
-            }
-          }
-        }
-        _ => false,
-      })
-    };
-
-    if g.group_choices.iter().any(validate_type_from_group_entry) {
-      return Ok(());
-    };
-
-    Err(Error::MultiError(validation_errors))
+    self.validate_choice_from_group(g, value)
   }
 
   fn validate_group(&self, g: &Group, occur: Option<&Occur>, value: &Value) -> Result {
-    let mut validation_errors: Vec<Error> = Vec::new();
-
-    // Find the first group choice that validates to true
-    if g
-      .group_choices
-      .iter()
-      .any(|gc| match self.validate_group_choice(gc, occur, value) {
-        Ok(()) => true,
-        Err(e) => {
-          validation_errors.push(e);
-          false
-        }
-      })
-    {
-      return Ok(());
-    }
-
-    Err(Error::MultiError(validation_errors))
+    self.validate_group_choices(g, occur, value)
   }
 
   fn validate_group_choice(
@@ -913,6 +360,41 @@ impl<'a> Validator<Value> for CDDL<'a> {
     occur: Option<&Occur>,
     value: &Value,
   ) -> Result {
+    if let Value::Array(values) = value {
+      // [ * reputon ]
+      if let [(GroupEntry::TypeGroupname { ge: tge, .. }, _)] = gc.group_entries.as_slice() {
+        if let Some(o) = &tge.occur {
+          if !self.is_group_rule(&tge.name) {
+            self.validate_array_occurrence(o, &tge.name.to_string(), values)?;
+
+            if let Occur::ZeroOrMore(_) = o {
+              if values.is_empty() {
+                return Ok(());
+              }
+            }
+
+            if is_type_json_prelude(tge.name.ident) {
+              let t2 = Type2::Typename {
+                ident: tge.name.clone(),
+                generic_arg: tge.generic_arg.clone(),
+                span: (0, 0, 0),
+              };
+
+              return validate_elements(values, |v| self.validate_type2(&t2, None, None, None, v));
+            }
+
+            return validate_elements(values, |v| match &tge.generic_arg {
+              Some(ga) => self.validate_generic_rule(&tge.name, ga, false, None, None, None, v),
+              None => self.validate_rule_for_ident(&tge.name, false, None, None, None, v),
+            });
+          }
+        }
+      }
+
+      // [ a: int, b: tstr ] and [ (int, int) // (tstr) ]
+      return self.validate_array_elements(gc, occur, values, value);
+    }
+
     let mut errors: Vec<Error> = Vec::new();
 
     // Check for a wildcard entry
@@ -938,110 +420,40 @@ impl<'a> Validator<Value> for CDDL<'a> {
       _ => None,
     });
 
-    for (idx, ge) in gc.group_entries.iter().enumerate() {
-      match value {
-        Value::Array(values) => {
-          // [ ( a: int, b: tstr ) ]
-          if let GroupEntry::InlineGroup { group, .. } = &ge.0 {
-            self.validate_group(group, None, value)?;
-            continue;
-          }
-
-          if let GroupEntry::TypeGroupname { ge: tge, .. } = &ge.0 {
-            // [ * reputon ]
-            if gc.group_entries.len() == 1 {
-              if let Some(o) = &tge.occur {
-                self.validate_array_occurrence(o, &tge.name.to_string(), values)?;
-
-                if let Occur::ZeroOrMore(_) = o {
-                  if values.is_empty() {
-                    return Ok(());
-                  }
-                }
-
-                if is_type_json_prelude(&tge.name.ident) {
-                  let validate_all_values = |v| {
-                    self
-                      .validate_type2(
-                        &Type2::Typename {
-                          ident: tge.name.clone(),
-                          generic_arg: tge.generic_arg.clone(),
-                          span: (0, 0, 0),
-                        },
-                        None,
-                        None,
-                        None,
-                        v,
-                      )
-                      .is_ok()
-                  };
-
-                  if values.iter().all(validate_all_values) {
-                    return Ok(());
-                  } else {
-                    return Err(
-                      JSONError {
-                        expected_memberkey: None,
-                        expected_value: gc.to_string(),
-                        actual_memberkey: None,
-                        actual_value: value.clone(),
-                      }
-                      .into(),
-                    );
-                  }
-                }
+    let member_names = gc
+      .group_entries
+      .iter()
+      .filter_map(|ge| match &ge.0 {
+        GroupEntry::ValueMemberKey { ge, .. } => ge.member_key.as_ref().and_then(member_name),
+        _ => None,
+      })
+      .collect::<Vec<_>>();
 
-                let validate_all_values = |v| {
-                  self
-                    .validate_rule_for_ident(&tge.name, false, None, None, None, v)
-                    .is_ok()
-                };
-
-                if values.iter().all(validate_all_values) {
-                  return Ok(());
-                } else {
-                  return Err(
-                    JSONError {
-                      expected_memberkey: None,
-                      expected_value: gc.to_string(),
-                      actual_memberkey: None,
-                      actual_value: value.clone(),
-                    }
-                    .into(),
-                  );
-                }
+    for ge in gc.group_entries.iter() {
+      match value {
+        // Validate the object key/value pairs against each group entry,
+        // collecting errors along the way. Entries whose member names are
+        // matched by a type, e.g. * tstr => any, are left with the members
+        // that no explicitly named entry consumes.
+        Value::Object(om) => {
+          let result = match &ge.0 {
+            GroupEntry::ValueMemberKey { ge: vmke, .. } => match &vmke.member_key {
+              Some(mk @ MemberKey::Type1 { t1, .. }) if member_name(mk).is_none() => {
+                self.validate_members_with_key_type(vmke, mk, t1, occur, om, &member_names, value)
               }
-            }
-          }
+              // CDDL { x: int, ? [int] } validates JSON { "x": 1 }
+              None if !is_unwrap(&vmke.entry_type) => self.validate_keyless_entry(vmke, value),
+              _ => self.validate_group_entry(&ge.0, false, wildcard_entry, occur, value),
+            },
+            _ => self.validate_group_entry(&ge.0, false, wildcard_entry, occur, value),
+          };
 
-          // [ a: int, b: tstr ]
-          if let GroupEntry::ValueMemberKey { ge: vmke, .. } = &ge.0 {
-            // Ignore name/value entries with an occurrence indicator to avoid ambiguity
-            if vmke.occur.is_none() {
-              if let Some(v) = values.get(idx) {
-                self.validate_group_entry(&ge.0, false, None, occur, v)?;
-              }
-            }
-          }
-        }
-        // Validate the object key/value pairs against each group entry,
-        // collecting errors along the way
-        Value::Object(_) => {
-          match self.validate_group_entry(&ge.0, false, wildcard_entry, occur, value) {
-            Ok(()) => continue,
-            Err(e) => errors.push(e),
+          if let Err(e) = result {
+            errors.push(e);
           }
         }
         _ => {
-          return Err(
-            JSONError {
-              expected_memberkey: None,
-              expected_value: gc.to_string(),
-              actual_memberkey: None,
-              actual_value: value.clone(),
-            }
-            .into(),
-          );
+          return Err(ValidationError::new(None, gc, None, value).into());
         }
       }
     }
@@ -1067,89 +479,42 @@ impl<'a> Validator<Value> for CDDL<'a> {
           match mk {
             MemberKey::Type1 { t1, is_cut, .. } => match &t1.type2 {
               // CDDL { "my-key" => tstr, } validates JSON { "my-key": "myvalue" }
-              Type2::TextValue { value: t, .. } => match value {
-                Value::Object(om) => {
-                  if !is_type_json_prelude(&vmke.entry_type.to_string()) {
-                    if let Some(v) = om.get(*t) {
-                      return self.validate_type(
-                        &vmke.entry_type,
-                        Some(mk.to_string()),
-                        Some((*t).to_string()),
-                        occur,
-                        v,
-                      );
-                    }
-
-                    return self.validate_type(
-                      &vmke.entry_type,
-                      Some(mk.to_string()),
-                      None,
-                      occur,
-                      value,
-                    );
-                  }
+              Type2::TextValue { value: t, .. } => {
+                self.validate_text_member(ge, vmke, mk, t, *is_cut, wildcard_entry, occur, value)
+              }
 
-                  if let Some(v) = om.get(*t) {
-                    let r = self.validate_type(
-                      &vmke.entry_type,
-                      Some(mk.to_string()),
-                      Some((*t).to_string()),
-                      occur,
-                      v,
-                    );
-
-                    if r.is_err() && !is_cut {
-                      if let Some(entry_type) = wildcard_entry {
-                        return self.validate_type(
-                          entry_type,
-                          Some(mk.to_string()),
-                          Some((*t).to_string()),
-                          occur,
-                          v,
-                        );
-                      }
-                    }
-
-                    r
-                  } else {
-                    Err(
-                      JSONError {
-                        expected_memberkey: Some(mk.to_string()),
-                        expected_value: ge.to_string(),
-                        actual_memberkey: None,
-                        actual_value: value.clone(),
-                      }
-                      .into(),
-                    )
-                  }
+              // CDDL { * tstr => any } validates { "otherkey1": "anyvalue", "otherkey2": true }
+              // and CDDL { label => int } validates { "my-label": 1 } given
+              // label = tstr .size 8
+              _ => match value {
+                Value::Object(om) => {
+                  self.validate_members_with_key_type(vmke, mk, t1, occur, om, &[], value)
                 }
-                // Otherwise, validate JSON against the type of the entry.
-                // Matched when in an array and the key for the group entry is
-                // ignored.
-                // CDDL [ city: tstr, ] validates JSON [ "city" ]
                 _ => self.validate_type(&vmke.entry_type, Some(mk.to_string()), None, occur, value),
               },
-
-              // CDDL { * tstr => any } validates { "otherkey1": "anyvalue", "otherkey2": true }
-              Type2::Typename { ident, .. } if ident.ident == "tstr" || ident.ident == "text" => {
-                Ok(())
-              }
-              _ => Err(Error::Syntax(
-                "CDDL member key must be quoted string or bareword for validating JSON objects"
-                  .to_string(),
-              )),
             },
+            // CDDL { "my-key": tstr } validates JSON { "my-key": "myvalue" }
+            MemberKey::Value {
+              value: token::Value::TEXT(t),
+              ..
+            } => self.validate_text_member(ge, vmke, mk, t, true, wildcard_entry, occur, value),
             MemberKey::Bareword { ident, .. } => match value {
               Value::Object(om) => {
                 if !is_type_json_prelude(&vmke.entry_type.to_string()) {
                   if let Some(v) = om.get(ident.ident) {
-                    return self.validate_type(
-                      &vmke.entry_type,
-                      Some(mk.to_string()),
-                      Some((ident.ident).to_string()),
-                      vmke.occur.as_ref(),
-                      v,
-                    );
+                    return at(ident.ident, || {
+                      self.validate_type(
+                        &vmke.entry_type,
+                        Some(mk.to_string()),
+                        Some((ident.ident).to_string()),
+                        vmke.occur.as_ref(),
+                        v,
+                      )
+                    });
+                  }
+
+                  if is_optional_member(vmke, occur) {
+                    return Ok(());
                   }
 
                   return self.validate_type(
@@ -1162,36 +527,25 @@ impl<'a> Validator<Value> for CDDL<'a> {
                 }
 
                 match om.get(ident.ident) {
-                  Some(v) => self.validate_type(
-                    &vmke.entry_type,
-                    Some(mk.to_string()),
-                    Some(ident.ident.to_string()),
-                    vmke.occur.as_ref(),
-                    v,
+                  Some(v) => at(ident.ident, || {
+                    self.validate_type(
+                      &vmke.entry_type,
+                      Some(mk.to_string()),
+                      Some(ident.ident.to_string()),
+                      vmke.occur.as_ref(),
+                      v,
+                    )
+                  }),
+                  None if is_optional_member(vmke, occur) => Ok(()),
+                  None => Err(
+                    ValidationError::new(
+                      Some(mk.to_string()),
+                      &format_args!("{} {}", mk, vmke.entry_type),
+                      None,
+                      value,
+                    )
+                    .into(),
                   ),
-                  None => match occur {
-                    Some(o) => match o {
-                      Occur::Optional(_) | Occur::OneOrMore(_) => Ok(()),
-                      _ => Err(
-                        JSONError {
-                          expected_memberkey: Some(mk.to_string()),
-                          expected_value: format!("{} {}", mk, vmke.entry_type),
-                          actual_memberkey: None,
-                          actual_value: value.clone(),
-                        }
-                        .into(),
-                      ),
-                    },
-                    None => Err(
-                      JSONError {
-                        expected_memberkey: Some(mk.to_string()),
-                        expected_value: format!("{} {}", mk, vmke.entry_type),
-                        actual_memberkey: None,
-                        actual_value: value.clone(),
-                      }
-                      .into(),
-                    ),
-                  },
                 }
               }
               _ => self.validate_type(
@@ -1202,33 +556,82 @@ impl<'a> Validator<Value> for CDDL<'a> {
                 value,
               ),
             },
-            _ => Err(Error::Syntax(
-              "CDDL member key must be quoted string or bareword for validating JSON objects"
-                .to_string(),
-            )),
+            // JSON objects only have text keys, so none of their members
+            // matches any other member key, e.g. `1: tstr`
+            _ => match value {
+              Value::Object(_) if is_optional_member(vmke, occur) => Ok(()),
+              Value::Object(_) => Err(
+                ValidationError::new(
+                  Some(mk.to_string()),
+                  &format_args!("{} {}", mk, vmke.entry_type),
+                  None,
+                  value,
+                )
+                .into(),
+              ),
+              _ => self.validate_type(
+                &vmke.entry_type,
+                Some(mk.to_string()),
+                None,
+                vmke.occur.as_ref(),
+                value,
+              ),
+            },
           }
         } else {
           self.validate_type(&vmke.entry_type, None, None, occur, value)
         }
       }
-      GroupEntry::TypeGroupname { ge: tge, .. } => self.validate_rule_for_ident(
-        &tge.name,
-        is_enumeration,
-        None,
-        None,
-        tge.occur.as_ref(),
-        value,
-      ),
+      GroupEntry::TypeGroupname { ge: tge, span } => match (&tge.occur, value) {
+        // CDDL { a: int, ? ext } where ext = (b: int, c: tstr) validates JSON
+        // objects with either both or neither of the members of ext
+        (Some(o), Value::Object(_)) if !is_enumeration && self.is_group_rule(&tge.name) => {
+          self.validate_group_name_in_map(tge, o, *span, value)
+        }
+        _ => match &tge.generic_arg {
+          Some(ga) => self.validate_generic_rule(
+            &tge.name,
+            ga,
+            is_enumeration,
+            None,
+            None,
+            tge.occur.as_ref(),
+            value,
+          ),
+          None => self.validate_rule_for_ident(
+            &tge.name,
+            is_enumeration,
+            None,
+            None,
+            tge.occur.as_ref(),
+            value,
+          ),
+        },
+      },
       GroupEntry::InlineGroup {
         occur: igo,
         group: g,
         ..
       } => {
-        if igo.is_some() {
+        if let Some(o) = igo {
           if is_enumeration {
             return self.validate_group_to_choice_enum(g, igo.as_ref(), value);
           }
 
+          // CDDL { ? (foo: int, bar: tstr) } validates JSON objects with
+          // either both members or neither, since a group of named members
+          // can't be repeated within the same object
+          if let Value::Object(om) = value {
+            let names = group_member_names(g);
+            if !names.is_empty() {
+              if occurrence_bounds(Some(o)).0 == 0 && !names.iter().any(|n| om.contains_key(*n)) {
+                return Ok(());
+              }
+
+              return self.validate_group(g, None, value);
+            }
+          }
+
           self.validate_group(g, igo.as_ref(), value)
         } else {
           if is_enumeration {
@@ -1241,110 +644,11 @@ impl<'a> Validator<Value> for CDDL<'a> {
   }
 
   fn validate_array_occurrence(&self, occur: &Occur, group: &str, values: &[Value]) -> Result {
-    match occur {
-      Occur::ZeroOrMore(_) | Occur::Optional(_) => Ok(()),
-      Occur::OneOrMore(_) => {
-        if values.is_empty() {
-          Err(Error::Occurrence(format!(
-            "Expecting one or more values of group {}",
-            group
-          )))
-        } else {
-          Ok(())
-        }
-      }
-      Occur::Exact { lower, upper, .. } => {
-        if let Some(li) = lower {
-          if let Some(ui) = upper {
-            if values.len() < *li || values.len() > *ui {
-              if li == ui {
-                return Err(Error::Occurrence(format!(
-                  "Expecting exactly {} values of group {}. Got {} values",
-                  li,
-                  group,
-                  values.len()
-                )));
-              }
-
-              return Err(Error::Occurrence(format!(
-                "Expecting between {} and {} values of group {}. Got {} values",
-                li,
-                ui,
-                group,
-                values.len()
-              )));
-            }
-          }
-
-          if values.len() < *li {
-            return Err(Error::Occurrence(format!(
-              "Expecting at least {} values of group {}. Got {} values",
-              li,
-              group,
-              values.len()
-            )));
-          }
-        }
-
-        if let Some(ui) = upper {
-          if values.len() > *ui {
-            return Err(Error::Occurrence(format!(
-              "Expecting no more than {} values of group {}. Got {} values",
-              ui,
-              group,
-              values.len()
-            )));
-          }
-        }
-
-        Ok(())
-      }
-    }
+    validate_occurrence_count(occur, group, values.len())
   }
 
   fn expect_bool(&self, ident: &str, value: &Value) -> Result {
-    match value {
-      Value::Bool(b) => {
-        if ident == "bool" {
-          return Ok(());
-        }
-
-        if let Ok(bfs) = ident.parse::<bool>() {
-          if bfs == *b {
-            return Ok(());
-          }
-
-          return Err(
-            JSONError {
-              expected_memberkey: None,
-              expected_value: ident.to_string(),
-              actual_memberkey: None,
-              actual_value: value.clone(),
-            }
-            .into(),
-          );
-        }
-
-        Err(
-          JSONError {
-            expected_memberkey: None,
-            expected_value: ident.to_string(),
-            actual_memberkey: None,
-            actual_value: value.clone(),
-          }
-          .into(),
-        )
-      }
-      _ => Err(
-        JSONError {
-          expected_memberkey: None,
-          expected_value: ident.to_string(),
-          actual_memberkey: None,
-          actual_value: value.clone(),
-        }
-        .into(),
-      ),
-    }
+    self.validate_bool(ident, value)
   }
 
   fn validate_numeric_data_type(
@@ -1354,192 +658,584 @@ impl<'a> Validator<Value> for CDDL<'a> {
     ident: &str,
     value: &Value,
   ) -> Result {
-    match value {
+    let is_valid = match value {
       Value::Number(n) => match ident {
-        "uint" => n
-          .as_u64()
-          .ok_or_else(|| {
-            JSONError {
-              expected_memberkey,
-              expected_value: ident.to_string(),
-              actual_memberkey,
-              actual_value: value.clone(),
-            }
-            .into()
-          })
-          .map(|_| ()),
+        "uint" => n.is_u64(),
         "nint" => match n.as_i64() {
-          Some(n64) if n64 < 0 => Ok(()),
-          _ => Err(
-            JSONError {
-              expected_memberkey,
-              expected_value: ident.to_string(),
-              actual_memberkey,
-              actual_value: value.clone(),
-            }
-            .into(),
-          ),
-        },
-        "int" => n
-          .as_i64()
-          .ok_or_else(|| {
-            JSONError {
-              expected_memberkey,
-              expected_value: ident.to_string(),
-              actual_memberkey,
-              actual_value: value.clone(),
-            }
-            .into()
-          })
-          .map(|_| ()),
-        "number" => Ok(()),
-        "float16" => match n.as_f64() {
-          Some(_) => Ok(()),
-          _ => Err(
-            JSONError {
-              expected_memberkey,
-              expected_value: ident.to_string(),
-              actual_memberkey,
-              actual_value: value.clone(),
-            }
-            .into(),
-          ),
-        },
-        // TODO: Finish rest of numerical data types
-        "float32" => match n.as_f64() {
-          Some(_) => Ok(()),
-          _ => Err(
-            JSONError {
-              expected_memberkey,
-              expected_value: ident.to_string(),
-              actual_memberkey,
-              actual_value: value.clone(),
-            }
-            .into(),
-          ),
+          Some(i) => i < 0,
+          None => false,
         },
-        // TODO: Finish rest of numerical data types
-        _ => Err(
-          JSONError {
-            expected_memberkey,
-            expected_value: ident.to_string(),
-            actual_memberkey,
-            actual_value: value.clone(),
-          }
-          .into(),
-        ),
-      },
-      _ => Err(
-        JSONError {
-          expected_memberkey,
-          expected_value: ident.to_string(),
-          actual_memberkey,
-          actual_value: value.clone(),
+        "int" => n.is_i64() || n.is_u64(),
+        "unsigned" | "biguint" => n.is_u64() || is_big_integer(n, false),
+        "bignint" => is_big_integer(n, true) || n.as_i64().map(|i| i < 0) == Some(true),
+        "integer" | "bigint" => {
+          n.is_i64() || n.is_u64() || is_big_integer(n, false) || is_big_integer(n, true)
         }
-        .into(),
-      ),
+        "float16" => n.as_f64().map(|f| f.abs() <= FLOAT16_MAX) == Some(true),
+        "float32" | "float16-32" => n.as_f64().map(|f| f.abs() <= FLOAT32_MAX) == Some(true),
+        // serde_json numbers are never NaN or infinite, so there's no need to
+        // check them against `ValidationOptions::non_finite_floats`
+        "float64" | "float32-64" | "float" | "number" => true,
+        _ => false,
+      },
+      _ => false,
+    };
+
+    if !is_valid {
+      return Err(ValidationError::new(expected_memberkey, ident, actual_memberkey, value).into());
+    }
+
+    if let Value::Number(n) = value {
+      warn_of_lossy_number(ident, n);
     }
+
+    Ok(())
   }
 }
 
-fn validate_numeric_value(t2: &Type2, value: &Value) -> Result {
-  match value {
-    Value::Number(n) => match *t2 {
-      Type2::IntValue { value: i, .. } => match n.as_i64() {
-        Some(n64) if n64 == i as i64 => Ok(()),
-        _ => Err(
-          JSONError {
-            expected_memberkey: None,
-            expected_value: t2.to_string(),
-            actual_memberkey: None,
-            actual_value: value.clone(),
-          }
-          .into(),
-        ),
-      },
-      Type2::FloatValue { value: f, .. } => match n.as_f64() {
-        Some(n64) if (n64 - f as f64).abs() < f64::EPSILON => Ok(()),
-        _ => Err(
-          JSONError {
-            expected_memberkey: None,
-            expected_value: t2.to_string(),
-            actual_memberkey: None,
-            actual_value: value.clone(),
-          }
-          .into(),
-        ),
+impl DataValue for Value {
+  const FORMAT: &'static str = "JSON";
+  const HAS_UNDEFINED: bool = false;
+
+  fn kind(&self) -> DataKind<'_, Value> {
+    match self {
+      Value::Null => DataKind::Null,
+      Value::Bool(b) => DataKind::Bool(*b),
+      Value::Number(n) => match integer_value(n) {
+        Some(i) => DataKind::Integer(i),
+        None => n.as_f64().map_or(DataKind::Other, DataKind::Float),
       },
-      _ => Ok(()),
-    },
-    // Expecting a numerical value but got different type
-    _ => Err(
-      JSONError {
-        expected_memberkey: None,
-        expected_value: t2.to_string(),
-        actual_memberkey: None,
-        actual_value: value.clone(),
-      }
-      .into(),
-    ),
+      Value::String(s) => DataKind::Text(s),
+      Value::Array(values) => DataKind::Array(values),
+      _ => DataKind::Other,
+    }
   }
-}
 
-fn expect_null(ident: &str) -> Result {
-  match ident {
-    "null" | "nil" => Ok(()),
-    _ => Err(
-      JSONError {
-        expected_memberkey: None,
-        expected_value: ident.to_string(),
-        actual_memberkey: None,
-        actual_value: Value::Null,
-      }
-      .into(),
-    ),
+  // Byte strings are represented by strings, encoded as configured by
+  // `ValidationOptions::json_bytes_encoding`
+  fn bytes<'v>(&'v self, cddl: &CDDL) -> Option<Cow<'v, [u8]>> {
+    match self {
+      Value::String(s) => cddl.json_bytes(s).map(Cow::Owned),
+      _ => None,
+    }
   }
+
+  fn lookup(&self, token: &str) -> Option<&Value> {
+    match self {
+      Value::Object(o) => o.get(token),
+      Value::Array(a) => pointer_index(token).and_then(|idx| a.get(idx)),
+      _ => None,
+    }
+  }
+
+  fn from_size(size: usize) -> Value {
+    Value::from(size)
+  }
+}
+
+// Integers beyond 2^53 in magnitude lose precision in the many JSON parsers
+// that represent numbers as doubles
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+// Raises a warning about a number matching a numeric data type if it can't be
+// converted to that type, or read by other JSON parsers, without losing
+// precision
+fn warn_of_lossy_number(ident: &str, n: &serde_json::Number) {
+  if let Some(f) = n.as_f64() {
+    let is_lossy = match ident {
+      "float16" => !is_f16_representable(f),
+      "float32" | "float16-32" => !is_f32_representable(f),
+      _ => false,
+    };
+
+    if is_lossy {
+      diagnose(Severity::Warning, || {
+        format!("{} loses precision when converted to {}", n, ident)
+      });
+    }
+  }
+
+  if n.is_f64() {
+    return;
+  }
+
+  if let Some(f) = n.as_f64() {
+    if f.abs() > MAX_SAFE_INTEGER {
+      diagnose(Severity::Warning, || {
+        format!(
+          "{} is too large to be represented exactly by JSON parsers using doubles",
+          n
+        )
+      });
+    }
+  }
+}
+
+impl<'a> CDDL<'a> {
+  /// Validates JSON text against the root rule, without having to parse it
+  /// or import the `Validator` trait first. `CDDL::validate_cbor_slice` is
+  /// the equivalent for encoded CBOR.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::ast::CDDL;
+  /// use std::convert::TryFrom;
+  ///
+  /// let cddl = CDDL::try_from("person = { name: tstr, age: uint }").unwrap();
+  ///
+  /// assert!(cddl.validate_json_str(r#"{ "name": "Alice", "age": 30 }"#).is_ok());
+  /// assert!(cddl.validate_json_str(r#"{ "name": "Alice" }"#).is_err());
+  /// ```
+  pub fn validate_json_str(&self, json: &str) -> Result {
+    let value = serde_json::from_str::<Value>(json)
+      .map_err(|e| Error::Compilation(CompilationError::Target(e.into())))?;
+
+    let unsorted = if self.validation_options.canonical_key_order {
+      canonical::first_unsorted_key(json)
+        .map_err(|e| Error::Compilation(CompilationError::Target(e.into())))?
+    } else {
+      None
+    };
+
+    let result = Validator::<Value>::validate(self, &value);
+    let unsorted = match unsorted {
+      Some(unsorted) => Error::UnsortedKey(Box::new(unsorted)),
+      None => return result,
+    };
+
+    match result {
+      Ok(()) => Err(unsorted),
+      Err(e) => {
+        let mut errors = vec![unsorted];
+        errors.extend(e.into_errors());
+        Err(Error::MultiError(errors))
+      }
+    }
+  }
+
+  // Decodes a JSON string standing in for a byte string, unless byte strings
+  // aren't represented in JSON or the string isn't encoded as expected
+  fn json_bytes(&self, s: &str) -> Option<Vec<u8>> {
+    match self.validation_options.json_bytes_encoding {
+      BytesEncoding::Base64Url => {
+        let mut decoded = vec![0; s.len() / 4 * 3 + 3];
+
+        base64::decode_config_slice(s, base64::URL_SAFE_NO_PAD, &mut decoded)
+          .ok()
+          .map(|len| {
+            decoded.truncate(len);
+            decoded
+          })
+      }
+      BytesEncoding::Base16 => decode_b16(s.as_bytes()),
+      BytesEncoding::Forbid => None,
+    }
+  }
+
+  // Validates an object against the group of a map, which additionally
+  // requires that the group choice matched leaves none of its members
+  // unaccounted for
+  fn validate_object(
+    &self,
+    g: &Group,
+    occur: Option<&Occur>,
+    om: &Map<String, Value>,
+    value: &Value,
+  ) -> Result {
+    let mut validation_errors: Vec<Error> = Vec::new();
+
+    if g.group_choices.iter().enumerate().any(|(idx, gc)| {
+      let is_traced = open_step(|| group_choice(g, idx), value);
+      let result = self
+        .validate_group_choice(gc, occur, value)
+        .and_then(|_| self.validate_residual_members(gc, om));
+      close_step(is_traced, &result);
+
+      match result {
+        Ok(()) => true,
+        Err(e) => {
+          validation_errors.push(e);
+          false
+        }
+      }
+    }) {
+      return Ok(());
+    }
+
+    // Report the group choices that got furthest rather than every one of them
+    let validation_errors = dedup_errors(most_plausible(validation_errors));
+
+    Err(Error::MultiError(validation_errors))
+  }
+
+  // Validates the object member named by a quoted member key, e.g. "my-key" =>
+  // or "my-key":, against the type of the group entry
+  #[allow(clippy::too_many_arguments)]
+  #[inline(never)]
+  fn validate_text_member(
+    &self,
+    ge: &GroupEntry,
+    vmke: &ValueMemberKeyEntry,
+    mk: &MemberKey,
+    t: &str,
+    is_cut: bool,
+    wildcard_entry: Option<&Type>,
+    occur: Option<&Occur>,
+    value: &Value,
+  ) -> Result {
+    match value {
+      Value::Object(om) => {
+        if !is_type_json_prelude(&vmke.entry_type.to_string()) {
+          if let Some(v) = om.get(t) {
+            return at(t, || {
+              self.validate_type(
+                &vmke.entry_type,
+                Some(mk.to_string()),
+                Some(t.to_string()),
+                occur,
+                v,
+              )
+            });
+          }
+
+          if is_optional_member(vmke, occur) {
+            return Ok(());
+          }
+
+          return self.validate_type(&vmke.entry_type, Some(mk.to_string()), None, occur, value);
+        }
+
+        if let Some(v) = om.get(t) {
+          at(t, || {
+            let r = self.validate_type(
+              &vmke.entry_type,
+              Some(mk.to_string()),
+              Some(t.to_string()),
+              occur,
+              v,
+            );
+
+            if r.is_err() && !is_cut {
+              if let Some(entry_type) = wildcard_entry {
+                return self.validate_type(
+                  entry_type,
+                  Some(mk.to_string()),
+                  Some(t.to_string()),
+                  occur,
+                  v,
+                );
+              }
+            }
+
+            r
+          })
+        } else if is_optional_member(vmke, occur) {
+          Ok(())
+        } else {
+          Err(ValidationError::new(Some(mk.to_string()), ge, None, value).into())
+        }
+      }
+      // Otherwise, validate JSON against the type of the entry.
+      // Matched when in an array and the key for the group entry is
+      // ignored.
+      // CDDL [ city: tstr, ] validates JSON [ "city" ]
+      _ => self.validate_type(&vmke.entry_type, Some(mk.to_string()), None, occur, value),
+    }
+  }
+
+  // Validates the values of the object members whose names match the given
+  // type against the type of the group entry, skipping the members consumed by
+  // entries with the given names. The number of members matched must satisfy
+  // the occurrence of the group entry, or else that of the entry referencing
+  // its group, which is exactly one if neither has any.
+  #[allow(clippy::too_many_arguments)]
+  fn validate_members_with_key_type(
+    &self,
+    vmke: &ValueMemberKeyEntry,
+    mk: &MemberKey,
+    key_type: &Type1,
+    occur: Option<&Occur>,
+    om: &Map<String, Value>,
+    member_names: &[&str],
+    value: &Value,
+  ) -> Result {
+    let mut matched = 0;
+    let mut errors = Vec::new();
+
+    for (k, v) in om.iter().filter(|(k, _)| !member_names.contains(&&k[..])) {
+      if self
+        .validate_type1(key_type, None, None, None, &Value::String(k.clone()))
+        .is_err()
+      {
+        continue;
+      }
+
+      matched += 1;
+
+      if let Err(e) = at(&**k, || {
+        self.validate_type(
+          &vmke.entry_type,
+          Some(mk.to_string()),
+          Some(k.clone()),
+          vmke.occur.as_ref(),
+          v,
+        )
+      }) {
+        if !is_collecting() {
+          return Err(e);
+        }
+
+        errors.push(e);
+      }
+    }
+
+    let (lower, upper) = occurrence_bounds(vmke.occur.as_ref().or(occur));
+
+    if matched < lower || upper.map(|upper| matched > upper).unwrap_or(false) {
+      // The entry is rendered whole, so the member key is left out of the error
+      // to not repeat it
+      let mut e = ValidationError::new(None, &format_args!("{}", vmke), None, value);
+      if matched < lower {
+        e.reason = Reason::MissingEntry;
+      }
+      errors.push(e.into());
+    }
+
+    match errors.len() {
+      0 => Ok(()),
+      1 => Err(errors.remove(0)),
+      _ => Err(Error::MultiError(errors)),
+    }
+  }
+
+  // Rejects the members of an object that are neither consumed by an entry
+  // naming them nor matched by an entry keyed by a type. Members are only
+  // checked when the group choice consists of entries with member keys once
+  // the groups it references are expanded, since the members consumed by
+  // groups with several choices aren't known here.
+  fn validate_residual_members(&self, gc: &GroupChoice, om: &Map<String, Value>) -> Result {
+    let mut entries = Vec::new();
+    if !gc
+      .group_entries
+      .iter()
+      .all(|(ge, _)| self.keyed_entries(ge, &mut entries))
+    {
+      return Ok(());
+    }
+
+    let mut member_names = Vec::new();
+    let mut key_types = Vec::new();
+    for vmke in entries {
+      match &vmke.member_key {
+        Some(mk) => match (member_name(mk), mk) {
+          (Some(name), _) => member_names.push(name),
+          (None, MemberKey::Type1 { t1, .. }) => key_types.push(&**t1),
+          _ => return Ok(()),
+        },
+        None => return Ok(()),
+      }
+    }
+
+    let mut errors = Vec::new();
+
+    for (k, v) in om.iter().filter(|(k, _)| !member_names.contains(&&k[..])) {
+      let name = Value::String(k.clone());
+      if key_types
+        .iter()
+        .any(|t1| self.validate_type1(t1, None, None, None, &name).is_ok())
+      {
+        continue;
+      }
+
+      let error = at(&**k, || {
+        Err(
+          Error::from(ValidationError::new(None, gc, Some(k.clone()), v))
+            .with_reason(Reason::UnexpectedEntry),
+        )
+      });
+
+      if !is_collecting() {
+        return error;
+      }
+
+      if let Err(e) = error {
+        errors.push(e);
+      }
+    }
+
+    match errors.len() {
+      0 => Ok(()),
+      1 => Err(errors.remove(0)),
+      _ => Err(Error::MultiError(errors)),
+    }
+  }
+}
+
+// Returns the names of the object members consumed by the entries of a group,
+// including those of nested inline groups
+fn group_member_names<'b>(g: &'b Group) -> Vec<&'b str> {
+  let mut names = Vec::new();
+
+  for gc in g.group_choices.iter() {
+    for (ge, _) in gc.group_entries.iter() {
+      match ge {
+        GroupEntry::ValueMemberKey { ge, .. } => {
+          names.extend(ge.member_key.as_ref().and_then(member_name))
+        }
+        GroupEntry::InlineGroup { group, .. } => names.extend(group_member_names(group)),
+        _ => (),
+      }
+    }
+  }
+
+  names
+}
+
+// Returns the name of the object member consumed by an entry with a member key
+// naming it, e.g. name: or "name" =>
+fn member_name<'b>(mk: &'b MemberKey) -> Option<&'b str> {
+  match mk {
+    MemberKey::Bareword { ident, .. } => Some(ident.ident),
+    MemberKey::Value {
+      value: token::Value::TEXT(t),
+      ..
+    } => Some(t),
+    MemberKey::Type1 { t1, .. } if t1.operator.is_none() => match &t1.type2 {
+      Type2::TextValue { value, .. } => Some(value),
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+// Returns whether an object may lack the member of an entry, which is the case
+// when either the entry or the group it's validated as a part of occurs zero
+// times at minimum, e.g. ? name: tstr
+fn is_optional_member(vmke: &ValueMemberKeyEntry, occur: Option<&Occur>) -> bool {
+  occurrence_bounds(vmke.occur.as_ref().or(occur)).0 == 0
+}
+
+// JSON numbers don't carry an encoded width and decimal fractions rarely have an
+// exact binary representation, so float widths are validated against the range
+// of finite values each width can represent
+const FLOAT16_MAX: f64 = 65504.0;
+const FLOAT32_MAX: f64 = 3.402_823_466_385_288_6e38;
+
+// Integers whose magnitude exceeds 64 bits are parsed by serde_json as floats.
+// Checks whether or not the given number is such an integer with the given sign
+fn is_big_integer(n: &serde_json::Number, is_negative: bool) -> bool {
+  // 2^64 and -2^63 respectively
+  const U64_UPPER_BOUND: f64 = 18_446_744_073_709_551_616.0;
+  const I64_LOWER_BOUND: f64 = -9_223_372_036_854_775_808.0;
+
+  match n.as_f64() {
+    Some(f) if is_negative => f.fract() == 0.0 && f < I64_LOWER_BOUND,
+    Some(f) => f.fract() == 0.0 && f >= U64_UPPER_BOUND,
+    None => false,
+  }
+}
+
+// Returns the given number if it is an integer, widened so that it compares
+// exactly against both int and uint literals
+fn integer_value(n: &serde_json::Number) -> Option<i128> {
+  n.as_i64()
+    .map(i128::from)
+    .or_else(|| n.as_u64().map(i128::from))
+}
+
+fn validate_numeric_value(comparison: FloatComparison, t2: &Type2, value: &Value) -> Result {
+  match value {
+    Value::Number(n) => match *t2 {
+      Type2::IntValue { value: i, .. } => match integer_value(n) {
+        Some(ni) if ni == i => Ok(()),
+        _ => Err(ValidationError::new(None, t2, None, value).into()),
+      },
+      Type2::UintValue { value: ui, .. } => match integer_value(n) {
+        Some(ni) if ni >= 0 && ni as u128 == ui => Ok(()),
+        _ => Err(ValidationError::new(None, t2, None, value).into()),
+      },
+      Type2::FloatValue { value: f, .. } => match n.as_f64() {
+        Some(n64) if is_float_literal_match(comparison, f, n64) => Ok(()),
+        _ => Err(ValidationError::new(None, t2, None, value).into()),
+      },
+      _ => Ok(()),
+    },
+    // Expecting a numerical value but got different type
+    _ => Err(ValidationError::new(None, t2, None, value).into()),
+  }
+}
+
+// Returns the bytes of a byte string literal
+fn byte_string_literal(t2: &Type2) -> Option<Vec<u8>> {
+  match t2 {
+    Type2::UTF8ByteString { value, .. } => Some(value.to_vec()),
+    Type2::B16ByteString { value, .. } => decode_b16(value),
+    Type2::B64ByteString { value, .. } => decode_b64(value),
+    _ => None,
+  }
+}
+
+fn is_byte_string_type(ident: &str) -> bool {
+  ident == "bstr" || ident == "bytes"
 }
 
 /// Validates JSON input against given CDDL input
 pub fn validate_json_from_str(cddl_input: &str, json_input: &str) -> Result {
-  validate_json(
-    &parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
-      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?,
-    &serde_json::from_str(json_input)
-      .map_err(|e| Error::Compilation(CompilationError::Target(e.into())))?,
-  )
+  parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+    .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?
+    .validate_json_str(json_input)
 }
 
-fn validate_json<V: Validator<Value>>(cddl: &V, json: &Value) -> Result {
-  cddl.validate(json)
+/// Validates JSON input against the rule with the given name in the given
+/// CDDL input, rather than against the root type
+///
+/// # Example
+///
+/// ```
+/// use cddl::validate_json_from_str_for_rule;
+///
+/// let cddl_input = r#"
+///   request = { method: tstr, id: uint }
+///   response = { result: any, id: uint }
+/// "#;
+///
+/// let json = r#"{ "result": true, "id": 1 }"#;
+/// assert!(validate_json_from_str_for_rule(cddl_input, json, "response").is_ok());
+/// assert!(validate_json_from_str_for_rule(cddl_input, json, "request").is_err());
+/// ```
+pub fn validate_json_from_str_for_rule(
+  cddl_input: &str,
+  json_input: &str,
+  rule_name: &str,
+) -> Result {
+  parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+    .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?
+    .validate_with_root(
+      rule_name,
+      &serde_json::from_str::<Value>(json_input)
+        .map_err(|e| Error::Compilation(CompilationError::Target(e.into())))?,
+    )
 }
 
 fn is_type_json_prelude(t: &str) -> bool {
   match t {
     "any" | "uint" | "nint" | "int" | "tstr" | "text" | "number" | "float16" | "float32"
     | "float64" | "float16-32" | "float32-64" | "float" | "false" | "true" | "bool" | "nil"
-    | "null" => true,
+    | "null" | "undefined" => true,
     _ => false,
   }
 }
 
-fn validate_tdate(value: &str) -> Result {
-  let _ = chrono::DateTime::parse_from_rfc3339(value)
-    .map_err(|e| Error::Syntax(format!("Error parsing date value {}: {}", value, e)))?;
-
-  Ok(())
-}
-
-#[cfg(feature = "nightly")]
-fn validate_uri(value: &str) -> Result {
-  let _ = uriparse::uri_reference::URIReference::try_from(value)
-    .map_err(|e| Error::Syntax(format!("Error parsing URI value {}: {}", value, e)))?;
-
-  Ok(())
-}
-
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::{
+    error::Limit,
+    validation::{
+      is_deferring, is_tracing, ActualValue, DateTimeLeniency, TraceTarget, ValidationOptions,
+    },
+  };
 
   #[test]
   fn validate_json_null() -> Result {
@@ -1640,6 +1336,26 @@ mod tests {
     validate_json_from_str(cddl_input, json_input)
   }
 
+  #[test]
+  fn validate_choice_from_group() -> Result {
+    let cddl_input = r#"color = &colors
+colors = ( red: 0, green: 1 // blue: 2 )"#;
+    validate_json_from_str(cddl_input, "0")?;
+    validate_json_from_str(cddl_input, "2")?;
+
+    assert!(validate_json_from_str(cddl_input, "3").is_err());
+    assert!(validate_json_from_str(cddl_input, r#""red""#).is_err());
+
+    // Entries referring to other groups contribute their entries
+    let cddl_input = r##"palette = [* &(colors, black: "#000")]
+colors = ( red: "#f00", green: "#0f0" )"##;
+    validate_json_from_str(cddl_input, r##"["#f00", "#000"]"##)?;
+
+    assert!(validate_json_from_str(cddl_input, r##"["#00f"]"##).is_err());
+
+    Ok(())
+  }
+
   #[test]
   fn validate_number_int_range() -> Result {
     let json_input = r#"3"#;
@@ -1672,6 +1388,65 @@ mod tests {
     Ok(())
   }
 
+  #[test]
+  fn validate_named_range_bounds() -> Result {
+    let cddl_input = r#"port = 1..max-port
+    max-port = 65535"#;
+
+    validate_json_from_str(cddl_input, r#"8080"#)?;
+    assert!(validate_json_from_str(cddl_input, r#"65536"#).is_err());
+
+    let cddl_input = r#"level = low ... high
+    low = 0.0
+    high = 1.0"#;
+
+    validate_json_from_str(cddl_input, r#"0.5"#)?;
+    assert!(validate_json_from_str(cddl_input, r#"1.0"#).is_err());
+
+    let cddl_input = r#"port = 1..max-port
+    max-port = tstr"#;
+
+    assert!(validate_json_from_str(cddl_input, r#"8080"#).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_generic_rules() -> Result {
+    let cddl_input = r#"message = { header<int>, entry<"size", uint> }
+    header<t> = (alg: t, crit: [* tstr])
+    entry<k, v> = (k => v)"#;
+
+    validate_json_from_str(cddl_input, r#"{ "alg": 1, "crit": [], "size": 3 }"#)?;
+    validate_json_from_str(cddl_input, r#"{ "alg": -7, "crit": ["b64"], "size": 3 }"#)?;
+
+    assert!(
+      validate_json_from_str(cddl_input, r#"{ "alg": "ES256", "crit": [], "size": 3 }"#).is_err()
+    );
+    assert!(validate_json_from_str(cddl_input, r#"{ "alg": 1, "crit": [1], "size": 3 }"#).is_err());
+    assert!(validate_json_from_str(cddl_input, r#"{ "alg": 1, "crit": [], "size": -3 }"#).is_err());
+    assert!(validate_json_from_str(cddl_input, r#"{ "alg": 1, "crit": [] }"#).is_err());
+
+    let cddl_input = r#"points = [* pair<uint>]
+    pair<t> = (t, t)"#;
+
+    validate_json_from_str(cddl_input, r#"[1, 2, 3, 4]"#)?;
+
+    assert!(validate_json_from_str(cddl_input, r#"[1, 2, 3]"#).is_err());
+    assert!(validate_json_from_str(cddl_input, r#"[1, -2]"#).is_err());
+
+    let cddl_input = r#"message = outer<tstr>
+    outer<t> = { id: t, inner<[* t]> }
+    inner<u> = (tags: u)"#;
+
+    validate_json_from_str(cddl_input, r#"{ "id": "a", "tags": ["b"] }"#)?;
+
+    assert!(validate_json_from_str(cddl_input, r#"{ "id": "a", "tags": [1] }"#).is_err());
+    assert!(validate_json_from_str("a = pair<int, int>\npair<t> = [t, t]", "[1, 2]").is_err());
+
+    Ok(())
+  }
+
   #[test]
   fn validate_uri_text_value() -> Result {
     let json_input = r#""https://gitub.com""#;
@@ -1679,4 +1454,1457 @@ mod tests {
 
     validate_json_from_str(cddl_input, json_input)
   }
+
+  #[test]
+  fn validate_lossy_floats() -> Result {
+    let is_lossy = |ident: &str, json: &str| -> std::result::Result<bool, Error> {
+      let cddl_input = format!("root = {}", ident);
+      let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(&cddl_input), &cddl_input, false)
+        .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+      let value: Value = serde_json::from_str(json).unwrap();
+      let report = cddl.validate_with_diagnostics(&value);
+      assert!(report.is_ok(), "{} {}", ident, json);
+
+      Ok(
+        report
+          .diagnostics
+          .iter()
+          .any(|d| d.severity == Severity::Warning),
+      )
+    };
+
+    assert!(!is_lossy("float16", "0.5")?);
+    assert!(!is_lossy("float16", "65504.0")?);
+    assert!(!is_lossy("float16", "2048")?);
+    // The smallest subnormal half-precision float, 2^-24
+    assert!(!is_lossy("float16", "5.9604644775390625e-8")?);
+
+    assert!(is_lossy("float16", "1.0001")?);
+    assert!(is_lossy("float16", "2049")?);
+    assert!(is_lossy("float16", "2.98023223876953125e-8")?);
+    assert!(is_lossy("float16", "0.1")?);
+
+    assert!(!is_lossy("float32", "1.0001220703125")?);
+    assert!(is_lossy("float32", "1.0001")?);
+    assert!(is_lossy("float16-32", "1.0001")?);
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_numeric_data_types() -> Result {
+    validate_json_from_str(r#"root = float16"#, r#"0.5"#)?;
+    validate_json_from_str(r#"root = float32"#, r#"100000.0"#)?;
+    validate_json_from_str(r#"root = float16-32"#, r#"100000.0"#)?;
+    validate_json_from_str(r#"root = float64"#, r#"0.1"#)?;
+    validate_json_from_str(r#"root = int"#, r#"18446744073709551615"#)?;
+    validate_json_from_str(r#"root = biguint"#, r#"36893488147419103232"#)?;
+    validate_json_from_str(r#"root = bignint"#, r#"-36893488147419103232"#)?;
+    validate_json_from_str(r#"root = integer"#, r#"-36893488147419103232"#)?;
+
+    assert!(validate_json_from_str(r#"root = float16"#, r#"100000.0"#).is_err());
+    assert!(validate_json_from_str(r#"root = float32"#, r#"1e39"#).is_err());
+    assert!(validate_json_from_str(r#"root = uint"#, r#"36893488147419103232"#).is_err());
+    assert!(validate_json_from_str(r#"root = biguint"#, r#"-1"#).is_err());
+    assert!(validate_json_from_str(r#"root = integer"#, r#"1.5"#).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_integer_literals() -> Result {
+    validate_json_from_str(r#"root = 18446744073709551615"#, r#"18446744073709551615"#)?;
+    validate_json_from_str(r#"root = -9223372036854775808"#, r#"-9223372036854775808"#)?;
+    validate_json_from_str(
+      r#"root = 0..18446744073709551615"#,
+      r#"18446744073709551615"#,
+    )?;
+    validate_json_from_str(r#"root = -1..18446744073709551615"#, r#"-1"#)?;
+    validate_json_from_str(
+      r#"root = uint .lt 340282366920938463463374607431768211455"#,
+      r#"18446744073709551615"#,
+    )?;
+
+    assert!(validate_json_from_str(r#"root = 1"#, r#"2"#).is_err());
+    assert!(validate_json_from_str(r#"root = 1"#, r#"-1"#).is_err());
+    assert!(
+      validate_json_from_str(r#"root = 18446744073709551615"#, r#"18446744073709551614"#).is_err()
+    );
+    assert!(validate_json_from_str(r#"root = -1"#, r#"18446744073709551615"#).is_err());
+    assert!(validate_json_from_str(
+      r#"root = 0...18446744073709551615"#,
+      r#"18446744073709551615"#
+    )
+    .is_err());
+    assert!(validate_json_from_str(
+      r#"root = uint .le 18446744073709551614"#,
+      r#"18446744073709551615"#
+    )
+    .is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_number_literals() -> Result {
+    validate_json_from_str(r#"root = 0xff"#, r#"255"#)?;
+    validate_json_from_str(r#"root = -0b101"#, r#"-5"#)?;
+    validate_json_from_str(r#"root = 1.5e3"#, r#"1500"#)?;
+    validate_json_from_str(r#"root = 0x1.8p-1"#, r#"0.75"#)?;
+    validate_json_from_str(r#"root = 0x00..0x7f"#, r#"127"#)?;
+
+    assert!(validate_json_from_str(r#"root = 0xff"#, r#"256"#).is_err());
+    assert!(validate_json_from_str(r#"root = 1e-3"#, r#"0.01"#).is_err());
+    assert!(validate_json_from_str(r#"root = 0x00..0x7f"#, r#"128"#).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_member_key_types() -> Result {
+    let cddl_input = r#"labels = { + label => int }
+label = tstr .size 8"#;
+
+    validate_json_from_str(cddl_input, r#"{ "abcdefgh": 1, "12345678": 2 }"#)?;
+
+    assert!(validate_json_from_str(cddl_input, r#"{ "abcdefgh": "a" }"#).is_err());
+    assert!(validate_json_from_str(cddl_input, r#"{ "short": 1 }"#).is_err());
+
+    // The member key is rendered once, as part of the entry
+    let errors = validate_json_from_str(cddl_input, r#"{}"#)
+      .unwrap_err()
+      .into_errors();
+    let e = errors[0].validation_error().unwrap();
+    assert_eq!(
+      (&e.expected.member_key, e.expected.value.as_str(), e.reason),
+      (&None, "+ label => int", Reason::MissingEntry)
+    );
+    assert!(errors[0]
+      .to_string()
+      .contains("expected: ( + label => int )"));
+
+    assert!(validate_json_from_str(
+      r#"labels = { label => int }  label = tstr .size 8"#,
+      r#"{ "abcdefgh": 1, "12345678": 2 }"#
+    )
+    .is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_array_group_choices() -> Result {
+    let cddl_input = r#"root = [ (int, int) // (tstr) ]"#;
+    validate_json_from_str(cddl_input, r#"[1, 2]"#)?;
+    validate_json_from_str(cddl_input, r#"["a"]"#)?;
+
+    assert!(validate_json_from_str(cddl_input, r#"[1]"#).is_err());
+    assert!(validate_json_from_str(cddl_input, r#"["a", 1]"#).is_err());
+    assert!(validate_json_from_str(cddl_input, r#"[1, 2, "a"]"#).is_err());
+
+    let cddl_input = r#"root = [ tstr, * (int, int), ? bool ]"#;
+    validate_json_from_str(cddl_input, r#"["a", 1, 2, 3, 4, true]"#)?;
+    validate_json_from_str(cddl_input, r#"["a"]"#)?;
+
+    assert!(validate_json_from_str(cddl_input, r#"["a", 1, 2, 3, true]"#).is_err());
+
+    let cddl_input = r#"root = [ id: uint, name: tstr ]"#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+    let errors = cddl.validate_all(&serde_json::json!([-1, 2])).unwrap_err();
+    let paths = errors
+      .iter()
+      .filter_map(|e| e.validation_error())
+      .map(|e| e.path.as_str())
+      .collect::<Vec<_>>();
+    assert_eq!(paths, vec!["/0", "/1"]);
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_array_group_rules() -> Result {
+    let cddl_input = r#"message = [ basic-header, extra ]
+basic-header = (field1: int, field2: tstr)
+extra = bool"#;
+    validate_json_from_str(cddl_input, r#"[1, "a", true]"#)?;
+
+    assert!(validate_json_from_str(cddl_input, r#"[1, true]"#).is_err());
+    assert!(validate_json_from_str(cddl_input, r#"[1, "a", true, false]"#).is_err());
+
+    // Failures are reported for the elements matched by the group's entries
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+    let errors = cddl
+      .validate_all(&serde_json::json!(["a", 2, true]))
+      .unwrap_err();
+    let failures = errors
+      .iter()
+      .filter_map(|e| e.validation_error())
+      .map(|e| (e.path.as_str(), e.expected.value.as_str()))
+      .collect::<Vec<_>>();
+    assert_eq!(failures, vec![("/0", "int"), ("/1", "tstr")]);
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_empty_containers() -> Result {
+    for (cddl_input, valid, invalid) in &[
+      ("root = {}", "{}", r#"{ "a": 1 }"#),
+      ("root = []", "[]", "[1]"),
+      ("root = { ? a: uint, ? b: tstr }", "{}", r#"{ "c": 1 }"#),
+      ("root = [ ? uint, ? tstr ]", "[]", "[true]"),
+      ("root = { empty }\nempty = ()", "{}", r#"{ "a": 1 }"#),
+      ("root = [ empty ]\nempty = ()", "[]", "[1]"),
+      (
+        "root = { opts }\nopts = ( ? a: uint )",
+        "{}",
+        r#"{ "b": 1 }"#,
+      ),
+      ("root = [ opts ]\nopts = ( ? uint )", "[]", r#"["a"]"#),
+      (
+        "root = [ opts, tstr ]\nopts = ( * uint )",
+        r#"["a"]"#,
+        "[1]",
+      ),
+      ("root = [ ( ? uint ) ]", "[]", "[1, 2]"),
+      (
+        "root = [ 2*3 opts ]\nopts = ( ? uint )",
+        "[]",
+        "[1, 2, 3, 4]",
+      ),
+      (
+        "root = { a: {}, b: [] }",
+        r#"{ "a": {}, "b": [] }"#,
+        r#"{ "a": [], "b": {} }"#,
+      ),
+    ] {
+      validate_json_from_str(cddl_input, valid)?;
+
+      assert!(
+        validate_json_from_str(cddl_input, invalid).is_err(),
+        "{} {}",
+        cddl_input,
+        invalid
+      );
+    }
+
+    // [1, 2] is matched by two occurrences of uint as well as by a single
+    // occurrence of (uint, uint)
+    validate_json_from_str("root = [ 2*2 (uint // (uint, uint)) ]", "[1, 2]")?;
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_array_segments() -> Result {
+    let cddl_input = r#"root = [* int, * tstr]"#;
+    validate_json_from_str(cddl_input, r#"[1, 2, "a", "b"]"#)?;
+    validate_json_from_str(cddl_input, r#"["a"]"#)?;
+    validate_json_from_str(cddl_input, r#"[]"#)?;
+
+    // Elements are consumed by each entry in turn, so they can't interleave
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+    for (json, path, expected) in &[
+      (serde_json::json!([1, "a", 2]), "/2", "* tstr"),
+      (serde_json::json!(["a", 1]), "/1", "* tstr"),
+      (serde_json::json!([true]), "/0", "* int, * tstr"),
+    ] {
+      let errors = cddl.validate_all(json).unwrap_err();
+      let failures = errors
+        .iter()
+        .filter_map(|e| e.validation_error())
+        .map(|e| (e.path.as_str(), e.expected.value.as_str()))
+        .collect::<Vec<_>>();
+      assert_eq!(failures, vec![(*path, *expected)]);
+    }
+
+    let cddl_input = r#"root = [1*2 coordinate, * tstr]
+coordinate = { x: int, y: int }"#;
+    validate_json_from_str(cddl_input, r#"[{ "x": 1, "y": 2 }, "a"]"#)?;
+
+    assert!(validate_json_from_str(
+      cddl_input,
+      r#"[{ "x": 1, "y": 2 }, "a", { "x": 1, "y": 2 }]"#
+    )
+    .is_err());
+    assert!(validate_json_from_str(cddl_input, r#"[{ "x": 1, "y": 2 }, 1, 2, 3]"#).is_err());
+    assert!(validate_json_from_str(cddl_input, r#"["a"]"#).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_any_and_undefined() -> Result {
+    let json_input = r#"[null, true, 1, "a", [false], { "a": null }]"#;
+
+    validate_json_from_str(r#"root = [* any]"#, json_input)?;
+    validate_json_from_str(r#"root = [* #]"#, json_input)?;
+    validate_json_from_str(r#"root = { a: undefined }"#, r#"{ "a": null }"#)?;
+
+    assert!(validate_json_from_str(r#"root = { a: undefined }"#, r#"{ "a": 0 }"#).is_err());
+    assert!(validate_json_from_str(r#"root = undefined"#, r#"false"#).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_simple_value_rules() -> Result {
+    let cddl_input = r#"settings = { enabled: flag, parent: nothing, mode: off / nothing }
+    flag = true
+    off = false
+    nothing = null"#;
+
+    validate_json_from_str(
+      cddl_input,
+      r#"{ "enabled": true, "parent": null, "mode": false }"#,
+    )?;
+    validate_json_from_str(
+      cddl_input,
+      r#"{ "enabled": true, "parent": null, "mode": null }"#,
+    )?;
+
+    assert!(validate_json_from_str(
+      cddl_input,
+      r#"{ "enabled": false, "parent": null, "mode": false }"#
+    )
+    .is_err());
+    assert!(validate_json_from_str(
+      cddl_input,
+      r#"{ "enabled": true, "parent": false, "mode": true }"#
+    )
+    .is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_optional_inline_groups() -> Result {
+    let cddl_input = r#"root = { ? (foo: int, bar: tstr), baz: bool }"#;
+
+    validate_json_from_str(cddl_input, r#"{ "baz": true }"#)?;
+    validate_json_from_str(cddl_input, r#"{ "foo": 1, "bar": "a", "baz": true }"#)?;
+
+    assert!(validate_json_from_str(cddl_input, r#"{ "foo": 1, "baz": true }"#).is_err());
+    assert!(validate_json_from_str(cddl_input, r#"{ "bar": "a", "baz": true }"#).is_err());
+    assert!(
+      validate_json_from_str(cddl_input, r#"{ "foo": "a", "bar": "a", "baz": true }"#).is_err()
+    );
+
+    let cddl_input = r#"root = { ? (foo: int, ? (bar: tstr, baz: tstr)) }"#;
+    validate_json_from_str(cddl_input, r#"{}"#)?;
+    validate_json_from_str(cddl_input, r#"{ "foo": 1 }"#)?;
+    validate_json_from_str(cddl_input, r#"{ "foo": 1, "bar": "a", "baz": "b" }"#)?;
+
+    assert!(validate_json_from_str(cddl_input, r#"{ "foo": 1, "bar": "a" }"#).is_err());
+    assert!(validate_json_from_str(cddl_input, r#"{ "bar": "a", "baz": "b" }"#).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_optional_group_names() -> Result {
+    let cddl_input = r#"root = { id: int, ? extension }
+    extension = (name: tstr, version: uint)"#;
+
+    validate_json_from_str(cddl_input, r#"{ "id": 1 }"#)?;
+    validate_json_from_str(cddl_input, r#"{ "id": 1, "name": "a", "version": 2 }"#)?;
+
+    assert!(validate_json_from_str(cddl_input, r#"{ "id": 1, "name": "a" }"#).is_err());
+    assert!(
+      validate_json_from_str(cddl_input, r#"{ "id": 1, "name": "a", "version": -2 }"#).is_err()
+    );
+
+    let cddl_input = r#"root = { * attribute }
+    attribute = (tstr => bool)"#;
+
+    validate_json_from_str(cddl_input, r#"{}"#)?;
+    validate_json_from_str(cddl_input, r#"{ "enabled": true, "hidden": false }"#)?;
+
+    assert!(validate_json_from_str(cddl_input, r#"{ "enabled": 1 }"#).is_err());
+
+    let cddl_input = r#"root = { id: int, ? label: tstr, options }
+    options = (? verbose: bool)"#;
+
+    validate_json_from_str(cddl_input, r#"{ "id": 1 }"#)?;
+    validate_json_from_str(cddl_input, r#"{ "id": 1, "label": "a", "verbose": true }"#)?;
+
+    assert!(validate_json_from_str(cddl_input, r#"{ "id": 1, "label": 2 }"#).is_err());
+    assert!(validate_json_from_str(cddl_input, r#"{ "id": 1, "verbose": 1 }"#).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_open_ended_occurrences() -> Result {
+    let cddl_input = r#"root = [2* int, *2 tstr]"#;
+
+    validate_json_from_str(cddl_input, r#"[1, 2]"#)?;
+    validate_json_from_str(cddl_input, r#"[1, 2, 3, "a", "b"]"#)?;
+
+    assert!(validate_json_from_str(cddl_input, r#"[1, "a"]"#).is_err());
+    assert!(validate_json_from_str(cddl_input, r#"[1, 2, "a", "b", "c"]"#).is_err());
+
+    let cddl_input = r#"root = [2*3 int, 1*2 (tstr, bool)]"#;
+
+    validate_json_from_str(cddl_input, r#"[1, 2, "a", true]"#)?;
+    validate_json_from_str(cddl_input, r#"[1, 2, 3, "a", true, "b", false]"#)?;
+
+    assert!(validate_json_from_str(cddl_input, r#"[1, 2, 3, 4, "a", true]"#).is_err());
+    assert!(
+      validate_json_from_str(cddl_input, r#"[1, 2, "a", true, "b", false, "c", true]"#).is_err()
+    );
+
+    let cddl_input = r#"root = { id: int, *1 name: tstr, 2* tstr => uint }"#;
+
+    validate_json_from_str(cddl_input, r#"{ "id": -1, "a": 1, "b": 2 }"#)?;
+    validate_json_from_str(cddl_input, r#"{ "id": -1, "name": "n", "a": 1, "b": 2 }"#)?;
+
+    assert!(validate_json_from_str(cddl_input, r#"{ "id": -1, "a": 1 }"#).is_err());
+    assert!(
+      validate_json_from_str(cddl_input, r#"{ "id": -1, "name": 1, "a": 1, "b": 2 }"#).is_err()
+    );
+
+    let cddl_input = r#"root = { id: int, *1 (x: int, y: int) }"#;
+
+    validate_json_from_str(cddl_input, r#"{ "id": -1 }"#)?;
+    validate_json_from_str(cddl_input, r#"{ "id": -1, "x": 0, "y": 0 }"#)?;
+
+    assert!(validate_json_from_str(cddl_input, r#"{ "id": -1, "x": 0 }"#).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_residual_members() -> Result {
+    validate_json_from_str(
+      r#"root = { foo: int, * tstr => any }"#,
+      r#"{ "foo": 1, "bar": "x", "baz": true }"#,
+    )?;
+    validate_json_from_str(
+      r#"root = { foo: int, * tstr => uint }"#,
+      r#"{ "foo": -1, "bar": 2 }"#,
+    )?;
+
+    assert!(validate_json_from_str(r#"root = { foo: int }"#, r#"{ "foo": 1, "bar": 2 }"#).is_err());
+    assert!(validate_json_from_str(
+      r#"root = { foo: int, * tstr => uint }"#,
+      r#"{ "foo": 1, "bar": "x" }"#
+    )
+    .is_err());
+
+    let cddl_input = r#"root = { foo: int, * label => any }  label = tstr .size 3"#;
+    validate_json_from_str(cddl_input, r#"{ "foo": 1, "bar": null }"#)?;
+
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+    let errors = cddl
+      .validate_all(&serde_json::json!({ "foo": 1, "bar": null, "quux": 2 }))
+      .unwrap_err();
+    let paths = errors
+      .iter()
+      .filter_map(|e| e.validation_error())
+      .map(|e| (e.path.as_str(), e.reason))
+      .collect::<Vec<_>>();
+    assert_eq!(paths, vec![("/quux", Reason::UnexpectedEntry)]);
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_size_control() -> Result {
+    validate_json_from_str(r#"root = tstr .size (1..3)"#, r#""ab""#)?;
+    validate_json_from_str(r#"root = tstr .size 2"#, r#""é""#)?;
+    validate_json_from_str(r#"root = uint .size 1"#, r#"255"#)?;
+
+    assert!(validate_json_from_str(r#"root = tstr .size (1..3)"#, r#""abcd""#).is_err());
+    assert!(validate_json_from_str(r#"root = tstr .size 1"#, r#""é""#).is_err());
+    assert!(validate_json_from_str(r#"root = uint .size 1"#, r#"256"#).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  #[cfg(feature = "formats")]
+  fn validate_prelude_text_types() -> Result {
+    validate_json_from_str(r#"root = tdate"#, r#""2020-05-12T08:30:00Z""#)?;
+    validate_json_from_str(r#"root = uri"#, r#""https://example.com/cddl""#)?;
+    validate_json_from_str(r#"root = b64url"#, r#""aGVsbG8_d29ybGQ""#)?;
+    validate_json_from_str(r#"root = b64legacy"#, r#""aGVsbG8/d29ybGQ=""#)?;
+    validate_json_from_str(r#"root = regexp"#, r#""^[a-z]+$""#)?;
+    validate_json_from_str(
+      r#"root = mime-message"#,
+      r#""Content-Type: text/plain\r\n\r\nhello""#,
+    )?;
+
+    assert!(validate_json_from_str(r#"root = tdate"#, r#""2020-05-12""#).is_err());
+    assert!(validate_json_from_str(r#"root = uri"#, r#""http://exa mple.com""#).is_err());
+    assert!(validate_json_from_str(r#"root = b64url"#, r#""aGVsbG8/d29ybGQ""#).is_err());
+    assert!(validate_json_from_str(r#"root = regexp"#, r#""[a-z""#).is_err());
+    assert!(validate_json_from_str(r#"root = mime-message"#, r#""hello""#).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_date_and_time_types() -> Result {
+    let cddl_input = r#"event = { at: tdate, ? epoch: time, ? lasts: duration }"#;
+    let mut cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    let local = serde_json::json!({ "at": "2020-05-12T08:30:00" });
+    for valid in &[
+      serde_json::json!({ "at": "2020-05-12T08:30:00Z", "epoch": 1589272200 }),
+      serde_json::json!({ "at": "2020-05-12T08:30:00+02:00", "epoch": 1589272200.5 }),
+      serde_json::json!({ "at": "2020-05-12T08:30:00Z", "lasts": "PT1H30M" }),
+      serde_json::json!({ "at": "2020-05-12T08:30:00Z", "lasts": 5400 }),
+    ] {
+      cddl.validate(valid)?;
+    }
+
+    for invalid in &[
+      local.clone(),
+      serde_json::json!({ "at": "2020-05-12" }),
+      serde_json::json!({ "at": "2020-05-12T08:30:00Z", "epoch": "2020-05-12T08:30:00Z" }),
+      serde_json::json!({ "at": "2020-05-12T08:30:00Z", "lasts": "1 hour" }),
+      serde_json::json!({ "at": "2020-05-12T08:30:00Z", "lasts": true }),
+    ] {
+      assert!(cddl.validate(invalid).is_err(), "{}", invalid);
+    }
+
+    cddl.set_validation_options(ValidationOptions {
+      date_time: DateTimeLeniency::AllowMissingOffset,
+      ..Default::default()
+    });
+    cddl.validate(&local)?;
+
+    // A definition of its own takes precedence over the extension type
+    validate_json_from_str(
+      r#"event = { lasts: duration }  duration = uint"#,
+      r#"{"lasts": 1}"#,
+    )?;
+    assert!(validate_json_from_str(
+      r#"event = { lasts: duration }  duration = uint"#,
+      r#"{"lasts": "PT1S"}"#
+    )
+    .is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  #[cfg(not(feature = "formats"))]
+  fn validate_unchecked_formats() -> Result {
+    validate_json_from_str(r#"root = uri"#, r#""http://exa mple.com""#)?;
+    validate_json_from_str(r#"root = regexp"#, r#""[a-z""#)?;
+    assert!(validate_json_from_str(r#"root = regexp"#, r#"1"#).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_prelude_rule_fallback() -> Result {
+    validate_json_from_str(r#"root = time"#, r#"1589272200"#)?;
+    validate_json_from_str(r#"root = integer"#, r#"-10"#)?;
+    validate_json_from_str(r#"root = [* unsigned]"#, r#"[1, 2, 3]"#)?;
+    validate_json_from_str(r#"root = age  age = uint"#, r#"42"#)?;
+
+    assert!(validate_json_from_str(r#"root = unsigned"#, r#"-10"#).is_err());
+    assert!(validate_json_from_str(r#"root = time"#, r#""now""#).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_choice_alternates() -> Result {
+    let cddl_input = r#"root = { color: color }
+color = "red"
+color /= "green""#;
+
+    validate_json_from_str(cddl_input, r#"{ "color": "red" }"#)?;
+    validate_json_from_str(cddl_input, r#"{ "color": "green" }"#)?;
+    assert!(validate_json_from_str(cddl_input, r#"{ "color": "blue" }"#).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_max_depth() -> Result {
+    let cddl_input = r#"tree = [* tree] / uint"#;
+    let json = serde_json::from_str::<Value>(&format!("{}1{}", "[".repeat(20), "]".repeat(20)))
+      .map_err(|e| Error::Compilation(CompilationError::Target(e.into())))?;
+
+    let mut cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+    cddl.validate(&json)?;
+
+    cddl.set_max_validation_depth(10);
+    match cddl.validate(&json) {
+      Err(Error::Recursion { rule, max_depth }) => {
+        assert_eq!(rule, "tree");
+        assert_eq!(max_depth, 10);
+      }
+      r => panic!("expected recursion error, got {:?}", r),
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_recursion_fails_fast() -> Result {
+    // Every entry recurses, so backtracking through each one after reaching
+    // the maximum depth would take exponential time
+    let cddl_input = r#"r = { a, r, r }"#;
+    let mut cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+    cddl.set_max_validation_depth(32);
+
+    match cddl.validate(&serde_json::json!({})) {
+      Err(Error::Recursion { .. }) => Ok(()),
+      r => panic!("expected recursion error, got {:?}", r),
+    }
+  }
+
+  #[test]
+  fn validate_rfc8610_controls() -> Result {
+    let cddl_input = r#"timer = {
+      time: uint,
+      ? step: (number .gt 0) .default 1,
+      speed: number .ge 0,
+      mode: uint .bits flag,
+    }
+    flag = &(read: 0, write: 1)"#;
+
+    validate_json_from_str(
+      cddl_input,
+      r#"{"time": 60, "step": 0.5, "speed": 1.5, "mode": 3}"#,
+    )?;
+    validate_json_from_str(cddl_input, r#"{"time": 60, "speed": 0, "mode": 0}"#)?;
+
+    for invalid in &[
+      r#"{"time": 60, "step": 0, "speed": 1, "mode": 0}"#,
+      r#"{"time": 60, "speed": -0.5, "mode": 0}"#,
+      r#"{"time": 60, "speed": 1, "mode": 4}"#,
+    ] {
+      assert!(validate_json_from_str(cddl_input, invalid).is_err());
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_numeric_controls_of_any_numeric_type() -> Result {
+    validate_json_from_str(r#"root = int .lt 10"#, r#"-5"#)?;
+    validate_json_from_str(r#"root = int .le 10"#, r#"10"#)?;
+    validate_json_from_str(r#"root = int .eq 5"#, r#"5"#)?;
+    validate_json_from_str(r#"root = int .gt -1"#, r#"0"#)?;
+    validate_json_from_str(r#"root = float .lt 10"#, r#"9.5"#)?;
+    validate_json_from_str(r#"root = number .le 10"#, r#"10.0"#)?;
+
+    for (cddl_input, json) in &[
+      (r#"root = int .lt 10"#, r#"10"#),
+      (r#"root = int .le 10"#, r#"11"#),
+      (r#"root = int .eq 5"#, r#"6"#),
+      (r#"root = int .lt 10"#, r#"5.5"#),
+      (r#"root = float .lt 10"#, r#"10.5"#),
+      (r#"root = uint .le 10"#, r#"-1"#),
+    ] {
+      assert!(
+        validate_json_from_str(cddl_input, json).is_err(),
+        "{} {}",
+        cddl_input,
+        json
+      );
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_canonical_key_order() -> Result {
+    let cddl_input = r#"message = { payload: { * tstr => uint }, signature: tstr }"#;
+    let mut cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    let unsorted = r#"{"payload": {"b": 1, "a": 2}, "signature": "x"}"#;
+    cddl.validate_json_str(unsorted)?;
+
+    cddl.set_validation_options(ValidationOptions {
+      canonical_key_order: true,
+      ..Default::default()
+    });
+    cddl.validate_json_str(r#"{"payload": {"a": 2, "b": 1}, "signature": "x"}"#)?;
+
+    match cddl.validate_json_str(unsorted) {
+      Err(Error::UnsortedKey(uk)) => {
+        assert_eq!(uk.key, "a");
+        assert_eq!(uk.path, "/payload/a");
+      }
+      r => panic!("unexpected result {:?}", r),
+    }
+
+    // Structural failures are reported alongside the key out of order
+    let errors = cddl
+      .validate_json_str(r#"{"signature": "x", "payload": {"a": -1}}"#)
+      .unwrap_err()
+      .into_errors();
+    assert_eq!(errors.len(), 2);
+    assert!(match &errors[0] {
+      Error::UnsortedKey(uk) => uk.path == "/payload",
+      _ => false,
+    });
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_json_control() -> Result {
+    let cddl_input = r#"message = { payload: tstr .json config }
+    config = { name: tstr, ? retries: uint }"#;
+
+    validate_json_from_str(
+      cddl_input,
+      r#"{"payload": "{\"name\": \"a\", \"retries\": 3}"}"#,
+    )?;
+
+    for invalid in &[
+      r#"{"payload": "{\"name\": 1}"}"#,
+      r#"{"payload": "{\"name\": "}"#,
+      r#"{"payload": {"name": "a"}}"#,
+    ] {
+      assert!(validate_json_from_str(cddl_input, invalid).is_err());
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_unsupported_control() {
+    let cddl_input = r#"payload = tstr .cbor uint"#;
+
+    let error = validate_json_from_str(cddl_input, r#""AQ""#).unwrap_err();
+    assert!(error.into_errors().iter().any(|e| match e {
+      Error::Control(e) =>
+        e.expected.value == "tstr .cbor uint, which can't be checked when validating JSON",
+      _ => false,
+    }));
+  }
+
+  #[test]
+  fn validate_misapplied_definitions_as_failures() -> Result {
+    let failure = |cddl_input: &str, json: &str| {
+      validate_json_from_str(cddl_input, json)
+        .unwrap_err()
+        .into_errors()
+        .remove(0)
+    };
+
+    for (cddl_input, json) in &[
+      (r#"flag = bool .lt 1"#, "true"),
+      (r#"name = tstr .pcre 1"#, r#""a""#),
+      (r#"id = uint .capture 1"#, "1"),
+    ] {
+      match failure(cddl_input, json) {
+        Error::Control(_) => (),
+        e => panic!("{}: unexpected error {:?}", cddl_input, e),
+      }
+    }
+
+    match failure(r#"ratio = 0..1.5"#, "1") {
+      Error::TypeMismatch(e) => assert_eq!(e.reason, Reason::Range),
+      e => panic!("unexpected error {:?}", e),
+    }
+
+    // JSON objects have no integer keys
+    validate_json_from_str(r#"message = { ? 1: tstr }"#, "{}")?;
+    match failure(r#"message = { 1: tstr }"#, "{}") {
+      Error::KeyMissing(_) => (),
+      e => panic!("unexpected error {:?}", e),
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_ne_control() -> Result {
+    validate_json_from_str(r#"port = uint .ne 0"#, r#"8080"#)?;
+    validate_json_from_str(r#"name = tstr .ne "root""#, r#""admin""#)?;
+    validate_json_from_str(r#"ratio = float .ne 1.0"#, r#"0.5"#)?;
+
+    for (cddl_input, json) in &[
+      (r#"port = uint .ne 0"#, r#"0"#),
+      (r#"port = uint .ne 0"#, r#""8080""#),
+      (r#"name = tstr .ne "root""#, r#""root""#),
+      (r#"ratio = float .ne 1.0"#, r#"1.0"#),
+    ] {
+      match validate_json_from_str(cddl_input, json) {
+        Err(e) => assert!(e.into_errors().iter().all(|e| match e {
+          Error::Control(_) => true,
+          _ => false,
+        })),
+        Ok(()) => panic!("{} accepted {}", cddl_input, json),
+      }
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_keyless_entries() -> Result {
+    let cddl_input = r#"pair = [ [int], { x: int } / 1 ]"#;
+
+    validate_json_from_str(cddl_input, r#"[[1], {"x": 1}]"#)?;
+    validate_json_from_str(cddl_input, r#"[[2], 1]"#)?;
+
+    assert!(validate_json_from_str(cddl_input, r#"[[1], {"x": "a"}]"#).is_err());
+    assert!(validate_json_from_str(cddl_input, r#"[1, 1]"#).is_err());
+
+    // Nothing in an object can match an entry without a member key
+    validate_json_from_str(r#"point = { x: int, ? [int] }"#, r#"{"x": 1}"#)?;
+    let error = validate_json_from_str(r#"point = { x: int, [int] }"#, r#"{"x": 1}"#).unwrap_err();
+    let reasons = error
+      .into_errors()
+      .iter()
+      .filter_map(|e| e.validation_error().map(|e| e.reason))
+      .collect::<Vec<_>>();
+    assert_eq!(reasons, vec![Reason::MissingEntry]);
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_json_bytes() -> Result {
+    let cddl_input = r#"key = { kid: bstr .size 4, kty: h'0102', x: bytes }"#;
+    let mut cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+    let key = |kid: &str, kty: &str, x: &str| serde_json::json!({ "kid": kid, "kty": kty, "x": x });
+
+    // Byte strings don't match JSON data by default
+    assert!(cddl.validate(&key("AQIDBA", "AQI", "_-8")).is_err());
+
+    cddl.set_validation_options(ValidationOptions {
+      json_bytes_encoding: BytesEncoding::Base64Url,
+      ..Default::default()
+    });
+    cddl.validate(&key("AQIDBA", "AQI", "_-8"))?;
+    cddl.validate(&key("AQIDBA==", "AQI=", "_-8="))?;
+    assert!(cddl.validate(&key("AQID", "AQI", "_-8")).is_err());
+    assert!(cddl.validate(&key("AQIDBA", "AQM", "_-8")).is_err());
+    assert!(cddl.validate(&key("AQIDBA", "AQI", "+/8")).is_err());
+
+    cddl.set_validation_options(ValidationOptions {
+      json_bytes_encoding: BytesEncoding::Base16,
+      ..Default::default()
+    });
+    cddl.validate(&key("01020304", "0102", "ff"))?;
+    assert!(cddl.validate(&key("AQIDBA", "0102", "ff")).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_text_size_in_chars() -> Result {
+    let cddl_input = r#"name = tstr .size (1..4)"#;
+    let mut cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    // "Zoë" takes four bytes and "Zoë!" five
+    cddl.validate(&serde_json::json!("Zoë"))?;
+    assert!(cddl.validate(&serde_json::json!("Zoë!")).is_err());
+
+    cddl.set_validation_options(ValidationOptions {
+      text_size_in_chars: true,
+      ..Default::default()
+    });
+    cddl.validate(&serde_json::json!("Zoë!"))?;
+    assert!(cddl.validate(&serde_json::json!("Zoë!?")).is_err());
+    assert!(cddl.validate(&serde_json::json!("")).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_max_array_length() -> Result {
+    let cddl_input = r#"matrix = [* [* number]]"#;
+    let mut cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+    cddl.set_validation_options(ValidationOptions {
+      max_array_length: Some(2),
+      ..Default::default()
+    });
+
+    cddl.validate(&serde_json::json!([[1, 2], [3]]))?;
+
+    match cddl.validate(&serde_json::json!([[1, 2], [3, 4, 5]])) {
+      Err(Error::Limit { limit, max }) => {
+        assert_eq!(limit, Limit::ArrayLength);
+        assert_eq!(max, 2);
+      }
+      r => panic!("expected array length to be exceeded, got {:?}", r),
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_with_diagnostics() -> Result {
+    let cddl_input = r#"message = {
+  port: uint .within (0..65535),
+  ratio: float32,
+  ids: [* uint / tstr],
+  ? pattern: tstr .regexp "[a-z]+",
+}"#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    let report = cddl.validate_with_diagnostics(&serde_json::json!({
+      "port": 70000,
+      "ratio": 0.1,
+      "ids": [1, "a", 9_007_199_254_740_993u64],
+      "pattern": "abc",
+    }));
+    assert!(report.is_ok());
+    assert_eq!(
+      report
+        .diagnostics
+        .iter()
+        .map(|d| (d.severity, d.path.as_str()))
+        .collect::<Vec<_>>(),
+      [
+        (Severity::Warning, "/port"),
+        (Severity::Warning, "/ratio"),
+        (Severity::Warning, "/ids/2"),
+        (Severity::Note, "/pattern"),
+      ]
+    );
+
+    let report = cddl.validate_with_diagnostics(&serde_json::json!({
+      "port": 80,
+      "ratio": 0.5,
+      "ids": [true],
+    }));
+    assert!(!report.is_ok());
+    assert!(report.diagnostics.is_empty());
+    assert!(report.all().iter().all(|d| d.severity == Severity::Error));
+    assert!(report.into_result().is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_with_trace() -> Result {
+    let cddl_input = r#"message = { type: "a", a: int // type: "b", b: [* item] }
+item = int / tstr"#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    // Keys in sorted order, so that the rendered value doesn't depend on
+    // whether serde_json preserves their order
+    let trace = cddl.validate_with_trace(&serde_json::json!({ "b": [1, "x"], "type": "b" }));
+    assert!(trace.is_ok());
+    assert_eq!(
+      trace.explain(),
+      r#"valid
+rule message matched at / {"b":[1,"x"],"type":"b"}
+  group choice 2 of 2 `type: "b", b: [* item]` matched at / {"b":[1,"x"],"type":"b"}
+    rule item matched at /b/0 1
+      type choice 1 of 2 `int` matched at /b/0 1
+    rule item matched at /b/1 "x"
+      type choice 2 of 2 `tstr` matched at /b/1 "x"
+"#
+    );
+
+    // Every choice tried is kept, including those that failed
+    let message = &trace.steps[0];
+    assert_eq!(message.target, TraceTarget::Rule("message".to_string()));
+    assert_eq!(
+      message
+        .steps
+        .iter()
+        .map(|s| (s.is_match, s.path.as_str()))
+        .collect::<Vec<_>>(),
+      [(false, ""), (true, "")]
+    );
+
+    let trace = cddl.validate_with_trace(&serde_json::json!({ "type": "b", "b": [true] }));
+    assert!(!trace.is_ok());
+    assert!(trace.explain().starts_with("invalid\n"));
+    assert!(trace
+      .explain()
+      .contains("\n      type choice 2 of 2 `tstr` failed at /b/0 true\n"));
+
+    // Validation isn't traced otherwise
+    cddl.validate(&serde_json::json!({ "type": "a", "a": 1 }))?;
+    assert!(!is_tracing());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_and_extract() -> Result {
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Circle {
+      radius: u64,
+    }
+
+    let cddl_input = r#"drawing = { title: tstr, shapes: [* shape] }
+shape = circle / square
+circle = { radius: uint }
+square = { side: uint }"#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    let extraction = cddl.validate_and_extract(&serde_json::json!({
+      "title": "a",
+      "shapes": [{ "side": 1 }, { "radius": 2 }, { "radius": 3 }],
+    }))?;
+
+    assert_eq!(extraction.rules_at(""), ["drawing"]);
+    assert_eq!(extraction.rules_at("/shapes/0"), ["shape", "square"]);
+    assert_eq!(extraction.rules_at("/shapes/1"), ["shape", "circle"]);
+    assert_eq!(extraction.values_of("shape").len(), 3);
+    assert_eq!(
+      extraction
+        .values_of("circle")
+        .iter()
+        .map(|v| v.deserialize::<Circle>())
+        .collect::<std::result::Result<Vec<_>, _>>()?,
+      [Circle { radius: 2 }, Circle { radius: 3 }]
+    );
+    assert!(extraction.values_of("square")[0]
+      .deserialize::<Circle>()
+      .is_err());
+
+    // Nothing is extracted from invalid data
+    assert!(cddl
+      .validate_and_extract(&serde_json::json!({ "title": "a", "shapes": [{ "side": -1 }] }))
+      .is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_captures() -> Result {
+    let cddl_input = r#"message = {
+  id: uint .capture "id",
+  body: text-body / number-body,
+  tags: [* tstr .capture "tag"],
+}
+text-body = { text: tstr .capture "body" }
+number-body = { number: uint .capture "body" }"#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    let json = serde_json::json!({ "id": 7, "body": { "number": 2 }, "tags": ["a", "b"] });
+    cddl.validate(&json)?;
+
+    let captures = cddl.validate_and_capture(&json)?;
+    assert_eq!(captures.len(), 3);
+    assert_eq!(captures["id"], ActualValue::JSON(serde_json::json!(7)));
+    assert_eq!(captures["body"], ActualValue::JSON(serde_json::json!(2)));
+    assert_eq!(captures["tag"], ActualValue::JSON(serde_json::json!("a")));
+
+    assert!(cddl
+      .validate_and_capture(&serde_json::json!({ "id": -1, "body": { "number": 2 }, "tags": [] }))
+      .is_err());
+
+    // The name can also be given by a rule
+    let cddl_input = r#"id = uint .capture id-name  id-name = "id""#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    assert!(cddl
+      .validate_and_capture(&serde_json::json!(1))?
+      .contains_key("id"));
+
+    let cddl_input = r#"id = uint .capture 1"#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    let errors = cddl
+      .validate(&serde_json::json!(1))
+      .unwrap_err()
+      .into_errors();
+    assert!(match errors.as_slice() {
+      [Error::Control(_)] => true,
+      _ => false,
+    });
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_memoized() -> Result {
+    let cddl_input = r#"tree = uint / left / right
+left = { a: tree, b: tree, kind: "left" }
+right = { a: tree, b: tree, kind: "right" }"#;
+
+    // Every node is first validated as a left node, which fails only once both
+    // of its subtrees have been validated, doubling the work at each level
+    let tree = |depth: usize, leaf: &str| {
+      let tree = (0..depth).fold(leaf.to_string(), |tree, _| {
+        format!(r#"{{ "a": {}, "b": 1, "kind": "right" }}"#, tree)
+      });
+      serde_json::from_str::<Value>(&tree)
+        .map_err(|e| Error::Compilation(CompilationError::Target(e.into())))
+    };
+
+    let mut cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+    let unmemoized = cddl.validate_all(&tree(4, "-1")?).unwrap_err();
+
+    cddl.set_validation_memoization(true);
+    assert_eq!(
+      format!("{:?}", cddl.validate_all(&tree(4, "-1")?).unwrap_err()),
+      format!("{:?}", unmemoized)
+    );
+
+    cddl.validate(&tree(64, "1")?)?;
+    assert!(cddl.validate(&tree(64, "-1")?).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_reported_lazily() -> Result {
+    let cddl_input = r#"entry = { name: tstr, value: int / tstr / [* int] }"#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    cddl.validate(&serde_json::json!({ "name": "a", "value": [1, 2] }))?;
+
+    // Failures are reported in full even though they were first only detected
+    let errors = cddl
+      .validate(&serde_json::json!({ "name": "a", "value": [1, "2"] }))
+      .unwrap_err()
+      .into_errors();
+    let e = errors[0].validation_error().unwrap();
+    assert_eq!(
+      (e.path.as_str(), e.rule.as_ref().map(|r| &r[..]), &e.actual),
+      (
+        "/value/1",
+        Some("entry"),
+        &ActualValue::JSON(serde_json::json!("2"))
+      )
+    );
+    assert!(!is_deferring());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_with_root() -> Result {
+    let cddl_input = r#"request = { method: tstr, id: uint }
+response = { result: any, id: uint }
+notification = [method: tstr, id: uint]"#;
+
+    let response = r#"{ "result": null, "id": 1 }"#;
+    validate_json_from_str_for_rule(cddl_input, response, "response")?;
+    assert!(validate_json_from_str_for_rule(cddl_input, response, "request").is_err());
+    validate_json_from_str_for_rule(cddl_input, r#"["exit", 2]"#, "notification")?;
+
+    match validate_json_from_str_for_rule(cddl_input, response, "error") {
+      Err(Error::Compilation(CompilationError::CDDL(_))) => (),
+      r => panic!("expected missing rule error, got {:?}", r),
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_value_at() -> Result {
+    let cddl_input = r#"config = { name: tstr, "a/b": [* server] }
+server = { host: tstr, port: uint }"#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    let json = serde_json::json!({
+      "name": 1,
+      "a/b": [{ "host": "a", "port": 80 }, { "host": "b", "port": "http" }],
+    });
+
+    // Only the located value is validated, so the invalid name is ignored
+    cddl.validate_value_at("server", "/a~1b/0", &json)?;
+    assert!(cddl.validate(&json).is_err());
+
+    // Failures are located relative to the root of the document
+    let errors = cddl
+      .validate_value_at("server", "/a~1b/1", &json)
+      .unwrap_err()
+      .into_errors();
+    let e = errors[0].validation_error().unwrap();
+    assert_eq!(e.path, "/a~1b/1/port");
+
+    for pointer in &["/a~1b/2", "/a~1b/01", "/name/0", "a~1b"] {
+      match cddl.validate_value_at("server", pointer, &json) {
+        Err(Error::Pointer(p)) => assert_eq!(&p, pointer),
+        r => panic!("expected pointer error, got {:?}", r),
+      }
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_all_errors() -> Result {
+    let cddl_input = r#"reputation = { application: tstr, reputons: [* reputon] }
+reputon = { rater: tstr, rating: float / null }"#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    let json = serde_json::json!({
+      "application": 1,
+      "reputons": [
+        { "rater": "Ninja", "rating": "high" },
+        { "rater": "Ninja", "rating": 0.5 },
+        { "rater": 2, "rating": null },
+      ],
+    });
+
+    let errors = cddl.validate_all(&json).unwrap_err();
+    let failures = errors
+      .iter()
+      .map(|e| {
+        let e = e.validation_error().unwrap();
+        (
+          e.path.as_str(),
+          e.rule.as_ref().map(|r| &r[..]),
+          e.expected.value.as_str(),
+        )
+      })
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      failures,
+      [
+        ("/application", Some("reputation"), "tstr"),
+        ("/reputons/0/rating", Some("reputon"), "float / null"),
+        ("/reputons/2/rater", Some("reputon"), "tstr"),
+      ]
+    );
+
+    // Validation otherwise stops at the first failing array element
+    assert_eq!(cddl.validate(&json).unwrap_err().into_errors().len(), 2);
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_error_reasons() -> Result {
+    let cddl_input = r#"person = { name: tstr, age: 0..120, email: tstr .regexp "[^@]+@[^@]+" }"#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    let json = serde_json::json!({ "age": 130, "email": "alice" });
+    let errors = cddl.validate_all(&json).unwrap_err();
+    let reasons = errors
+      .iter()
+      .map(|e| {
+        let e = e.validation_error().unwrap();
+        (e.path.as_str(), e.expected.value.as_str(), e.reason)
+      })
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      reasons,
+      [
+        ("", "tstr", Reason::MissingEntry),
+        ("/age", "Range: 0 <= value <= 120", Reason::Range),
+        ("/email", "text .pcre [^@]+@[^@]+", Reason::Control),
+      ]
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_error_variants() -> Result {
+    let cddl_input = r#"person = { name: tstr, age: uint, email: tstr .size (3..64) }"#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    let json = serde_json::json!({ "age": "old", "email": "a" });
+    let errors = cddl.validate_all(&json).unwrap_err();
+
+    match errors.as_slice() {
+      [Error::KeyMissing(name), Error::TypeMismatch(age), Error::Control(email)] => {
+        assert_eq!(name.expected.member_key.as_deref(), Some("name:"));
+        assert_eq!(age.path, "/age");
+        assert_eq!(email.path, "/email");
+      }
+      errors => panic!("unexpected errors {:?}", errors),
+    }
+
+    let json = serde_json::json!({ "name": "a", "age": 1, "email": "abc", "phone": 1 });
+    match cddl.validate(&json).unwrap_err().into_errors().as_slice() {
+      [Error::ExtraKey(phone)] => assert_eq!(phone.path, "/phone"),
+      errors => panic!("unexpected errors {:?}", errors),
+    }
+
+    let cddl_input = "person = { name: tstr, address: address }";
+    let error = validate_json_from_str(cddl_input, r#"{ "name": "a" }"#).unwrap_err();
+    match error.into_errors().as_slice() {
+      [Error::UnknownRule(rule)] => assert_eq!(rule, "address"),
+      errors => panic!("unexpected errors {:?}", errors),
+    }
+
+    let error = validate_json_from_str("person = tstr", r#"{ "name": "#).unwrap_err();
+    let source = std::error::Error::source(&error)
+      .and_then(std::error::Error::source)
+      .and_then(|e| e.downcast_ref::<serde_json::Error>());
+    assert!(source.is_some());
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_most_plausible_choice() -> Result {
+    let cddl_input = r#"shapes = [* shape]
+shape = circle / rect / [x: int, y: int]
+circle = { kind: "circle", radius: uint }
+rect = { kind: "rect", width: uint, height: uint, ? label: tstr / [* tstr] }"#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    // Only the choice that got furthest is reported
+    let json = serde_json::json!([{ "kind": "rect", "width": 1, "height": "2" }]);
+    let error = cddl.validate(&json).unwrap_err();
+    assert_eq!(
+      error.explain(),
+      "0\n  height\n    expected height: uint, found \"2\"\n"
+    );
+
+    let json = serde_json::json!([{ "kind": "rect", "width": 1, "height": 2, "label": ["a", 1] }]);
+    let error = cddl.validate(&json).unwrap_err();
+    assert_eq!(
+      error.explain(),
+      "0\n  label\n    1\n      expected tstr, found 1\n"
+    );
+
+    let json = serde_json::json!([{ "kind": "circle", "radius": "1" }, [1, "2"]]);
+    let errors = cddl.validate_all(&json).unwrap_err();
+    assert_eq!(
+      Error::MultiError(errors).explain(),
+      "0\n  radius\n    expected radius: uint, found \"1\"\n1\n  1\n    expected y: int, found \"2\"\n"
+    );
+
+    // Of the choices failing as far in, the one failing the fewest times wins
+    let json = serde_json::json!([{ "kind": "square" }]);
+    let error = cddl.validate(&json).unwrap_err();
+    assert_eq!(
+      error.explain(),
+      "0\n  missing radius: uint\n  kind\n    expected kind: \"circle\", found \"square\"\n"
+    );
+
+    // Choices failing equally far in and equally often are all reported
+    let cddl_input = "setting = { value: uint } / { value: tstr }";
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    let error = cddl
+      .validate(&serde_json::json!({ "value": true }))
+      .unwrap_err();
+    assert_eq!(
+      error.explain(),
+      "value\n  expected value: uint, found true\n  expected value: tstr, found true\n"
+    );
+
+    // Group choices of arrays and maps are narrowed down the same way
+    let cddl_input =
+      r#"shape = [(kind: "point", pos: [x: int, y: int]) // (kind: "label", text: tstr)]"#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    let error = cddl
+      .validate(&serde_json::json!(["point", [1, "a"]]))
+      .unwrap_err();
+    assert_eq!(
+      error.explain(),
+      "1\n  1\n    expected y: int, found \"a\"\n"
+    );
+
+    let cddl_input = r#"shape = { kind: "point", pos: [int, int] // kind: "label", text: tstr }"#;
+    let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    let error = cddl
+      .validate(&serde_json::json!({ "kind": "point", "pos": [1, "a"] }))
+      .unwrap_err();
+    assert_eq!(error.explain(), "pos\n  1\n    expected int, found \"a\"\n");
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_unknown_rules_with_resolver() -> Result {
+    let cddl_input = r#"order = { id: uint, status: status, ? items: [* sku] }"#;
+    let mut cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, true)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+
+    cddl.on_unknown_rule(|name, value| match (name, value) {
+      ("status", ActualValue::JSON(value)) => Some(value == "open" || value == "shipped"),
+      ("sku", ActualValue::JSON(value)) => Some(value.as_str().map_or(false, |s| s.len() == 8)),
+      _ => None,
+    });
+
+    let json = serde_json::json!({ "id": 1, "status": "open", "items": ["AB-12345"] });
+    cddl.validate(&json)?;
+
+    let json = serde_json::json!({ "id": 1, "status": "lost", "items": ["AB-12345", "AB"] });
+    match cddl.validate_all(&json).unwrap_err().as_slice() {
+      [Error::TypeMismatch(status), Error::TypeMismatch(sku)] => {
+        assert_eq!(status.path, "/status");
+        assert_eq!(status.expected.value, "status");
+        assert_eq!(sku.path, "/items/1");
+      }
+      errors => panic!("unexpected errors {:?}", errors),
+    }
+
+    // Types the resolver doesn't know either are still reported as unknown
+    let cddl_input = "order = { id: uint, customer: customer }";
+    let mut cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, true)
+      .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+    cddl.on_unknown_rule(|_, _| None);
+
+    let json = serde_json::json!({ "id": 1, "customer": "Alice" });
+    match cddl.validate(&json).unwrap_err().into_errors().as_slice() {
+      [Error::UnknownRule(rule)] => assert_eq!(rule, "customer"),
+      errors => panic!("unexpected errors {:?}", errors),
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn validate_text_escapes() -> Result {
+    let cddl_input =
+      r#"greeting = { "say \"hi\"": "caf\u00e9 \uD83D\uDE00", path: "C:\\temp" / "a\/b" }"#;
+
+    validate_json_from_str(
+      cddl_input,
+      r#"{ "say \"hi\"": "café 😀", "path": "C:\\temp" }"#,
+    )?;
+    validate_json_from_str(
+      cddl_input,
+      r#"{ "say \"hi\"": "caf\u00e9 \ud83d\ude00", "path": "a/b" }"#,
+    )?;
+
+    assert!(validate_json_from_str(
+      cddl_input,
+      r#"{ "say \"hi\"": "caf\\u00e9 \\uD83D\\uDE00", "path": "a/b" }"#
+    )
+    .is_err());
+    assert!(validate_json_from_str(
+      cddl_input,
+      r#"{ "say \"hi\"": "café 😀", "path": "C:\\\\temp" }"#
+    )
+    .is_err());
+
+    // Multi-line text literals include their line breaks
+    validate_json_from_str("note = \"one\n two\"", r#""one\n two""#)?;
+
+    Ok(())
+  }
 }
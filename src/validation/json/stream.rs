@@ -0,0 +1,353 @@
+use super::{
+  super::{at, forget_memoized, repeated_entry, report_lazily, RepeatedEntry},
+  is_type_json_prelude, validate_occurrence_count, CompilationError, Error, Result,
+};
+use crate::{ast::*, lexer, parser, validation::Validator};
+use serde::de::{
+  self, value::MapAccessDeserializer, Deserialize, DeserializeSeed, MapAccess, SeqAccess, Visitor,
+};
+use serde_json::{error::Category, Value};
+use std::{fmt, io};
+
+impl<'a> CDDL<'a> {
+  /// Validates a JSON document read from the given reader against the root
+  /// rule
+  ///
+  /// If the root rule describes an array with a single repeated entry, e.g.
+  /// `records = [* record]`, the elements of the array are read and validated
+  /// one at a time, so only a single element is held in memory at any point.
+  /// Validation stops at the first element that doesn't conform, which is
+  /// located by its index, e.g. `/1/id`. Documents that aren't arrays, and
+  /// documents for any other root rule, are validated in full. Readers
+  /// such as files should be wrapped in a `std::io::BufReader`.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{lexer_from_str, parser::cddl_from_str};
+  ///
+  /// let input = r#"
+  ///   records = [* record]
+  ///   record = { id: uint, name: tstr }
+  /// "#;
+  /// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+  ///
+  /// let json = r#"[{ "id": 1, "name": "a" }, { "id": 2, "name": "b" }]"#;
+  /// assert!(cddl.validate_json_reader(json.as_bytes()).is_ok());
+  /// ```
+  pub fn validate_json_reader<R: io::Read>(&self, reader: R) -> Result {
+    let entry = match streamed_entry(self) {
+      Some(entry) => entry,
+      None => {
+        let value: Value = serde_json::from_reader(reader).map_err(target_error)?;
+        return Validator::<Value>::validate(self, &value);
+      }
+    };
+
+    self.validate_root(|| {
+      let mut error = None;
+      let mut deserializer = serde_json::Deserializer::from_reader(reader);
+
+      let elements = Elements {
+        cddl: self,
+        entry,
+        error: &mut error,
+      };
+
+      let result = elements
+        .deserialize(&mut deserializer)
+        .and_then(|root| deserializer.end().map(|_| root));
+
+      // Validation errors abort deserialization, so they take precedence
+      if let Some(error) = error {
+        return Err(error);
+      }
+
+      let count = match result.map_err(target_error)? {
+        Root::Elements(count) => count,
+        // Anything but an array fails against the root rule just as it would
+        // had the document been read in full
+        Root::Value(value) => return Validator::<Value>::validate(self, &value),
+      };
+      match entry {
+        RepeatedEntry::Typename { occur, name, .. } => {
+          validate_occurrence_count(occur, &name.to_string(), count)
+        }
+        RepeatedEntry::Type { occur, t } => validate_occurrence_count(occur, &t.to_string(), count),
+      }
+    })
+  }
+}
+
+/// Validates JSON read from the given reader against given CDDL input. See
+/// [`CDDL::validate_json_reader`](../../ast/struct.CDDL.html#method.validate_json_reader)
+/// for which documents are validated incrementally.
+pub fn validate_json_from_reader<R: io::Read>(cddl_input: &str, reader: R) -> Result {
+  parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+    .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?
+    .validate_json_reader(reader)
+}
+
+// Returns the repeated entry of the array described by the root rule, if it
+// describes an array with a single repeated entry. As when validating a
+// document read in full, the root rule is the first type rule.
+fn streamed_entry<'a, 'b>(cddl: &'b CDDL<'a>) -> Option<RepeatedEntry<'a, 'b>> {
+  let rule = cddl.rules.iter().find_map(|r| match r {
+    Rule::Type { rule, .. } => Some(rule),
+    _ => None,
+  })?;
+
+  if rule.generic_param.is_some() {
+    return None;
+  }
+
+  repeated_entry(cddl, &rule.value)
+}
+
+// Root of a document whose root rule describes an array
+enum Root {
+  // Number of elements of the array, each validated as it was read
+  Elements(usize),
+  // Any other value, read in full
+  Value(Value),
+}
+
+// Validates each element of the array as it's deserialized, returning the
+// number of elements
+struct Elements<'a, 'b, 'c> {
+  cddl: &'b CDDL<'a>,
+  entry: RepeatedEntry<'a, 'b>,
+  error: &'c mut Option<Error>,
+}
+
+impl<'a, 'b, 'c> Elements<'a, 'b, 'c> {
+  fn validate(&self, idx: usize, value: &Value) -> Result {
+    // Elements are read into the same location in memory one after the other
+    forget_memoized();
+
+    report_lazily(|| {
+      at(idx, || match self.entry {
+        RepeatedEntry::Typename {
+          name, generic_arg, ..
+        } => {
+          if is_type_json_prelude(name.ident) {
+            return self.cddl.validate_type2(
+              &Type2::Typename {
+                ident: name.clone(),
+                generic_arg: generic_arg.clone(),
+                span: (0, 0, 0),
+              },
+              None,
+              None,
+              None,
+              value,
+            );
+          }
+
+          match generic_arg {
+            Some(ga) => self
+              .cddl
+              .validate_generic_rule(name, ga, false, None, None, None, value),
+            None => self
+              .cddl
+              .validate_rule_for_ident(name, false, None, None, None, value),
+          }
+        }
+        RepeatedEntry::Type { t, .. } => self.cddl.validate_type(t, None, None, None, value),
+      })
+    })
+  }
+}
+
+impl<'de, 'a, 'b, 'c> DeserializeSeed<'de> for Elements<'a, 'b, 'c> {
+  type Value = Root;
+
+  fn deserialize<D>(self, deserializer: D) -> std::result::Result<Root, D::Error>
+  where
+    D: de::Deserializer<'de>,
+  {
+    deserializer.deserialize_any(self)
+  }
+}
+
+impl<'de, 'a, 'b, 'c> Visitor<'de> for Elements<'a, 'b, 'c> {
+  type Value = Root;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "any JSON value")
+  }
+
+  fn visit_bool<E: de::Error>(self, v: bool) -> std::result::Result<Root, E> {
+    Ok(Root::Value(Value::from(v)))
+  }
+
+  fn visit_i64<E: de::Error>(self, v: i64) -> std::result::Result<Root, E> {
+    Ok(Root::Value(Value::from(v)))
+  }
+
+  fn visit_u64<E: de::Error>(self, v: u64) -> std::result::Result<Root, E> {
+    Ok(Root::Value(Value::from(v)))
+  }
+
+  fn visit_f64<E: de::Error>(self, v: f64) -> std::result::Result<Root, E> {
+    Ok(Root::Value(Value::from(v)))
+  }
+
+  fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<Root, E> {
+    Ok(Root::Value(Value::from(v)))
+  }
+
+  fn visit_unit<E: de::Error>(self) -> std::result::Result<Root, E> {
+    Ok(Root::Value(Value::Null))
+  }
+
+  fn visit_map<A>(self, map: A) -> std::result::Result<Root, A::Error>
+  where
+    A: MapAccess<'de>,
+  {
+    Value::deserialize(MapAccessDeserializer::new(map)).map(Root::Value)
+  }
+
+  fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Root, A::Error>
+  where
+    A: SeqAccess<'de>,
+  {
+    let mut count = 0;
+
+    while let Some(value) = seq.next_element::<Value>()? {
+      if let Err(e) = self.validate(count, &value) {
+        *self.error = Some(e);
+        return Err(de::Error::custom(format!(
+          "invalid array element at index {}",
+          count
+        )));
+      }
+
+      count += 1;
+    }
+
+    Ok(Root::Elements(count))
+  }
+}
+
+// Type mismatches are validation failures. Anything else is a malformed
+// document.
+fn target_error(e: serde_json::Error) -> Error {
+  match e.classify() {
+    Category::Data => Error::Target(e.into()),
+    _ => Error::Compilation(CompilationError::Target(e.into())),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const RECORDS: &str = r#"
+    records = [* record]
+    record = { id: uint, name: tstr }
+  "#;
+
+  #[test]
+  fn validate_streamed_records() -> Result {
+    let json = r#"[{ "id": 1, "name": "a" }, { "id": 2, "name": "b" }]"#;
+
+    validate_json_from_reader(RECORDS, json.as_bytes())
+  }
+
+  #[test]
+  fn validate_streamed_records_stop_at_invalid_record() {
+    let json = r#"[{ "id": 1, "name": "a" }, { "id": "2", "name": "b" }, }"#;
+
+    // The invalid record is reported rather than the syntax error that follows
+    let errors = validate_json_from_reader(RECORDS, json.as_bytes())
+      .unwrap_err()
+      .into_errors();
+    assert_eq!(errors[0].validation_error().unwrap().path, "/1/id");
+  }
+
+  #[test]
+  fn validate_streamed_occurrence() {
+    let cddl_input = r#"
+      values = [+ value]
+      value = int / tstr
+    "#;
+
+    assert!(validate_json_from_reader(cddl_input, r#"[1, "a"]"#.as_bytes()).is_ok());
+    assert!(validate_json_from_reader(cddl_input, r#"[1, null]"#.as_bytes()).is_err());
+
+    match validate_json_from_reader(cddl_input, r#"[]"#.as_bytes()) {
+      Err(Error::Occurrence(_)) => (),
+      result => panic!("unexpected result: {:?}", result),
+    }
+  }
+
+  #[test]
+  fn validate_streamed_non_array() {
+    // The mismatch is located at the root of the document
+    for json in &[r#"{ "id": 1 }"#, r#""records""#, "1", "null"] {
+      let errors = validate_json_from_reader(RECORDS, json.as_bytes())
+        .unwrap_err()
+        .into_errors();
+      assert_eq!(errors[0].validation_error().unwrap().path, "", "{}", json);
+    }
+
+    match validate_json_from_reader(RECORDS, r#"{ "id": 1 } 2"#.as_bytes()) {
+      Err(Error::Compilation(_)) => (),
+      result => panic!("unexpected result: {:?}", result),
+    }
+
+    match validate_json_from_reader(RECORDS, r#"[{ "id": 1, "name": "a" }"#.as_bytes()) {
+      Err(Error::Compilation(_)) => (),
+      result => panic!("unexpected result: {:?}", result),
+    }
+  }
+
+  #[test]
+  fn validate_streamed_generic_elements() {
+    let cddl_input = r#"
+      messages = [* message<tstr>]
+      message<T> = { id: uint, body: T }
+    "#;
+
+    let json = r#"[{ "id": 1, "body": "a" }, { "id": 2, "body": "b" }]"#;
+    assert!(validate_json_from_reader(cddl_input, json.as_bytes()).is_ok());
+
+    let json = r#"[{ "id": 1, "body": "a" }, { "id": 2, "body": 2 }]"#;
+    let errors = validate_json_from_reader(cddl_input, json.as_bytes())
+      .unwrap_err()
+      .into_errors();
+    assert_eq!(errors[0].validation_error().unwrap().path, "/1/body");
+  }
+
+  #[test]
+  fn validate_generic_root_as_materialized() {
+    // The first type rule is the root even if it's generic
+    let cddl_input = r#"
+      message<T> = { id: uint, body: T }
+      messages = [* message<tstr>]
+    "#;
+    let cddl =
+      parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false).unwrap();
+
+    for json in &[
+      r#"[{ "id": 1, "body": "a" }]"#,
+      r#"{ "id": 1, "body": "a" }"#,
+    ] {
+      let value: Value = serde_json::from_str(json).unwrap();
+      assert_eq!(
+        cddl.validate_json_reader(json.as_bytes()).is_ok(),
+        Validator::<Value>::validate(&cddl, &value).is_ok(),
+        "{}",
+        json
+      );
+    }
+  }
+
+  #[test]
+  fn validate_materialized_root() -> Result {
+    let cddl_input = r#"record = { id: uint, name: tstr }"#;
+
+    validate_json_from_reader(cddl_input, r#"{ "id": 1, "name": "a" }"#.as_bytes())
+  }
+}
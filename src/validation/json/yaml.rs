@@ -0,0 +1,460 @@
+use super::{CompilationError, Error, Result};
+use crate::{lexer, parser, validation::Validator};
+use serde_json::{Map, Number, Value};
+use std::{collections::HashMap, fmt};
+use yaml_rust::{
+  parser::{Event, MarkedEventReceiver, Parser},
+  scanner::{Marker, ScanError, TScalarStyle, TokenType},
+  Yaml,
+};
+
+/// How mapping keys that aren't strings, e.g. `200` in `200: OK`, are
+/// converted, given that JSON object keys are always strings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyPolicy {
+  /// Numbers, booleans and nulls are converted to their text, e.g. `"200"`.
+  /// Sequences and mappings used as keys are rejected.
+  Stringify,
+  /// Every key that isn't a string is rejected
+  Reject,
+}
+
+// Deriving Default for enums isn't supported by the minimum supported Rust
+// version
+#[allow(clippy::derivable_impls)]
+impl Default for KeyPolicy {
+  fn default() -> Self {
+    KeyPolicy::Stringify
+  }
+}
+
+/// How aliases of anchored nodes, e.g. `*defaults` referring to
+/// `&defaults { retries: 3 }`, are treated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AliasPolicy {
+  /// Each alias is replaced with a copy of the node it refers to. Since nested
+  /// aliases can expand to documents exponentially larger than their text,
+  /// untrusted input is better rejected.
+  Expand,
+  /// Documents containing aliases are rejected
+  Reject,
+}
+
+// Deriving Default for enums isn't supported by the minimum supported Rust
+// version
+#[allow(clippy::derivable_impls)]
+impl Default for AliasPolicy {
+  fn default() -> Self {
+    AliasPolicy::Expand
+  }
+}
+
+/// Options for converting YAML to the JSON data model
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct YamlOptions {
+  /// How mapping keys that aren't strings are converted
+  pub non_string_keys: KeyPolicy,
+  /// How aliases of anchored nodes are treated
+  pub aliases: AliasPolicy,
+}
+
+/// Error converting YAML to the JSON data model
+#[derive(Debug, Clone, PartialEq)]
+pub enum YamlError {
+  /// Input isn't well-formed YAML
+  Syntax(ScanError),
+  /// Input contains more than one document
+  MultipleDocuments,
+  /// Alias rejected by `AliasPolicy::Reject`, or referring to an anchor that
+  /// isn't defined
+  Alias {
+    /// Line of the alias, starting at 1
+    line: usize,
+    /// Column of the alias, starting at 1
+    column: usize,
+  },
+  /// Mapping key that can't be converted to a JSON object key
+  Key {
+    /// Line of the key, starting at 1
+    line: usize,
+    /// Column of the key, starting at 1
+    column: usize,
+  },
+  /// Number that JSON can't represent, e.g. `.inf`
+  Number(String),
+}
+
+impl fmt::Display for YamlError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      YamlError::Syntax(e) => write!(f, "{}", e),
+      YamlError::MultipleDocuments => write!(f, "expected a single YAML document"),
+      YamlError::Alias { line, column } => {
+        write!(f, "unsupported alias at line {} column {}", line, column)
+      }
+      YamlError::Key { line, column } => write!(
+        f,
+        "mapping key that isn't a string at line {} column {}",
+        line, column
+      ),
+      YamlError::Number(n) => write!(f, "number {} can't be represented in JSON", n),
+    }
+  }
+}
+
+impl std::error::Error for YamlError {}
+
+/// Converts a YAML document to the JSON data model. Scalars are resolved
+/// according to the core schema of YAML 1.2, so `true`, `~` and `0x1f` are a
+/// boolean, a null and a number respectively, unless quoted or tagged `!!str`.
+///
+/// # Example
+///
+/// ```
+/// use cddl::validation::json::yaml::{yaml_to_json, YamlOptions};
+/// use serde_json::json;
+///
+/// let yaml = "
+/// defaults: &defaults { retries: 3 }
+/// primary: *defaults
+/// codes: { 200: OK }
+/// ";
+///
+/// assert_eq!(
+///   yaml_to_json(yaml, &YamlOptions::default()).unwrap(),
+///   json!({
+///     "defaults": { "retries": 3 },
+///     "primary": { "retries": 3 },
+///     "codes": { "200": "OK" }
+///   })
+/// );
+/// ```
+pub fn yaml_to_json(input: &str, options: &YamlOptions) -> std::result::Result<Value, YamlError> {
+  let mut builder = Builder {
+    options,
+    stack: Vec::new(),
+    anchors: HashMap::new(),
+    documents: Vec::new(),
+    error: None,
+  };
+
+  Parser::new(input.chars())
+    .load(&mut builder, true)
+    .map_err(YamlError::Syntax)?;
+
+  if let Some(error) = builder.error {
+    return Err(error);
+  }
+
+  let mut documents = builder.documents.into_iter();
+  match (documents.next(), documents.next()) {
+    (Some(document), None) => Ok(document),
+    (None, _) => Ok(Value::Null),
+    (Some(_), Some(_)) => Err(YamlError::MultipleDocuments),
+  }
+}
+
+/// Validates a YAML document against given CDDL input as if it were JSON,
+/// converting it with the default `YamlOptions`
+///
+/// # Example
+///
+/// ```
+/// use cddl::validate_yaml_from_str;
+///
+/// let cddl_input = "service = { name: tstr, ports: [+ uint] }";
+///
+/// assert!(validate_yaml_from_str(cddl_input, "name: web\nports: [80, 443]").is_ok());
+/// assert!(validate_yaml_from_str(cddl_input, "name: web\nports: []").is_err());
+/// ```
+pub fn validate_yaml_from_str(cddl_input: &str, yaml_input: &str) -> Result {
+  validate_yaml_from_str_with_options(cddl_input, yaml_input, &YamlOptions::default())
+}
+
+/// Validates a YAML document against given CDDL input as if it were JSON,
+/// converting it with the given options
+pub fn validate_yaml_from_str_with_options(
+  cddl_input: &str,
+  yaml_input: &str,
+  options: &YamlOptions,
+) -> Result {
+  let cddl = parser::cddl_from_str(&mut lexer::Lexer::new(cddl_input), cddl_input, false)
+    .map_err(|e| Error::Compilation(CompilationError::CDDL(e)))?;
+  let value = yaml_to_json(yaml_input, options)
+    .map_err(|e| Error::Compilation(CompilationError::Target(e.into())))?;
+
+  Validator::<Value>::validate(&cddl, &value)
+}
+
+// Sequence or mapping whose entries are being received, along with the
+// anchor it's defined with, if any
+enum Node {
+  Sequence(usize, Vec<Value>),
+  Mapping(usize, Map<String, Value>, Option<String>),
+}
+
+// Builds JSON values from parser events. The parser doesn't support aborting,
+// so the first error is recorded and the events after it are ignored.
+struct Builder<'o> {
+  options: &'o YamlOptions,
+  stack: Vec<Node>,
+  anchors: HashMap<usize, Value>,
+  documents: Vec<Value>,
+  error: Option<YamlError>,
+}
+
+impl<'o> MarkedEventReceiver for Builder<'o> {
+  fn on_event(&mut self, event: Event, mark: Marker) {
+    if self.error.is_some() {
+      return;
+    }
+
+    let result = match event {
+      Event::SequenceStart(anchor) => {
+        self.stack.push(Node::Sequence(anchor, Vec::new()));
+        Ok(())
+      }
+      Event::MappingStart(anchor) => {
+        self.stack.push(Node::Mapping(anchor, Map::new(), None));
+        Ok(())
+      }
+      Event::SequenceEnd | Event::MappingEnd => match self.stack.pop() {
+        Some(Node::Sequence(anchor, values)) => self.insert(Value::Array(values), anchor, mark),
+        Some(Node::Mapping(anchor, entries, _)) => {
+          self.insert(Value::Object(entries), anchor, mark)
+        }
+        None => Ok(()),
+      },
+      Event::Scalar(text, style, anchor, tag) => {
+        scalar(text, style, tag).and_then(|value| self.insert(value, anchor, mark))
+      }
+      Event::Alias(id) => match (self.options.aliases, self.anchors.get(&id)) {
+        (AliasPolicy::Expand, Some(value)) => {
+          let value = value.clone();
+          self.insert(value, 0, mark)
+        }
+        _ => Err(YamlError::Alias {
+          line: mark.line(),
+          column: mark.col() + 1,
+        }),
+      },
+      _ => Ok(()),
+    };
+
+    if let Err(e) = result {
+      self.error = Some(e);
+    }
+  }
+}
+
+impl<'o> Builder<'o> {
+  fn insert(
+    &mut self,
+    value: Value,
+    anchor: usize,
+    mark: Marker,
+  ) -> std::result::Result<(), YamlError> {
+    if anchor != 0 && self.options.aliases == AliasPolicy::Expand {
+      self.anchors.insert(anchor, value.clone());
+    }
+
+    match self.stack.last_mut() {
+      None => self.documents.push(value),
+      Some(Node::Sequence(_, values)) => values.push(value),
+      Some(Node::Mapping(_, entries, key)) => match key.take() {
+        Some(key) => {
+          entries.insert(key, value);
+        }
+        None => {
+          *key = Some(
+            object_key(value, self.options.non_string_keys).ok_or(YamlError::Key {
+              line: mark.line(),
+              column: mark.col() + 1,
+            })?,
+          )
+        }
+      },
+    }
+
+    Ok(())
+  }
+}
+
+fn object_key(value: Value, policy: KeyPolicy) -> Option<String> {
+  match (value, policy) {
+    (Value::String(s), _) => Some(s),
+    (Value::Array(_), _) | (Value::Object(_), _) | (_, KeyPolicy::Reject) => None,
+    (value, KeyPolicy::Stringify) => Some(value.to_string()),
+  }
+}
+
+fn scalar(
+  text: String,
+  style: TScalarStyle,
+  tag: Option<TokenType>,
+) -> std::result::Result<Value, YamlError> {
+  // Tags of the core schema, e.g. `!!int "1"`, override the resolution of the
+  // scalar's text, whereas other tags are ignored
+  let resolve = match tag {
+    Some(TokenType::Tag(ref handle, ref suffix)) if handle == "!!" => suffix != "str",
+    _ => style == TScalarStyle::Plain,
+  };
+
+  if !resolve {
+    return Ok(Value::String(text));
+  }
+
+  match Yaml::from_str(&text) {
+    Yaml::Null => Ok(Value::Null),
+    Yaml::Boolean(b) => Ok(Value::Bool(b)),
+    Yaml::Integer(i) => Ok(Value::from(i)),
+    // Integers beyond the range of i64 are resolved as reals
+    Yaml::Real(r) => match r.parse::<u64>() {
+      Ok(u) => Ok(Value::from(u)),
+      Err(_) => Yaml::Real(r.clone())
+        .as_f64()
+        .and_then(Number::from_f64)
+        .map(Value::Number)
+        .ok_or(YamlError::Number(r)),
+    },
+    _ => Ok(Value::String(text)),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn convert(input: &str, options: &YamlOptions) -> std::result::Result<Value, YamlError> {
+    yaml_to_json(input, options)
+  }
+
+  #[test]
+  fn verify_yaml_scalars() {
+    let yaml = r#"
+      null: ~
+      empty:
+      bool: true
+      int: -12
+      hex: 0x1f
+      big: 18446744073709551615
+      float: 1.5
+      quoted: "true"
+      tagged: !!str 12
+      text: hello world
+      block: |
+        line
+    "#;
+
+    assert_eq!(
+      convert(yaml, &YamlOptions::default()).unwrap(),
+      json!({
+        "null": null,
+        "empty": null,
+        "bool": true,
+        "int": -12,
+        "hex": 31,
+        "big": 18446744073709551615u64,
+        "float": 1.5,
+        "quoted": "true",
+        "tagged": "12",
+        "text": "hello world",
+        "block": "line\n",
+      })
+    );
+
+    assert_eq!(
+      convert(".inf", &YamlOptions::default()),
+      Err(YamlError::Number(".inf".to_string()))
+    );
+    assert_eq!(convert("", &YamlOptions::default()), Ok(Value::Null));
+    assert_eq!(
+      convert("a\n---\nb", &YamlOptions::default()),
+      Err(YamlError::MultipleDocuments)
+    );
+    assert!(match convert("a: [1", &YamlOptions::default()) {
+      Err(YamlError::Syntax(_)) => true,
+      _ => false,
+    });
+  }
+
+  #[test]
+  fn verify_yaml_key_policy() {
+    let yaml = "1: one\ntrue: yes\n~: none";
+    assert_eq!(
+      convert(yaml, &YamlOptions::default()).unwrap(),
+      json!({ "1": "one", "true": "yes", "null": "none" })
+    );
+
+    let options = YamlOptions {
+      non_string_keys: KeyPolicy::Reject,
+      ..Default::default()
+    };
+    assert_eq!(
+      convert(yaml, &options),
+      Err(YamlError::Key { line: 1, column: 1 })
+    );
+    assert_eq!(convert("a: 1", &options).unwrap(), json!({ "a": 1 }));
+
+    // Sequences can't be keys whatever the policy
+    assert!(convert("? [a, b]\n: c", &YamlOptions::default()).is_err());
+  }
+
+  #[test]
+  fn verify_yaml_alias_policy() {
+    let yaml = "base: &base [1, 2]\ncopy: *base";
+    assert_eq!(
+      convert(yaml, &YamlOptions::default()).unwrap(),
+      json!({ "base": [1, 2], "copy": [1, 2] })
+    );
+
+    let options = YamlOptions {
+      aliases: AliasPolicy::Reject,
+      ..Default::default()
+    };
+    assert_eq!(
+      convert(yaml, &options),
+      Err(YamlError::Alias { line: 2, column: 7 })
+    );
+    // Anchors without aliases are harmless
+    assert_eq!(
+      convert("base: &base [1, 2]", &options).unwrap(),
+      json!({ "base": [1, 2] })
+    );
+  }
+
+  #[test]
+  fn validate_yaml() {
+    let cddl = r#"
+      service = { name: tstr, ports: [+ port], ? env: { * tstr => tstr } }
+      port = 1..65535
+    "#;
+
+    assert!(
+      validate_yaml_from_str(cddl, "name: web\nports: [80, 443]\nenv: { MODE: prod }").is_ok()
+    );
+
+    let errors = validate_yaml_from_str(cddl, "name: web\nports: [0]")
+      .unwrap_err()
+      .into_errors();
+    assert_eq!(
+      errors[0].validation_error().map(|e| e.path.as_str()),
+      Some("/ports/0")
+    );
+
+    // A mapping key that isn't a string fails to convert rather than validate
+    let options = YamlOptions {
+      non_string_keys: KeyPolicy::Reject,
+      ..Default::default()
+    };
+    assert!(match validate_yaml_from_str_with_options(
+      cddl,
+      "name: web\nports: [80]\nenv: { 1: prod }",
+      &options
+    ) {
+      Err(Error::Compilation(CompilationError::Target(_))) => true,
+      _ => false,
+    });
+  }
+}
@@ -1,88 +1,1144 @@
 /// CBOR validation implementation
+#[cfg(feature = "cbor-validation")]
 pub mod cbor;
 
 /// JSON validation implementation
+#[cfg(feature = "json-validation")]
 pub mod json;
 
-use crate::{ast::*, token::Numeric};
-use std::{fmt, result};
+mod array;
+mod controls;
+mod engine;
+#[cfg(feature = "formats")]
+mod formats;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod time;
+mod value;
+
+use crate::{
+  ast::*,
+  cbor_core::{is_f16_representable, is_f32_representable},
+  error::Limit,
+  prelude::{is_extension_type, is_numeric_data_type, is_prelude_type},
+  token::Numeric,
+};
+#[cfg(feature = "parallel")]
+use value::DataKind;
+use value::DataValue;
+
+pub use crate::lint::Severity;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+  cell::{Cell, RefCell},
+  cmp::Reverse,
+  collections::{HashMap, HashSet},
+  convert::TryFrom,
+  fmt, result,
+  sync::Arc,
+};
 
 /// Alias for `Result` with an error of type `validator::ValidationError`
 pub type Result = result::Result<(), Error>;
 
+/// Default maximum number of nested rule references followed while validating
+/// data. Can be overridden with `CDDL::set_max_validation_depth`.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+// const initializers for thread locals aren't supported by the minimum supported
+// Rust version
+thread_local! {
+  // Number of rule references currently being followed by the validator on
+  // this thread
+  #[allow(clippy::missing_const_for_thread_local)]
+  static DEPTH: Cell<usize> = Cell::new(0);
+  // Error for the maximum depth or another limit set by `ValidationOptions`
+  // when first exceeded. Recorded so the failure can be reported once
+  // validation completes rather than being masked by the errors of alternative
+  // choices.
+  #[allow(clippy::missing_const_for_thread_local)]
+  static EXCEEDED: RefCell<Option<Error>> = RefCell::new(None);
+  // Location within the data being validated, as JSON Pointer reference
+  // tokens, and names of the rules being resolved on this thread. Captured by
+  // errors when they're created.
+  #[allow(clippy::missing_const_for_thread_local)]
+  static PATH: RefCell<Vec<PathToken>> = RefCell::new(Vec::new());
+  #[allow(clippy::missing_const_for_thread_local)]
+  static RULES: RefCell<Vec<String>> = RefCell::new(Vec::new());
+  // Whether validation continues past the first failing entry or array
+  // element in order to report every failure
+  #[allow(clippy::missing_const_for_thread_local)]
+  static COLLECT: Cell<bool> = Cell::new(false);
+  // Whether failures are only being detected rather than reported, in which
+  // case neither the location nor the details of a failure are recorded
+  #[allow(clippy::missing_const_for_thread_local)]
+  static DEFER: Cell<bool> = Cell::new(false);
+  // Results of validating data against rules, for definitions that enable
+  // memoization. Keyed by the location of the data in memory, so the results
+  // are only valid for as long as the data being validated is borrowed and are
+  // forgotten once the outermost validation completes.
+  #[allow(clippy::missing_const_for_thread_local)]
+  static MEMO: RefCell<HashMap<MemoKey, Result>> = RefCell::new(HashMap::new());
+  // Whether the data being validated was decoded from the data given to the
+  // validator, e.g. by the .cbor control, and only lives until the control has
+  // been checked. Results aren't memoized then, since data decoded later may
+  // reuse its location in memory.
+  #[allow(clippy::missing_const_for_thread_local)]
+  static TRANSIENT: Cell<bool> = Cell::new(false);
+  // Warnings and notes raised so far, if they're being captured by
+  // `Validator::validate_with_diagnostics`
+  #[allow(clippy::missing_const_for_thread_local)]
+  static DIAGNOSTICS: RefCell<Option<Vec<Diagnostic>>> = RefCell::new(None);
+  // Steps of validation recorded so far, if they're being traced by
+  // `Validator::validate_with_trace`
+  #[allow(clippy::missing_const_for_thread_local)]
+  static TRACE: RefCell<Option<Tracer>> = RefCell::new(None);
+}
+
+/// Options controlling how data is validated against a CDDL definition
+///
+/// ```
+/// use cddl::{lexer_from_str, parser::cddl_from_str, validation::ValidationOptions};
+///
+/// let input = r#"records = [* record]  record = { id: uint }"#;
+/// let mut cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+///
+/// cddl.set_validation_options(ValidationOptions {
+///   max_depth: Some(32),
+///   memoize: true,
+///   ..Default::default()
+/// });
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationOptions {
+  /// Maximum number of nested rule references followed while validating
+  /// data. Defaults to `DEFAULT_MAX_DEPTH` if not set. See
+  /// `CDDL::set_max_validation_depth`.
+  pub max_depth: Option<usize>,
+  /// Whether the results of validating values against rules are recorded and
+  /// reused. See `CDDL::set_validation_memoization`.
+  pub memoize: bool,
+  /// Maximum number of elements of any array being validated, guarding
+  /// against documents too large to validate in reasonable time. Exceeding it
+  /// fails validation with `Error::Limit`.
+  pub max_array_length: Option<usize>,
+  /// How byte strings are represented in JSON data, which has no type for
+  /// them. By default, byte strings don't match any JSON data.
+  #[cfg(feature = "json-validation")]
+  pub json_bytes_encoding: json::BytesEncoding,
+  /// Whether `.size` constraints on text strings count Unicode scalar values,
+  /// i.e. characters, rather than bytes of UTF-8 as specified by RFC 8610.
+  /// Useful for definitions whose authors meant to limit the number of
+  /// characters.
+  pub text_size_in_chars: bool,
+  /// Whether encoded CBOR must also follow the deterministic encoding
+  /// requirements of RFC 8949 section 4.2.1, as COSE signatures and consensus
+  /// protocols rely on. Each departure is reported as an `Error::Encoding`
+  /// alongside any structural failures. Only applies to validation of encoded
+  /// CBOR, e.g. `CDDL::validate_cbor_slice`.
+  pub cbor_deterministic: bool,
+  /// Whether encoded CBOR must not contain indefinite-length strings, arrays
+  /// or maps, as required by deterministic profiles that otherwise tolerate
+  /// non-shortest encodings. Each indefinite-length item is reported as an
+  /// `Error::Encoding`. Implied by `cbor_deterministic`.
+  pub cbor_definite_lengths: bool,
+  /// Whether the keys of every map must appear in canonical order, as
+  /// protocols that sign serialized data require. Keys of encoded CBOR must
+  /// sort in bytewise lexicographic order of their encodings (RFC 8949 section
+  /// 4.2.1), and member names of JSON text in order of their UTF-16 code units
+  /// (RFC 8785). The first key out of order is reported as an
+  /// `Error::UnsortedKey` alongside any structural failures. Only applies to
+  /// validation of encoded data, i.e. `CDDL::validate_cbor_slice` and
+  /// `CDDL::validate_json_str`.
+  pub canonical_key_order: bool,
+  /// How maps with more than one entry with the same key are treated when
+  /// validating encoded CBOR. By default, the last entry with each key is
+  /// validated.
+  #[cfg(feature = "cbor-validation")]
+  pub cbor_duplicate_keys: cbor::DuplicateKeyPolicy,
+  /// How Decimal128 values in BSON documents are converted to the CBOR data
+  /// model. By default, they're converted to floats.
+  #[cfg(feature = "bson")]
+  pub bson_decimal: cbor::bson::DecimalPolicy,
+  /// How float literals such as `1.5` are compared against floats in the
+  /// data. By default, they must be equal.
+  pub float_comparison: FloatComparison,
+  /// Whether NaN and infinite floats, which CBOR can encode but JSON can't,
+  /// satisfy `float` and the other float types. By default, they do.
+  pub non_finite_floats: NonFiniteFloats,
+  /// How strictly text strings are checked against `tdate`. By default, they
+  /// must include a time zone offset as RFC 3339 requires.
+  pub date_time: DateTimeLeniency,
+  /// Whether the elements of an array described by the root rule with a
+  /// single repeated entry, e.g. `records = [* record]`, are validated
+  /// concurrently on rayon's global thread pool. Failures are reported by the
+  /// index of the offending element as they would be otherwise.
+  #[cfg(feature = "parallel")]
+  pub parallel: bool,
+  /// Called with the name of each type the data is validated against that
+  /// neither the definition nor the standard prelude defines, rather than
+  /// failing with `Error::UnknownRule`. See `CDDL::on_unknown_rule`.
+  pub on_unknown_rule: Option<UnknownRuleResolver>,
+}
+
+/// How float literals in CDDL are compared against floats in the data being
+/// validated. Set via `ValidationOptions::float_comparison`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FloatComparison {
+  /// Floats must be equal as defined by IEEE 754, under which `0.0` and `-0.0`
+  /// are equal and NaN equals nothing
+  Exact,
+  /// Floats may differ by up to the given number of units in the last place,
+  /// allowing for rounding by whatever produced the data. `0.0` and `-0.0`
+  /// are zero units apart.
+  Ulps(u32),
+}
+
+// Deriving Default for enums isn't supported by the minimum supported Rust
+// version
+#[allow(clippy::derivable_impls)]
+impl Default for FloatComparison {
+  fn default() -> Self {
+    FloatComparison::Exact
+  }
+}
+
+/// Whether NaN, infinity and negative infinity satisfy the float types of the
+/// standard prelude. Set via `ValidationOptions::non_finite_floats`. JSON
+/// numbers are always finite, so this only makes a difference to CBOR.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NonFiniteFloats {
+  /// Non-finite floats satisfy `float`, `number` and the float types of each
+  /// width, as their encodings are floats all the same
+  Allow,
+  /// Non-finite floats only match `any` and major type constraints such as
+  /// `#7.25`, for protocols that can't make sense of them
+  Reject,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for NonFiniteFloats {
+  fn default() -> Self {
+    NonFiniteFloats::Allow
+  }
+}
+
+/// How strictly text strings are checked against the date-time format of RFC
+/// 3339 when validating `tdate`. Set via `ValidationOptions::date_time`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateTimeLeniency {
+  /// The date and time must be followed by a time zone offset, e.g.
+  /// `2020-05-12T08:30:00Z`
+  Strict,
+  /// The time zone offset may be left out, e.g. `2020-05-12T08:30:00`, for
+  /// data produced by systems that only deal in local time
+  AllowMissingOffset,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for DateTimeLeniency {
+  fn default() -> Self {
+    DateTimeLeniency::Strict
+  }
+}
+
+/// Callback deciding whether a value matches a type that the CDDL definition
+/// refers to but doesn't define, e.g. an enumeration whose values are only
+/// known to the application at runtime. Returns `Some(true)` if the value
+/// matches, `Some(false)` if it doesn't and `None` if the type isn't known to
+/// the application either, in which case validation fails with
+/// `Error::UnknownRule` as it would without the callback.
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub struct UnknownRuleResolver(Arc<dyn Fn(&str, &ActualValue) -> Option<bool> + Send + Sync>);
+
+impl UnknownRuleResolver {
+  /// Wraps a function taking the name of the type and the value being
+  /// validated against it
+  pub fn new<F>(resolve: F) -> Self
+  where
+    F: Fn(&str, &ActualValue) -> Option<bool> + Send + Sync + 'static,
+  {
+    UnknownRuleResolver(Arc::new(resolve))
+  }
+}
+
+impl fmt::Debug for UnknownRuleResolver {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("UnknownRuleResolver")
+  }
+}
+
+// Resolvers are only known to behave the same if they're the same function
+impl PartialEq for UnknownRuleResolver {
+  fn eq(&self, other: &Self) -> bool {
+    Arc::ptr_eq(&self.0, &other.0)
+  }
+}
+
 /// Validation error types
-#[derive(Debug)]
+///
+/// Failures of the data to match a CDDL definition are reported as one of the
+/// `TypeMismatch`, `KeyMissing`, `ExtraKey` or `Control` variants, each
+/// holding a `ValidationError` locating the value at fault. New variants may be
+/// added in future releases without that being a breaking change.
+#[non_exhaustive]
+#[derive(Debug, thiserror::Error)]
 pub enum Error {
   /// CDDL syntax error, specific to the target data structure being validated
+  #[error("CDDL syntax error: {0}")]
   Syntax(String),
-  /// Error validating specific target data structure (i.e. JSON or CBOR)
-  Target(Box<dyn std::error::Error>),
+  /// A rule referenced by the CDDL is neither defined by it nor by the standard
+  /// prelude
+  #[error("no rule with name \"{0}\" defined")]
+  UnknownRule(String),
+  /// Value isn't of the expected type, doesn't have the expected value or is
+  /// outside of the expected range
+  #[error("{0}")]
+  TypeMismatch(Box<ValidationError>),
+  /// Map doesn't contain an entry with the expected member key
+  #[error("{0}")]
+  KeyMissing(Box<ValidationError>),
+  /// Map contains an entry that no member key matches
+  #[error("{0}")]
+  ExtraKey(Box<ValidationError>),
+  /// Value doesn't satisfy a control operator, e.g. `.size` or `.regexp`
+  #[error("{0}")]
+  Control(Box<ValidationError>),
+  /// Error reading the target data structure (i.e. JSON or CBOR) being
+  /// validated
+  #[error("{0}")]
+  Target(#[source] Box<dyn std::error::Error + Send + Sync>),
   /// Error compiling CDDL and/or target data structure
-  Compilation(CompilationError),
+  #[error("error on compilation: {0}")]
+  Compilation(#[source] CompilationError),
   /// Occurrence error
+  #[error("occurrence error: {0}")]
   Occurrence(String),
   /// Aggregate errors
+  #[error("{}", display_errors(.0))]
   MultiError(Vec<Error>),
+  /// Maximum validation depth exceeded
+  #[error("maximum validation depth of {max_depth} exceeded while resolving rule \"{rule}\"")]
+  Recursion {
+    /// Name of the rule that was being resolved when the limit was reached
+    rule: String,
+    /// Maximum depth in effect
+    max_depth: usize,
+  },
+  /// A limit set by `ValidationOptions` was exceeded
+  #[error("maximum {limit} of {max} exceeded")]
+  Limit {
+    /// Limit that was exceeded
+    limit: Limit,
+    /// Maximum in effect
+    max: usize,
+  },
+  /// CBOR isn't deterministically encoded, if required by
+  /// `ValidationOptions::cbor_deterministic`
+  #[error("{0}")]
+  Encoding(#[source] crate::cbor_core::EncodingViolation),
+  /// A map has more than one entry with the same key, if rejected by
+  /// `ValidationOptions::cbor_duplicate_keys`
+  #[error("duplicate map key {key} at offsets {}", display_positions(.positions))]
+  DuplicateKey {
+    /// Duplicated key in diagnostic notation
+    key: String,
+    /// Byte offsets of each occurrence of the key in the encoded data
+    positions: Vec<usize>,
+  },
+  /// A map key doesn't sort after the key before it, if keys are required to
+  /// be in canonical order by `ValidationOptions::canonical_key_order`
+  #[error("{0}")]
+  UnsortedKey(Box<UnsortedKey>),
+  /// The JSON Pointer given to `Validator::validate_value_at` doesn't locate a
+  /// value within the data
+  #[error("no value at JSON Pointer \"{0}\"")]
+  Pointer(String),
 }
 
-impl fmt::Display for Error {
-  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+fn display_errors(errors: &[Error]) -> String {
+  let mut display = String::new();
+
+  for e in errors.iter() {
+    match e {
+      // Temporary work around for nested MultiError's
+      Error::MultiError(_) => display.push_str(&e.to_string()),
+      _ => display.push_str(&format!("{}\n\n", e)),
+    }
+  }
+
+  display
+}
+
+fn display_positions(positions: &[usize]) -> String {
+  positions
+    .iter()
+    .map(ToString::to_string)
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+impl Error {
+  /// Returns the individual failures making up the error, flattening nested
+  /// `Error::MultiError`s
+  pub fn into_errors(self) -> Vec<Error> {
     match self {
-      Error::Syntax(se) => write!(f, "CDDL syntax error: {}", se),
-      Error::Target(te) => write!(f, "{}", te),
-      Error::Compilation(ce) => write!(f, "error on compilation: {}", ce),
-      Error::Occurrence(oe) => write!(f, "occurrence error: {}", oe),
-      Error::MultiError(me) => {
-        let mut errors = String::new();
-
-        for e in me.iter() {
-          match e {
-            // Temporary work around for nested MultiError's
-            Error::MultiError(_) => errors.push_str(&format!("{}", e)),
-            _ => errors.push_str(&format!("{}\n\n", e)),
-          }
-        }
+      Error::MultiError(errors) => errors.into_iter().flat_map(Error::into_errors).collect(),
+      e => vec![e],
+    }
+  }
+
+  /// Renders the failures making up the error as an indented tree following
+  /// the structure of the data, listing the failures of the values nested
+  /// within each map or array beneath its key or index. Errors other than
+  /// failures to match the data, such as references to undefined rules, are
+  /// listed first.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{lexer_from_str, parser::cddl_from_str, validation::Error, Validator};
+  ///
+  /// let input = r#"reputon = { rater: tstr, ratings: [* uint] }"#;
+  /// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+  ///
+  /// let json = serde_json::json!({ "rater": 1, "ratings": [5, -1, "12"] });
+  /// let errors = cddl.validate_all(&json).unwrap_err();
+  ///
+  /// assert_eq!(
+  ///   Error::MultiError(errors).explain(),
+  ///   r#"rater
+  ///   expected rater: tstr, found 1
+  /// ratings
+  ///   1
+  ///     expected uint, found -1
+  ///   2
+  ///     expected uint, found "12"
+  /// "#
+  /// );
+  /// ```
+  pub fn explain(&self) -> String {
+    let mut output = String::new();
+    let mut tree = FailureTree::default();
 
-        write!(f, "{}", errors)
+    for e in self.errors() {
+      match e.validation_error() {
+        Some(ve) => tree.insert(
+          &pointer_tokens(&ve.path).unwrap_or_default(),
+          describe_failure(ve),
+        ),
+        None => output.push_str(&format!("{}\n", e)),
       }
     }
+    tree.render(&mut output, 0);
+
+    output
+  }
+
+  // Returns the individual failures making up the error like `into_errors`,
+  // without taking ownership of them
+  fn errors(&self) -> Vec<&Error> {
+    match self {
+      Error::MultiError(errors) => errors.iter().flat_map(Error::errors).collect(),
+      e => vec![e],
+    }
   }
-}
 
-impl std::error::Error for Error {
-  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+  /// Returns the `ValidationError` locating the value at fault if the error is a
+  /// failure of the data to match the CDDL definition
+  pub fn validation_error(&self) -> Option<&ValidationError> {
     match self {
-      Error::Compilation(ce) => Some(ce),
-      Error::Target(te) => Some(te.as_ref()),
+      Error::TypeMismatch(e) | Error::KeyMissing(e) | Error::ExtraKey(e) | Error::Control(e) => {
+        Some(e)
+      }
       _ => None,
     }
   }
+
+  /// Returns the error specific to the target data structure if it's of the
+  /// given type, i.e. either the `ValidationError` of a failure to match the
+  /// CDDL definition or the error of the library reading the data, e.g.
+  /// `serde_json::Error`
+  pub fn target<E: std::error::Error + 'static>(&self) -> Option<&E> {
+    match self {
+      Error::Target(te) => te.downcast_ref::<E>(),
+      _ => self
+        .validation_error()
+        .and_then(|e| (e as &(dyn std::error::Error + 'static)).downcast_ref::<E>()),
+    }
+  }
+
+  // Returns a copy of the error if it's made up of failures that can be copied,
+  // i.e. everything but errors from the target data structure's own library
+  // and compilation errors
+  fn try_clone(&self) -> Option<Error> {
+    match self {
+      Error::Syntax(se) => Some(Error::Syntax(se.clone())),
+      Error::UnknownRule(rule) => Some(Error::UnknownRule(rule.clone())),
+      Error::TypeMismatch(e) => Some(Error::TypeMismatch(e.clone())),
+      Error::KeyMissing(e) => Some(Error::KeyMissing(e.clone())),
+      Error::ExtraKey(e) => Some(Error::ExtraKey(e.clone())),
+      Error::Control(e) => Some(Error::Control(e.clone())),
+      Error::Target(_) | Error::Compilation(_) => None,
+      Error::Occurrence(oe) => Some(Error::Occurrence(oe.clone())),
+      Error::MultiError(errors) => errors
+        .iter()
+        .map(Error::try_clone)
+        .collect::<Option<Vec<_>>>()
+        .map(Error::MultiError),
+      Error::Recursion { rule, max_depth } => Some(Error::Recursion {
+        rule: rule.clone(),
+        max_depth: *max_depth,
+      }),
+      Error::Limit { limit, max } => Some(Error::Limit {
+        limit: *limit,
+        max: *max,
+      }),
+      Error::Encoding(ee) => Some(Error::Encoding(ee.clone())),
+      Error::DuplicateKey { key, positions } => Some(Error::DuplicateKey {
+        key: key.clone(),
+        positions: positions.clone(),
+      }),
+      Error::UnsortedKey(uk) => Some(Error::UnsortedKey(uk.clone())),
+      Error::Pointer(p) => Some(Error::Pointer(p.clone())),
+    }
+  }
+
+  // Attributes the failure of the value currently being validated to the given
+  // reason, leaving failures of values nested within it alone
+  fn with_reason(self, reason: Reason) -> Self {
+    match self {
+      Error::TypeMismatch(mut e) if e.reason == Reason::Mismatch && e.path == current_path() => {
+        e.reason = reason;
+        e.into()
+      }
+      Error::MultiError(errors) => {
+        Error::MultiError(errors.into_iter().map(|e| e.with_reason(reason)).collect())
+      }
+      e => e,
+    }
+  }
+}
+
+/// Failure to match data against the structure described by a CDDL definition
+///
+/// Held by the `Error` variant corresponding to its `reason`, e.g.
+/// `Error::TypeMismatch`, and serializable so that validation failures can be
+/// reported in a machine-readable form:
+///
+/// ```
+/// use cddl::{validate_json_from_str, validation::ValidationError};
+///
+/// let cddl_input = r#"person = { age: uint }"#;
+/// let errors = validate_json_from_str(cddl_input, r#"{ "age": -1 }"#)
+///   .unwrap_err()
+///   .into_errors();
+/// let error = errors[0].validation_error().unwrap();
+///
+/// assert_eq!(
+///   serde_json::to_value(error).unwrap(),
+///   serde_json::json!({
+///     "path": "/age",
+///     "rule": "person",
+///     "expected": { "member_key": "age:", "value": "uint" },
+///     "actual": -1,
+///     "reason": "mismatch",
+///   })
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValidationError {
+  /// Location of the value that failed to validate as a JSON Pointer (RFC
+  /// 6901), e.g. `/reputons/0/rating`. The root value is located at the empty
+  /// string.
+  pub path: String,
+  /// Name of the innermost rule being validated against, if any
+  pub rule: Option<String>,
+  /// What the value was expected to be
+  pub expected: Expected,
+  /// Value that failed to validate
+  pub actual: ActualValue,
+  /// Why the value failed to validate
+  pub reason: Reason,
+}
+
+/// Type or value expected by a CDDL definition
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Expected {
+  /// Member key of the expected map entry or array element, if any, e.g.
+  /// `name:`
+  pub member_key: Option<String>,
+  /// Expected type or value in CDDL syntax, e.g. `tstr` or `0..10`
+  pub value: String,
+}
+
+/// Value found in the data being validated
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ActualValue {
+  /// JSON value
+  JSON(serde_json::Value),
+  /// CBOR data item
+  #[cfg(feature = "cbor-validation")]
+  CBOR(serde_cbor::Value),
+}
+
+impl From<serde_json::Value> for ActualValue {
+  fn from(value: serde_json::Value) -> Self {
+    ActualValue::JSON(value)
+  }
+}
+
+#[cfg(feature = "cbor-validation")]
+impl From<serde_cbor::Value> for ActualValue {
+  fn from(value: serde_cbor::Value) -> Self {
+    ActualValue::CBOR(value)
+  }
+}
+
+impl ActualValue {
+  /// Deserializes the value into a type of the application, e.g. a struct
+  /// deriving `Deserialize` for a rule the value matched
+  pub fn deserialize<D: DeserializeOwned>(&self) -> result::Result<D, Error> {
+    match self {
+      ActualValue::JSON(value) => {
+        serde_json::from_value(value.clone()).map_err(|e| Error::Target(Box::new(e)))
+      }
+      #[cfg(feature = "cbor-validation")]
+      ActualValue::CBOR(value) => {
+        serde_cbor::value::from_value(value.clone()).map_err(|e| Error::Target(Box::new(e)))
+      }
+    }
+  }
+
+  fn is_map(&self) -> bool {
+    match self {
+      ActualValue::JSON(value) => value.is_object(),
+      #[cfg(feature = "cbor-validation")]
+      ActualValue::CBOR(serde_cbor::Value::Map(_)) => true,
+      #[cfg(feature = "cbor-validation")]
+      ActualValue::CBOR(_) => false,
+    }
+  }
+}
+
+impl fmt::Display for ActualValue {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let value = match self {
+      ActualValue::JSON(value) => serde_json::to_string_pretty(value),
+      #[cfg(feature = "cbor-validation")]
+      ActualValue::CBOR(value) => serde_json::to_string_pretty(value),
+    };
+
+    write!(f, "{}", value.map_err(|_| fmt::Error)?)
+  }
+}
+
+/// Map key out of canonical order, as reported by `Error::UnsortedKey`
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnsortedKey {
+  /// Key out of order, in diagnostic notation for CBOR
+  pub key: String,
+  /// Location of the entry with the key, as a JSON Pointer (RFC 6901)
+  pub path: String,
+}
+
+impl fmt::Display for UnsortedKey {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "map key {} at \"{}\" is out of canonical order",
+      self.key, self.path
+    )
+  }
+}
+
+/// Reason a value failed to validate
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+  /// Value isn't of the expected type or doesn't have the expected value
+  Mismatch,
+  /// Map doesn't contain an entry with the expected member key
+  MissingEntry,
+  /// Map contains an entry that no member key matches
+  UnexpectedEntry,
+  /// Value is outside of the expected range
+  Range,
+  /// Value doesn't satisfy a control operator, e.g. `.size` or `.regexp`
+  Control,
+}
+
+impl ValidationError {
+  // Creates an error at the current location within the data being validated.
+  // The expectation and value are only rendered and copied if the failure is
+  // being reported.
+  pub(crate) fn new<E, V>(
+    expected_memberkey: Option<String>,
+    expected_value: &E,
+    actual_memberkey: Option<String>,
+    actual_value: &V,
+  ) -> Self
+  where
+    E: fmt::Display + ?Sized,
+    V: Clone + Into<ActualValue>,
+  {
+    if is_deferring() {
+      return ValidationError {
+        path: String::new(),
+        rule: None,
+        expected: Expected {
+          member_key: None,
+          value: String::new(),
+        },
+        actual: ActualValue::JSON(serde_json::Value::Null),
+        reason: Reason::Mismatch,
+      };
+    }
+
+    let actual = actual_value.clone().into();
+    let expected_value = expected_value.to_string();
+
+    // The map itself is the actual value when the expected entry is missing
+    let reason = if expected_memberkey.is_some() && actual_memberkey.is_none() && actual.is_map() {
+      Reason::MissingEntry
+    } else {
+      Reason::Mismatch
+    };
+
+    // Some expectations are described by the whole entry, e.g. `name: tstr`
+    let expected_value = match &expected_memberkey {
+      Some(mk) if expected_value.starts_with(mk.as_str()) => {
+        expected_value[mk.len()..].trim_start().to_string()
+      }
+      _ => expected_value,
+    };
+
+    ValidationError {
+      path: current_path(),
+      rule: current_rule(),
+      expected: Expected {
+        member_key: expected_memberkey,
+        value: expected_value,
+      },
+      actual,
+      reason,
+    }
+  }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl fmt::Display for ValidationError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match (self.path.as_str(), &self.rule) {
+      ("", None) => (),
+      ("", Some(rule)) => writeln!(f, "in rule \"{}\"", rule)?,
+      (path, None) => writeln!(f, "at \"{}\"", path)?,
+      (path, Some(rule)) => writeln!(f, "at \"{}\" in rule \"{}\"", path, rule)?,
+    }
+
+    match &self.expected.member_key {
+      Some(mk) => write!(f, "expected: ( {} {} )", mk, self.expected.value)?,
+      None => write!(f, "expected: ( {} )", self.expected.value)?,
+    }
+
+    write!(f, "\nactual: {}", self.actual)
+  }
+}
+
+impl From<ValidationError> for Error {
+  fn from(e: ValidationError) -> Self {
+    Box::new(e).into()
+  }
+}
+
+impl From<Box<ValidationError>> for Error {
+  fn from(e: Box<ValidationError>) -> Self {
+    match e.reason {
+      Reason::Mismatch | Reason::Range => Error::TypeMismatch(e),
+      Reason::MissingEntry => Error::KeyMissing(e),
+      Reason::UnexpectedEntry => Error::ExtraKey(e),
+      Reason::Control => Error::Control(e),
+    }
+  }
+}
+
+// Failures located within a value, and within each of its members or
+// elements, in the order they were first reported
+#[derive(Default)]
+struct FailureTree {
+  failures: Vec<String>,
+  children: Vec<(String, FailureTree)>,
+}
+
+impl FailureTree {
+  fn insert(&mut self, tokens: &[String], failure: String) {
+    let (token, rest) = match tokens.split_first() {
+      Some(split) => split,
+      None => return self.failures.push(failure),
+    };
+
+    let idx = match self.children.iter().position(|(t, _)| t == token) {
+      Some(idx) => idx,
+      None => {
+        self.children.push((token.clone(), FailureTree::default()));
+        self.children.len() - 1
+      }
+    };
+    self.children[idx].1.insert(rest, failure);
+  }
+
+  fn render(&self, output: &mut String, depth: usize) {
+    let indent = "  ".repeat(depth);
+
+    for failure in self.failures.iter() {
+      output.push_str(&format!("{}{}\n", indent, failure));
+    }
+    for (token, child) in self.children.iter() {
+      output.push_str(&format!("{}{}\n", indent, token));
+      child.render(output, depth + 1);
+    }
+  }
+}
+
+// Describes a failure on a single line, leaving out its location
+fn describe_failure(e: &ValidationError) -> String {
+  let expected = match &e.expected.member_key {
+    Some(mk) => format!("{} {}", mk, single_line(&e.expected.value)),
+    None => single_line(&e.expected.value),
+  };
+
+  match e.reason {
+    Reason::MissingEntry => format!("missing {}", expected),
+    Reason::UnexpectedEntry => format!("unexpected entry {}", compact_value(&e.actual)),
+    _ => format!("expected {}, found {}", expected, compact_value(&e.actual)),
+  }
 }
 
 /// Compilation errors
-#[derive(Debug)]
+#[non_exhaustive]
+#[derive(Debug, thiserror::Error)]
 pub enum CompilationError {
   /// Error compiling CDDL data definition
+  #[error("{0}")]
   CDDL(String),
   /// Error compiling data target (i.e. JSON or CBOR)
-  Target(Box<dyn std::error::Error>),
+  #[error("{0}")]
+  Target(#[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// Warning or note about data that doesn't fail validation, e.g. a value that
+/// can't be converted between JSON and CBOR without loss, or a failure
+/// described by a `Report`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+  /// How severe the issue is
+  pub severity: Severity,
+  /// Location of the value the issue applies to as a JSON Pointer (RFC 6901)
+  pub path: String,
+  /// Description of the issue
+  pub message: String,
 }
 
-impl fmt::Display for CompilationError {
+impl fmt::Display for Diagnostic {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    match self {
-      CompilationError::CDDL(ce) => write!(f, "{}", ce),
-      CompilationError::Target(te) => write!(f, "{}", te),
+    write!(
+      f,
+      "{} at \"{}\": {}",
+      self.severity, self.path, self.message
+    )
+  }
+}
+
+impl<'a> From<&'a Error> for Diagnostic {
+  fn from(e: &'a Error) -> Self {
+    Diagnostic {
+      severity: Severity::Error,
+      path: e
+        .validation_error()
+        .map(|ve| ve.path.clone())
+        .unwrap_or_default(),
+      message: e.to_string(),
+    }
+  }
+}
+
+/// Outcome of validating data with `Validator::validate_with_diagnostics`.
+/// Like a `Result`, it's either ok or holds the errors that failed validation,
+/// but it also holds any warnings and notes raised along the way, which don't.
+#[derive(Debug)]
+pub struct Report {
+  /// Every failure found, as returned by `Validator::validate_all`
+  pub errors: Vec<Error>,
+  /// Warnings and notes, in the order they were raised
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Report {
+  /// Returns whether or not the data is valid, regardless of any warnings
+  pub fn is_ok(&self) -> bool {
+    self.errors.is_empty()
+  }
+
+  /// Returns the diagnostics of every severity, starting with the errors
+  pub fn all(&self) -> Vec<Diagnostic> {
+    self
+      .errors
+      .iter()
+      .map(Diagnostic::from)
+      .chain(self.diagnostics.iter().cloned())
+      .collect()
+  }
+
+  /// Converts the report into a `Result` holding the warnings and notes if
+  /// the data is valid, or the errors otherwise
+  pub fn into_result(self) -> result::Result<Vec<Diagnostic>, Vec<Error>> {
+    if self.errors.is_empty() {
+      Ok(self.diagnostics)
+    } else {
+      Err(self.errors)
     }
   }
 }
 
-impl std::error::Error for CompilationError {
-  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+/// Outcome of validating data along with the steps taken to do so, as
+/// returned by `Validator::validate_with_trace`
+#[derive(Debug)]
+pub struct Trace {
+  /// Result of validating the data, as returned by `Validator::validate`
+  pub result: Result,
+  /// Outermost steps, each holding the steps taken within it
+  pub steps: Vec<TraceStep>,
+}
+
+/// Validation of a value against a rule, against one of several type or group
+/// choices, or against the target of a `.capture` control
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceStep {
+  /// What the value was validated against
+  pub target: TraceTarget,
+  /// Location of the value within the data, as a JSON Pointer (RFC 6901)
+  pub path: String,
+  /// Value that was validated
+  pub value: ActualValue,
+  /// Whether or not the value matched
+  pub is_match: bool,
+  /// Steps taken to validate the value, in the order they were taken
+  pub steps: Vec<TraceStep>,
+}
+
+/// What a value was validated against in a step of a trace
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceTarget {
+  /// Rule with the given name
+  Rule(String),
+  /// Type choice, e.g. `tstr` in `int / tstr`
+  TypeChoice {
+    /// Position of the choice, starting from 0
+    index: usize,
+    /// Number of choices
+    count: usize,
+    /// Choice in CDDL syntax
+    choice: String,
+  },
+  /// Group choice, e.g. `b: tstr` in `{ a: int // b: tstr }`
+  GroupChoice {
+    /// Position of the choice, starting from 0
+    index: usize,
+    /// Number of choices
+    count: usize,
+    /// Choice in CDDL syntax
+    choice: String,
+  },
+  /// Capture with the given name, made by a `.capture` control
+  Capture(String),
+}
+
+impl fmt::Display for TraceTarget {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match self {
-      CompilationError::Target(te) => Some(te.as_ref()),
-      _ => None,
+      TraceTarget::Rule(name) => write!(f, "rule {}", name),
+      TraceTarget::TypeChoice {
+        index,
+        count,
+        choice,
+      } => write!(f, "type choice {} of {} `{}`", index + 1, count, choice),
+      TraceTarget::GroupChoice {
+        index,
+        count,
+        choice,
+      } => write!(f, "group choice {} of {} `{}`", index + 1, count, choice),
+      TraceTarget::Capture(name) => write!(f, "capture {}", name),
+    }
+  }
+}
+
+impl Trace {
+  /// Returns whether or not the data is valid
+  pub fn is_ok(&self) -> bool {
+    self.result.is_ok()
+  }
+
+  /// Renders the steps taken as an indented tree explaining the outcome. For
+  /// valid data, only the steps that matched are included, showing the rules
+  /// the data matched and which of their choices it matched. For invalid data,
+  /// every step is included.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{lexer_from_str, parser::cddl_from_str, Validator};
+  ///
+  /// let input = r#"shape = circle / square  circle = { radius: uint }  square = { side: uint }"#;
+  /// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+  ///
+  /// let trace = cddl.validate_with_trace(&serde_json::json!({ "side": 2 }));
+  /// assert_eq!(
+  ///   trace.explain(),
+  ///   r#"valid
+  /// rule shape matched at / {"side":2}
+  ///   type choice 2 of 2 `square` matched at / {"side":2}
+  ///     rule square matched at / {"side":2}
+  /// "#
+  /// );
+  /// ```
+  pub fn explain(&self) -> String {
+    let mut output = String::from(if self.is_ok() { "valid\n" } else { "invalid\n" });
+
+    for step in self.steps.iter() {
+      explain_step(&mut output, step, 0, self.is_ok());
+    }
+
+    output
+  }
+}
+
+// Maximum number of characters of a value shown by an explanation
+const MAX_EXPLAINED_VALUE_LEN: usize = 40;
+
+fn explain_step(output: &mut String, step: &TraceStep, depth: usize, is_valid: bool) {
+  if is_valid && !step.is_match {
+    return;
+  }
+
+  let value = compact_value(&step.value);
+  output.push_str(&format!(
+    "{}{} {} at {} {}\n",
+    "  ".repeat(depth),
+    step.target,
+    if step.is_match { "matched" } else { "failed" },
+    if step.path.is_empty() {
+      "/"
+    } else {
+      &step.path
+    },
+    value
+  ));
+
+  for s in step.steps.iter() {
+    explain_step(output, s, depth + 1, is_valid);
+  }
+}
+
+// Renders a value on a single line, truncated if it's too long to be read at a
+// glance
+fn compact_value(value: &ActualValue) -> String {
+  let mut value = match value {
+    ActualValue::JSON(value) => serde_json::to_string(value),
+    #[cfg(feature = "cbor-validation")]
+    ActualValue::CBOR(value) => serde_json::to_string(value),
+  }
+  .unwrap_or_default();
+
+  if value.chars().count() > MAX_EXPLAINED_VALUE_LEN {
+    value = value.chars().take(MAX_EXPLAINED_VALUE_LEN).collect();
+    value.push_str("...");
+  }
+
+  value
+}
+
+/// Values of the data matched by the rules of a definition, as returned by
+/// `Validator::validate_and_extract`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Extraction {
+  /// Each value matched by a rule, outer values before the values within them
+  pub matches: Vec<RuleMatch>,
+  /// Values captured by `.capture` controls, by the name given to the control.
+  /// A name captured more than once, e.g. by the elements of an array, holds
+  /// the value captured first.
+  pub captures: HashMap<String, ActualValue>,
+}
+
+/// Value of the data matched by a rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleMatch {
+  /// Name of the rule
+  pub rule: String,
+  /// Location of the value within the data, as a JSON Pointer (RFC 6901)
+  pub path: String,
+  /// Value that matched
+  pub value: ActualValue,
+}
+
+impl Extraction {
+  /// Returns the first value matched by the rule with the given name
+  pub fn value_of(&self, rule: &str) -> Option<&ActualValue> {
+    self.values_of(rule).into_iter().next()
+  }
+
+  /// Returns every value matched by the rule with the given name, in the order
+  /// they appear in the data
+  pub fn values_of(&self, rule: &str) -> Vec<&ActualValue> {
+    self
+      .matches
+      .iter()
+      .filter(|m| m.rule == rule)
+      .map(|m| &m.value)
+      .collect()
+  }
+
+  /// Returns the names of the rules matched by the value at the given JSON
+  /// Pointer, outermost first, e.g. `["shape", "circle"]` for a value matching
+  /// the `circle` choice of `shape = circle / square`
+  pub fn rules_at(&self, path: &str) -> Vec<&str> {
+    self
+      .matches
+      .iter()
+      .filter(|m| m.path == path)
+      .map(|m| m.rule.as_str())
+      .collect()
+  }
+}
+
+// Collects the rules matched by the steps of a trace that matched. Failed steps
+// are skipped along with everything within them, since whatever matched there
+// was only matched by a choice the data turned out not to take.
+fn collect_matches<'s>(
+  extraction: &mut Extraction,
+  seen: &mut HashSet<(&'s str, &'s str)>,
+  steps: &'s [TraceStep],
+) {
+  for step in steps.iter().filter(|s| s.is_match) {
+    match &step.target {
+      // A value can be validated against the same rule more than once, e.g.
+      // while working out which entries of an array its elements belong to
+      TraceTarget::Rule(rule) if seen.insert((rule, &step.path)) => {
+        extraction.matches.push(RuleMatch {
+          rule: rule.clone(),
+          path: step.path.clone(),
+          value: step.value.clone(),
+        });
+      }
+      TraceTarget::Capture(name) if !extraction.captures.contains_key(name) => {
+        extraction.captures.insert(name.clone(), step.value.clone());
+      }
+      _ => (),
     }
+
+    collect_matches(extraction, seen, &step.steps);
   }
 }
 
@@ -91,6 +1147,181 @@ pub trait Validator<T> {
   /// Initiate validation
   fn validate(&self, value: &T) -> Result;
 
+  /// Initiate validation, continuing past failing map entries and array
+  /// elements, and return every failure found rather than stopping at the
+  /// first
+  ///
+  /// Each error is a single failure rather than an `Error::MultiError`.
+  /// Failures to match the data against the structure described by the CDDL
+  /// hold a `ValidationError`, which records the location of the failure
+  /// within the data and the rule that failed to match.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{lexer_from_str, parser::cddl_from_str, Validator};
+  ///
+  /// let input = r#"reputon = { rater: tstr, ratings: [* rating] }  rating = 0..10"#;
+  /// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+  ///
+  /// let json = serde_json::json!({ "rater": 1, "ratings": [5, 11, 12] });
+  /// let errors = cddl.validate_all(&json).unwrap_err();
+  ///
+  /// let paths = errors
+  ///   .iter()
+  ///   .filter_map(|e| e.validation_error())
+  ///   .map(|e| e.path.as_str())
+  ///   .collect::<Vec<_>>();
+  /// assert_eq!(paths, ["/rater", "/ratings/1", "/ratings/2"]);
+  /// ```
+  fn validate_all(&self, value: &T) -> result::Result<(), Vec<Error>> {
+    let _guard = CollectGuard::new();
+
+    self.validate(value).map_err(Error::into_errors)
+  }
+
+  /// Initiate validation, collecting every failure like `validate_all` along
+  /// with warnings and notes about the data that don't fail validation, such
+  /// as values matching the target but not the controller of a `.within`
+  /// control, or JSON integers too large to be represented exactly by most
+  /// JSON parsers
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{lexer_from_str, parser::cddl_from_str, validation::Severity, Validator};
+  ///
+  /// let input = r#"id = uint .within (0..65535)"#;
+  /// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+  ///
+  /// let report = cddl.validate_with_diagnostics(&serde_json::json!(70000));
+  /// assert!(report.is_ok());
+  /// assert_eq!(report.diagnostics[0].severity, Severity::Warning);
+  /// ```
+  fn validate_with_diagnostics(&self, value: &T) -> Report {
+    let _guard = DiagnosticsGuard::new();
+    let errors = self.validate_all(value).err().unwrap_or_default();
+
+    Report {
+      errors,
+      diagnostics: DIAGNOSTICS.with(|d| d.borrow_mut().take().unwrap_or_default()),
+    }
+  }
+
+  /// Initiate validation, recording each rule the data is validated against
+  /// and each type and group choice tried, along with the value validated and
+  /// whether or not it matched. Useful for working out why data is or isn't
+  /// valid against definitions with many choices, e.g. via `Trace::explain`.
+  ///
+  /// Rules resolved via the standard prelude aren't recorded, and neither are
+  /// types or groups with a single choice. Values of the target of a
+  /// `.capture` control are recorded along with the name of the capture.
+  fn validate_with_trace(&self, value: &T) -> Trace {
+    let _guard = TraceGuard::new();
+    let result = self.validate(value);
+
+    Trace {
+      result,
+      steps: TRACE.with(|t| t.borrow_mut().take().map(|t| t.steps).unwrap_or_default()),
+    }
+  }
+
+  /// Initiate validation and, if the data is valid, return the values matched
+  /// by each rule of the definition along with where they were found, so that
+  /// they can be extracted without walking the data again
+  ///
+  /// Like `validate_with_trace`, values matched by rules of the standard
+  /// prelude aren't included, so values of entries such as `name: tstr` are
+  /// found within the value of the rule defining them.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{lexer_from_str, parser::cddl_from_str, Validator};
+  ///
+  /// let input = r#"order = { customer: tstr, items: [* item] }
+  /// item = { sku: tstr, quantity: uint }"#;
+  /// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+  ///
+  /// let json = serde_json::json!({
+  ///   "customer": "Ada",
+  ///   "items": [{ "sku": "A1", "quantity": 2 }, { "sku": "B2", "quantity": 1 }],
+  /// });
+  /// let extraction = cddl.validate_and_extract(&json).unwrap();
+  ///
+  /// assert_eq!(extraction.values_of("item").len(), 2);
+  /// assert_eq!(extraction.rules_at("/items/1"), ["item"]);
+  /// ```
+  fn validate_and_extract(&self, value: &T) -> result::Result<Extraction, Error> {
+    let trace = self.validate_with_trace(value);
+    trace.result?;
+
+    let mut extraction = Extraction::default();
+    collect_matches(&mut extraction, &mut HashSet::new(), &trace.steps);
+
+    Ok(extraction)
+  }
+
+  /// Initiate validation and, if the data is valid, return the values marked
+  /// for extraction by `.capture` controls of the definition, by the name
+  /// given to each control, e.g. `customer: tstr .capture "customer"`
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{lexer_from_str, parser::cddl_from_str, validation::ActualValue, Validator};
+  ///
+  /// let input = r#"order = { customer: tstr .capture "customer", items: [* item] }
+  /// item = { sku: tstr, quantity: uint }"#;
+  /// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+  ///
+  /// let json = serde_json::json!({ "customer": "Ada", "items": [] });
+  /// let captures = cddl.validate_and_capture(&json).unwrap();
+  ///
+  /// assert_eq!(
+  ///   captures["customer"],
+  ///   ActualValue::JSON(serde_json::json!("Ada"))
+  /// );
+  /// ```
+  fn validate_and_capture(&self, value: &T) -> result::Result<HashMap<String, ActualValue>, Error> {
+    self
+      .validate_and_extract(value)
+      .map(|extraction| extraction.captures)
+  }
+
+  /// Initiate validation and explain the outcome, as rendered by
+  /// `Trace::explain`
+  fn explain(&self, value: &T) -> String {
+    self.validate_with_trace(value).explain()
+  }
+
+  /// Initiate validation against the rule with the given name rather than the
+  /// first type rule, e.g. for definitions with a rule per message type
+  fn validate_with_root(&self, rule_name: &str, value: &T) -> Result;
+
+  /// Validate only the value located by the given JSON Pointer (RFC 6901)
+  /// within the data against the rule with the given name, e.g. to re-validate
+  /// the part of a large document that was just edited. Failures are located
+  /// relative to the root of the data rather than to the value.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use cddl::{lexer_from_str, parser::cddl_from_str, Validator};
+  ///
+  /// let input = r#"order = { customer: tstr, items: [* item] }
+  /// item = { sku: tstr, quantity: uint }"#;
+  /// let cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+  ///
+  /// let json = serde_json::json!({
+  ///   "customer": "Ada",
+  ///   "items": [{ "sku": "A1", "quantity": 2 }, { "sku": "B2", "quantity": -1 }],
+  /// });
+  /// assert!(cddl.validate_value_at("item", "/items/0", &json).is_ok());
+  /// assert!(cddl.validate_value_at("item", "/items/1", &json).is_err());
+  /// ```
+  fn validate_value_at(&self, rule_name: &str, pointer: &str, value: &T) -> Result;
+
   /// Validate data against the rule with the given identifier
   fn validate_rule_for_ident(
     &self,
@@ -102,6 +1333,20 @@ pub trait Validator<T> {
     value: &T,
   ) -> Result;
 
+  /// Validate data against the rule with the given identifier, with its
+  /// generic parameters replaced by the given arguments
+  #[allow(clippy::too_many_arguments)]
+  fn validate_generic_rule(
+    &self,
+    ident: &Identifier,
+    generic_arg: &GenericArg,
+    is_enumeration: bool,
+    expected_memberkey: Option<String>,
+    actual_memberkey: Option<String>,
+    occur: Option<&Occur>,
+    value: &T,
+  ) -> Result;
+
   /// Validate data against a given type rule
   fn validate_type_rule(
     &self,
@@ -202,32 +1447,1078 @@ pub trait Validator<T> {
   ) -> Result;
 }
 
-impl<'a> CDDL<'a> {
-  fn numerical_value_type_from_ident(&self, ident: &Identifier) -> Option<Vec<&Type2>> {
-    let mut type_choices = Vec::new();
-
-    for rule in self.rules.iter() {
-      match rule {
-        Rule::Type { rule, .. } if rule.name.ident == ident.ident => {
-          for tc in rule.value.type_choices.iter() {
-            match &tc.type2 {
-              Type2::IntValue { .. } | Type2::UintValue { .. } | Type2::FloatValue { .. } => {
-                type_choices.push(&tc.type2);
-              }
-              Type2::Typename { ident, .. } => return self.numerical_value_type_from_ident(ident),
-              _ => continue,
-            }
-          }
-        }
-        _ => continue,
-      }
-    }
+// Decrements the validation depth of the current thread when dropped, along
+// with leaving the rule being resolved if the name was defined by one
+struct DepthGuard(bool);
 
-    if !type_choices.is_empty() {
-      return Some(type_choices);
-    }
+impl Drop for DepthGuard {
+  fn drop(&mut self) {
+    DEPTH.with(|d| d.set(d.get() - 1));
 
-    None
+    if self.0 {
+      RULES.with(|r| r.borrow_mut().pop());
+    }
+  }
+}
+
+// Leaves the current location within the data when dropped, if it was
+// recorded
+struct PathGuard(bool);
+
+impl Drop for PathGuard {
+  fn drop(&mut self) {
+    if self.0 {
+      PATH.with(|p| p.borrow_mut().pop());
+    }
+  }
+}
+
+// Resumes reporting failures on the current thread when dropped
+struct DeferGuard;
+
+impl Drop for DeferGuard {
+  fn drop(&mut self) {
+    DEFER.with(|d| d.set(false));
+  }
+}
+
+// Reference token locating a value within its parent array or map. Indices are
+// only rendered when the path is.
+#[derive(Clone)]
+enum PathToken {
+  Index(usize),
+  Key(String),
+}
+
+impl From<usize> for PathToken {
+  fn from(idx: usize) -> Self {
+    PathToken::Index(idx)
+  }
+}
+
+impl<'a> From<&'a str> for PathToken {
+  fn from(key: &'a str) -> Self {
+    PathToken::Key(key.to_string())
+  }
+}
+
+// Captures the diagnostics raised on the current thread until dropped
+struct DiagnosticsGuard(Option<Vec<Diagnostic>>);
+
+impl DiagnosticsGuard {
+  fn new() -> Self {
+    DiagnosticsGuard(DIAGNOSTICS.with(|d| d.replace(Some(Vec::new()))))
+  }
+}
+
+impl Drop for DiagnosticsGuard {
+  fn drop(&mut self) {
+    DIAGNOSTICS.with(|d| d.replace(self.0.take()));
+  }
+}
+
+// Locates the data being validated on the current thread beneath the given
+// path until dropped
+struct PathPrefixGuard(Vec<PathToken>);
+
+impl PathPrefixGuard {
+  fn new(prefix: Vec<PathToken>) -> Self {
+    PathPrefixGuard(PATH.with(|p| p.replace(prefix)))
+  }
+}
+
+impl Drop for PathPrefixGuard {
+  fn drop(&mut self) {
+    PATH.with(|p| p.replace(self.0.split_off(0)));
+  }
+}
+
+// Steps of a trace, with the steps still being taken kept apart until
+// they're complete
+#[derive(Default)]
+struct Tracer {
+  steps: Vec<TraceStep>,
+  open: Vec<TraceStep>,
+}
+
+// Traces validation on the current thread until dropped
+struct TraceGuard(Option<Tracer>);
+
+impl TraceGuard {
+  fn new() -> Self {
+    TraceGuard(TRACE.with(|t| t.replace(Some(Tracer::default()))))
+  }
+}
+
+impl Drop for TraceGuard {
+  fn drop(&mut self) {
+    TRACE.with(|t| t.replace(self.0.take()));
+  }
+}
+
+// Enables collecting every failure on the current thread until dropped
+struct CollectGuard(bool);
+
+impl CollectGuard {
+  fn new() -> Self {
+    CollectGuard(COLLECT.with(|c| c.replace(true)))
+  }
+}
+
+impl Drop for CollectGuard {
+  fn drop(&mut self) {
+    COLLECT.with(|c| c.set(self.0));
+  }
+}
+
+// Validates data found at the given map key or array index of the data being
+// validated
+fn at<T: Into<PathToken>, F: FnOnce() -> Result>(token: T, f: F) -> Result {
+  let is_recorded = !is_deferring();
+  if is_recorded {
+    PATH.with(|p| p.borrow_mut().push(token.into()));
+  }
+  let _guard = PathGuard(is_recorded);
+
+  f()
+}
+
+// Validates the number of values of a repeated entry or group against its
+// occurrence indicator
+fn validate_occurrence_count(occur: &Occur, group: &str, len: usize) -> Result {
+  match occur {
+    Occur::ZeroOrMore(_) => Ok(()),
+    Occur::Optional(_) => {
+      if len > 1 {
+        Err(Error::Occurrence(format!(
+          "Expecting zero or one values of group {}",
+          group
+        )))
+      } else {
+        Ok(())
+      }
+    }
+    Occur::OneOrMore(_) => {
+      if len == 0 {
+        Err(Error::Occurrence(format!(
+          "Expecting one or more values of group {}",
+          group
+        )))
+      } else {
+        Ok(())
+      }
+    }
+    Occur::Exact { lower, upper, .. } => {
+      if let Some(li) = lower {
+        if let Some(ui) = upper {
+          if len < *li || len > *ui {
+            if li == ui {
+              return Err(Error::Occurrence(format!(
+                "Expecting exactly {} values of group {}. Got {} values",
+                li, group, len
+              )));
+            }
+
+            return Err(Error::Occurrence(format!(
+              "Expecting between {} and {} values of group {}. Got {} values",
+              li, ui, group, len
+            )));
+          }
+        }
+
+        if len < *li {
+          return Err(Error::Occurrence(format!(
+            "Expecting at least {} values of group {}. Got {} values",
+            li, group, len
+          )));
+        }
+      }
+
+      if let Some(ui) = upper {
+        if len > *ui {
+          return Err(Error::Occurrence(format!(
+            "Expecting no more than {} values of group {}. Got {} values",
+            ui, group, len
+          )));
+        }
+      }
+
+      Ok(())
+    }
+  }
+}
+
+// Widens a uint literal for comparison against integers being validated.
+// Literals beyond the range of i128 saturate, since neither JSON nor CBOR
+// integers get that large anyway
+fn uint_literal(ui: u128) -> i128 {
+  i128::try_from(ui).unwrap_or((!0u128 >> 1) as i128)
+}
+
+// Returns the minimum and maximum number of times an entry with the given
+// occurrence indicator occurs, which is exactly once without one
+fn occurrence_bounds(occur: Option<&Occur>) -> (usize, Option<usize>) {
+  match occur {
+    None => (1, Some(1)),
+    Some(Occur::Optional(_)) => (0, Some(1)),
+    Some(Occur::ZeroOrMore(_)) => (0, None),
+    Some(Occur::OneOrMore(_)) => (1, None),
+    Some(Occur::Exact { lower, upper, .. }) => (lower.unwrap_or(0), *upper),
+  }
+}
+
+// Returns whether the given type unwraps a group, as in { ~a, b: int }
+fn is_unwrap(t: &Type) -> bool {
+  match t.type_choices.as_slice() {
+    [Type1 {
+      type2: Type2::Unwrap { .. },
+      operator: None,
+      ..
+    }] => true,
+    _ => false,
+  }
+}
+
+// Validates each element of an array, stopping at the first failure unless
+// every failure is being collected
+fn validate_elements<T, F: Fn(&T) -> Result>(values: &[T], f: F) -> Result {
+  let mut errors = Vec::new();
+
+  for (idx, v) in values.iter().enumerate() {
+    if let Err(e) = at(idx, || f(v)) {
+      if !is_collecting() {
+        return Err(e);
+      }
+
+      errors.push(e);
+    }
+  }
+
+  match errors.len() {
+    0 => Ok(()),
+    1 => Err(errors.remove(0)),
+    _ => Err(Error::MultiError(errors)),
+  }
+}
+
+// Drops failures identical to one reported earlier, e.g. by another choice
+// that failed on the same nested value
+fn dedup_errors<I: IntoIterator<Item = Error>>(errors: I) -> Vec<Error> {
+  let mut deduped: Vec<Error> = Vec::new();
+
+  for e in errors {
+    let is_duplicate = match e.validation_error() {
+      Some(ve) => deduped.iter().any(|d| d.validation_error() == Some(ve)),
+      None => false,
+    };
+
+    if !is_duplicate {
+      deduped.push(e);
+    }
+  }
+
+  deduped
+}
+
+// Returns the failures of the choices most plausibly meant to match the data,
+// given the error of each choice that didn't. The choices whose failures lie
+// deepest within the value got furthest before failing, and of those, the ones
+// failing the fewest times came closest to matching. Other errors, such as
+// references to undefined rules, are kept whichever choice they came from.
+fn most_plausible(choices: Vec<Error>) -> Vec<Error> {
+  let choices = choices
+    .into_iter()
+    .map(Error::into_errors)
+    .collect::<Vec<_>>();
+
+  let progress = |errors: &[Error]| {
+    let failures = || errors.iter().filter_map(Error::validation_error);
+    (
+      failures().map(failure_depth).max(),
+      Reverse(failures().count()),
+    )
+  };
+  let best = choices.iter().map(|errors| progress(errors)).max();
+
+  let mut errors = Vec::new();
+  for choice in choices {
+    let is_plausible = Some(progress(&choice)) == best;
+    errors.extend(
+      choice
+        .into_iter()
+        .filter(|e| is_plausible || e.validation_error().is_none()),
+    );
+  }
+
+  errors
+}
+
+// Number of values the failure is nested within. An unexpected entry is a
+// failure of the map it's in.
+fn failure_depth(e: &ValidationError) -> usize {
+  let depth = e.path.matches('/').count();
+
+  match e.reason {
+    Reason::UnexpectedEntry => depth.saturating_sub(1),
+    _ => depth,
+  }
+}
+
+// Returns whether or not the error is a failure to match the value currently
+// being validated, as opposed to a value nested within it
+fn is_value_mismatch(e: &Error) -> bool {
+  match e.validation_error() {
+    Some(e) => e.path == current_path(),
+    None => false,
+  }
+}
+
+// Returns whether or not validation should continue past failures in order to
+// report every one of them
+fn is_collecting() -> bool {
+  COLLECT.with(|c| c.get())
+}
+
+// Returns whether or not failures are only being detected rather than reported
+fn is_deferring() -> bool {
+  DEFER.with(|d| d.get())
+}
+
+// Returns whether or not diagnostics are being captured
+fn is_diagnosing() -> bool {
+  DIAGNOSTICS.with(|d| d.borrow().is_some())
+}
+
+// Returns whether or not validation is being traced
+fn is_tracing() -> bool {
+  TRACE.with(|t| t.borrow().is_some())
+}
+
+// Opens a step of the trace for the given value if validation is being traced
+// and the given closure returns what the data is being validated against,
+// returning whether or not one was opened. Opening and closing steps inline
+// rather than wrapping validation in a closure keeps deeply nested data from
+// costing extra stack frames
+fn open_step<T, G>(target: G, value: &T) -> bool
+where
+  T: Clone + Into<ActualValue>,
+  G: FnOnce() -> Option<TraceTarget>,
+{
+  if !is_tracing() {
+    return false;
+  }
+  let target = match target() {
+    Some(target) => target,
+    None => return false,
+  };
+
+  let step = TraceStep {
+    target,
+    path: current_path(),
+    value: value.clone().into(),
+    is_match: false,
+    steps: Vec::new(),
+  };
+  TRACE.with(|t| {
+    if let Some(tracer) = t.borrow_mut().as_mut() {
+      tracer.open.push(step);
+    }
+  });
+
+  true
+}
+
+// Closes the step opened by open_step, recording the result of validation
+fn close_step(is_open: bool, result: &Result) {
+  if !is_open {
+    return;
+  }
+
+  TRACE.with(|t| {
+    if let Some(tracer) = t.borrow_mut().as_mut() {
+      if let Some(mut step) = tracer.open.pop() {
+        step.is_match = result.is_ok();
+        match tracer.open.last_mut() {
+          Some(parent) => parent.steps.push(step),
+          None => tracer.steps.push(step),
+        }
+      }
+    }
+  });
+}
+
+// Returns the type choice at the given position as the target of a step of a
+// trace, if there's more than one
+fn type_choice(t: &Type, index: usize) -> Option<TraceTarget> {
+  if t.type_choices.len() < 2 {
+    return None;
+  }
+
+  Some(TraceTarget::TypeChoice {
+    index,
+    count: t.type_choices.len(),
+    choice: single_line(&t.type_choices[index].to_string()),
+  })
+}
+
+// Returns the group choice at the given position as the target of a step of a
+// trace, if there's more than one
+fn group_choice(g: &Group, index: usize) -> Option<TraceTarget> {
+  if g.group_choices.len() < 2 {
+    return None;
+  }
+
+  Some(TraceTarget::GroupChoice {
+    index,
+    count: g.group_choices.len(),
+    choice: single_line(&g.group_choices[index].to_string()),
+  })
+}
+
+// Collapses the whitespace of CDDL text rendered across several lines
+fn single_line(cddl: &str) -> String {
+  cddl.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Raises a warning or note about the value currently being validated, if
+// diagnostics are being captured. Raising the same one twice has no effect.
+fn diagnose<F: FnOnce() -> String>(severity: Severity, message: F) {
+  if is_diagnosing() {
+    record_diagnostic(Diagnostic {
+      severity,
+      path: current_path(),
+      message: message(),
+    });
+  }
+}
+
+fn record_diagnostic(diagnostic: Diagnostic) {
+  DIAGNOSTICS.with(|d| {
+    if let Some(diagnostics) = d.borrow_mut().as_mut() {
+      if !diagnostics.contains(&diagnostic) {
+        diagnostics.push(diagnostic);
+      }
+    }
+  });
+}
+
+// Validates data via the given function, discarding the diagnostics it raised
+// if it fails, since they concern a choice that doesn't apply to the data
+fn tentatively<F: FnOnce() -> Result>(f: F) -> Result {
+  let raised = DIAGNOSTICS.with(|d| d.borrow().as_ref().map(Vec::len));
+
+  let result = f();
+  if let (Err(_), Some(raised)) = (&result, raised) {
+    DIAGNOSTICS.with(|d| {
+      if let Some(diagnostics) = d.borrow_mut().as_mut() {
+        diagnostics.truncate(raised);
+      }
+    });
+  }
+
+  result
+}
+
+// Validates data that's been decoded from the data given to the validator via
+// the given function, which the decoded data doesn't outlive
+fn transiently<F: FnOnce() -> Result>(f: F) -> Result {
+  let was_transient = TRANSIENT.with(|t| t.replace(true));
+  let result = f();
+  TRANSIENT.with(|t| t.set(was_transient));
+
+  result
+}
+
+// Validates data via the given function without recording the details of any
+// failures, then again to report them if there were any. Most failures are
+// found in choices that are discarded once another choice matches, so valid
+// data is validated without rendering a single expectation or copying a
+// single value.
+fn report_lazily<F: Fn() -> Result>(f: F) -> Result {
+  // Diagnostics and traces are located by the path of the data, which isn't
+  // recorded while deferring
+  if is_deferring() || is_diagnosing() || is_tracing() {
+    return f();
+  }
+
+  let detected = {
+    DEFER.with(|d| d.set(true));
+    let _guard = DeferGuard;
+
+    f()
+  };
+  // Results memoized along the way lack the details of their failures
+  forget_memoized();
+
+  match detected {
+    Ok(()) => Ok(()),
+    Err(_) => f(),
+  }
+}
+
+// Returns the current location within the data being validated as a JSON
+// Pointer (RFC 6901)
+fn current_path() -> String {
+  PATH.with(|p| {
+    p.borrow().iter().fold(String::new(), |mut path, token| {
+      path.push('/');
+      match token {
+        PathToken::Index(idx) => path.push_str(&idx.to_string()),
+        PathToken::Key(key) => path.push_str(&key.replace('~', "~0").replace('/', "~1")),
+      }
+      path
+    })
+  })
+}
+
+// Splits a JSON Pointer (RFC 6901) into its unescaped reference tokens
+fn pointer_tokens(pointer: &str) -> result::Result<Vec<String>, Error> {
+  if pointer.is_empty() {
+    return Ok(Vec::new());
+  }
+  if !pointer.starts_with('/') {
+    return Err(Error::Pointer(pointer.to_string()));
+  }
+
+  Ok(
+    pointer[1..]
+      .split('/')
+      .map(|token| token.replace("~1", "/").replace("~0", "~"))
+      .collect(),
+  )
+}
+
+// Parses a reference token of a JSON Pointer as an array index, which is made
+// up of digits without leading zeros
+fn pointer_index(token: &str) -> Option<usize> {
+  if token.is_empty() || !token.bytes().all(|b| b.is_ascii_digit()) {
+    return None;
+  }
+  if token.len() > 1 && token.starts_with('0') {
+    return None;
+  }
+
+  token.parse().ok()
+}
+
+// Returns the name of the innermost rule being resolved, if any
+fn current_rule() -> Option<String> {
+  RULES.with(|r| r.borrow().last().cloned())
+}
+
+// Identifies the validation of data against a rule along with the context the
+// rule was referenced in, since member keys are part of the failures reported
+#[derive(PartialEq, Eq, Hash)]
+struct MemoKey {
+  cddl: usize,
+  rule: String,
+  value: usize,
+  is_enumeration: bool,
+  expected_memberkey: Option<String>,
+  actual_memberkey: Option<String>,
+}
+
+// Validates data via the given function, reusing the result recorded for the
+// same key if there is one
+fn memoized<F: FnOnce() -> Result>(key: Option<MemoKey>, f: F) -> Result {
+  let key = match key {
+    Some(key) => key,
+    None => return f(),
+  };
+
+  let recalled = MEMO.with(|m| match m.borrow().get(&key) {
+    Some(Ok(())) => Some(Ok(())),
+    Some(Err(e)) => e.try_clone().map(Err),
+    None => None,
+  });
+  if let Some(result) = recalled {
+    return result;
+  }
+
+  let result = f();
+
+  let recorded = match &result {
+    Ok(()) => Some(Ok(())),
+    Err(e) => e.try_clone().map(Err),
+  };
+  if let Some(recorded) = recorded {
+    MEMO.with(|m| m.borrow_mut().insert(key, recorded));
+  }
+
+  result
+}
+
+// Forgets every memoized result, e.g. once the data they were recorded for is
+// dropped
+fn forget_memoized() {
+  MEMO.with(|m| m.borrow_mut().clear());
+}
+
+// Number of type rules followed when looking for the array described by a
+// rule, e.g. root = records, records = [* record]
+const MAX_ARRAY_INDIRECTION: usize = 16;
+
+// Repeated entry of an array with no other entries
+#[derive(Clone, Copy)]
+enum RepeatedEntry<'a, 'b> {
+  // [* record]
+  Typename {
+    occur: &'b Occur,
+    name: &'b Identifier<'a>,
+    generic_arg: &'b Option<GenericArg<'a>>,
+  },
+  // [* { id: uint }]
+  Type {
+    occur: &'b Occur,
+    t: &'b Type<'a>,
+  },
+}
+
+// Returns the repeated entry of the array described by the given type, if it
+// describes an array with a single repeated entry, following type rules that
+// merely refer to another type
+fn repeated_entry<'a, 'b>(cddl: &'b CDDL<'a>, t: &'b Type<'a>) -> Option<RepeatedEntry<'a, 'b>> {
+  let mut t = t;
+
+  for _ in 0..MAX_ARRAY_INDIRECTION {
+    let t2 = match t.type_choices.as_slice() {
+      [Type1 {
+        type2,
+        operator: None,
+        ..
+      }] => type2,
+      _ => return None,
+    };
+
+    match t2 {
+      Type2::Typename {
+        ident,
+        generic_arg: None,
+        ..
+      } => match cddl.rules_for(ident).collect::<Vec<_>>().as_slice() {
+        [Rule::Type { rule, .. }] if rule.generic_param.is_none() => t = &rule.value,
+        _ => return None,
+      },
+      Type2::Array { group, .. } => return repeated_group_entry(cddl, group),
+      _ => return None,
+    }
+  }
+
+  None
+}
+
+// Returns the repeated entry of the given group, if it's the group's only entry
+fn repeated_group_entry<'a, 'b>(
+  cddl: &'b CDDL<'a>,
+  group: &'b Group<'a>,
+) -> Option<RepeatedEntry<'a, 'b>> {
+  let entry = match group.group_choices.as_slice() {
+    [GroupChoice { group_entries, .. }] => match group_entries.as_slice() {
+      [(entry, _)] => entry,
+      _ => return None,
+    },
+    _ => return None,
+  };
+
+  match entry {
+    GroupEntry::TypeGroupname { ge, .. } => {
+      // Elements of a repeated group rule span several array items
+      if let Some(Rule::Group { .. }) = cddl.rule(ge.name.ident) {
+        return None;
+      }
+
+      Some(RepeatedEntry::Typename {
+        occur: ge.occur.as_ref()?,
+        name: &ge.name,
+        generic_arg: &ge.generic_arg,
+      })
+    }
+    GroupEntry::ValueMemberKey { ge, .. } if ge.member_key.is_none() => Some(RepeatedEntry::Type {
+      occur: ge.occur.as_ref()?,
+      t: &ge.entry_type,
+    }),
+    _ => None,
+  }
+}
+
+impl<'a> CDDL<'a> {
+  /// Sets the maximum number of nested rule references that are followed while
+  /// validating data. Validation fails with `Error::Recursion` once exceeded.
+  pub fn set_max_validation_depth(&mut self, max_depth: usize) {
+    self.validation_options.max_depth = Some(max_depth);
+  }
+
+  /// Enables or disables memoization of validation results. When enabled, the
+  /// result of validating a value against a rule is recorded for the remainder
+  /// of the validation and reused whenever the same value is validated against
+  /// the same rule again, as happens when backtracking through type and group
+  /// choices. This trades memory for time on definitions with many choices
+  /// between structures sharing the same nested rules.
+  pub fn set_validation_memoization(&mut self, enabled: bool) {
+    self.validation_options.memoize = enabled;
+  }
+
+  /// Registers a callback deciding whether values match types that are
+  /// referred to but not defined, allowing types defined by the application at
+  /// runtime, such as enumerations stored in a database, to take part in
+  /// validation. See `UnknownRuleResolver`.
+  ///
+  /// ```
+  /// use cddl::{lexer_from_str, parser::cddl_from_str, validation::ActualValue, Validator};
+  ///
+  /// let input = r#"order = { id: uint, status: order-status }"#;
+  /// let mut cddl = cddl_from_str(&mut lexer_from_str(input), input, true).unwrap();
+  ///
+  /// cddl.on_unknown_rule(|name, value| match (name, value) {
+  ///   ("order-status", ActualValue::JSON(status)) => {
+  ///     Some(["open", "shipped"].iter().any(|s| status == s))
+  ///   }
+  ///   _ => None,
+  /// });
+  ///
+  /// let json = serde_json::json!({ "id": 1, "status": "shipped" });
+  /// assert!(cddl.validate(&json).is_ok());
+  ///
+  /// let json = serde_json::json!({ "id": 1, "status": "lost" });
+  /// assert!(cddl.validate(&json).is_err());
+  /// ```
+  pub fn on_unknown_rule<F>(&mut self, resolve: F)
+  where
+    F: Fn(&str, &ActualValue) -> Option<bool> + Send + Sync + 'static,
+  {
+    self.validation_options.on_unknown_rule = Some(UnknownRuleResolver::new(resolve));
+  }
+
+  /// Replaces every option controlling how data is validated
+  pub fn set_validation_options(&mut self, options: ValidationOptions) {
+    self.validation_options = options;
+  }
+
+  /// Returns the options controlling how data is validated
+  pub fn validation_options(&self) -> &ValidationOptions {
+    &self.validation_options
+  }
+
+  // Records that the rule with the given name is being resolved for as long as
+  // the returned guard is alive, failing if the maximum depth is exceeded
+  fn enter_rule(&self, ident: &Identifier) -> result::Result<DepthGuard, Error> {
+    let max_depth = self
+      .validation_options
+      .max_depth
+      .unwrap_or(DEFAULT_MAX_DEPTH);
+
+    // Validation fails as a whole once a limit has been exceeded, so there's no
+    // point resolving any more rules, which could otherwise take exponential
+    // time when every rule reaching the limit is backtracked through
+    if let Some(error) = EXCEEDED.with(|e| e.borrow().as_ref().and_then(Error::try_clone)) {
+      return Err(error);
+    }
+
+    DEPTH.with(|d| {
+      if d.get() >= max_depth {
+        let error = || Error::Recursion {
+          rule: ident.ident.to_string(),
+          max_depth,
+        };
+        EXCEEDED.with(|e| {
+          e.borrow_mut().get_or_insert_with(error);
+        });
+
+        return Err(error());
+      }
+
+      d.set(d.get() + 1);
+
+      // Names resolved via the standard prelude aren't reported as rules, and
+      // neither are any while failures are only being detected
+      let is_rule = !is_deferring() && self.rule(ident.ident).is_some();
+      if is_rule {
+        RULES.with(|r| r.borrow_mut().push(ident.ident.to_string()));
+      }
+
+      Ok(DepthGuard(is_rule))
+    })
+  }
+
+  // Returns the rule with the given name as the target of a step of a trace,
+  // unless it's resolved via the standard prelude
+  fn rule_target(&self, ident: &Identifier) -> Option<TraceTarget> {
+    if is_prelude_type(ident.ident) {
+      return None;
+    }
+
+    self
+      .rule(ident.ident)
+      .map(|_| TraceTarget::Rule(ident.to_string()))
+  }
+
+  // Fails if an array with the given number of elements exceeds the maximum
+  // array length
+  fn check_array_length(&self, len: usize) -> Result {
+    match self.validation_options.max_array_length {
+      Some(max) if len > max => {
+        let error = || Error::Limit {
+          limit: Limit::ArrayLength,
+          max,
+        };
+        EXCEEDED.with(|e| {
+          e.borrow_mut().get_or_insert_with(error);
+        });
+
+        Err(error())
+      }
+      _ => Ok(()),
+    }
+  }
+
+  // Returns whether a float satisfies the float types of the prelude, which
+  // NaN and infinities only do if allowed
+  fn is_float_accepted(&self, f: f64) -> bool {
+    f.is_finite() || self.validation_options.non_finite_floats == NonFiniteFloats::Allow
+  }
+
+  // Validates a map against an entry without a member key, such as the [int]
+  // in { x: int, ? [int] }. No key/value pair can match such an entry, so the
+  // map is only valid if its occurrence allows it to be absent.
+  fn validate_keyless_entry<T>(&self, vmke: &ValueMemberKeyEntry, value: &T) -> Result
+  where
+    T: Clone + Into<ActualValue>,
+  {
+    match occurrence_bounds(vmke.occur.as_ref()) {
+      (0, _) => Ok(()),
+      _ => Err(
+        Error::from(ValidationError::new(None, &vmke.entry_type, None, value))
+          .with_reason(Reason::MissingEntry),
+      ),
+    }
+  }
+
+  // Validates data against the types of the entries of a group, as for the
+  // enumeration &( red: 0, green: 1 ), whose member keys only serve as
+  // documentation. Entries referring to other groups contribute the types of
+  // their entries in turn.
+  fn validate_choice_from_group<T>(&self, g: &Group, value: &T) -> Result
+  where
+    Self: Validator<T>,
+    T: Clone + Into<ActualValue>,
+  {
+    let mut validation_errors = Vec::new();
+
+    for (ge, _) in g.group_choices.iter().flat_map(|gc| &gc.group_entries) {
+      let result = tentatively(|| match ge {
+        GroupEntry::ValueMemberKey { ge, .. } => {
+          self.validate_type(&ge.entry_type, None, None, None, value)
+        }
+        GroupEntry::TypeGroupname { ge, .. } if self.is_group_rule(&ge.name) => {
+          match &ge.generic_arg {
+            Some(ga) => self.validate_generic_rule(&ge.name, ga, true, None, None, None, value),
+            None => self.validate_rule_for_ident(&ge.name, true, None, None, None, value),
+          }
+        }
+        GroupEntry::TypeGroupname { ge, .. } => self.validate_group_name(ge, value),
+        GroupEntry::InlineGroup { group, .. } => self.validate_choice_from_group(group, value),
+      });
+
+      match result {
+        Ok(()) => return Ok(()),
+        Err(e) => validation_errors.push(e),
+      }
+    }
+
+    let validation_errors = dedup_errors(most_plausible(validation_errors));
+    if validation_errors.iter().all(is_value_mismatch) {
+      return Err(ValidationError::new(None, &format_args!("&({})", g), None, value).into());
+    }
+
+    Err(Error::MultiError(validation_errors))
+  }
+
+  // Returns the key under which the result of validating data against the rule
+  // with the given name is memoized, if memoization is enabled. Results depend
+  // on the occurrence indicator in effect, so they're only memoized without
+  // one.
+  #[allow(clippy::too_many_arguments)]
+  fn memo_key<T>(
+    &self,
+    ident: &Identifier,
+    generic_arg: Option<&GenericArg>,
+    is_enumeration: bool,
+    expected_memberkey: &Option<String>,
+    actual_memberkey: &Option<String>,
+    occur: Option<&Occur>,
+    value: &T,
+  ) -> Option<MemoKey> {
+    if !self.validation_options.memoize || occur.is_some() || TRANSIENT.with(Cell::get) {
+      return None;
+    }
+
+    Some(MemoKey {
+      cddl: self as *const CDDL as usize,
+      rule: match generic_arg {
+        Some(ga) => format!("{}{}", ident, ga),
+        None => ident.to_string(),
+      },
+      value: value as *const T as usize,
+      is_enumeration,
+      expected_memberkey: expected_memberkey.clone(),
+      actual_memberkey: actual_memberkey.clone(),
+    })
+  }
+
+  // Returns the name of the rule with the given name, failing if there's no
+  // such rule to validate against
+  fn root_ident(&self, rule_name: &str) -> result::Result<&Identifier<'a>, Error> {
+    match self.rule(rule_name) {
+      Some(Rule::Type { rule, .. }) => Ok(&rule.name),
+      Some(Rule::Group { rule, .. }) => Ok(&rule.name),
+      None => Err(Error::Compilation(CompilationError::CDDL(format!(
+        "no rule named \"{}\" to validate against",
+        rule_name
+      )))),
+    }
+  }
+
+  // Validates data against the rule with the given identifier
+  fn validate_root_rule<T>(&self, ident: &Identifier, value: &T) -> Result
+  where
+    Self: Validator<T>,
+    T: DataValue,
+  {
+    self.validate_root(|| {
+      report_lazily(|| {
+        #[cfg(feature = "parallel")]
+        {
+          // Steps taken on other threads can't be traced
+          let is_parallel = self.validation_options.parallel && !is_tracing();
+          if let (true, DataKind::Array(values)) = (is_parallel, value.kind()) {
+            if let Some(entry) = self.root_repeated_entry(ident) {
+              let _guard = self.enter_rule(ident)?;
+              self.check_array_length(values.len())?;
+
+              return self.validate_elements_in_parallel(entry, values);
+            }
+          }
+        }
+
+        self.validate_rule_for_ident(ident, false, None, None, None, value)
+      })
+    })
+  }
+
+  // Validates the value located by the given JSON Pointer within the data
+  // against the rule with the given name
+  fn validate_value_at_pointer<T>(&self, rule_name: &str, pointer: &str, value: &T) -> Result
+  where
+    Self: Validator<T>,
+    T: DataValue,
+  {
+    let ident = self.root_ident(rule_name)?;
+
+    let mut target = value;
+    let mut path = Vec::new();
+    for token in pointer_tokens(pointer)? {
+      target = target
+        .lookup(&token)
+        .ok_or_else(|| Error::Pointer(pointer.to_string()))?;
+      path.push(PathToken::Key(token));
+    }
+
+    let _guard = PathPrefixGuard::new(path);
+    self.validate_root_rule(ident, target)
+  }
+
+  // Validates an element of an array with a single repeated entry against the
+  // entry
+  #[cfg(any(feature = "parallel", feature = "tokio"))]
+  fn validate_element<T>(&self, entry: RepeatedEntry, value: &T) -> Result
+  where
+    CDDL<'a>: Validator<T>,
+  {
+    match entry {
+      RepeatedEntry::Typename {
+        name, generic_arg, ..
+      } => {
+        if self.is_builtin_type(name) {
+          return self.validate_type2(
+            &Type2::Typename {
+              ident: name.clone(),
+              generic_arg: generic_arg.clone(),
+              span: (0, 0, 0),
+            },
+            None,
+            None,
+            None,
+            value,
+          );
+        }
+
+        self.validate_rule_for_ident(name, false, None, None, None, value)
+      }
+      RepeatedEntry::Type { t, .. } => self.validate_type(t, None, None, None, value),
+    }
+  }
+
+  // Returns the repeated entry of the array described by the rule with the
+  // given name, if it describes an array with a single repeated entry
+  #[cfg(feature = "parallel")]
+  fn root_repeated_entry<'b>(&'b self, ident: &Identifier<'a>) -> Option<RepeatedEntry<'a, 'b>> {
+    match self.rules_for(ident).collect::<Vec<_>>().as_slice() {
+      [Rule::Type { rule, .. }] if rule.generic_param.is_none() => {
+        repeated_entry(self, &rule.value)
+      }
+      _ => None,
+    }
+  }
+
+  // Validates data via the given function, returning `Error::Recursion` or
+  // `Error::Limit` in place of its result if the maximum depth or
+  // another limit was exceeded along the way
+  fn validate_root<F: FnOnce() -> Result>(&self, f: F) -> Result {
+    // Validation started from within an ongoing validation leaves reporting to
+    // the outermost one
+    if DEPTH.with(|d| d.get()) > 0 {
+      return f();
+    }
+
+    EXCEEDED.with(|e| e.borrow_mut().take());
+    forget_memoized();
+
+    let result = f();
+    forget_memoized();
+
+    match EXCEEDED.with(|e| e.borrow_mut().take()) {
+      Some(e) => Err(e),
+      None => result,
+    }
+  }
+
+  // Returns whether the type name refers to a type whose semantics the
+  // validators check themselves, i.e. a standard prelude type or an extension
+  // type that the definition doesn't define
+  fn is_builtin_type(&self, ident: &Identifier) -> bool {
+    is_prelude_type(ident.ident)
+      || (is_extension_type(ident.ident) && self.rules_for(ident).next().is_none())
+  }
+
+  // Validates a text string against the semantics of a standard prelude type
+  // whose values are conveyed as text strings, failing with a type mismatch
+  // for the given value otherwise. Returns None if the given type name isn't
+  // one of these types.
+  fn validate_prelude_text<V: Clone + Into<ActualValue>>(
+    &self,
+    ident: &str,
+    text: &str,
+    expected_memberkey: Option<String>,
+    actual_memberkey: Option<String>,
+    value: &V,
+  ) -> Option<Result> {
+    let result = parse_prelude_text(ident, text, &self.validation_options)?;
+
+    Some(
+      result.map_err(|_| {
+        ValidationError::new(expected_memberkey, ident, actual_memberkey, value).into()
+      }),
+    )
   }
 
   // Checks whether or not a given type is a type name identifier and that it
@@ -235,8 +2526,8 @@ impl<'a> CDDL<'a> {
   fn is_type_string_data_type(&self, t2: &Type2) -> bool {
     match t2 {
       Type2::Typename { ident, .. } if ident.ident == "text" || ident.ident == "tstr" => true,
-      Type2::Typename { ident, .. } => self.rules.iter().any(|r| match r {
-        Rule::Type { rule, .. } if rule.name.ident == ident.ident => rule
+      Type2::Typename { ident, .. } => self.rules_for(ident).any(|r| match r {
+        Rule::Type { rule, .. } => rule
           .value
           .type_choices
           .iter()
@@ -247,11 +2538,26 @@ impl<'a> CDDL<'a> {
     }
   }
 
+  fn is_type_byte_string_data_type(&self, t2: &Type2) -> bool {
+    match t2 {
+      Type2::Typename { ident, .. } if ident.ident == "bytes" || ident.ident == "bstr" => true,
+      Type2::Typename { ident, .. } => self.rules_for(ident).any(|r| match r {
+        Rule::Type { rule, .. } => rule
+          .value
+          .type_choices
+          .iter()
+          .any(|tc| self.is_type_byte_string_data_type(&tc.type2)),
+        _ => false,
+      }),
+      _ => false,
+    }
+  }
+
   fn is_type_numeric_data_type(&self, t2: &Type2) -> bool {
     match t2 {
-      Type2::Typename { ident, .. } if is_numeric_data_type(&ident.ident) => true,
-      Type2::Typename { ident, .. } => self.rules.iter().any(|r| match r {
-        Rule::Type { rule, .. } if rule.name.ident == ident.ident => rule
+      Type2::Typename { ident, .. } if is_numeric_data_type(ident.ident) => true,
+      Type2::Typename { ident, .. } => self.rules_for(ident).any(|r| match r {
+        Rule::Type { rule, .. } => rule
           .value
           .type_choices
           .iter()
@@ -262,16 +2568,17 @@ impl<'a> CDDL<'a> {
     }
   }
 
-  // Returns the text value(s) from a given type
-  fn text_values_from_type(&'a self, ident: &'a Type2) -> result::Result<Vec<&'a str>, Error> {
+  // Returns the text value(s) from a given type, or None if it isn't a text
+  // value or a rule defined as one
+  fn text_values_from_type(&'a self, ident: &'a Type2) -> Option<Vec<&'a str>> {
     match ident {
-      Type2::TextValue { value, .. } => Ok(vec![value]),
+      Type2::TextValue { value, .. } => Some(vec![value]),
       Type2::Typename { ident, .. } => {
         let mut text_values = Vec::new();
 
-        for r in self.rules.iter() {
+        for r in self.rules_for(ident) {
           match r {
-            Rule::Type { rule, .. } if rule.name.ident == ident.ident => {
+            Rule::Type { rule, .. } => {
               for tc in rule.value.type_choices.iter() {
                 text_values.append(&mut self.text_values_from_type(&tc.type2)?);
               }
@@ -280,103 +2587,84 @@ impl<'a> CDDL<'a> {
           }
         }
 
-        Ok(text_values)
+        Some(text_values)
       }
-      _ => Err(Error::Syntax(
-        "Value can only be referenced via another type name identifier".into(),
-      )),
+      _ => None,
     }
   }
 
-  // Returns the numeric value(s) from a given type, provided their types match
-  // that of the given target data type
-  fn numeric_values_from_type(
-    &self,
-    target: &Type2,
-    ident: &Type2,
-  ) -> result::Result<Vec<Numeric>, Error> {
-    let target_idents = self.numerical_ident_from_type(target)?;
-
-    match ident {
-      Type2::IntValue{ value, .. } => {
-        if target_idents.into_iter().any(|ti| ti == "int" || ti == "number") {
-          return Ok(vec![Numeric::INT(*value)])
-        }
-
-        Err(Error::Syntax("Target data type must be an 'int' or 'number' in order to validate against an integer value".into()))
-      }
-      Type2::UintValue{ value, .. } => {
-        if target_idents.into_iter().any(|ti| ti == "uint" || ti == "number") {
-          return Ok(vec![Numeric::UINT(*value)]);
-        }
-
-        Err(Error::Syntax("Target data type must be a 'uint' or 'number' in order to validate against an unsigned integer value".into()))
-      }
-      Type2::FloatValue{ value, .. } => {
-        if target_idents.into_iter().any(|ti| match ti {
-          "float" | "float16" | "float32" | "float64" | "float16-32" | "float32-64" | "number" => true,
-          _ => false,
-        }) {
-          return Ok(vec![Numeric::FLOAT(*value)]);
-        }
-
-        Err(Error::Syntax("Target data type must be a 'float', 'float16', 'float32', 'float64', 'float16-32', 'float32-64' or 'number' in order to validate against an unsigned integer value".into()))
-      }
-      Type2::Typename{ ident, .. } => {
+  // Returns the numeric value(s) from a given type, or None if it isn't a
+  // numeric value or a rule defined as one. The values are compared
+  // numerically, so they apply to a target of any numeric type, e.g.
+  // `int .lt 10`.
+  fn numeric_values_from_type(&self, t2: &Type2) -> Option<Vec<Numeric>> {
+    match t2 {
+      Type2::IntValue { value, .. } => Some(vec![Numeric::INT(*value)]),
+      Type2::UintValue { value, .. } => Some(vec![Numeric::UINT(*value)]),
+      Type2::FloatValue { value, .. } => Some(vec![Numeric::FLOAT(*value)]),
+      Type2::Typename { ident, .. } => {
         let mut numeric_values = Vec::new();
 
-        for r in self.rules.iter() {
+        for r in self.rules_for(ident) {
           match r {
-            Rule::Type{ rule, ..}  if rule.name.ident == ident.ident => {
+            Rule::Type { rule, .. } => {
               for tc in rule.value.type_choices.iter() {
-                numeric_values.append(&mut self.numeric_values_from_type(target, &tc.type2)?);
+                numeric_values.append(&mut self.numeric_values_from_type(&tc.type2)?);
               }
             }
             _ => continue,
           }
         }
 
-        Ok(numeric_values)
+        Some(numeric_values)
       }
-      _ => Err(Error::Syntax(
-        "Value can only be referenced via another type name identifier whose target type is the type of the value".into(),
-      )),
+      _ => None,
     }
   }
+}
 
-  fn numerical_ident_from_type(&'a self, t2: &'a Type2) -> result::Result<Vec<&'a str>, Error> {
-    let mut numeric_type_idents = Vec::new();
+fn parse_prelude_text(
+  ident: &str,
+  value: &str,
+  options: &ValidationOptions,
+) -> Option<result::Result<(), String>> {
+  match ident {
+    "tstr" | "text" => Some(Ok(())),
+    "tdate" => Some(time::check_tdate(value, options.date_time)),
+    #[cfg(feature = "formats")]
+    "uri" | "b64url" | "b64legacy" | "regexp" | "mime-message" => {
+      Some(formats::check_format(ident, value))
+    }
+    // The formats of these types are only checked with the "formats" feature
+    #[cfg(not(feature = "formats"))]
+    "uri" | "b64url" | "b64legacy" | "regexp" | "mime-message" => Some(Ok(())),
+    _ => None,
+  }
+}
 
-    match t2 {
-      Type2::Typename { ident, .. } if is_numeric_data_type(&ident.ident) => {
-        numeric_type_idents.push(ident.ident);
-        Ok(numeric_type_idents)
+// Returns whether a float in the data being validated matches a float literal
+// as compared by the given means
+fn is_float_literal_match(comparison: FloatComparison, literal: f64, f: f64) -> bool {
+  match comparison {
+    FloatComparison::Exact => f == literal,
+    FloatComparison::Ulps(max) => {
+      if f.is_nan() || literal.is_nan() {
+        return false;
       }
-      Type2::Typename { ident, .. } => {
-        for r in self.rules.iter() {
-          match r {
-            Rule::Type { rule, .. } if rule.name.ident == ident.ident => {
-              for tc in rule.value.type_choices.iter() {
-                numeric_type_idents.append(&mut self.numerical_ident_from_type(&tc.type2)?);
-              }
-            }
-            _ => continue,
-          }
+
+      // Mapping the bits of negative floats below those of positive ones gives
+      // integers in the same order as the floats, one apart for each
+      // representable float in between. Both zeros map to 0.
+      let ordered = |f: f64| {
+        let bits = f.to_bits() as i64;
+        if bits < 0 {
+          i128::from(i64::min_value()) - i128::from(bits)
+        } else {
+          i128::from(bits)
         }
+      };
 
-        Ok(numeric_type_idents)
-      }
-      _ => Err(Error::Syntax(
-        "Numeric identifier can only be referenced via another type name identifier".into(),
-      )),
+      (ordered(f) - ordered(literal)).abs() <= i128::from(max)
     }
   }
 }
-
-fn is_numeric_data_type(t: &str) -> bool {
-  match t {
-    "uint" | "nint" | "int" | "number" | "float" | "float16" | "float32" | "float64"
-    | "float16-32" | "float32-64" => true,
-    _ => false,
-  }
-}
@@ -0,0 +1,227 @@
+use super::{
+  at, is_collecting, is_diagnosing, record_diagnostic, Diagnostic, Error, PathToken, RepeatedEntry,
+  Result, Validator, COLLECT, DEFER, DEPTH, DIAGNOSTICS, EXCEEDED, MEMO, PATH, RULES,
+};
+use crate::ast::*;
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+// Validation state of the thread that started validating an array, carried
+// over to the threads validating its elements
+struct Context {
+  path: Vec<PathToken>,
+  rules: Vec<String>,
+  depth: usize,
+  collect: bool,
+  defer: bool,
+  diagnose: bool,
+}
+
+impl Context {
+  fn current() -> Self {
+    Context {
+      path: PATH.with(|p| p.borrow().clone()),
+      rules: RULES.with(|r| r.borrow().clone()),
+      depth: DEPTH.with(|d| d.get()),
+      collect: COLLECT.with(|c| c.get()),
+      defer: DEFER.with(|d| d.get()),
+      diagnose: is_diagnosing(),
+    }
+  }
+
+  // Runs the given function with the context in place of the state of the
+  // current thread, which is restored afterwards since rayon may run the
+  // function on the thread that started the validation
+  fn enter<F: FnOnce() -> Result>(&self, f: F) -> Outcome {
+    let path = PATH.with(|p| p.replace(self.path.clone()));
+    let rules = RULES.with(|r| r.replace(self.rules.clone()));
+    let depth = DEPTH.with(|d| d.replace(self.depth));
+    let collect = COLLECT.with(|c| c.replace(self.collect));
+    let defer = DEFER.with(|d| d.replace(self.defer));
+    let exceeded = EXCEEDED.with(|e| e.replace(None));
+    let memo = MEMO.with(|m| m.replace(HashMap::new()));
+    let diagnostics = DIAGNOSTICS.with(|d| {
+      d.replace(if self.diagnose {
+        Some(Vec::new())
+      } else {
+        None
+      })
+    });
+
+    let result = f();
+    let element_exceeded = EXCEEDED.with(|e| e.replace(exceeded));
+    let element_diagnostics = DIAGNOSTICS.with(|d| d.replace(diagnostics));
+
+    PATH.with(|p| p.replace(path));
+    RULES.with(|r| r.replace(rules));
+    DEPTH.with(|d| d.set(depth));
+    COLLECT.with(|c| c.set(collect));
+    DEFER.with(|d| d.set(defer));
+    MEMO.with(|m| m.replace(memo));
+
+    Outcome {
+      result,
+      exceeded: element_exceeded,
+      diagnostics: element_diagnostics.unwrap_or_default(),
+    }
+  }
+}
+
+// Result of validating an element on another thread, along with the state it
+// left behind that's merged back into the state of the thread that started the
+// validation
+struct Outcome {
+  result: Result,
+  // Error for the maximum depth or another limit if one was exceeded
+  exceeded: Option<Error>,
+  // Warnings and notes raised, if they're being captured
+  diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> CDDL<'a> {
+  // Validates the elements of an array against its repeated entry across the
+  // threads of rayon's global thread pool. Failures are merged in the order of
+  // the elements they were found in, so the outcome is the same as validating
+  // the elements one after the other.
+  pub(super) fn validate_elements_in_parallel<T>(
+    &self,
+    entry: RepeatedEntry,
+    values: &[T],
+  ) -> Result
+  where
+    CDDL<'a>: Validator<T> + Sync,
+    T: Sync,
+  {
+    let (occur, group) = match entry {
+      RepeatedEntry::Typename { occur, name, .. } => (occur, name.to_string()),
+      RepeatedEntry::Type { occur, t } => (occur, t.to_string()),
+    };
+    self.validate_array_occurrence(occur, &group, values)?;
+
+    let context = Context::current();
+    let results = values
+      .par_iter()
+      .enumerate()
+      .map(|(idx, v)| context.enter(|| at(idx, || self.validate_element(entry, v))))
+      .collect::<Vec<_>>();
+
+    let mut errors = Vec::new();
+    for outcome in results {
+      if let Some(exceeded) = outcome.exceeded {
+        EXCEEDED.with(|e| {
+          e.borrow_mut().get_or_insert(exceeded);
+        });
+      }
+
+      for diagnostic in outcome.diagnostics {
+        record_diagnostic(diagnostic);
+      }
+
+      if let Err(e) = outcome.result {
+        if !is_collecting() {
+          return Err(e);
+        }
+
+        errors.push(e);
+      }
+    }
+
+    match errors.len() {
+      0 => Ok(()),
+      1 => Err(errors.remove(0)),
+      _ => Err(Error::MultiError(errors)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{lexer_from_str, parser::cddl_from_str, validation::ValidationOptions};
+  use serde_json::json;
+
+  const RECORDS: &str = r#"
+    records = [* record]
+    record = { id: uint, name: tstr }
+  "#;
+
+  fn parallel_cddl(input: &str) -> CDDL<'_> {
+    let mut cddl = cddl_from_str(&mut lexer_from_str(input), input, false).unwrap();
+    cddl.set_validation_options(ValidationOptions {
+      parallel: true,
+      ..Default::default()
+    });
+
+    cddl
+  }
+
+  #[test]
+  fn validate_json_in_parallel() {
+    let cddl = parallel_cddl(RECORDS);
+    let records = (0..1000)
+      .map(|id| json!({ "id": id, "name": format!("record {}", id) }))
+      .collect::<Vec<_>>();
+
+    assert!(cddl.validate(&json!(records)).is_ok());
+    assert!(cddl.validate(&json!({ "id": 1, "name": "a" })).is_err());
+  }
+
+  #[test]
+  fn validate_json_in_parallel_in_order() {
+    let sequential = cddl_from_str(&mut lexer_from_str(RECORDS), RECORDS, false).unwrap();
+    let cddl = parallel_cddl(RECORDS);
+    let mut records = (0..1000)
+      .map(|id| json!({ "id": id, "name": format!("record {}", id) }))
+      .collect::<Vec<_>>();
+    records[400]["id"] = json!(-1);
+    records[600]["name"] = json!(1);
+    let records = json!(records);
+
+    assert_eq!(
+      format!("{:?}", cddl.validate(&records)),
+      format!("{:?}", sequential.validate(&records))
+    );
+
+    let errors = cddl.validate_all(&records).unwrap_err();
+    assert_eq!(
+      format!("{:?}", errors),
+      format!("{:?}", sequential.validate_all(&records).unwrap_err())
+    );
+    assert_eq!(errors.len(), 2);
+  }
+
+  #[test]
+  fn validate_json_with_diagnostics_in_parallel() {
+    let cddl = parallel_cddl("ids = [* int]");
+    let mut ids = (0..1000).map(|id| json!(id)).collect::<Vec<_>>();
+    ids[300] = json!(1u64 << 60);
+    ids[700] = json!(1u64 << 61);
+
+    let paths = cddl
+      .validate_with_diagnostics(&json!(ids))
+      .into_result()
+      .unwrap()
+      .into_iter()
+      .map(|d| d.path)
+      .collect::<Vec<_>>();
+    assert_eq!(paths, ["/300", "/700"]);
+  }
+
+  #[test]
+  fn validate_cbor_in_parallel() {
+    let cddl = parallel_cddl("values = [+ value]  value = int / tstr");
+    let values = (0..1000)
+      .map(serde_cbor::Value::Integer)
+      .collect::<Vec<_>>();
+
+    assert!(cddl.validate(&serde_cbor::Value::Array(values)).is_ok());
+    assert!(cddl
+      .validate(&serde_cbor::Value::Array(vec![serde_cbor::Value::Null]))
+      .is_err());
+
+    match cddl.validate(&serde_cbor::Value::Array(Vec::new())) {
+      Err(Error::Occurrence(_)) => (),
+      result => panic!("unexpected result: {:?}", result),
+    }
+  }
+}
@@ -0,0 +1,172 @@
+use super::{
+  value::{DataKind, DataValue},
+  DateTimeLeniency,
+};
+use std::result;
+
+/// Checks a text string against the date-time format of RFC 3339 that `tdate`
+/// requires, e.g. `2020-05-12T08:30:00Z`
+pub fn check_tdate(value: &str, leniency: DateTimeLeniency) -> result::Result<(), String> {
+  let error = |e: chrono::ParseError| format!("error parsing date value {}: {}", value, e);
+
+  match chrono::DateTime::parse_from_rfc3339(value) {
+    Ok(_) => Ok(()),
+    Err(e) if leniency == DateTimeLeniency::AllowMissingOffset => {
+      chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+        .map(|_| ())
+        .map_err(|_| error(e))
+    }
+    Err(e) => Err(error(e)),
+  }
+}
+
+/// Checks a number of seconds relative to the epoch that `time` requires. NaN
+/// and the infinities don't denote a point in time, and RFC 8949 advises
+/// against tagging them as one.
+pub fn check_time(seconds: f64) -> result::Result<(), String> {
+  if seconds.is_finite() {
+    Ok(())
+  } else {
+    Err(format!("{} isn't a point in time", seconds))
+  }
+}
+
+/// Checks a value against `duration`, which is either a text string in the
+/// duration format of ISO 8601 or a number of seconds. Tagged durations of RFC
+/// 9581 are only conveyed by CBOR, whose validator unwraps them.
+pub fn check_duration<T: DataValue>(value: &T) -> result::Result<(), String> {
+  match value.kind() {
+    DataKind::Text(s) => check_duration_text(s),
+    DataKind::Integer(_) => Ok(()),
+    DataKind::Float(f) => check_time(f),
+    _ => Err("a duration must be a text string or a number of seconds".into()),
+  }
+}
+
+// Checks a text string against the duration format of ISO 8601 as described in
+// appendix A of RFC 3339, e.g. P1Y2M10DT2H30M or P3W. The components have to
+// appear in order, and only the last of them may have a decimal fraction.
+fn check_duration_text(value: &str) -> result::Result<(), String> {
+  let error = || format!("error parsing duration value {}", value);
+
+  if !value.starts_with('P') {
+    return Err(error());
+  }
+
+  let components = &value[1..];
+  let fractions = if components.ends_with('W') {
+    duration_components(components, "W")
+  } else {
+    match components.find('T') {
+      Some(idx) => duration_components(&components[..idx], "YMD").and_then(|mut date| {
+        let time = duration_components(&components[idx + 1..], "HMS")?;
+        if time.is_empty() {
+          return None;
+        }
+
+        date.extend(time);
+        Some(date)
+      }),
+      None => duration_components(components, "YMD"),
+    }
+  };
+
+  match fractions {
+    Some(ref f) if !f.is_empty() && !f[..f.len() - 1].contains(&true) => Ok(()),
+    _ => Err(error()),
+  }
+}
+
+// Returns whether each of the components of the date or time part of a
+// duration has a decimal fraction, or None if the part isn't made up of numbers
+// followed by the given designators in order
+fn duration_components(part: &str, designators: &str) -> Option<Vec<bool>> {
+  let mut fractions = Vec::new();
+  let mut designators = designators;
+  let mut start = 0;
+
+  for (idx, c) in part.char_indices() {
+    if c.is_ascii_digit() || c == '.' || c == ',' {
+      continue;
+    }
+
+    let position = designators.find(c)?;
+    let number = &part[start..idx];
+    let (whole, fraction) = match number.find(&['.', ','][..]) {
+      Some(separator) => (&number[..separator], Some(&number[separator + 1..])),
+      None => (number, None),
+    };
+
+    let is_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    if !is_digits(whole) || !fraction.into_iter().all(is_digits) {
+      return None;
+    }
+
+    fractions.push(fraction.is_some());
+    designators = &designators[position + 1..];
+    start = idx + 1;
+  }
+
+  // A trailing number lacks its designator
+  if start == part.len() {
+    Some(fractions)
+  } else {
+    None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn verify_tdate() {
+    for valid in &[
+      "2020-05-12T08:30:00Z",
+      "2020-05-12T08:30:00.123+02:00",
+      "1985-04-12t23:20:50.52z",
+    ] {
+      assert!(
+        check_tdate(valid, DateTimeLeniency::Strict).is_ok(),
+        "{}",
+        valid
+      );
+    }
+
+    for invalid in &["2020-05-12", "2020-05-12T08:30:00", "2020-13-12T08:30:00Z"] {
+      assert!(
+        check_tdate(invalid, DateTimeLeniency::Strict).is_err(),
+        "{}",
+        invalid
+      );
+    }
+
+    assert!(check_tdate("2020-05-12T08:30:00", DateTimeLeniency::AllowMissingOffset).is_ok());
+    assert!(check_tdate(
+      "2020-05-12T08:30:00.5",
+      DateTimeLeniency::AllowMissingOffset
+    )
+    .is_ok());
+    assert!(check_tdate("2020-05-12", DateTimeLeniency::AllowMissingOffset).is_err());
+  }
+
+  #[test]
+  fn verify_duration_text() {
+    for valid in &[
+      "P1Y2M10DT2H30M",
+      "P3W",
+      "PT36H",
+      "P1D",
+      "PT0.5S",
+      "P1DT1,5H",
+    ] {
+      assert!(check_duration_text(valid).is_ok(), "{}", valid);
+    }
+
+    for invalid in &[
+      "P", "PT", "1D", "P1H", "PT1D", "P1D2Y", "P1W2D", "P1.5DT1H", "P1", "PT1.S", "P1DT",
+    ] {
+      assert!(check_duration_text(invalid).is_err(), "{}", invalid);
+    }
+  }
+}
@@ -0,0 +1,53 @@
+use super::ActualValue;
+use crate::ast::CDDL;
+use std::borrow::Cow;
+
+// Data that can be validated against CDDL by the shared validation engine.
+// Implemented for the values of each supported data format, so that the
+// handling of rules, choices, ranges and controls only has to be written once
+// while the format-specific parts, such as matching literals, prelude types
+// and maps, are left to each format's `Validator` implementation.
+pub(crate) trait DataValue: Clone + Into<ActualValue> + Sync + Sized {
+  // Name of the data format, as used in error messages
+  const FORMAT: &'static str;
+
+  // Whether the format has an undefined value distinct from null
+  const HAS_UNDEFINED: bool;
+
+  // Returns the kind of the value, for the checks shared between formats
+  fn kind(&self) -> DataKind<'_, Self>;
+
+  // Returns the bytes of the value if it represents a byte string
+  fn bytes<'v>(&'v self, cddl: &CDDL) -> Option<Cow<'v, [u8]>>;
+
+  // Returns the member or element of the value located by a reference token of
+  // a JSON Pointer
+  fn lookup(&self, token: &str) -> Option<&Self>;
+
+  // Creates an unsigned integer value, such as the size of another value
+  fn from_size(size: usize) -> Self;
+}
+
+// The kinds of values the shared validation engine tells apart
+pub(crate) enum DataKind<'v, T> {
+  Null,
+  // CBOR's undefined, which JSON has no counterpart of
+  Undefined,
+  Bool(bool),
+  Integer(i128),
+  Float(f64),
+  Text(&'v str),
+  Array(&'v [T]),
+  Other,
+}
+
+impl<'v, T> DataKind<'v, T> {
+  // Returns the value as a float if it's a number
+  pub(crate) fn as_f64(&self) -> Option<f64> {
+    match *self {
+      DataKind::Integer(i) => Some(i as f64),
+      DataKind::Float(f) => Some(f),
+      _ => None,
+    }
+  }
+}
@@ -0,0 +1,351 @@
+use super::ast::*;
+
+/// Traversal over the nodes of a CDDL AST
+///
+/// Every method has a default implementation that simply walks the children of
+/// the given node via the corresponding `walk_*` function. Implementors
+/// override only the methods for the nodes they're interested in, calling the
+/// matching `walk_*` function from the override in order to continue the
+/// traversal into that node's children.
+///
+/// # Example
+///
+/// ```
+/// use cddl::{ast::Identifier, lexer_from_str, parser::cddl_from_str, visitor::{self, Visitor}};
+///
+/// #[derive(Default)]
+/// struct TypenameCollector<'a> {
+///   typenames: Vec<&'a str>,
+/// }
+///
+/// impl<'a> Visitor<'a> for TypenameCollector<'a> {
+///   fn visit_typename(&mut self, ident: &Identifier<'a>) {
+///     self.typenames.push(ident.ident);
+///   }
+/// }
+///
+/// let input = r#"myrule = { name: tstr, age: uint / null }"#;
+/// let mut lexer = lexer_from_str(input);
+/// let cddl = cddl_from_str(&mut lexer, input, false).unwrap();
+///
+/// let mut collector = TypenameCollector::default();
+/// visitor::walk_cddl(&mut collector, &cddl);
+///
+/// assert_eq!(collector.typenames, ["tstr", "uint", "null"]);
+/// ```
+pub trait Visitor<'a> {
+  /// Visit a CDDL definition
+  fn visit_cddl(&mut self, cddl: &CDDL<'a>) {
+    walk_cddl(self, cddl);
+  }
+
+  /// Visit a rule
+  fn visit_rule(&mut self, rule: &Rule<'a>) {
+    walk_rule(self, rule);
+  }
+
+  /// Visit a type rule
+  fn visit_type_rule(&mut self, tr: &TypeRule<'a>) {
+    walk_type_rule(self, tr);
+  }
+
+  /// Visit a group rule
+  fn visit_group_rule(&mut self, gr: &GroupRule<'a>) {
+    walk_group_rule(self, gr);
+  }
+
+  /// Visit the identifier of a rule being defined
+  fn visit_rule_name(&mut self, _ident: &Identifier<'a>) {}
+
+  /// Visit generic parameters
+  fn visit_generic_params(&mut self, gp: &GenericParm<'a>) {
+    walk_generic_params(self, gp);
+  }
+
+  /// Visit a single generic parameter
+  fn visit_generic_param(&mut self, _ident: &Identifier<'a>) {}
+
+  /// Visit generic arguments
+  fn visit_generic_args(&mut self, ga: &GenericArg<'a>) {
+    walk_generic_args(self, ga);
+  }
+
+  /// Visit a type, which may include type choices
+  fn visit_type(&mut self, t: &Type<'a>) {
+    walk_type(self, t);
+  }
+
+  /// Visit a type with an optional range or control operator
+  fn visit_type1(&mut self, t1: &Type1<'a>) {
+    walk_type1(self, t1);
+  }
+
+  /// Visit a range or control operator
+  fn visit_range_ctl_op(&mut self, _op: &RangeCtlOp) {}
+
+  /// Visit a type
+  fn visit_type2(&mut self, t2: &Type2<'a>) {
+    walk_type2(self, t2);
+  }
+
+  /// Visit a reference to a type or group by name, including those referenced
+  /// by unwraps and group enumerations
+  fn visit_typename(&mut self, _ident: &Identifier<'a>) {}
+
+  /// Visit a group, which may include group choices
+  fn visit_group(&mut self, g: &Group<'a>) {
+    walk_group(self, g);
+  }
+
+  /// Visit a group choice
+  fn visit_group_choice(&mut self, gc: &GroupChoice<'a>) {
+    walk_group_choice(self, gc);
+  }
+
+  /// Visit a group entry
+  fn visit_group_entry(&mut self, ge: &GroupEntry<'a>) {
+    walk_group_entry(self, ge);
+  }
+
+  /// Visit a group entry with an optional member key
+  fn visit_value_member_key_entry(&mut self, vmke: &ValueMemberKeyEntry<'a>) {
+    walk_value_member_key_entry(self, vmke);
+  }
+
+  /// Visit a group entry referencing a type or group by name
+  fn visit_type_groupname_entry(&mut self, tge: &TypeGroupnameEntry<'a>) {
+    walk_type_groupname_entry(self, tge);
+  }
+
+  /// Visit a member key
+  fn visit_member_key(&mut self, mk: &MemberKey<'a>) {
+    walk_member_key(self, mk);
+  }
+
+  /// Visit an occurrence indicator
+  fn visit_occurrence(&mut self, _o: &Occur) {}
+}
+
+/// Walk the rules of a CDDL definition
+pub fn walk_cddl<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, cddl: &CDDL<'a>) {
+  for rule in cddl.rules.iter() {
+    visitor.visit_rule(rule);
+  }
+}
+
+/// Walk a rule
+pub fn walk_rule<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, rule: &Rule<'a>) {
+  match rule {
+    Rule::Type { rule, .. } => visitor.visit_type_rule(rule),
+    Rule::Group { rule, .. } => visitor.visit_group_rule(rule),
+  }
+}
+
+/// Walk a type rule
+pub fn walk_type_rule<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, tr: &TypeRule<'a>) {
+  visitor.visit_rule_name(&tr.name);
+
+  if let Some(gp) = &tr.generic_param {
+    visitor.visit_generic_params(gp);
+  }
+
+  visitor.visit_type(&tr.value);
+}
+
+/// Walk a group rule
+pub fn walk_group_rule<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, gr: &GroupRule<'a>) {
+  visitor.visit_rule_name(&gr.name);
+
+  if let Some(gp) = &gr.generic_param {
+    visitor.visit_generic_params(gp);
+  }
+
+  visitor.visit_group_entry(&gr.entry);
+}
+
+/// Walk generic parameters
+pub fn walk_generic_params<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, gp: &GenericParm<'a>) {
+  for param in gp.params.iter() {
+    visitor.visit_generic_param(param);
+  }
+}
+
+/// Walk generic arguments
+pub fn walk_generic_args<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, ga: &GenericArg<'a>) {
+  for arg in ga.args.iter() {
+    visitor.visit_type1(arg);
+  }
+}
+
+/// Walk the type choices of a type
+pub fn walk_type<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, t: &Type<'a>) {
+  for tc in t.type_choices.iter() {
+    visitor.visit_type1(tc);
+  }
+}
+
+/// Walk a type and its optional range or control operator
+pub fn walk_type1<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, t1: &Type1<'a>) {
+  visitor.visit_type2(&t1.type2);
+
+  if let Some((op, t2)) = &t1.operator {
+    visitor.visit_range_ctl_op(op);
+    visitor.visit_type2(t2);
+  }
+}
+
+/// Walk the children of a type, if any
+pub fn walk_type2<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, t2: &Type2<'a>) {
+  match t2 {
+    Type2::Typename {
+      ident, generic_arg, ..
+    }
+    | Type2::Unwrap {
+      ident, generic_arg, ..
+    }
+    | Type2::ChoiceFromGroup {
+      ident, generic_arg, ..
+    } => {
+      visitor.visit_typename(ident);
+
+      if let Some(ga) = generic_arg {
+        visitor.visit_generic_args(ga);
+      }
+    }
+    Type2::ParenthesizedType { pt: t, .. } | Type2::TaggedData { t, .. } => visitor.visit_type(t),
+    Type2::Map { group, .. }
+    | Type2::Array { group, .. }
+    | Type2::ChoiceFromInlineGroup { group, .. } => visitor.visit_group(group),
+    _ => (),
+  }
+}
+
+/// Walk the group choices of a group
+pub fn walk_group<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, g: &Group<'a>) {
+  for gc in g.group_choices.iter() {
+    visitor.visit_group_choice(gc);
+  }
+}
+
+/// Walk the group entries of a group choice
+pub fn walk_group_choice<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, gc: &GroupChoice<'a>) {
+  for (ge, _) in gc.group_entries.iter() {
+    visitor.visit_group_entry(ge);
+  }
+}
+
+/// Walk a group entry
+pub fn walk_group_entry<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, ge: &GroupEntry<'a>) {
+  match ge {
+    GroupEntry::ValueMemberKey { ge, .. } => visitor.visit_value_member_key_entry(ge),
+    GroupEntry::TypeGroupname { ge, .. } => visitor.visit_type_groupname_entry(ge),
+    GroupEntry::InlineGroup { occur, group, .. } => {
+      if let Some(o) = occur {
+        visitor.visit_occurrence(o);
+      }
+
+      visitor.visit_group(group);
+    }
+  }
+}
+
+/// Walk the occurrence, member key and type of a group entry
+pub fn walk_value_member_key_entry<'a, V: Visitor<'a> + ?Sized>(
+  visitor: &mut V,
+  vmke: &ValueMemberKeyEntry<'a>,
+) {
+  if let Some(o) = &vmke.occur {
+    visitor.visit_occurrence(o);
+  }
+
+  if let Some(mk) = &vmke.member_key {
+    visitor.visit_member_key(mk);
+  }
+
+  visitor.visit_type(&vmke.entry_type);
+}
+
+/// Walk the occurrence, name and generic arguments of a group entry
+pub fn walk_type_groupname_entry<'a, V: Visitor<'a> + ?Sized>(
+  visitor: &mut V,
+  tge: &TypeGroupnameEntry<'a>,
+) {
+  if let Some(o) = &tge.occur {
+    visitor.visit_occurrence(o);
+  }
+
+  visitor.visit_typename(&tge.name);
+
+  if let Some(ga) = &tge.generic_arg {
+    visitor.visit_generic_args(ga);
+  }
+}
+
+/// Walk the children of a member key, if any
+pub fn walk_member_key<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, mk: &MemberKey<'a>) {
+  match mk {
+    MemberKey::Type1 { t1, .. } => visitor.visit_type1(t1),
+    MemberKey::NonMemberKey(NonMemberKey::Group(g)) => visitor.visit_group(g),
+    MemberKey::NonMemberKey(NonMemberKey::Type(t)) => visitor.visit_type(t),
+    MemberKey::Bareword { .. } | MemberKey::Value { .. } => (),
+  }
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+  use super::*;
+  use crate::{lexer::Lexer, parser::cddl_from_str};
+
+  #[derive(Default)]
+  struct Counter {
+    rule_names: Vec<String>,
+    typenames: Vec<String>,
+    occurrences: usize,
+    ctl_ops: usize,
+  }
+
+  impl<'a> Visitor<'a> for Counter {
+    fn visit_rule_name(&mut self, ident: &Identifier<'a>) {
+      self.rule_names.push(ident.to_string());
+    }
+
+    fn visit_typename(&mut self, ident: &Identifier<'a>) {
+      self.typenames.push(ident.to_string());
+    }
+
+    fn visit_occurrence(&mut self, _o: &Occur) {
+      self.occurrences += 1;
+    }
+
+    fn visit_range_ctl_op(&mut self, _op: &RangeCtlOp) {
+      self.ctl_ops += 1;
+    }
+  }
+
+  #[test]
+  fn verify_walk_cddl() {
+    let input = r#"reputon = {
+  rater: text,
+  ? confidence: float16 .le 1.0,
+  * tstr => [* rating<uint>],
+}
+
+rating<t> = ( value: t, &colors )
+colors = ( red: 1, green: 2 )"#;
+
+    let mut lexer = Lexer::new(input);
+    let cddl = cddl_from_str(&mut lexer, input, false).unwrap();
+
+    let mut counter = Counter::default();
+    walk_cddl(&mut counter, &cddl);
+
+    assert_eq!(counter.rule_names, ["reputon", "rating", "colors"]);
+    assert_eq!(
+      counter.typenames,
+      ["text", "float16", "tstr", "rating", "uint", "t", "colors"]
+    );
+    assert_eq!(counter.occurrences, 3);
+    assert_eq!(counter.ctl_ops, 1);
+  }
+}
@@ -1,4 +1,4 @@
-#![cfg(feature = "std")]
+#![cfg(feature = "cbor-validation")]
 
 use cddl::{self, validate_cbor_from_slice};
 use serde::{Deserialize, Serialize};
@@ -33,6 +33,15 @@ pub mod cbor {
     pub const BYTES_EMPTY:  &[u8] = b"\x40";
     pub const BYTES_1234:   &[u8] = b"\x44\x01\x02\x03\x04"; // hex 01020304
 
+    pub const DATE_TIME:    &[u8] = b"\xc0\x742013-03-21T20:04:00Z";     // 0("2013-03-21T20:04:00Z")
+    pub const EPOCH_TIME:   &[u8] = b"\xc1\x1a\x51\x4b\x67\xb0";     // 1(1363896240)
+    pub const EPOCH_FLOAT:  &[u8] = b"\xc1\xfb\x41\xd4\x52\xd9\xec\x20\x00\x00"; // 1(1363896240.5)
+    pub const BIGNUM_2_64:  &[u8] = b"\xc2\x49\x01\x00\x00\x00\x00\x00\x00\x00\x00"; // 18446744073709551616
+    pub const BIGNUM_NEG:   &[u8] = b"\xc3\x49\x01\x00\x00\x00\x00\x00\x00\x00\x00"; // -18446744073709551617
+    pub const DECIMAL_FRAC: &[u8] = b"\xc4\x82\x21\x19\x6a\xb3";   // 4([-2, 27315])
+    pub const ENCODED_IETF: &[u8] = b"\xd8\x18\x45\x64\x49\x45\x54\x46"; // 24(h'6449455446')
+    pub const REGEXP_IETF:  &[u8] = b"\xd8\x23\x64\x49\x45\x54\x46"; // 35("IETF")
+
 }
 
 #[test]
@@ -69,8 +78,20 @@ fn validate_cbor_float() {
   validate_cbor_from_slice(cddl_input, cbor::FLOAT_1_0).unwrap();
   validate_cbor_from_slice(cddl_input, cbor::FLOAT_1E300).unwrap();
 
-  // TODO: check that large floats don't validate against a smaller size.
-  // E.g. CBOR #7.27 (64-bit) shouldn't validate against "float16" or "float32".
+  // Floats that can't be represented in a smaller size without loss of
+  // precision shouldn't validate against that size.
+  let cddl_input = r#"thing = float16"#;
+  validate_cbor_from_slice(cddl_input, cbor::FLOAT_1E5).unwrap_err();
+  validate_cbor_from_slice(cddl_input, cbor::FLOAT_1E300).unwrap_err();
+  let cddl_input = r#"thing = float32"#;
+  validate_cbor_from_slice(cddl_input, cbor::FLOAT_1E300).unwrap_err();
+  let cddl_input = r#"thing = float16-32"#;
+  validate_cbor_from_slice(cddl_input, cbor::FLOAT_1_0).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::FLOAT_1E5).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::FLOAT_1E300).unwrap_err();
+  let cddl_input = r#"thing = float32-64"#;
+  validate_cbor_from_slice(cddl_input, cbor::FLOAT_1E300).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::INT_1).unwrap_err();
 }
 
 #[test]
@@ -91,6 +112,29 @@ fn validate_cbor_integer() {
   validate_cbor_from_slice(cddl_input, cbor::INT_0).unwrap();
   validate_cbor_from_slice(cddl_input, cbor::INT_24).unwrap();
   validate_cbor_from_slice(cddl_input, cbor::NINT_1000).unwrap_err();
+
+  let cddl_input = r#"thing = unsigned"#;
+  validate_cbor_from_slice(cddl_input, cbor::INT_24).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::BIGNUM_2_64).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::BYTES_1234).unwrap_err();
+  validate_cbor_from_slice(cddl_input, cbor::NINT_1000).unwrap_err();
+  let cddl_input = r#"thing = bignint"#;
+  validate_cbor_from_slice(cddl_input, cbor::NINT_1000).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::BIGNUM_NEG).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::INT_0).unwrap_err();
+  // A positive bignum (tag 2) isn't a negative one (tag 3)
+  validate_cbor_from_slice(cddl_input, cbor::BIGNUM_2_64).unwrap_err();
+  let cddl_input = r#"thing = biguint"#;
+  validate_cbor_from_slice(cddl_input, cbor::BIGNUM_NEG).unwrap_err();
+  let cddl_input = r#"thing = bigint"#;
+  validate_cbor_from_slice(cddl_input, cbor::BIGNUM_2_64).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::BIGNUM_NEG).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::DECIMAL_FRAC).unwrap_err();
+  let cddl_input = r#"thing = integer"#;
+  validate_cbor_from_slice(cddl_input, cbor::NINT_1000).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::BIGNUM_NEG).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::FLOAT_1_0).unwrap_err();
+  validate_cbor_from_slice(cddl_input, cbor::DECIMAL_FRAC).unwrap_err();
 }
 
 #[test]
@@ -116,16 +160,105 @@ fn validate_cbor_array() {
   let cddl_input = r#"thing = []"#;
   validate_cbor_from_slice(cddl_input, cbor::ARRAY_EMPTY).unwrap();
   validate_cbor_from_slice(cddl_input, cbor::NULL).unwrap_err();
-  // FIXME: broken
-  if false {
-    validate_cbor_from_slice(cddl_input, cbor::ARRAY_123).unwrap_err();
-  }
-
-  // FIXME: unimplemented!() in validation
-  if false {
-    let cddl_input = r#"thing = [1, 2, 3]"#;
-    validate_cbor_from_slice(cddl_input, cbor::ARRAY_123).unwrap();
-  }
+  validate_cbor_from_slice(cddl_input, cbor::ARRAY_123).unwrap_err();
+
+  let cddl_input = r#"thing = [1, 2, 3]"#;
+  validate_cbor_from_slice(cddl_input, cbor::ARRAY_123).unwrap();
+}
+
+#[test]
+fn validate_cbor_major_type() {
+  let cddl_input = r#"thing = #0"#;
+  validate_cbor_from_slice(cddl_input, cbor::INT_0).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::INT_24).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::NINT_1000).unwrap_err();
+  validate_cbor_from_slice(cddl_input, cbor::TEXT_IETF).unwrap_err();
+  let cddl_input = r#"thing = #0.23"#;
+  validate_cbor_from_slice(cddl_input, cbor::INT_23).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::INT_24).unwrap_err();
+  let cddl_input = r#"thing = #1.999"#;
+  validate_cbor_from_slice(cddl_input, cbor::NINT_1000).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::INT_1).unwrap_err();
+
+  let cddl_input = r#"thing = #2"#;
+  validate_cbor_from_slice(cddl_input, cbor::BYTES_1234).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::TEXT_IETF).unwrap_err();
+  let cddl_input = r#"thing = #2.4"#;
+  validate_cbor_from_slice(cddl_input, cbor::BYTES_1234).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::BYTES_EMPTY).unwrap_err();
+  let cddl_input = r#"thing = #3.3"#;
+  validate_cbor_from_slice(cddl_input, cbor::TEXT_CJK).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::TEXT_IETF).unwrap_err();
+  let cddl_input = r#"thing = #4.3"#;
+  validate_cbor_from_slice(cddl_input, cbor::ARRAY_123).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::ARRAY_EMPTY).unwrap_err();
+  let cddl_input = r#"thing = #5.0"#;
+  validate_cbor_from_slice(cddl_input, b"\xa0").unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::ARRAY_EMPTY).unwrap_err();
+
+  let cddl_input = r#"thing = #7"#;
+  validate_cbor_from_slice(cddl_input, cbor::BOOL_TRUE).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::NULL).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::FLOAT_1E300).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::INT_0).unwrap_err();
+  let cddl_input = r#"thing = #7.20 / #7.22"#;
+  validate_cbor_from_slice(cddl_input, cbor::BOOL_FALSE).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::NULL).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::BOOL_TRUE).unwrap_err();
+  let cddl_input = r#"thing = #7.25"#;
+  validate_cbor_from_slice(cddl_input, cbor::FLOAT_1_0).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::FLOAT_1E5).unwrap_err();
+  let cddl_input = r#"thing = #7.26"#;
+  validate_cbor_from_slice(cddl_input, cbor::FLOAT_1E5).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::FLOAT_1E300).unwrap_err();
+}
+
+#[test]
+fn validate_cbor_any() {
+  let cddl_input = r#"thing = #"#;
+  validate_cbor_from_slice(cddl_input, cbor::INT_0).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::NULL).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::ARRAY_1_23_45).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::TEXT_CJK).unwrap();
+}
+
+#[test]
+fn validate_cbor_prelude() {
+  let cddl_input = r#"thing = tdate"#;
+  validate_cbor_from_slice(cddl_input, cbor::DATE_TIME).unwrap();
+  // The date/time string must be tagged
+  let cbor_bytes = serde_cbor::to_vec(&"2013-03-21T20:04:00Z").unwrap();
+  validate_cbor_from_slice(cddl_input, &cbor_bytes).unwrap_err();
+  validate_cbor_from_slice(cddl_input, cbor::EPOCH_TIME).unwrap_err();
+  validate_cbor_from_slice(cddl_input, cbor::TEXT_IETF).unwrap_err();
+  validate_cbor_from_slice(cddl_input, cbor::INT_1).unwrap_err();
+
+  let cddl_input = r#"thing = time"#;
+  validate_cbor_from_slice(cddl_input, cbor::EPOCH_TIME).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::EPOCH_FLOAT).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::INT_24).unwrap_err();
+  validate_cbor_from_slice(cddl_input, cbor::FLOAT_1E5).unwrap_err();
+  validate_cbor_from_slice(cddl_input, cbor::DATE_TIME).unwrap_err();
+  validate_cbor_from_slice(cddl_input, cbor::TEXT_IETF).unwrap_err();
+
+  let cddl_input = r#"thing = biguint"#;
+  validate_cbor_from_slice(cddl_input, cbor::BIGNUM_2_64).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::BYTES_1234).unwrap_err();
+  validate_cbor_from_slice(cddl_input, cbor::TEXT_IETF).unwrap_err();
+
+  let cddl_input = r#"thing = encoded-cbor / regexp"#;
+  validate_cbor_from_slice(cddl_input, cbor::ENCODED_IETF).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::REGEXP_IETF).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::BYTES_EMPTY).unwrap_err();
+  validate_cbor_from_slice(cddl_input, cbor::TEXT_IETF).unwrap_err();
+  validate_cbor_from_slice(cddl_input, cbor::INT_0).unwrap_err();
+
+  let cddl_input = r#"thing = [* integer]"#;
+  validate_cbor_from_slice(cddl_input, cbor::ARRAY_123).unwrap();
+
+  let cddl_input = r#"thing = zipcode  zipcode = uint"#;
+  validate_cbor_from_slice(cddl_input, cbor::INT_24).unwrap();
+  validate_cbor_from_slice(cddl_input, cbor::NINT_1000).unwrap_err();
 }
 
 // These data structures exist so that we can serialize some more complex
@@ -149,7 +282,7 @@ struct LongTuple(String, u32, u32);
 struct ShortTuple(String);
 
 #[derive(Debug, Serialize, Deserialize)]
-struct KitchenSink(String, u32, f64, bool);
+struct KitchenSink(String, u32, f32, bool);
 
 #[test]
 fn validate_cbor_group() {
@@ -181,14 +314,31 @@ fn validate_cbor_homogenous_array() {
 }
 
 #[test]
-#[ignore] // FIXME: broken
 fn validate_cbor_array_groups() {
   let cddl_input = r#"thing = [int, (int, int)]"#;
   validate_cbor_from_slice(cddl_input, cbor::ARRAY_123).unwrap();
-  // TODO: try splitting arrays into groups a few other ways:
-  // [(int, int, int)]
-  // [* (int)]
-  // [* (int, int)]
+  let cddl_input = r#"thing = [(int, int, int)]"#;
+  validate_cbor_from_slice(cddl_input, cbor::ARRAY_123).unwrap();
+  let cddl_input = r#"thing = [* (int)]"#;
+  validate_cbor_from_slice(cddl_input, cbor::ARRAY_123).unwrap();
+  let cddl_input = r#"thing = [* (int, int)]"#;
+  validate_cbor_from_slice(cddl_input, cbor::ARRAY_123).unwrap_err();
+  let cddl_input = r#"thing = [(int, int) // (int, int, int)]"#;
+  validate_cbor_from_slice(cddl_input, cbor::ARRAY_123).unwrap();
+  let cddl_input = r#"thing = [(int, int) // (tstr)]"#;
+  validate_cbor_from_slice(cddl_input, cbor::ARRAY_123).unwrap_err();
+}
+
+#[test]
+fn validate_cbor_array_group_rules() {
+  let cddl_input = r#"thing = [header, int]  header = (a: int, b: int)"#;
+  validate_cbor_from_slice(cddl_input, cbor::ARRAY_123).unwrap();
+  let cddl_input = r#"thing = [header]  header = (a: int, more)  more = (b: int, c: int)"#;
+  validate_cbor_from_slice(cddl_input, cbor::ARRAY_123).unwrap();
+  let cddl_input = r#"thing = [header, int]  header = (a: int, b: tstr)"#;
+  validate_cbor_from_slice(cddl_input, cbor::ARRAY_123).unwrap_err();
+  let cddl_input = r#"thing = [header]  header = (a: int, b: int)"#;
+  validate_cbor_from_slice(cddl_input, cbor::ARRAY_123).unwrap_err();
 }
 
 #[test]
@@ -254,11 +404,8 @@ fn validate_cbor_map() {
   let cddl_input = r#"thing = {name: tstr, age: tstr}"#;
   validate_cbor_from_slice(cddl_input, &cbor_bytes).unwrap_err();
 
-  // FIXME: broken
-  if false {
-    let cddl_input = r#"thing = {name: tstr}"#;
-    validate_cbor_from_slice(cddl_input, &cbor_bytes).unwrap_err();
-  }
+  let cddl_input = r#"thing = {name: tstr}"#;
+  validate_cbor_from_slice(cddl_input, &cbor_bytes).unwrap_err();
 
   // "* keytype => valuetype" is the expected syntax for collecting
   // any remaining key/value pairs of the expected type.
@@ -271,13 +418,11 @@ fn validate_cbor_map() {
   let cddl_input = r#"thing = {+ tstr => any}"#;
   validate_cbor_from_slice(cddl_input, &cbor_bytes).unwrap();
 
-  // FIXME: broken
-  if false {
-    // Should fail because the CBOR input has one entry that can't be
-    // collected because the value type doesn't match.
-    let cddl_input = r#"thing = {* tstr => int}"#;
-    validate_cbor_from_slice(cddl_input, &cbor_bytes).unwrap_err();
-  }
+  // Should fail because the CBOR input has one entry that can't be
+  // collected because the value type doesn't match.
+  let cddl_input = r#"thing = {* tstr => int}"#;
+  validate_cbor_from_slice(cddl_input, &cbor_bytes).unwrap_err();
+
   // Should fail because the CBOR input has two entries that can't be
   // collected because the key type doesn't match.
   let cddl_input = r#"thing = {* int => any}"#;
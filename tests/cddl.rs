@@ -1,4 +1,4 @@
-#![cfg(feature = "std")]
+#![cfg(feature = "json-validation")]
 
 mod data;
 
@@ -29,6 +29,76 @@ fn verify_cddl_compiles() -> Result<(), parser::Error> {
   Ok(())
 }
 
+// Returns the AST of the given CDDL as JSON, without the spans locating each
+// node in the text it was parsed from
+fn ast_without_spans(input: &str) -> serde_json::Value {
+  fn strip(value: &mut serde_json::Value) {
+    match value {
+      serde_json::Value::Object(o) => {
+        o.remove("span");
+        // Variants holding nothing but a span
+        for variant in &["Any", "ZeroOrMore", "OneOrMore", "Optional"] {
+          if let Some(span) = o.get_mut(*variant) {
+            *span = serde_json::Value::Null;
+          }
+        }
+        o.values_mut().for_each(strip);
+      }
+      serde_json::Value::Array(a) => a.iter_mut().for_each(strip),
+      _ => (),
+    }
+  }
+
+  let cddl = parser::cddl_from_str(&mut lexer_from_str(input), input, true).unwrap();
+  let mut ast = serde_json::to_value(&cddl).unwrap();
+  strip(&mut ast);
+
+  ast
+}
+
+#[test]
+fn verify_cddl_roundtrips() {
+  let mut inputs = fs::read_dir("tests/data/cddl/")
+    .unwrap()
+    .map(|file| fs::read_to_string(file.unwrap().path()).unwrap())
+    .collect::<Vec<_>>();
+  inputs.extend(
+    [
+      "a = { b: int, }\nc = [int,]\nd = (e: tstr,)",
+      "a = 1.5..10.5 / -1..-10 / 1...5 / 1e3 / 0x10 / -0.0 / 1.5e-7",
+      "a = x .. y\nx = 1\ny = 2",
+      r#"a = { ? "k" ^ => int, 1 => int, 2: tstr, -1: bool, h'01': bstr, * tstr => any }"#,
+      "a = [2*3 int, 1* tstr, *5 bool, + uint, ? text]",
+      "a = &(x: 1, y: 2) / &( b: 1 // c: 2 ) / #6.32(tstr) / #6(int) / #1.2 / #",
+      "a = (int / tstr) .size 3 / uint .size (1..3)",
+      "a = { (b: int // c: tstr) }\nd = [ + (b: int, c: tstr) ]\ne = [a: int, b: tstr // c: bool]",
+      "a<T, U> = [T, U]\nb = a<int, tstr>\nc = ~d<1..3>\nd<T> = { x: T }\ne = &f<int>\nf<T> = (x: T)",
+      "$a = int\n$a /= tstr\n$$g = (a: int)\n$$g //= (b: int)\nc = { $$g, * $a => any }",
+      r#"a = tstr .regexp "[a-z]+" / bstr .cbor int / text .default "x\"y" / 'abc' / b64'SGVsbG8='"#,
+      "a = {} / [] / [* [* int]] / { ~b }\nb = { c: int }",
+      r#"a = "\u{1F600}\t\\\u0001" / "multi\nline" / "\d+\/""#,
+      "a = \"first line\n  second line\"",
+      r"a = 'by\'te' / 'back\\slash\n' / '' / '\u{1F600}'",
+      "tcp-option@iana = { $$opt.ext, a.b--c: x1-2 }\n$$opt.ext //= (_x: int)\nx1-2 = lo.. hi\nlo = 1\nhi = 2",
+    ]
+    .iter()
+    .map(|input| input.to_string()),
+  );
+
+  for input in inputs {
+    let printed = parser::cddl_from_str(&mut lexer_from_str(&input), &input, true)
+      .unwrap()
+      .to_string();
+
+    assert_eq!(
+      ast_without_spans(&printed),
+      ast_without_spans(&input),
+      "{}",
+      printed
+    );
+  }
+}
+
 #[test]
 fn verify_json_validation() -> Result<(), validation::Error> {
   validate_json_from_str(
@@ -37,6 +107,72 @@ fn verify_json_validation() -> Result<(), validation::Error> {
   )
 }
 
+#[test]
+#[cfg(feature = "cbor-validation")]
+fn verify_conformance_suites() {
+  for file in fs::read_dir("tests/data/conformance/").unwrap() {
+    let file = file.unwrap();
+
+    if file.path().extension().unwrap() == "cddltest" {
+      cddl::conformance::assert_file(file.path());
+    }
+  }
+}
+
+// Runs the examples of RFC 8610 in tests/data/rfc8610/, and any corpus of
+// .cddl files and .cddltest manifests added there, against the cases listed in
+// known-failures.txt
+#[test]
+#[cfg(feature = "cbor-validation")]
+fn verify_rfc8610_suite() {
+  let dir = std::path::Path::new("tests/data/rfc8610/");
+  let known_failures = fs::read_to_string(dir.join("known-failures.txt")).unwrap();
+  let known_failures = known_failures
+    .lines()
+    .map(str::trim)
+    .filter(|l| !l.is_empty() && !l.starts_with('#'))
+    .collect::<Vec<_>>();
+
+  let mut unexpected = Vec::new();
+  let mut fixed = known_failures.clone();
+
+  for file in fs::read_dir(dir).unwrap() {
+    let path = file.unwrap().path();
+
+    match path.extension().and_then(|e| e.to_str()) {
+      Some("cddl") => {
+        let input = fs::read_to_string(&path).unwrap();
+        if let Err(e) = parser::cddl_from_str(&mut lexer_from_str(&input), &input, false) {
+          unexpected.push(format!("{}: {}", path.display(), e));
+        }
+      }
+      Some("cddltest") => {
+        let report = cddl::conformance::run_file(&path).unwrap();
+
+        for outcome in report.outcomes.iter() {
+          let instance = outcome.case.instance.strip_prefix(dir).unwrap();
+          let instance = instance.to_string_lossy().replace('\\', "/");
+          let is_known = known_failures.contains(&instance.as_str());
+
+          match &outcome.failure {
+            Some(failure) if !is_known => unexpected.push(format!("{}: {}", instance, failure)),
+            Some(_) => fixed.retain(|k| *k != instance),
+            None => (),
+          }
+        }
+      }
+      _ => (),
+    }
+  }
+
+  assert!(unexpected.is_empty(), "{}", unexpected.join("\n"));
+  assert!(
+    fixed.is_empty(),
+    "known failures that now pass: {}",
+    fixed.join(", ")
+  );
+}
+
 // #[test]
 // fn verify_ast_correctness() -> Result<(), Box<dyn std::error::Error>> {
 //   let c = parser::cddl_from_str(std::str::from_utf8(include_bytes!(